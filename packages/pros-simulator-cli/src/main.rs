@@ -0,0 +1,646 @@
+use std::{
+    io::{stdout, Write},
+    path::PathBuf,
+    process::exit,
+    sync::mpsc,
+    time::Duration,
+};
+
+use clap::{Parser, ValueEnum};
+use pros_simulator::{MatchTiming, SimulatorOptions, StartKind, StopReason, Timeout, WarningKind};
+use pros_simulator_interface::{
+    text_width, truncate_to_width, CompetitionPhase, DataAbortScreen, DeviceType, DisplayGeometry,
+    LcdLines, LogLevel, ProsVersion, ResourceLimit, SimulatorEvent, SimulatorMessage,
+};
+
+/// Run a VEX V5 robot program in the terminal using the PROS API interface.
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// The competition phase the robot should start in.
+    #[clap(long, value_enum, default_value_t = Phase::Opcontrol)]
+    phase: Phase,
+
+    /// Stop the simulation if it's still running after this many seconds.
+    #[clap(long, value_name = "SECONDS", value_parser = parse_timeout)]
+    timeout: Option<Duration>,
+
+    /// Stop the simulation once this many seconds have passed inside it, however fast it runs.
+    #[clap(long, value_name = "SECONDS", value_parser = parse_timeout, conflicts_with = "timeout")]
+    simulated_timeout: Option<Duration>,
+
+    /// Seed the random number generator used by robot code, making runs reproducible.
+    #[clap(long)]
+    seed: Option<u64>,
+
+    /// Run each task on its own OS thread, for robot code that relies on parallelism.
+    #[clap(long)]
+    threaded: bool,
+
+    /// Preallocate resources for running up to this many tasks at once, making spawning tasks
+    /// faster.
+    #[clap(long)]
+    instance_pool: Option<u32>,
+
+    /// Start the clock returned by `millis()` at this value, e.g. to test timers wrapping around.
+    #[clap(long, default_value_t = 0)]
+    start_millis: u32,
+
+    /// Send console output at this baud rate (115200 if not given) like the V5's USB serial
+    /// connection, instead of instantly.
+    #[clap(long, value_name = "BAUD", num_args = 0..=1, default_missing_value = "115200")]
+    throttle_serial: Option<u32>,
+
+    /// Hold back each task's stdout until it prints a newline or fills newlib's 1 KiB buffer,
+    /// like on the brain.
+    #[clap(long)]
+    line_buffering: bool,
+
+    /// Send a snapshot of the robot's state this many times per second.
+    #[clap(long, value_name = "HZ")]
+    telemetry: Option<u32>,
+
+    /// Send at most this many updates per second about each motor's voltage, instead of every
+    /// change.
+    #[clap(long, value_name = "HZ")]
+    motor_update_rate: Option<u32>,
+
+    /// Send counts of the events sent so far this many times per second.
+    #[clap(long, value_name = "HZ")]
+    channel_stats: Option<u32>,
+
+    /// Report how much of each simulated second robot code spent running rather than idle,
+    /// like the brain's CPU usage.
+    #[clap(long)]
+    task_stats: bool,
+
+    /// Run tasks in a random order and make delays last up to this many milliseconds longer, to
+    /// flush out race conditions. Use with `--seed` to get the same schedule again.
+    #[clap(long, value_name = "MS")]
+    jitter: Option<u64>,
+
+    /// Report when tasks have to wait for a mutex, and when one is held for longer than this
+    /// many milliseconds, to make synchronization bugs visible.
+    #[clap(long, value_name = "MS")]
+    mutex_events: Option<u64>,
+
+    /// Stop the simulation if robot code grows its memory past this many MiB.
+    #[clap(long, value_name = "MIB")]
+    max_memory: Option<usize>,
+
+    /// Stop the simulation if robot code tries to have more than this many tasks at once.
+    #[clap(long, value_name = "N")]
+    max_tasks: Option<u32>,
+
+    /// Stop the simulation if more than this many events are sent in a second.
+    #[clap(long, value_name = "N")]
+    max_event_rate: Option<u32>,
+
+    /// Sample the robot code's stack every millisecond and write the samples to this file in the
+    /// folded stack format, for turning into a flame graph.
+    #[clap(long, value_name = "FILE")]
+    profile: Option<PathBuf>,
+
+    /// Write the sections robot code timed with `sim_profile_begin` and `sim_profile_end` to
+    /// this file in the Chrome trace format, for opening in Perfetto or `chrome://tracing`.
+    #[clap(long, value_name = "FILE")]
+    chrome_trace: Option<PathBuf>,
+
+    /// Keep what robot code saves to flash with `sim_flash_write` in this file, so it's there
+    /// the next time the robot code runs.
+    #[clap(long, value_name = "FILE")]
+    flash: Option<PathBuf>,
+
+    /// Write a report of which APIs the robot code called to this file.
+    #[clap(long, value_name = "FILE")]
+    coverage: Option<PathBuf>,
+
+    /// Check which of the robot code's imports the simulator supports against this version of
+    /// the PROS API.
+    #[clap(long, value_name = "VERSION", default_value_t = ProsVersion::default())]
+    pros_version: ProsVersion,
+
+    /// Make unimplemented PROS APIs fail with `ENOSYS` instead of stopping the task that calls
+    /// them.
+    #[clap(long)]
+    permissive: bool,
+
+    /// Warn the first time each task reads `errno` before any PROS API call has set it.
+    #[clap(long)]
+    check_errno: bool,
+
+    /// Simulate a hot start, where only the hot image was uploaded: the robot code's `hot_init`
+    /// runs before `initialize`, but `cold_init` doesn't.
+    #[clap(long)]
+    hot_start: bool,
+
+    /// Surround the buffers the simulator allocates in robot code memory with canaries, and warn
+    /// when robot code writes out of bounds of them.
+    #[clap(long)]
+    canaries: bool,
+
+    /// When a task faults, stop only that task and keep running the rest of the robot code.
+    #[clap(long)]
+    isolate_crashes: bool,
+
+    /// Work out what an autonomous selector drawn on the LCD offers from its lines and button
+    /// callbacks, and report it as the robot code runs.
+    #[clap(long)]
+    lcd_selector: bool,
+
+    /// Let unit tests compiled into the robot code control the simulation with functions like
+    /// `sim_set_pose` and `sim_advance_time`.
+    #[clap(long)]
+    test_build: bool,
+
+    /// Set a value that robot code in a test build can read with `sim_config_get`, e.g.
+    /// `--test-config test=drive`. Can be repeated.
+    #[clap(long = "test-config", value_name = "KEY=VALUE", value_parser = parse_test_config)]
+    test_config: Vec<(String, String)>,
+
+    /// Run the functions the robot code exports whose names match a pattern as tests, one at a
+    /// time, instead of its competition functions, e.g. `--run-tests 'sim_test_*'`. Implies
+    /// `--test-build`.
+    #[clap(long, value_name = "PATTERN")]
+    run_tests: Option<String>,
+
+    /// Plug a device into a smart port, e.g. `--device 1=motor`. Can be repeated. Devices
+    /// aren't simulated yet, but robot code can see them with the PROS registry API.
+    #[clap(long = "device", value_name = "PORT=TYPE", value_parser = parse_device)]
+    devices: Vec<(u8, DeviceType)>,
+
+    /// Run a VEX Robotics Competition match once the robot code has initialized, then stop:
+    /// 15 seconds of autonomous followed by 1:45 of driver control.
+    #[clap(long = "match")]
+    run_match: bool,
+
+    /// Stop the simulation with an error when robot code causes this kind of warning:
+    /// `unimplemented-call`, `motor-voltage-clamped`, `suspended-at-exit`, `failed-assertion`,
+    /// `unset-errno`, `heap-corruption` or `invalid-argument`. Can be repeated.
+    #[clap(long, value_name = "KIND")]
+    strict: Vec<WarningKind>,
+
+    /// Only show messages robot code logs with `sim_log` at this level or more severe: `error`,
+    /// `warn`, `info`, `debug` or `trace`.
+    #[clap(long, value_name = "LEVEL", default_value_t = LogLevel::Info)]
+    log_level: LogLevel,
+
+    /// The robot code to simulate (WASM file).
+    robot_code: PathBuf,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Phase {
+    Opcontrol,
+    Autonomous,
+    Disabled,
+}
+
+impl From<Phase> for CompetitionPhase {
+    fn from(phase: Phase) -> Self {
+        CompetitionPhase {
+            autonomous: matches!(phase, Phase::Autonomous),
+            enabled: !matches!(phase, Phase::Disabled),
+            is_competition: false,
+        }
+    }
+}
+
+/// Parses a timeout in seconds, which can't be negative or too large to wait for.
+fn parse_timeout(arg: &str) -> Result<Duration, String> {
+    let seconds = arg
+        .parse::<f64>()
+        .map_err(|_| format!("`{arg}` isn't a number of seconds"))?;
+    Duration::try_from_secs_f64(seconds).map_err(|_| format!("the timeout can't be {arg} seconds"))
+}
+
+/// Parses a `PORT=TYPE` device argument.
+fn parse_device(arg: &str) -> Result<(u8, DeviceType), String> {
+    let (port, device) = arg
+        .split_once('=')
+        .ok_or("expected PORT=TYPE, e.g. `1=motor`")?;
+    let port = port
+        .parse::<u8>()
+        .ok()
+        .filter(|port| (1..=21).contains(port))
+        .ok_or(format!("`{port}` isn't a smart port (1-21)"))?;
+    Ok((port, device.parse()?))
+}
+
+/// Parses a `KEY=VALUE` test config argument.
+fn parse_test_config(arg: &str) -> Result<(String, String), String> {
+    let (key, value) = arg
+        .split_once('=')
+        .ok_or("expected KEY=VALUE, e.g. `test=drive`")?;
+    Ok((key.to_string(), value.to_string()))
+}
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+
+/// Formats an RGBA color as the `r;g;b` part of an ANSI true color escape sequence.
+fn rgb(color: u32) -> String {
+    let [r, g, b, _a] = color.to_be_bytes();
+    format!("{r};{g};{b}")
+}
+
+/// Draws the LCD emulator, `width` columns wide.
+fn draw_lcd(lines: &LcdLines, width: usize) {
+    let border = "─".repeat(width);
+    let mut out = stdout().lock();
+    _ = writeln!(out, "┌{border}┐");
+    for line in lines {
+        let mut style = String::new();
+        if let Some(foreground) = line.foreground {
+            style.push_str(&format!("\x1b[38;2;{}m", rgb(foreground)));
+        }
+        if let Some(background) = line.background {
+            style.push_str(&format!("\x1b[48;2;{}m", rgb(background)));
+        }
+        // pad by columns rather than characters so wide characters don't push the border out
+        let text = truncate_to_width(&line.text, width);
+        let padding = " ".repeat(width - text_width(text));
+        _ = writeln!(out, "│{style}{text}{padding}{RESET}│");
+    }
+    _ = writeln!(out, "└{border}┘");
+}
+
+/// Draws the brain's data abort screen in place of the LCD.
+fn draw_data_abort(screen: &DataAbortScreen, width: usize) {
+    let border = "─".repeat(width);
+    let mut out = stdout().lock();
+    _ = writeln!(out, "┌{border}┐");
+    for line in screen.to_string().lines() {
+        let text = truncate_to_width(line, width);
+        let padding = " ".repeat(width - text_width(text));
+        _ = writeln!(out, "│{RED}{text}{padding}{RESET}│");
+    }
+    _ = writeln!(out, "└{border}┘");
+}
+
+/// Pretty-prints a simulator event, drawing displays at the size the simulator last gave.
+fn render_event(event: SimulatorEvent, display: &mut DisplayGeometry) {
+    let lcd_width = display.lcd_width as usize;
+    match event {
+        SimulatorEvent::ConsoleMessage(message) => {
+            let mut out = stdout().lock();
+            _ = out.write_all(message.text.as_bytes());
+            _ = out.flush();
+        }
+        SimulatorEvent::Warning(message) => {
+            eprintln!("{YELLOW}{BOLD}warning{RESET}{BOLD}:{RESET} {message}");
+        }
+        SimulatorEvent::RobotCodeLoading => eprintln!("{DIM}Loading robot code...{RESET}"),
+        SimulatorEvent::ProgramInfo(info) => {
+            eprintln!("{DIM}Detected {} robot code.{RESET}", info.abi);
+            *display = info.display.unwrap_or_default();
+            if let Some(name) = &info.name {
+                let version = info
+                    .version
+                    .as_ref()
+                    .map(|version| format!(" v{version}"))
+                    .unwrap_or_default();
+                let slot = info
+                    .slot
+                    .map(|slot| format!(" (slot {slot})"))
+                    .unwrap_or_default();
+                eprintln!("{DIM}Program: {BOLD}{name}{RESET}{DIM}{version}{slot}{RESET}");
+            }
+            if let Some(description) = &info.description {
+                eprintln!("{DIM}{description}{RESET}");
+            }
+        }
+        SimulatorEvent::ApiCompatibility(compatibility) => {
+            let supported = compatibility.implemented.len() + compatibility.stubbed.len();
+            let total = supported + compatibility.missing.len();
+            eprintln!(
+                "{DIM}Simulator supports {supported} of the {total} PROS {} APIs the robot code \
+                 imports ({} stubbed).{RESET}",
+                compatibility.target,
+                compatibility.stubbed.len()
+            );
+        }
+        SimulatorEvent::RobotCodeStarting => eprintln!("{DIM}Robot code starting.{RESET}"),
+        SimulatorEvent::RobotCodeFinished => eprintln!("{DIM}Robot code finished.{RESET}"),
+        SimulatorEvent::RobotCodeError {
+            message,
+            task_id,
+            task_name,
+            errno,
+            trap,
+            backtrace,
+            panic,
+        } => {
+            if let Some(panic) = panic {
+                eprintln!(
+                    "{RED}{BOLD}panicked at {}:{}:{}{RESET}{BOLD}:{RESET} {}",
+                    panic.file, panic.line, panic.column, panic.message
+                );
+            } else {
+                eprintln!("{RED}{BOLD}error{RESET}{BOLD}:{RESET} {message}");
+            }
+            eprintln!("  {DIM}in task `{task_name}` (#{task_id}){RESET}");
+            if let Some(trap) = trap {
+                eprintln!("  {DIM}trap: {trap}{RESET}");
+            }
+            if let Some(errno) = errno {
+                eprintln!("  {DIM}errno: {errno}{RESET}");
+            }
+            for (index, frame) in backtrace.iter().enumerate() {
+                let frame = frame.to_string().replace('\n', "\n       ");
+                eprintln!("{index:>4}: {frame}");
+            }
+        }
+        SimulatorEvent::DataAbort(screen) => draw_data_abort(&screen, lcd_width),
+        SimulatorEvent::UnimplementedCall { name, backtrace } => {
+            eprintln!(
+                "{YELLOW}{BOLD}warning{RESET}{BOLD}:{RESET} Robot code called unimplemented API \
+                 `{name}`, so the task that called it was stopped"
+            );
+            for (index, frame) in backtrace.iter().enumerate() {
+                let frame = frame.to_string().replace('\n', "\n       ");
+                eprintln!("{index:>4}: {frame}");
+            }
+        }
+        SimulatorEvent::AssertionFailed {
+            message,
+            task_id,
+            task_name,
+            backtrace,
+        } => {
+            eprintln!("{RED}{BOLD}assertion failed{RESET}{BOLD}:{RESET} {message}");
+            eprintln!("  {DIM}in task `{task_name}` (#{task_id}){RESET}");
+            for (index, frame) in backtrace.iter().enumerate() {
+                let frame = frame.to_string().replace('\n', "\n       ");
+                eprintln!("{index:>4}: {frame}");
+            }
+        }
+        SimulatorEvent::TaskCreationFailed {
+            reason, task_name, ..
+        } => {
+            eprintln!(
+                "{YELLOW}{BOLD}warning{RESET}{BOLD}:{RESET} Task `{task_name}` couldn't create a \
+                 task: {reason}"
+            );
+        }
+        SimulatorEvent::InvalidArgument {
+            function,
+            argument,
+            value,
+            reason,
+            task_name,
+            ..
+        } => {
+            eprintln!(
+                "{YELLOW}{BOLD}warning{RESET}{BOLD}:{RESET} Task `{task_name}` called {function} \
+                 with {argument} = {value}, which {reason}"
+            );
+        }
+        SimulatorEvent::LcdSelectorUpdated(selector) => {
+            let choice = selector.choice.as_deref().unwrap_or("unknown");
+            eprintln!(
+                "{DIM}LCD selector showing `{choice}`, choices: {}{RESET}",
+                selector.choices.join(", ")
+            );
+        }
+        SimulatorEvent::PoseSet(pose) => {
+            eprintln!(
+                "{DIM}Robot placed at ({:.1}, {:.1}) in, heading {:.1}°.{RESET}",
+                pose.x, pose.y, pose.heading
+            );
+        }
+        SimulatorEvent::TaskStats(stats) => {
+            eprintln!(
+                "{DIM}CPU usage: {}% ({} tasks).{RESET}",
+                stats.cpu_usage, stats.task_count
+            );
+        }
+        SimulatorEvent::MutexCreated { .. } => {}
+        SimulatorEvent::MutexContended {
+            mutex_id,
+            task_name,
+            holder_name,
+            ..
+        } => {
+            eprintln!("{DIM}Task `{task_name}` is waiting for mutex {mutex_id}, held by `{holder_name}`.{RESET}");
+        }
+        SimulatorEvent::MutexHeldTooLong {
+            mutex_id,
+            task_name,
+            held_millis,
+            ..
+        } => {
+            eprintln!(
+                "{DIM}Task `{task_name}` has held mutex {mutex_id} for {held_millis} ms.{RESET}"
+            );
+        }
+        SimulatorEvent::ResourceLimitExceeded { limit, max } => {
+            let max = match limit {
+                ResourceLimit::Memory => format!("{} MiB", max / 1024 / 1024),
+                ResourceLimit::Tasks => format!("{max} tasks"),
+                ResourceLimit::EventRate => format!("{max} events per second"),
+            };
+            eprintln!(
+                "{RED}{BOLD}error{RESET}{BOLD}:{RESET} Robot code exceeded its {limit} limit of \
+                 {max}"
+            );
+        }
+        SimulatorEvent::TaskCrashed { task_name, .. } => {
+            eprintln!(
+                "{DIM}Task `{task_name}` stopped; the rest of the robot code keeps running.{RESET}"
+            )
+        }
+        SimulatorEvent::LcdInitialized => draw_lcd(&Default::default(), lcd_width),
+        SimulatorEvent::LcdUpdated(lines) => draw_lcd(&lines, lcd_width),
+        SimulatorEvent::LcdColorsUpdated { .. } => {}
+        SimulatorEvent::LcdShutdown => eprintln!("{DIM}LCD shut down.{RESET}"),
+        SimulatorEvent::ControllerDisconnected(controller) => {
+            eprintln!("{DIM}{controller:?} controller disconnected.{RESET}")
+        }
+        SimulatorEvent::MotorUpdated { .. } => {}
+        SimulatorEvent::CompetitionTimer { .. } => {}
+        SimulatorEvent::Telemetry(_)
+        | SimulatorEvent::ChannelStats(_)
+        | SimulatorEvent::Frame { .. } => {}
+        SimulatorEvent::ApiCoverage(coverage) => {
+            let used = coverage.calls.values().filter(|count| **count > 0).count();
+            eprintln!(
+                "{DIM}Robot code called {used} of the {} APIs it imports.{RESET}",
+                coverage.calls.len()
+            );
+        }
+        SimulatorEvent::Log {
+            level,
+            message,
+            task_name,
+            ..
+        } => {
+            let color = match level {
+                LogLevel::Error => RED,
+                LogLevel::Warn => YELLOW,
+                LogLevel::Info => "",
+                LogLevel::Debug | LogLevel::Trace => DIM,
+            };
+            eprintln!("{color}{BOLD}{level}{RESET} {DIM}[{task_name}]{RESET} {message}");
+        }
+        SimulatorEvent::TestStarted { name } => eprintln!("{DIM}Running test `{name}`...{RESET}"),
+        SimulatorEvent::TestFinished(result) => {
+            let status = if result.passed {
+                format!("{GREEN}ok{RESET}")
+            } else {
+                format!("{RED}{BOLD}FAILED{RESET}")
+            };
+            eprintln!("test {} ... {status}", result.name);
+        }
+        // events added after this version of the CLI
+        _ => {}
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let args = Args::parse();
+
+    let (tx, rx) = mpsc::channel::<SimulatorMessage>();
+    tx.send(SimulatorMessage::PhaseChange(args.phase.into()))
+        .unwrap();
+
+    let mut options = SimulatorOptions::new();
+    if let Some(timeout) = args.timeout {
+        options = options.timeout(Timeout::RealTime(timeout));
+    }
+    if let Some(timeout) = args.simulated_timeout {
+        options = options.timeout(Timeout::Simulated(timeout));
+    }
+    if let Some(seed) = args.seed {
+        options = options.deterministic(seed);
+    }
+    options = options
+        .threaded(args.threaded)
+        .start_millis(args.start_millis)
+        .target_pros_version(args.pros_version)
+        .permissive(args.permissive)
+        .check_errno(args.check_errno)
+        .canaries(args.canaries)
+        .isolate_crashes(args.isolate_crashes)
+        .lcd_selector(args.lcd_selector)
+        .test_build(args.test_build)
+        .task_stats(args.task_stats)
+        .line_buffering(args.line_buffering);
+    if args.hot_start {
+        options = options.start_kind(StartKind::Hot);
+    }
+    for (port, device) in &args.devices {
+        options = options.smart_port(*port, *device);
+    }
+    for (key, value) in &args.test_config {
+        options = options.test_config(key, value);
+    }
+    if let Some(pattern) = &args.run_tests {
+        options = options.run_tests(pattern);
+    }
+    for kind in &args.strict {
+        options = options.strict(*kind);
+    }
+    if args.run_match {
+        options = options.run_match(MatchTiming::default());
+    }
+    if let Some(max_tasks) = args.instance_pool {
+        options = options.instance_pool(max_tasks);
+    }
+    if let Some(baud_rate) = args.throttle_serial {
+        options = options.throttle_serial(baud_rate);
+    }
+    if let Some(hz) = args.telemetry {
+        options = options.telemetry(hz);
+    }
+    if let Some(hz) = args.motor_update_rate {
+        options = options.motor_update_rate(hz);
+    }
+    if let Some(hz) = args.channel_stats {
+        options = options.channel_stats(hz);
+    }
+    if let Some(max_delay) = args.jitter {
+        options = options.jitter(Duration::from_millis(max_delay));
+    }
+    if let Some(threshold) = args.mutex_events {
+        options = options.mutex_events(Duration::from_millis(threshold));
+    }
+    if let Some(mib) = args.max_memory {
+        options = options.max_memory(mib * 1024 * 1024);
+    }
+    if let Some(max_tasks) = args.max_tasks {
+        options = options.max_tasks(max_tasks);
+    }
+    if let Some(max) = args.max_event_rate {
+        options = options.max_event_rate(max);
+    }
+    if let Some(path) = &args.flash {
+        options = options.flash(path);
+    }
+    if let Some(output) = &args.profile {
+        options = options.profile(output);
+    }
+    if let Some(output) = &args.chrome_trace {
+        options = options.chrome_trace(output);
+    }
+    if let Some(output) = &args.coverage {
+        options = options.coverage_report(output);
+    }
+
+    let log_level = args.log_level;
+    let mut display = DisplayGeometry::default();
+    let res = pros_simulator::simulate(
+        &args.robot_code,
+        options,
+        move |event| {
+            // keep the message channel open for as long as the simulator is running
+            let _ = &tx;
+            if matches!(&event, SimulatorEvent::Log { level, .. } if *level > log_level) {
+                return;
+            }
+            render_event(event, &mut display);
+        },
+        rx,
+    )
+    .await;
+
+    let outcome = match res {
+        Ok(outcome) => outcome,
+        Err(err) => {
+            eprintln!("{RED}{BOLD}error{RESET}{BOLD}:{RESET} {err:?}");
+            exit(1);
+        }
+    };
+    let time = outcome.simulated_time.as_secs_f64();
+    let code = match outcome.reason {
+        StopReason::Finished => 0,
+        StopReason::Exited(code) => {
+            eprintln!("{DIM}Robot code exited with code {code} after {time:.3}s.{RESET}");
+            code
+        }
+        StopReason::Crashed(_) => 1,
+        StopReason::TimedOut => {
+            eprintln!("{RED}{BOLD}error{RESET}{BOLD}:{RESET} Timed out after {time:.3}s");
+            124
+        }
+        StopReason::Cancelled => 130,
+        StopReason::StrictWarning(kind) => {
+            eprintln!(
+                "{RED}{BOLD}error{RESET}{BOLD}:{RESET} Stopped after {time:.3}s by a strict \
+                 {kind} warning"
+            );
+            1
+        }
+        StopReason::LimitExceeded(_) => 1,
+        StopReason::TestsFailed { failed, total } => {
+            eprintln!("{RED}{BOLD}error{RESET}{BOLD}:{RESET} {failed} of {total} tests failed");
+            1
+        }
+    };
+    exit(code);
+}
@@ -1,10 +1,297 @@
+//! Types for communicating with `pros-simulator`, which can be sent over IPC or a WebSocket as
+//! JSON.
+//!
+//! ## Compatibility
+//!
+//! Frontends built against an older version of this crate keep working with newer simulators:
+//!
+//! * Variants and fields are sent with their Rust names. One that's renamed keeps its old name on
+//!   the wire with `#[serde(rename)]`, or accepts it as a `#[serde(alias)]` if it's sent with
+//!   the new one.
+//! * When a payload gets more structure, its old form is still accepted, and is still what's
+//!   sent if the new parts aren't used. For example, [`ConsoleOutput`] and [`LcdLine`] are plain
+//!   strings unless they say which task wrote them or what color they are.
+//! * Fields added to messages are optional, so messages from older frontends still deserialize.
+//!   Fields added to events are ignored by older frontends, which don't reject unknown fields.
+//! * New events and messages may be added in minor versions, so both enums are
+//!   `#[non_exhaustive]`. Frontends reading a stream of events should skip ones they can't
+//!   deserialize instead of giving up.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    ops::{Index, IndexMut},
+    str::FromStr,
+};
+
 use serde::{Deserialize, Serialize};
+use unicode_width::UnicodeWidthChar;
 
+/// The size of the LCD emulator in lines and columns of text. Frontends should draw the LCD at
+/// the size given in [`ProgramInfo::display`] instead where it's available.
 pub const LCD_HEIGHT: u32 = 8;
 pub const LCD_WIDTH: u32 = 40;
-pub type LcdLines = [String; LCD_HEIGHT as usize];
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+/// The size of the brain's screen in pixels, including the 32 pixel tall status bar VEXos draws
+/// along the top.
+pub const SCREEN_WIDTH: u32 = 480;
+pub const SCREEN_HEIGHT: u32 = 272;
+
+/// The size of a controller's screen in lines and columns of text.
+pub const CONTROLLER_SCREEN_HEIGHT: u32 = 3;
+pub const CONTROLLER_SCREEN_WIDTH: u32 = 15;
+
+/// How many columns of the LCD some text takes up. Wide characters, like most CJK characters and
+/// emoji, take up two columns, and control characters take up none.
+pub fn text_width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+/// The longest prefix of some text that fits in `width` columns. Characters are never split, so
+/// a wide character that would straddle the edge is dropped.
+pub fn truncate_to_width(text: &str, width: usize) -> &str {
+    let mut used = 0;
+    for (index, char) in text.char_indices() {
+        used += char_width(char);
+        if used > width {
+            return &text[..index];
+        }
+    }
+    text
+}
+
+/// How many columns a character takes up on the LCD: 2 for wide characters like kanji, and 0
+/// for control characters.
+pub fn char_width(char: char) -> usize {
+    char.width().unwrap_or(0)
+}
+
+/// A single line of text on the LCD.
+///
+/// Lines without color overrides are serialized as plain strings, so the JSON representation of
+/// [`LcdLines`] is compatible with the older `[String; 8]` format.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+#[serde(from = "LcdLineRepr", into = "LcdLineRepr")]
+pub struct LcdLine {
+    pub text: String,
+    /// Foreground color (RGBA) of this line, if different from the rest of the LCD.
+    pub foreground: Option<u32>,
+    /// Background color (RGBA) of this line, if different from the rest of the LCD.
+    pub background: Option<u32>,
+}
+
+impl From<String> for LcdLine {
+    fn from(text: String) -> Self {
+        Self {
+            text,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<&str> for LcdLine {
+    fn from(text: &str) -> Self {
+        text.to_string().into()
+    }
+}
+
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum LcdLineRepr {
+    Text(String),
+    Rich {
+        text: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        foreground: Option<u32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        background: Option<u32>,
+    },
+}
+
+impl From<LcdLineRepr> for LcdLine {
+    fn from(repr: LcdLineRepr) -> Self {
+        match repr {
+            LcdLineRepr::Text(text) => text.into(),
+            LcdLineRepr::Rich {
+                text,
+                foreground,
+                background,
+            } => Self {
+                text,
+                foreground,
+                background,
+            },
+        }
+    }
+}
+
+impl From<LcdLine> for LcdLineRepr {
+    fn from(line: LcdLine) -> Self {
+        if line.foreground.is_none() && line.background.is_none() {
+            Self::Text(line.text)
+        } else {
+            Self::Rich {
+                text: line.text,
+                foreground: line.foreground,
+                background: line.background,
+            }
+        }
+    }
+}
+
+#[cfg(feature = "schemars")]
+impl schemars::JsonSchema for LcdLine {
+    fn schema_name() -> String {
+        "LcdLine".to_string()
+    }
+
+    fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        LcdLineRepr::json_schema(gen)
+    }
+}
+
+/// Text robot code wrote to the console, and the task that wrote it.
+///
+/// Output that isn't attributed to a task is serialized as a plain string, so the JSON
+/// representation of [`SimulatorEvent::ConsoleMessage`] is compatible with the older format.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+#[serde(from = "ConsoleOutputRepr", into = "ConsoleOutputRepr")]
+pub struct ConsoleOutput {
+    pub text: String,
+    /// ID of the task that wrote the output.
+    pub task_id: Option<u32>,
+    /// Name of the task that wrote the output.
+    pub task_name: Option<String>,
+}
+
+impl From<String> for ConsoleOutput {
+    fn from(text: String) -> Self {
+        Self {
+            text,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<&str> for ConsoleOutput {
+    fn from(text: &str) -> Self {
+        text.to_string().into()
+    }
+}
+
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ConsoleOutputRepr {
+    Text(String),
+    Attributed {
+        text: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        task_id: Option<u32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        task_name: Option<String>,
+    },
+}
+
+impl From<ConsoleOutputRepr> for ConsoleOutput {
+    fn from(repr: ConsoleOutputRepr) -> Self {
+        match repr {
+            ConsoleOutputRepr::Text(text) => text.into(),
+            ConsoleOutputRepr::Attributed {
+                text,
+                task_id,
+                task_name,
+            } => Self {
+                text,
+                task_id,
+                task_name,
+            },
+        }
+    }
+}
+
+impl From<ConsoleOutput> for ConsoleOutputRepr {
+    fn from(output: ConsoleOutput) -> Self {
+        if output.task_id.is_none() && output.task_name.is_none() {
+            Self::Text(output.text)
+        } else {
+            Self::Attributed {
+                text: output.text,
+                task_id: output.task_id,
+                task_name: output.task_name,
+            }
+        }
+    }
+}
+
+#[cfg(feature = "schemars")]
+impl schemars::JsonSchema for ConsoleOutput {
+    fn schema_name() -> String {
+        "ConsoleOutput".to_string()
+    }
+
+    fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        ConsoleOutputRepr::json_schema(gen)
+    }
+}
+
+/// The contents of every line of the LCD, from top to bottom.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+#[serde(transparent)]
+pub struct LcdLines(pub [LcdLine; LCD_HEIGHT as usize]);
+
+impl LcdLines {
+    pub fn iter(&self) -> impl Iterator<Item = &LcdLine> {
+        self.0.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut LcdLine> {
+        self.0.iter_mut()
+    }
+}
+
+impl<'a> IntoIterator for &'a LcdLines {
+    type Item = &'a LcdLine;
+    type IntoIter = std::slice::Iter<'a, LcdLine>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut LcdLines {
+    type Item = &'a mut LcdLine;
+    type IntoIter = std::slice::IterMut<'a, LcdLine>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter_mut()
+    }
+}
+
+impl Index<usize> for LcdLines {
+    type Output = LcdLine;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.0[index]
+    }
+}
+
+impl IndexMut<usize> for LcdLines {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.0[index]
+    }
+}
+
+impl From<[String; LCD_HEIGHT as usize]> for LcdLines {
+    fn from(lines: [String; LCD_HEIGHT as usize]) -> Self {
+        Self(lines.map(LcdLine::from))
+    }
+}
+
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
 pub struct DigitalControllerState {
     pub l1: bool,
     pub l2: bool,
@@ -20,7 +307,8 @@ pub struct DigitalControllerState {
     pub a: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
 pub struct AnalogControllerState {
     pub left_x: i8,
     pub left_y: i8,
@@ -28,12 +316,132 @@ pub struct AnalogControllerState {
     pub right_y: i8,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
 pub struct ControllerState {
     pub digital: DigitalControllerState,
     pub analog: AnalogControllerState,
+    /// The legacy competition switch plugged into the master controller's competition port,
+    /// if there is one. It's ignored on the partner controller.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub competition_switch: Option<CompetitionSwitch>,
+}
+
+/// The two toggles of a legacy competition switch, which teams use to practice the phases of a
+/// match without field control. Setting it is an alternative to sending
+/// [`SimulatorMessage::PhaseChange`].
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CompetitionSwitch {
+    /// The Enable/Disable toggle.
+    pub enabled: bool,
+    /// The Autonomous/Driver toggle.
+    pub autonomous: bool,
+}
+
+impl CompetitionSwitch {
+    /// The competition phase the brain sees while the switch is plugged in.
+    pub fn phase(&self) -> CompetitionPhase {
+        CompetitionPhase {
+            autonomous: self.autonomous,
+            enabled: self.enabled,
+            is_competition: true,
+        }
+    }
 }
 
+/// One of the two controllers that can be connected to the brain.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ControllerId {
+    Master,
+    Partner,
+}
+
+/// Shaping applied to a controller's joysticks before robot code reads them, to emulate how
+/// different controllers and radios feel. The deadzone is applied first, then the curve.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub struct InputShaping {
+    /// Joystick values within this distance of the center (0 to 127) read as 0. The rest of
+    /// the range is stretched, so a joystick pushed all the way still reads 127.
+    #[serde(default)]
+    pub deadzone: u8,
+    /// How much of a cubic curve to blend in, from 0 (linear) to 100 percent (fully cubic),
+    /// which gives finer control near the center.
+    #[serde(default)]
+    pub expo: u8,
+}
+
+impl InputShaping {
+    /// Shapes a joystick value from -127 to 127.
+    pub fn apply(&self, value: i8) -> i8 {
+        let deadzone = f64::from(self.deadzone.min(126));
+        let magnitude = f64::from(value.unsigned_abs().min(127));
+        if magnitude <= deadzone {
+            return 0;
+        }
+        let linear = (magnitude - deadzone) / (127.0 - deadzone);
+        let expo = f64::from(self.expo.min(100)) / 100.0;
+        let shaped = expo * linear.powi(3) + (1.0 - expo) * linear;
+        ((shaped * 127.0).round() as i8) * value.signum()
+    }
+}
+
+/// A kind of device that can be plugged into one of the V5 brain's smart ports.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceType {
+    Motor,
+    Rotation,
+    Imu,
+    Distance,
+    Radio,
+    Vision,
+    Adi,
+    Optical,
+    Gps,
+    Serial,
+}
+
+impl Display for DeviceType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Motor => "motor",
+            Self::Rotation => "rotation",
+            Self::Imu => "imu",
+            Self::Distance => "distance",
+            Self::Radio => "radio",
+            Self::Vision => "vision",
+            Self::Adi => "adi",
+            Self::Optical => "optical",
+            Self::Gps => "gps",
+            Self::Serial => "serial",
+        })
+    }
+}
+
+impl FromStr for DeviceType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "motor" => Self::Motor,
+            "rotation" => Self::Rotation,
+            "imu" => Self::Imu,
+            "distance" => Self::Distance,
+            "radio" => Self::Radio,
+            "vision" => Self::Vision,
+            "adi" => Self::Adi,
+            "optical" => Self::Optical,
+            "gps" => Self::Gps,
+            "serial" => Self::Serial,
+            _ => return Err(format!("unknown device type `{s}`")),
+        })
+    }
+}
+
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
 pub struct CompetitionPhase {
     pub autonomous: bool,
@@ -41,23 +449,546 @@ pub struct CompetitionPhase {
     pub is_competition: bool,
 }
 
+/// How a drivetrain's wheels are arranged.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum DriveType {
+    /// Two sides of wheels that turn by driving at different speeds.
+    Tank,
+    /// A tank drive with an extra sideways wheel in the middle.
+    HDrive,
+    /// Four omni wheels mounted diagonally at the corners.
+    XDrive,
+    /// Four mecanum wheels.
+    Mecanum,
+}
+
+/// The physical layout of a robot's drivetrain, so that a frontend or physics model can turn
+/// motor output into robot motion without assuming one.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DrivetrainConfig {
+    pub drive_type: DriveType,
+    /// The diameter of the drive wheels in inches.
+    pub wheel_diameter: f64,
+    /// The distance between the centers of the left and right wheels in inches.
+    pub track_width: f64,
+    /// Wheel rotations per motor output shaft rotation, e.g. `0.6` for a 36:60 reduction.
+    pub gear_ratio: f64,
+    /// Smart ports (1-21) of the motors driving the left side, front to back. Like PROS motor
+    /// groups, a negative port means the motor is reversed.
+    pub left_motors: Vec<i8>,
+    /// Smart ports of the motors driving the right side, front to back.
+    pub right_motors: Vec<i8>,
+    /// Smart ports of the motors driving sideways wheels, for H-drives.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub strafe_motors: Vec<i8>,
+}
+
+// floats are compared bit for bit so that events can be `Eq`
+impl PartialEq for DrivetrainConfig {
+    fn eq(&self, other: &Self) -> bool {
+        self.drive_type == other.drive_type
+            && self.wheel_diameter.to_bits() == other.wheel_diameter.to_bits()
+            && self.track_width.to_bits() == other.track_width.to_bits()
+            && self.gear_ratio.to_bits() == other.gear_ratio.to_bits()
+            && self.left_motors == other.left_motors
+            && self.right_motors == other.right_motors
+            && self.strafe_motors == other.strafe_motors
+    }
+}
+
+impl Eq for DrivetrainConfig {}
+
+/// Motors that work together, like one side of a drivetrain or a lift, whose voltages are sent
+/// together in [`SimulatorEvent::MotorGroupUpdated`] so that dashboards don't have to combine
+/// [`SimulatorEvent::MotorUpdated`] events themselves. See [`SimulatorMessage::AddMotorGroup`].
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct MotorGroup {
+    /// Identifies the group in [`SimulatorEvent::MotorGroupUpdated`], e.g. `left drive`.
+    pub name: String,
+    /// Smart ports (1-21) of the group's motors. Like in a [`DrivetrainConfig`], a negative port
+    /// means the motor is reversed.
+    pub ports: Vec<i8>,
+}
+
+/// A snapshot of the simulated robot's state, sent periodically if telemetry is enabled so that
+/// dashboards can redraw from a single event.
+///
+/// Only state the simulator models is included. Motors come from a model of a motor with nothing
+/// attached, and batteries and sensors aren't simulated yet.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Telemetry {
+    /// The value robot code would get from `millis` at the time of the snapshot.
+    pub millis: u32,
+    pub competition_phase: CompetitionPhase,
+    /// The master controller's state, or `None` if it's disconnected.
+    pub master: Option<ControllerState>,
+    /// The partner controller's state, or `None` if it's disconnected.
+    pub partner: Option<ControllerState>,
+    /// How many tasks exist, including the simulator's system daemon.
+    pub task_count: u32,
+    /// Where the robot was last placed on the field, or `None` if it hasn't been.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pose: Option<Pose>,
+    /// The state of each motor plugged into a smart port, in port order. Older simulators
+    /// didn't send it, so it reads as empty from them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub motors: Vec<MotorState>,
+}
+
+/// A motor's state in a [`Telemetry`] snapshot, in the same units as
+/// [`SimulatorEvent::MotorUpdated`].
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct MotorState {
+    /// The smart port the motor is plugged into, from 1 to 21.
+    pub port: u8,
+    /// The voltage robot code last drove the motor at.
+    pub millivolts: i32,
+    /// How fast the motor is turning, in RPM.
+    pub velocity: i32,
+    /// How far the motor has turned since the simulation started, in degrees.
+    pub position: i32,
+    /// The current the motor draws, in milliamps.
+    pub current: i32,
+}
+
+/// How often the simulator sends each category of frequent event, in times per second.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EventRates {
+    /// The rate of [`SimulatorEvent::Telemetry`] snapshots. `None` disables them.
+    pub telemetry: Option<u32>,
+    /// The most [`SimulatorEvent::MotorUpdated`] events sent for each motor per second. `None`
+    /// sends every change right away.
+    #[serde(default)]
+    pub motor_updates: Option<u32>,
+    /// The rate of [`SimulatorEvent::ChannelStats`] events. `None` disables them.
+    #[serde(default)]
+    pub channel_stats: Option<u32>,
+    /// The rate of [`SimulatorEvent::Frame`] markers, usually the frame rate of the video being
+    /// recorded. `None` disables them.
+    #[serde(default)]
+    pub frames: Option<u32>,
+}
+
+/// How many events the simulator has sent, and what happened to the ones a frontend couldn't
+/// keep up with. Events are only queued, dropped or coalesced when they're streamed with a
+/// limited queue; otherwise they're handed to the frontend as they're sent.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ChannelStats {
+    /// Every event sent since the simulation started, including dropped and coalesced ones.
+    pub sent: u64,
+    /// Events waiting for the frontend to read them.
+    pub queued: u32,
+    /// Events that were thrown away because the queue was full.
+    pub dropped: u64,
+    /// Events that replaced an older queued event of the same kind because the queue was full.
+    pub coalesced: u64,
+}
+
+/// How busy the robot code kept the scheduler over the last second of simulated time,
+/// approximating the CPU usage the brain reports. Time spent in the simulator's own system daemon
+/// counts as idle, like the brain's background work does.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TaskStats {
+    /// The value robot code would get from `millis` at the end of the second.
+    pub millis: u32,
+    /// How many tasks exist, including the simulator's system daemon.
+    pub task_count: u32,
+    /// Microseconds spent running robot code tasks.
+    pub busy_micros: u64,
+    /// Microseconds spent with no robot code task running, because they were all waiting.
+    pub idle_micros: u64,
+    /// The share of the second spent running robot code, from 0 to 100 percent.
+    pub cpu_usage: u8,
+}
+
+/// What the header at the top of the V5 brain's screen shows while a program runs.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BrainHeader {
+    /// Whether the program is running. The header's button stops a program that's running and
+    /// runs it again once it's stopped.
+    pub running: bool,
+    /// How long the program has been running, in whole seconds, like the header's timer.
+    pub seconds: u32,
+    /// The battery's charge, from 0 to 100 percent. Batteries aren't simulated, so this is
+    /// always 100.
+    pub battery: u8,
+}
+
+/// A button in the header of the V5 brain's screen, pressed with a
+/// [`SimulatorMessage::BrainButton`].
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum BrainButton {
+    /// Stop the program that's running.
+    Stop,
+    /// Run the program again after it's stopped, or restart it if it's still running.
+    Run,
+}
+
+/// A task in the simulator's task table, as listed in a [`SimulatorEvent::TaskList`].
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct TaskInfo {
+    /// The task's handle, as returned to robot code by `task_create`.
+    pub id: u32,
+    pub name: String,
+    /// From 1 to 16, like PROS's `TASK_PRIORITY_MIN` to `TASK_PRIORITY_MAX`.
+    pub priority: u32,
+    pub state: TaskState,
+    /// Whether the task belongs to the simulator rather than the robot code, like the system
+    /// daemon, which also runs LCD button callbacks.
+    pub system: bool,
+}
+
+/// What a task is doing, as far as the scheduler knows. Tasks waiting on a delay or mutex are
+/// still `Ready`, since they're woken up by being polled.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum TaskState {
+    /// The task the scheduler is running, which is the system daemon when the list is made.
+    Running,
+    Ready,
+    /// The task has stopped, and will be removed from the table the next time it's scheduled.
+    Finished,
+}
+
+/// Which of the simulator's APIs the robot code used during a run.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct ApiCoverage {
+    /// How many times each function the robot code imports was called, including functions it
+    /// never called.
+    pub calls: BTreeMap<String, u64>,
+    /// Imported functions the simulator doesn't implement. A task that calls one is stopped
+    /// before the call is counted, so their call counts are 0 unless the simulator was told to
+    /// link them to stubs.
+    pub unimplemented: Vec<String>,
+}
+
+/// A version of the PROS kernel API, which robot code can be checked against with
+/// [`SimulatorEvent::ApiCompatibility`].
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProsVersion {
+    /// PROS 3.8, the version pros-rs targets.
+    #[default]
+    V3_8,
+}
+
+impl Display for ProsVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::V3_8 => "3.8",
+        })
+    }
+}
+
+impl FromStr for ProsVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "3.8" => Ok(Self::V3_8),
+            _ => Err(format!("unknown PROS version `{s}` (expected 3.8)")),
+        }
+    }
+}
+
+/// How well the simulator supports the PROS APIs the robot code imports.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct ApiCompatibility {
+    /// The PROS version the imports were checked against.
+    pub target: ProsVersion,
+    /// Imports the simulator implements.
+    pub implemented: Vec<String>,
+    /// Imports the simulator provides but doesn't fully simulate, like
+    /// `controller_get_battery_level`, which always reports a full battery.
+    pub stubbed: Vec<String>,
+    /// Imports from the target version's API that the simulator doesn't implement. A task that
+    /// calls one is stopped with [`SimulatorEvent::UnimplementedCall`], unless the simulator
+    /// was told to link them to stubs.
+    pub missing: Vec<String>,
+    /// Imports that aren't part of the target version's API, like newlib's system calls or the
+    /// simulator's own extensions, whether or not the simulator provides them.
+    pub not_in_target: Vec<String>,
+}
+
+/// A panic in Rust robot code, parsed from the message passed to `sim_abort`.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct RobotCodePanic {
+    /// The panic message, e.g. `called `Option::unwrap()` on a `None` value`.
+    pub message: String,
+    /// The source file containing the panic.
+    pub file: String,
+    pub line: u32,
+    pub column: u32,
+}
+
+/// What the V5 brain's "Data Abort Exception" screen would show after robot code crashed, with
+/// WebAssembly equivalents standing in for the ARM registers.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct DataAbortScreen {
+    /// Offset of the faulting instruction from the start of the robot code module, which stands
+    /// in for the program counter.
+    pub pc: Option<usize>,
+    /// ID of the task that faulted.
+    pub task_id: u32,
+    /// Name of the task that faulted.
+    pub task_name: String,
+    /// Index of the function that faulted in the robot code module.
+    pub func_index: Option<u32>,
+    /// Why the WebAssembly runtime trapped.
+    pub trap: String,
+    /// The task's `errno` value at the time of the fault, if it had been used.
+    pub errno: Option<i32>,
+    /// The program counter of each frame on the stack, innermost first.
+    pub stack_trace: Vec<usize>,
+}
+
+impl Display for DataAbortScreen {
+    /// Formats the screen line by line, like the brain shows it.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "DATA ABORT EXCEPTION")?;
+        match self.pc {
+            Some(pc) => writeln!(f, "PC: {pc:x}")?,
+            None => writeln!(f, "PC: UNKNOWN")?,
+        }
+        writeln!(f, "CURRENT TASK: {}", self.task_name)?;
+        writeln!(f, "REGISTERS AT ABORT")?;
+        if let Some(func_index) = self.func_index {
+            writeln!(f, " FUNC: {func_index}")?;
+        }
+        if let Some(errno) = self.errno {
+            writeln!(f, " ERRNO: {errno}")?;
+        }
+        writeln!(f, " TRAP: {}", self.trap)?;
+        writeln!(f, "STACK TRACE")?;
+        for pc in &self.stack_trace {
+            writeln!(f, " {pc:x}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A function call in a robot code backtrace.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct BacktraceFrame {
+    /// Index of the function in the robot code's WebAssembly module.
+    pub func_index: u32,
+    /// Demangled name of the function, if the robot code has a name section.
+    pub func_name: Option<String>,
+    /// Offset of the instruction being executed from the start of the module.
+    pub module_offset: Option<usize>,
+    /// Source locations of this frame, if the robot code has DWARF debug info. There may be
+    /// more than one if functions have been inlined.
+    pub symbols: Vec<SourceLocation>,
+}
+
+impl Display for BacktraceFrame {
+    /// Formats the frame like the WebAssembly runtime does, e.g.
+    /// `0x1a2b - example::opcontrol` followed by an indented line for each source location.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(offset) = self.module_offset {
+            write!(f, "{offset:#x} - ")?;
+        }
+        match &self.func_name {
+            Some(name) => write!(f, "{name}")?,
+            None => write!(f, "<wasm function {}>", self.func_index)?,
+        }
+        for symbol in &self.symbols {
+            let Some(file) = &symbol.file else {
+                continue;
+            };
+            write!(f, "\n    at {file}")?;
+            if let Some(line) = symbol.line {
+                write!(f, ":{line}")?;
+                if let Some(column) = symbol.column {
+                    write!(f, ":{column}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A location in robot code source, resolved from debug info.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub name: Option<String>,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+}
+
+/// The SDK a robot program was built against, which determines the API the simulator links it
+/// against and how it's started.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ProgramAbi {
+    /// pros-rs, which imports the PROS API and exports `wasm_memalign` and `wasm_free`.
+    ProsRs,
+    /// The C/C++ PROS template, which imports the PROS API and newlib's system calls.
+    ProsC,
+    /// vexide, which imports VEXos's SDK from the `vex` module.
+    Vexide,
+    /// Something the simulator can't run, like a WASI program.
+    Unknown,
+}
+
+impl Display for ProgramAbi {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::ProsRs => "pros-rs",
+            Self::ProsC => "PROS C/C++",
+            Self::Vexide => "vexide",
+            Self::Unknown => "unknown",
+        })
+    }
+}
+
+/// What the simulator detected about a robot program when loading it.
+///
+/// Apart from the ABI, everything here comes from the program's `pros_program` custom section,
+/// which holds `key=value` lines like the ones in PROS's `project.pros`:
+///
+/// ```text
+/// name=Competition Bot
+/// version=1.2.0
+/// slot=3
+/// icon=USER902x.bmp
+/// description=Worlds code
+/// ```
+///
+/// If the section doesn't give a name, the module name from the `name` section is used.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ProgramInfo {
+    pub abi: ProgramAbi,
+    /// The name shown in the brain's program list.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// The program slot (1 to 8) the program is uploaded to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slot: Option<u8>,
+    /// The file name of the program's icon on the brain, e.g. `USER902x.bmp`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The size of the displays the simulator emulates, which frontends should draw them at.
+    /// The simulator always sends this, but older simulators leave it out, in which case the
+    /// displays are [`DisplayGeometry::default`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<DisplayGeometry>,
+}
+
+impl ProgramInfo {
+    /// Info about a program that doesn't describe itself.
+    pub fn new(abi: ProgramAbi) -> Self {
+        Self {
+            abi,
+            name: None,
+            version: None,
+            slot: None,
+            icon: None,
+            description: None,
+            display: None,
+        }
+    }
+}
+
+/// The size of the displays robot code can draw on.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct DisplayGeometry {
+    /// The LCD emulator's size in columns and lines of text.
+    pub lcd_width: u32,
+    pub lcd_height: u32,
+    /// The brain screen's size in pixels, including the status bar along the top.
+    pub screen_width: u32,
+    pub screen_height: u32,
+}
+
+impl Default for DisplayGeometry {
+    fn default() -> Self {
+        Self {
+            lcd_width: LCD_WIDTH,
+            lcd_height: LCD_HEIGHT,
+            screen_width: SCREEN_WIDTH,
+            screen_height: SCREEN_HEIGHT,
+        }
+    }
+}
+
 /// An event that happens inside the simulator that the API consumer might want to know about.
 /// Use this to monitor robot code progress, simulated LCD updates, log messages, and more.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[allow(clippy::large_enum_variant)]
+#[non_exhaustive]
 pub enum SimulatorEvent {
     /// A warning message has been emitted by the simulator backend. The robot code is likely using the PROS API incorrectly.
     Warning(String),
-    /// The robot code has written the following text to the simulated serial port. A trailing newline should not be assumed.
-    ConsoleMessage(String),
+    /// The robot code has written the following text to the simulated serial port. A trailing
+    /// newline should not be assumed. Output is tagged with the task that wrote it, so
+    /// interleaved prints from several tasks can be untangled.
+    ConsoleMessage(ConsoleOutput),
 
     /// The robot code is being loaded into the simulator and compiled.
     RobotCodeLoading,
+    /// The robot code has been compiled, and the simulator has detected which SDK it was built
+    /// against. Sent before the robot code is rejected if the simulator can't run it.
+    ProgramInfo(ProgramInfo),
+    /// Which of the PROS APIs the robot code imports the simulator supports, checked against the
+    /// PROS version the simulator was configured to target. Sent while the robot code is
+    /// loading, for PROS programs only.
+    ApiCompatibility(ApiCompatibility),
     /// The robot code has begun executing and the initialize/opcontrol task is about to be spawned.
     RobotCodeStarting,
     /// All tasks have finished executing.
     RobotCodeFinished,
     /// The robot code has panicked or otherwise faulted.
-    RobotCodeError { message: String, backtrace: String },
+    RobotCodeError {
+        /// Description of what went wrong.
+        message: String,
+        /// ID of the task that faulted.
+        task_id: u32,
+        /// Name of the task that faulted.
+        task_name: String,
+        /// The task's `errno` value at the time of the fault, if it had been used.
+        errno: Option<i32>,
+        /// The reason the WebAssembly runtime trapped, if it did (e.g. an out of bounds memory
+        /// access or unreachable instruction).
+        trap: Option<String>,
+        /// The functions being executed when the fault happened, innermost first.
+        backtrace: Vec<BacktraceFrame>,
+        /// Details of the panic, if the fault was caused by a Rust panic.
+        panic: Option<RobotCodePanic>,
+    },
+    /// A task called an imported function the simulator doesn't implement. The task is stopped,
+    /// but the rest of the robot code keeps running.
+    UnimplementedCall {
+        /// The name of the function that was called.
+        name: String,
+        /// The functions being executed when the call happened, innermost first.
+        backtrace: Vec<BacktraceFrame>,
+    },
 
     /// The LCD has been initialized and may be updated in the future.
     LcdInitialized,
@@ -67,12 +998,704 @@ pub enum SimulatorEvent {
     LcdColorsUpdated { foreground: u32, background: u32 },
     /// The LCD has shut down and should be blanked.
     LcdShutdown,
+
+    /// A controller has been disconnected. Robot code reads 0 from all of its buttons and
+    /// joysticks until it reconnects.
+    ControllerDisconnected(ControllerId),
+
+    /// A motor's voltage or modeled state changed. Ports are numbered from 1 to 21, like on the
+    /// brain. Changes can be combined into fewer events with [`EventRates::motor_updates`].
+    ///
+    /// The motor's velocity, position and current draw come from a model of a motor with
+    /// nothing attached, as it was when the change was reported, so a motor speeding up at the
+    /// same voltage keeps being reported until it settles. Older simulators only sent an event
+    /// when the voltage changed, and didn't send these fields or `millis`, so they read as 0
+    /// from them.
+    MotorUpdated {
+        port: u8,
+        millivolts: i32,
+        /// The value robot code would get from `millis` when the change was reported.
+        #[serde(default)]
+        millis: u32,
+        /// The speed the motor settles at when driven at `millivolts`, in RPM.
+        #[serde(default)]
+        target_velocity: i32,
+        /// How fast the motor is turning, in RPM.
+        #[serde(default)]
+        actual_velocity: i32,
+        /// How far the motor has turned since the simulation started, in degrees.
+        #[serde(default)]
+        position: i32,
+        /// The current the motor draws, in milliamps.
+        #[serde(default)]
+        current: i32,
+    },
+
+    /// The time left in the current period of an automated match, sent when each period starts
+    /// and then ten times per second. Once the match is over, this is sent one last time with
+    /// the robot disabled and no time left, and the simulation stops.
+    CompetitionTimer {
+        phase: CompetitionPhase,
+        remaining_ms: u32,
+    },
+
+    /// A periodic snapshot of the robot's state, if telemetry was enabled when the simulation
+    /// started.
+    Telemetry(Telemetry),
+    /// Periodic counts of the events the simulator has sent, if enabled with
+    /// [`EventRates::channel_stats`].
+    ChannelStats(ChannelStats),
+    /// A summary of which APIs the robot code called, sent once the simulation has stopped.
+    ApiCoverage(ApiCoverage),
+    /// The contents of robot code memory, in reply to [`SimulatorMessage::ReadMemory`]. If the
+    /// memory couldn't be read, a [`Warning`](SimulatorEvent::Warning) is sent instead.
+    MemoryValue {
+        location: MemoryLocation,
+        /// The address `location` resolved to.
+        address: u32,
+        bytes: Vec<u8>,
+    },
+    /// The value of a variable watched with [`SimulatorMessage::Watch`], sent at the requested
+    /// rate.
+    WatchValue {
+        location: MemoryLocation,
+        /// The value robot code would get from `millis` when the variable was read.
+        millis: u32,
+        value: WatchValue,
+    },
+    /// Robot code called an API it was asked to break on with
+    /// [`SimulatorMessage::BreakOnCall`], and is paused until a [`SimulatorMessage::Resume`].
+    Breakpoint {
+        api: String,
+        /// The arguments the API was called with.
+        args: Vec<i64>,
+        /// ID of the task that called the API.
+        task_id: u32,
+        /// Name of the task that called the API.
+        task_name: String,
+        /// The functions being executed when the API was called, innermost first.
+        backtrace: Vec<BacktraceFrame>,
+    },
+    /// Robot code called `sim_assert` with a false condition. The robot code carries on unless
+    /// failed assertions were made strict.
+    AssertionFailed {
+        message: String,
+        /// ID of the task that made the assertion.
+        task_id: u32,
+        /// Name of the task that made the assertion.
+        task_name: String,
+        /// The functions being executed when the assertion failed, innermost first.
+        backtrace: Vec<BacktraceFrame>,
+    },
+    /// Robot code tried to create a task that couldn't be created. Like on a real brain,
+    /// `task_create` returned `NULL` with `errno` set, and the robot code carries on.
+    TaskCreationFailed {
+        /// Why the task couldn't be created.
+        reason: String,
+        /// The `errno` `task_create` set.
+        errno: i32,
+        /// ID of the task that tried to create it.
+        task_id: u32,
+        /// Name of the task that tried to create it.
+        task_name: String,
+    },
+    /// A task faulted and was stopped, but the rest of the robot code keeps running because
+    /// crashes are isolated to the task that caused them. The details of the fault are in the
+    /// [`RobotCodeError`](Self::RobotCodeError) sent before this.
+    TaskCrashed {
+        /// ID of the task that faulted.
+        task_id: u32,
+        /// Name of the task that faulted.
+        task_name: String,
+    },
+    /// Robot code trapped, and this is what the brain's "Data Abort Exception" screen would show.
+    /// Sent just after the [`RobotCodeError`](Self::RobotCodeError) for the same fault.
+    DataAbort(DataAbortScreen),
+    /// Robot code passed an invalid argument to an API function, which failed and set `errno`
+    /// instead of running.
+    InvalidArgument {
+        /// The API function that was called.
+        function: String,
+        /// The name of the invalid argument.
+        argument: String,
+        /// The value that was passed. Pointers are addresses in robot code memory.
+        value: i64,
+        /// Why the value is invalid, completing "which …", e.g. "isn't a smart port".
+        reason: String,
+        /// The `errno` value the call failed with.
+        errno: i32,
+        /// ID of the task that made the call.
+        task_id: u32,
+        /// Name of the task that made the call.
+        task_name: String,
+    },
+    /// The LCD's autonomous selector changed, because a button callback was registered or
+    /// cleared or a new choice was shown. Only sent with
+    /// `SimulatorOptions::lcd_selector`, once a callback has been registered.
+    LcdSelectorUpdated(LcdSelector),
+    /// The programs loaded into the simulated brain's slots, like its program selection screen.
+    /// Sent by `pros-simulator-server` once, before the first program starts, when it's given
+    /// more than one program.
+    ProgramSlots(Vec<ProgramSlot>),
+    /// The program in the given slot is about to be loaded, because it was chosen with
+    /// [`SimulatorMessage::SelectSlot`] or is the first program of the session. The events that
+    /// follow, up to the next `SlotSelected`, come from that program.
+    SlotSelected(u8),
+    /// The robot was placed on the field, by robot code in a test build calling `sim_set_pose`
+    /// or by a [`SimulatorMessage::SetPose`]. The simulator doesn't model the field, so this is
+    /// for frontends that do.
+    PoseSet(Pose),
+    /// A marker for lining up video of a frontend with the event log, sent at the rate set by
+    /// `SimulatorOptions::frame_markers` or [`EventRates::frames`]. Frames are numbered by the
+    /// simulation's clock, so frame `n` of a `hz` rate is always `n / hz` seconds after the
+    /// robot code started. Frames are skipped rather than sent late if the simulator falls
+    /// behind, so numbers only ever go up, but not always by one.
+    Frame {
+        frame: u64,
+        /// The value robot code would get from `millis` at the start of the frame.
+        millis: u32,
+    },
+    /// Robot code created a mutex. Only sent with `SimulatorOptions::mutex_events`, like the
+    /// other mutex events.
+    MutexCreated {
+        mutex_id: u32,
+        /// ID of the task that created it.
+        task_id: u32,
+        /// Name of the task that created it.
+        task_name: String,
+    },
+    /// A task tried to take a mutex that another task is holding, so it has to wait (or give
+    /// up, if it didn't give a timeout).
+    MutexContended {
+        mutex_id: u32,
+        /// ID of the task that has to wait.
+        task_id: u32,
+        /// Name of the task that has to wait.
+        task_name: String,
+        /// ID of the task holding the mutex.
+        holder_id: u32,
+        /// Name of the task holding the mutex.
+        holder_name: String,
+    },
+    /// A task has been holding a mutex for longer than the threshold given to
+    /// `SimulatorOptions::mutex_events`. Sent once per hold, as soon as the threshold passes,
+    /// so a task that never gives the mutex back is still reported.
+    MutexHeldTooLong {
+        mutex_id: u32,
+        /// ID of the task holding the mutex.
+        task_id: u32,
+        /// Name of the task holding the mutex.
+        task_name: String,
+        /// How long it had been held when this was sent.
+        held_millis: u32,
+    },
+    /// How much of the last second of simulated time robot code spent running rather than
+    /// idle, sent every second if enabled with `SimulatorOptions::task_stats`.
+    TaskStats(TaskStats),
+    /// Every task that exists, including the simulator's own, sent in reply to a
+    /// [`SimulatorMessage::ListTasks`]. Its length is what robot code would get from
+    /// `task_get_count`.
+    TaskList(Vec<TaskInfo>),
+    /// Robot code used more of a resource than the simulator was allowed to give it, so the
+    /// simulation is stopping. `max` is the limit, in the resource's unit.
+    ResourceLimitExceeded { limit: ResourceLimit, max: u64 },
+    /// The first event on each connection to a server running several simulations at once,
+    /// giving the ID of the session the connection was given. Every event after it comes from
+    /// that session's simulation.
+    SessionStarted { session: u64 },
+    /// Robot code logged a message with `sim_log`, which unlike console output has a level
+    /// frontends can filter by.
+    Log {
+        level: LogLevel,
+        message: String,
+        task_id: u32,
+        task_name: String,
+    },
+    /// The robot scored points in a [`ScoringZone`], or lost the points of an
+    /// [`Inside`](ScoringRule::Inside) zone by leaving it.
+    ScoreChanged {
+        zone: String,
+        /// How much the score changed by.
+        points: i32,
+        /// The total score of every zone.
+        score: i32,
+        /// The value robot code would get from `millis` when the score changed.
+        millis: u32,
+    },
+    /// A game object was placed on the field or pushed, for frontends to draw it where it is
+    /// now. Objects with the same name replace each other.
+    GameObjectUpdated(GameObject),
+    /// An intake picked up or let out a game object, or a lift moved. Lift heights are sent
+    /// when they've moved a tenth of an inch or reached the end of their travel.
+    MechanismUpdated(MechanismState),
+    /// Robot code drew on part of the brain's screen, e.g. with LVGL. Changes are sent at most 60
+    /// times per second of simulated time, like the screen's refresh rate, or each time robot
+    /// code calls `vexDisplayRender` once it has.
+    ScreenUpdated(ScreenRegion),
+    /// A test exported by the robot code started running in a task of its own. Only sent when
+    /// the simulator is running the robot code's tests instead of its competition functions.
+    TestStarted { name: String },
+    /// A test exported by the robot code finished.
+    TestFinished(TestResult),
+    /// The header at the top of the brain's screen changed, for frontends to draw it like the
+    /// brain does. Sent when the program starts, each time its timer reaches another second,
+    /// and once it's stopped.
+    BrainHeader(BrainHeader),
+    /// Robot code sent its own event with `sim_emit_event`, e.g. odometry or the state of a
+    /// state machine. The simulator doesn't interpret `bytes`; frontends that know the `tag` can
+    /// decode them however the robot code encoded them.
+    Custom { tag: String, bytes: Vec<u8> },
+    /// The scheduler's bookkeeping broke one of its invariants, which means the simulator has a
+    /// bug rather than the robot code. Only checked with `SimulatorOptions::check_scheduler`,
+    /// and sent once per invariant.
+    SchedulerInvariantViolated {
+        invariant: SchedulerInvariant,
+        message: String,
+    },
+    /// Robot code wrote to a controller's screen. `lines` is everything on the screen
+    /// afterwards, with [`CONTROLLER_SCREEN_HEIGHT`] lines of up to [`CONTROLLER_SCREEN_WIDTH`]
+    /// characters.
+    ControllerTextUpdated {
+        controller: ControllerId,
+        lines: Vec<String>,
+    },
+    /// Robot code rumbled a controller. Each character of `pattern` is a short rumble (`.`), a
+    /// long rumble (`-`) or a pause (` `).
+    ControllerRumbled {
+        controller: ControllerId,
+        pattern: String,
+    },
+    /// The voltage of a motor in a [`MotorGroup`] changed. `motors` has the voltage of each of
+    /// the group's motors in millivolts, in the order of its ports and negated for reversed
+    /// motors, and `millivolts` is their average. Sent along with the motors'
+    /// [`MotorUpdated`](Self::MotorUpdated) events, so it's rate limited the same way.
+    MotorGroupUpdated {
+        name: String,
+        millivolts: i32,
+        motors: Vec<i32>,
+    },
+    /// The robot's drivetrain, for frontends and physics models to turn the voltages of its
+    /// motor groups into robot motion. Sent once, just before
+    /// [`RobotCodeStarting`](Self::RobotCodeStarting), if `SimulatorOptions::drivetrain` is
+    /// set.
+    DrivetrainConfigured(DrivetrainConfig),
+}
+
+/// Something that should always be true of the simulator's scheduler between cycles, reported
+/// in a [`SimulatorEvent::SchedulerInvariantViolated`] if it isn't.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum SchedulerInvariant {
+    /// The task chosen to run next is one of the tasks in the task table.
+    CurrentTask,
+    /// Tasks that have finished or been deleted are never chosen to run.
+    NoFinishedTasks,
+    /// The scheduler is suspended exactly while a task that exists and is running holds it
+    /// suspended with `rtos_suspend_all`.
+    SuspendBalance,
+}
+
+/// A resource whose use by robot code can be limited, for simulating untrusted code.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceLimit {
+    /// Bytes of robot code memory.
+    Memory,
+    /// Robot code tasks existing at once.
+    Tasks,
+    /// Events sent in a second.
+    EventRate,
+}
+
+impl Display for ResourceLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Memory => "memory",
+            Self::Tasks => "task",
+            Self::EventRate => "event rate",
+        })
+    }
+}
+
+/// How severe a message robot code logged with `sim_log` is. Levels are ordered from most to
+/// least severe, so `level <= LogLevel::Info` keeps errors, warnings and info.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    /// The level robot code passes to `sim_log`, numbered like the `log` crate's levels: 1 for
+    /// errors up to 5 for traces.
+    pub fn from_number(level: i32) -> Option<Self> {
+        Some(match level {
+            1 => Self::Error,
+            2 => Self::Warn,
+            3 => Self::Info,
+            4 => Self::Debug,
+            5 => Self::Trace,
+            _ => return None,
+        })
+    }
+}
+
+impl Display for LogLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Error => "error",
+            Self::Warn => "warn",
+            Self::Info => "info",
+            Self::Debug => "debug",
+            Self::Trace => "trace",
+        })
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "error" => Self::Error,
+            "warn" => Self::Warn,
+            "info" => Self::Info,
+            "debug" => Self::Debug,
+            "trace" => Self::Trace,
+            _ => return Err(format!("unknown log level `{s}`")),
+        })
+    }
+}
+
+/// Where the robot is on the field.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct Pose {
+    /// Inches from the field's origin.
+    pub x: f64,
+    /// Inches from the field's origin.
+    pub y: f64,
+    /// Degrees clockwise, like an IMU's heading.
+    pub heading: f64,
+}
+
+// floats are compared bit for bit so that events can be `Eq`
+impl PartialEq for Pose {
+    fn eq(&self, other: &Self) -> bool {
+        self.x.to_bits() == other.x.to_bits()
+            && self.y.to_bits() == other.y.to_bits()
+            && self.heading.to_bits() == other.heading.to_bits()
+    }
+}
+
+impl Eq for Pose {}
+
+/// A part of the field that scores points when the robot reaches it, e.g. an autonomous
+/// win-point line, a parking zone, or a game object the robot has to touch. Zones are judged
+/// against the robot's [`Pose`], so the robot only moves if a frontend or test build moves it.
+/// See [`SimulatorMessage::AddScoringZone`].
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ScoringZone {
+    /// Identifies the zone in [`SimulatorEvent::ScoreChanged`].
+    pub name: String,
+    pub shape: ZoneShape,
+    /// The points the zone is worth, which can be negative for penalties.
+    pub points: i32,
+    #[serde(default)]
+    pub rule: ScoringRule,
+    /// Only score the zone during autonomous. Points already scored are kept once autonomous
+    /// ends, so `Inside` zones count where the robot was when it ended.
+    #[serde(default)]
+    pub autonomous_only: bool,
+}
+
+/// The area a [`ScoringZone`] covers, in inches from the field's origin like a [`Pose`].
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub enum ZoneShape {
+    Rectangle {
+        min_x: f64,
+        min_y: f64,
+        max_x: f64,
+        max_y: f64,
+    },
+    /// A circle, e.g. around a game object the robot has to reach.
+    Circle { x: f64, y: f64, radius: f64 },
+}
+
+impl ZoneShape {
+    /// Whether the robot's center is in the zone.
+    pub fn contains(&self, pose: &Pose) -> bool {
+        match *self {
+            Self::Rectangle {
+                min_x,
+                min_y,
+                max_x,
+                max_y,
+            } => (min_x..=max_x).contains(&pose.x) && (min_y..=max_y).contains(&pose.y),
+            Self::Circle { x, y, radius } => (pose.x - x).hypot(pose.y - y) <= radius,
+        }
+    }
+}
+
+// floats are compared bit for bit so that messages can be `Eq`
+impl PartialEq for ZoneShape {
+    fn eq(&self, other: &Self) -> bool {
+        let bits = |values: &[f64]| {
+            values
+                .iter()
+                .map(|value| value.to_bits())
+                .collect::<Vec<_>>()
+        };
+        match (*self, *other) {
+            (
+                Self::Rectangle {
+                    min_x,
+                    min_y,
+                    max_x,
+                    max_y,
+                },
+                Self::Rectangle {
+                    min_x: other_min_x,
+                    min_y: other_min_y,
+                    max_x: other_max_x,
+                    max_y: other_max_y,
+                },
+            ) => {
+                bits(&[min_x, min_y, max_x, max_y])
+                    == bits(&[other_min_x, other_min_y, other_max_x, other_max_y])
+            }
+            (
+                Self::Circle { x, y, radius },
+                Self::Circle {
+                    x: other_x,
+                    y: other_y,
+                    radius: other_radius,
+                },
+            ) => bits(&[x, y, radius]) == bits(&[other_x, other_y, other_radius]),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for ZoneShape {}
+
+/// A game object on the field, like a ball, ring or mobile goal, which the robot pushes out of its
+/// way as its [`Pose`] changes. See [`SimulatorMessage::AddGameObject`].
+///
+/// Objects are circles, and so is the robot, with the radius set by
+/// `SimulatorOptions::robot_radius`. Objects push each other too, but nothing pushes back on the
+/// robot, since its pose comes from the frontend or a test build.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GameObject {
+    /// Identifies the object in [`SimulatorEvent::GameObjectUpdated`].
+    pub name: String,
+    /// What the object is, e.g. `ring` or `goal`, for frontends to draw it.
+    pub kind: String,
+    /// Inches from the field's origin.
+    pub x: f64,
+    /// Inches from the field's origin.
+    pub y: f64,
+    /// The object's collision radius in inches.
+    pub radius: f64,
+    /// Whether the object stays where it is when pushed, like a goal fixed to the field.
+    #[serde(default)]
+    pub fixed: bool,
+    /// The [`Mechanism`] holding the object, if one is. Held objects move with the robot and
+    /// aren't pushed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub held_by: Option<String>,
+}
+
+// floats are compared bit for bit so that events can be `Eq`
+impl PartialEq for GameObject {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.kind == other.kind
+            && self.x.to_bits() == other.x.to_bits()
+            && self.y.to_bits() == other.y.to_bits()
+            && self.radius.to_bits() == other.radius.to_bits()
+            && self.fixed == other.fixed
+            && self.held_by == other.held_by
+    }
+}
+
+impl Eq for GameObject {}
+
+/// A mechanism on the robot driven by a motor, modeled simply enough that the robot can pick up
+/// [`GameObject`]s without a 3D physics model. See [`SimulatorMessage::AddMechanism`].
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Mechanism {
+    /// Identifies the mechanism in [`SimulatorEvent::MechanismUpdated`].
+    pub name: String,
+    /// The smart port (1-21) of the motor driving the mechanism. Like in a [`DrivetrainConfig`],
+    /// a negative port means the motor is reversed.
+    pub port: i8,
+    pub kind: MechanismKind,
+}
+
+/// What a [`Mechanism`] does when its motor runs. Motors run a mechanism once they're driven at
+/// a quarter of their maximum voltage.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub enum MechanismKind {
+    /// Picks up game objects in front of the robot that are within `reach` inches of its edge
+    /// while the motor runs forward, holding up to `capacity` at once. Running the motor in
+    /// reverse lets them out in front of the robot.
+    Intake { reach: f64, capacity: u32 },
+    /// Raises while the motor runs forward and lowers while it runs in reverse, at `speed`
+    /// inches per second at full voltage, between 0 and `max_height` inches.
+    Lift { max_height: f64, speed: f64 },
+}
+
+// floats are compared bit for bit so that messages can be `Eq`
+impl PartialEq for MechanismKind {
+    fn eq(&self, other: &Self) -> bool {
+        match (*self, *other) {
+            (
+                Self::Intake { reach, capacity },
+                Self::Intake {
+                    reach: other_reach,
+                    capacity: other_capacity,
+                },
+            ) => reach.to_bits() == other_reach.to_bits() && capacity == other_capacity,
+            (
+                Self::Lift { max_height, speed },
+                Self::Lift {
+                    max_height: other_max_height,
+                    speed: other_speed,
+                },
+            ) => {
+                max_height.to_bits() == other_max_height.to_bits()
+                    && speed.to_bits() == other_speed.to_bits()
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Eq for MechanismKind {}
+
+/// What a [`Mechanism`] is doing, sent in a [`SimulatorEvent::MechanismUpdated`].
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MechanismState {
+    pub name: String,
+    /// The names of the game objects an intake is holding, in the order it picked them up.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub held: Vec<String>,
+    /// How high a lift is in inches, or 0 for an intake.
+    #[serde(default)]
+    pub height: f64,
+}
+
+// floats are compared bit for bit so that events can be `Eq`
+impl PartialEq for MechanismState {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.held == other.held
+            && self.height.to_bits() == other.height.to_bits()
+    }
+}
+
+impl Eq for MechanismState {}
+
+/// When a [`ScoringZone`] scores its points.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScoringRule {
+    /// The first time the robot enters the zone, and never again.
+    #[default]
+    Enter,
+    /// Whenever the robot is in the zone. The points are taken away again when it leaves.
+    Inside,
+}
+
+/// A program loaded into one of the simulated brain's program slots.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ProgramSlot {
+    /// The slot number, from 1 to 8.
+    pub slot: u8,
+    /// The program's name from its [`ProgramInfo`], or its file name if it doesn't give one.
+    pub name: String,
+    /// The path of the robot code file.
+    pub file: String,
+}
+
+/// What an autonomous selector built on LLEMU seems to offer, worked out from the LCD's lines
+/// and the button callbacks robot code has registered. By convention, the left and right
+/// buttons cycle through the choices and the center button confirms the one shown.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct LcdSelector {
+    /// The text of the line showing the current choice. This is the line that changed the
+    /// last time the left or right button was pressed, so it's unknown until one has been.
+    pub choice: Option<String>,
+    /// Every choice shown on that line so far, in the order they were first seen.
+    pub choices: Vec<String>,
+    /// The buttons that have a callback registered, from left to right.
+    pub buttons: [Option<LcdSelectorButton>; 3],
+}
+
+/// An LCD button that has a callback registered.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct LcdSelectorButton {
+    /// What the button conventionally does in a selector, based on where it is.
+    pub role: LcdSelectorRole,
+    /// Name of the task that registered the callback.
+    pub task_name: String,
+}
+
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum LcdSelectorRole {
+    /// The left button, which shows the previous choice.
+    Previous,
+    /// The center button, which confirms the choice shown.
+    Select,
+    /// The right button, which shows the next choice.
+    Next,
+}
+
+impl LcdSelectorRole {
+    /// The role of each button, from left to right.
+    pub const BUTTONS: [Self; 3] = [Self::Previous, Self::Select, Self::Next];
+}
+
+/// Several events sent in one frame, oldest first, for transports where sending each event on
+/// its own is expensive. Batches serialize as a JSON array of events, so they can be told apart
+/// from single events, which are objects or strings.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+#[serde(transparent)]
+pub struct SimulatorEventBatch {
+    pub events: Vec<SimulatorEvent>,
+}
+
+/// The first line a frontend sends after connecting to a `pros-simulator-server` that was
+/// started with `--token`, e.g. `{"token": "hunter2"}`. The server disconnects frontends that
+/// send the wrong token, or don't send one in time, and waits for another to connect.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Handshake {
+    pub token: String,
 }
 
 /// A message sent to the simulator to control the robot code environment.
 /// The `pros-simulator` API accepts these over an async stream, and API consumers can use
 /// them to simulate changes in robot hardware (like controller input and LCD touch events).
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum SimulatorMessage {
     /// Master and Partner controllers have updated (in that order). None = disconnected.
     ControllerUpdate(Option<ControllerState>, Option<ControllerState>),
@@ -83,4 +1706,377 @@ pub enum SimulatorMessage {
     LcdButtonsUpdate([bool; 3]), // {"LcdButtonsUpdate": [true, false, false]}
     /// The robot has switched competition modes (opcontrol or autonomous or disabled).
     PhaseChange(CompetitionPhase),
+    /// Stop the simulation, as if the robot had been turned off.
+    Stop,
+    /// Change how often periodic events are sent, e.g. to save bandwidth on a slow transport.
+    SetRates(EventRates),
+    /// Make the next call to a PROS API fail with the given `errno`, to test the robot code's
+    /// error handling. Only APIs that report errors through `errno` can be made to fail.
+    FailNextCall { api: String, errno: i32 },
+    /// Read `len` bytes of robot code memory, e.g. to inspect a variable while the robot code is
+    /// running. The simulator replies with a [`SimulatorEvent::MemoryValue`].
+    ReadMemory { location: MemoryLocation, len: u32 },
+    /// Read a variable `rate` times per second, sending each value in a
+    /// [`SimulatorEvent::WatchValue`]. Watching a location again changes its type and rate, and a
+    /// rate of 0 stops watching it.
+    Watch {
+        location: MemoryLocation,
+        #[serde(rename = "type")]
+        value_type: ValueType,
+        rate: u32,
+    },
+    /// Pause robot code when it calls `api`, if the call meets the condition, and send a
+    /// [`SimulatorEvent::Breakpoint`]. The simulator keeps handling messages while robot code is
+    /// paused. Breakpoints are ignored while robot code has the scheduler suspended.
+    BreakOnCall {
+        api: String,
+        #[serde(default)]
+        condition: Option<CallCondition>,
+    },
+    /// Remove every breakpoint added with [`SimulatorMessage::BreakOnCall`].
+    ClearBreakpoints,
+    /// Let robot code paused at a breakpoint run again.
+    Resume,
+    /// Tap the LCD's next button (or previous button, if there's no next button) until the
+    /// autonomous selector shows the given choice, usually one of its
+    /// [`choices`](LcdSelector::choices). A warning is sent if the choices cycle back around
+    /// without showing it.
+    LcdSelectorChoose(String),
+    /// Stop the program that's running, if any, and run the one in the given
+    /// [slot](ProgramSlot), like choosing it on the brain's program selection screen. Only
+    /// `pros-simulator-server` has more than one slot.
+    SelectSlot(u8),
+    /// Teleport the robot to the given pose, like a motion capture system would report, e.g.
+    /// to set up a field situation before running autonomous. The pose overrides wherever the
+    /// frontend's physics model had the robot, and is echoed back in a
+    /// [`SimulatorEvent::PoseSet`] so every frontend and recording sees it.
+    SetPose(Pose),
+    /// Shape a controller's joysticks before robot code reads them with
+    /// `controller_get_analog`, until it's changed again. The default [`InputShaping`] turns
+    /// shaping off. [`Telemetry`] still reports the unshaped values the frontend sent.
+    SetInputShaping {
+        controller: ControllerId,
+        shaping: InputShaping,
+    },
+    /// Ask for a [`SimulatorEvent::TaskList`] of every task that exists right now.
+    ListTasks,
+    /// Part of a program to load into a slot, for frontends on a different machine than the
+    /// simulator. Once every chunk has arrived, the program runs like it does after `pros upload`.
+    /// Only `pros-simulator-server` started with `--upload-dir` accepts uploads.
+    UploadProgram(ProgramChunk),
+    /// Score points when the robot reaches a part of the field, e.g. to evaluate an autonomous
+    /// routine automatically. The simulator sends a [`SimulatorEvent::ScoreChanged`] each time
+    /// the score changes.
+    AddScoringZone(ScoringZone),
+    /// Place a game object on the field for the robot to push around. The simulator sends a
+    /// [`SimulatorEvent::GameObjectUpdated`] once it's placed and each time it moves.
+    AddGameObject(GameObject),
+    /// Model a motor as driving an intake or lift. Mechanisms with the same name as one the robot
+    /// already has are ignored with a warning. The simulator sends a
+    /// [`SimulatorEvent::MechanismUpdated`] whenever it picks up or lets out a game object, or
+    /// moves.
+    AddMechanism(Mechanism),
+    /// Press or release the brain's touchscreen at a pixel, in the same coordinates as
+    /// [`ScreenRegion`]. Robot code reads touches with `vexTouchDataGet`, like LVGL's input
+    /// driver does.
+    ScreenTouch { x: i16, y: i16, pressed: bool },
+    /// Overwrite robot code memory with `bytes`, e.g. to try different PID constants without
+    /// rebuilding the robot code. Only simulations of a test build accept writes; otherwise, or
+    /// if the memory couldn't be written, a [`Warning`](SimulatorEvent::Warning) is sent
+    /// instead.
+    WriteMemory {
+        location: MemoryLocation,
+        bytes: Vec<u8>,
+    },
+    /// Press a button in the header of the brain's screen. Stopping a program ends the
+    /// simulation; running it again needs `pros-simulator-server`, which restarts the program in
+    /// the same slot.
+    BrainButton(BrainButton),
+    /// Send robot code a message of its own, e.g. from an autonomous selector or a tuning UI.
+    /// Messages wait until robot code polls for their `tag` with `sim_poll_message`, which gets
+    /// `bytes` as they were sent. The counterpart of [`SimulatorEvent::Custom`].
+    Custom { tag: String, bytes: Vec<u8> },
+    /// Report the voltages of a group of motors together. Groups with the same name as one that
+    /// already exists are ignored with a warning. The simulator sends a
+    /// [`SimulatorEvent::MotorGroupUpdated`] whenever one of the group's motors changes voltage.
+    AddMotorGroup(MotorGroup),
+}
+
+/// A piece of a program being uploaded with [`SimulatorMessage::UploadProgram`]. Programs are
+/// sent in order, in chunks made by [`ProgramChunk::split`], so that no message is too big for
+/// the transport. A chunk at offset 0 starts a new upload.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ProgramChunk {
+    /// The slot to load the program into, from 1 to 8.
+    pub slot: u8,
+    /// The program's name, like the file name it was built as.
+    pub name: String,
+    /// Where this chunk starts in the program, in bytes.
+    pub offset: u64,
+    /// The size of the whole program, in bytes.
+    pub len: u64,
+    /// This chunk's bytes, base64 encoded.
+    pub data: String,
+}
+
+impl ProgramChunk {
+    /// Splits a program into chunks of up to `chunk_size` bytes.
+    pub fn split(slot: u8, name: &str, program: &[u8], chunk_size: usize) -> Vec<Self> {
+        let chunk = |offset: usize, bytes: &[u8]| Self {
+            slot,
+            name: name.to_string(),
+            offset: offset as u64,
+            len: program.len() as u64,
+            data: encode_base64(bytes),
+        };
+        if program.is_empty() {
+            return vec![chunk(0, &[])];
+        }
+        program
+            .chunks(chunk_size.max(1))
+            .enumerate()
+            .map(|(i, bytes)| chunk(i * chunk_size.max(1), bytes))
+            .collect()
+    }
+
+    /// This chunk's bytes, or `None` if they aren't valid base64.
+    pub fn bytes(&self) -> Option<Vec<u8>> {
+        decode_base64(&self.data)
+    }
+}
+
+/// A rectangle of the brain's screen, sent in [`SimulatorEvent::ScreenUpdated`]. Coordinates
+/// are in pixels from the top left corner of the screen, which is [`SCREEN_WIDTH`] by
+/// [`SCREEN_HEIGHT`].
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ScreenRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// The region's pixels as 3 bytes of RGB each, row by row from the top, base64 encoded.
+    pub pixels: String,
+}
+
+impl ScreenRegion {
+    /// A region holding the given RGB pixels, which should be `width * height * 3` bytes.
+    pub fn new(x: u32, y: u32, width: u32, height: u32, rgb: &[u8]) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+            pixels: encode_base64(rgb),
+        }
+    }
+
+    /// The region's RGB pixels, or `None` if they aren't valid base64.
+    pub fn rgb(&self) -> Option<Vec<u8>> {
+        decode_base64(&self.pixels)
+    }
+}
+
+/// How a test exported by the robot code went, sent in a [`SimulatorEvent::TestFinished`].
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct TestResult {
+    /// The name of the exported function.
+    pub name: String,
+    /// Whether the test finished without crashing or failing any `sim_assert`s.
+    pub passed: bool,
+    /// How many `sim_assert`s failed while the test ran.
+    pub failed_assertions: u32,
+    /// Why the test's task crashed, if it did.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// How long the test ran for, in milliseconds of simulated time.
+    pub millis: u32,
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn encode_base64(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for group in bytes.chunks(3) {
+        let n = group
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &byte)| n | (byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= group.len() {
+                text.push(BASE64_ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim_end_matches('=').as_bytes();
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    for group in text.chunks(4) {
+        if group.len() == 1 {
+            return None;
+        }
+        let mut n = 0u32;
+        for (i, &c) in group.iter().enumerate() {
+            let digit = BASE64_ALPHABET.iter().position(|&a| a == c)? as u32;
+            n |= digit << (18 - 6 * i);
+        }
+        for i in 0..group.len() - 1 {
+            bytes.push((n >> (16 - 8 * i)) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+/// A condition on the arguments of an API call, e.g. that `motor_move` is called with port 5.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct CallCondition {
+    /// Which argument to check, counting from 0.
+    pub arg: u32,
+    pub equals: i64,
+}
+
+impl CallCondition {
+    /// Whether a call with the given arguments meets the condition.
+    pub fn matches(&self, args: &[i64]) -> bool {
+        args.get(self.arg as usize) == Some(&self.equals)
+    }
+}
+
+/// A place in robot code memory.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum MemoryLocation {
+    Address(u32),
+    /// A global exported by the robot code that holds an address, which is how the linker
+    /// exports data symbols (e.g. `-C link-arg=--export=ODOMETRY_STATE`).
+    Symbol(String),
+}
+
+/// How to interpret the bytes of a watched variable. Values are little endian, like in robot
+/// code memory.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ValueType {
+    #[serde(rename = "bool")]
+    Bool,
+    #[serde(rename = "i8")]
+    I8,
+    #[serde(rename = "u8")]
+    U8,
+    #[serde(rename = "i16")]
+    I16,
+    #[serde(rename = "u16")]
+    U16,
+    #[serde(rename = "i32")]
+    I32,
+    #[serde(rename = "u32")]
+    U32,
+    #[serde(rename = "i64")]
+    I64,
+    #[serde(rename = "u64")]
+    U64,
+    #[serde(rename = "f32")]
+    F32,
+    #[serde(rename = "f64")]
+    F64,
+}
+
+impl ValueType {
+    /// How many bytes a value of this type takes up.
+    pub fn size(self) -> usize {
+        match self {
+            Self::Bool | Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::I64 | Self::U64 | Self::F64 => 8,
+        }
+    }
+
+    /// Interprets [`size`](Self::size) little endian bytes as a value of this type.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes` is shorter than the type's size.
+    pub fn decode(self, bytes: &[u8]) -> WatchValue {
+        let mut b = [0; 8];
+        b[..self.size()].copy_from_slice(&bytes[..self.size()]);
+        let [b0, b1, b2, b3, ..] = b;
+        match self {
+            Self::Bool => WatchValue::Bool(b0 != 0),
+            Self::I8 => WatchValue::Int(b0 as i8 as i64),
+            Self::I16 => WatchValue::Int(i16::from_le_bytes([b0, b1]) as i64),
+            Self::I32 => WatchValue::Int(i32::from_le_bytes([b0, b1, b2, b3]) as i64),
+            Self::I64 => WatchValue::Int(i64::from_le_bytes(b)),
+            Self::U8 | Self::U16 | Self::U32 | Self::U64 => WatchValue::UInt(u64::from_le_bytes(b)),
+            Self::F32 => WatchValue::Float(f32::from_le_bytes([b0, b1, b2, b3]) as f64),
+            Self::F64 => WatchValue::Float(f64::from_le_bytes(b)),
+        }
+    }
+
+    /// Converts a number to [`size`](Self::size) little endian bytes of this type, e.g. for a
+    /// [`SimulatorMessage::WriteMemory`]. Integers are rounded to the nearest whole number and
+    /// clamped to the type's range, and any number but 0 is `true`.
+    pub fn encode(self, value: f64) -> Vec<u8> {
+        let value = match self {
+            Self::Bool | Self::F32 | Self::F64 => value,
+            _ => value.round(),
+        };
+        match self {
+            Self::Bool => vec![(value != 0.0) as u8],
+            Self::I8 => (value as i8).to_le_bytes().to_vec(),
+            Self::U8 => (value as u8).to_le_bytes().to_vec(),
+            Self::I16 => (value as i16).to_le_bytes().to_vec(),
+            Self::U16 => (value as u16).to_le_bytes().to_vec(),
+            Self::I32 => (value as i32).to_le_bytes().to_vec(),
+            Self::U32 => (value as u32).to_le_bytes().to_vec(),
+            Self::I64 => (value as i64).to_le_bytes().to_vec(),
+            Self::U64 => (value as u64).to_le_bytes().to_vec(),
+            Self::F32 => (value as f32).to_le_bytes().to_vec(),
+            Self::F64 => value.to_le_bytes().to_vec(),
+        }
+    }
+}
+
+/// The value of a watched variable, which is a plain JSON boolean or number.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(untagged)]
+pub enum WatchValue {
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+}
+
+// floats are compared bit for bit so that events can be `Eq`
+impl PartialEq for WatchValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Bool(a), Self::Bool(b)) => a == b,
+            (Self::Int(a), Self::Int(b)) => a == b,
+            (Self::UInt(a), Self::UInt(b)) => a == b,
+            (Self::Float(a), Self::Float(b)) => a.to_bits() == b.to_bits(),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for WatchValue {}
+
+impl Display for MemoryLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Address(address) => write!(f, "{address:#x}"),
+            Self::Symbol(symbol) => write!(f, "`{symbol}`"),
+        }
+    }
 }
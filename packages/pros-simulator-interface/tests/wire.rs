@@ -0,0 +1,593 @@
+//! Checks that the JSON representation of events and messages stays compatible with frontends
+//! built against older versions of this crate.
+
+use pros_simulator_interface::{
+    BrainButton, BrainHeader, CompetitionPhase, ConsoleOutput, ControllerId, DisplayGeometry,
+    DriveType, DrivetrainConfig, EventRates, GameObject, Handshake, LcdLine, LcdLines, LogLevel,
+    Mechanism, MechanismKind, MechanismState, MemoryLocation, MotorGroup, MotorState, ProgramAbi,
+    ProgramChunk, ProgramInfo, SchedulerInvariant, ScoringRule, ScoringZone, ScreenRegion,
+    SimulatorEvent, SimulatorEventBatch, SimulatorMessage, Telemetry, TestResult, ValueType,
+    WatchValue, ZoneShape,
+};
+use serde_json::{from_str, json, to_value};
+
+#[test]
+fn event_names() {
+    assert_eq!(
+        to_value(SimulatorEvent::RobotCodeStarting).unwrap(),
+        json!("RobotCodeStarting")
+    );
+    assert_eq!(
+        to_value(SimulatorEvent::ConsoleMessage("hi\n".into())).unwrap(),
+        json!({ "ConsoleMessage": "hi\n" })
+    );
+    assert_eq!(
+        to_value(SimulatorEvent::MotorUpdated {
+            port: 1,
+            millivolts: 12000,
+            millis: 250,
+            target_velocity: 200,
+            actual_velocity: 150,
+            position: 90,
+            current: 625,
+        })
+        .unwrap(),
+        json!({ "MotorUpdated": {
+            "port": 1,
+            "millivolts": 12000,
+            "millis": 250,
+            "target_velocity": 200,
+            "actual_velocity": 150,
+            "position": 90,
+            "current": 625,
+        } })
+    );
+    assert_eq!(
+        to_value(SimulatorEvent::SessionStarted { session: 3 }).unwrap(),
+        json!({ "SessionStarted": { "session": 3 } })
+    );
+    assert_eq!(
+        to_value(SimulatorEvent::Log {
+            level: LogLevel::Warn,
+            message: "low battery".into(),
+            task_id: 2,
+            task_name: "opcontrol".into(),
+        })
+        .unwrap(),
+        json!({ "Log": {
+            "level": "Warn",
+            "message": "low battery",
+            "task_id": 2,
+            "task_name": "opcontrol",
+        } })
+    );
+    assert_eq!(
+        to_value(SimulatorEvent::ScoreChanged {
+            zone: "park".into(),
+            points: -5,
+            score: 10,
+            millis: 15000,
+        })
+        .unwrap(),
+        json!({ "ScoreChanged": { "zone": "park", "points": -5, "score": 10, "millis": 15000 } })
+    );
+}
+
+#[test]
+fn batches_are_arrays() {
+    let batch = SimulatorEventBatch {
+        events: vec![
+            SimulatorEvent::RobotCodeStarting,
+            SimulatorEvent::ConsoleMessage("hi\n".into()),
+        ],
+    };
+    let value = to_value(&batch).unwrap();
+    assert_eq!(
+        value,
+        json!(["RobotCodeStarting", { "ConsoleMessage": "hi\n" }])
+    );
+    assert_eq!(
+        from_str::<SimulatorEventBatch>(&value.to_string()).unwrap(),
+        batch
+    );
+}
+
+#[test]
+fn handshakes() {
+    assert_eq!(
+        from_str::<Handshake>(r#"{"token": "hunter2"}"#).unwrap(),
+        Handshake {
+            token: "hunter2".into()
+        }
+    );
+}
+
+#[test]
+fn program_info_display() {
+    let mut info = ProgramInfo::new(ProgramAbi::Vexide);
+    info.display = Some(DisplayGeometry::default());
+    let event = SimulatorEvent::ProgramInfo(info);
+    let value = to_value(&event).unwrap();
+    assert_eq!(
+        value,
+        json!({
+            "ProgramInfo": {
+                "abi": "Vexide",
+                "display": {
+                    "lcd_width": 40,
+                    "lcd_height": 8,
+                    "screen_width": 480,
+                    "screen_height": 272,
+                },
+            }
+        })
+    );
+    assert_eq!(
+        from_str::<SimulatorEvent>(&value.to_string()).unwrap(),
+        event
+    );
+}
+
+#[test]
+fn program_info_without_metadata() {
+    let event = SimulatorEvent::ProgramInfo(ProgramInfo::new(ProgramAbi::ProsRs));
+    let value = to_value(&event).unwrap();
+    assert_eq!(value, json!({ "ProgramInfo": { "abi": "ProsRs" } }));
+    assert_eq!(
+        from_str::<SimulatorEvent>(&value.to_string()).unwrap(),
+        event
+    );
+}
+
+#[test]
+fn lcd_lines_without_colors_are_strings() {
+    let mut lines = LcdLines::default();
+    lines[0] = LcdLine::from("Hello");
+    let value = to_value(SimulatorEvent::LcdUpdated(lines)).unwrap();
+    assert_eq!(value["LcdUpdated"][0], json!("Hello"));
+}
+
+#[test]
+fn messages_from_older_frontends() {
+    let message = from_str::<SimulatorMessage>(
+        r#"{"PhaseChange":{"autonomous":true,"enabled":true,"is_competition":false}}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        message,
+        SimulatorMessage::PhaseChange(CompetitionPhase {
+            autonomous: true,
+            enabled: true,
+            is_competition: false,
+        })
+    );
+
+    // before motor update and channel stats rates were added
+    let message = from_str::<SimulatorMessage>(r#"{"SetRates":{"telemetry":10}}"#).unwrap();
+    assert_eq!(
+        message,
+        SimulatorMessage::SetRates(EventRates {
+            telemetry: Some(10),
+            ..EventRates::default()
+        })
+    );
+
+    // before controllers could have a competition switch
+    let message = from_str::<SimulatorMessage>(
+        r#"{"ControllerUpdate":[{"digital":{"l1":false,"l2":false,"r1":false,"r2":false,"up":false,"down":false,"left":false,"right":false,"x":false,"b":false,"y":false,"a":true},"analog":{"left_x":0,"left_y":0,"right_x":0,"right_y":0}},null]}"#,
+    )
+    .unwrap();
+    let SimulatorMessage::ControllerUpdate(Some(master), None) = message else {
+        panic!("{message:?}");
+    };
+    assert!(master.digital.a);
+    assert_eq!(master.competition_switch, None);
+    assert!(to_value(&master)
+        .unwrap()
+        .get("competition_switch")
+        .is_none());
+}
+
+#[test]
+fn console_output() {
+    // output without a task is a plain string, like before it was attributed to tasks
+    let output = ConsoleOutput {
+        text: "hi\n".into(),
+        task_id: Some(2),
+        task_name: Some("User Initialization (PROS)".into()),
+    };
+    let value = to_value(SimulatorEvent::ConsoleMessage(output.clone())).unwrap();
+    assert_eq!(
+        value,
+        json!({ "ConsoleMessage": {
+            "text": "hi\n", "task_id": 2, "task_name": "User Initialization (PROS)"
+        } })
+    );
+    assert_eq!(
+        from_str::<SimulatorEvent>(&value.to_string()).unwrap(),
+        SimulatorEvent::ConsoleMessage(output)
+    );
+}
+
+#[test]
+fn events_from_older_simulators() {
+    // before motor updates had timestamps or came from a motor model
+    let event =
+        from_str::<SimulatorEvent>(r#"{"MotorUpdated":{"port":1,"millivolts":12000}}"#).unwrap();
+    assert_eq!(
+        event,
+        SimulatorEvent::MotorUpdated {
+            port: 1,
+            millivolts: 12000,
+            millis: 0,
+            target_velocity: 0,
+            actual_velocity: 0,
+            position: 0,
+            current: 0,
+        }
+    );
+
+    // before console output was attributed to tasks
+    let event = from_str::<SimulatorEvent>(r#"{"ConsoleMessage":"hi\n"}"#).unwrap();
+    assert_eq!(event, SimulatorEvent::ConsoleMessage("hi\n".into()));
+
+    // before LCD lines had colors
+    let event =
+        from_str::<SimulatorEvent>(r#"{"LcdUpdated":["Hello","","","","","","",""]}"#).unwrap();
+    let mut lines = LcdLines::default();
+    lines[0] = LcdLine::from("Hello");
+    assert_eq!(event, SimulatorEvent::LcdUpdated(lines));
+
+    // before telemetry included motors
+    let event = from_str::<SimulatorEvent>(
+        r#"{"Telemetry":{"millis":20,"competition_phase":{"autonomous":false,"enabled":true,"is_competition":false},"master":null,"partner":null,"task_count":2}}"#,
+    )
+    .unwrap();
+    let SimulatorEvent::Telemetry(telemetry) = event else {
+        panic!("{event:?}");
+    };
+    assert!(telemetry.motors.is_empty());
+}
+
+#[test]
+fn motor_telemetry() {
+    let telemetry = Telemetry {
+        millis: 20,
+        competition_phase: CompetitionPhase::default(),
+        master: None,
+        partner: None,
+        task_count: 2,
+        pose: None,
+        motors: vec![MotorState {
+            port: 1,
+            millivolts: 12000,
+            velocity: 173,
+            position: 58,
+            current: 338,
+        }],
+    };
+    assert_eq!(
+        to_value(&telemetry).unwrap()["motors"],
+        json!([{ "port": 1, "millivolts": 12000, "velocity": 173, "position": 58, "current": 338 }])
+    );
+}
+
+#[test]
+fn watches() {
+    let message = from_str::<SimulatorMessage>(
+        r#"{"Watch":{"location":{"Symbol":"ODOMETRY_STATE"},"type":"f32","rate":20}}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        message,
+        SimulatorMessage::Watch {
+            location: MemoryLocation::Symbol("ODOMETRY_STATE".into()),
+            value_type: ValueType::F32,
+            rate: 20,
+        }
+    );
+
+    let value = ValueType::F32.decode(&1.5f32.to_le_bytes());
+    assert_eq!(value, WatchValue::Float(1.5));
+    assert_eq!(ValueType::I16.decode(&[0xff, 0xff]), WatchValue::Int(-1));
+    let event = SimulatorEvent::WatchValue {
+        location: MemoryLocation::Address(2048),
+        millis: 10,
+        value,
+    };
+    assert_eq!(
+        to_value(event).unwrap(),
+        json!({ "WatchValue": { "location": { "Address": 2048 }, "millis": 10, "value": 1.5 } })
+    );
+}
+
+#[test]
+fn memory_writes() {
+    let message = from_str::<SimulatorMessage>(
+        r#"{"WriteMemory":{"location":{"Symbol":"DRIVE_KP"},"bytes":[0,0,32,65]}}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        message,
+        SimulatorMessage::WriteMemory {
+            location: MemoryLocation::Symbol("DRIVE_KP".into()),
+            bytes: 10.0f32.to_le_bytes().to_vec(),
+        }
+    );
+
+    assert_eq!(ValueType::F32.encode(10.0), [0, 0, 32, 65]);
+    assert_eq!(ValueType::I16.encode(-1.6), [0xfe, 0xff]);
+    assert_eq!(ValueType::U8.encode(300.0), [255]);
+    assert_eq!(ValueType::Bool.encode(0.25), [1]);
+    for value_type in [
+        ValueType::I8,
+        ValueType::U32,
+        ValueType::I64,
+        ValueType::F64,
+    ] {
+        let bytes = value_type.encode(3.0);
+        assert_eq!(bytes.len(), value_type.size());
+        assert!(matches!(
+            value_type.decode(&bytes),
+            WatchValue::Int(3) | WatchValue::UInt(3) | WatchValue::Float(3.0)
+        ));
+    }
+}
+
+#[test]
+fn scoring_zones() {
+    // the rule defaults to scoring once
+    let message = from_str::<SimulatorMessage>(
+        r#"{"AddScoringZone":{"name":"win point","shape":{"Circle":{"x":24,"y":0,"radius":6}},"points":10}}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        message,
+        SimulatorMessage::AddScoringZone(ScoringZone {
+            name: "win point".into(),
+            shape: ZoneShape::Circle {
+                x: 24.0,
+                y: 0.0,
+                radius: 6.0,
+            },
+            points: 10,
+            rule: ScoringRule::Enter,
+            autonomous_only: false,
+        })
+    );
+}
+
+#[test]
+fn game_objects() {
+    // objects can be pushed unless they say otherwise
+    let message = from_str::<SimulatorMessage>(
+        r#"{"AddGameObject":{"name":"ring 1","kind":"ring","x":-24,"y":48,"radius":3.5}}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        message,
+        SimulatorMessage::AddGameObject(GameObject {
+            name: "ring 1".into(),
+            kind: "ring".into(),
+            x: -24.0,
+            y: 48.0,
+            radius: 3.5,
+            fixed: false,
+            held_by: None,
+        })
+    );
+}
+
+#[test]
+fn mechanisms() {
+    let message = from_str::<SimulatorMessage>(
+        r#"{"AddMechanism":{"name":"intake","port":-3,"kind":{"Intake":{"reach":2,"capacity":1}}}}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        message,
+        SimulatorMessage::AddMechanism(Mechanism {
+            name: "intake".into(),
+            port: -3,
+            kind: MechanismKind::Intake {
+                reach: 2.0,
+                capacity: 1,
+            },
+        })
+    );
+
+    // intakes that aren't holding anything leave `held` out
+    let event = SimulatorEvent::MechanismUpdated(MechanismState {
+        name: "intake".into(),
+        held: Vec::new(),
+        height: 0.0,
+    });
+    assert_eq!(
+        to_value(event).unwrap(),
+        json!({ "MechanismUpdated": { "name": "intake", "height": 0.0 } })
+    );
+}
+
+#[test]
+fn screen() {
+    let message =
+        from_str::<SimulatorMessage>(r#"{"ScreenTouch":{"x":12,"y":-4,"pressed":true}}"#).unwrap();
+    assert_eq!(
+        message,
+        SimulatorMessage::ScreenTouch {
+            x: 12,
+            y: -4,
+            pressed: true,
+        }
+    );
+
+    let region = ScreenRegion::new(3, 4, 1, 1, &[0xff, 0x80, 0x00]);
+    assert_eq!(
+        to_value(SimulatorEvent::ScreenUpdated(region.clone())).unwrap(),
+        json!({ "ScreenUpdated": {
+            "x": 3, "y": 4, "width": 1, "height": 1, "pixels": "/4AA"
+        } })
+    );
+    assert_eq!(region.rgb().unwrap(), [0xff, 0x80, 0x00]);
+}
+
+#[test]
+fn test_results() {
+    // tests that didn't crash leave `error` out
+    let event = SimulatorEvent::TestFinished(TestResult {
+        name: "sim_test_odometry".into(),
+        passed: false,
+        failed_assertions: 2,
+        error: None,
+        millis: 40,
+    });
+    assert_eq!(
+        to_value(event).unwrap(),
+        json!({ "TestFinished": {
+            "name": "sim_test_odometry", "passed": false, "failed_assertions": 2, "millis": 40
+        } })
+    );
+}
+
+#[test]
+fn program_chunks() {
+    let chunks = ProgramChunk::split(2, "skills", b"\0asm\x01", 4);
+    assert_eq!(
+        to_value(SimulatorMessage::UploadProgram(chunks[0].clone())).unwrap(),
+        json!({ "UploadProgram": {
+            "slot": 2, "name": "skills", "offset": 0, "len": 5, "data": "AGFzbQ=="
+        } })
+    );
+    assert_eq!(chunks[1].offset, 4);
+    assert_eq!(chunks[1].data, "AQ==");
+
+    let program = (0..=255).collect::<Vec<u8>>();
+    for len in 0..8 {
+        let chunks = ProgramChunk::split(1, "robot", &program[..len], 3);
+        let bytes = chunks.iter().flat_map(|chunk| chunk.bytes().unwrap());
+        assert_eq!(bytes.collect::<Vec<_>>(), &program[..len]);
+    }
+    let chunks = ProgramChunk::split(1, "robot", &program, 100);
+    let bytes = chunks.iter().flat_map(|chunk| chunk.bytes().unwrap());
+    assert_eq!(bytes.collect::<Vec<_>>(), program);
+}
+
+#[test]
+fn brain_header() {
+    let event = SimulatorEvent::BrainHeader(BrainHeader {
+        running: true,
+        seconds: 12,
+        battery: 100,
+    });
+    assert_eq!(
+        to_value(event).unwrap(),
+        json!({ "BrainHeader": { "running": true, "seconds": 12, "battery": 100 } })
+    );
+
+    let message = from_str::<SimulatorMessage>(r#"{"BrainButton":"Stop"}"#).unwrap();
+    assert_eq!(message, SimulatorMessage::BrainButton(BrainButton::Stop));
+}
+
+#[test]
+fn custom_events() {
+    let event = SimulatorEvent::Custom {
+        tag: "odom".into(),
+        bytes: vec![1, 2, 255],
+    };
+    assert_eq!(
+        to_value(event).unwrap(),
+        json!({ "Custom": { "tag": "odom", "bytes": [1, 2, 255] } })
+    );
+}
+
+#[test]
+fn custom_messages() {
+    let message =
+        from_str::<SimulatorMessage>(r#"{"Custom":{"tag":"auton","bytes":[3]}}"#).unwrap();
+    assert_eq!(
+        message,
+        SimulatorMessage::Custom {
+            tag: "auton".into(),
+            bytes: vec![3],
+        }
+    );
+}
+
+#[test]
+fn scheduler_invariants() {
+    let event = SimulatorEvent::SchedulerInvariantViolated {
+        invariant: SchedulerInvariant::SuspendBalance,
+        message: "Task #3 holds the scheduler suspended".into(),
+    };
+    assert_eq!(
+        to_value(event).unwrap(),
+        json!({ "SchedulerInvariantViolated": {
+            "invariant": "SuspendBalance", "message": "Task #3 holds the scheduler suspended"
+        } })
+    );
+}
+
+#[test]
+fn controller_text() {
+    let event = SimulatorEvent::ControllerTextUpdated {
+        controller: ControllerId::Partner,
+        lines: vec!["Auton: left".into(), String::new(), String::new()],
+    };
+    assert_eq!(
+        to_value(event).unwrap(),
+        json!({ "ControllerTextUpdated": {
+            "controller": "Partner", "lines": ["Auton: left", "", ""]
+        } })
+    );
+}
+
+#[test]
+fn motor_groups() {
+    let message =
+        from_str::<SimulatorMessage>(r#"{"AddMotorGroup":{"name":"left drive","ports":[1,2,-3]}}"#)
+            .unwrap();
+    assert_eq!(
+        message,
+        SimulatorMessage::AddMotorGroup(MotorGroup {
+            name: "left drive".into(),
+            ports: vec![1, 2, -3],
+        })
+    );
+    let event = SimulatorEvent::MotorGroupUpdated {
+        name: "left drive".into(),
+        millivolts: 4000,
+        motors: vec![6000, 6000, 0],
+    };
+    assert_eq!(
+        to_value(event).unwrap(),
+        json!({ "MotorGroupUpdated": {
+            "name": "left drive", "millivolts": 4000, "motors": [6000, 6000, 0]
+        } })
+    );
+}
+
+#[test]
+fn drivetrain_configured() {
+    let event = SimulatorEvent::DrivetrainConfigured(DrivetrainConfig {
+        drive_type: DriveType::Tank,
+        wheel_diameter: 3.25,
+        track_width: 11.5,
+        gear_ratio: 0.6,
+        left_motors: vec![1, -2],
+        right_motors: vec![-3, 4],
+        strafe_motors: Vec::new(),
+    });
+    let value = json!({ "DrivetrainConfigured": {
+        "drive_type": "Tank",
+        "wheel_diameter": 3.25,
+        "track_width": 11.5,
+        "gear_ratio": 0.6,
+        "left_motors": [1, -2],
+        "right_motors": [-3, 4]
+    } });
+    assert_eq!(to_value(&event).unwrap(), value);
+    assert_eq!(
+        serde_json::from_value::<SimulatorEvent>(value).unwrap(),
+        event
+    );
+}
@@ -0,0 +1,68 @@
+//! Events written to the frontend in batches by `--batch-events`. Rather than a line per event, the
+//! events sent during each scheduler tick are written as one line holding a
+//! [`SimulatorEventBatch`], which saves a write and some JSON framing per event when robot code
+//! is busy.
+
+use std::{
+    sync::{Arc, Mutex, Weak},
+    thread,
+    time::Duration,
+};
+
+use pros_simulator_interface::{SimulatorEvent, SimulatorEventBatch};
+
+use crate::transport::EventWriter;
+
+/// How often batched events are written, which matches the length of a scheduler tick.
+const TICK: Duration = Duration::from_millis(1);
+
+pub struct EventBatcher {
+    pending: Arc<Mutex<Vec<SimulatorEvent>>>,
+    output: EventWriter,
+}
+
+impl EventBatcher {
+    /// Starts writing batches to `output` once per tick, until the batcher is dropped.
+    pub fn spawn(output: EventWriter) -> Self {
+        let pending = Arc::new(Mutex::new(Vec::new()));
+        thread::spawn({
+            let pending = Arc::downgrade(&pending);
+            let output = output.clone();
+            move || flush_every_tick(pending, output)
+        });
+        Self { pending, output }
+    }
+
+    /// Queues an event to be written with the rest of this tick's events.
+    pub fn push(&self, event: SimulatorEvent) {
+        self.pending.lock().unwrap().push(event);
+    }
+
+    /// Writes the events that haven't been written yet, e.g. once the simulation has stopped.
+    pub fn flush(&self) {
+        flush(&self.pending, &self.output);
+    }
+}
+
+fn flush_every_tick(pending: Weak<Mutex<Vec<SimulatorEvent>>>, output: EventWriter) {
+    loop {
+        thread::sleep(TICK);
+        let Some(pending) = pending.upgrade() else {
+            break;
+        };
+        flush(&pending, &output);
+    }
+}
+
+/// Writes the pending events as one batch, if there are any. The lock is held while writing so
+/// batches can't be written out of order.
+fn flush(pending: &Mutex<Vec<SimulatorEvent>>, output: &EventWriter) {
+    let mut pending = pending.lock().unwrap();
+    if pending.is_empty() {
+        return;
+    }
+    let batch = SimulatorEventBatch {
+        events: std::mem::take(&mut *pending),
+    };
+    output.write(&batch);
+}
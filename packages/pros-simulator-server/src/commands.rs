@@ -0,0 +1,261 @@
+//! Text commands for driving a simulation by hand, accepted on stdin by `run --commands`.
+
+use pros_simulator_interface::{
+    BrainButton, CallCondition, CompetitionPhase, CompetitionSwitch, ControllerId, ControllerState,
+    InputShaping, Pose, SimulatorMessage,
+};
+
+/// The commands that can be typed, shown by the `help` command.
+pub const HELP: &str = "\
+Commands:
+  press BUTTON          hold a button on the master controller (a, b, x, y, up, down, left,
+                        right, l1, l2, r1 or r2)
+  release BUTTON        let go of a button
+  stick AXIS VALUE      move a joystick axis (left-x, left-y, right-x or right-y) to -127..127
+  shape DEADZONE EXPO   shape the master controller's joysticks with a deadzone (0-126) and a
+                        cubic curve (0-100%) before robot code reads them
+  disconnect            disconnect the master controller until the next press, release or stick
+  phase PHASE           change the competition phase (disabled, auton or opcontrol)
+  switch PHASE          set the competition switch on the master controller to a phase, or
+                        unplug it with `off`
+  fail API ERRNO        make the next call to a PROS API fail with the given errno
+  break API [ARG VALUE] pause when robot code calls a PROS API, optionally only when the
+                        argument numbered ARG (from 0) is VALUE
+  resume                continue after a breakpoint
+  choose CHOICE         tap the LCD's buttons until its autonomous selector shows CHOICE
+                        (needs `--lcd-selector`)
+  pose X Y HEADING      teleport the robot to X and Y inches, facing HEADING degrees
+  slot SLOT             stop the program and run the one in another slot (needs `--slot`)
+  rerun                 run the program again from the start, like the brain's run button
+                        (needs `--slot`)
+  tasks                 list every task, including the simulator's own
+  stop                  stop the simulation
+  help                  show this message";
+
+/// The reply to `port`, since devices can't be plugged in after the simulation starts.
+const PLUG_IN_AT_START: &str =
+    "Devices can't be plugged in while the simulation is running. Use `--device PORT=TYPE` instead";
+
+/// Translates text commands into messages. The master controller's state is remembered, so
+/// each command only changes the buttons or axes it mentions.
+#[derive(Default)]
+pub struct Commands {
+    controller: ControllerState,
+}
+
+impl Commands {
+    /// Parses a command, returning the messages to send or a description of what's wrong.
+    pub fn parse(&mut self, line: &str) -> Result<Vec<SimulatorMessage>, String> {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        let message = match words[..] {
+            ["press", button] => {
+                *self.button(button)? = true;
+                self.controller_update()
+            }
+            ["release", button] => {
+                *self.button(button)? = false;
+                self.controller_update()
+            }
+            ["stick", axis, value] => {
+                let value = value
+                    .parse::<i8>()
+                    .ok()
+                    .filter(|value| *value >= -127)
+                    .ok_or(format!("`{value}` isn't a joystick value (-127 to 127)"))?;
+                *self.axis(axis)? = value;
+                self.controller_update()
+            }
+            ["shape", deadzone, expo] => SimulatorMessage::SetInputShaping {
+                controller: ControllerId::Master,
+                shaping: InputShaping {
+                    deadzone: deadzone
+                        .parse()
+                        .map_err(|_| format!("`{deadzone}` isn't a deadzone (0 to 126)"))?,
+                    expo: expo
+                        .parse()
+                        .map_err(|_| format!("`{expo}` isn't an expo percentage (0 to 100)"))?,
+                },
+            },
+            ["disconnect"] => SimulatorMessage::ControllerUpdate(None, None),
+            ["phase", phase] => SimulatorMessage::PhaseChange(parse_phase(phase)?),
+            ["switch", "off"] => {
+                self.controller.competition_switch = None;
+                self.controller_update()
+            }
+            ["switch", phase] => {
+                let phase = parse_phase(phase)?;
+                self.controller.competition_switch = Some(CompetitionSwitch {
+                    enabled: phase.enabled,
+                    autonomous: phase.autonomous,
+                });
+                self.controller_update()
+            }
+            ["fail", api, errno] => SimulatorMessage::FailNextCall {
+                api: api.to_string(),
+                errno: errno
+                    .parse()
+                    .map_err(|_| format!("`{errno}` isn't an errno value"))?,
+            },
+            ["break", api] => SimulatorMessage::BreakOnCall {
+                api: api.to_string(),
+                condition: None,
+            },
+            ["break", api, arg, value] => SimulatorMessage::BreakOnCall {
+                api: api.to_string(),
+                condition: Some(CallCondition {
+                    arg: arg
+                        .parse()
+                        .map_err(|_| format!("`{arg}` isn't an argument number"))?,
+                    equals: value
+                        .parse()
+                        .map_err(|_| format!("`{value}` isn't an integer"))?,
+                }),
+            },
+            ["resume"] => SimulatorMessage::Resume,
+            ["choose", _, ..] => {
+                let choice = line.trim().strip_prefix("choose").unwrap_or_default();
+                SimulatorMessage::LcdSelectorChoose(choice.trim().to_string())
+            }
+            ["pose", x, y, heading] => SimulatorMessage::SetPose(Pose {
+                x: parse_number(x)?,
+                y: parse_number(y)?,
+                heading: parse_number(heading)?,
+            }),
+            ["tasks"] => SimulatorMessage::ListTasks,
+            ["slot", slot] => SimulatorMessage::SelectSlot(
+                slot.parse()
+                    .map_err(|_| format!("`{slot}` isn't a program slot"))?,
+            ),
+            ["rerun"] => SimulatorMessage::BrainButton(BrainButton::Run),
+            ["stop"] => SimulatorMessage::Stop,
+            ["port", ..] => return Err(PLUG_IN_AT_START.to_string()),
+            [] => return Ok(vec![]),
+            _ => return Err(format!("Unknown command `{}`", line.trim())),
+        };
+        Ok(vec![message])
+    }
+
+    fn controller_update(&self) -> SimulatorMessage {
+        SimulatorMessage::ControllerUpdate(Some(self.controller.clone()), None)
+    }
+
+    fn button(&mut self, name: &str) -> Result<&mut bool, String> {
+        let digital = &mut self.controller.digital;
+        Ok(match name.to_ascii_lowercase().as_str() {
+            "a" => &mut digital.a,
+            "b" => &mut digital.b,
+            "x" => &mut digital.x,
+            "y" => &mut digital.y,
+            "up" => &mut digital.up,
+            "down" => &mut digital.down,
+            "left" => &mut digital.left,
+            "right" => &mut digital.right,
+            "l1" => &mut digital.l1,
+            "l2" => &mut digital.l2,
+            "r1" => &mut digital.r1,
+            "r2" => &mut digital.r2,
+            _ => return Err(format!("Unknown button `{name}`")),
+        })
+    }
+
+    fn axis(&mut self, name: &str) -> Result<&mut i8, String> {
+        let analog = &mut self.controller.analog;
+        Ok(match name.to_ascii_lowercase().replace('_', "-").as_str() {
+            "left-x" => &mut analog.left_x,
+            "left-y" => &mut analog.left_y,
+            "right-x" => &mut analog.right_x,
+            "right-y" => &mut analog.right_y,
+            _ => return Err(format!("Unknown joystick axis `{name}`")),
+        })
+    }
+}
+
+fn parse_number(text: &str) -> Result<f64, String> {
+    text.parse().map_err(|_| format!("`{text}` isn't a number"))
+}
+
+fn parse_phase(name: &str) -> Result<CompetitionPhase, String> {
+    let (autonomous, enabled) = match name {
+        "disabled" => (false, false),
+        "auton" | "autonomous" => (true, true),
+        "opcontrol" | "driver" => (false, true),
+        _ => return Err(format!("Unknown competition phase `{name}`")),
+    };
+    Ok(CompetitionPhase {
+        autonomous,
+        enabled,
+        is_competition: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn controller_state_is_remembered() {
+        let mut commands = Commands::default();
+        commands.parse("press A").unwrap();
+        let messages = commands.parse("stick left_y -127").unwrap();
+        let [SimulatorMessage::ControllerUpdate(Some(master), None)] = &messages[..] else {
+            panic!("{messages:?}");
+        };
+        assert!(master.digital.a);
+        assert_eq!(master.analog.left_y, -127);
+
+        let messages = commands.parse("release a").unwrap();
+        let [SimulatorMessage::ControllerUpdate(Some(master), None)] = &messages[..] else {
+            panic!("{messages:?}");
+        };
+        assert!(!master.digital.a);
+        assert_eq!(master.analog.left_y, -127);
+    }
+
+    #[test]
+    fn messages() {
+        let mut commands = Commands::default();
+        let mut parse = |line| commands.parse(line).unwrap();
+        assert_eq!(
+            parse("phase auton"),
+            [SimulatorMessage::PhaseChange(CompetitionPhase {
+                autonomous: true,
+                enabled: true,
+                is_competition: false,
+            })]
+        );
+        assert_eq!(
+            parse("fail motor_move 6"),
+            [SimulatorMessage::FailNextCall {
+                api: "motor_move".into(),
+                errno: 6,
+            }]
+        );
+        assert_eq!(
+            parse("break motor_move 0 3"),
+            [SimulatorMessage::BreakOnCall {
+                api: "motor_move".into(),
+                condition: Some(CallCondition { arg: 0, equals: 3 }),
+            }]
+        );
+        assert_eq!(
+            parse("choose Left Side"),
+            [SimulatorMessage::LcdSelectorChoose("Left Side".into())]
+        );
+        assert_eq!(parse("slot 2"), [SimulatorMessage::SelectSlot(2)]);
+        assert_eq!(parse("  "), []);
+    }
+
+    #[test]
+    fn invalid_commands() {
+        let mut commands = Commands::default();
+        let mut parse = |line| commands.parse(line).unwrap_err();
+        assert_eq!(parse("press z"), "Unknown button `z`");
+        assert_eq!(
+            parse("stick left-y -128"),
+            "`-128` isn't a joystick value (-127 to 127)"
+        );
+        assert_eq!(parse("phase lunch"), "Unknown competition phase `lunch`");
+        assert_eq!(parse("port 1 motor"), PLUG_IN_AT_START);
+        assert_eq!(parse("dance"), "Unknown command `dance`");
+    }
+}
@@ -0,0 +1,125 @@
+//! Recording the controller input sent to a simulation, and playing it back in a later one.
+//!
+//! Recordings are line delimited JSON, with one line for each time the controllers changed:
+//!
+//! ```json
+//! {"time_ms":1520,"master":{"digital":{...},"analog":{...}},"partner":null}
+//! ```
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::{Duration, Instant},
+};
+
+use pros_simulator_interface::{ControllerState, SimulatorMessage};
+use serde::{Deserialize, Serialize};
+
+/// The state of both controllers at a point in a recording.
+#[derive(Serialize, Deserialize)]
+struct ControllerInput {
+    /// Milliseconds since the recording started.
+    time_ms: u64,
+    master: Option<ControllerState>,
+    partner: Option<ControllerState>,
+}
+
+/// Forward messages to the simulator, saving the controller updates among them to a file.
+pub fn record(messages: Receiver<SimulatorMessage>, path: &Path) -> Receiver<SimulatorMessage> {
+    let mut output = BufWriter::new(File::create(path).unwrap());
+    let (tx, rx) = mpsc::channel();
+    let start = Instant::now();
+    thread::spawn(move || {
+        for message in messages {
+            if let SimulatorMessage::ControllerUpdate(master, partner) = &message {
+                let input = ControllerInput {
+                    time_ms: start.elapsed().as_millis() as u64,
+                    master: master.clone(),
+                    partner: partner.clone(),
+                };
+                serde_json::to_writer(&mut output, &input).unwrap();
+                writeln!(output).unwrap();
+                output.flush().unwrap();
+            }
+            if tx.send(message).is_err() {
+                break;
+            }
+        }
+    });
+    rx
+}
+
+/// Send the controller updates saved by [`record`] to the simulator at the same times they were
+/// recorded.
+pub fn play(path: &Path, messages: Sender<SimulatorMessage>) {
+    let inputs = BufReader::new(File::open(path).unwrap())
+        .lines()
+        .map(|line| line.unwrap())
+        .filter(|line| !line.trim().is_empty())
+        .map(
+            |line| match serde_json::from_str::<ControllerInput>(&line) {
+                Ok(input) => input,
+                Err(err) => {
+                    eprintln!("Error reading controller input: {err}");
+                    std::process::exit(1);
+                }
+            },
+        )
+        .collect::<Vec<_>>();
+
+    let start = Instant::now();
+    thread::spawn(move || {
+        for input in inputs {
+            let at = start + Duration::from_millis(input.time_ms);
+            thread::sleep(at.saturating_duration_since(Instant::now()));
+            let message = SimulatorMessage::ControllerUpdate(input.master, input.partner);
+            if messages.send(message).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_and_play() {
+        let path = std::env::temp_dir().join(format!(
+            "pros-simulator-server-input-{}.jsonl",
+            std::process::id()
+        ));
+        let mut pressed = ControllerState::default();
+        pressed.digital.a = true;
+        let updates = [
+            SimulatorMessage::ControllerUpdate(Some(pressed), None),
+            SimulatorMessage::ControllerUpdate(None, Some(ControllerState::default())),
+        ];
+
+        let (tx, messages) = mpsc::channel();
+        let forwarded = record(messages, &path);
+        tx.send(updates[0].clone()).unwrap();
+        tx.send(SimulatorMessage::Stop).unwrap();
+        tx.send(updates[1].clone()).unwrap();
+        drop(tx);
+        // every message reaches the simulator, but only controller updates are recorded
+        let forwarded = forwarded.iter().collect::<Vec<_>>();
+        assert_eq!(forwarded.len(), 3);
+        assert!(matches!(forwarded[1], SimulatorMessage::Stop));
+        let recorded = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(recorded.lines().count(), 2);
+
+        let (tx, played) = mpsc::channel();
+        play(&path, tx);
+        let played = played.iter().collect::<Vec<_>>();
+        assert_eq!(
+            serde_json::to_value(played).unwrap(),
+            serde_json::to_value(updates).unwrap()
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+}
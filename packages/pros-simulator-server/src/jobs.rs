@@ -0,0 +1,50 @@
+//! Running independent simulations side by side for `test --jobs`. Each simulation gets a thread
+//! and a runtime of its own, and the simulator gives each one its own engine, so they don't
+//! share anything but the CPU.
+
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+/// Runs `run` on each item, at most `jobs` at a time, returning the results in the same order as
+/// the items.
+pub fn run_parallel<T, R, F>(items: Vec<T>, jobs: usize, run: impl Fn(T) -> F + Sync) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+    F: Future<Output = R>,
+{
+    let count = items.len();
+    let items = Mutex::new(items.into_iter().map(Some).collect::<Vec<_>>());
+    let results = Mutex::new((0..count).map(|_| None).collect::<Vec<_>>());
+    let next = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, count.max(1)) {
+            scope.spawn(|| {
+                let runtime = tokio::runtime::Builder::new_current_thread()
+                    .build()
+                    .unwrap();
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    if index >= count {
+                        break;
+                    }
+                    let item = items.lock().unwrap()[index].take().unwrap();
+                    let result = runtime.block_on(run(item));
+                    results.lock().unwrap()[index] = Some(result);
+                }
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.expect("every item is run"))
+        .collect()
+}
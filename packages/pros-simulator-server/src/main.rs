@@ -1,57 +1,1341 @@
+mod batch;
+mod commands;
+mod input;
+mod jobs;
+mod match_log;
+mod replay;
+mod report;
+mod serial;
+mod serve;
+mod slots;
+mod transport;
+mod upload;
+mod waveform;
+mod websocket;
+
 use std::{
-    io::{stdin, stdout, BufReader},
-    path::PathBuf,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Write},
+    net::IpAddr,
+    path::{Path, PathBuf},
     process::exit,
-    sync::mpsc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
 };
 
-use clap::Parser;
+use batch::EventBatcher;
+use clap::{Parser, Subcommand};
 use jsonl::{read, write, ReadError};
-use pros_simulator_interface::SimulatorMessage;
+use match_log::MatchLog;
+#[cfg(feature = "render")]
+use pros_simulator::FrameOutput;
+use pros_simulator::{
+    MatchTiming, SimulatorConfig, SimulatorOptions, StartKind, Timeout, WarningKind,
+};
+use pros_simulator_interface::{
+    DeviceType, Handshake, ProsVersion, SimulatorEvent, SimulatorEventBatch, SimulatorMessage,
+};
+use report::{describe_failure, Check, Report};
+use schemars::{schema_for, JsonSchema};
+use serde::Deserialize;
+use serial::SerialSocket;
+use serve::Sessions;
+use slots::{forward, EventSink, Slots, NUM_SLOTS};
+use transport::{
+    spawn_reader, Access, Closing, Connection, Endpoint, EventWriter, Stdio, Transport,
+};
+use upload::Uploads;
+use waveform::Waveform;
 
 /// Simulate a VEX V5 robot using the PROS API interface.
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// Stream line delimited JSON events over stdio.
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Simulate robot code, streaming line delimited JSON events over stdio or `--listen`.
+    Run {
+        /// The robot code to simulate (WASM file). Can be left out with `--upload-dir`, to wait
+        /// for a frontend to upload it.
+        #[clap(required_unless_present = "upload_dir")]
+        robot_code: Option<PathBuf>,
+        #[command(flatten)]
+        simulation: SimulationArgs,
+        #[command(flatten)]
+        input: InputArgs,
+        /// Write the events sent during each scheduler tick as one line holding a JSON array,
+        /// rather than a line per event.
+        #[clap(long)]
+        batch_events: bool,
+        /// Load another program into a slot, e.g. `--slot 2=skills.wasm`. Can be repeated. The
+        /// robot code goes in slot 1 and runs first, and `SelectSlot` messages switch programs.
+        #[clap(long = "slot", value_name = "SLOT=FILE", value_parser = parse_slot)]
+        slots: Vec<(u8, PathBuf)>,
+    },
+    /// Compile robot code and report any PROS APIs it uses that aren't implemented by the
+    /// simulator, without running it.
+    Check {
+        /// The robot code to check (WASM file).
+        robot_code: PathBuf,
+    },
+    /// Simulate robot code like `run`, additionally saving every event to a file.
+    Record {
+        /// The robot code to simulate (WASM file). Can be left out with `--upload-dir`, to wait
+        /// for a frontend to upload it.
+        #[clap(required_unless_present = "upload_dir")]
+        robot_code: Option<PathBuf>,
+        #[command(flatten)]
+        simulation: SimulationArgs,
+        /// Where to save the line delimited JSON event log.
+        #[clap(short, long)]
+        output: PathBuf,
+        #[command(flatten)]
+        input: InputArgs,
+        /// Write the events sent during each scheduler tick to the frontend as one line holding a
+        /// JSON array. The saved event log still has a line per event.
+        #[clap(long)]
+        batch_events: bool,
+        /// Load another program into a slot, e.g. `--slot 2=skills.wasm`. Can be repeated.
+        #[clap(long = "slot", value_name = "SLOT=FILE", value_parser = parse_slot)]
+        slots: Vec<(u8, PathBuf)>,
+    },
+    /// Run a separate simulation for every frontend that connects to `--listen`, e.g. for a
+    /// classroom or a web service. Each session's events only go to its own frontend.
+    Serve {
+        /// The robot code each session runs (WASM file). Can be left out with `--upload-dir`, so
+        /// that each session waits for its frontend to upload robot code.
+        #[clap(required_unless_present = "upload_dir")]
+        robot_code: Option<PathBuf>,
+        #[command(flatten)]
+        simulation: SimulationArgs,
+        /// Where to wait for frontends, e.g. `tcp:0.0.0.0:5250`, `ws:0.0.0.0:5250` for web pages,
+        /// or `unix:/tmp/sim.sock`.
+        #[clap(long, value_name = "ENDPOINT")]
+        listen: Endpoint,
+        #[command(flatten)]
+        access: AccessArgs,
+        /// Accept programs uploaded by frontends in `UploadProgram` messages, saving each
+        /// session's to a directory of its own in this one.
+        #[clap(long, value_name = "DIR")]
+        upload_dir: Option<PathBuf>,
+        /// The most sessions to run at once. Frontends connecting while this many are running
+        /// are turned away with a warning.
+        #[clap(long, value_name = "N", default_value_t = 8)]
+        max_sessions: usize,
+    },
+    /// Stream saved events over stdio or `--listen` as if robot code were running, from a file
+    /// created with `record` or `--event-log`, so frontends can be developed without robot code.
+    Replay {
+        /// The events to replay, saved by `record` or `--event-log`. Gzipped files are
+        /// decompressed.
+        recording: PathBuf,
+        /// Send each event at the simulated time it was logged, rather than as fast as the
+        /// frontend reads them. Only logs written with `--event-log` have timestamps.
+        #[clap(long)]
+        realtime: bool,
+        /// Wait for a frontend to connect to this address, then replay to it instead of stdout,
+        /// e.g. `tcp:127.0.0.1:5250`, `ws:127.0.0.1:5250` or `unix:/tmp/sim.sock`.
+        #[clap(long, value_name = "ENDPOINT")]
+        listen: Option<Endpoint>,
+        #[command(flatten)]
+        access: AccessArgs,
+    },
+    /// Print the JSON schema of the events and messages sent over stdio.
+    Schema,
+    /// Run robot code headlessly for CI, exiting with a non-zero code if it faults or an
+    /// expectation isn't met.
+    Test {
+        /// The robot code to simulate (WASM file).
+        robot_code: PathBuf,
+        #[command(flatten)]
+        simulation: SimulationArgs,
+        /// Line delimited JSON messages to send to the robot code when it starts, such as a
+        /// competition phase change, and joystick waveforms to play. Can be repeated to simulate
+        /// each scenario separately.
+        #[clap(long = "scenario", value_name = "FILE")]
+        scenarios: Vec<PathBuf>,
+        /// Play back controller input saved with `--record-input`, at the same times it was
+        /// recorded.
+        #[clap(long, value_name = "FILE")]
+        play_input: Option<PathBuf>,
+        /// Fail unless the robot code's console output contains this text. Can be repeated.
+        #[clap(long = "expect-output", value_name = "TEXT")]
+        expect_output: Vec<String>,
+        /// Fail if the simulator emits any warnings.
+        #[clap(long)]
+        deny_warnings: bool,
+        /// Fail unless the robot scores at least this many points in the scenario's
+        /// `AddScoringZone` zones, e.g. to check an autonomous routine gets the win point.
+        #[clap(long, value_name = "POINTS", allow_negative_numbers = true)]
+        min_score: Option<i32>,
+        /// Where to write a JUnit XML report.
+        #[clap(long, value_name = "FILE")]
+        junit: Option<PathBuf>,
+        /// Where to write a JSON report.
+        #[clap(long, value_name = "FILE")]
+        json: Option<PathBuf>,
+        /// Run up to this many simulations at once, each on a thread of its own. With
+        /// `--run-tests`, each of the robot code's tests is then simulated separately too.
+        #[clap(short, long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+        jobs: u16,
+    },
+}
+
+/// Options shared by every subcommand that runs robot code.
+#[derive(clap::Args, Debug)]
+struct SimulationArgs {
+    /// Load the robot's setup from a `simulator.toml` file: its ports, drivetrain, controllers,
+    /// field and a scenario to send when the robot code starts. Other flags are applied on top.
+    #[clap(long, value_name = "FILE", value_parser = parse_config)]
+    config: Option<SimulatorConfig>,
+
+    /// Stop the simulation if it's still running after this many seconds.
+    #[clap(long, value_name = "SECONDS", value_parser = parse_timeout)]
+    timeout: Option<Duration>,
+
+    /// Stop the simulation once this many seconds have passed inside it, however fast it runs.
+    #[clap(long, value_name = "SECONDS", value_parser = parse_timeout, conflicts_with = "timeout")]
+    simulated_timeout: Option<Duration>,
+
+    /// Seed the random number generator used by robot code, making runs reproducible.
+    #[clap(long)]
+    seed: Option<u64>,
+
+    /// Run each task on its own OS thread, for robot code that relies on parallelism.
     #[clap(long)]
-    stdio: bool,
+    threaded: bool,
+
+    /// Preallocate resources for running up to this many tasks at once, making spawning tasks
+    /// faster.
+    #[clap(long)]
+    instance_pool: Option<u32>,
+
+    /// Start the clock returned by `millis()` at this value, e.g. to test timers wrapping around.
+    #[clap(long, default_value_t = 0)]
+    start_millis: u32,
+
+    /// Send console output at this baud rate (115200 if not given) like the V5's USB serial
+    /// connection, instead of instantly.
+    #[clap(long, value_name = "BAUD", num_args = 0..=1, default_missing_value = "115200")]
+    throttle_serial: Option<u32>,
+
+    /// Hold back each task's stdout until it prints a newline or fills newlib's 1 KiB buffer,
+    /// like on the brain.
+    #[clap(long)]
+    line_buffering: bool,
+
+    /// Send a snapshot of the robot's state this many times per second.
+    #[clap(long, value_name = "HZ")]
+    telemetry: Option<u32>,
+
+    /// Write a CSV log of the robot's state to this file, with a row for each telemetry snapshot
+    /// (50 per second unless `--telemetry` is given), for analysis in a spreadsheet.
+    #[clap(long, value_name = "FILE")]
+    match_log: Option<PathBuf>,
+
+    /// Serve console output on this TCP address (e.g. `127.0.0.1:5252`), framed like the V5's
+    /// USB serial protocol so PROS terminal tooling can read it. Simulator warnings are sent as
+    /// errors.
+    #[clap(long, value_name = "ADDR")]
+    serial_socket: Option<String>,
+
+    /// Send at most this many updates per second about each motor's voltage, instead of every
+    /// change.
+    #[clap(long, value_name = "HZ")]
+    motor_update_rate: Option<u32>,
+
+    /// Send counts of the events sent so far this many times per second.
+    #[clap(long, value_name = "HZ")]
+    channel_stats: Option<u32>,
+
+    /// Report how much of each simulated second robot code spent running rather than idle,
+    /// like the brain's CPU usage.
+    #[clap(long)]
+    task_stats: bool,
+
+    /// Send a numbered frame marker this many times per second of simulated time, for lining
+    /// up screen recordings of a frontend with the event log.
+    #[clap(long, value_name = "HZ")]
+    frame_markers: Option<u32>,
+
+    /// Run tasks in a random order and make delays last up to this many milliseconds longer, to
+    /// flush out race conditions. Use with `--seed` to get the same schedule again.
+    #[clap(long, value_name = "MS")]
+    jitter: Option<u64>,
+
+    /// Report when tasks have to wait for a mutex, and when one is held for longer than this
+    /// many milliseconds, to make synchronization bugs visible.
+    #[clap(long, value_name = "MS")]
+    mutex_events: Option<u64>,
+
+    /// Stop the simulation if robot code grows its memory past this many MiB.
+    #[clap(long, value_name = "MIB")]
+    max_memory: Option<usize>,
+
+    /// Stop the simulation if robot code tries to have more than this many tasks at once.
+    #[clap(long, value_name = "N")]
+    max_tasks: Option<u32>,
+
+    /// Stop the simulation if more than this many events are sent in a second.
+    #[clap(long, value_name = "N")]
+    max_event_rate: Option<u32>,
+
+    /// Sample the robot code's stack every millisecond and write the samples to this file in the
+    /// folded stack format, for turning into a flame graph.
+    #[clap(long, value_name = "FILE")]
+    profile: Option<PathBuf>,
+
+    /// Write the sections robot code timed with `sim_profile_begin` and `sim_profile_end` to
+    /// this file in the Chrome trace format, for opening in Perfetto or `chrome://tracing`.
+    #[clap(long, value_name = "FILE")]
+    chrome_trace: Option<PathBuf>,
+
+    /// Keep what robot code saves to flash with `sim_flash_write` in this file, so it's there
+    /// the next time the robot code runs.
+    #[clap(long, value_name = "FILE")]
+    flash: Option<PathBuf>,
+
+    /// Write a report of which APIs the robot code called to this file.
+    #[clap(long, value_name = "FILE")]
+    coverage: Option<PathBuf>,
+
+    /// Export each task's lifetime and each host call as OpenTelemetry spans to the collector at
+    /// this URL, e.g. `http://localhost:4318`.
+    #[cfg(feature = "otlp")]
+    #[clap(long, value_name = "URL")]
+    otlp_endpoint: Option<String>,
+
+    /// Draw the field, the robot and its game objects into this directory as numbered PNGs, at
+    /// the `--render-rate`.
+    #[cfg(feature = "render")]
+    #[clap(long, value_name = "DIR", conflicts_with = "render_mjpeg")]
+    render_png: Option<PathBuf>,
+
+    /// Draw the field, the robot and its game objects into this file as a Motion JPEG video, at
+    /// the `--render-rate`.
+    #[cfg(feature = "render")]
+    #[clap(long, value_name = "FILE")]
+    render_mjpeg: Option<PathBuf>,
+
+    /// How many frames to draw per second of simulated time with `--render-png` or
+    /// `--render-mjpeg`.
+    #[cfg(feature = "render")]
+    #[clap(long, value_name = "HZ", default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..))]
+    render_rate: u32,
+
+    /// Append every event to this gzip-compressed file as JSON lines while the simulation runs,
+    /// so they aren't lost if the simulator crashes or the frontend disconnects.
+    #[cfg(feature = "event-log")]
+    #[clap(long, value_name = "FILE")]
+    event_log: Option<PathBuf>,
+
+    /// Check which of the robot code's imports the simulator supports against this version of
+    /// the PROS API.
+    #[clap(long, value_name = "VERSION", default_value_t = ProsVersion::default())]
+    pros_version: ProsVersion,
+
+    /// Make unimplemented PROS APIs fail with `ENOSYS` instead of stopping the task that calls
+    /// them.
+    #[clap(long)]
+    permissive: bool,
+
+    /// Warn the first time each task reads `errno` before any PROS API call has set it.
+    #[clap(long)]
+    check_errno: bool,
+
+    /// Simulate a hot start, where only the hot image was uploaded: the robot code's `hot_init`
+    /// runs before `initialize`, but `cold_init` doesn't.
+    #[clap(long)]
+    hot_start: bool,
+
+    /// Surround the buffers the simulator allocates in robot code memory with canaries, and warn
+    /// when robot code writes out of bounds of them.
+    #[clap(long)]
+    canaries: bool,
+
+    /// Check the scheduler's invariants every time it switches tasks, and send an event when one
+    /// is broken. For catching bugs in the simulator itself.
+    #[clap(long)]
+    check_scheduler: bool,
+
+    /// When a task faults, stop only that task and keep running the rest of the robot code.
+    #[clap(long)]
+    isolate_crashes: bool,
+
+    /// Work out what an autonomous selector drawn on the LCD offers from its lines and button
+    /// callbacks, and report it as the robot code runs.
+    #[clap(long)]
+    lcd_selector: bool,
+
+    /// Let unit tests compiled into the robot code control the simulation with functions like
+    /// `sim_set_pose` and `sim_advance_time`.
+    #[clap(long)]
+    test_build: bool,
+
+    /// Set a value that robot code in a test build can read with `sim_config_get`, e.g.
+    /// `--test-config test=drive`. Can be repeated.
+    #[clap(long = "test-config", value_name = "KEY=VALUE", value_parser = parse_test_config)]
+    test_config: Vec<(String, String)>,
+
+    /// Run the functions the robot code exports whose names match a pattern as tests, one at a
+    /// time, instead of its competition functions, e.g. `--run-tests 'sim_test_*'`. Implies
+    /// `--test-build`. With `test`, each one is a check in the report.
+    #[clap(long, value_name = "PATTERN")]
+    run_tests: Option<String>,
+
+    /// Plug a device into a smart port, e.g. `--device 1=motor`. Can be repeated. Devices
+    /// aren't simulated yet, but robot code can see them with the PROS registry API.
+    #[clap(long = "device", value_name = "PORT=TYPE", value_parser = parse_device)]
+    devices: Vec<(u8, DeviceType)>,
+
+    /// Load a plugin from a WebAssembly module, letting robot code call the functions it
+    /// exports, e.g. to simulate a device the simulator doesn't support. Can be repeated.
+    #[clap(long = "plugin", value_name = "FILE")]
+    plugins: Vec<PathBuf>,
+
+    /// The radius in inches of the circle the robot pushes game objects from `AddGameObject`
+    /// messages with. Defaults to 9, which fits an 18 inch robot.
+    #[clap(long, value_name = "INCHES", value_parser = parse_robot_radius)]
+    robot_radius: Option<f64>,
+
+    /// Run a VEX Robotics Competition match once the robot code has initialized, then stop:
+    /// 15 seconds of autonomous followed by 1:45 of driver control.
+    #[clap(long = "match")]
+    run_match: bool,
+
+    /// Stop the simulation with an error when robot code causes this kind of warning:
+    /// `unimplemented-call`, `motor-voltage-clamped`, `suspended-at-exit`, `failed-assertion`,
+    /// `unset-errno`, `heap-corruption` or `invalid-argument`. Can be repeated.
+    #[clap(long, value_name = "KIND")]
+    strict: Vec<WarningKind>,
+
+    /// Delay controller updates by this many milliseconds, like the V5's radio link.
+    #[clap(long, value_name = "MS")]
+    controller_latency: Option<u64>,
+}
+
+/// Options for where the simulator's input comes from.
+#[derive(clap::Args, Debug)]
+struct InputArgs {
+    /// Also accept text commands from the frontend, such as `press a` or `phase auton`, so the
+    /// robot can be driven by hand. Lines starting with `{` or `"` are still read as JSON
+    /// messages. Type `help` for a list of commands.
+    #[clap(long)]
+    commands: bool,
+
+    /// Save the controller input sent to the robot, and when it was sent, to this file.
+    #[clap(long, value_name = "FILE")]
+    record_input: Option<PathBuf>,
+
+    /// Play back controller input saved with `--record-input`, at the same times it was
+    /// recorded.
+    #[clap(long, value_name = "FILE")]
+    play_input: Option<PathBuf>,
+
+    /// Wait for a frontend to connect to this address, then talk to it instead of stdio, e.g.
+    /// `tcp:127.0.0.1:5250`, `ws:127.0.0.1:5250` for web pages, or `unix:/tmp/sim.sock`.
+    #[clap(long, value_name = "ENDPOINT")]
+    listen: Option<Endpoint>,
+
+    #[command(flatten)]
+    access: AccessArgs,
+
+    /// Accept programs uploaded by the frontend in `UploadProgram` messages, saving them to this
+    /// directory. A program runs as soon as it's uploaded, replacing the one that's running.
+    #[clap(long, value_name = "DIR")]
+    upload_dir: Option<PathBuf>,
+}
+
+/// Options for which frontends are accepted over `--listen`.
+#[derive(clap::Args, Debug)]
+struct AccessArgs {
+    /// Only accept a frontend that sends this token in a handshake line, e.g.
+    /// `{"token": "hunter2"}`, before anything else.
+    #[clap(long, requires = "listen")]
+    token: Option<String>,
+
+    /// Only accept TCP and WebSocket frontends connecting from this address. Can be repeated.
+    #[clap(long = "allow-ip", value_name = "IP", requires = "listen")]
+    allowed_ips: Vec<IpAddr>,
+
+    /// Also accept WebSockets opened by web pages from this origin, e.g.
+    /// `https://example.com`. Only pages served from this computer are accepted by default.
+    /// Can be repeated.
+    #[clap(long = "allow-origin", value_name = "ORIGIN", requires = "listen")]
+    allowed_origins: Vec<String>,
+}
+
+impl AccessArgs {
+    fn access(&self) -> Access {
+        Access {
+            token: self.token.clone(),
+            allowed_ips: self.allowed_ips.clone(),
+            allowed_origins: self.allowed_origins.clone(),
+        }
+    }
+}
+
+/// Parses a `PORT=TYPE` device argument.
+fn parse_device(arg: &str) -> Result<(u8, DeviceType), String> {
+    let (port, device) = arg
+        .split_once('=')
+        .ok_or("expected PORT=TYPE, e.g. `1=motor`")?;
+    let port = port
+        .parse::<u8>()
+        .ok()
+        .filter(|port| (1..=21).contains(port))
+        .ok_or(format!("`{port}` isn't a smart port (1-21)"))?;
+    Ok((port, device.parse()?))
+}
+
+/// Parses a `KEY=VALUE` test config argument.
+fn parse_test_config(arg: &str) -> Result<(String, String), String> {
+    let (key, value) = arg
+        .split_once('=')
+        .ok_or("expected KEY=VALUE, e.g. `test=drive`")?;
+    Ok((key.to_string(), value.to_string()))
+}
+
+/// Loads a `--config` file.
+fn parse_config(arg: &str) -> Result<SimulatorConfig, String> {
+    SimulatorConfig::load(arg).map_err(|err| format!("{err:#}"))
+}
+
+/// Parses a `--robot-radius` argument, which can't be negative.
+fn parse_robot_radius(arg: &str) -> Result<f64, String> {
+    arg.parse::<f64>()
+        .ok()
+        .filter(|radius| radius.is_finite() && *radius >= 0.0)
+        .ok_or(format!("`{arg}` isn't a radius in inches"))
+}
+
+/// Parses a timeout in seconds, which can't be negative or too large to wait for.
+fn parse_timeout(arg: &str) -> Result<Duration, String> {
+    let seconds = arg
+        .parse::<f64>()
+        .map_err(|_| format!("`{arg}` isn't a number of seconds"))?;
+    Duration::try_from_secs_f64(seconds).map_err(|_| format!("the timeout can't be {arg} seconds"))
+}
+
+/// Parses a `SLOT=FILE` program slot argument.
+fn parse_slot(arg: &str) -> Result<(u8, PathBuf), String> {
+    let (slot, file) = arg
+        .split_once('=')
+        .ok_or("expected SLOT=FILE, e.g. `2=skills.wasm`")?;
+    let slot = slot
+        .parse::<u8>()
+        .ok()
+        .filter(|slot| (1..=NUM_SLOTS).contains(slot))
+        .ok_or(format!("`{slot}` isn't a program slot (1-{NUM_SLOTS})"))?;
+    Ok((slot, PathBuf::from(file)))
+}
+
+impl SimulationArgs {
+    /// The first flag given that writes to a file or socket, which simulations running at the
+    /// same time would all share.
+    fn shared_output(&self) -> Option<&'static str> {
+        let shared = [
+            ("--match-log", self.match_log.is_some()),
+            ("--serial-socket", self.serial_socket.is_some()),
+            ("--profile", self.profile.is_some()),
+            ("--chrome-trace", self.chrome_trace.is_some()),
+            ("--flash", self.flash.is_some()),
+            ("--coverage", self.coverage.is_some()),
+            #[cfg(feature = "render")]
+            ("--render-png", self.render_png.is_some()),
+            #[cfg(feature = "render")]
+            ("--render-mjpeg", self.render_mjpeg.is_some()),
+            #[cfg(feature = "event-log")]
+            ("--event-log", self.event_log.is_some()),
+        ];
+        shared
+            .into_iter()
+            .find_map(|(flag, used)| used.then_some(flag))
+    }
+
+    fn match_log(&self) -> Option<MatchLog> {
+        self.match_log
+            .as_ref()
+            .map(|path| MatchLog::create(path).unwrap())
+    }
+
+    fn serial_socket(&self) -> Option<SerialSocket> {
+        self.serial_socket
+            .as_ref()
+            .map(|addr| SerialSocket::bind(addr).unwrap())
+    }
+
+    /// The scenario named by the `--config` file, if there is one.
+    fn scenario(&self) -> Option<PathBuf> {
+        let config = self.config.as_ref()?;
+        config.scenario().map(Path::to_path_buf)
+    }
+
+    fn options(&self) -> SimulatorOptions {
+        let mut options = match &self.config {
+            Some(config) => config.options(),
+            None => SimulatorOptions::new(),
+        };
+        if let Some(timeout) = self.timeout {
+            options = options.timeout(Timeout::RealTime(timeout));
+        }
+        if let Some(timeout) = self.simulated_timeout {
+            options = options.timeout(Timeout::Simulated(timeout));
+        }
+        if let Some(seed) = self.seed {
+            options = options.deterministic(seed);
+        }
+        options = options
+            .threaded(self.threaded)
+            .start_millis(self.start_millis)
+            .target_pros_version(self.pros_version)
+            .permissive(self.permissive)
+            .check_errno(self.check_errno)
+            .canaries(self.canaries)
+            .check_scheduler(self.check_scheduler)
+            .isolate_crashes(self.isolate_crashes)
+            .lcd_selector(self.lcd_selector)
+            .test_build(self.test_build)
+            .task_stats(self.task_stats)
+            .line_buffering(self.line_buffering);
+        if self.hot_start {
+            options = options.start_kind(StartKind::Hot);
+        }
+        for (port, device) in &self.devices {
+            options = options.smart_port(*port, *device);
+        }
+        for plugin in &self.plugins {
+            options = options.plugin(plugin);
+        }
+        for (key, value) in &self.test_config {
+            options = options.test_config(key, value);
+        }
+        if let Some(pattern) = &self.run_tests {
+            options = options.run_tests(pattern);
+        }
+        for kind in &self.strict {
+            options = options.strict(*kind);
+        }
+        if self.run_match {
+            options = options.run_match(MatchTiming::default());
+        }
+        if let Some(max_tasks) = self.instance_pool {
+            options = options.instance_pool(max_tasks);
+        }
+        if let Some(baud_rate) = self.throttle_serial {
+            options = options.throttle_serial(baud_rate);
+        }
+        let telemetry_hz = self
+            .telemetry
+            .or(self.match_log.as_ref().map(|_| match_log::DEFAULT_HZ));
+        if let Some(hz) = telemetry_hz {
+            options = options.telemetry(hz);
+        }
+        if let Some(hz) = self.motor_update_rate {
+            options = options.motor_update_rate(hz);
+        }
+        if let Some(hz) = self.channel_stats {
+            options = options.channel_stats(hz);
+        }
+        if let Some(hz) = self.frame_markers {
+            options = options.frame_markers(hz);
+        }
+        if let Some(latency) = self.controller_latency {
+            options = options.controller_latency(Duration::from_millis(latency));
+        }
+        if let Some(max_delay) = self.jitter {
+            options = options.jitter(Duration::from_millis(max_delay));
+        }
+        if let Some(threshold) = self.mutex_events {
+            options = options.mutex_events(Duration::from_millis(threshold));
+        }
+        if let Some(mib) = self.max_memory {
+            options = options.max_memory(mib * 1024 * 1024);
+        }
+        if let Some(max_tasks) = self.max_tasks {
+            options = options.max_tasks(max_tasks);
+        }
+        if let Some(max) = self.max_event_rate {
+            options = options.max_event_rate(max);
+        }
+        if let Some(radius) = self.robot_radius {
+            options = options.robot_radius(radius);
+        }
+        if let Some(path) = &self.flash {
+            options = options.flash(path);
+        }
+        if let Some(output) = &self.profile {
+            options = options.profile(output);
+        }
+        if let Some(output) = &self.chrome_trace {
+            options = options.chrome_trace(output);
+        }
+        if let Some(output) = &self.coverage {
+            options = options.coverage_report(output);
+        }
+        #[cfg(feature = "otlp")]
+        if let Some(url) = &self.otlp_endpoint {
+            options = options.otlp_endpoint(url);
+        }
+        #[cfg(feature = "render")]
+        if let Some(dir) = &self.render_png {
+            options = options.render_frames(FrameOutput::Png(dir.clone()), self.render_rate);
+        }
+        #[cfg(feature = "render")]
+        if let Some(path) = &self.render_mjpeg {
+            options = options.render_frames(FrameOutput::Mjpeg(path.clone()), self.render_rate);
+        }
+        #[cfg(feature = "event-log")]
+        if let Some(path) = &self.event_log {
+            options = options.event_log(path);
+        }
+        options
+    }
+}
+
+/// The JSON schemas of the stdio protocol, keyed by direction.
+#[derive(JsonSchema)]
+#[allow(dead_code)]
+struct Protocol {
+    /// Sent from the simulator to stdout.
+    event: SimulatorEvent,
+    /// Sent from the simulator to stdout instead of each event with `--batch-events`.
+    batch: SimulatorEventBatch,
+    /// Sent by a frontend before anything else when the server requires a `--token`.
+    handshake: Handshake,
+    /// Read by the simulator from stdin.
+    message: SimulatorMessage,
+}
+
+async fn run(
+    robot_code: Option<&Path>,
+    simulation: &SimulationArgs,
+    input_args: &InputArgs,
+    mut recording: Option<BufWriter<File>>,
+    batch_events: bool,
+    slots: &[(u8, PathBuf)],
+) {
+    let uploads = input_args.upload_dir.clone().map(|dir| {
+        Uploads::new(dir).unwrap_or_else(|err| {
+            eprintln!("Error: Couldn't create the upload directory: {err}");
+            exit(1);
+        })
+    });
+    let slots = (!slots.is_empty() || uploads.is_some()).then(|| {
+        Slots::new(robot_code, slots, uploads).unwrap_or_else(|err| {
+            eprintln!("Error: {err}");
+            exit(1);
+        })
+    });
+
+    let connection = connect_frontend(input_args.listen.as_ref(), &input_args.access);
+    let output = EventWriter::new(connection.writer, Closing::ExitServer);
+
+    let (tx, rx) = mpsc::channel::<SimulatorMessage>();
+    if let Some(scenario) = simulation.scenario() {
+        let (messages, waveforms) = read_scenario(&scenario);
+        for message in messages {
+            tx.send(message).unwrap();
+        }
+        if !waveforms.is_empty() {
+            waveform::play(waveforms, tx.clone());
+        }
+    }
+    if let Some(path) = &input_args.play_input {
+        input::play(path, tx.clone());
+    }
+    spawn_reader(
+        connection.reader,
+        input_args.commands,
+        Closing::ExitServer,
+        tx,
+    );
+    let rx = match &input_args.record_input {
+        Some(path) => input::record(rx, path),
+        None => rx,
+    };
+    let mut match_log = simulation.match_log();
+    let serial_socket = simulation.serial_socket();
+    let batcher = batch_events.then(|| Arc::new(EventBatcher::spawn(output.clone())));
+    let sink: EventSink = Arc::new(Mutex::new({
+        let batcher = batcher.clone();
+        move |event| {
+            if let Some(match_log) = &mut match_log {
+                match_log.log(&event).unwrap();
+            }
+            if let Some(serial_socket) = &serial_socket {
+                serial_socket.send(&event);
+            }
+            if let Some(recording) = &mut recording {
+                write(&mut *recording, &event).unwrap();
+                recording.flush().unwrap();
+            }
+            match &batcher {
+                Some(batcher) => batcher.push(event),
+                None => output.write(&event),
+            }
+        }
+    }));
+    let outcome = match slots {
+        Some(slots) => slots.run(|| simulation.options(), &sink, rx).await,
+        None => Some(
+            pros_simulator::simulate(
+                robot_code.unwrap(),
+                simulation.options(),
+                forward(&sink),
+                rx,
+            )
+            .await
+            .unwrap(),
+        ),
+    };
+    if let Some(batcher) = &batcher {
+        batcher.flush();
+    }
+
+    if !outcome.is_some_and(|outcome| outcome.is_success()) {
+        exit(1);
+    }
+}
+
+/// Waits for a frontend to connect to `listen`, or uses stdio if it isn't given.
+fn connect_frontend(listen: Option<&Endpoint>, access: &AccessArgs) -> Connection {
+    let transport = match listen {
+        Some(endpoint) => endpoint.bind(access.access()).unwrap_or_else(|err| {
+            eprintln!("Error: Couldn't listen for frontends: {err}");
+            exit(1);
+        }),
+        None => Box::new(Stdio) as Box<dyn Transport>,
+    };
+    transport.connect().unwrap_or_else(|err| {
+        eprintln!("Error: Couldn't connect to a frontend: {err}");
+        exit(1);
+    })
+}
 
-    /// The robot code to simulate (WASM file).
-    robot_code: PathBuf,
+/// A line of a scenario file.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ScenarioLine {
+    Waveform { waveform: Waveform },
+    Message(SimulatorMessage),
+}
+
+/// Read the line delimited JSON messages and joystick waveforms in a scenario file.
+fn read_scenario(scenario: &PathBuf) -> (Vec<SimulatorMessage>, Vec<Waveform>) {
+    let mut reader = BufReader::new(File::open(scenario).unwrap());
+    let mut messages = Vec::new();
+    let mut waveforms = Vec::new();
+    loop {
+        match read(&mut reader) {
+            Ok(ScenarioLine::Message(message)) => messages.push(message),
+            Ok(ScenarioLine::Waveform { waveform }) => waveforms.push(waveform),
+            Err(ReadError::Eof) => break,
+            Err(err) => {
+                eprintln!("Error reading scenario: {}", err);
+                exit(1);
+            }
+        }
+    }
+    (messages, waveforms)
+}
+
+/// One simulation of a `test` command: one of its scenarios, and with `--jobs` and
+/// `--run-tests`, one of the robot code's tests.
+#[derive(Debug, Clone, Default)]
+struct TestRun {
+    scenario: Option<PathBuf>,
+    test: Option<String>,
+}
+
+impl TestRun {
+    /// The runs a `test` command makes: one per scenario, split up by test if the robot code's
+    /// tests are being run separately.
+    fn plan(scenarios: &[PathBuf], tests: Option<&[String]>) -> Vec<Self> {
+        let scenarios = match scenarios {
+            [] => vec![None],
+            scenarios => scenarios.iter().cloned().map(Some).collect(),
+        };
+        let tests = match tests {
+            Some(tests) => tests.iter().cloned().map(Some).collect(),
+            None => vec![None],
+        };
+        scenarios
+            .iter()
+            .flat_map(|scenario| {
+                tests.iter().map(|test| Self {
+                    scenario: scenario.clone(),
+                    test: test.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// What the run is called in reports, if it's one of several.
+    fn name(&self) -> Option<String> {
+        match (&self.scenario, &self.test) {
+            (None, None) => None,
+            (Some(scenario), None) => Some(scenario.display().to_string()),
+            (None, Some(test)) => Some(test.clone()),
+            (Some(scenario), Some(test)) => Some(format!("{} {test}", scenario.display())),
+        }
+    }
+}
+
+/// What `test` checks about each run, besides the robot code running successfully.
+#[derive(Debug)]
+struct Expectations {
+    expect_output: Vec<String>,
+    deny_warnings: bool,
+    min_score: Option<i32>,
+}
+
+/// Simulates one run of a `test` command. With `echo`, the robot code's output and problems are
+/// printed as they happen, which is turned off when runs are made side by side.
+async fn test(
+    robot_code: &Path,
+    simulation: &SimulationArgs,
+    run: &TestRun,
+    play_input: Option<&PathBuf>,
+    expectations: &Expectations,
+    echo: bool,
+) -> Report {
+    // prints to stderr unless runs are being made side by side
+    macro_rules! note {
+        ($($arg:tt)*) => {
+            if echo {
+                eprintln!($($arg)*);
+            }
+        };
+    }
+
+    let (tx, rx) = mpsc::channel::<SimulatorMessage>();
+    let (messages, waveforms) = run.scenario.as_ref().map(read_scenario).unwrap_or_default();
+    for message in messages {
+        tx.send(message).unwrap();
+    }
+    if !waveforms.is_empty() {
+        waveform::play(waveforms, tx.clone());
+    }
+    if let Some(path) = play_input {
+        input::play(path, tx.clone());
+    }
+
+    let report = Arc::new(Mutex::new(Report {
+        robot_code: robot_code.display().to_string(),
+        run: run.name(),
+        ..Default::default()
+    }));
+    let mut options = simulation.options();
+    if let Some(test) = &run.test {
+        options = options.run_tests(test);
+    }
+    let start = Instant::now();
+    let res = pros_simulator::simulate(
+        robot_code,
+        options,
+        {
+            let report = report.clone();
+            let mut match_log = simulation.match_log();
+            let serial_socket = simulation.serial_socket();
+            move |event| {
+                if let Some(match_log) = &mut match_log {
+                    match_log.log(&event).unwrap();
+                }
+                if let Some(serial_socket) = &serial_socket {
+                    serial_socket.send(&event);
+                }
+                // keep the message channel open for as long as the simulator is running
+                let _ = &tx;
+                let mut report = report.lock().unwrap();
+                match event {
+                    SimulatorEvent::ConsoleMessage(message) => {
+                        if echo {
+                            print!("{}", message.text);
+                        }
+                        report.console.push_str(&message.text);
+                    }
+                    SimulatorEvent::Warning(message) => {
+                        note!("Warning: {message}");
+                        report.warnings.push(message);
+                    }
+                    SimulatorEvent::RobotCodeError {
+                        message,
+                        task_name,
+                        panic,
+                        ..
+                    } => {
+                        let error = match panic {
+                            Some(panic) => format!(
+                                "Task `{task_name}` panicked at {}:{}:{}: {}",
+                                panic.file, panic.line, panic.column, panic.message
+                            ),
+                            None => format!("Task `{task_name}` faulted: {message}"),
+                        };
+                        note!("Error: {error}");
+                        report.errors.push(error);
+                    }
+                    SimulatorEvent::UnimplementedCall { name, .. } => {
+                        note!("Error: Robot code called unimplemented API `{name}`");
+                        report.unimplemented_calls.push(name);
+                    }
+                    SimulatorEvent::AssertionFailed {
+                        message, task_name, ..
+                    } => {
+                        note!("Error: Assertion failed in task `{task_name}`: {message}");
+                        report.failed_assertions.push(message);
+                    }
+                    SimulatorEvent::TaskCreationFailed {
+                        reason, task_name, ..
+                    } => {
+                        let message =
+                            format!("Task `{task_name}` couldn't create a task: {reason}");
+                        note!("Warning: {message}");
+                        report.warnings.push(message);
+                    }
+                    SimulatorEvent::InvalidArgument {
+                        function,
+                        argument,
+                        value,
+                        reason,
+                        task_name,
+                        ..
+                    } => {
+                        let message = format!(
+                            "Task `{task_name}` called {function} with {argument} = {value}, \
+                             which {reason}"
+                        );
+                        note!("Warning: {message}");
+                        report.warnings.push(message);
+                    }
+                    SimulatorEvent::ScoreChanged {
+                        zone,
+                        points,
+                        score,
+                        ..
+                    } => {
+                        note!("Scored {points:+} in `{zone}`, for a score of {score}");
+                        report.score = score;
+                    }
+                    SimulatorEvent::TestFinished(result) => {
+                        let failure = match (&result.error, result.failed_assertions) {
+                            (Some(error), _) => Some(format!("Test crashed: {error}")),
+                            (None, 0) => None,
+                            (None, failed) => Some(format!("{failed} assertion(s) failed")),
+                        };
+                        report
+                            .checks
+                            .push(Check::new(format!("test `{}`", result.name), failure));
+                    }
+                    _ => {}
+                }
+            }
+        },
+        rx,
+    )
+    .await;
+
+    let mut report = std::mem::take(&mut *report.lock().unwrap());
+    match res {
+        Ok(outcome) => {
+            report.set_times(outcome.simulated_time, start.elapsed());
+            report.checks.push(Check::new(
+                "robot code runs successfully",
+                describe_failure(&outcome.reason),
+            ));
+        }
+        Err(err) => {
+            report.set_times(Duration::ZERO, start.elapsed());
+            report.checks.push(Check::new(
+                "robot code runs successfully",
+                Some(format!("Failed to load robot code: {err}")),
+            ));
+        }
+    }
+
+    let failure = (!report.unimplemented_calls.is_empty()).then(|| {
+        format!(
+            "Robot code called unimplemented APIs: {}",
+            report.unimplemented_calls.join(", ")
+        )
+    });
+    report.checks.push(Check::new(
+        "robot code only calls implemented APIs",
+        failure,
+    ));
+
+    let failure = (!report.failed_assertions.is_empty()).then(|| {
+        format!(
+            "Robot code assertions failed: {}",
+            report.failed_assertions.join(", ")
+        )
+    });
+    report
+        .checks
+        .push(Check::new("robot code assertions pass", failure));
+
+    for text in &expectations.expect_output {
+        let failure = (!report.console.contains(text.as_str()))
+            .then(|| "Console output did not contain the expected text".to_string());
+        report.checks.push(Check::new(
+            format!("console output contains {text:?}"),
+            failure,
+        ));
+    }
+
+    if expectations.deny_warnings {
+        let failure = (!report.warnings.is_empty())
+            .then(|| format!("The simulator emitted {} warning(s)", report.warnings.len()));
+        report.checks.push(Check::new("no warnings", failure));
+    }
+
+    if let Some(min_score) = expectations.min_score {
+        let failure =
+            (report.score < min_score).then(|| format!("The robot scored {} points", report.score));
+        report.checks.push(Check::new(
+            format!("score is at least {min_score}"),
+            failure,
+        ));
+    }
+
+    report
 }
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() {
     let args = Args::parse();
 
-    if args.stdio {
-        let (tx, rx) = mpsc::channel::<SimulatorMessage>();
-        tokio::task::spawn_blocking(move || {
-            let mut reader = BufReader::new(stdin().lock());
-            loop {
-                let event = read(&mut reader);
-                match event {
-                    Ok(message) => _ = tx.send(message),
-                    Err(ReadError::Eof) => break,
-                    Err(err) => {
-                        eprintln!("Error reading from stdio: {}", err);
-                        exit(1);
+    match args.command {
+        Command::Run {
+            robot_code,
+            simulation,
+            input,
+            batch_events,
+            slots,
+        } => {
+            let robot_code = robot_code.as_deref();
+            run(robot_code, &simulation, &input, None, batch_events, &slots).await;
+        }
+        Command::Record {
+            robot_code,
+            simulation,
+            output,
+            input,
+            batch_events,
+            slots,
+        } => {
+            let recording = BufWriter::new(File::create(output).unwrap());
+            let robot_code = robot_code.as_deref();
+            run(
+                robot_code,
+                &simulation,
+                &input,
+                Some(recording),
+                batch_events,
+                &slots,
+            )
+            .await;
+        }
+        Command::Serve {
+            robot_code,
+            simulation,
+            listen,
+            access,
+            upload_dir,
+            max_sessions,
+        } => {
+            if let Some(flag) = simulation.shared_output() {
+                eprintln!(
+                    "Error: `{flag}` can't be used with `serve`, as every session would share it"
+                );
+                exit(1);
+            }
+            if let Some(Err(err)) = robot_code.as_ref().map(fs::metadata) {
+                eprintln!("Error: Couldn't read the robot code: {err}");
+                exit(1);
+            }
+            let transport = listen.bind(access.access()).unwrap_or_else(|err| {
+                eprintln!("Error: Couldn't listen for frontends: {err}");
+                exit(1);
+            });
+            let sessions = Sessions {
+                robot_code,
+                options: Box::new(move || simulation.options()),
+                upload_dir,
+                max_sessions,
+            };
+            if let Err(err) = serve::serve(&*transport, sessions) {
+                eprintln!("Error: Couldn't accept a frontend: {err}");
+                exit(1);
+            }
+        }
+        Command::Check { robot_code } => {
+            let unsupported = Arc::new(AtomicBool::new(false));
+            let res = pros_simulator::check(&robot_code, {
+                let unsupported = unsupported.clone();
+                move |event| {
+                    if let SimulatorEvent::Warning(message) = event {
+                        eprintln!("{message}");
+                        unsupported.store(true, Ordering::Relaxed);
                     }
                 }
+            })
+            .await;
+            if let Err(err) = res {
+                eprintln!("Error: {err:?}");
+                exit(1);
             }
-        });
-        pros_simulator::simulate(
-            &args.robot_code,
-            move |event| {
-                write(stdout().lock(), &event).unwrap();
-            },
-            rx,
-        )
-        .await
-        .unwrap();
-    } else {
-        panic!("No connection method: append the --stdio flag to use stdin/stdout.")
+            if unsupported.load(Ordering::Relaxed) {
+                exit(1);
+            }
+        }
+        Command::Replay {
+            recording,
+            realtime,
+            listen,
+            access,
+        } => {
+            let (saved, warning) = replay::open(&recording).unwrap_or_else(|err| {
+                eprintln!("Error: Couldn't read the recording: {err}");
+                exit(1);
+            });
+            let Connection {
+                mut reader, writer, ..
+            } = connect_frontend(listen.as_ref(), &access);
+            // there's no robot code for messages to control
+            thread::spawn(move || io::copy(&mut reader, &mut io::sink()));
+            let output = EventWriter::new(writer, Closing::ExitServer);
+            if let Err(err) = replay::replay(saved, &output, realtime) {
+                eprintln!("Error reading recording: {err}");
+                exit(1);
+            }
+            if let Some(warning) = warning {
+                eprintln!("Warning: {warning}");
+            }
+        }
+        Command::Test {
+            robot_code,
+            simulation,
+            scenarios,
+            play_input,
+            expect_output,
+            deny_warnings,
+            min_score,
+            junit,
+            json,
+            jobs,
+        } => {
+            let jobs = usize::from(jobs);
+            // robot code that can't be loaded is reported by the run itself
+            let tests = match &simulation.run_tests {
+                Some(pattern) if jobs > 1 => pros_simulator::list_tests(&robot_code, pattern)
+                    .ok()
+                    .filter(|tests| !tests.is_empty()),
+                _ => None,
+            };
+            // the config's scenario is used unless others are given
+            let scenarios = match (scenarios.is_empty(), simulation.scenario()) {
+                (true, Some(scenario)) => vec![scenario],
+                _ => scenarios,
+            };
+            let runs = TestRun::plan(&scenarios, tests.as_deref());
+            let parallel = jobs > 1 && runs.len() > 1;
+            if let Some(flag) = simulation.shared_output().filter(|_| parallel) {
+                eprintln!(
+                    "Error: `{flag}` can't be used with `--jobs`, as every simulation would \
+                     share it"
+                );
+                exit(1);
+            }
+
+            let expectations = Expectations {
+                expect_output,
+                deny_warnings,
+                min_score,
+            };
+            let start = Instant::now();
+            let reports = if parallel {
+                jobs::run_parallel(runs, jobs, |run| {
+                    let (robot_code, simulation) = (&robot_code, &simulation);
+                    let (play_input, expectations) = (play_input.as_ref(), &expectations);
+                    async move {
+                        test(
+                            robot_code,
+                            simulation,
+                            &run,
+                            play_input,
+                            expectations,
+                            false,
+                        )
+                        .await
+                    }
+                })
+            } else {
+                let mut reports = Vec::new();
+                for run in &runs {
+                    let report = test(
+                        &robot_code,
+                        &simulation,
+                        run,
+                        play_input.as_ref(),
+                        &expectations,
+                        true,
+                    )
+                    .await;
+                    reports.push(report);
+                }
+                reports
+            };
+            let wall_time = start.elapsed().as_secs_f64();
+
+            if let Some(junit) = junit {
+                fs::write(junit, report::to_junit(&reports, wall_time)).unwrap();
+            }
+            if let Some(json) = json {
+                fs::write(json, report::to_json(&reports)).unwrap();
+            }
+            for report in &reports {
+                let run = report
+                    .run
+                    .as_ref()
+                    .map(|run| format!("{run}: "))
+                    .unwrap_or_default();
+                eprintln!(
+                    "{run}{} of {} checks passed in {:.3}s ({:.3}s simulated)",
+                    report.checks.len() - report.failures(),
+                    report.checks.len(),
+                    report.wall_time,
+                    report.simulated_time,
+                );
+                for check in &report.checks {
+                    if let Some(failure) = &check.failure {
+                        eprintln!("FAILED {run}{}: {failure}", check.name);
+                    }
+                }
+            }
+            let passed = reports.iter().filter(|report| report.passed()).count();
+            if reports.len() > 1 {
+                eprintln!(
+                    "{passed} of {} runs passed in {wall_time:.3}s",
+                    reports.len()
+                );
+            }
+            if passed < reports.len() {
+                exit(1);
+            }
+        }
+        Command::Schema => {
+            let schema = schema_for!(Protocol);
+            println!("{}", serde_json::to_string_pretty(&schema).unwrap());
+        }
     }
     exit(0);
 }
@@ -0,0 +1,207 @@
+//! CSV logs of a simulation for analysis in a spreadsheet or with pandas, without parsing the
+//! event stream. Written by `--match-log`.
+//!
+//! Each row is a telemetry snapshot: the clock, the competition phase, both controllers, the task
+//! count, and the voltage each motor was last driven at.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use pros_simulator_interface::{CompetitionPhase, ControllerState, SimulatorEvent, Telemetry};
+
+/// How many telemetry snapshots are logged per second if `--telemetry` isn't given.
+pub const DEFAULT_HZ: u32 = 50;
+
+const NUM_MOTORS: usize = 21;
+
+pub struct MatchLog {
+    output: BufWriter<File>,
+    /// The voltage of each motor in millivolts, from the latest `MotorUpdated` events.
+    motor_voltages: [i32; NUM_MOTORS],
+}
+
+impl MatchLog {
+    /// Creates the log file and writes its header.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let mut output = BufWriter::new(File::create(path)?);
+        write!(output, "millis,phase,is_competition,task_count")?;
+        for controller in ["master", "partner"] {
+            write!(
+                output,
+                ",{controller}_connected,{controller}_left_x,{controller}_left_y,\
+                 {controller}_right_x,{controller}_right_y,{controller}_buttons"
+            )?;
+        }
+        for port in 1..=NUM_MOTORS {
+            write!(output, ",motor_{port}_millivolts")?;
+        }
+        writeln!(output)?;
+        Ok(Self {
+            output,
+            motor_voltages: [0; NUM_MOTORS],
+        })
+    }
+
+    /// Logs a row for telemetry snapshots, and remembers motor voltages for the next row.
+    pub fn log(&mut self, event: &SimulatorEvent) -> io::Result<()> {
+        match event {
+            SimulatorEvent::MotorUpdated {
+                port, millivolts, ..
+            } => {
+                let index = (*port as usize).checked_sub(1);
+                if let Some(voltage) = index.and_then(|index| self.motor_voltages.get_mut(index)) {
+                    *voltage = *millivolts;
+                }
+                Ok(())
+            }
+            SimulatorEvent::Telemetry(telemetry) => self.write_row(telemetry),
+            _ => Ok(()),
+        }
+    }
+
+    fn write_row(&mut self, telemetry: &Telemetry) -> io::Result<()> {
+        let output = &mut self.output;
+        write!(
+            output,
+            "{},{},{},{}",
+            telemetry.millis,
+            phase_name(&telemetry.competition_phase),
+            telemetry.competition_phase.is_competition,
+            telemetry.task_count,
+        )?;
+        for controller in [&telemetry.master, &telemetry.partner] {
+            match controller {
+                Some(ControllerState {
+                    digital, analog, ..
+                }) => {
+                    let buttons = [
+                        ("l1", digital.l1),
+                        ("l2", digital.l2),
+                        ("r1", digital.r1),
+                        ("r2", digital.r2),
+                        ("up", digital.up),
+                        ("down", digital.down),
+                        ("left", digital.left),
+                        ("right", digital.right),
+                        ("x", digital.x),
+                        ("b", digital.b),
+                        ("y", digital.y),
+                        ("a", digital.a),
+                    ]
+                    .into_iter()
+                    .filter_map(|(name, pressed)| pressed.then_some(name))
+                    .collect::<Vec<_>>();
+                    write!(
+                        output,
+                        ",true,{},{},{},{},{}",
+                        analog.left_x,
+                        analog.left_y,
+                        analog.right_x,
+                        analog.right_y,
+                        buttons.join(" ")
+                    )?;
+                }
+                None => write!(output, ",false,,,,,")?,
+            }
+        }
+        for millivolts in self.motor_voltages {
+            write!(output, ",{millivolts}")?;
+        }
+        writeln!(output)?;
+        output.flush()
+    }
+}
+
+fn phase_name(phase: &CompetitionPhase) -> &'static str {
+    match phase {
+        CompetitionPhase { enabled: false, .. } => "disabled",
+        CompetitionPhase {
+            autonomous: true, ..
+        } => "auton",
+        _ => "opcontrol",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows() {
+        let path = std::env::temp_dir().join(format!(
+            "pros-simulator-server-match-log-{}.csv",
+            std::process::id()
+        ));
+        let mut log = MatchLog::create(&path).unwrap();
+        let mut master = ControllerState::default();
+        master.digital.a = true;
+        master.digital.r1 = true;
+        master.analog.left_y = 42;
+        let telemetry = |millis| {
+            SimulatorEvent::Telemetry(Telemetry {
+                millis,
+                competition_phase: CompetitionPhase {
+                    autonomous: true,
+                    enabled: true,
+                    is_competition: true,
+                },
+                master: Some(master.clone()),
+                partner: None,
+                task_count: 3,
+                pose: None,
+                motors: Vec::new(),
+            })
+        };
+
+        log.log(&telemetry(20)).unwrap();
+        log.log(&SimulatorEvent::MotorUpdated {
+            port: 2,
+            millivolts: -6000,
+            millis: 25,
+            target_velocity: 0,
+            actual_velocity: 0,
+            position: 0,
+            current: 0,
+        })
+        .unwrap();
+        // ports that don't exist are ignored
+        log.log(&SimulatorEvent::MotorUpdated {
+            port: 0,
+            millivolts: 1,
+            millis: 25,
+            target_velocity: 0,
+            actual_velocity: 0,
+            position: 0,
+            current: 0,
+        })
+        .unwrap();
+        log.log(&telemetry(40)).unwrap();
+        drop(log);
+
+        let csv = std::fs::read_to_string(&path).unwrap();
+        let rows = csv
+            .lines()
+            .map(|line| line.split(',').collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(rows.len(), 3);
+        assert!(rows.iter().all(|row| row.len() == 4 + 2 * 6 + NUM_MOTORS));
+        assert_eq!(
+            rows[0][..4],
+            ["millis", "phase", "is_competition", "task_count"]
+        );
+        assert_eq!(rows[0][5], "master_left_x");
+        assert_eq!(rows[0][17], "motor_2_millivolts");
+
+        assert_eq!(rows[1][..4], ["20", "auton", "true", "3"]);
+        assert_eq!(rows[1][4..10], ["true", "0", "42", "0", "0", "r1 a"]);
+        assert_eq!(rows[1][10..16], ["false", "", "", "", "", ""]);
+        assert_eq!(rows[1][17], "0");
+        // motor voltages carry over to later rows
+        assert_eq!(rows[2][0], "40");
+        assert_eq!(rows[2][16..18], ["0", "-6000"]);
+        std::fs::remove_file(path).unwrap();
+    }
+}
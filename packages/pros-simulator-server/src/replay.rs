@@ -0,0 +1,257 @@
+//! Playing saved events back to a frontend with `replay`, as if robot code were running, so
+//! frontends can be developed and demoed without it.
+//!
+//! Both kinds of saved events can be replayed: the line delimited JSON events saved by
+//! `record`, and event logs written with `--event-log`, which are gzip-compressed and give
+//! each event the simulated time it was sent at:
+//!
+//! ```json
+//! {"millis":1520,"time":1700000000123,"event":"RobotCodeFinished"}
+//! ```
+//!
+//! Any file can be gzip-compressed. Events are sent as fast as the frontend reads them unless
+//! they're paced by their timestamps, which only event logs have.
+
+use std::{
+    fs,
+    io::{BufRead, Cursor},
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+use jsonl::{read, ReadError};
+use miniz_oxide::{
+    inflate::stream::{inflate, InflateState},
+    DataFormat, MZFlush, MZStatus,
+};
+use pros_simulator_interface::SimulatorEvent;
+use serde::Deserialize;
+
+use crate::transport::EventWriter;
+
+/// The first bytes of every gzip member.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// The warning given when a gzip file is cut off.
+const TRUNCATED: &str =
+    "The saved events end partway through, so the last of them can't be replayed";
+
+/// A line of saved events.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SavedLine {
+    /// An event from an event log, sent `millis` milliseconds into the simulation.
+    Logged { millis: u64, event: SimulatorEvent },
+    /// An event saved by `record`.
+    Recorded(SimulatorEvent),
+}
+
+/// Reads a file of saved events, decompressing it if it's gzipped. If the file ends partway
+/// through a gzip member, e.g. because the simulator crashed while writing it, everything
+/// before that member is still read and the returned warning says what was lost.
+pub fn open(path: &Path) -> Result<(impl BufRead, Option<String>), String> {
+    let bytes = fs::read(path).map_err(|err| err.to_string())?;
+    if !bytes.starts_with(&GZIP_MAGIC) {
+        return Ok((Cursor::new(bytes), None));
+    }
+    let (data, warning) = gunzip(&bytes)?;
+    Ok((Cursor::new(data), warning))
+}
+
+/// Sends the saved events to the frontend, waiting until each one's time has come if
+/// `realtime` is set.
+pub fn replay(mut saved: impl BufRead, output: &EventWriter, realtime: bool) -> Result<(), String> {
+    let start = Instant::now();
+    loop {
+        let (millis, event) = match read(&mut saved) {
+            Ok(SavedLine::Logged { millis, event }) => (Some(millis), event),
+            Ok(SavedLine::Recorded(event)) => (None, event),
+            Err(ReadError::Eof) => return Ok(()),
+            Err(err) => return Err(err.to_string()),
+        };
+        if let (true, Some(millis)) = (realtime, millis) {
+            let due = start + Duration::from_millis(millis);
+            thread::sleep(due.saturating_duration_since(Instant::now()));
+        }
+        output.write(&event);
+    }
+}
+
+/// Decompresses every gzip member in `bytes`, one after the other. A member that's cut off
+/// ends the data early with a warning rather than failing.
+fn gunzip(mut bytes: &[u8]) -> Result<(Vec<u8>, Option<String>), String> {
+    let mut data = Vec::new();
+    while !bytes.is_empty() {
+        let Some(header) = header_len(bytes)? else {
+            return Ok((data, Some(TRUNCATED.into())));
+        };
+        let member_start = data.len();
+        let mut state = InflateState::new_boxed(DataFormat::Raw);
+        let mut input = &bytes[header..];
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            let result = inflate(&mut state, input, &mut buffer, MZFlush::None);
+            data.extend(&buffer[..result.bytes_written]);
+            input = &input[result.bytes_consumed..];
+            match result.status {
+                Ok(MZStatus::StreamEnd) => break,
+                Ok(_) if result.bytes_consumed > 0 || result.bytes_written > 0 => {}
+                // out of input before the end of the member
+                _ if input.is_empty() => {
+                    data.truncate(member_start);
+                    return Ok((data, Some(TRUNCATED.into())));
+                }
+                _ => return Err("the gzip data is corrupt".into()),
+            }
+        }
+        // the CRC and length of the member's data
+        let Some(rest) = input.get(8..) else {
+            data.truncate(member_start);
+            return Ok((data, Some(TRUNCATED.into())));
+        };
+        bytes = rest;
+    }
+    Ok((data, None))
+}
+
+/// The length of the gzip member header at the start of `bytes`, or `None` if it's cut off.
+fn header_len(bytes: &[u8]) -> Result<Option<usize>, String> {
+    const FHCRC: u8 = 1 << 1;
+    const FEXTRA: u8 = 1 << 2;
+    const FNAME: u8 = 1 << 3;
+    const FCOMMENT: u8 = 1 << 4;
+
+    if bytes.len() < 10 {
+        return Ok(None);
+    }
+    if !bytes.starts_with(&GZIP_MAGIC) || bytes[2] != 8 {
+        return Err("expected another gzip member".into());
+    }
+    let flags = bytes[3];
+    let mut len = 10;
+    if flags & FEXTRA != 0 {
+        let Some(extra) = bytes.get(len..len + 2) else {
+            return Ok(None);
+        };
+        len += 2 + u16::from_le_bytes([extra[0], extra[1]]) as usize;
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            let Some(end) = bytes
+                .get(len..)
+                .and_then(|rest| rest.iter().position(|&b| b == 0))
+            else {
+                return Ok(None);
+            };
+            len += end + 1;
+        }
+    }
+    if flags & FHCRC != 0 {
+        len += 2;
+    }
+    Ok((len <= bytes.len()).then_some(len))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{self, Write},
+        sync::{Arc, Mutex},
+    };
+
+    use super::*;
+    use crate::transport::Closing;
+
+    /// Where a test's events are written, shared with the [`EventWriter`] writing them.
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Output {
+        fn lines(&self) -> Vec<String> {
+            let output = self.0.lock().unwrap();
+            String::from_utf8_lossy(&output)
+                .lines()
+                .map(str::to_string)
+                .collect()
+        }
+    }
+
+    fn replay_to_frontend(saved: &str, realtime: bool) -> (Result<(), String>, Vec<String>) {
+        let output = Output::default();
+        let writer = EventWriter::new(Box::new(output.clone()), Closing::ExitServer);
+        let result = replay(Cursor::new(saved.to_string()), &writer, realtime);
+        (result, output.lines())
+    }
+
+    /// A gzip member like the ones in event logs, without the CRC since it isn't checked.
+    fn gzip_member(data: &str) -> Vec<u8> {
+        let mut member = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+        member.extend(miniz_oxide::deflate::compress_to_vec(data.as_bytes(), 6));
+        member.extend([0; 4]);
+        member.extend((data.len() as u32).to_le_bytes());
+        member
+    }
+
+    #[test]
+    fn recorded_and_logged_events() {
+        let saved = concat!(
+            "\"RobotCodeStarting\"\n",
+            "{\"millis\":30,\"time\":1700000000123,\"event\":\"LcdInitialized\"}\n",
+            "\"RobotCodeFinished\"\n",
+        );
+        let (result, events) = replay_to_frontend(saved, false);
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            events,
+            [
+                "\"RobotCodeStarting\"",
+                "\"LcdInitialized\"",
+                "\"RobotCodeFinished\""
+            ]
+        );
+
+        // in real time, the logged event waits until 30 ms in
+        let start = Instant::now();
+        let (result, events) = replay_to_frontend(saved, true);
+        assert_eq!(result, Ok(()));
+        assert_eq!(events.len(), 3);
+        assert!(start.elapsed() >= Duration::from_millis(30));
+    }
+
+    #[test]
+    fn invalid_line() {
+        let (result, events) = replay_to_frontend("\"RobotCodeStarting\"\nnot json\n", false);
+        assert!(result.is_err());
+        assert_eq!(events, ["\"RobotCodeStarting\""]);
+    }
+
+    #[test]
+    fn truncated_log() {
+        let first = "\"RobotCodeStarting\"\n";
+        let second = gzip_member("\"RobotCodeFinished\"\n");
+        let mut bytes = gzip_member(first);
+        bytes.extend(&second[..second.len() - 12]);
+
+        let (data, warning) = gunzip(&bytes).unwrap();
+        assert_eq!(String::from_utf8(data).unwrap(), first);
+        assert_eq!(warning.as_deref(), Some(TRUNCATED));
+
+        let mut bytes = gzip_member(first);
+        bytes.extend(&second);
+        assert_eq!(
+            gunzip(&bytes).unwrap(),
+            (format!("{first}\"RobotCodeFinished\"\n").into_bytes(), None)
+        );
+    }
+}
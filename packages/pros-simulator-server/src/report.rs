@@ -0,0 +1,173 @@
+//! Summaries of headless `test` runs, written as JSON or JUnit XML for CI pipelines. A `test`
+//! command that makes several runs, e.g. one per scenario, reports each one separately.
+
+use std::{fmt::Write, time::Duration};
+
+use pros_simulator::StopReason;
+use serde::Serialize;
+
+/// A single check performed during a test run.
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub name: String,
+    /// Why the check failed, or `None` if it passed.
+    pub failure: Option<String>,
+}
+
+impl Check {
+    pub fn new(name: impl Into<String>, failure: Option<String>) -> Self {
+        Self {
+            name: name.into(),
+            failure,
+        }
+    }
+}
+
+/// Everything that happened during a test run.
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    pub robot_code: String,
+    /// Which of several runs this is, like the scenario it ran.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run: Option<String>,
+    pub checks: Vec<Check>,
+    pub warnings: Vec<String>,
+    pub errors: Vec<String>,
+    /// Unimplemented APIs the robot code called, which stopped the tasks that called them.
+    pub unimplemented_calls: Vec<String>,
+    /// Messages of the `sim_assert` calls that failed.
+    pub failed_assertions: Vec<String>,
+    /// The total score of the scenario's scoring zones when the run ended.
+    pub score: i32,
+    pub console: String,
+    /// Simulated time the robot code ran for, in seconds.
+    pub simulated_time: f64,
+    /// Wall-clock time the run took, including loading the robot code, in seconds.
+    pub wall_time: f64,
+}
+
+impl Report {
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.failure.is_none())
+    }
+
+    pub fn failures(&self) -> usize {
+        self.checks
+            .iter()
+            .filter(|check| check.failure.is_some())
+            .count()
+    }
+
+    pub fn set_times(&mut self, simulated_time: Duration, wall_time: Duration) {
+        self.simulated_time = simulated_time.as_secs_f64();
+        self.wall_time = wall_time.as_secs_f64();
+    }
+
+    /// The report's name as a JUnit test suite.
+    fn suite_name(&self) -> String {
+        match &self.run {
+            Some(run) => format!("{} ({run})", self.robot_code),
+            None => self.robot_code.clone(),
+        }
+    }
+
+    /// Writes the report as a JUnit test suite with one test case per check.
+    fn write_junit_suite(&self, xml: &mut String) {
+        _ = writeln!(
+            xml,
+            r#"  <testsuite name="{}" tests="{}" failures="{}" errors="0" time="{:.3}">"#,
+            escape(&self.suite_name()),
+            self.checks.len(),
+            self.failures(),
+            self.wall_time,
+        );
+        for check in &self.checks {
+            let name = escape(&check.name);
+            match &check.failure {
+                None => _ = writeln!(xml, r#"    <testcase name="{name}"/>"#),
+                Some(failure) => {
+                    _ = writeln!(xml, r#"    <testcase name="{name}">"#);
+                    _ = writeln!(xml, r#"      <failure message="{}"/>"#, escape(failure));
+                    _ = writeln!(xml, "    </testcase>");
+                }
+            }
+        }
+        _ = writeln!(
+            xml,
+            "    <system-out>{}</system-out>",
+            escape(&self.console)
+        );
+        let mut stderr = self.warnings.clone();
+        stderr.extend(self.errors.iter().cloned());
+        _ = writeln!(
+            xml,
+            "    <system-err>{}</system-err>",
+            escape(&stderr.join("\n"))
+        );
+        _ = writeln!(xml, "  </testsuite>");
+    }
+}
+
+/// Formats the reports of a `test` command as JSON: the report itself if there's only one, or
+/// an array of them.
+pub fn to_json(reports: &[Report]) -> String {
+    match reports {
+        [report] => serde_json::to_string_pretty(report).unwrap(),
+        reports => serde_json::to_string_pretty(reports).unwrap(),
+    }
+}
+
+/// Formats the reports of a `test` command as a JUnit XML document with one test suite per run.
+/// `wall_time` is how long all of them took.
+pub fn to_junit(reports: &[Report], wall_time: f64) -> String {
+    let mut xml = String::new();
+    _ = writeln!(xml, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    _ = writeln!(
+        xml,
+        r#"<testsuites name="pros-simulator" tests="{}" failures="{}" time="{:.3}">"#,
+        reports
+            .iter()
+            .map(|report| report.checks.len())
+            .sum::<usize>(),
+        reports.iter().map(Report::failures).sum::<usize>(),
+        wall_time,
+    );
+    for report in reports {
+        report.write_junit_suite(&mut xml);
+    }
+    _ = writeln!(xml, "</testsuites>");
+    xml
+}
+
+/// Describes why a simulation that didn't succeed stopped.
+pub fn describe_failure(reason: &StopReason) -> Option<String> {
+    match reason {
+        StopReason::Finished | StopReason::Exited(0) => None,
+        StopReason::Exited(code) => Some(format!("Robot code exited with code {code}")),
+        StopReason::Crashed(err) => Some(format!("Robot code crashed: {err}")),
+        StopReason::TimedOut => Some("Robot code timed out".into()),
+        StopReason::Cancelled => Some("Simulation was cancelled".into()),
+        StopReason::StrictWarning(kind) => Some(format!("Robot code caused a {kind} warning")),
+        StopReason::LimitExceeded(limit) => Some(format!("Robot code exceeded its {limit} limit")),
+        StopReason::TestsFailed { failed, total } => {
+            Some(format!("{failed} of {total} robot code tests failed"))
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // XML 1.0 doesn't allow most control characters, even when escaped
+            c if c.is_control() && !matches!(c, '\n' | '\r' | '\t') => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,133 @@
+//! Console output framed like the V5's USB serial protocol, served over TCP by `--serial-socket`
+//! so PROS terminal tooling can read it.
+//!
+//! The brain multiplexes several streams over its serial connection. Each packet is a four byte
+//! stream ID (`sout` for standard output, `serr` for errors) followed by the data, COBS encoded
+//! and terminated by a zero byte.
+
+use std::{
+    io::{self, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use pros_simulator_interface::SimulatorEvent;
+
+const STDOUT: &[u8; 4] = b"sout";
+const STDERR: &[u8; 4] = b"serr";
+
+pub struct SerialSocket {
+    clients: Arc<Mutex<Vec<TcpStream>>>,
+}
+
+impl SerialSocket {
+    /// Listens for connections on the given address. Output written before a client connects
+    /// isn't sent to it.
+    pub fn bind(addr: &str) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        eprintln!("Serving serial output on {}", listener.local_addr()?);
+
+        let clients = Arc::new(Mutex::new(Vec::new()));
+        thread::spawn({
+            let clients = clients.clone();
+            move || {
+                for stream in listener.incoming().flatten() {
+                    clients.lock().unwrap().push(stream);
+                }
+            }
+        });
+        Ok(Self { clients })
+    }
+
+    /// Sends console output on the `sout` stream, and simulator warnings and data aborts on the
+    /// `serr` stream. Clients that have disconnected are dropped.
+    pub fn send(&self, event: &SimulatorEvent) {
+        let packet = match event {
+            SimulatorEvent::ConsoleMessage(message) => frame(STDOUT, message.text.as_bytes()),
+            SimulatorEvent::Warning(message) => {
+                frame(STDERR, format!("Warning: {message}\n").as_bytes())
+            }
+            // the brain prints the data abort screen to its error stream too
+            SimulatorEvent::DataAbort(screen) => frame(STDERR, screen.to_string().as_bytes()),
+            _ => return,
+        };
+        self.clients
+            .lock()
+            .unwrap()
+            .retain_mut(|client| client.write_all(&packet).is_ok());
+    }
+}
+
+/// Builds a packet for the given stream.
+fn frame(stream: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    let mut packet = cobs_encode(&[stream, data].concat());
+    packet.push(0);
+    packet
+}
+
+/// Encodes data with Consistent Overhead Byte Stuffing, which removes every zero byte so that
+/// zeros can mark where packets end.
+fn cobs_encode(data: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(data.len() + data.len() / 254 + 1);
+    // each block starts with the offset of the next zero, or 0xFF for 254 bytes without one
+    let mut block_start = 0;
+    encoded.push(0);
+    for &byte in data {
+        if byte != 0 {
+            encoded.push(byte);
+        }
+        let block_len = encoded.len() - block_start;
+        if byte == 0 || block_len == 0xFF {
+            encoded[block_start] = block_len as u8;
+            block_start = encoded.len();
+            encoded.push(0);
+        }
+    }
+    encoded[block_start] = (encoded.len() - block_start) as u8;
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+
+    #[test]
+    fn cobs() {
+        assert_eq!(cobs_encode(&[]), [1]);
+        assert_eq!(cobs_encode(&[0]), [1, 1]);
+        assert_eq!(cobs_encode(&[0x11, 0, 0x22]), [2, 0x11, 2, 0x22]);
+        // a block of 254 bytes without a zero
+        let long = vec![0x33; 254];
+        let encoded = cobs_encode(&long);
+        assert_eq!(encoded[0], 0xFF);
+        assert_eq!(encoded[1..255], long[..]);
+        assert_eq!(encoded[255..], [1]);
+    }
+
+    #[test]
+    fn streams() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let socket = SerialSocket {
+            clients: Arc::new(Mutex::new(vec![stream])),
+        };
+
+        socket.send(&SimulatorEvent::ConsoleMessage("hi\n".to_string().into()));
+        socket.send(&SimulatorEvent::RobotCodeStarting);
+        socket.send(&SimulatorEvent::Warning("oops".into()));
+        drop(socket);
+
+        let mut received = Vec::new();
+        client.read_to_end(&mut received).unwrap();
+        let expected = [frame(STDOUT, b"hi\n"), frame(STDERR, b"Warning: oops\n")].concat();
+        assert_eq!(received, expected);
+        assert_eq!(
+            &expected[..8],
+            [8, b's', b'o', b'u', b't', b'h', b'i', b'\n']
+        );
+    }
+}
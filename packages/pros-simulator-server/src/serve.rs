@@ -0,0 +1,193 @@
+//! `serve`, which runs a separate simulation for every frontend that connects, for classrooms and
+//! web services where many people share one server. Each session gets its own thread and its own
+//! simulator, so robot code that never yields only holds up its own session, and the resource
+//! limits in [`SimulationArgs`](crate::SimulationArgs) keep one session's robot code from
+//! starving the rest.
+//!
+//! A connection's first event is a [`SimulatorEvent::SessionStarted`] giving its session's ID,
+//! which the server's log on stderr uses too. A session ends when its frontend disconnects,
+//! even if its robot code is stuck in a loop. Frontends are accepted on their own threads, so
+//! one that is slow to send its handshake doesn't hold up the others.
+
+use std::{
+    fs, io,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+};
+
+use jsonl::write;
+use pros_simulator::SimulatorOptions;
+use pros_simulator_interface::SimulatorEvent;
+
+use crate::{
+    report::describe_failure,
+    slots::{EventSink, Slots},
+    transport::{spawn_reader, Closing, Connection, EventWriter, Pending, Transport},
+    upload::Uploads,
+};
+
+/// What every session starts with.
+pub struct Sessions {
+    /// The robot code each session runs first. Without it, sessions wait for an upload.
+    pub robot_code: Option<PathBuf>,
+    /// The options each simulation runs with.
+    pub options: Box<dyn Fn() -> SimulatorOptions + Send + Sync>,
+    /// Where uploaded programs are saved, in a directory per session that's removed once the
+    /// session ends.
+    pub upload_dir: Option<PathBuf>,
+    /// The most sessions that can run at once. Frontends connecting while this many are running
+    /// are turned away.
+    pub max_sessions: usize,
+}
+
+/// Starts a session for every frontend that connects, until waiting for one fails.
+pub fn serve(transport: &dyn Transport, sessions: Sessions) -> io::Result<()> {
+    let server = Arc::new(Server {
+        sessions,
+        running: AtomicUsize::new(0),
+        next_id: AtomicU64::new(1),
+    });
+    loop {
+        let pending = transport.incoming()?;
+        let server = server.clone();
+        thread::Builder::new()
+            .name(format!("frontend {}", pending.peer))
+            .spawn(move || server.accept(pending))?;
+    }
+}
+
+/// What every session's thread shares.
+struct Server {
+    sessions: Sessions,
+    /// How many sessions are running.
+    running: AtomicUsize,
+    next_id: AtomicU64,
+}
+
+impl Server {
+    /// Checks a frontend's handshake, then runs its session on the current thread if there's
+    /// room for one.
+    fn accept(self: Arc<Self>, pending: Pending) {
+        let peer = pending.peer.clone();
+        let connection = match pending.accept() {
+            Ok(Ok(connection)) => connection,
+            Ok(Err(reason)) => return eprintln!("Refused a frontend from {peer}: {reason}"),
+            Err(err) => return eprintln!("Lost a frontend from {peer}: {err}"),
+        };
+
+        let max_sessions = self.sessions.max_sessions;
+        let reserved = self
+            .running
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |running| {
+                (running < max_sessions).then_some(running + 1)
+            });
+        if reserved.is_err() {
+            eprintln!(
+                "Refused a frontend from {peer}: {max_sessions} sessions are already running"
+            );
+            let warning = SimulatorEvent::Warning(
+                "Connection refused: the server is running as many sessions as it can. Try \
+                 again once one has finished."
+                    .into(),
+            );
+            _ = write(connection.writer, &warning);
+            return;
+        }
+
+        let _finished = Finished(self.clone());
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        eprintln!("Session {id} started for {peer}");
+        run_session(id, connection, &self.sessions);
+    }
+}
+
+/// Counts a session as finished when dropped, even if its thread panicked.
+struct Finished(Arc<Server>);
+
+impl Drop for Finished {
+    fn drop(&mut self) {
+        self.0.running.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Removes a session's upload directory when dropped, even if its thread panicked.
+struct UploadDir(PathBuf);
+
+impl Drop for UploadDir {
+    fn drop(&mut self) {
+        _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn run_session(id: u64, connection: Connection, sessions: &Sessions) {
+    // set once the frontend disconnects, which stops the simulation
+    let stop = Arc::new(AtomicBool::new(false));
+    let closing = Closing::StopSession(stop.clone());
+    let output = EventWriter::new(connection.writer, closing.clone());
+    output.write(&SimulatorEvent::SessionStarted { session: id });
+
+    let upload_dir = sessions
+        .upload_dir
+        .as_ref()
+        .map(|dir| dir.join(format!("session-{id}")));
+    let _upload_dir = upload_dir.clone().map(UploadDir);
+    let slots = upload_dir
+        .clone()
+        .map(Uploads::new)
+        .transpose()
+        .map_err(|err| format!("Couldn't create the session's upload directory: {err}"))
+        .and_then(|uploads| Slots::new(sessions.robot_code.as_deref(), &[], uploads));
+    let slots = match slots {
+        Ok(slots) => slots,
+        Err(err) => {
+            eprintln!("Session {id} couldn't start: {err}");
+            output.write(&SimulatorEvent::Warning(err));
+            return;
+        }
+    };
+
+    let sink: EventSink = Arc::new(Mutex::new({
+        let output = output.clone();
+        move |event| output.write(&event)
+    }));
+    let (tx, rx) = mpsc::channel();
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let outcome = runtime.block_on(async {
+        spawn_reader(connection.reader, false, closing, tx);
+        let options = || (sessions.options)().stop_signal(stop.clone());
+        slots.run(options, &sink, rx).await
+    });
+
+    match outcome.and_then(|outcome| describe_failure(&outcome.reason)) {
+        Some(failure) => eprintln!("Session {id} ended: {failure}"),
+        None => eprintln!("Session {id} ended"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upload_dir_removed_on_panic() {
+        let dir = std::env::temp_dir().join(format!(
+            "pros-simulator-server-session-{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("slot_1.wasm"), b"\0asm").unwrap();
+
+        let panicked = std::panic::catch_unwind(|| {
+            let _upload_dir = UploadDir(dir.clone());
+            panic!("session crashed");
+        });
+        assert!(panicked.is_err());
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,312 @@
+//! Program slots, for running several programs in one session with `--slot`. Like the brain's
+//! program selection screen, a [`SimulatorMessage::SelectSlot`] stops the program that's running
+//! and runs the one in the chosen slot. Once a program stops, the session waits for another slot
+//! to be chosen until the frontend disconnects. Programs uploaded with `--upload-dir` are loaded
+//! into their slot and run straight away, and a [`BrainButton::Run`] press runs the last program
+//! again, like the run button in the brain's screen header.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+};
+
+use pros_simulator::{read_program_info, SimulationOutcome, SimulatorOptions};
+use pros_simulator_interface::{BrainButton, ProgramSlot, SimulatorEvent, SimulatorMessage};
+
+use crate::upload::Uploads;
+
+/// The number of program slots on a V5 brain.
+pub const NUM_SLOTS: u8 = 8;
+
+/// Where every event is sent, whichever program is running.
+pub type EventSink = Arc<Mutex<dyn FnMut(SimulatorEvent) + Send>>;
+
+/// Sends events to `sink`, for passing to the simulator.
+pub fn forward(sink: &EventSink) -> impl FnMut(SimulatorEvent) + Send + 'static {
+    let sink = sink.clone();
+    move |event| (sink.lock().unwrap())(event)
+}
+
+/// The programs loaded into each slot.
+pub struct Slots {
+    programs: Arc<Mutex<BTreeMap<u8, ProgramSlot>>>,
+    uploads: Mutex<Option<Uploads>>,
+}
+
+impl Slots {
+    /// Loads `first` into slot 1, if there is one, and the rest into the slots they're given.
+    pub fn new(
+        first: Option<&Path>,
+        others: &[(u8, PathBuf)],
+        uploads: Option<Uploads>,
+    ) -> Result<Self, String> {
+        let mut programs = BTreeMap::new();
+        let first = first.map(|first| (1, first.to_path_buf()));
+        for (slot, path) in first.iter().chain(others) {
+            let wasm = fs::read(path)
+                .map_err(|err| format!("Couldn't read slot {slot}'s robot code: {err}"))?;
+            let name = read_program_info(&wasm).0.name.unwrap_or_else(|| {
+                path.file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned()
+            });
+            let program = ProgramSlot {
+                slot: *slot,
+                name,
+                file: path.display().to_string(),
+            };
+            if programs.insert(*slot, program).is_some() {
+                return Err(format!(
+                    "Slot {slot} was given more than one program. The robot code passed \
+                     without `--slot` goes in slot 1."
+                ));
+            }
+        }
+        Ok(Self {
+            programs: Arc::new(Mutex::new(programs)),
+            uploads: Mutex::new(uploads),
+        })
+    }
+
+    /// Runs the program in slot 1, then whichever slots are chosen, until the frontend
+    /// disconnects. Without a program in slot 1, waits for one to be uploaded first. Returns how
+    /// the last program to run stopped, or `None` if it couldn't be loaded or none ran.
+    pub async fn run(
+        &self,
+        options: impl Fn() -> SimulatorOptions,
+        sink: &EventSink,
+        messages: mpsc::Receiver<SimulatorMessage>,
+    ) -> Option<SimulationOutcome> {
+        send_slots(&self.programs, sink);
+
+        let uploads = self.uploads.lock().unwrap().take();
+        let router = SlotRouter::spawn(messages, self.programs.clone(), uploads, sink);
+        let mut slot = 1;
+        if !self.programs.lock().unwrap().contains_key(&slot) {
+            slot = router.next_slot()?;
+        }
+        loop {
+            (sink.lock().unwrap())(SimulatorEvent::SlotSelected(slot));
+            let path = PathBuf::from(&self.programs.lock().unwrap()[&slot].file);
+            let outcome =
+                pros_simulator::simulate(&path, options(), forward(sink), router.messages(slot))
+                    .await;
+            let outcome = match outcome {
+                Ok(outcome) => Some(outcome),
+                Err(err) => {
+                    (sink.lock().unwrap())(SimulatorEvent::Warning(format!(
+                        "Couldn't run slot {slot}: {err:?}"
+                    )));
+                    None
+                }
+            };
+            match router.next_slot() {
+                Some(next) => slot = next,
+                None => return outcome,
+            }
+        }
+    }
+}
+
+/// Sends a [`SimulatorEvent::ProgramSlots`] listing what's in each slot.
+fn send_slots(programs: &Mutex<BTreeMap<u8, ProgramSlot>>, sink: &EventSink) {
+    let programs = programs.lock().unwrap().values().cloned().collect();
+    (sink.lock().unwrap())(SimulatorEvent::ProgramSlots(programs));
+}
+
+/// Forwards messages to the program that's running, except for slot selections, run button
+/// presses and finished uploads, which stop it.
+struct SlotRouter {
+    /// Where messages for the running program go. Messages sent while no program is running are
+    /// dropped, as the robot isn't listening to the controller then.
+    current: Arc<Mutex<Option<mpsc::Sender<SimulatorMessage>>>>,
+    /// The slot of the program that's running or last ran, which the run button runs again.
+    last_slot: Arc<Mutex<Option<u8>>>,
+    selections: mpsc::Receiver<u8>,
+}
+
+impl SlotRouter {
+    fn spawn(
+        messages: mpsc::Receiver<SimulatorMessage>,
+        programs: Arc<Mutex<BTreeMap<u8, ProgramSlot>>>,
+        mut uploads: Option<Uploads>,
+        sink: &EventSink,
+    ) -> Self {
+        let current = Arc::new(Mutex::new(None::<mpsc::Sender<SimulatorMessage>>));
+        let last_slot = Arc::new(Mutex::new(None));
+        let (selection_tx, selections) = mpsc::channel();
+        thread::spawn({
+            let current = current.clone();
+            let last_slot = last_slot.clone();
+            let sink = sink.clone();
+            move || {
+                for message in messages {
+                    let slot = match message {
+                        SimulatorMessage::SelectSlot(slot) => slot,
+                        SimulatorMessage::BrainButton(BrainButton::Run) => {
+                            // nothing has run yet while waiting for the first upload
+                            let Some(slot) = *last_slot.lock().unwrap() else {
+                                continue;
+                            };
+                            slot
+                        }
+                        SimulatorMessage::UploadProgram(chunk) => {
+                            let slot = chunk.slot;
+                            let Some(uploads) = &mut uploads else {
+                                (sink.lock().unwrap())(SimulatorEvent::Warning(format!(
+                                    "Can't upload a program to slot {slot}, because uploads \
+                                     aren't enabled. Start the server with `--upload-dir` to \
+                                     accept them."
+                                )));
+                                continue;
+                            };
+                            match uploads.receive(chunk) {
+                                Ok(Some(program)) => {
+                                    programs.lock().unwrap().insert(slot, program);
+                                    send_slots(&programs, &sink);
+                                    slot
+                                }
+                                Ok(None) => continue,
+                                Err(err) => {
+                                    (sink.lock().unwrap())(SimulatorEvent::Warning(format!(
+                                        "Couldn't upload a program to slot {slot}: {err}"
+                                    )));
+                                    continue;
+                                }
+                            }
+                        }
+                        message => {
+                            if let Some(tx) = &*current.lock().unwrap() {
+                                _ = tx.send(message);
+                            }
+                            continue;
+                        }
+                    };
+                    if !programs.lock().unwrap().contains_key(&slot) {
+                        (sink.lock().unwrap())(SimulatorEvent::Warning(format!(
+                            "Can't switch to slot {slot}, because there's no program in it. \
+                             Programs can be loaded into slots 1 to {NUM_SLOTS} with `--slot`."
+                        )));
+                        continue;
+                    }
+                    if let Some(tx) = current.lock().unwrap().take() {
+                        _ = tx.send(SimulatorMessage::Stop);
+                    }
+                    _ = selection_tx.send(slot);
+                }
+                // like disconnecting without slots, the running program is left to finish
+                current.lock().unwrap().take();
+            }
+        });
+        Self {
+            current,
+            last_slot,
+            selections,
+        }
+    }
+
+    /// A channel of messages for the program in `slot`, which is about to run.
+    fn messages(&self, slot: u8) -> mpsc::Receiver<SimulatorMessage> {
+        let (tx, rx) = mpsc::channel();
+        *self.current.lock().unwrap() = Some(tx);
+        *self.last_slot.lock().unwrap() = Some(slot);
+        rx
+    }
+
+    /// Waits for a slot to be chosen, returning `None` once the frontend disconnects. If several
+    /// were chosen while the last program was stopping, the last one wins.
+    fn next_slot(&self) -> Option<u8> {
+        self.current.lock().unwrap().take();
+        let queued = self.selections.try_iter().last();
+        queued.or_else(|| self.selections.recv().ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pros_simulator::StopReason;
+
+    use super::*;
+
+    /// Builds a program that exits with `code` as soon as it starts.
+    fn program(name: &str, code: i32) -> PathBuf {
+        let wasm = wat::parse_str(format!(
+            r#"(module
+                (import "env" "memory" (memory 18 16384 shared))
+                (import "env" "exit" (func $exit (param i32)))
+                (table (export "__indirect_function_table") 8 funcref)
+                (func (export "initialize") (call $exit (i32.const {code})))
+                (func (export "wasm_memalign") (param i32 i32) (result i32) (i32.const 65536))
+                (func (export "wasm_free") (param i32)))"#
+        ))
+        .unwrap();
+        let path = std::env::temp_dir().join(format!(
+            "pros-simulator-server-slots-{}-{name}.wasm",
+            std::process::id()
+        ));
+        fs::write(&path, wasm).unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn select_slot() {
+        let first = program("first", 1);
+        let second = program("second", 2);
+        let slots = Slots::new(Some(&first), &[(2, second.clone())], None).unwrap();
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink: EventSink = Arc::new(Mutex::new({
+            let events = events.clone();
+            move |event| events.lock().unwrap().push(event)
+        }));
+        let (tx, rx) = mpsc::channel();
+        tx.send(SimulatorMessage::SelectSlot(5)).unwrap();
+        tx.send(SimulatorMessage::SelectSlot(2)).unwrap();
+        // the frontend disconnects once the second program has run
+        drop(tx);
+        let outcome = slots.run(SimulatorOptions::new, &sink, rx).await.unwrap();
+        assert!(
+            matches!(outcome.reason, StopReason::Exited(2)),
+            "{:?}",
+            outcome.reason
+        );
+
+        let events = events.lock().unwrap();
+        let listed = events.iter().find_map(|event| match event {
+            SimulatorEvent::ProgramSlots(programs) => Some(
+                programs
+                    .iter()
+                    .map(|program| (program.slot, program.file.clone()))
+                    .collect::<Vec<_>>(),
+            ),
+            _ => None,
+        });
+        assert_eq!(
+            listed.unwrap(),
+            [
+                (1, first.display().to_string()),
+                (2, second.display().to_string())
+            ]
+        );
+        let selected = events
+            .iter()
+            .filter_map(|event| match event {
+                SimulatorEvent::SlotSelected(slot) => Some(*slot),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(selected, [1, 2]);
+        // slot 5 is empty, so choosing it doesn't stop anything
+        assert!(events.iter().any(|event| matches!(
+            event,
+            SimulatorEvent::Warning(warning) if warning.starts_with("Can't switch to slot 5")
+        )));
+
+        fs::remove_file(first).unwrap();
+        fs::remove_file(second).unwrap();
+    }
+}
@@ -0,0 +1,852 @@
+//! The connections the server talks to a frontend over. Each [`Transport`] only has to set up its
+//! connections; reading messages from them and writing events to them is shared.
+//!
+//! Every transport carries the same line delimited JSON: events one way, and messages (or text
+//! commands with `--commands`) the other. Over [`WebSocket`], each line is a message of its own.
+//! Network transports can also require a [`Handshake`] before a frontend is accepted; see
+//! [`Access`].
+
+use std::{
+    io::{self, stdin, stdout, BufRead, BufReader, Read, Write},
+    net::{IpAddr, TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    process::exit,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use jsonl::{read, write, ReadError};
+use pros_simulator_interface::{Handshake, SimulatorEvent, SimulatorMessage};
+use serde::Serialize;
+
+use crate::{
+    commands::{self, Commands},
+    websocket::{self, Upgrade},
+};
+
+/// A connection to a frontend, split into the side messages are read from and the side events
+/// are written to.
+pub struct Connection {
+    pub reader: Box<dyn BufRead + Send>,
+    pub writer: Box<dyn Write + Send>,
+}
+
+/// A frontend that has connected but hasn't been accepted yet, since waiting for its
+/// [`Handshake`] can take a while.
+pub struct Pending {
+    /// Where the frontend connected from, for logs.
+    pub peer: String,
+    accept: Box<dyn FnOnce() -> io::Result<Result<Connection, &'static str>> + Send>,
+}
+
+impl Pending {
+    /// Checks the frontend's handshake if its transport requires one, returning why the
+    /// frontend was refused if it was.
+    pub fn accept(self) -> io::Result<Result<Connection, &'static str>> {
+        (self.accept)()
+    }
+}
+
+/// A way for frontends to connect to the server.
+pub trait Transport: Send + Sync {
+    /// Waits for the next frontend to connect, without accepting it yet.
+    fn incoming(&self) -> io::Result<Pending>;
+
+    /// Waits for the next frontend to connect and be accepted.
+    fn connect(&self) -> io::Result<Connection> {
+        loop {
+            let pending = self.incoming()?;
+            let peer = pending.peer.clone();
+            match pending.accept() {
+                Ok(Ok(connection)) => return Ok(connection),
+                Ok(Err(reason)) => eprintln!("Refused a frontend from {peer}: {reason}"),
+                Err(err) => eprintln!("Lost a frontend from {peer}: {err}"),
+            }
+        }
+    }
+}
+
+/// The server's own stdin and stdout, which are always connected.
+pub struct Stdio;
+
+impl Transport for Stdio {
+    fn incoming(&self) -> io::Result<Pending> {
+        Ok(Pending {
+            peer: "stdio".into(),
+            accept: Box::new(|| {
+                Ok(Ok(Connection {
+                    reader: Box::new(BufReader::new(stdin())),
+                    writer: Box::new(stdout()),
+                }))
+            }),
+        })
+    }
+}
+
+/// How long a frontend has to send its whole [`Handshake`] after connecting.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The longest line a frontend can send as its [`Handshake`], so it can't make the server
+/// buffer an endless line.
+const MAX_HANDSHAKE_LEN: u64 = 4096;
+
+/// The longest request a WebSocket frontend can open its connection with.
+const MAX_UPGRADE_LEN: u64 = 8192;
+
+/// Which frontends a network transport accepts, so a simulator reachable from a LAN can't be
+/// driven by anyone on it. Frontends that aren't accepted are disconnected, and the transport
+/// waits for another one.
+#[derive(Debug, Clone, Default)]
+pub struct Access {
+    /// A token frontends have to send in a [`Handshake`] before anything else.
+    pub token: Option<String>,
+    /// The addresses TCP and WebSocket frontends can connect from. Any address can if it's
+    /// empty.
+    pub allowed_ips: Vec<IpAddr>,
+    /// The web pages that can open a WebSocket to the server, like `https://example.com`, as
+    /// well as pages served from this computer. Pages elsewhere are refused even if it's empty.
+    ///
+    /// Browsers always say which page opened a WebSocket, so this stops other sites a user
+    /// visits from driving a simulator on their network. Frontends that aren't browsers don't
+    /// have to send an origin and can claim any, so they're kept out with a token instead.
+    pub allowed_origins: Vec<String>,
+}
+
+/// Whether a normalized origin like `http://localhost:3000` is a page served from this computer.
+fn is_local_origin(origin: &str) -> bool {
+    let Some((_, host)) = origin.split_once("://") else {
+        return false;
+    };
+    let host = match host.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next().unwrap_or_default(),
+        None => host.split(':').next().unwrap_or_default(),
+    };
+    host == "localhost"
+        || host
+            .parse::<IpAddr>()
+            .is_ok_and(|ip| ip.to_canonical().is_loopback())
+}
+
+impl Access {
+    fn allows_ip(&self, ip: IpAddr) -> bool {
+        self.allowed_ips.is_empty() || self.allowed_ips.contains(&ip.to_canonical())
+    }
+
+    fn allows_origin(&self, origin: Option<&str>) -> bool {
+        let Some(origin) = origin else {
+            return true;
+        };
+        let normalize = |origin: &str| origin.trim_end_matches('/').to_ascii_lowercase();
+        let origin = normalize(origin);
+        is_local_origin(&origin)
+            || self
+                .allowed_origins
+                .iter()
+                .any(|allowed| normalize(allowed) == origin)
+    }
+
+    /// Sets up a connection over a stream, after checking the frontend's handshake if a token
+    /// is required. Returns why the frontend was refused if it was.
+    fn accept<S: Stream>(&self, stream: S) -> io::Result<Result<Connection, &'static str>> {
+        self.accept_within(stream, HANDSHAKE_TIMEOUT)
+    }
+
+    /// Like [`accept`](Self::accept), but with a custom time limit for the handshake.
+    fn accept_within<S: Stream>(
+        &self,
+        mut stream: S,
+        timeout: Duration,
+    ) -> io::Result<Result<Connection, &'static str>> {
+        if let Some(token) = &self.token {
+            let accepted = match read_handshake(&mut stream, Instant::now() + timeout) {
+                Ok(handshake) if tokens_match(&handshake.token, token) => Ok(()),
+                Ok(_) => Err("wrong token"),
+                Err(reason) => Err(reason),
+            };
+            if let Err(reason) = accepted {
+                let warning = SimulatorEvent::Warning(format!("Connection refused: {reason}"));
+                _ = write(&mut stream, &warning);
+                return Ok(Err(reason));
+            }
+            stream.set_read_timeout(None)?;
+        }
+        Ok(Ok(Connection {
+            reader: Box::new(BufReader::new(stream.try_clone()?)),
+            writer: Box::new(stream),
+        }))
+    }
+
+    /// Opens a WebSocket over a stream, then accepts it like [`accept`](Self::accept). The
+    /// upgrade request and the handshake both have to arrive within [`HANDSHAKE_TIMEOUT`].
+    fn accept_websocket<S: Stream>(
+        &self,
+        stream: S,
+    ) -> io::Result<Result<Connection, &'static str>> {
+        self.accept_websocket_within(stream, HANDSHAKE_TIMEOUT)
+    }
+
+    /// Like [`accept_websocket`](Self::accept_websocket), but with a custom time limit.
+    fn accept_websocket_within<S: Stream>(
+        &self,
+        mut stream: S,
+        timeout: Duration,
+    ) -> io::Result<Result<Connection, &'static str>> {
+        let deadline = Instant::now() + timeout;
+        let upgrade = match read_upgrade(&mut stream, deadline) {
+            Ok(upgrade) => upgrade,
+            Err(reason) => {
+                _ = stream.write_all(websocket::BAD_REQUEST);
+                return Ok(Err(reason));
+            }
+        };
+        if !self.allows_origin(upgrade.origin.as_deref()) {
+            _ = stream.write_all(websocket::FORBIDDEN);
+            return Ok(Err("its origin isn't allowed"));
+        }
+        stream.write_all(upgrade.response().as_bytes())?;
+        stream.set_read_timeout(None)?;
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        self.accept_within(websocket::WebSocket::new(stream), remaining)
+    }
+}
+
+/// Why a line couldn't be read from a frontend that's being accepted.
+enum LineError {
+    TimedOut,
+    TooLong,
+    Disconnected,
+}
+
+/// Reads a line a byte at a time, so nothing sent after it is consumed. The whole line has to
+/// arrive before `deadline`, however slowly the frontend sends it, and can't be longer than
+/// `max_len`. The line ending isn't included.
+fn read_line<S: Stream>(
+    stream: &mut S,
+    deadline: Instant,
+    max_len: u64,
+) -> Result<Vec<u8>, LineError> {
+    let mut reader = stream.take(max_len);
+    let mut line = Vec::new();
+    let mut byte = [0];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(LineError::TimedOut);
+        }
+        if reader.get_ref().set_read_timeout(Some(remaining)).is_err() {
+            return Err(LineError::Disconnected);
+        }
+        match reader.read(&mut byte) {
+            Ok(0) if reader.limit() == 0 => return Err(LineError::TooLong),
+            Ok(0) => return Err(LineError::Disconnected),
+            Ok(_) if byte[0] == b'\n' => return Ok(line),
+            Ok(_) => line.push(byte[0]),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                return Err(LineError::TimedOut)
+            }
+            Err(_) => return Err(LineError::Disconnected),
+        }
+    }
+}
+
+/// Reads a frontend's handshake line, which has to arrive before `deadline` and can't be longer
+/// than [`MAX_HANDSHAKE_LEN`].
+fn read_handshake<S: Stream>(stream: &mut S, deadline: Instant) -> Result<Handshake, &'static str> {
+    let line = read_line(stream, deadline, MAX_HANDSHAKE_LEN).map_err(|err| match err {
+        LineError::TimedOut => "no handshake in time",
+        LineError::TooLong => "handshake too long",
+        LineError::Disconnected => "disconnected before its handshake",
+    })?;
+    serde_json::from_slice(&line).map_err(|_| "no valid handshake")
+}
+
+/// Reads the HTTP request a WebSocket frontend opens its connection with, which has to arrive
+/// before `deadline` and can't be longer than [`MAX_UPGRADE_LEN`] in all.
+fn read_upgrade<S: Stream>(stream: &mut S, deadline: Instant) -> Result<Upgrade, &'static str> {
+    let mut lines = Vec::new();
+    let mut len = 0;
+    loop {
+        let line = read_line(stream, deadline, MAX_UPGRADE_LEN - len).map_err(|err| match err {
+            LineError::TimedOut => "no WebSocket request in time",
+            LineError::TooLong => "WebSocket request too long",
+            LineError::Disconnected => "disconnected before its WebSocket request",
+        })?;
+        len += line.len() as u64 + 1;
+        let line = String::from_utf8(line).map_err(|_| "not a WebSocket request")?;
+        let line = line.strip_suffix('\r').unwrap_or(&line);
+        if line.is_empty() {
+            break;
+        }
+        // a frontend that isn't opening a WebSocket is refused without waiting for the rest
+        if lines.is_empty() && !line.starts_with("GET ") {
+            return Err("not a WebSocket request");
+        }
+        lines.push(line.to_string());
+    }
+    Upgrade::parse(&lines)
+}
+
+/// Compares tokens in constant time, so a frontend can't guess one a byte at a time.
+fn tokens_match(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// A socket a network transport accepted.
+trait Stream: Read + Write + Send + Sized + 'static {
+    fn try_clone(&self) -> io::Result<Self>;
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+}
+
+impl Stream for TcpStream {
+    fn try_clone(&self) -> io::Result<Self> {
+        TcpStream::try_clone(self)
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+}
+
+impl<S: Stream> Stream for websocket::WebSocket<S> {
+    fn try_clone(&self) -> io::Result<Self> {
+        Ok(self.with_stream(self.get_ref().try_clone()?))
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.get_ref().set_read_timeout(timeout)
+    }
+}
+
+#[cfg(unix)]
+impl Stream for std::os::unix::net::UnixStream {
+    fn try_clone(&self) -> io::Result<Self> {
+        std::os::unix::net::UnixStream::try_clone(self)
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        std::os::unix::net::UnixStream::set_read_timeout(self, timeout)
+    }
+}
+
+/// Accepted frontends connecting to a TCP address.
+pub struct Tcp {
+    listener: TcpListener,
+    access: Access,
+}
+
+impl Tcp {
+    pub fn bind(addr: &str, access: Access) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        eprintln!("Waiting for frontends on {}", listener.local_addr()?);
+        Ok(Self { listener, access })
+    }
+}
+
+impl Transport for Tcp {
+    fn incoming(&self) -> io::Result<Pending> {
+        let (stream, peer) = accept_tcp(&self.listener, &self.access)?;
+        let access = self.access.clone();
+        Ok(Pending {
+            peer,
+            accept: Box::new(move || access.accept(stream)),
+        })
+    }
+}
+
+/// Waits for a TCP frontend from an allowed address, returning its socket and where it
+/// connected from.
+fn accept_tcp(listener: &TcpListener, access: &Access) -> io::Result<(TcpStream, String)> {
+    loop {
+        let (stream, peer) = listener.accept()?;
+        if !access.allows_ip(peer.ip()) {
+            eprintln!("Refused a frontend from {peer}: its address isn't allowed");
+            continue;
+        }
+        if let Err(err) = stream.set_nodelay(true) {
+            eprintln!("Lost a frontend from {peer}: {err}");
+            continue;
+        }
+        return Ok((stream, peer.to_string()));
+    }
+}
+
+/// Accepted frontends opening a WebSocket to a TCP address, like a web page. Frontends connect
+/// to `ws://ADDR/`, with any path.
+pub struct WebSocket {
+    listener: TcpListener,
+    access: Access,
+}
+
+impl WebSocket {
+    pub fn bind(addr: &str, access: Access) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        eprintln!("Waiting for frontends on ws://{}", listener.local_addr()?);
+        Ok(Self { listener, access })
+    }
+}
+
+impl Transport for WebSocket {
+    fn incoming(&self) -> io::Result<Pending> {
+        let (stream, peer) = accept_tcp(&self.listener, &self.access)?;
+        let access = self.access.clone();
+        Ok(Pending {
+            peer,
+            accept: Box::new(move || access.accept_websocket(stream)),
+        })
+    }
+}
+
+/// Accepted frontends connecting to a Unix domain socket, which is created at the path and
+/// removed again when the transport is dropped. Only the token of its [`Access`] is checked.
+#[cfg(unix)]
+pub struct Unix {
+    listener: std::os::unix::net::UnixListener,
+    path: PathBuf,
+    access: Access,
+}
+
+#[cfg(unix)]
+impl Unix {
+    pub fn bind(path: &Path, access: Access) -> io::Result<Self> {
+        use std::os::unix::fs::FileTypeExt;
+
+        // a socket left behind by a server that exited without cleaning up would stop binding
+        if std::fs::metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+            std::fs::remove_file(path)?;
+        }
+        let listener = std::os::unix::net::UnixListener::bind(path)?;
+        eprintln!("Waiting for frontends on {}", path.display());
+        Ok(Self {
+            listener,
+            path: path.to_path_buf(),
+            access,
+        })
+    }
+}
+
+#[cfg(unix)]
+impl Transport for Unix {
+    fn incoming(&self) -> io::Result<Pending> {
+        let (stream, _) = self.listener.accept()?;
+        let access = self.access.clone();
+        Ok(Pending {
+            peer: self.path.display().to_string(),
+            accept: Box::new(move || access.accept(stream)),
+        })
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Unix {
+    fn drop(&mut self) {
+        _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Where `--listen` says to wait for a frontend, e.g. `tcp:127.0.0.1:5250`,
+/// `ws:127.0.0.1:5250` or `unix:/tmp/sim.sock`.
+#[derive(Debug, Clone)]
+pub enum Endpoint {
+    Tcp(String),
+    WebSocket(String),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl Endpoint {
+    /// Starts listening for frontends.
+    pub fn bind(&self, access: Access) -> io::Result<Box<dyn Transport>> {
+        Ok(match self {
+            Self::Tcp(addr) => Box::new(Tcp::bind(addr, access)?),
+            Self::WebSocket(addr) => Box::new(WebSocket::bind(addr, access)?),
+            #[cfg(unix)]
+            Self::Unix(path) => Box::new(Unix::bind(path, access)?),
+        })
+    }
+}
+
+impl FromStr for Endpoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some(("tcp", addr)) => Ok(Self::Tcp(addr.to_string())),
+            Some(("ws", addr)) => Ok(Self::WebSocket(addr.to_string())),
+            #[cfg(unix)]
+            Some(("unix", path)) => Ok(Self::Unix(PathBuf::from(path))),
+            #[cfg(not(unix))]
+            Some(("unix", _)) => {
+                Err("Unix domain sockets aren't supported on this platform".into())
+            }
+            _ => Err("expected tcp:ADDR, ws:ADDR or unix:PATH, e.g. `tcp:127.0.0.1:5250`".into()),
+        }
+    }
+}
+
+/// What happens when a frontend disconnects, or its connection breaks.
+#[derive(Debug, Clone)]
+pub enum Closing {
+    /// The server exits if the connection breaks. If the frontend just stops sending messages,
+    /// the running program is left to finish.
+    ExitServer,
+    /// The session's simulation is stopped by setting its
+    /// [stop signal](pros_simulator::SimulatorOptions::stop_signal), even if its robot code never
+    /// yields, and the rest of the server carries on.
+    StopSession(Arc<AtomicBool>),
+}
+
+/// The side of a connection events are written to, shared by everything that writes them.
+#[derive(Clone)]
+pub struct EventWriter {
+    /// `None` once writing has failed in a session, after which events are dropped.
+    writer: Arc<Mutex<Option<Box<dyn Write + Send>>>>,
+    closing: Closing,
+}
+
+impl EventWriter {
+    pub fn new(writer: Box<dyn Write + Send>, closing: Closing) -> Self {
+        Self {
+            writer: Arc::new(Mutex::new(Some(writer))),
+            closing,
+        }
+    }
+
+    /// Writes a line of JSON. If the frontend has gone away in a session, its simulation is
+    /// stopped.
+    pub fn write(&self, value: &impl Serialize) {
+        let mut writer = self.writer.lock().unwrap();
+        let Some(output) = &mut *writer else {
+            return;
+        };
+        if let Err(err) = write(output, value) {
+            match &self.closing {
+                Closing::ExitServer => {
+                    eprintln!("Error writing events: {err}");
+                    exit(1);
+                }
+                Closing::StopSession(stop) => stop.store(true, Ordering::Release),
+            }
+            *writer = None;
+        }
+    }
+}
+
+/// Forwards messages read from a connection to the simulator, translating text commands into
+/// messages if `commands` is set. Lines that look like JSON are always forwarded as they are.
+pub fn spawn_reader(
+    mut reader: Box<dyn BufRead + Send>,
+    commands: bool,
+    closing: Closing,
+    tx: mpsc::Sender<SimulatorMessage>,
+) {
+    tokio::task::spawn_blocking(move || {
+        let result = if commands {
+            read_commands(reader, &tx)
+        } else {
+            loop {
+                match read(&mut reader) {
+                    Ok(message) => _ = tx.send(message),
+                    Err(ReadError::Eof) => break Ok(()),
+                    Err(err) => break Err(err.to_string()),
+                }
+            }
+        };
+        match closing {
+            Closing::ExitServer => {
+                if let Err(err) = result {
+                    eprintln!("Error reading messages: {err}");
+                    exit(1);
+                }
+            }
+            Closing::StopSession(stop) => stop.store(true, Ordering::Release),
+        }
+    });
+}
+
+fn read_commands(
+    reader: Box<dyn BufRead + Send>,
+    tx: &mpsc::Sender<SimulatorMessage>,
+) -> Result<(), String> {
+    let mut commands = Commands::default();
+    for line in reader.lines() {
+        let line = line.map_err(|err| err.to_string())?;
+        let messages = if line.trim_start().starts_with(['{', '"']) {
+            serde_json::from_str(&line)
+                .map(|message| vec![message])
+                .map_err(|err| format!("Invalid message: {err}"))
+        } else if line.trim() == "help" {
+            eprintln!("{}", commands::HELP);
+            continue;
+        } else {
+            commands.parse(&line)
+        };
+        match messages {
+            Ok(messages) => {
+                for message in messages {
+                    _ = tx.send(message);
+                }
+            }
+            Err(err) => eprintln!("{err}. Type `help` for a list of commands."),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::Ipv6Addr, thread};
+
+    use super::*;
+
+    fn access(token: &str) -> Access {
+        Access {
+            token: Some(token.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Accepts a frontend that sends `handshake` (and nothing else) over a Unix socket pair,
+    /// returning why it was refused, if it was, and what the server wrote back.
+    #[cfg(unix)]
+    fn refusal(handshake: &[u8]) -> (Option<&'static str>, String) {
+        use std::os::unix::net::UnixStream;
+
+        let (server, mut frontend) = UnixStream::pair().unwrap();
+        frontend.write_all(handshake).unwrap();
+        frontend.shutdown(std::net::Shutdown::Write).unwrap();
+        let accepted = access("hunter2").accept(server).unwrap();
+        // the connection is reset instead of closed if the server didn't read everything
+        let mut reply = String::new();
+        _ = frontend.read_to_string(&mut reply);
+        (accepted.err(), reply)
+    }
+
+    #[test]
+    fn tokens_match_only_when_equal() {
+        assert!(tokens_match("hunter2", "hunter2"));
+        assert!(tokens_match("", ""));
+        assert!(!tokens_match("hunter2", "hunter3"));
+        assert!(!tokens_match("hunter2", "hunter"));
+        assert!(!tokens_match("", "hunter2"));
+    }
+
+    #[test]
+    fn allowed_ips() {
+        let localhost = IpAddr::from([127, 0, 0, 1]);
+        let lan = IpAddr::from([192, 168, 1, 20]);
+        assert!(Access::default().allows_ip(lan));
+
+        let access = Access {
+            allowed_ips: vec![localhost],
+            ..Default::default()
+        };
+        assert!(access.allows_ip(localhost));
+        assert!(!access.allows_ip(lan));
+        // IPv4 clients of a dual stack listener show up as mapped IPv6 addresses
+        let mapped = IpAddr::V6(Ipv6Addr::from([0, 0, 0, 0, 0, 0xffff, 0x7f00, 1]));
+        assert!(access.allows_ip(mapped));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn refused_handshakes() {
+        let (reason, reply) = refusal(b"{\"token\":\"hunter3\"}\n");
+        assert_eq!(reason, Some("wrong token"));
+        assert!(reply.contains("Connection refused: wrong token"), "{reply}");
+
+        assert_eq!(refusal(b"").0, Some("disconnected before its handshake"));
+        assert_eq!(refusal(b"hello\n").0, Some("no valid handshake"));
+
+        // an endless line is cut off instead of buffered
+        let long = vec![b'x'; MAX_HANDSHAKE_LEN as usize * 2];
+        assert_eq!(refusal(&long).0, Some("handshake too long"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn handshake_deadline() {
+        use std::os::unix::net::UnixStream;
+
+        // a frontend trickling bytes can't keep the connection open past the deadline
+        let (server, mut frontend) = UnixStream::pair().unwrap();
+        thread::spawn(move || {
+            while frontend.write_all(b" ").is_ok() {
+                thread::sleep(Duration::from_millis(10));
+            }
+        });
+        let started = Instant::now();
+        let accepted = access("hunter2")
+            .accept_within(server, Duration::from_millis(200))
+            .unwrap();
+        assert_eq!(accepted.err(), Some("no handshake in time"));
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    /// A masked text frame, as a WebSocket frontend sends it.
+    fn text_frame(text: &str) -> Vec<u8> {
+        let mask = [1, 2, 3, 4];
+        let mut frame = vec![0x81, 0x80 | text.len() as u8];
+        frame.extend_from_slice(&mask);
+        frame.extend(text.bytes().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+        frame
+    }
+
+    const UPGRADE: &str = "GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\n\
+                           Connection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+                           Sec-WebSocket-Version: 13\r\n\r\n";
+
+    #[cfg(unix)]
+    #[test]
+    fn websocket() {
+        use std::os::unix::net::UnixStream;
+
+        let (server, mut frontend) = UnixStream::pair().unwrap();
+        frontend.write_all(UPGRADE.as_bytes()).unwrap();
+        frontend
+            .write_all(&text_frame("{\"token\":\"hunter2\"}"))
+            .unwrap();
+        frontend.write_all(&text_frame("\"Stop\"")).unwrap();
+        let mut connection = access("hunter2").accept_websocket(server).unwrap().unwrap();
+        let message = read::<_, SimulatorMessage>(&mut connection.reader).unwrap();
+        assert_eq!(message, SimulatorMessage::Stop);
+
+        write(&mut connection.writer, &SimulatorEvent::RobotCodeStarting).unwrap();
+        drop(connection);
+        let mut reply = Vec::new();
+        frontend.read_to_end(&mut reply).unwrap();
+        let text = String::from_utf8_lossy(&reply);
+        assert!(
+            text.starts_with("HTTP/1.1 101 Switching Protocols\r\n"),
+            "{text}"
+        );
+        assert!(reply.ends_with(b"\x81\x13\"RobotCodeStarting\""), "{text}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn refused_websockets() {
+        use std::os::unix::net::UnixStream;
+
+        let refusal = |request: &str| {
+            let (server, mut frontend) = UnixStream::pair().unwrap();
+            frontend.write_all(request.as_bytes()).unwrap();
+            frontend.shutdown(std::net::Shutdown::Write).unwrap();
+            let accepted = Access::default().accept_websocket(server).unwrap();
+            let mut reply = String::new();
+            _ = frontend.read_to_string(&mut reply);
+            (accepted.err(), reply)
+        };
+
+        let (reason, reply) = refusal("GET / HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        assert_eq!(reason, Some("not a WebSocket request"));
+        assert!(reply.starts_with("HTTP/1.1 400 Bad Request"), "{reply}");
+        // a plain TCP frontend sending JSON instead
+        assert_eq!(refusal("\"Stop\"\n").0, Some("not a WebSocket request"));
+        assert_eq!(
+            refusal("GET / HTTP/1.1\r\n").0,
+            Some("disconnected before its WebSocket request")
+        );
+        let long = format!("GET / HTTP/1.1\r\nX: {}\r\n", "x".repeat(10_000));
+        assert_eq!(refusal(&long).0, Some("WebSocket request too long"));
+    }
+
+    #[test]
+    fn commands_and_messages() {
+        let input = "press a\n\"Stop\"\nnonsense\nphase disabled\n";
+        let (tx, rx) = mpsc::channel();
+        read_commands(Box::new(io::Cursor::new(input)), &tx).unwrap();
+        drop(tx);
+        let messages = rx.iter().collect::<Vec<_>>();
+        // JSON messages can be mixed in, and invalid commands are skipped
+        assert!(
+            matches!(
+                messages[..],
+                [
+                    SimulatorMessage::ControllerUpdate(Some(_), None),
+                    SimulatorMessage::Stop,
+                    SimulatorMessage::PhaseChange(_),
+                ]
+            ),
+            "{messages:?}"
+        );
+    }
+
+    #[test]
+    fn allowed_origins() {
+        assert!(!Access::default().allows_origin(Some("https://evil.example")));
+        assert!(Access::default().allows_origin(Some("http://localhost:3000")));
+        assert!(Access::default().allows_origin(Some("http://127.0.0.1:8080")));
+        assert!(Access::default().allows_origin(Some("http://[::1]:8080")));
+        assert!(!Access::default().allows_origin(Some("http://localhost.evil.example")));
+        assert!(!Access::default().allows_origin(Some("null")));
+
+        let access = Access {
+            allowed_origins: vec!["https://example.com".into()],
+            ..Default::default()
+        };
+        assert!(access.allows_origin(Some("https://example.com")));
+        assert!(access.allows_origin(Some("HTTPS://Example.com/")));
+        assert!(!access.allows_origin(Some("https://evil.example")));
+        assert!(!access.allows_origin(Some("http://example.com")));
+        // only browsers send an origin
+        assert!(access.allows_origin(None));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn refused_origin() {
+        use std::os::unix::net::UnixStream;
+
+        let access = Access {
+            allowed_origins: vec!["https://example.com".into()],
+            ..Default::default()
+        };
+        let connect = |origin: &str| {
+            let (server, mut frontend) = UnixStream::pair().unwrap();
+            let request = UPGRADE.replace("\r\n\r\n", &format!("\r\nOrigin: {origin}\r\n\r\n"));
+            frontend.write_all(request.as_bytes()).unwrap();
+            frontend.shutdown(std::net::Shutdown::Write).unwrap();
+            let reason = access.accept_websocket(server).unwrap().err();
+            let mut reply = Vec::new();
+            _ = frontend.read_to_end(&mut reply);
+            (reason, String::from_utf8_lossy(&reply).into_owned())
+        };
+
+        let (reason, reply) = connect("https://evil.example");
+        assert_eq!(reason, Some("its origin isn't allowed"));
+        assert!(reply.starts_with("HTTP/1.1 403 Forbidden"), "{reply}");
+
+        let (reason, reply) = connect("https://example.com");
+        assert_eq!(reason, None);
+        assert!(reply.starts_with("HTTP/1.1 101"), "{reply}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn accepted_handshake() {
+        use std::os::unix::net::UnixStream;
+
+        let (server, mut frontend) = UnixStream::pair().unwrap();
+        frontend
+            .write_all(b"{\"token\":\"hunter2\"}\n\"Stop\"\n")
+            .unwrap();
+        let mut connection = access("hunter2").accept(server).unwrap().unwrap();
+        // the message after the handshake is left for the connection to read
+        let message = read::<_, SimulatorMessage>(&mut connection.reader).unwrap();
+        assert_eq!(message, SimulatorMessage::Stop);
+    }
+}
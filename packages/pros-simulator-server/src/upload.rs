@@ -0,0 +1,188 @@
+//! Programs uploaded by a frontend with [`SimulatorMessage::UploadProgram`], for `--upload-dir`.
+//! Each program is saved to the directory once all of it has arrived, and then loaded into its
+//! slot like one passed with `--slot`.
+//!
+//! [`SimulatorMessage::UploadProgram`]: pros_simulator_interface::SimulatorMessage::UploadProgram
+
+use std::{fs, io, path::PathBuf};
+
+use pros_simulator::read_program_info;
+use pros_simulator_interface::{ProgramChunk, ProgramSlot};
+
+use crate::slots::NUM_SLOTS;
+
+/// The biggest program that can be uploaded, in bytes.
+const MAX_PROGRAM_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Where uploaded programs are saved, and the one being uploaded.
+pub struct Uploads {
+    dir: PathBuf,
+    pending: Option<PendingUpload>,
+}
+
+struct PendingUpload {
+    slot: u8,
+    name: String,
+    len: u64,
+    bytes: Vec<u8>,
+}
+
+impl Uploads {
+    /// Saves uploaded programs to `dir`, creating it if it doesn't exist.
+    pub fn new(dir: PathBuf) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir, pending: None })
+    }
+
+    /// Adds a chunk to the upload it's part of, returning the uploaded program once every chunk
+    /// has arrived. An upload is abandoned if one of its chunks is invalid or out of order.
+    pub fn receive(&mut self, chunk: ProgramChunk) -> Result<Option<ProgramSlot>, String> {
+        let pending = self.pending.take();
+        if !(1..=NUM_SLOTS).contains(&chunk.slot) {
+            return Err(format!("there's no slot {}", chunk.slot));
+        }
+        if chunk.len > MAX_PROGRAM_SIZE {
+            return Err(format!(
+                "the program is {} bytes, but at most {MAX_PROGRAM_SIZE} can be uploaded",
+                chunk.len
+            ));
+        }
+        let bytes = chunk.bytes().ok_or("a chunk's data isn't valid base64")?;
+
+        let mut pending = match pending {
+            _ if chunk.offset == 0 => PendingUpload {
+                slot: chunk.slot,
+                name: chunk.name,
+                len: chunk.len,
+                bytes: Vec::new(),
+            },
+            Some(pending)
+                if pending.slot == chunk.slot
+                    && pending.len == chunk.len
+                    && pending.bytes.len() as u64 == chunk.offset =>
+            {
+                pending
+            }
+            _ => {
+                return Err(format!(
+                    "got a chunk at offset {} that doesn't follow the last one. Uploads have to \
+                     be sent in order, starting at offset 0.",
+                    chunk.offset
+                ))
+            }
+        };
+        pending.bytes.extend(bytes);
+        if (pending.bytes.len() as u64) < pending.len {
+            self.pending = Some(pending);
+            return Ok(None);
+        }
+        if pending.bytes.len() as u64 > pending.len {
+            return Err(format!(
+                "got more than the {} bytes the program was said to be",
+                pending.len
+            ));
+        }
+
+        let path = self.dir.join(format!("slot_{}.wasm", pending.slot));
+        fs::write(&path, &pending.bytes)
+            .map_err(|err| format!("couldn't save it to {}: {err}", path.display()))?;
+        Ok(Some(ProgramSlot {
+            slot: pending.slot,
+            name: read_program_info(&pending.bytes)
+                .0
+                .name
+                .unwrap_or(pending.name),
+            file: path.display().to_string(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An empty WebAssembly module.
+    const PROGRAM: &[u8] = b"\0asm\x01\0\0\0";
+
+    fn uploads(test: &str) -> Uploads {
+        let dir = std::env::temp_dir().join(format!(
+            "pros-simulator-server-uploads-{}-{test}",
+            std::process::id()
+        ));
+        Uploads::new(dir).unwrap()
+    }
+
+    #[test]
+    fn complete_upload() {
+        let mut uploads = uploads("complete");
+        let chunks = ProgramChunk::split(3, "robot.wasm", PROGRAM, 3);
+        assert_eq!(chunks.len(), 3);
+
+        let mut results = chunks
+            .into_iter()
+            .map(|chunk| uploads.receive(chunk).unwrap())
+            .collect::<Vec<_>>();
+        let uploaded = results.pop().unwrap().unwrap();
+        assert!(results.iter().all(Option::is_none));
+
+        assert_eq!(uploaded.slot, 3);
+        assert_eq!(uploaded.name, "robot.wasm");
+        let path = PathBuf::from(&uploaded.file);
+        assert_eq!(path, uploads.dir.join("slot_3.wasm"));
+        assert_eq!(fs::read(&path).unwrap(), PROGRAM);
+        fs::remove_dir_all(&uploads.dir).unwrap();
+    }
+
+    #[test]
+    fn out_of_order_chunk() {
+        let mut uploads = uploads("out-of-order");
+        let chunks = ProgramChunk::split(1, "robot.wasm", PROGRAM, 3);
+
+        let err = uploads.receive(chunks[1].clone()).unwrap_err();
+        assert!(err.contains("offset 3"), "{err}");
+
+        assert_eq!(uploads.receive(chunks[0].clone()), Ok(None));
+        assert!(uploads.receive(chunks[2].clone()).is_err());
+        // the upload was abandoned, so it has to start over
+        assert!(uploads.receive(chunks[1].clone()).is_err());
+        assert!(!uploads.dir.join("slot_1.wasm").exists());
+        fs::remove_dir_all(&uploads.dir).unwrap();
+    }
+
+    #[test]
+    fn too_big() {
+        let mut uploads = uploads("too-big");
+        let mut chunk = ProgramChunk::split(1, "robot.wasm", PROGRAM, PROGRAM.len())
+            .pop()
+            .unwrap();
+        chunk.len = MAX_PROGRAM_SIZE + 1;
+        let err = uploads.receive(chunk).unwrap_err();
+        assert!(err.contains("at most 67108864"), "{err}");
+
+        // a chunk can't carry more than the program was said to be either
+        let mut chunk = ProgramChunk::split(1, "robot.wasm", PROGRAM, PROGRAM.len())
+            .pop()
+            .unwrap();
+        chunk.len = 4;
+        let err = uploads.receive(chunk).unwrap_err();
+        assert!(err.contains("more than the 4 bytes"), "{err}");
+        assert!(!uploads.dir.join("slot_1.wasm").exists());
+        fs::remove_dir_all(&uploads.dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_slot() {
+        let mut uploads = uploads("invalid-slot");
+        for slot in [0, NUM_SLOTS + 1] {
+            let chunk = ProgramChunk::split(slot, "robot.wasm", PROGRAM, PROGRAM.len())
+                .pop()
+                .unwrap();
+            assert_eq!(
+                uploads.receive(chunk),
+                Err(format!("there's no slot {slot}"))
+            );
+        }
+        assert_eq!(fs::read_dir(&uploads.dir).unwrap().count(), 0);
+        fs::remove_dir_all(&uploads.dir).unwrap();
+    }
+}
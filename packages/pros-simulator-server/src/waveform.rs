@@ -0,0 +1,179 @@
+//! Joystick input generated from waveforms, for characterizing how drivetrain code responds to
+//! the driver. Waveforms are written in scenario files as `{"waveform": {...}}` lines.
+
+use std::{
+    f64::consts::TAU,
+    sync::mpsc::Sender,
+    thread,
+    time::{Duration, Instant},
+};
+
+use pros_simulator_interface::{ControllerState, SimulatorMessage};
+use serde::Deserialize;
+
+/// How often the joysticks are moved while a waveform is playing, like the V5 controller's
+/// update rate.
+const SAMPLE_PERIOD: Duration = Duration::from_millis(20);
+
+/// A joystick axis on the master controller.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Channel {
+    LeftX,
+    LeftY,
+    RightX,
+    RightY,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Shape {
+    /// Holds the axis at the amplitude.
+    Step,
+    /// Moves the axis from 0 to the amplitude over each period, then jumps back to 0.
+    Ramp,
+    /// Swings the axis between the amplitude and its negative once per period.
+    Sine,
+}
+
+/// Moves a joystick axis in a pattern for some time, then lets it go back to 0.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Waveform {
+    pub shape: Shape,
+    pub channel: Channel,
+    /// The furthest the axis is moved, from -127 to 127.
+    pub amplitude: i8,
+    /// How long a ramp or sine wave takes to repeat. Defaults to the duration.
+    #[serde(default)]
+    pub period_ms: Option<u64>,
+    /// When to start, relative to the start of the simulation.
+    #[serde(default)]
+    pub start_ms: u64,
+    pub duration_ms: u64,
+}
+
+impl Waveform {
+    /// The axis value `elapsed` after the simulation started, or `None` if the waveform isn't
+    /// playing.
+    fn value(&self, elapsed: Duration) -> Option<i8> {
+        let t = elapsed.checked_sub(Duration::from_millis(self.start_ms))?;
+        if t >= Duration::from_millis(self.duration_ms) {
+            return None;
+        }
+        let period = self.period_ms.unwrap_or(self.duration_ms).max(1) as f64 / 1000.0;
+        let phase = t.as_secs_f64() % period / period;
+        let amplitude = self.amplitude as f64;
+        let value = match self.shape {
+            Shape::Step => amplitude,
+            Shape::Ramp => amplitude * phase,
+            Shape::Sine => amplitude * (TAU * phase).sin(),
+        };
+        Some(value.round().clamp(-127.0, 127.0) as i8)
+    }
+
+    fn end(&self) -> Duration {
+        Duration::from_millis(self.start_ms + self.duration_ms)
+    }
+}
+
+/// Send master controller updates following the waveforms until they've all ended. Where
+/// waveforms overlap on the same channel, the one listed last wins.
+pub fn play(waveforms: Vec<Waveform>, messages: Sender<SimulatorMessage>) {
+    let end = waveforms
+        .iter()
+        .map(Waveform::end)
+        .max()
+        .unwrap_or_default();
+    let start = Instant::now();
+    thread::spawn(move || {
+        let mut last_state = None;
+        loop {
+            let elapsed = start.elapsed();
+            let mut state = ControllerState::default();
+            for waveform in &waveforms {
+                if let Some(value) = waveform.value(elapsed) {
+                    let analog = &mut state.analog;
+                    *match waveform.channel {
+                        Channel::LeftX => &mut analog.left_x,
+                        Channel::LeftY => &mut analog.left_y,
+                        Channel::RightX => &mut analog.right_x,
+                        Channel::RightY => &mut analog.right_y,
+                    } = value;
+                }
+            }
+            if last_state.as_ref() != Some(&state) {
+                let message = SimulatorMessage::ControllerUpdate(Some(state.clone()), None);
+                if messages.send(message).is_err() {
+                    break;
+                }
+                last_state = Some(state);
+            }
+            if elapsed > end {
+                break;
+            }
+            thread::sleep(SAMPLE_PERIOD);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+
+    fn waveform(json: &str) -> Waveform {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn shapes() {
+        let ms = Duration::from_millis;
+        let step = waveform(
+            r#"{"shape":"step","channel":"left-y","amplitude":100,"start_ms":50,"duration_ms":100}"#,
+        );
+        assert_eq!(step.value(ms(0)), None);
+        assert_eq!(step.value(ms(50)), Some(100));
+        assert_eq!(step.value(ms(149)), Some(100));
+        assert_eq!(step.value(ms(150)), None);
+
+        let ramp = waveform(
+            r#"{"shape":"ramp","channel":"left-x","amplitude":-100,"period_ms":100,"duration_ms":300}"#,
+        );
+        assert_eq!(ramp.value(ms(0)), Some(0));
+        assert_eq!(ramp.value(ms(50)), Some(-50));
+        assert_eq!(ramp.value(ms(125)), Some(-25));
+
+        let sine = waveform(
+            r#"{"shape":"sine","channel":"right-x","amplitude":127,"period_ms":400,"duration_ms":400}"#,
+        );
+        assert_eq!(sine.value(ms(0)), Some(0));
+        assert_eq!(sine.value(ms(100)), Some(127));
+        assert_eq!(sine.value(ms(300)), Some(-127));
+    }
+
+    #[test]
+    fn play_controller_updates() {
+        let (tx, rx) = mpsc::channel();
+        play(
+            vec![
+                waveform(r#"{"shape":"step","channel":"left-y","amplitude":60,"duration_ms":200}"#),
+                waveform(
+                    r#"{"shape":"step","channel":"right-x","amplitude":-30,"duration_ms":200}"#,
+                ),
+            ],
+            tx,
+        );
+        let sticks = rx
+            .iter()
+            .map(|message| match message {
+                SimulatorMessage::ControllerUpdate(Some(master), None) => {
+                    (master.analog.left_y, master.analog.right_x)
+                }
+                message => panic!("unexpected message {message:?}"),
+            })
+            .collect::<Vec<_>>();
+        // an update is only sent when the sticks move, and they're let go at the end
+        assert_eq!(sticks, [(60, -30), (0, 0)]);
+    }
+}
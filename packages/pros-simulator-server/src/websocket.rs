@@ -0,0 +1,508 @@
+//! Just enough of the WebSocket protocol ([RFC 6455](https://www.rfc-editor.org/rfc/rfc6455))
+//! for browser frontends to connect to the server. Each text message a frontend sends is read as
+//! a line of JSON, and each line the server writes is sent as a text message of its own.
+
+use std::{
+    collections::VecDeque,
+    io::{self, Read, Write},
+    sync::{Arc, Mutex},
+};
+
+/// The longest message a frontend can send, so it can't make the server buffer an endless one.
+/// Big enough for any `UploadProgram` chunk.
+const MAX_MESSAGE_LEN: u64 = 16 << 20;
+
+/// Added to a frontend's key to prove the server speaks WebSocket.
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Sent instead of upgrading a request that can't be, before disconnecting.
+pub const BAD_REQUEST: &[u8] =
+    b"HTTP/1.1 400 Bad Request\r\nConnection: close\r\nContent-Length: 0\r\n\r\n";
+
+/// Sent instead of upgrading a request from a page that isn't allowed, before disconnecting.
+pub const FORBIDDEN: &[u8] =
+    b"HTTP/1.1 403 Forbidden\r\nConnection: close\r\nContent-Length: 0\r\n\r\n";
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+/// The HTTP request a frontend opens a WebSocket with.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Upgrade {
+    /// The `Sec-WebSocket-Key` header, which the server's response has to answer.
+    pub key: String,
+    /// The page that opened the WebSocket, which browsers always send.
+    pub origin: Option<String>,
+}
+
+impl Upgrade {
+    /// Parses the lines of an upgrade request, without their line endings and up to the blank
+    /// line that ends the headers.
+    pub fn parse(lines: &[String]) -> Result<Self, &'static str> {
+        let request_line = lines.first().ok_or("not a WebSocket request")?;
+        let mut words = request_line.split_whitespace();
+        if words.next() != Some("GET")
+            || words.next().is_none()
+            || !words
+                .next()
+                .is_some_and(|version| version.starts_with("HTTP/1."))
+        {
+            return Err("not a WebSocket request");
+        }
+
+        let mut upgrade = false;
+        let mut connection = false;
+        let mut version = None;
+        let mut key = None;
+        let mut origin = None;
+        for line in &lines[1..] {
+            let (name, value) = line.split_once(':').ok_or("not a WebSocket request")?;
+            let value = value.trim();
+            let has_token = |token| {
+                value
+                    .split(',')
+                    .any(|item| item.trim().eq_ignore_ascii_case(token))
+            };
+            match name.trim().to_ascii_lowercase().as_str() {
+                "upgrade" => upgrade = has_token("websocket"),
+                "connection" => connection = has_token("upgrade"),
+                "sec-websocket-version" => version = Some(value.to_string()),
+                "sec-websocket-key" => key = Some(value.to_string()),
+                "origin" => origin = Some(value.to_string()),
+                _ => {}
+            }
+        }
+        let (true, true, Some(key)) = (upgrade, connection, key) else {
+            return Err("not a WebSocket request");
+        };
+        if version.as_deref() != Some("13") {
+            return Err("unsupported WebSocket version");
+        }
+        Ok(Self { key, origin })
+    }
+
+    /// The response that accepts the upgrade.
+    pub fn response(&self) -> String {
+        format!(
+            "HTTP/1.1 101 Switching Protocols\r\n\
+             Upgrade: websocket\r\n\
+             Connection: Upgrade\r\n\
+             Sec-WebSocket-Accept: {}\r\n\r\n",
+            accept_key(&self.key)
+        )
+    }
+}
+
+/// The `Sec-WebSocket-Accept` header answering a frontend's `Sec-WebSocket-Key`.
+fn accept_key(key: &str) -> String {
+    encode_base64(&sha1(format!("{key}{ACCEPT_GUID}").as_bytes()))
+}
+
+/// A WebSocket connection over a stream, read and written as if its messages were lines.
+///
+/// Clones made with [`with_stream`](Self::with_stream) share what's been received, so one can
+/// read messages while another writes events.
+pub struct WebSocket<S> {
+    stream: S,
+    incoming: Arc<Mutex<Incoming>>,
+    /// Held while a frame is written, so frames from different clones don't interleave.
+    writing: Arc<Mutex<()>>,
+    /// What's been written since the last complete line.
+    line: Vec<u8>,
+}
+
+/// The messages that have been received but not read yet.
+#[derive(Default)]
+struct Incoming {
+    buffer: VecDeque<u8>,
+    /// How much of a message split into several frames has been received.
+    message_len: u64,
+    closed: bool,
+}
+
+impl<S: Read + Write> WebSocket<S> {
+    /// Wraps a stream whose upgrade request has already been answered.
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            incoming: Default::default(),
+            writing: Default::default(),
+            line: Vec::new(),
+        }
+    }
+
+    /// Another handle to the same connection, over a clone of its stream.
+    pub fn with_stream(&self, stream: S) -> Self {
+        Self {
+            stream,
+            incoming: self.incoming.clone(),
+            writing: self.writing.clone(),
+            line: Vec::new(),
+        }
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    fn send(&mut self, opcode: u8, payload: &[u8]) -> io::Result<()> {
+        let mut frame = Vec::with_capacity(payload.len() + 10);
+        frame.push(0x80 | opcode);
+        match payload.len() {
+            len @ 0..=125 => frame.push(len as u8),
+            len @ 126..=0xFFFF => {
+                frame.push(126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                frame.push(127);
+                frame.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        frame.extend_from_slice(payload);
+        let _writing = self.writing.lock().unwrap();
+        self.stream.write_all(&frame)?;
+        self.stream.flush()
+    }
+
+    /// Receives a frame, adding its payload to `incoming` if it's part of a message and
+    /// answering it if it's a ping or close frame.
+    fn receive(&mut self, incoming: &mut Incoming) -> io::Result<()> {
+        let mut header = [0; 2];
+        match self.stream.read_exact(&mut header) {
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                incoming.closed = true;
+                return Ok(());
+            }
+            result => result?,
+        }
+        let [first, second] = header;
+        let fin = first & 0x80 != 0;
+        let opcode = first & 0x0F;
+        if first & 0x70 != 0 {
+            return Err(protocol_error("frontend used an unsupported extension"));
+        }
+        if second & 0x80 == 0 {
+            return Err(protocol_error("frontend sent an unmasked frame"));
+        }
+        let len = match second & 0x7F {
+            126 => {
+                let mut len = [0; 2];
+                self.stream.read_exact(&mut len)?;
+                u64::from(u16::from_be_bytes(len))
+            }
+            127 => {
+                let mut len = [0; 8];
+                self.stream.read_exact(&mut len)?;
+                u64::from_be_bytes(len)
+            }
+            len => u64::from(len),
+        };
+        let control = opcode & 0x08 != 0;
+        if control && (len > 125 || !fin) {
+            return Err(protocol_error("frontend sent an invalid control frame"));
+        }
+        if incoming.message_len.saturating_add(len) > MAX_MESSAGE_LEN {
+            return Err(protocol_error("frontend sent a message that's too long"));
+        }
+        let mut mask = [0; 4];
+        self.stream.read_exact(&mut mask)?;
+        let mut payload = Vec::new();
+        (&mut self.stream).take(len).read_to_end(&mut payload)?;
+        if payload.len() as u64 != len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+
+        match opcode {
+            OPCODE_CONTINUATION | OPCODE_TEXT | OPCODE_BINARY => {
+                incoming.buffer.extend(&payload);
+                incoming.message_len += len;
+                if fin {
+                    incoming.message_len = 0;
+                    // each message is a line, whether or not the frontend ended it with one
+                    if incoming.buffer.back().is_some_and(|&byte| byte != b'\n') {
+                        incoming.buffer.push_back(b'\n');
+                    }
+                }
+            }
+            OPCODE_CLOSE => {
+                // the close frame is echoed back with its status code, if it had one
+                _ = self.send(OPCODE_CLOSE, &payload[..payload.len().min(2)]);
+                incoming.closed = true;
+            }
+            OPCODE_PING => self.send(OPCODE_PONG, &payload)?,
+            OPCODE_PONG => {}
+            _ => return Err(protocol_error("frontend sent an unknown opcode")),
+        }
+        Ok(())
+    }
+}
+
+impl<S: Read + Write> Read for WebSocket<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let incoming = self.incoming.clone();
+        let mut incoming = incoming.lock().unwrap();
+        while incoming.buffer.is_empty() {
+            if incoming.closed {
+                return Ok(0);
+            }
+            self.receive(&mut incoming)?;
+        }
+        incoming.buffer.read(buf)
+    }
+}
+
+impl<S: Read + Write> Write for WebSocket<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.line.extend_from_slice(buf);
+        while let Some(end) = self.line.iter().position(|&byte| byte == b'\n') {
+            let line = self.line.drain(..=end).collect::<Vec<_>>();
+            self.send(OPCODE_TEXT, &line[..end])?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+fn protocol_error(reason: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason)
+}
+
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+
+    for block in message.chunks_exact(64) {
+        let mut words = [0u32; 80];
+        for (word, bytes) in words.iter_mut().zip(block.chunks_exact(4)) {
+            *word = u32::from_be_bytes(bytes.try_into().unwrap());
+        }
+        for i in 16..80 {
+            words[i] = (words[i - 3] ^ words[i - 8] ^ words[i - 14] ^ words[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (i, word) in words.into_iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let next = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(word);
+            (a, b, c, d, e) = (next, a, b.rotate_left(30), c, d);
+        }
+        for (state, value) in state.iter_mut().zip([a, b, c, d, e]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0; 20];
+    for (bytes, value) in digest.chunks_exact_mut(4).zip(state) {
+        bytes.copy_from_slice(&value.to_be_bytes());
+    }
+    digest
+}
+
+fn encode_base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut text = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for group in bytes.chunks(3) {
+        let n = group
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &byte)| n | (byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= group.len() {
+                text.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(request: &str) -> Vec<String> {
+        request.lines().map(str::to_string).collect()
+    }
+
+    /// A frame as a frontend sends it, masked with an arbitrary key.
+    fn client_frame(first: u8, payload: &[u8]) -> Vec<u8> {
+        let mask = [0x12, 0x34, 0x56, 0x78];
+        let mut frame = vec![first];
+        match payload.len() {
+            len @ 0..=125 => frame.push(0x80 | len as u8),
+            len => {
+                frame.push(0x80 | 126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+        }
+        frame.extend_from_slice(&mask);
+        frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+        frame
+    }
+
+    /// An in-memory connection: what the frontend sent, and what the server writes back.
+    struct Pipe {
+        input: io::Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Read for Pipe {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Pipe {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn websocket(input: Vec<u8>) -> WebSocket<Pipe> {
+        WebSocket::new(Pipe {
+            input: io::Cursor::new(input),
+            output: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn digests() {
+        assert_eq!(
+            sha1(b"abc"),
+            [
+                0xa9, 0x99, 0x3e, 0x36, 0x47, 0x06, 0x81, 0x6a, 0xba, 0x3e, 0x25, 0x71, 0x78, 0x50,
+                0xc2, 0x6c, 0x9c, 0xd0, 0xd8, 0x9d
+            ]
+        );
+        assert_eq!(encode_base64(&sha1(b"")), "2jmj7l5rSw0yVb/vlWAYkK/YBwk=");
+    }
+
+    #[test]
+    fn upgrade() {
+        // the example from RFC 6455
+        let upgrade = Upgrade::parse(&lines(
+            "GET /chat HTTP/1.1\n\
+             Host: server.example.com\n\
+             Upgrade: websocket\n\
+             Connection: keep-alive, Upgrade\n\
+             Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\n\
+             Origin: http://example.com\n\
+             Sec-WebSocket-Version: 13",
+        ))
+        .unwrap();
+        assert_eq!(upgrade.origin.as_deref(), Some("http://example.com"));
+        assert!(upgrade
+            .response()
+            .contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+
+        assert_eq!(
+            Upgrade::parse(&lines("GET / HTTP/1.1\nHost: localhost")),
+            Err("not a WebSocket request")
+        );
+        assert_eq!(
+            Upgrade::parse(&lines(
+                "GET / HTTP/1.1\nUpgrade: websocket\nConnection: Upgrade\n\
+                 Sec-WebSocket-Key: x\nSec-WebSocket-Version: 8"
+            )),
+            Err("unsupported WebSocket version")
+        );
+        assert_eq!(Upgrade::parse(&[]), Err("not a WebSocket request"));
+    }
+
+    #[test]
+    fn messages_are_lines() {
+        let mut input = client_frame(0x80 | OPCODE_TEXT, b"\"Stop\"");
+        // a message split over two frames, with a ping in between
+        input.extend(client_frame(OPCODE_TEXT, b"{\"SelectSlot\""));
+        input.extend(client_frame(0x80 | OPCODE_PING, b"hi"));
+        input.extend(client_frame(0x80 | OPCODE_CONTINUATION, b": 2}\n"));
+        input.extend(client_frame(0x80 | OPCODE_CLOSE, &[0x03, 0xE8]));
+        let mut websocket = websocket(input);
+        let mut text = String::new();
+        websocket.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "\"Stop\"\n{\"SelectSlot\": 2}\n");
+        // the ping was answered and the close frame echoed
+        assert_eq!(
+            websocket.get_ref().output,
+            [
+                &[0x80 | OPCODE_PONG, 2][..],
+                b"hi",
+                &[0x80 | OPCODE_CLOSE, 2, 0x03, 0xE8]
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn lines_are_messages() {
+        let mut websocket = websocket(Vec::new());
+        websocket.write_all(b"\"RobotCode").unwrap();
+        assert!(websocket.get_ref().output.is_empty());
+        websocket.write_all(b"Starting\"\n").unwrap();
+        let long = "x".repeat(200);
+        writeln!(websocket, "{long}").unwrap();
+        let mut expected = vec![0x80 | OPCODE_TEXT, 19];
+        expected.extend_from_slice(b"\"RobotCodeStarting\"");
+        expected.extend_from_slice(&[0x80 | OPCODE_TEXT, 126, 0, 200]);
+        expected.extend_from_slice(long.as_bytes());
+        assert_eq!(websocket.get_ref().output, expected);
+    }
+
+    #[test]
+    fn protocol_errors() {
+        let read = |input: Vec<u8>| {
+            websocket(input)
+                .read(&mut [0; 16])
+                .map_err(|err| err.to_string())
+        };
+        // frontends have to mask what they send
+        assert_eq!(
+            read(vec![0x80 | OPCODE_TEXT, 1, b'x']),
+            Err("frontend sent an unmasked frame".into())
+        );
+        assert_eq!(
+            read(client_frame(0x80 | 0x3, b"")),
+            Err("frontend sent an unknown opcode".into())
+        );
+        let mut too_long = vec![0x80 | OPCODE_TEXT, 0x80 | 127];
+        too_long.extend_from_slice(&(MAX_MESSAGE_LEN + 1).to_be_bytes());
+        assert_eq!(
+            read(too_long),
+            Err("frontend sent a message that's too long".into())
+        );
+        // a connection that ends between frames is closed, not broken
+        assert_eq!(read(Vec::new()), Ok(0));
+    }
+}
@@ -0,0 +1,50 @@
+//! Measures how quickly the simulator spawns and switches between tasks, calls host functions,
+//! and emits events. Every benchmark includes the fixed cost of compiling the robot code, which
+//! is measured on its own by `load`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use pros_simulator::{SimulatorOptions, StopReason};
+
+#[allow(dead_code)]
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+fn run(c: &mut Criterion, benchmark: &str, fixture: &str, options: SimulatorOptions) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    c.bench_function(benchmark, |b| {
+        b.to_async(&runtime).iter(|| async {
+            let run = common::run_fixture_with_options(fixture, options.clone(), []).await;
+            assert!(matches!(run.outcome.reason, StopReason::Exited(0)));
+        })
+    });
+}
+
+fn benchmarks(c: &mut Criterion) {
+    let options = common::default_options();
+    run(c, "load", "bench_empty", options.clone());
+    run(c, "spawn 100 tasks", "bench_spawn", options.clone());
+    run(
+        c,
+        "spawn 100 tasks (instance pool)",
+        "bench_spawn",
+        options.clone().instance_pool(4),
+    );
+    run(
+        c,
+        "10 tasks yield 100 times",
+        "bench_switch",
+        options.clone(),
+    );
+    run(c, "100,000 host calls", "bench_host_calls", options.clone());
+    run(c, "10,000 console messages", "bench_events", options);
+}
+
+criterion_group! {
+    name = scheduler;
+    config = Criterion::default().sample_size(10);
+    targets = benchmarks
+}
+criterion_main!(scheduler);
@@ -0,0 +1,72 @@
+;; A minimal robot program that exercises every API affected by simulator messages: it polls
+;; the controllers and competition status in every phase and registers LCD button callbacks.
+(module
+  (import "env" "memory" (memory 18 16384 shared))
+  (import "env" "delay" (func $delay (param i32)))
+  (import "env" "lcd_initialize" (func $lcd_initialize (result i32)))
+  (import "env" "lcd_set_text" (func $lcd_set_text (param i32 i32) (result i32)))
+  (import "env" "lcd_register_btn0_cb" (func $lcd_register_btn0_cb (param i32) (result i32)))
+  (import "env" "lcd_register_btn1_cb" (func $lcd_register_btn1_cb (param i32) (result i32)))
+  (import "env" "lcd_register_btn2_cb" (func $lcd_register_btn2_cb (param i32) (result i32)))
+  (import "env" "controller_get_analog" (func $controller_get_analog (param i32 i32) (result i32)))
+  (import "env" "controller_get_digital" (func $controller_get_digital (param i32 i32) (result i32)))
+  (import "env" "controller_get_digital_new_press" (func $controller_get_digital_new_press (param i32 i32) (result i32)))
+  (import "env" "controller_is_connected" (func $controller_is_connected (param i32) (result i32)))
+  (import "env" "competition_get_status" (func $competition_get_status (result i32)))
+
+  (table (export "__indirect_function_table") 2 funcref)
+  (elem (i32.const 1) $on_button)
+
+  (global $heap (mut i32) (i32.const 65536))
+  (data (i32.const 1024) "button pressed\00")
+
+  (func (export "wasm_memalign") (param $align i32) (param $size i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr
+      (i32.and
+        (i32.add (global.get $heap) (i32.sub (local.get $align) (i32.const 1)))
+        (i32.sub (i32.const 0) (local.get $align))))
+    (global.set $heap (i32.add (local.get $ptr) (local.get $size)))
+    (local.get $ptr))
+  (func (export "wasm_free") (param i32))
+
+  (func $on_button
+    (drop (call $lcd_set_text (i32.const 0) (i32.const 1024))))
+
+  ;; Reads every controller input and the competition status a few times.
+  (func $poll
+    (local $i i32)
+    (local $id i32)
+    (local $channel i32)
+    (loop $ticks
+      (local.set $id (i32.const 0))
+      (loop $controllers
+        (drop (call $controller_is_connected (local.get $id)))
+        (local.set $channel (i32.const 0))
+        (loop $channels
+          (drop (call $controller_get_analog (local.get $id) (local.get $channel)))
+          (local.set $channel (i32.add (local.get $channel) (i32.const 1)))
+          (br_if $channels (i32.lt_u (local.get $channel) (i32.const 4))))
+        (local.set $channel (i32.const 6))
+        (loop $buttons
+          (drop (call $controller_get_digital (local.get $id) (local.get $channel)))
+          (drop (call $controller_get_digital_new_press (local.get $id) (local.get $channel)))
+          (local.set $channel (i32.add (local.get $channel) (i32.const 1)))
+          (br_if $buttons (i32.lt_u (local.get $channel) (i32.const 18))))
+        (local.set $id (i32.add (local.get $id) (i32.const 1)))
+        (br_if $controllers (i32.lt_u (local.get $id) (i32.const 2))))
+      (drop (call $competition_get_status))
+      (call $delay (i32.const 2))
+      (local.set $i (i32.add (local.get $i) (i32.const 1)))
+      (br_if $ticks (i32.lt_u (local.get $i) (i32.const 10)))))
+
+  (func (export "initialize")
+    (drop (call $lcd_initialize))
+    (drop (call $lcd_register_btn0_cb (i32.const 1)))
+    (drop (call $lcd_register_btn1_cb (i32.const 1)))
+    (drop (call $lcd_register_btn2_cb (i32.const 1))))
+  (func (export "competition_initialize") (call $poll))
+  (func (export "autonomous") (call $poll))
+  (func (export "opcontrol") (call $poll))
+  (func (export "disabled") (call $poll))
+)
@@ -0,0 +1,48 @@
+//! Sends arbitrary line delimited JSON messages to a minimal robot program, checking that
+//! malformed or adversarial frontend input can't crash the simulator.
+
+#![no_main]
+
+use std::{
+    path::PathBuf,
+    sync::{mpsc, OnceLock},
+    time::Duration,
+};
+
+use libfuzzer_sys::fuzz_target;
+use pros_simulator::{SimulatorOptions, StopReason, Timeout};
+use pros_simulator_interface::SimulatorMessage;
+
+/// Compiles the guest once and returns the path to the WASM file.
+fn guest() -> &'static PathBuf {
+    static GUEST: OnceLock<PathBuf> = OnceLock::new();
+    GUEST.get_or_init(|| {
+        let wasm = wat::parse_str(include_str!("guest.wat")).unwrap();
+        let path =
+            std::env::temp_dir().join(format!("pros-simulator-fuzz-{}.wasm", std::process::id()));
+        std::fs::write(&path, wasm).unwrap();
+        path
+    })
+}
+
+fuzz_target!(|data: &[u8]| {
+    let (tx, rx) = mpsc::channel();
+    let messages = serde_json::Deserializer::from_slice(data).into_iter::<SimulatorMessage>();
+    for message in messages {
+        let Ok(message) = message else { break };
+        tx.send(message).unwrap();
+    }
+    drop(tx);
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .unwrap();
+    let options = SimulatorOptions::new().timeout(Timeout::Simulated(Duration::from_millis(200)));
+    let outcome = runtime
+        .block_on(pros_simulator::simulate(guest(), options, |_| {}, rx))
+        .unwrap();
+    if let StopReason::Crashed(err) = outcome.reason {
+        panic!("robot code crashed: {err:?}");
+    }
+});
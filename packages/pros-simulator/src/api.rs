@@ -1,12 +1,206 @@
 use wasmtime::{Linker, SharedMemory, Store};
 
-use crate::host::Host;
+use crate::host::{
+    abi::{ProgramAbi, VEX_MODULE},
+    Host, HostCtx,
+};
 
+/// Registers an async host function, generating the `func_wrapN_async` plumbing and a
+/// `trace`-level span that records its arguments. Each call is counted for the API coverage
+/// summary, and pauses robot code first if it hits a
+/// [breakpoint](pros_simulator_interface::SimulatorMessage::BreakOnCall). With the `otlp`
+/// feature, calls are also exported as spans if that's enabled.
+///
+/// The body can use the [`Caller`](wasmtime::Caller) under the name given as the first
+/// parameter, and returns an `anyhow::Result` of the return type. Errors stop the robot code.
+///
+/// With `#[errno(value)]`, the body instead returns a `Result<_, i32>`. Errors are stored in
+/// the task's `errno` and `value` is returned to the robot code. These functions can also be
+/// made to fail without running the body by
+/// [`SimulatorMessage::FailNextCall`](pros_simulator_interface::SimulatorMessage::FailNextCall).
+///
+/// `#[before(expr)]` after `#[errno]` runs an `anyhow::Result` future before anything else, for
+/// checks that should still stop the robot code.
+///
+/// Arguments can be marked with one of the checks in [`validate`], which runs before the body.
+///
+/// ```ignore
+/// host_fn!(linker, "env", #[errno(0)] fn lcd_clear_line(caller, #[lcd_line] line: i32) -> u32 {
+///     caller.lcd_lock().await.clear_line(line).map(|()| 1)
+/// });
+/// ```
+macro_rules! host_fn {
+    (@wrap $ret:ty;) => {
+        ::wasmtime::Linker::<$crate::host::Host>::func_wrap0_async::<::anyhow::Result<$ret>>
+    };
+    (@wrap $ret:ty; $a1:ident) => {
+        ::wasmtime::Linker::<$crate::host::Host>::func_wrap1_async::<_, ::anyhow::Result<$ret>>
+    };
+    (@wrap $ret:ty; $a1:ident $a2:ident) => {
+        ::wasmtime::Linker::<$crate::host::Host>::func_wrap2_async::<_, _, ::anyhow::Result<$ret>>
+    };
+    (@wrap $ret:ty; $a1:ident $a2:ident $a3:ident) => {
+        ::wasmtime::Linker::<$crate::host::Host>::func_wrap3_async::<
+            _,
+            _,
+            _,
+            ::anyhow::Result<$ret>,
+        >
+    };
+    (@wrap $ret:ty; $a1:ident $a2:ident $a3:ident $a4:ident) => {
+        ::wasmtime::Linker::<$crate::host::Host>::func_wrap4_async::<
+            _,
+            _,
+            _,
+            _,
+            ::anyhow::Result<$ret>,
+        >
+    };
+    (@wrap $ret:ty; $a1:ident $a2:ident $a3:ident $a4:ident $a5:ident) => {
+        ::wasmtime::Linker::<$crate::host::Host>::func_wrap5_async::<
+            _,
+            _,
+            _,
+            _,
+            _,
+            ::anyhow::Result<$ret>,
+        >
+    };
+    (@wrap $ret:ty; $a1:ident $a2:ident $a3:ident $a4:ident $a5:ident $a6:ident) => {
+        ::wasmtime::Linker::<$crate::host::Host>::func_wrap6_async::<
+            _,
+            _,
+            _,
+            _,
+            _,
+            _,
+            ::anyhow::Result<$ret>,
+        >
+    };
+    (
+        @register $linker:expr, $module:expr,
+        fn $name:ident($caller:ident $(, $arg:ident: $ty:ty)*) -> $ret:ty $body:block
+    ) => {
+        host_fn!(@wrap $ret; $($arg)*)(
+            &mut *$linker,
+            $module,
+            stringify!($name),
+            |#[allow(unused_mut)] mut $caller: ::wasmtime::Caller<'_, $crate::host::Host>
+             $(, $arg: $ty)*| {
+                $crate::host::HostCtx::api_usage(&$caller).record(stringify!($name));
+                let args = [$(::std::convert::Into::<i64>::into($arg)),*];
+                let hit = $crate::host::HostCtx::breakpoints(&$caller).hit(stringify!($name), &args);
+                ::std::boxed::Box::new(::tracing::Instrument::instrument(
+                    async move {
+                        if hit {
+                            $crate::host::breakpoints::pause(
+                                &mut $caller,
+                                stringify!($name),
+                                args.to_vec(),
+                            )
+                            .await;
+                        }
+                        #[cfg(feature = "otlp")]
+                        let call =
+                            $crate::host::otlp::HostCall::start(&$caller, stringify!($name)).await;
+                        let result: ::anyhow::Result<$ret> = async { $body }.await;
+                        #[cfg(feature = "otlp")]
+                        if let Some(call) = call {
+                            call.end();
+                        }
+                        result
+                    },
+                    ::tracing::trace_span!(stringify!($name) $(, $arg)*),
+                ))
+            },
+        )?;
+    };
+    (
+        $linker:expr, $module:expr,
+        #[errno($error_value:expr)]
+        $(#[before($before:expr)])?
+        fn $name:ident(
+            $caller:ident
+            $(, $(#[$check:ident $(($($param:expr),*))?])? $arg:ident: $ty:ty)* $(,)?
+        ) -> $ret:ty $body:block
+    ) => {
+        host_fn!(@register $linker, $module, fn $name($caller $(, $arg: $ty)*) -> $ret {
+            use $crate::host::ResultExt as _;
+            $($before.await?;)?
+            let injected = $crate::host::HostCtx::injected_failures_lock(&$caller)
+                .await
+                .take(stringify!($name));
+            let result: Result<$ret, i32> = match injected {
+                Some(errno) => Err(errno),
+                None => async {
+                    $($(
+                        if let Err(invalid) =
+                            $crate::api::validate::$check(&$caller, $arg $(, $($param),*)?)
+                        {
+                            let value = ::std::convert::Into::<i64>::into($arg);
+                            return Err(invalid
+                                .report(&$caller, stringify!($name), stringify!($arg), value)
+                                .await);
+                        }
+                    )?)*
+                    $body
+                }
+                .await,
+            };
+            result.unwrap_or_errno_as(&mut $caller, $error_value).await
+        })
+    };
+    (
+        $linker:expr, $module:expr,
+        fn $name:ident(
+            $caller:ident
+            $(, $(#[$check:ident $(($($param:expr),*))?])? $arg:ident: $ty:ty)* $(,)?
+        ) $body:block
+    ) => {
+        host_fn!($linker, $module, fn $name(
+            $caller $(, $(#[$check $(($($param),*))?])? $arg: $ty)*
+        ) -> () $body)
+    };
+    (
+        $linker:expr, $module:expr,
+        fn $name:ident(
+            $caller:ident
+            $(, $(#[$check:ident $(($($param:expr),*))?])? $arg:ident: $ty:ty)* $(,)?
+        ) -> $ret:ty $body:block
+    ) => {
+        host_fn!(@register $linker, $module, fn $name($caller $(, $arg: $ty)*) -> $ret {
+            $($(
+                if let Err(invalid) =
+                    $crate::api::validate::$check(&$caller, $arg $(, $($param),*)?)
+                {
+                    let value = ::std::convert::Into::<i64>::into($arg);
+                    ::anyhow::bail!(invalid.message(stringify!($name), stringify!($arg), value));
+                }
+            )?)*
+            $body
+        })
+    };
+}
+
+mod apix;
+mod atomics;
+mod display;
+mod flash;
 mod generic_io;
 mod llemu;
 mod misc;
+mod motors;
+mod newlib;
+mod printf;
 mod rtos_facilities;
+mod stubs;
+mod testing;
+mod validate;
+mod vexide;
+
+pub use stubs::stub_unknown_imports;
 
+/// Links the API for the SDK the robot code was built against.
 pub fn configure_api(
     linker: &mut Linker<Host>,
     store: &mut Store<Host>,
@@ -14,11 +208,30 @@ pub fn configure_api(
 ) -> anyhow::Result<()> {
     linker.define(&mut *store, "env", "memory", shared_memory.clone())?;
 
-    llemu::configure_llemu_api(&mut *linker)?;
-    misc::configure_misc_api(&mut *linker)?;
-    rtos_facilities::configure_rtos_facilities_api(&mut *linker)?;
+    match store.data().abi() {
+        ProgramAbi::ProsRs | ProgramAbi::ProsC => {
+            apix::configure_apix_api(&mut *linker)?;
+            llemu::configure_llemu_api(&mut *linker)?;
+            misc::configure_misc_api(&mut *linker)?;
+            motors::configure_motors_api(&mut *linker)?;
+            rtos_facilities::configure_rtos_facilities_api(&mut *linker)?;
 
-    generic_io::configure_generic_io_api(&mut *linker)?;
+            generic_io::configure_generic_io_api(&mut *linker)?;
+            testing::configure_testing_api(&mut *linker)?;
+            flash::configure_flash_api(&mut *linker)?;
+            display::configure_display_api(&mut *linker, "env")?;
+            if store.data().abi() == ProgramAbi::ProsC {
+                newlib::configure_newlib_api(&mut *linker)?;
+            }
+        }
+        ProgramAbi::Vexide => {
+            vexide::configure_vexide_api(&mut *linker)?;
+            display::configure_display_api(&mut *linker, VEX_MODULE)?;
+        }
+        ProgramAbi::Unknown => {}
+    }
+    atomics::configure_atomics_api(&mut *linker)?;
+    store.data().plugins().link(&mut *linker, store)?;
 
     Ok(())
 }
@@ -0,0 +1,50 @@
+//! Advanced PROS API
+//!
+//! ## Reference
+//!
+//! * `registry_bind_port`
+//! * `registry_get_bound_type`
+//! * `registry_get_plugged_type`
+//! * `registry_unbind_port`
+//!
+//! Only the device registry is implemented. Ports are zero-indexed, and devices are plugged in
+//! with [`SimulatorOptions::smart_port`](crate::SimulatorOptions::smart_port).
+
+use pros_sys::{apix::E_DEVICE_UNDEFINED, PROS_ERR};
+use wasmtime::Linker;
+
+use crate::host::{Host, HostCtx};
+
+pub fn configure_apix_api(linker: &mut Linker<Host>) -> anyhow::Result<()> {
+    host_fn!(linker, "env", #[errno(PROS_ERR)] fn registry_bind_port(
+        caller,
+        #[smart_port] port: u32,
+        device_type: u32,
+    ) -> i32 {
+        caller.smart_ports_lock().await.bind(port, device_type).map(|()| 1)
+    });
+
+    host_fn!(linker, "env", #[errno(PROS_ERR)] fn registry_unbind_port(
+        caller,
+        #[smart_port] port: u32,
+    ) -> i32 {
+        caller.smart_ports_lock().await.unbind(port).map(|()| 1)
+    });
+
+    // invalid ports report an undefined device, with errno set to ENXIO
+    host_fn!(linker, "env", #[errno(E_DEVICE_UNDEFINED)] fn registry_get_bound_type(
+        caller,
+        #[smart_port] port: u32,
+    ) -> u32 {
+        caller.smart_ports_lock().await.bound_type(port)
+    });
+
+    host_fn!(linker, "env", #[errno(E_DEVICE_UNDEFINED)] fn registry_get_plugged_type(
+        caller,
+        #[smart_port] port: u32,
+    ) -> u32 {
+        caller.smart_ports_lock().await.plugged_type(port)
+    });
+
+    Ok(())
+}
@@ -0,0 +1,120 @@
+//! Atomics API - host functions that replace WebAssembly's atomic wait and notify instructions
+//! so that they block cooperatively. See [`host::atomics`](crate::host::atomics) for details.
+//!
+//! ## Reference
+//!
+//! * `memory_atomic_wait32`
+//! * `memory_atomic_wait64`
+//! * `memory_atomic_notify`
+
+use std::{future::pending, sync::atomic::Ordering};
+
+use anyhow::{bail, Context};
+use wasmtime::{Caller, Linker};
+
+use super::rtos_facilities::{ensure_can_block, sleep_until_tick};
+use crate::host::{
+    atomics::ATOMICS_MODULE, memory::SharedMemoryExt, task::TaskPool, Host, HostCtx, TICK_PERIOD_MS,
+};
+
+/// The length of a tick in nanoseconds, the unit of atomic wait timeouts.
+const TICK_PERIOD_NS: u64 = TICK_PERIOD_MS * 1_000_000;
+
+/// Returns the address accessed by an atomic instruction, trapping like WebAssembly does if it's
+/// out of bounds or unaligned.
+fn effective_address(
+    caller: &Caller<'_, Host>,
+    address: u32,
+    offset: u32,
+    size: usize,
+) -> anyhow::Result<u32> {
+    let address = address
+        .checked_add(offset)
+        .context("Atomic access is out of bounds")?;
+    if !(address as usize).is_multiple_of(size) {
+        bail!("Atomic access to {address:#x} is unaligned");
+    }
+    if address as usize + size > caller.memory().data_size() {
+        bail!("Atomic access to {address:#x} is out of bounds");
+    }
+    Ok(address)
+}
+
+/// Blocks the current task until the address is notified or the timeout (in nanoseconds, or
+/// negative for no timeout) expires. Returns 0 if notified and 2 if timed out.
+///
+/// Like other blocking calls, the timeout is rounded up to whole ticks of the simulation's
+/// clock.
+async fn wait(caller: &Caller<'_, Host>, address: u32, timeout: i64) -> anyhow::Result<u32> {
+    if timeout != 0 {
+        ensure_can_block(caller, "memory.atomic.wait").await?;
+    }
+    let deadline = u64::try_from(timeout)
+        .ok()
+        .map(|timeout| caller.ticks() + timeout.div_ceil(TICK_PERIOD_NS));
+    let extra_delay = caller.tasks_lock().await.extra_delay();
+    let woken = caller.atomic_waiters_lock().await.wait(address);
+    let notified = async {
+        while !woken.load(Ordering::Acquire) {
+            TaskPool::yield_now().await;
+        }
+    };
+    let timed_out = async {
+        match deadline {
+            Some(tick) => sleep_until_tick(caller, tick, extra_delay).await,
+            None => pending().await,
+        }
+    };
+    tokio::select! {
+        biased;
+        () = notified => Ok(0),
+        () = timed_out => {
+            caller.atomic_waiters_lock().await.cancel(address, &woken);
+            Ok(2)
+        }
+    }
+}
+
+pub fn configure_atomics_api(linker: &mut Linker<Host>) -> anyhow::Result<()> {
+    host_fn!(linker, ATOMICS_MODULE, fn memory_atomic_wait32(
+        caller,
+        address: u32,
+        expected: i32,
+        timeout: i64,
+        offset: u32,
+    ) -> u32 {
+        let address = effective_address(&caller, address, offset, 4)?;
+        let bytes = caller.memory().read_relaxed(address as usize, 4)?;
+        if i32::from_le_bytes(bytes.try_into().unwrap()) != expected {
+            return Ok(1);
+        }
+        wait(&caller, address, timeout).await
+    });
+
+    host_fn!(linker, ATOMICS_MODULE, fn memory_atomic_wait64(
+        caller,
+        address: u32,
+        expected: i64,
+        timeout: i64,
+        offset: u32,
+    ) -> u32 {
+        let address = effective_address(&caller, address, offset, 8)?;
+        let bytes = caller.memory().read_relaxed(address as usize, 8)?;
+        if i64::from_le_bytes(bytes.try_into().unwrap()) != expected {
+            return Ok(1);
+        }
+        wait(&caller, address, timeout).await
+    });
+
+    host_fn!(linker, ATOMICS_MODULE, fn memory_atomic_notify(
+        caller,
+        address: u32,
+        count: u32,
+        offset: u32,
+    ) -> u32 {
+        let address = effective_address(&caller, address, offset, 4)?;
+        Ok(caller.atomic_waiters_lock().await.notify(address, count))
+    });
+
+    Ok(())
+}
@@ -0,0 +1,114 @@
+//! VEX SDK display API - the `vexDisplay*` and touch functions that an LVGL display and input
+//! driver is built on, for robot code that bundles its own copy of LVGL or draws on the screen
+//! some other way. PROS programs import these from `env` and vexide programs from `vex`.
+//!
+//! Coordinates are pixels from the top left corner of the 480x272 screen, including the 32 pixel
+//! status bar, and colors are `0x00RRGGBB`.
+//!
+//! ## Reference
+//!
+//! * `vexDisplayForegroundColor`
+//! * `vexDisplayBackgroundColor`
+//! * `vexDisplayErase`
+//! * `vexDisplayRectFill`
+//! * `vexDisplayCopyRect`
+//!   Copies a buffer of `srcStride` pixels per row onto the screen, which is what an LVGL
+//!   driver's `flush_cb` calls. Only the pixels that end up on the screen are read.
+//! * `vexDisplayRender`
+//!   Turns on double buffering: from the first call, what's drawn is only shown each time this
+//!   is called. Yields to other tasks if `runScheduler` is set.
+//! * `vexTouchDataGet`
+//!   Fills in a `V5_TouchStatus`: the last event (a 32-bit `V5_TouchEvent`), its x and y as
+//!   16-bit integers, then 32-bit counts of presses and releases. A press is reported as
+//!   `kTouchEventPress` once and as `kTouchEventPressAuto` while the screen is held.
+
+use wasmtime::Linker;
+
+use crate::host::{
+    memory::{OutOfBoundsError, SharedMemoryExt},
+    screen::Rect,
+    task::TaskPool,
+    Host, HostCtx,
+};
+
+pub fn configure_display_api(linker: &mut Linker<Host>, module: &str) -> anyhow::Result<()> {
+    host_fn!(linker, module, fn vexDisplayForegroundColor(caller, color: u32) {
+        caller.screen_lock().await.set_foreground(color);
+        Ok(())
+    });
+
+    host_fn!(linker, module, fn vexDisplayBackgroundColor(caller, color: u32) {
+        caller.screen_lock().await.set_background(color);
+        Ok(())
+    });
+
+    host_fn!(linker, module, fn vexDisplayErase(caller) {
+        caller.screen_lock().await.erase();
+        Ok(())
+    });
+
+    host_fn!(linker, module, fn vexDisplayRectFill(caller, x1: i32, y1: i32, x2: i32, y2: i32) {
+        caller.screen_lock().await.fill_rect(x1, y1, x2, y2);
+        Ok(())
+    });
+
+    host_fn!(linker, module, fn vexDisplayCopyRect(
+        caller,
+        x1: i32,
+        y1: i32,
+        x2: i32,
+        y2: i32,
+        #[in_memory] source: u32,
+        stride: i32,
+    ) {
+        // parts off the screen are ignored, and so are rectangles whose corners are the wrong
+        // way around
+        if x1 > x2 || y1 > y2 {
+            return Ok(());
+        }
+        let Some(rect) = Rect::clipped(x1, y1, x2, y2) else {
+            return Ok(());
+        };
+        // only the pixels from the top left to the bottom right of the visible part are read,
+        // counted in u128 so that no rectangle or stride overflows
+        let stride = stride.max(0) as usize;
+        let bytes = |rows: i64, columns: i64| (rows as u128 * stride as u128 + columns as u128) * 4;
+        let start = u128::from(source)
+            + bytes(
+                i64::from(rect.y1) - i64::from(y1),
+                i64::from(rect.x1) - i64::from(x1),
+            );
+        let len = bytes(
+            i64::from(rect.y2 - rect.y1),
+            i64::from(rect.x2 - rect.x1) + 1,
+        );
+        let (start, len) = usize::try_from(start)
+            .ok()
+            .zip(usize::try_from(len).ok())
+            .ok_or(OutOfBoundsError)?;
+        let pixels = caller.memory().read_relaxed(start, len)?;
+        caller
+            .screen_lock()
+            .await
+            .copy_rect(rect, &pixels, stride);
+        Ok(())
+    });
+
+    host_fn!(linker, module, fn vexDisplayRender(caller, _vsync: u32, run_scheduler: u32) {
+        caller.screen_lock().await.render();
+        if run_scheduler != 0 {
+            TaskPool::yield_now().await;
+        }
+        Ok(())
+    });
+
+    host_fn!(linker, module, fn vexTouchDataGet(caller, #[in_memory] status: u32) {
+        let touch = caller.screen_lock().await.touch_status();
+        caller
+            .memory()
+            .write_relaxed(status as usize, &touch.to_bytes())?;
+        Ok(())
+    });
+
+    Ok(())
+}
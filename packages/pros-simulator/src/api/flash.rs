@@ -0,0 +1,95 @@
+//! Flash API - simulator-specific functions for saving small values that are kept between runs,
+//! like an autonomous selection or odometry calibration. See
+//! [`SimulatorOptions::flash`](crate::SimulatorOptions::flash). Robot code running on a V5 can't
+//! link them, so they should be imported weakly.
+//!
+//! Keys are C strings that can't be empty or contain `=` or line breaks, and values are any
+//! bytes. Up to [`FLASH_CAPACITY`] bytes of keys and values can be stored.
+//!
+//! ## Reference
+//!
+//! * `sim_flash_read`
+//!   Copies as much of the value saved under a key as fits into a buffer, returning the value's
+//!   full length. Fails with `ENOENT` if nothing is saved under the key.
+//! * `sim_flash_write`
+//!   Saves a value under a key, replacing any value already saved there, and returns 1. Fails
+//!   with `ENOSPC` if the value doesn't fit.
+//! * `sim_flash_erase`
+//!   Removes the value saved under a key and returns 1. Fails with `ENOENT` if there isn't one.
+
+use pros_simulator_interface::SimulatorEvent;
+use pros_sys::{EINVAL, ENOENT, ENOSPC};
+use wasmtime::{Caller, Linker};
+
+use crate::host::{
+    flash::{Flash, FLASH_CAPACITY},
+    memory::SharedMemoryExt,
+    Host, HostCtx,
+};
+
+/// Reads a key, failing with `EINVAL` if robot code can't use it.
+fn read_key(caller: &Caller<'_, Host>, key: u32) -> Result<String, i32> {
+    let key = caller.read_c_str(key)?;
+    if !Flash::is_valid_key(&key) {
+        return Err(EINVAL);
+    }
+    Ok(key)
+}
+
+/// Writes the flash to its file, warning if it can't be. Robot code carries on either way, since
+/// the values are still there until the simulation stops.
+fn save(caller: &Caller<'_, Host>, flash: &Flash) {
+    if let Err(err) = flash.save() {
+        let path = flash.path().unwrap();
+        caller.interface().send(SimulatorEvent::Warning(format!(
+            "Failed to save the flash to {}: {err}",
+            path.display()
+        )));
+    }
+}
+
+pub fn configure_flash_api(linker: &mut Linker<Host>) -> anyhow::Result<()> {
+    host_fn!(linker, "env", #[errno(-1)] fn sim_flash_read(
+        caller,
+        #[in_memory] key: u32,
+        #[buffer(len)] buffer: u32,
+        len: u32,
+    ) -> i32 {
+        let key = read_key(&caller, key)?;
+        let value = caller.flash_lock().await.get(&key).ok_or(ENOENT)?.to_vec();
+        let copied = value.len().min(len as usize);
+        caller
+            .memory()
+            .write_relaxed(buffer as usize, &value[..copied])?;
+        Ok(value.len() as i32)
+    });
+
+    host_fn!(linker, "env", #[errno(-1)] fn sim_flash_write(
+        caller,
+        #[in_memory] key: u32,
+        #[buffer(len)] buffer: u32,
+        len: u32,
+    ) -> i32 {
+        let key = read_key(&caller, key)?;
+        if len as usize > FLASH_CAPACITY {
+            return Err(ENOSPC);
+        }
+        let value = caller.memory().read_relaxed(buffer as usize, len as usize)?;
+        let mut flash = caller.flash_lock().await;
+        flash.set(&key, value).map_err(|_| ENOSPC)?;
+        save(&caller, &flash);
+        Ok(1)
+    });
+
+    host_fn!(linker, "env", #[errno(-1)] fn sim_flash_erase(caller, #[in_memory] key: u32) -> i32 {
+        let key = read_key(&caller, key)?;
+        let mut flash = caller.flash_lock().await;
+        if !flash.remove(&key) {
+            return Err(ENOENT);
+        }
+        save(&caller, &flash);
+        Ok(1)
+    });
+
+    Ok(())
+}
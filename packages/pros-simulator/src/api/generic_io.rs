@@ -5,95 +5,315 @@
 //!
 //! * `__errno`
 //! * `sim_abort`
-//!   This is a simulator-specific function that will print the given message to stderr and exit.
+//!   This is a simulator-specific function that will stop the robot code with the given error
+//!   message.
 //! * `sim_log_backtrace`
 //!   This is a simulator-specific function that will print a backtrace to the debug terminal.
+//! * `sim_random`
+//!   This is a simulator-specific function that returns a random 64-bit integer. The generator
+//!   is seeded by `SimulatorOptions::deterministic` so that tests using it are reproducible.
+//! * `sim_assert`
+//!   This is a simulator-specific function that reports a failed self-check to the frontend if
+//!   its condition is false, for collecting into test reports.
+//! * `sim_log`
+//!   This is a simulator-specific function that logs a message at a level from 1 (error) to 5
+//!   (trace), like the `log` crate numbers them, so frontends can filter robot code's logs by
+//!   severity instead of treating them all as console output.
+//! * `sim_is_simulator`
+//!   This is a simulator-specific function that returns 1, so robot code can tell it's being
+//!   simulated. Robot code running on a V5 can't link it, so it should be imported weakly.
+//! * `sim_capability`
+//!   This is a simulator-specific function that returns whether the simulator has the named
+//!   capability. See [`has_capability`].
+//! * `sim_emit_event`
+//!   This is a simulator-specific function that sends a payload of robot code's choosing to the
+//!   frontend, tagged so the frontend knows how to decode it.
+//! * `sim_poll_message`
+//!   This is a simulator-specific function that copies the oldest payload a frontend sent with
+//!   the given tag into a buffer, returning the payload's length, or -1 if there isn't one.
+//!   Payloads longer than the buffer are cut short.
+//! * `sim_profile_begin`
+//!   This is a simulator-specific function that begins a named span on the current task, for
+//!   timing a section of robot code on the simulation's clock. Spans are exported with
+//!   `SimulatorOptions::chrome_trace`, and this does nothing otherwise.
+//! * `sim_profile_end`
+//!   This is a simulator-specific function that ends the current task's innermost span with
+//!   the given name, along with any spans begun inside it. Fails with `EINVAL` if the task
+//!   doesn't have a span with that name open.
 //! * `exit`
 //! * `puts`
 
-use std::process::exit;
-
-use pros_simulator_interface::SimulatorEvent;
+use anyhow::bail;
+use pros_simulator_interface::{LogLevel, SimulatorEvent};
 use wasmtime::{Caller, Linker, WasmBacktrace};
 
-use crate::host::{memory::SharedMemoryExt, task::TaskPool, ContextExt, Host, HostCtx};
+use super::rtos_facilities::sleep_until;
+use crate::{
+    host::{
+        backtrace::backtrace_frames, memory::SharedMemoryExt, serial::Writer, ContextExt, Host,
+        HostCtx,
+    },
+    SimulatorOptions, StartKind, StopReason, WarningKind,
+};
+
+/// Stops the simulation with the given exit code. Never returns.
+pub(super) async fn exit(caller: &Caller<'_, Host>, code: i32) -> anyhow::Result<()> {
+    caller.serial().flush_stdout();
+    if code != 0 {
+        let writer = console_writer(caller).await;
+        caller.serial().write(writer, format!("Error {code}\n"));
+    }
+    caller
+        .tasks_lock()
+        .await
+        .start_shutdown(StopReason::Exited(code));
+    // the scheduler stops this task before it runs again
+    futures_util::future::pending::<()>().await;
+    unreachable!("exit")
+}
+
+/// Sends text written by the robot code to the console, warning if it isn't valid UTF-8. Text
+/// written to `stdout` may be line buffered.
+pub(super) async fn write_console(caller: &Caller<'_, Host>, buffer: Vec<u8>, stdout: bool) {
+    let buffer_string = match String::from_utf8(buffer) {
+        Ok(string) => string,
+        Err(err) => {
+            caller.interface().send(SimulatorEvent::Warning(
+                "Text written to the console is not valid UTF-8".into(),
+            ));
+            String::from_utf8_lossy(err.as_bytes()).into_owned()
+        }
+    };
+    send_console(caller, buffer_string, stdout).await;
+}
+
+/// Sends a message over the serial connection, blocking the current task if its transmit buffer
+/// is full. Messages written to `stdout` may be line buffered first.
+async fn send_console(caller: &Caller<'_, Host>, message: String, stdout: bool) {
+    let writer = console_writer(caller).await;
+    let resume = if stdout {
+        caller.serial().write_stdout(writer, message)
+    } else {
+        caller.serial().write(writer, message)
+    };
+    if let Some(resume) = resume {
+        sleep_until(caller, resume).await;
+    }
+}
+
+/// The current task, which console output is attributed to.
+async fn console_writer(caller: &Caller<'_, Host>) -> Writer {
+    let task = caller.current_task().await;
+    let task = task.lock().await;
+    Writer {
+        task_id: task.id(),
+        task_name: task.name().to_string(),
+    }
+}
+
+/// Whether the simulation has a capability that robot code may want to adapt to:
+///
+/// * `threaded`: tasks run in parallel on their own threads.
+/// * `deterministic`: runs are reproducible from a seed.
+/// * `jitter`: the schedule and delays are randomly perturbed.
+/// * `match`: a competition match is run automatically.
+/// * `hot-start`: only the hot image was started, so `cold_init` didn't run. See [`StartKind`].
+/// * `test-build`: the test build API, like `sim_set_pose`, can be used. See
+///   [`SimulatorOptions::test_build`].
+/// * `profile`: spans timed with `sim_profile_begin` and `sim_profile_end` are recorded. See
+///   [`SimulatorOptions::chrome_trace`].
+///
+/// Other capabilities, including sensors and devices the simulator doesn't model, aren't
+/// supported.
+fn has_capability(options: &SimulatorOptions, name: &str) -> bool {
+    match name {
+        "threaded" => options.threaded,
+        "deterministic" => options.seed.is_some(),
+        "jitter" => options.jitter.is_some(),
+        "match" => options.match_timing.is_some(),
+        "hot-start" => options.start_kind == StartKind::Hot,
+        "test-build" => options.test_build,
+        "profile" => options.chrome_trace.is_some(),
+        _ => false,
+    }
+}
 
 pub fn configure_generic_io_api(linker: &mut Linker<Host>) -> anyhow::Result<()> {
-    linker.func_wrap0_async("env", "__errno", |mut caller: Caller<'_, Host>| {
-        Box::new(async move { Ok(caller.errno_address().await) })
-    })?;
-
-    linker.func_wrap1_async("env", "sim_abort", |caller: Caller<'_, Host>, msg: u32| {
-        Box::new(async move {
-            let backtrace = WasmBacktrace::force_capture(&caller);
-            let abort_msg = caller.memory().read_c_str(msg).unwrap();
-            eprintln!("{abort_msg}");
-            eprintln!("{backtrace}");
-            exit(1);
-        })
-    })?;
-
-    linker.func_wrap1_async("env", "puts", |caller: Caller<'_, Host>, buffer: u32| {
-        Box::new(async move {
-            let mut console_message = caller.memory().read_c_str(buffer).unwrap();
-            console_message.push('\n');
-            caller
-                .interface()
-                .send(SimulatorEvent::ConsoleMessage(console_message));
-            u32::from(true)
-        })
-    })?;
-
-    linker.func_wrap3_async(
-        "env",
-        "write",
-        |mut caller: Caller<'_, Host>, fd: i32, buffer: u32, count: u32| {
-            Box::new(async move {
-                if fd < 0 || count > i32::MAX as u32 {
-                    caller.set_errno(pros_sys::EINVAL).await;
-                    return Ok(-1);
-                }
-                if fd != 1 && fd != 2 {
-                    caller.set_errno(pros_sys::EBADF).await;
-                    return Ok(-1);
-                }
-
-                let buffer = caller
-                    .memory()
-                    .read_relaxed(buffer as usize, count as usize)?;
-                let buffer_string = String::from_utf8(buffer).unwrap();
+    host_fn!(linker, "env", fn __errno(caller) -> u32 {
+        if caller.options().check_errno {
+            let warning = {
+                let task = caller.current_task().await;
+                let mut task = task.lock().await;
+                task.should_warn_unset_errno().then(|| {
+                    format!(
+                        "Task `{}` read errno before any API call set it, so it's only 0 because \
+                         memory starts out zeroed. Check the return value of a call before \
+                         checking errno",
+                        task.name()
+                    )
+                })
+            };
+            if let Some(warning) = warning {
+                caller.interface().send(SimulatorEvent::Warning(warning));
                 caller
-                    .interface()
-                    .send(SimulatorEvent::ConsoleMessage(buffer_string));
-                Ok(count as i32)
-            })
-        },
-    )?;
-
-    linker.func_wrap1_async("env", "exit", |caller: Caller<'_, Host>, code: i32| {
-        Box::new(async move {
-            if code != 0 {
-                caller
-                    .interface()
-                    .send(SimulatorEvent::ConsoleMessage(format!("Error {code}\n")));
-            }
-            {
-                let mut tasks = caller.tasks_lock().await;
-                tasks.start_shutdown();
+                    .tasks_lock()
+                    .await
+                    .fail_if_strict(WarningKind::UnsetErrno);
             }
-            TaskPool::yield_now().await;
-            unreachable!("exit")
-        })
-    })?;
-
-    linker.func_wrap0_async("env", "sim_log_backtrace", |caller: Caller<'_, Host>| {
-        Box::new(async move {
-            let backtrace = WasmBacktrace::force_capture(&caller);
-            caller
-                .interface()
-                .send(SimulatorEvent::ConsoleMessage(format!("{backtrace}\n",)));
-        })
-    })?;
+        }
+        caller.errno_address().await
+    });
+
+    host_fn!(linker, "env", fn sim_abort(caller, msg: u32) {
+        let abort_msg = caller.read_c_str(msg)?;
+        bail!(abort_msg)
+    });
+
+    host_fn!(linker, "env", #[errno(-1)] fn puts(caller, #[in_memory] buffer: u32) -> i32 {
+        let mut console_message = caller.read_c_str(buffer)?;
+        console_message.push('\n');
+        send_console(&caller, console_message, true).await;
+        Ok(1)
+    });
+
+    host_fn!(linker, "env", #[errno(-1)] fn write(
+        caller,
+        fd: i32,
+        #[buffer(count)] buffer: u32,
+        count: u32,
+    ) -> i32 {
+        if fd < 0 || count > i32::MAX as u32 {
+            return Err(pros_sys::EINVAL);
+        }
+        if fd != 1 && fd != 2 {
+            return Err(pros_sys::EBADF);
+        }
+
+        let buffer = caller
+            .memory()
+            .read_relaxed(buffer as usize, count as usize)?;
+        write_console(&caller, buffer, fd == 1).await;
+        Ok(count as i32)
+    });
+
+    host_fn!(linker, "env", fn exit(caller, code: i32) {
+        exit(&caller, code).await
+    });
+
+    host_fn!(linker, "env", fn sim_log_backtrace(caller) {
+        let backtrace = WasmBacktrace::force_capture(&caller);
+        send_console(&caller, format!("{backtrace}\n"), false).await;
+        Ok(())
+    });
+
+    host_fn!(linker, "env", fn sim_random(caller) -> u64 {
+        Ok(caller.rng_lock().await.u64(..))
+    });
+
+    host_fn!(linker, "env", #[errno(-1)] fn sim_log(
+        caller,
+        #[log_level] level: i32,
+        #[in_memory] message: u32,
+    ) -> i32 {
+        let level = LogLevel::from_number(level).ok_or(pros_sys::EINVAL)?;
+        let message = caller.read_c_str(message)?;
+        let (task_id, task_name) = {
+            let task = caller.current_task().await;
+            let task = task.lock().await;
+            (task.id(), task.name().to_string())
+        };
+        caller.interface().send(SimulatorEvent::Log {
+            level,
+            message,
+            task_id,
+            task_name,
+        });
+        Ok(1)
+    });
+
+    host_fn!(linker, "env", #[errno(-1)] fn sim_emit_event(
+        caller,
+        #[in_memory] tag: u32,
+        #[buffer(len)] payload: u32,
+        len: u32,
+    ) -> i32 {
+        let tag = caller.read_c_str(tag)?;
+        let bytes = caller.memory().read_relaxed(payload as usize, len as usize)?;
+        caller.interface().send(SimulatorEvent::Custom { tag, bytes });
+        Ok(1)
+    });
+
+    host_fn!(linker, "env", #[errno(-1)] fn sim_profile_begin(
+        caller,
+        #[in_memory] name: u32,
+    ) -> i32 {
+        let name = caller.read_c_str(name)?;
+        if let Some(trace) = caller.chrome_trace() {
+            let task = caller.current_task().await;
+            let task = task.lock().await;
+            trace.begin(task.id(), task.name(), name, caller.ticks());
+        }
+        Ok(1)
+    });
+
+    host_fn!(linker, "env", #[errno(-1)] fn sim_profile_end(
+        caller,
+        #[in_memory] name: u32,
+    ) -> i32 {
+        let name = caller.read_c_str(name)?;
+        if let Some(trace) = caller.chrome_trace() {
+            let task_id = caller.current_task().await.lock().await.id();
+            trace.end(task_id, &name, caller.ticks())?;
+        }
+        Ok(1)
+    });
+
+    host_fn!(linker, "env", fn sim_poll_message(
+        caller,
+        #[in_memory] tag: u32,
+        #[buffer(len)] buffer: u32,
+        len: u32,
+    ) -> i32 {
+        let tag = caller.read_c_str(tag)?;
+        let Some(bytes) = caller.custom_messages_lock().await.take(&tag) else {
+            return Ok(-1);
+        };
+        let copied = bytes.len().min(len as usize);
+        caller
+            .memory()
+            .write_relaxed(buffer as usize, &bytes[..copied])?;
+        Ok(bytes.len() as i32)
+    });
+
+    host_fn!(linker, "env", fn sim_is_simulator(_caller) -> i32 {
+        Ok(1)
+    });
+
+    host_fn!(linker, "env", fn sim_capability(caller, #[in_memory] name: u32) -> i32 {
+        let name = caller.read_c_str(name)?;
+        Ok(has_capability(&caller.options(), &name) as i32)
+    });
+
+    host_fn!(linker, "env", fn sim_assert(caller, condition: i32, message: u32) {
+        if condition != 0 {
+            return Ok(());
+        }
+        let message = caller.read_c_str(message)?;
+        let backtrace = backtrace_frames(&WasmBacktrace::force_capture(&caller));
+        let (task_id, task_name) = {
+            let task = caller.current_task().await;
+            let task = task.lock().await;
+            (task.id(), task.name().to_string())
+        };
+        caller.interface().send(SimulatorEvent::AssertionFailed {
+            message,
+            task_id,
+            task_name,
+            backtrace,
+        });
+        caller.tasks_lock().await.assertion_failed();
+        Ok(())
+    });
 
     Ok(())
 }
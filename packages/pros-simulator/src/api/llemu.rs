@@ -1,5 +1,7 @@
 //! Legacy LCD Emulator API
 //!
+//! The LCD is drawn onto the brain's screen once it's initialized, like on a real brain.
+//!
 //! ## Reference
 //!
 //! * `lcd_clear`
@@ -11,6 +13,8 @@
 //! * `lcd_register_btn0_cb`
 //! * `lcd_register_btn1_cb`
 //! * `lcd_register_btn2_cb`
+//!   Passing `NULL` unregisters the button's callback. Registering something that isn't a
+//!   function with no arguments fails with `EINVAL`.
 //! * `lcd_set_text`
 //! * `lcd_shutdown` (not implemented)
 //! * `lcd_set_background_color` (not implemented)
@@ -18,45 +22,43 @@
 
 use wasmtime::{Caller, Linker};
 
-use crate::host::{memory::SharedMemoryExt, Host, HostCtx, ResultExt};
+use crate::host::{lcd::Lcd, Host, HostCtx, ResultExt};
 
 pub fn configure_llemu_api(linker: &mut Linker<Host>) -> anyhow::Result<()> {
-    linker.func_wrap0_async("env", "lcd_initialize", |caller: Caller<'_, Host>| {
-        Box::new(async move {
-            let res = caller.lcd_lock().await.initialize();
-            Ok(u32::from(res.is_ok()))
-        })
-    })?;
+    host_fn!(linker, "env", fn lcd_initialize(caller) -> u32 {
+        let mut lcd = caller.lcd_lock().await;
+        let res = lcd.initialize();
+        if res.is_ok() {
+            lcd.draw(&mut *caller.screen_lock().await);
+        }
+        Ok(u32::from(res.is_ok()))
+    });
 
-    linker.func_wrap2_async(
-        "env",
-        "lcd_set_text",
-        |mut caller: Caller<'_, Host>, line: i32, text_ptr: u32| {
-            Box::new(async move {
-                let text = caller.memory().read_c_str(text_ptr)?;
-                let res = caller.lcd_lock().await.set_line(line, &text);
-                Ok(u32::from(res.unwrap_or_errno(&mut caller).await))
-            })
-        },
-    )?;
+    host_fn!(linker, "env", #[errno(0)] fn lcd_set_text(
+        caller,
+        #[lcd_line] line: i32,
+        #[in_memory] text_ptr: u32,
+    ) -> u32 {
+        let text = caller.read_c_str(text_ptr)?;
+        let mut lcd = caller.lcd_lock().await;
+        lcd.set_line(line, &text)?;
+        lcd.draw_line(line as usize, &mut *caller.screen_lock().await);
+        Ok(1)
+    });
 
-    linker.func_wrap1_async(
-        "env",
-        "lcd_clear_line",
-        |mut caller: Caller<'_, Host>, line: i32| {
-            Box::new(async move {
-                let res = caller.lcd_lock().await.clear_line(line);
-                Ok(u32::from(res.unwrap_or_errno(&mut caller).await))
-            })
-        },
-    )?;
+    host_fn!(linker, "env", #[errno(0)] fn lcd_clear_line(caller, #[lcd_line] line: i32) -> u32 {
+        let mut lcd = caller.lcd_lock().await;
+        lcd.clear_line(line)?;
+        lcd.draw_line(line as usize, &mut *caller.screen_lock().await);
+        Ok(1)
+    });
 
-    linker.func_wrap0_async("env", "lcd_clear", |mut caller: Caller<'_, Host>| {
-        Box::new(async move {
-            let res = caller.lcd_lock().await.clear();
-            Ok(u32::from(res.unwrap_or_errno(&mut caller).await))
-        })
-    })?;
+    host_fn!(linker, "env", #[errno(0)] fn lcd_clear(caller) -> u32 {
+        let mut lcd = caller.lcd_lock().await;
+        lcd.clear()?;
+        lcd.draw(&mut *caller.screen_lock().await);
+        Ok(1)
+    });
 
     for lcd_button in 0..3 {
         linker.func_wrap1_async(
@@ -64,13 +66,20 @@ pub fn configure_llemu_api(linker: &mut Linker<Host>) -> anyhow::Result<()> {
             &format!("lcd_register_btn{lcd_button}_cb"),
             move |mut caller: Caller<'_, Host>, cb: u32| {
                 Box::new(async move {
-                    let res = {
-                        caller
-                            .lcd_lock()
-                            .await
-                            .set_btn_press_callback(lcd_button, cb)
+                    let (table, task_name) = {
+                        let task = caller.current_task().await;
+                        let task = task.lock().await;
+                        (task.indirect_call_table, task.name().to_string())
                     };
-                    Ok(u32::from(res.unwrap_or_errno(&mut caller).await))
+                    let lcd = caller.lcd();
+                    // NULL unregisters the callback
+                    let res = lcd.lock().await.set_btn_press_callback(
+                        lcd_button,
+                        (cb != 0).then_some(cb),
+                        &task_name,
+                        |cb| Lcd::callback(&mut caller, table, cb).is_some(),
+                    );
+                    Ok(u32::from(res.unwrap_or_errno(&mut caller).await?))
                 })
             },
         )?;
@@ -10,134 +10,175 @@
 //! * `competition_is_autonomous`
 //! * `competition_is_connected`
 //! * `competition_is_disabled`
-//! * `controller_clear` (not implemented)
-//! * `controller_clear_line` (not implemented)
+//! * `controller_clear`
+//! * `controller_clear_line`
 //! * `controller_get_analog`
 //! * `controller_get_battery_capacity`
 //! * `controller_get_battery_level` (Return value always equal to capacity)
 //! * `controller_get_digital`
 //! * `controller_get_digital_new_press`
 //! * `controller_is_connected`
-//! * `controller_print` (not implemented)
-//! * `controller_rumble` (not implemented)
-//! * `controller_set_text` (not implemented)
+//! * `controller_print`
+//!   See [`printf`](super::printf) for the formats it supports.
+//! * `controller_rumble`
+//! * `controller_set_text`
 //! * `usd_is_installed` (not implemented)
+//!
+//! Like on a real controller, writes to a controller's screen and rumbles share its radio link,
+//! so they fail with `EAGAIN` and are dropped if they come less than 50 ms after the last one
+//! that was sent. They also fail with `EAGAIN` while the controller is disconnected.
 
-use wasmtime::{Caller, Linker};
+use pros_simulator_interface::{ControllerId, SimulatorEvent};
+use pros_sys::{E_CONTROLLER_PARTNER, PROS_ERR};
+use wasmtime::Linker;
 
+use super::printf;
 use crate::{
-    host::{Host, HostCtx, ResultExt},
+    host::{Host, HostCtx},
     system::system_daemon::CompetitionPhaseExt,
 };
 
+/// Tells the frontend what's on a controller's screen after a write.
+fn send_controller_text(caller: &impl HostCtx, id: u32, lines: Vec<String>) {
+    caller
+        .interface()
+        .send(SimulatorEvent::ControllerTextUpdated {
+            controller: controller_id(id),
+            lines,
+        });
+}
+
+/// The controller with a `controller_id_e_t` that's already been validated.
+fn controller_id(id: u32) -> ControllerId {
+    if id == E_CONTROLLER_PARTNER {
+        ControllerId::Partner
+    } else {
+        ControllerId::Master
+    }
+}
+
 pub fn configure_misc_api(linker: &mut Linker<Host>) -> anyhow::Result<()> {
-    linker.func_wrap2_async(
-        "env",
-        "controller_get_analog",
-        |mut caller: Caller<'_, Host>, id: u32, channel: u32| {
-            Box::new(async move {
-                let controllers = caller.controllers_lock().await;
-                let res = controllers.get_analog(id, channel);
-                drop(controllers);
-                Ok(res.unwrap_or_errno_as(&mut caller, 0).await)
-            })
-        },
-    )?;
-
-    linker.func_wrap2_async(
-        "env",
-        "controller_get_digital",
-        |mut caller: Caller<'_, Host>, id: u32, button: u32| {
-            Box::new(async move {
-                let controllers = caller.controllers_lock().await;
-                let res = controllers.get_digital(id, button);
-                drop(controllers);
-                Ok(i32::from(res.unwrap_or_errno_as(&mut caller, false).await))
-            })
-        },
-    )?;
-
-    linker.func_wrap2_async(
-        "env",
-        "controller_get_digital_new_press",
-        |mut caller: Caller<'_, Host>, id: u32, button: u32| {
-            Box::new(async move {
-                let mut controllers = caller.controllers_lock().await;
-                let res = controllers.get_digital_new_press(id, button);
-                drop(controllers);
-                Ok(i32::from(res.unwrap_or_errno_as(&mut caller, false).await))
-            })
-        },
-    )?;
-
-    linker.func_wrap1_async(
-        "env",
-        "controller_is_connected",
-        |mut caller: Caller<'_, Host>, id: u32| {
-            Box::new(async move {
-                let controllers = caller.controllers_lock().await;
-                let res = controllers.is_connected(id);
-                drop(controllers);
-                Ok(i32::from(res.unwrap_or_errno(&mut caller).await))
-            })
-        },
-    )?;
-
-    linker.func_wrap1_async(
-        "env",
-        "controller_get_battery_capacity",
-        |_caller: Caller<'_, Host>, _id: u32| Box::new(async move { Ok(100i32) }),
-    )?;
-
-    linker.func_wrap1_async(
-        "env",
-        "controller_get_battery_level",
-        |_caller: Caller<'_, Host>, _id: u32| Box::new(async move { Ok(100i32) }),
-    )?;
-
-    linker.func_wrap0_async(
-        "env",
-        "competition_get_status",
-        |caller: Caller<'_, Host>| {
-            Box::new(async move {
-                let phase = caller.competition_phase_lock().await;
-                Ok(phase.as_bits() as i32)
-            })
-        },
-    )?;
-
-    linker.func_wrap0_async(
-        "env",
-        "competition_is_autonomous",
-        |caller: Caller<'_, Host>| {
-            Box::new(async move {
-                let phase = caller.competition_phase_lock().await;
-                Ok(i32::from(phase.autonomous))
-            })
-        },
-    )?;
-
-    linker.func_wrap0_async(
-        "env",
-        "competition_is_connected",
-        |caller: Caller<'_, Host>| {
-            Box::new(async move {
-                let phase = caller.competition_phase_lock().await;
-                Ok(i32::from(phase.is_competition))
-            })
-        },
-    )?;
-
-    linker.func_wrap0_async(
-        "env",
-        "competition_is_disabled",
-        |caller: Caller<'_, Host>| {
-            Box::new(async move {
-                let phase = caller.competition_phase_lock().await;
-                Ok(i32::from(!phase.enabled))
-            })
-        },
-    )?;
+    host_fn!(linker, "env", #[errno(0)] fn controller_get_analog(
+        caller,
+        #[controller] id: u32,
+        channel: u32,
+    ) -> i32 {
+        caller.controllers_lock().await.get_analog(id, channel)
+    });
+
+    host_fn!(linker, "env", #[errno(0)] fn controller_get_digital(
+        caller,
+        #[controller] id: u32,
+        button: u32,
+    ) -> i32 {
+        caller.controllers_lock().await.get_digital(id, button).map(i32::from)
+    });
+
+    host_fn!(linker, "env", #[errno(0)] fn controller_get_digital_new_press(
+        caller,
+        #[controller] id: u32,
+        button: u32,
+    ) -> i32 {
+        let mut controllers = caller.controllers_lock().await;
+        controllers.get_digital_new_press(id, button).map(i32::from)
+    });
+
+    host_fn!(linker, "env", #[errno(0)] fn controller_is_connected(
+        caller,
+        #[controller] id: u32,
+    ) -> i32 {
+        caller.controllers_lock().await.is_connected(id).map(i32::from)
+    });
+
+    host_fn!(linker, "env", #[errno(PROS_ERR)] fn controller_set_text(
+        caller,
+        #[controller] id: u32,
+        line: u32,
+        col: u32,
+        #[in_memory] text: u32,
+    ) -> i32 {
+        let text = caller.read_c_str(text)?;
+        let tick = caller.ticks();
+        let lines = caller.controllers_lock().await.set_text(id, line, col, &text, tick)?;
+        send_controller_text(&caller, id, lines);
+        Ok(1)
+    });
+
+    host_fn!(linker, "env", #[errno(PROS_ERR)] fn controller_print(
+        caller,
+        #[controller] id: u32,
+        line: u32,
+        col: u32,
+        #[in_memory] format: u32,
+        args: u32,
+    ) -> i32 {
+        let format = caller.read_c_str(format)?;
+        let text = printf::format(&caller.memory(), &format, args)?;
+        let tick = caller.ticks();
+        let lines = caller.controllers_lock().await.set_text(id, line, col, &text, tick)?;
+        send_controller_text(&caller, id, lines);
+        Ok(1)
+    });
+
+    host_fn!(linker, "env", #[errno(PROS_ERR)] fn controller_clear_line(
+        caller,
+        #[controller] id: u32,
+        line: u32,
+    ) -> i32 {
+        let tick = caller.ticks();
+        let lines = caller.controllers_lock().await.clear_line(id, line, tick)?;
+        send_controller_text(&caller, id, lines);
+        Ok(1)
+    });
+
+    host_fn!(linker, "env", #[errno(PROS_ERR)] fn controller_clear(
+        caller,
+        #[controller] id: u32,
+    ) -> i32 {
+        let tick = caller.ticks();
+        let lines = caller.controllers_lock().await.clear(id, tick)?;
+        send_controller_text(&caller, id, lines);
+        Ok(1)
+    });
+
+    host_fn!(linker, "env", #[errno(PROS_ERR)] fn controller_rumble(
+        caller,
+        #[controller] id: u32,
+        #[in_memory] pattern: u32,
+    ) -> i32 {
+        let pattern = caller.read_c_str(pattern)?;
+        let tick = caller.ticks();
+        let pattern = caller.controllers_lock().await.rumble(id, &pattern, tick)?;
+        caller.interface().send(SimulatorEvent::ControllerRumbled {
+            controller: controller_id(id),
+            pattern,
+        });
+        Ok(1)
+    });
+
+    host_fn!(linker, "env", fn controller_get_battery_capacity(_caller, _id: u32) -> i32 {
+        Ok(100)
+    });
+
+    host_fn!(linker, "env", fn controller_get_battery_level(_caller, _id: u32) -> i32 {
+        Ok(100)
+    });
+
+    host_fn!(linker, "env", fn competition_get_status(caller) -> i32 {
+        Ok(caller.competition_phase_lock().await.as_bits() as i32)
+    });
+
+    host_fn!(linker, "env", fn competition_is_autonomous(caller) -> i32 {
+        Ok(i32::from(caller.competition_phase_lock().await.autonomous))
+    });
+
+    host_fn!(linker, "env", fn competition_is_connected(caller) -> i32 {
+        Ok(i32::from(caller.competition_phase_lock().await.is_competition))
+    });
+
+    host_fn!(linker, "env", fn competition_is_disabled(caller) -> i32 {
+        Ok(i32::from(!caller.competition_phase_lock().await.enabled))
+    });
 
     Ok(())
 }
@@ -0,0 +1,90 @@
+//! V5 Smart Motor API
+//!
+//! ## Reference
+//!
+//! * `motor_brake` (not implemented)
+//! * `motor_modify_profiled_velocity` (not implemented)
+//! * `motor_move`
+//! * `motor_move_absolute` (not implemented)
+//! * `motor_move_relative` (not implemented)
+//! * `motor_move_velocity` (not implemented)
+//! * `motor_move_voltage`
+//!
+//! Motors must be plugged in with [`SimulatorOptions::smart_port`](crate::SimulatorOptions::smart_port).
+//! Their voltage is sent to the frontend along with their velocity, position and current draw
+//! from a model of a motor with nothing attached, whenever any of them changes and at most as
+//! often as [`SimulatorOptions::motor_update_rate`](crate::SimulatorOptions::motor_update_rate)
+//! allows.
+
+use pros_simulator_interface::SimulatorEvent;
+use pros_sys::PROS_ERR;
+use wasmtime::Linker;
+
+use crate::{
+    host::{smart_ports::MAX_MOTOR_MILLIVOLTS, Host, HostCtx},
+    WarningKind,
+};
+
+/// Drives the motor plugged into the port at the given voltage, telling the frontend if it
+/// changed.
+async fn set_voltage(host: &(impl HostCtx + Sync), port: u32, millivolts: i32) -> Result<(), i32> {
+    let updates = {
+        let mut ports = host.smart_ports_lock().await;
+        let tick = host.ticks();
+        ports.set_motor_voltage(port, millivolts, tick)?;
+        ports.take_motor_updates(host.millis(), tick)
+    };
+    for event in updates {
+        host.interface().send(event);
+    }
+    Ok(())
+}
+
+/// Clamps a value passed to `api` to `-max..=max`, warning the frontend if it was out of range.
+async fn clamp_voltage(
+    caller: &(impl HostCtx + Sync),
+    api: &str,
+    port: u32,
+    value: i32,
+    max: i32,
+) -> i32 {
+    let clamped = value.clamp(-max, max);
+    if clamped != value {
+        caller.interface().send(SimulatorEvent::Warning(format!(
+            "{api} was called with {value} for port {port}, but only accepts values from -{max} \
+             to {max}, so it was clamped to {clamped}"
+        )));
+        caller
+            .tasks_lock()
+            .await
+            .fail_if_strict(WarningKind::MotorVoltageClamped);
+    }
+    clamped
+}
+
+/// The highest voltage `motor_move` accepts, which drives the motor at 12 V.
+const MAX_MOTOR_MOVE: i32 = 127;
+
+pub fn configure_motors_api(linker: &mut Linker<Host>) -> anyhow::Result<()> {
+    host_fn!(linker, "env", #[errno(PROS_ERR)] fn motor_move(
+        caller,
+        #[motor_port] port: u32,
+        voltage: i32,
+    ) -> i32 {
+        let clamped = clamp_voltage(&caller, "motor_move", port, voltage, MAX_MOTOR_MOVE).await;
+        let millivolts = clamped * MAX_MOTOR_MILLIVOLTS / MAX_MOTOR_MOVE;
+        set_voltage(&caller, port, millivolts).await.map(|()| 1)
+    });
+
+    host_fn!(linker, "env", #[errno(PROS_ERR)] fn motor_move_voltage(
+        caller,
+        #[motor_port] port: u32,
+        voltage: i32,
+    ) -> i32 {
+        let millivolts =
+            clamp_voltage(&caller, "motor_move_voltage", port, voltage, MAX_MOTOR_MILLIVOLTS).await;
+        set_voltage(&caller, port, millivolts).await.map(|()| 1)
+    });
+
+    Ok(())
+}
@@ -0,0 +1,79 @@
+//! Newlib system calls - the functions newlib expects the platform to provide, so that robot
+//! code built from the C/C++ PROS template can run. PROS implements these in its kernel.
+//!
+//! ## Reference
+//!
+//! * `sbrk`
+//!   Memory is shared with the simulator's own buffers in robot code that doesn't export
+//!   `wasm_memalign` and `wasm_free`. See [`HostHeap`](crate::host::heap::HostHeap).
+//! * `_exit`
+//! * `gettimeofday`
+//!   Returns the host's wall-clock time.
+//! * `getpid`
+//! * `kill` (always fails with `EINVAL`)
+//! * `isatty`
+//! * `getenv` (the environment is always empty)
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use wasmtime::{Extern, Linker, Val};
+
+use super::generic_io::exit;
+use crate::host::{memory::SharedMemoryExt, Host, HostCtx};
+
+pub fn configure_newlib_api(linker: &mut Linker<Host>) -> anyhow::Result<()> {
+    host_fn!(linker, "env", #[errno(u32::MAX)] fn sbrk(caller, increment: i32) -> u32 {
+        // newlib's malloc can use the heap the linker left after the robot code's data
+        let heap_base = caller
+            .get_export("__heap_base")
+            .and_then(Extern::into_global)
+            .map(|global| global.get(&mut caller));
+        if let Some(Val::I32(heap_base)) = heap_base {
+            caller.heap().set_heap_base(heap_base as u32);
+        }
+        caller.heap().sbrk(increment).ok_or(pros_sys::ENOMEM)
+    });
+
+    host_fn!(linker, "env", fn _exit(caller, code: i32) {
+        exit(&caller, code).await
+    });
+
+    host_fn!(linker, "env", #[errno(-1)] fn gettimeofday(
+        caller,
+        #[buffer(16)] tv: u32,
+        _tz: u32,
+    ) -> i32 {
+        if tv == 0 {
+            return Ok(0);
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        // struct timeval { time_t tv_sec; suseconds_t tv_usec; }, with a 64-bit time_t
+        let memory = caller.memory();
+        memory.write_relaxed(tv as usize, &(now.as_secs() as i64).to_le_bytes())?;
+        memory.write_relaxed(tv as usize + 8, &(now.subsec_micros() as i32).to_le_bytes())?;
+        Ok(0)
+    });
+
+    host_fn!(linker, "env", fn getpid(_caller) -> i32 {
+        Ok(1)
+    });
+
+    host_fn!(linker, "env", #[errno(-1)] fn kill(caller, _pid: i32, _sig: i32) -> i32 {
+        Err(pros_sys::EINVAL)
+    });
+
+    host_fn!(linker, "env", #[errno(0)] fn isatty(caller, fd: i32) -> i32 {
+        match fd {
+            0..=2 => Ok(1),
+            _ => Err(pros_sys::EBADF),
+        }
+    });
+
+    host_fn!(linker, "env", fn getenv(_caller, _name: u32) -> u32 {
+        Ok(0)
+    });
+
+    Ok(())
+}
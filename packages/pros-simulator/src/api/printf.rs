@@ -0,0 +1,255 @@
+//! Formatting for host functions that take a `printf` format string, like `controller_print`.
+//!
+//! Robot code compiled to WebAssembly passes variadic arguments as a pointer to a buffer that
+//! holds each argument in turn, aligned to its size. Integers smaller than an `int` are promoted
+//! to one and `float`s to `double`s, so every argument is 4 or 8 bytes.
+//!
+//! The flags, width and precision work like they do in C. The `d`, `i`, `u`, `o`, `x`, `X`, `c`,
+//! `s`, `p`, `f`, `F` and `%` conversions are supported, with the `hh`, `h`, `l`, `ll`, `j`, `z`
+//! and `t` length modifiers. Anything else fails with `EINVAL`, as do widths and precisions
+//! larger than [`MAX_COUNT`].
+
+use std::iter::Peekable;
+
+use pros_sys::{EFAULT, EINVAL};
+use wasmtime::SharedMemory;
+
+use crate::host::memory::SharedMemoryExt;
+
+/// The largest width or precision, which is already far wider than any screen the text could
+/// be shown on. Without a limit, robot code could make the host allocate gigabytes of padding.
+pub const MAX_COUNT: u64 = 256;
+
+/// Formats `format` with the variadic arguments at `args`. Fails with `EFAULT` if an argument
+/// is outside of robot code memory.
+pub fn format(memory: &SharedMemory, format: &str, args: u32) -> Result<String, i32> {
+    let mut args = Args { memory, ptr: args };
+    let mut out = String::new();
+    let mut chars = format.chars().peekable();
+    while let Some(char) = chars.next() {
+        if char != '%' {
+            out.push(char);
+            continue;
+        }
+
+        let mut spec = Spec::default();
+        while let Some(&flag) = chars.peek() {
+            match flag {
+                '-' => spec.left = true,
+                '0' => spec.zero = true,
+                '+' => spec.plus = true,
+                ' ' => spec.space = true,
+                '#' => spec.alternate = true,
+                _ => break,
+            }
+            chars.next();
+        }
+        if let Some(width) = count(&mut chars, &mut args)? {
+            if width < 0 {
+                spec.left = true;
+            }
+            spec.width = width.unsigned_abs() as usize;
+        }
+        if chars.next_if_eq(&'.').is_some() {
+            let precision = count(&mut chars, &mut args)?.unwrap_or(0);
+            spec.precision = usize::try_from(precision).ok();
+        }
+        let wide = match chars.peek() {
+            Some('h') => {
+                chars.next();
+                chars.next_if_eq(&'h');
+                false
+            }
+            Some('z' | 't') => {
+                chars.next();
+                false
+            }
+            Some('l') => {
+                chars.next();
+                chars.next_if_eq(&'l').is_some()
+            }
+            Some('j') => {
+                chars.next();
+                true
+            }
+            _ => false,
+        };
+
+        let conversion = chars.next().ok_or(EINVAL)?;
+        let (sign, body) = match conversion {
+            '%' => {
+                out.push('%');
+                continue;
+            }
+            'd' | 'i' => {
+                let value = args.signed(wide)?;
+                (
+                    spec.sign(value < 0),
+                    spec.digits(value.unsigned_abs().to_string()),
+                )
+            }
+            'u' => ("", spec.digits(args.unsigned(wide)?.to_string())),
+            'o' => {
+                let prefix = if spec.alternate { "0" } else { "" };
+                let digits = spec.digits(format!("{:o}", args.unsigned(wide)?));
+                (prefix, digits)
+            }
+            'x' | 'X' => {
+                let value = args.unsigned(wide)?;
+                let prefix = match (spec.alternate && value != 0, conversion) {
+                    (false, _) => "",
+                    (true, 'x') => "0x",
+                    (true, _) => "0X",
+                };
+                let digits = match conversion {
+                    'x' => format!("{value:x}"),
+                    _ => format!("{value:X}"),
+                };
+                (prefix, spec.digits(digits))
+            }
+            'p' => ("0x", format!("{:x}", args.unsigned(false)?)),
+            'c' => ("", char::from(args.unsigned(false)? as u8).to_string()),
+            's' => {
+                let text = memory.read_c_str(args.unsigned(false)? as u32)?.text;
+                let text = match spec.precision {
+                    Some(precision) => text.chars().take(precision).collect(),
+                    None => text,
+                };
+                ("", text)
+            }
+            'f' | 'F' => {
+                let value = args.double()?;
+                let digits = format!("{:.*}", spec.precision.unwrap_or(6), value.abs());
+                (spec.sign(value.is_sign_negative()), digits)
+            }
+            _ => return Err(EINVAL),
+        };
+        spec.pad(&mut out, sign, &body, matches!(conversion, 'f' | 'F'));
+    }
+    Ok(out)
+}
+
+/// How to format one argument.
+#[derive(Debug, Default)]
+struct Spec {
+    left: bool,
+    zero: bool,
+    plus: bool,
+    space: bool,
+    alternate: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl Spec {
+    /// What goes before a signed number.
+    fn sign(&self, negative: bool) -> &'static str {
+        match (negative, self.plus, self.space) {
+            (true, _, _) => "-",
+            (false, true, _) => "+",
+            (false, false, true) => " ",
+            (false, false, false) => "",
+        }
+    }
+
+    /// Pads an integer's digits with zeros to the precision, which is the least number of
+    /// digits to show. Zero is shown with no digits if the precision is 0.
+    fn digits(&self, digits: String) -> String {
+        match self.precision {
+            Some(0) if digits == "0" => String::new(),
+            Some(precision) => format!("{digits:0>precision$}"),
+            None => digits,
+        }
+    }
+
+    /// Writes the sign and body to `out`, padded to the width. Numbers are padded with zeros
+    /// after the sign with the `0` flag, unless an integer has a precision.
+    fn pad(&self, out: &mut String, sign: &str, body: &str, float: bool) {
+        let len = sign.chars().count() + body.chars().count();
+        let padding = self.width.saturating_sub(len);
+        if self.left {
+            out.push_str(sign);
+            out.push_str(body);
+            out.extend(std::iter::repeat_n(' ', padding));
+        } else if self.zero && (float || self.precision.is_none()) {
+            out.push_str(sign);
+            out.extend(std::iter::repeat_n('0', padding));
+            out.push_str(body);
+        } else {
+            out.extend(std::iter::repeat_n(' ', padding));
+            out.push_str(sign);
+            out.push_str(body);
+        }
+    }
+}
+
+/// Reads a width or precision, which is either a number or `*` to take it from the arguments.
+/// Fails with `EINVAL` if it's larger than [`MAX_COUNT`] either way.
+fn count(
+    chars: &mut Peekable<impl Iterator<Item = char>>,
+    args: &mut Args,
+) -> Result<Option<i64>, i32> {
+    let count = if chars.next_if_eq(&'*').is_some() {
+        Some(args.signed(false)?)
+    } else {
+        digits(chars)
+    };
+    match count {
+        Some(count) if count.unsigned_abs() > MAX_COUNT => Err(EINVAL),
+        count => Ok(count),
+    }
+}
+
+/// Reads a decimal number, saturating if it doesn't fit in an `i64`.
+fn digits(chars: &mut Peekable<impl Iterator<Item = char>>) -> Option<i64> {
+    let mut count = None;
+    while let Some(digit) = chars.peek().and_then(|char| char.to_digit(10)) {
+        chars.next();
+        count = Some(
+            count
+                .unwrap_or(0i64)
+                .saturating_mul(10)
+                .saturating_add(digit.into()),
+        );
+    }
+    count
+}
+
+/// The variadic arguments that haven't been formatted yet.
+struct Args<'a> {
+    memory: &'a SharedMemory,
+    ptr: u32,
+}
+
+impl Args<'_> {
+    /// Reads the next argument, aligned to its size. Fails with `EFAULT` if it runs past the end
+    /// of the address space.
+    fn next<const N: usize>(&mut self) -> Result<[u8; N], i32> {
+        let ptr = self.ptr.checked_next_multiple_of(N as u32).ok_or(EFAULT)?;
+        let bytes = self.memory.read_relaxed(ptr as usize, N)?;
+        self.ptr = ptr.checked_add(N as u32).ok_or(EFAULT)?;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    /// An `int`, or a `long long` if `wide`.
+    fn signed(&mut self, wide: bool) -> Result<i64, i32> {
+        Ok(if wide {
+            i64::from_le_bytes(self.next()?)
+        } else {
+            i32::from_le_bytes(self.next()?).into()
+        })
+    }
+
+    /// An `unsigned int` or pointer, or an `unsigned long long` if `wide`.
+    fn unsigned(&mut self, wide: bool) -> Result<u64, i32> {
+        Ok(if wide {
+            u64::from_le_bytes(self.next()?)
+        } else {
+            u32::from_le_bytes(self.next()?).into()
+        })
+    }
+
+    fn double(&mut self) -> Result<f64, i32> {
+        Ok(f64::from_le_bytes(self.next()?))
+    }
+}
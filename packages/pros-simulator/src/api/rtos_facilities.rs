@@ -14,8 +14,10 @@
 //! * `task_delay_until`
 //! * `task_delete`
 //! * `task_get_by_name` (not implemented)
-//! * `task_get_count` (not implemented)
-//! * `task_get_current` (not implemented)
+//! * `task_get_count`
+//! * `task_get_current`
+//!   Returns the calling task's handle, the same one `task_create` returned for it, which
+//!   `task_get_name` and `task_delete` accept.
 //! * `task_get_name`
 //! * `task_get_priority` (not implemented)
 //! * `task_get_state` (not implemented)
@@ -37,245 +39,354 @@
 //! * `xTaskAbortDelay` (not implemented)
 
 use std::{
-    alloc::Layout,
-    ffi::CString,
     mem::size_of,
     time::{Duration, Instant},
 };
 
-use futures_util::Future;
-use pros_sys::TIMEOUT_MAX;
+use anyhow::ensure;
+use pros_simulator_interface::{ResourceLimit, SimulatorEvent};
+use pros_sys::{EINVAL, ENOMEM, PROS_ERR, TIMEOUT_MAX};
 use wasmtime::{Caller, Linker};
 
 use crate::host::{
+    clock::TICK_PERIOD,
     memory::SharedMemoryExt,
-    task::{TaskOptions, TaskPool},
+    multitasking::{MutexHolder, MutexPool},
+    task::{TaskOptions, TaskPool, NO_FUNCTION_TABLE, TASK_PRIORITIES},
     thread_local::GetTaskStorage,
-    Host, HostCtx,
+    timer, Host, HostCtx, ResultExt,
 };
 
-pub fn configure_rtos_facilities_api(linker: &mut Linker<Host>) -> anyhow::Result<()> {
-    linker.func_wrap0_async("env", "mutex_create", |caller: Caller<'_, Host>| {
-        Box::new(async move {
-            let mutex_id = caller.mutexes_lock().await.create_mutex();
-            Ok(mutex_id as u32)
-        })
-    })?;
-
-    linker.func_wrap1_async(
-        "env",
-        "mutex_delete",
-        |caller: Caller<'_, Host>, mutex_id: u32| {
-            Box::new(async move {
-                caller.mutexes_lock().await.delete_mutex(mutex_id as usize);
-                Ok(())
-            })
-        },
-    )?;
-
-    linker.func_wrap1_async(
-        "env",
-        "mutex_give",
-        |caller: Caller<'_, Host>, mutex_id: u32| {
-            Box::new(async move {
-                caller.mutexes_lock().await.unlock(mutex_id as usize);
-
-                Ok(u32::from(true))
-            })
-        },
-    )?;
-
-    linker.func_wrap2_async(
-        "env",
-        "mutex_take",
-        |caller: Caller<'_, Host>, mutex_id: u32, timeout: u32| {
-            Box::new(async move {
-                let timeout = (timeout != TIMEOUT_MAX)
-                    .then(|| Instant::now() + Duration::from_millis(timeout.into()));
-                let success = caller
-                    .mutexes_lock()
-                    .await
-                    .lock(mutex_id as usize, timeout)
-                    .await;
-                Ok(u32::from(success))
-            })
-        },
-    )?;
-
-    linker.func_wrap2_async(
-        "env",
-        "pvTaskGetThreadLocalStoragePointer",
-        |mut caller: Caller<'_, Host>, task_handle: u32, storage_index: i32| {
-            Box::new(async move {
-                let storage = caller.task_storage(task_handle).await;
-                Ok(storage.get(caller.memory(), storage_index))
-            })
-        },
-    )?;
-
-    linker.func_wrap3_async(
-        "env",
-        "vTaskSetThreadLocalStoragePointer",
-        |mut caller: Caller<'_, Host>, task_handle: u32, storage_index: i32, value: u32| {
-            Box::new(async move {
-                let mut storage = caller.task_storage(task_handle).await;
-                storage.set(caller.memory(), storage_index, value)
-            })
-        },
-    )?;
-
-    linker.func_wrap0_async("env", "task_get_current", |caller: Caller<'_, Host>| {
-        #[allow(clippy::let_and_return)]
-        Box::new(async move {
-            let current = caller.current_task().await;
-
-            let id = current.lock().await.id();
-            // fixing warning causes compile error
-            id
-        })
-    })?;
-
-    fn task_delay(
-        _caller: Caller<'_, Host>,
-        millis: u32,
-    ) -> Box<dyn Future<Output = anyhow::Result<()>> + Send + '_> {
-        Box::new(async move {
-            if millis > 0 {
-                let end = Instant::now() + Duration::from_millis(millis.into());
-                while Instant::now() < end {
-                    TaskPool::yield_now().await;
-                }
-            } else {
-                TaskPool::yield_now().await;
-            }
+/// The ID and name of the task that called into the simulator.
+async fn current_task_name(caller: &Caller<'_, Host>) -> (u32, String) {
+    let task = caller.current_task().await;
+    let task = task.lock().await;
+    (task.id(), task.name().to_string())
+}
 
-            Ok(())
-        })
+/// Checks that a task created with `task_create` would be able to start, describing what's wrong
+/// if it wouldn't.
+async fn check_task_args(
+    caller: &mut Caller<'_, Host>,
+    function: u32,
+    priority: u32,
+) -> Result<(), String> {
+    if !(1..=TASK_PRIORITIES).contains(&priority) {
+        return Err(format!(
+            "priority {priority} isn't between 1 and {TASK_PRIORITIES}"
+        ));
     }
+    let table = caller.current_task().await.lock().await.indirect_call_table;
+    let table = table.ok_or(NO_FUNCTION_TABLE)?;
+    let entrypoint = table.get(&mut *caller, function).ok_or(format!(
+        "entrypoint {function} is out of bounds of the function table"
+    ))?;
+    let entrypoint = entrypoint
+        .funcref()
+        .flatten()
+        .ok_or(format!("entrypoint {function} isn't a function"))?;
+    if entrypoint.typed::<u32, ()>(&*caller).is_err() {
+        return Err(format!(
+            "entrypoint {function} doesn't take a single pointer argument and return nothing"
+        ));
+    }
+    Ok(())
+}
+
+/// Tells the frontend why a task couldn't be created, returning the `errno` to fail with.
+async fn task_creation_failed(caller: &Caller<'_, Host>, reason: String, errno: i32) -> i32 {
+    let (task_id, task_name) = {
+        let task = caller.current_task().await;
+        let task = task.lock().await;
+        (task.id(), task.name().to_string())
+    };
+    caller.interface().send(SimulatorEvent::TaskCreationFailed {
+        reason,
+        errno,
+        task_id,
+        task_name,
+    });
+    errno
+}
+
+/// Fails if the current task has the scheduler suspended, since no other task could run to
+/// unblock it. FreeRTOS asserts the same thing.
+pub(super) async fn ensure_can_block(caller: &Caller<'_, Host>, api: &str) -> anyhow::Result<()> {
+    let current = caller.current_task().await;
+    let id = current.lock().await.id();
+    ensure!(
+        caller.tasks_lock().await.suspended_by() != Some(id),
+        "{api} may not block while the scheduler is suspended by rtos_suspend_all"
+    );
+    Ok(())
+}
 
-    linker.func_wrap1_async("env", "delay", task_delay)?;
-    linker.func_wrap1_async("env", "task_delay", task_delay)?;
-
-    linker.func_wrap2_async(
-        "env",
-        "task_delay_until",
-        |caller: Caller<'_, Host>, prev_time_ptr: u32, delta_ms: u32| {
-            Box::new(async move {
-                assert_ne!(prev_time_ptr, 0);
-                assert!(delta_ms > 0);
-
-                let epoch = caller.start_time();
-
-                let memory = caller.memory();
-                let u32_bits = memory.read_relaxed(prev_time_ptr as usize, size_of::<u32>())?;
-                let prev_time = u32::from_le_bytes(u32_bits.try_into().unwrap());
-
-                let end = epoch
-                    + Duration::from_millis(prev_time.into())
-                    + Duration::from_millis(delta_ms.into());
-
-                TaskPool::yield_now().await;
-                while Instant::now() < end {
-                    TaskPool::yield_now().await;
-                }
-
-                Ok(())
-            })
-        },
-    )?;
-
-    linker.func_wrap0_async("env", "rtos_suspend_all", |caller: Caller<'_, Host>| {
-        Box::new(async move {
-            let mut tasks = caller.tasks_lock().await;
-            tasks.suspend_all();
-            Ok(())
-        })
-    })?;
-
-    linker.func_wrap0_async("env", "rtos_resume_all", |caller: Caller<'_, Host>| {
-        Box::new(async move {
-            let mut tasks = caller.tasks_lock().await;
-            let res = tasks.resume_all().await?;
-            Ok(i32::from(res))
-        })
-    })?;
-
-    linker.func_wrap0_async("env", "millis", |caller: Caller<'_, Host>| {
-        Box::new(async move { Ok(caller.start_time().elapsed().as_millis() as u32) })
-    })?;
+/// Records that the current task is starting a delay, for measuring loop periods. See
+/// [`Timeline`](crate::Timeline).
+async fn start_delay(caller: &Caller<'_, Host>) {
+    let tick = caller.ticks();
+    caller.current_task().await.lock().await.start_delay(tick);
+}
+
+/// Blocks the current task for the given number of ticks, or yields if it's 0.
+///
+/// Like `vTaskDelay`, the task wakes at the start of the `ticks`th tick from now, which may be
+/// slightly less than `ticks` milliseconds if the current tick is already underway.
+async fn task_delay(caller: &Caller<'_, Host>, api: &str, ticks: u32) -> anyhow::Result<()> {
+    if ticks > 0 {
+        ensure_can_block(caller, api).await?;
+        start_delay(caller).await;
+        let extra_delay = caller.tasks_lock().await.extra_delay();
+        sleep_until_tick(caller, caller.ticks() + u64::from(ticks), extra_delay).await;
+    } else {
+        TaskPool::yield_now().await;
+    }
+    Ok(())
+}
+
+/// Blocks the current task until the clock reaches the given tick, and then for `extra_delay`
+/// more.
+pub(super) async fn sleep_until_tick(caller: &Caller<'_, Host>, tick: u64, extra_delay: Duration) {
+    let clock = caller.clock();
+    if caller.options().threaded {
+        timer::sleep_until(clock.tick_start(tick) + extra_delay).await;
+        // the tick may be running late
+        while clock.ticks() < tick {
+            timer::sleep(TICK_PERIOD / 10).await;
+        }
+        return;
+    }
+    while clock.ticks() < tick || Instant::now() < clock.tick_start(tick) + extra_delay {
+        TaskPool::yield_now().await;
+    }
+}
+
+/// Blocks the current task until the given time.
+pub(super) async fn sleep_until(caller: &Caller<'_, Host>, end: Instant) {
+    if caller.options().threaded {
+        // the task has a thread to itself, so it can sleep without holding up other tasks
+        timer::sleep_until(end).await;
+        return;
+    }
+    while Instant::now() < end {
+        TaskPool::yield_now().await;
+    }
+}
+
+pub fn configure_rtos_facilities_api(linker: &mut Linker<Host>) -> anyhow::Result<()> {
+    host_fn!(linker, "env", fn mutex_create(caller) -> u32 {
+        let mutex_id = caller.mutexes_lock().await.create_mutex() as u32;
+        if caller.options().mutex_hold_threshold.is_some() {
+            let (task_id, task_name) = current_task_name(&caller).await;
+            caller.interface().send(SimulatorEvent::MutexCreated {
+                mutex_id,
+                task_id,
+                task_name,
+            });
+        }
+        Ok(mutex_id)
+    });
+
+    host_fn!(linker, "env", #[errno(())] fn mutex_delete(
+        caller,
+        #[mutex] mutex_id: u32,
+    ) -> () {
+        caller.mutexes_lock().await.delete_mutex(mutex_id as usize)
+    });
+
+    host_fn!(linker, "env", #[errno(PROS_ERR)] fn mutex_give(
+        caller,
+        #[mutex] mutex_id: u32,
+    ) -> i32 {
+        let (task_id, _) = current_task_name(&caller).await;
+        caller.mutexes_lock().await.unlock(mutex_id as usize, task_id)?;
+        Ok(i32::from(true))
+    });
+
+    host_fn!(linker, "env", #[errno(PROS_ERR)] #[before(async {
+        if timeout != 0 {
+            ensure_can_block(&caller, "mutex_take").await?;
+        }
+        anyhow::Ok(())
+    })] fn mutex_take(
+        caller,
+        #[mutex] mutex_id: u32,
+        timeout: u32,
+    ) -> i32 {
+        let extra_delay = caller.tasks_lock().await.extra_delay();
+        let timeout = (timeout != TIMEOUT_MAX)
+            .then(|| sleep_until_tick(&caller, caller.ticks() + u64::from(timeout), extra_delay));
+        let (task_id, task_name) = current_task_name(&caller).await;
+        let holder = caller.mutexes_lock().await.holder(mutex_id as usize)?.cloned();
+        if let Some(holder) = holder.filter(|_| caller.options().mutex_hold_threshold.is_some()) {
+            caller.interface().send(SimulatorEvent::MutexContended {
+                mutex_id,
+                task_id,
+                task_name: task_name.clone(),
+                holder_id: holder.task_id,
+                holder_name: holder.task_name,
+            });
+        }
+        let success = MutexPool::lock(&caller.mutexes(), mutex_id as usize, timeout).await?;
+        if success {
+            let holder = MutexHolder::new(task_id, task_name, caller.ticks());
+            caller.mutexes_lock().await.set_holder(mutex_id as usize, holder);
+        }
+        Ok(i32::from(success))
+    });
+
+    host_fn!(linker, "env", fn pvTaskGetThreadLocalStoragePointer(
+        caller,
+        task_handle: u32,
+        storage_index: i32,
+    ) -> u32 {
+        let Some(storage) = caller.task_storage(task_handle).await? else {
+            return Ok(0);
+        };
+        storage.get(caller.memory(), storage_index)
+    });
+
+    host_fn!(linker, "env", fn vTaskSetThreadLocalStoragePointer(
+        caller,
+        task_handle: u32,
+        storage_index: i32,
+        value: u32,
+    ) {
+        let Some(mut storage) = caller.task_storage(task_handle).await? else {
+            Err::<(), _>(EINVAL).unwrap_or_errno(&mut caller).await?;
+            return Ok(());
+        };
+        storage.set(caller.memory(), storage_index, value)
+    });
+
+    host_fn!(linker, "env", fn task_get_count(caller) -> u32 {
+        Ok(caller.tasks_lock().await.task_count() as u32)
+    });
+
+    host_fn!(linker, "env", fn task_get_current(caller) -> u32 {
+        let current = caller.current_task().await;
+        let id = current.lock().await.id();
+        Ok(id)
+    });
+
+    host_fn!(linker, "env", fn delay(caller, millis: u32) {
+        task_delay(&caller, "delay", millis).await
+    });
+
+    host_fn!(linker, "env", fn task_delay(caller, millis: u32) {
+        task_delay(&caller, "task_delay", millis).await
+    });
+
+    host_fn!(linker, "env", fn task_delay_until(
+        caller,
+        #[not_null] prev_time_ptr: u32,
+        delta_ms: u32,
+    ) {
+        ensure!(
+            delta_ms > 0,
+            "task_delay_until: delta must be greater than 0"
+        );
+        ensure_can_block(&caller, "task_delay_until").await?;
+        start_delay(&caller).await;
+
+        let memory = caller.memory();
+        let u32_bits = memory.read_relaxed(prev_time_ptr as usize, size_of::<u32>())?;
+        let prev_time = u32::from_le_bytes(u32_bits.try_into().unwrap());
+
+        // like PROS, update prev_time so that periodic loops don't drift
+        let wake_time = prev_time.wrapping_add(delta_ms);
+        memory.write_relaxed(prev_time_ptr as usize, &wake_time.to_le_bytes())?;
+
+        // the wake time may be in the past, or on the other side of `millis` wrapping around
+        let ticks = caller.ticks();
+        let now = caller.options().start_millis.wrapping_add(ticks as u32);
+        let remaining = (wake_time.wrapping_sub(now) as i32).max(0) as u64;
+        let extra_delay = caller.tasks_lock().await.extra_delay();
+
+        TaskPool::yield_now().await;
+        sleep_until_tick(&caller, ticks + remaining, extra_delay).await;
+
+        Ok(())
+    });
+
+    host_fn!(linker, "env", fn rtos_suspend_all(caller) {
+        let current = caller.current_task().await;
+        let id = current.lock().await.id();
+        // in threaded mode, wait for any other task's critical section to end first
+        while !caller.tasks_lock().await.suspend_all(id) {
+            TaskPool::yield_now().await;
+        }
+        Ok(())
+    });
+
+    host_fn!(linker, "env", fn rtos_resume_all(caller) -> i32 {
+        let yield_pending = caller.tasks_lock().await.resume_all()?;
+        if yield_pending {
+            TaskPool::yield_now().await;
+        }
+        Ok(i32::from(yield_pending))
+    });
+
+    host_fn!(linker, "env", fn millis(caller) -> u32 {
+        Ok(caller.millis())
+    });
 
     // task_t task_create ( task_fn_t function,
     //     void* parameters,
     //      uint8_t prio,
     //      uint16_t stack_depth,
     //      const char* name )
-    linker.func_wrap5_async(
-        "env",
-        "task_create",
-        |caller: Caller<'_, Host>,
-         function: u32,
-         parameters: u32,
-         priority: u32,
-         _stack_depth: u32,
-         _name: u32| {
-            Box::new(async move {
-                let mut tasks = caller.tasks_lock().await;
-                let opts =
-                    TaskOptions::new_extern(&mut tasks, caller.data(), function, parameters)?
-                        .priority(priority - 1);
-                let task = tasks
-                    .spawn(opts, &caller.module(), &caller.interface())
-                    .await?;
-
-                let task = task.lock().await;
-                Ok(task.id())
-            })
-        },
-    )?;
-
-    linker.func_wrap1_async(
-        "env",
-        "task_delete",
-        |caller: Caller<'_, Host>, task_id: u32| {
-            Box::new(async move {
-                let mut tasks = caller.tasks_lock().await;
-                tasks.delete_task(task_id).await;
-                Ok(())
-            })
-        },
-    )?;
-
-    linker.func_wrap1_async(
-        "env",
-        "task_get_name",
-        |mut caller: Caller<'_, Host>, task_id: u32| {
-            Box::new(async move {
-                let tasks = caller.tasks_lock().await;
-                let task = tasks.by_id(task_id);
+    host_fn!(linker, "env", #[errno(0)] fn task_create(
+        caller,
+        function: u32,
+        parameters: u32,
+        priority: u32,
+        _stack_depth: u32,
+        _name: u32,
+    ) -> u32 {
+        if let Err(reason) = check_task_args(&mut caller, function, priority).await {
+            return Err(task_creation_failed(&caller, reason, EINVAL).await);
+        }
+
+        let mut tasks = caller.tasks_lock().await;
+        let limits = caller.limits();
+        if let Some(max) = limits.max_tasks() {
+            if tasks.robot_code_task_count() >= max as usize {
                 drop(tasks);
-
-                if let Some(task) = task {
-                    let task = task.lock().await;
-                    let name = task.name();
-                    let c_name = CString::new(name).unwrap();
-                    let name_bytes = c_name.as_bytes_with_nul();
-                    drop(task);
-
-                    let current_task_handle = caller.current_task().await;
-                    let current_task = current_task_handle.lock().await;
-                    let allocator = current_task.allocator();
-                    let ptr = allocator
-                        .memalign(&mut caller, Layout::for_value(name_bytes))
-                        .await;
-                    caller.memory().write_relaxed(ptr as usize, name_bytes)?;
-
-                    Ok(ptr)
-                } else {
-                    Ok(0)
-                }
-            })
-        },
-    )?;
+                limits.exceed(ResourceLimit::Tasks);
+                let reason = format!("robot code already has {max} tasks, the most it's allowed");
+                return Err(task_creation_failed(&caller, reason, ENOMEM).await);
+            }
+        }
+        let task = match TaskOptions::new_extern(&mut tasks, caller.data(), function, parameters) {
+            Ok(opts) => {
+                tasks
+                    .spawn(opts.priority(priority - 1), &caller.module(), &caller.interface())
+                    .await
+            }
+            Err(err) => Err(err),
+        };
+        drop(tasks);
+        match task {
+            Ok(task) => Ok(task.lock().await.id()),
+            Err(err) => Err(task_creation_failed(&caller, format!("{err:#}"), ENOMEM).await),
+        }
+    });
+
+    host_fn!(linker, "env", fn task_delete(caller, task_id: u32) {
+        caller.tasks_lock().await.delete_task(task_id).await;
+        Ok(())
+    });
+
+    host_fn!(linker, "env", fn task_get_name(caller, task_id: u32) -> u32 {
+        let Some(task) = caller.task_by_handle(task_id).await else {
+            return Ok(0);
+        };
+        // the name is allocated by the calling task, which may be a different one
+        let allocator = caller.current_task().await.lock().await.allocator();
+        let mut task = task.lock().await;
+        task.c_name(&mut caller, &allocator).await
+    });
 
     Ok(())
 }
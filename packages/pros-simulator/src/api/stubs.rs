@@ -0,0 +1,66 @@
+//! Stubs for PROS APIs the simulator doesn't implement, used in
+//! [permissive mode](crate::SimulatorOptions::permissive).
+//!
+//! A stub sets `errno` to `ENOSYS` and returns the error value PROS functions with the same
+//! return type use: `PROS_ERR` for integers and pointers, `PROS_ERR_F` for floating point
+//! numbers, and nothing for `void` functions. Functions with any other return type can't be
+//! stubbed, so calling them still stops the task.
+
+use pros_sys::{PROS_ERR, PROS_ERR_F};
+use wasmtime::{ExternType, FuncType, Linker, Module, Store, Val, ValType};
+
+use crate::host::{ContextExt, Host, HostCtx};
+
+/// The `errno` value for functions that aren't implemented, from newlib's `errno.h`.
+pub const ENOSYS: i32 = 88;
+
+/// What a stub for a function of the given type returns, or `None` if it can't be stubbed.
+fn stub_result(ty: &FuncType) -> Option<Option<Val>> {
+    let results = ty.results().collect::<Vec<_>>();
+    match results[..] {
+        [] => Some(None),
+        [ValType::I32] => Some(Some(Val::I32(PROS_ERR))),
+        [ValType::F64] => Some(Some(Val::F64(PROS_ERR_F.to_bits()))),
+        _ => None,
+    }
+}
+
+/// Links every function the robot code imports from `env` that isn't already defined and has
+/// a return type that can be stubbed to a stub, returning their names.
+pub fn stub_unknown_imports(
+    linker: &mut Linker<Host>,
+    store: &mut Store<Host>,
+    module: &Module,
+) -> anyhow::Result<Vec<String>> {
+    let mut stubbed = Vec::new();
+    for import in module.imports() {
+        let ExternType::Func(ty) = import.ty() else {
+            continue;
+        };
+        if import.module() != "env" || linker.get(&mut *store, "env", import.name()).is_some() {
+            continue;
+        }
+        let Some(result) = stub_result(&ty) else {
+            continue;
+        };
+
+        let name = import.name().to_string();
+        linker.func_new_async("env", import.name(), ty, {
+            let name = name.clone();
+            move |mut caller, _params, results| {
+                let name = name.clone();
+                let result = result.clone();
+                Box::new(async move {
+                    caller.api_usage().record(&name);
+                    caller.set_errno(ENOSYS).await?;
+                    if let Some(result) = result {
+                        results[0] = result;
+                    }
+                    Ok(())
+                })
+            }
+        })?;
+        stubbed.push(name);
+    }
+    Ok(stubbed)
+}
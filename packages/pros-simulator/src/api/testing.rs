@@ -0,0 +1,113 @@
+//! Test build API - simulator-specific functions that let unit tests compiled into the robot
+//! code set up the simulation directly. They only work with
+//! [`SimulatorOptions::test_build`](crate::SimulatorOptions::test_build), and stop the robot
+//! code otherwise. Robot code running on a V5 can't link them, so they should be imported
+//! weakly.
+//!
+//! ## Reference
+//!
+//! * `sim_set_pose`
+//!   Places the robot on the field, given a pointer to three `double`s: x and y in inches, and
+//!   heading in degrees. Sends a
+//!   [`SimulatorEvent::PoseSet`](pros_simulator_interface::SimulatorEvent::PoseSet) event.
+//! * `sim_advance_time`
+//!   Moves the clock forward by the given number of milliseconds, so code waiting on `millis`
+//!   can be tested without waiting. See [`HostCtx::advance_time`].
+//! * `sim_config_get`
+//!   Copies the value of a
+//!   [`SimulatorOptions::test_config`](crate::SimulatorOptions::test_config) key into a buffer
+//!   like `snprintf`, returning its length, or -1 if it isn't set.
+//! * `sim_sensor_trace`
+//!   Copies a sample of a prebuilt sensor trace into a buffer of ten `double`s, given the
+//!   trace's name and the sample's index, and returns how many samples the trace has, or -1 if
+//!   there's no trace with that name. Nothing is copied if the index is past the end. Samples
+//!   hold the time in milliseconds, the robot's real x, y and heading, the IMU's heading, turn
+//!   rate, and forward and lateral acceleration, and the left and right tracking wheels'
+//!   distances. The traces are `still`, `straight`, `turn`, `arc`, `square` and `s_curve`;
+//!   see [`traces`] for how they're made.
+
+mod traces;
+
+use std::time::Duration;
+
+use anyhow::bail;
+use pros_simulator_interface::{Pose, SimulatorEvent};
+use wasmtime::{Caller, Linker};
+
+use self::traces::{sensor_trace, Sample};
+use crate::host::{memory::SharedMemoryExt, Host, HostCtx};
+
+/// Stops the robot code if this isn't a test build.
+fn ensure_test_build(caller: &Caller<'_, Host>, function: &str) -> anyhow::Result<()> {
+    if !caller.options().test_build {
+        bail!(
+            "{function} can only be called in a test build. Use \
+             `SimulatorOptions::test_build` or `--test-build` to enable it."
+        );
+    }
+    Ok(())
+}
+
+pub fn configure_testing_api(linker: &mut Linker<Host>) -> anyhow::Result<()> {
+    host_fn!(linker, "env", fn sim_set_pose(caller, #[buffer(24)] pose: u32) {
+        ensure_test_build(&caller, "sim_set_pose")?;
+        let bytes = caller.memory().read_relaxed(pose as usize, 24)?;
+        let field = |index: usize| {
+            f64::from_le_bytes(bytes[index * 8..][..8].try_into().unwrap())
+        };
+        let pose = Pose {
+            x: field(0),
+            y: field(1),
+            heading: field(2),
+        };
+        *caller.pose_lock().await = Some(pose);
+        caller.interface().send(SimulatorEvent::PoseSet(pose));
+        Ok(())
+    });
+
+    host_fn!(linker, "env", fn sim_advance_time(caller, millis: u32) {
+        ensure_test_build(&caller, "sim_advance_time")?;
+        caller.advance_time(Duration::from_millis(millis.into()))
+    });
+
+    host_fn!(linker, "env", fn sim_config_get(
+        caller,
+        #[in_memory] key: u32,
+        #[buffer(len)] buffer: u32,
+        len: u32,
+    ) -> i32 {
+        ensure_test_build(&caller, "sim_config_get")?;
+        let key = caller.read_c_str(key)?;
+        let Some(value) = caller.options().test_config.get(&key).cloned() else {
+            return Ok(-1);
+        };
+        if len > 0 {
+            let copied = value.len().min(len as usize - 1);
+            let mut bytes = value.as_bytes()[..copied].to_vec();
+            bytes.push(0);
+            caller.memory().write_relaxed(buffer as usize, &bytes)?;
+        }
+        Ok(value.len() as i32)
+    });
+
+    host_fn!(linker, "env", fn sim_sensor_trace(
+        caller,
+        #[in_memory] name: u32,
+        index: u32,
+        #[buffer(Sample::SIZE as u32)] sample: u32,
+    ) -> i32 {
+        ensure_test_build(&caller, "sim_sensor_trace")?;
+        let name = caller.read_c_str(name)?;
+        let Some(samples) = sensor_trace(&name) else {
+            return Ok(-1);
+        };
+        if let Some(value) = samples.get(index as usize) {
+            caller
+                .memory()
+                .write_relaxed(sample as usize, &value.to_bytes())?;
+        }
+        Ok(samples.len() as i32)
+    });
+
+    Ok(())
+}
@@ -0,0 +1,235 @@
+//! Prebuilt sensor traces of a differential drive robot doing common maneuvers, for testing
+//! odometry and sensor filters against a known path. Read with `sim_sensor_trace`.
+//!
+//! Each trace starts at the origin facing +y, holds still for half a second so filters can
+//! estimate the gyro's bias, then drives a series of segments that each follow a smooth
+//! velocity profile, starting and ending at rest. Samples are taken every
+//! [`SAMPLE_PERIOD_MS`]. The robot has two tracking wheels [`TRACK_WIDTH`] inches apart, and
+//! an IMU whose readings drift and are noisy. The noise is generated from a fixed seed, so a
+//! trace is the same every time it's read.
+
+use std::f64::consts::PI;
+
+/// How often samples are taken.
+pub const SAMPLE_PERIOD_MS: u32 = 10;
+/// Inches between the left and right tracking wheels.
+pub const TRACK_WIDTH: f64 = 10.0;
+/// The tracking wheels' diameter in inches.
+const WHEEL_DIAMETER: f64 = 2.75;
+/// The tracking wheels' encoder resolution.
+const TICKS_PER_REVOLUTION: f64 = 360.0;
+/// How fast the IMU's heading drifts, in degrees per second.
+const IMU_DRIFT: f64 = 0.02;
+/// The standard deviations of the IMU's heading, rate and acceleration readings.
+const HEADING_NOISE: f64 = 0.05;
+const RATE_NOISE: f64 = 0.2;
+const ACCELERATION_NOISE: f64 = 0.005;
+/// Inches per second squared in one g.
+const GRAVITY: f64 = 386.09;
+/// How long traces hold still before moving.
+const STILL_MS: u32 = 500;
+
+/// One sample of a trace, in the order robot code reads them as `double`s.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    /// Milliseconds since the trace started.
+    pub millis: f64,
+    /// Where the robot really is, in inches, and its heading in degrees clockwise from +y.
+    /// Headings aren't wrapped, so a full turn ends at 360.
+    pub x: f64,
+    pub y: f64,
+    pub heading: f64,
+    /// The IMU's heading in degrees clockwise, unwrapped like its rotation reading.
+    pub imu_heading: f64,
+    /// The IMU's turn rate in degrees per second clockwise.
+    pub imu_rate: f64,
+    /// The IMU's acceleration in g, forwards and to the robot's right.
+    pub imu_forward: f64,
+    pub imu_lateral: f64,
+    /// How far each tracking wheel has rolled forwards, in inches.
+    pub left_distance: f64,
+    pub right_distance: f64,
+}
+
+impl Sample {
+    /// How many bytes a sample takes in robot code's memory.
+    pub const SIZE: usize = 80;
+
+    /// The sample as robot code reads it: ten little endian `double`s.
+    pub fn to_bytes(self) -> [u8; Self::SIZE] {
+        let values = [
+            self.millis,
+            self.x,
+            self.y,
+            self.heading,
+            self.imu_heading,
+            self.imu_rate,
+            self.imu_forward,
+            self.imu_lateral,
+            self.left_distance,
+            self.right_distance,
+        ];
+        let mut bytes = [0; Self::SIZE];
+        for (chunk, value) in bytes.chunks_exact_mut(8).zip(values) {
+            chunk.copy_from_slice(&value.to_le_bytes());
+        }
+        bytes
+    }
+}
+
+/// Part of a maneuver: driving `distance` inches while turning `turn` degrees clockwise, over
+/// `millis`. Driving and turning together follow an arc.
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    millis: u32,
+    distance: f64,
+    turn: f64,
+}
+
+const fn segment(millis: u32, distance: f64, turn: f64) -> Segment {
+    Segment {
+        millis,
+        distance,
+        turn,
+    }
+}
+
+fn segments(name: &str) -> Option<Vec<Segment>> {
+    let quarter_arc = 24.0 * PI / 2.0;
+    let eighth_arc = 24.0 * PI / 4.0;
+    Some(match name {
+        "still" => vec![segment(4500, 0.0, 0.0)],
+        "straight" => vec![segment(2000, 48.0, 0.0)],
+        "turn" => vec![segment(1000, 0.0, 90.0)],
+        "arc" => vec![segment(2000, quarter_arc, 90.0)],
+        "square" => [segment(1500, 24.0, 0.0), segment(800, 0.0, 90.0)].repeat(4),
+        "s_curve" => vec![
+            segment(1200, eighth_arc, 45.0),
+            segment(1200, eighth_arc, -45.0),
+        ],
+        _ => return None,
+    })
+}
+
+/// The robot's exact state at a point in a trace.
+#[derive(Debug, Clone, Copy, Default)]
+struct Motion {
+    x: f64,
+    y: f64,
+    /// Radians clockwise from +y.
+    heading: f64,
+    /// How far the robot's center has travelled, in inches.
+    distance: f64,
+    /// Inches per second, radians per second clockwise, and inches per second squared.
+    velocity: f64,
+    rate: f64,
+    acceleration: f64,
+}
+
+impl Motion {
+    /// Where the robot is `elapsed` milliseconds into `segment`, having started it at `self`.
+    fn along(self, segment: Segment, elapsed: u32) -> Self {
+        let duration = f64::from(segment.millis) / 1000.0;
+        let u = f64::from(elapsed.min(segment.millis)) / f64::from(segment.millis);
+        // smoothstep, so the robot starts and stops without a jump in velocity
+        let progress = u * u * (3.0 - 2.0 * u);
+        let speed = 6.0 * u * (1.0 - u) / duration;
+        let change = (6.0 - 12.0 * u) / (duration * duration);
+
+        let turn = segment.turn.to_radians();
+        let heading = self.heading + turn * progress;
+        let travelled = segment.distance * progress;
+        let (x, y) = if turn == 0.0 {
+            (
+                self.x + travelled * self.heading.sin(),
+                self.y + travelled * self.heading.cos(),
+            )
+        } else {
+            let radius = segment.distance / turn;
+            (
+                self.x + radius * (self.heading.cos() - heading.cos()),
+                self.y + radius * (heading.sin() - self.heading.sin()),
+            )
+        };
+        Self {
+            x,
+            y,
+            heading,
+            distance: self.distance + travelled,
+            velocity: segment.distance * speed,
+            rate: turn * speed,
+            acceleration: segment.distance * change,
+        }
+    }
+
+    fn at_rest(self) -> Self {
+        Self {
+            velocity: 0.0,
+            rate: 0.0,
+            acceleration: 0.0,
+            ..self
+        }
+    }
+}
+
+/// A normally distributed number with the given standard deviation.
+fn noise(rng: &mut fastrand::Rng, deviation: f64) -> f64 {
+    // Box-Muller transform
+    let u1 = 1.0 - rng.f64();
+    let u2 = rng.f64();
+    deviation * (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+}
+
+/// What a tracking wheel's encoder reads after rolling `distance` inches.
+fn encoder(distance: f64) -> f64 {
+    let per_tick = PI * WHEEL_DIAMETER / TICKS_PER_REVOLUTION;
+    (distance / per_tick).round() * per_tick
+}
+
+/// The samples of a prebuilt trace, or `None` if there isn't one called `name`.
+pub fn sensor_trace(name: &str) -> Option<Vec<Sample>> {
+    let segments = segments(name)?;
+    let total = STILL_MS + segments.iter().map(|segment| segment.millis).sum::<u32>();
+    let mut rng = fastrand::Rng::with_seed(0x5e_4504);
+
+    let mut samples = Vec::new();
+    let mut start = Motion::default();
+    let mut remaining = segments.iter().copied().peekable();
+    let mut segment_start = STILL_MS;
+    for millis in (0..=total).step_by(SAMPLE_PERIOD_MS as usize) {
+        let mut motion = start;
+        while let Some(&segment) = remaining.peek() {
+            if millis < segment_start {
+                break;
+            }
+            let elapsed = millis - segment_start;
+            motion = start.along(segment, elapsed);
+            if elapsed < segment.millis {
+                break;
+            }
+            start = motion.at_rest();
+            motion = start;
+            segment_start += segment.millis;
+            remaining.next();
+        }
+
+        let seconds = f64::from(millis) / 1000.0;
+        let wheel_offset = motion.heading * TRACK_WIDTH / 2.0;
+        samples.push(Sample {
+            millis: f64::from(millis),
+            x: motion.x,
+            y: motion.y,
+            heading: motion.heading.to_degrees(),
+            imu_heading: motion.heading.to_degrees()
+                + IMU_DRIFT * seconds
+                + noise(&mut rng, HEADING_NOISE),
+            imu_rate: motion.rate.to_degrees() + IMU_DRIFT + noise(&mut rng, RATE_NOISE),
+            imu_forward: motion.acceleration / GRAVITY + noise(&mut rng, ACCELERATION_NOISE),
+            imu_lateral: motion.velocity * motion.rate / GRAVITY
+                + noise(&mut rng, ACCELERATION_NOISE),
+            left_distance: encoder(motion.distance + wheel_offset),
+            right_distance: encoder(motion.distance - wheel_offset),
+        });
+    }
+    Some(samples)
+}
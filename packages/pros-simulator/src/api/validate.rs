@@ -0,0 +1,183 @@
+//! Checks for the arguments robot code passes to host functions.
+//!
+//! [`host_fn!`] runs a check before the body for each argument marked with an attribute naming
+//! one of the functions here, passing it the caller, the argument and anything in the
+//! attribute's parentheses:
+//!
+//! ```ignore
+//! host_fn!(linker, "env", #[errno(-1)] fn write(
+//!     caller,
+//!     fd: i32,
+//!     #[buffer(count)] buffer: u32,
+//!     count: u32,
+//! ) -> i32 { ... });
+//! ```
+//!
+//! In functions with `#[errno]`, an invalid argument fails the call with the check's `errno`
+//! and is reported with a
+//! [`SimulatorEvent::InvalidArgument`](pros_simulator_interface::SimulatorEvent::InvalidArgument)
+//! event. Elsewhere, it stops the robot code like any other error.
+
+use pros_simulator_interface::{LogLevel, SimulatorEvent, LCD_HEIGHT};
+use pros_sys::{EFAULT, EINVAL, ENXIO, E_CONTROLLER_MASTER, E_CONTROLLER_PARTNER};
+
+use crate::{
+    host::{smart_ports::NUM_SMART_PORTS, HostCtx},
+    WarningKind,
+};
+
+/// Why an argument was rejected.
+#[derive(Debug)]
+pub struct Invalid {
+    /// What the call fails with.
+    pub errno: i32,
+    /// Completes "which …", e.g. "isn't a smart port".
+    pub reason: String,
+}
+
+impl Invalid {
+    fn new(errno: i32, reason: impl Into<String>) -> Self {
+        Self {
+            errno,
+            reason: reason.into(),
+        }
+    }
+
+    /// Describes the rejected argument, e.g.
+    /// "registry_bind_port was called with port = 30, which isn't a smart port".
+    pub fn message(&self, function: &str, argument: &str, value: i64) -> String {
+        format!(
+            "{function} was called with {argument} = {value}, which {}",
+            self.reason
+        )
+    }
+
+    /// Tells the frontend about the rejected argument, returning the `errno` to fail with.
+    pub async fn report(
+        self,
+        caller: &(impl HostCtx + Sync),
+        function: &str,
+        argument: &str,
+        value: i64,
+    ) -> i32 {
+        let (task_id, task_name) = {
+            let task = caller.current_task().await;
+            let task = task.lock().await;
+            (task.id(), task.name().to_string())
+        };
+        caller.interface().send(SimulatorEvent::InvalidArgument {
+            function: function.to_string(),
+            argument: argument.to_string(),
+            value,
+            reason: self.reason,
+            errno: self.errno,
+            task_id,
+            task_name,
+        });
+        caller
+            .tasks_lock()
+            .await
+            .fail_if_strict(WarningKind::InvalidArgument);
+        self.errno
+    }
+}
+
+/// A pointer that mustn't be `NULL`. Fails with `EINVAL`.
+pub fn not_null(_caller: &impl HostCtx, ptr: u32) -> Result<(), Invalid> {
+    if ptr == 0 {
+        return Err(Invalid::new(EINVAL, "is NULL"));
+    }
+    Ok(())
+}
+
+/// A pointer to something in robot code memory, like the start of a string. Fails with
+/// `EFAULT`.
+pub fn in_memory(caller: &impl HostCtx, ptr: u32) -> Result<(), Invalid> {
+    if ptr as usize >= caller.memory().data_size() {
+        return Err(Invalid::new(EFAULT, "points outside of robot code memory"));
+    }
+    Ok(())
+}
+
+/// A pointer to a buffer of `len` bytes, which must fit in robot code memory. Fails with
+/// `EFAULT`.
+pub fn buffer(caller: &impl HostCtx, ptr: u32, len: u32) -> Result<(), Invalid> {
+    let end = u64::from(ptr) + u64::from(len);
+    if end > caller.memory().data_size() as u64 {
+        return Err(Invalid::new(
+            EFAULT,
+            format!("points to a {len}-byte buffer that doesn't fit in robot code memory"),
+        ));
+    }
+    Ok(())
+}
+
+/// A zero-indexed smart port, as the registry API numbers them. Fails with `ENXIO`.
+pub fn smart_port(_caller: &impl HostCtx, port: u32) -> Result<(), Invalid> {
+    if port as usize >= NUM_SMART_PORTS {
+        return Err(Invalid::new(
+            ENXIO,
+            format!("isn't a smart port (0 to {})", NUM_SMART_PORTS - 1),
+        ));
+    }
+    Ok(())
+}
+
+/// A smart port numbered from 1, as the motor API numbers them. Fails with `ENXIO`.
+pub fn motor_port(_caller: &impl HostCtx, port: u32) -> Result<(), Invalid> {
+    if !(1..=NUM_SMART_PORTS as u32).contains(&port) {
+        return Err(Invalid::new(
+            ENXIO,
+            format!("isn't a smart port (1 to {NUM_SMART_PORTS})"),
+        ));
+    }
+    Ok(())
+}
+
+/// A `controller_id_e_t`. Fails with `EINVAL`.
+pub fn controller(_caller: &impl HostCtx, id: u32) -> Result<(), Invalid> {
+    if id != E_CONTROLLER_MASTER && id != E_CONTROLLER_PARTNER {
+        return Err(Invalid::new(
+            EINVAL,
+            "isn't E_CONTROLLER_MASTER or E_CONTROLLER_PARTNER",
+        ));
+    }
+    Ok(())
+}
+
+/// A `sim_log` level, numbered from 1 for errors to 5 for traces. Fails with `EINVAL`.
+pub fn log_level(_caller: &impl HostCtx, level: i32) -> Result<(), Invalid> {
+    if LogLevel::from_number(level).is_none() {
+        return Err(Invalid::new(
+            EINVAL,
+            "isn't a log level (1 for errors to 5 for traces)",
+        ));
+    }
+    Ok(())
+}
+
+/// A line of the legacy LCD emulator. Fails with `EINVAL`.
+pub fn lcd_line(_caller: &impl HostCtx, line: i32) -> Result<(), Invalid> {
+    if !(0..LCD_HEIGHT as i32).contains(&line) {
+        return Err(Invalid::new(
+            EINVAL,
+            format!("isn't between 0 and {}", LCD_HEIGHT - 1),
+        ));
+    }
+    Ok(())
+}
+
+/// A mutex returned by `mutex_create` that hasn't been deleted. Fails with `EINVAL`.
+///
+/// If another task is using the mutex pool right now, the ID is let through and the call fails
+/// with the same `errno` when it looks the mutex up, just without a report.
+pub fn mutex(caller: &impl HostCtx, mutex_id: u32) -> Result<(), Invalid> {
+    let mutexes = caller.mutexes();
+    let Ok(pool) = mutexes.try_lock() else {
+        return Ok(());
+    };
+    if !pool.contains(mutex_id as usize) {
+        return Err(Invalid::new(EINVAL, "isn't a mutex from mutex_create"));
+    }
+    Ok(())
+}
@@ -0,0 +1,95 @@
+//! vexide SDK - the subset of VEXos's `vex-sdk` functions that vexide programs use, imported
+//! from the `vex` module instead of `env`. The simulator serves these to robot code that
+//! imports anything from `vex`.
+//!
+//! Functions that take a controller ID or index use the same numbering as the PROS API.
+//!
+//! ## Reference
+//!
+//! * `vexSystemTimeGet`
+//! * `vexSystemHighResTimeGet`
+//! * `vexSystemExitRequest`
+//! * `vexTasksRun`
+//!   Yields to the simulator's other tasks.
+//! * `vexSerialWriteBuffer`
+//!   Only channel 1 (the debug terminal) is implemented.
+//! * `vexSerialWriteFree`
+//! * `vexCompetitionStatus`
+//! * `vexControllerGet`
+//! * `vexControllerConnectionStatusGet`
+
+use wasmtime::Linker;
+
+use super::generic_io::{exit, write_console};
+use crate::{
+    host::{abi::VEX_MODULE, memory::SharedMemoryExt, task::TaskPool, Host, HostCtx},
+    system::system_daemon::CompetitionPhaseExt,
+};
+
+/// The serial channel connected to the debug terminal.
+const STDIO_CHANNEL: u32 = 1;
+
+pub fn configure_vexide_api(linker: &mut Linker<Host>) -> anyhow::Result<()> {
+    host_fn!(linker, VEX_MODULE, fn vexSystemTimeGet(caller) -> u32 {
+        Ok(caller.millis())
+    });
+
+    host_fn!(linker, VEX_MODULE, fn vexSystemHighResTimeGet(caller) -> u64 {
+        Ok(caller.elapsed().as_micros() as u64)
+    });
+
+    host_fn!(linker, VEX_MODULE, fn vexSystemExitRequest(caller) {
+        exit(&caller, 0).await
+    });
+
+    host_fn!(linker, VEX_MODULE, fn vexTasksRun(_caller) {
+        TaskPool::yield_now().await;
+        Ok(())
+    });
+
+    host_fn!(linker, VEX_MODULE, fn vexSerialWriteBuffer(
+        caller,
+        channel: u32,
+        data: u32,
+        data_len: u32,
+    ) -> i32 {
+        if channel != STDIO_CHANNEL {
+            return Ok(-1);
+        }
+        let Ok(buffer) = caller.memory().read_relaxed(data as usize, data_len as usize) else {
+            return Ok(-1);
+        };
+        // vexide buffers its own output
+        write_console(&caller, buffer, false).await;
+        Ok(data_len as i32)
+    });
+
+    host_fn!(linker, VEX_MODULE, fn vexSerialWriteFree(caller, channel: u32) -> i32 {
+        Ok(if channel == STDIO_CHANNEL {
+            caller.serial().free() as i32
+        } else {
+            -1
+        })
+    });
+
+    host_fn!(linker, VEX_MODULE, fn vexCompetitionStatus(caller) -> u32 {
+        Ok(caller.competition_phase_lock().await.as_bits().into())
+    });
+
+    host_fn!(linker, VEX_MODULE, fn vexControllerGet(caller, id: u32, index: u32) -> i32 {
+        let controllers = caller.controllers_lock().await;
+        let value = controllers
+            .get_analog(id, index)
+            .or_else(|_| controllers.get_digital(id, index).map(i32::from))
+            .unwrap_or(0);
+        Ok(value)
+    });
+
+    host_fn!(linker, VEX_MODULE, fn vexControllerConnectionStatusGet(caller, id: u32) -> u32 {
+        // 1 is a tethered controller, 0 is offline
+        let connected = caller.controllers_lock().await.is_connected(id);
+        Ok(connected.map_or(0, u32::from))
+    });
+
+    Ok(())
+}
@@ -0,0 +1,266 @@
+//! Simulation setups saved in a `simulator.toml` file, so a team can commit their robot's setup
+//! next to its code instead of every frontend sending it. See [`SimulatorConfig`].
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use pros_simulator_interface::{
+    ControllerId, DeviceType, DrivetrainConfig, GameObject, InputShaping, Mechanism, MotorGroup,
+    ScoringZone,
+};
+use serde::Deserialize;
+
+use crate::{
+    host::smart_ports::{check_drivetrain, check_motor_group, drivetrain_groups, NUM_SMART_PORTS},
+    system::{field::check_object, mechanisms::check_mechanism, scoring::check_shape},
+    SimulatorOptions, Timeout,
+};
+
+/// A robot's simulation setup, loaded from a TOML file like this one:
+///
+/// ```toml
+/// seed = 1234
+/// # seconds of real time, or `simulated_timeout` for seconds of simulated time
+/// timeout = 30
+/// # milliseconds
+/// controller_latency = 10
+/// # messages and waveforms to send when the robot code starts, relative to this file
+/// scenario = "scenarios/skills.jsonl"
+///
+/// [ports]
+/// 5 = "imu"
+/// 8 = "motor"
+///
+/// # sent to frontends, with the motors plugged in and reported as the `left drive` and
+/// # `right drive` groups
+/// [drivetrain]
+/// drive_type = "Tank"
+/// wheel_diameter = 3.25
+/// track_width = 11.5
+/// gear_ratio = 0.6
+/// left_motors = [1, -2]
+/// right_motors = [-3, 4]
+///
+/// [controllers.master]
+/// deadzone = 5
+/// expo = 30
+///
+/// [field]
+/// robot_radius = 9.0
+///
+/// [[field.objects]]
+/// name = "ring 1"
+/// kind = "ring"
+/// x = 24.0
+/// y = 0.0
+/// radius = 3.5
+///
+/// [[field.scoring_zones]]
+/// name = "corner"
+/// shape = { Circle = { x = 60.0, y = 60.0, radius = 12.0 } }
+/// points = 5
+///
+/// [[mechanisms]]
+/// name = "intake"
+/// port = 8
+/// kind = { Intake = { reach = 4.0, capacity = 2 } }
+/// ```
+///
+/// Every section is optional. The drivetrain, game objects, scoring zones, mechanisms and motor
+/// groups are written like their messages and events in the stdio protocol. Unknown keys are an
+/// error, so typos don't go unnoticed.
+///
+/// There are no field presets yet: a season's field has to be written out as game objects and
+/// scoring zones.
+#[derive(Debug, Clone)]
+pub struct SimulatorConfig {
+    options: SimulatorOptions,
+    scenario: Option<PathBuf>,
+}
+
+impl SimulatorConfig {
+    /// Loads a config file, checking everything in it.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("couldn't read `{}`", path.display()))?;
+        let file: ConfigFile =
+            toml::from_str(&text).with_context(|| format!("`{}` is invalid", path.display()))?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        file.into_config(dir)
+            .with_context(|| format!("`{}` is invalid", path.display()))
+    }
+
+    /// The options the file sets, which further options can be set on top of.
+    pub fn options(&self) -> SimulatorOptions {
+        self.options.clone()
+    }
+
+    /// The scenario file to send to the robot code when it starts, if the config names one.
+    /// Relative paths are resolved against the config file's directory.
+    pub fn scenario(&self) -> Option<&Path> {
+        self.scenario.as_deref()
+    }
+}
+
+impl SimulatorOptions {
+    /// Loads options from a `simulator.toml` file. See [`SimulatorConfig`] for what it can
+    /// contain. The file's scenario is ignored, since only a frontend can send it.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(SimulatorConfig::load(path)?.options)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    seed: Option<u64>,
+    timeout: Option<f64>,
+    simulated_timeout: Option<f64>,
+    controller_latency: Option<u64>,
+    scenario: Option<PathBuf>,
+    /// Device types by smart port, like `--device` in the server.
+    #[serde(default)]
+    ports: BTreeMap<String, String>,
+    drivetrain: Option<DrivetrainConfig>,
+    #[serde(default)]
+    controllers: ControllersSection,
+    #[serde(default)]
+    field: FieldSection,
+    #[serde(default)]
+    mechanisms: Vec<Mechanism>,
+    #[serde(default)]
+    motor_groups: Vec<MotorGroup>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ControllersSection {
+    master: Option<InputShaping>,
+    partner: Option<InputShaping>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FieldSection {
+    robot_radius: Option<f64>,
+    #[serde(default)]
+    objects: Vec<GameObject>,
+    #[serde(default)]
+    scoring_zones: Vec<ScoringZone>,
+}
+
+impl ConfigFile {
+    /// Checks the file, since the options' builders panic on things a file can get wrong.
+    fn into_config(self, dir: &Path) -> Result<SimulatorConfig> {
+        let mut options = SimulatorOptions::new();
+        if let Some(seed) = self.seed {
+            options = options.deterministic(seed);
+        }
+        let duration = |seconds: f64| {
+            Duration::try_from_secs_f64(seconds)
+                .ok()
+                .with_context(|| format!("the timeout can't be {seconds} seconds"))
+        };
+        let timeout = match (self.timeout, self.simulated_timeout) {
+            (Some(_), Some(_)) => bail!("only one of `timeout` and `simulated_timeout` can be set"),
+            (Some(seconds), None) => Some(Timeout::RealTime(duration(seconds)?)),
+            (None, Some(seconds)) => Some(Timeout::Simulated(duration(seconds)?)),
+            (None, None) => None,
+        };
+        if let Some(timeout) = timeout {
+            options = options.timeout(timeout);
+        }
+        if let Some(latency) = self.controller_latency {
+            options = options.controller_latency(Duration::from_millis(latency));
+        }
+
+        let mut ports = BTreeMap::new();
+        for (port, device) in &self.ports {
+            let port = port
+                .parse::<u8>()
+                .ok()
+                .filter(|port| (1..=NUM_SMART_PORTS).contains(&(*port as usize)))
+                .with_context(|| format!("`{port}` isn't a smart port (1-21)"))?;
+            let device = device.parse::<DeviceType>().map_err(anyhow::Error::msg)?;
+            ports.insert(port, device);
+        }
+
+        if let Some(drivetrain) = &self.drivetrain {
+            if let Err(reason) = check_drivetrain(drivetrain) {
+                bail!("the drivetrain is invalid: {reason}");
+            }
+            for group in drivetrain_groups(drivetrain) {
+                for port in group.ports {
+                    let port = port.unsigned_abs();
+                    match ports.get(&port) {
+                        None | Some(DeviceType::Motor) => {}
+                        Some(_) => bail!("drive motor port {port} isn't a motor in `[ports]`"),
+                    }
+                }
+            }
+        }
+
+        for (port, device) in ports {
+            options = options.smart_port(port, device);
+        }
+        if let Some(drivetrain) = self.drivetrain {
+            options = options.drivetrain(drivetrain);
+        }
+        for (controller, shaping) in [
+            (ControllerId::Master, self.controllers.master),
+            (ControllerId::Partner, self.controllers.partner),
+        ] {
+            if let Some(shaping) = shaping {
+                options = options.input_shaping(controller, shaping);
+            }
+        }
+
+        if let Some(radius) = self.field.robot_radius {
+            if !radius.is_finite() || radius < 0.0 {
+                bail!("the robot's radius can't be {radius} inches");
+            }
+            options = options.robot_radius(radius);
+        }
+        for object in self.field.objects {
+            if let Err(reason) = check_object(&object) {
+                bail!("game object `{}` is invalid: {reason}", object.name);
+            }
+            options = options.game_object(object);
+        }
+        for zone in self.field.scoring_zones {
+            if let Err(reason) = check_shape(&zone.shape) {
+                bail!("scoring zone `{}` is invalid: {reason}", zone.name);
+            }
+            options = options.scoring_zone(zone);
+        }
+        for mechanism in self.mechanisms {
+            if let Err(reason) = check_mechanism(&mechanism) {
+                bail!("mechanism `{}` is invalid: {reason}", mechanism.name);
+            }
+            if options.mechanisms.iter().any(|m| m.name == mechanism.name) {
+                bail!("there's already a mechanism named `{}`", mechanism.name);
+            }
+            options = options.mechanism(mechanism);
+        }
+        for group in self.motor_groups {
+            if let Err(reason) = check_motor_group(&group) {
+                bail!("motor group `{}` is invalid: {reason}", group.name);
+            }
+            if options.motor_groups.iter().any(|g| g.name == group.name) {
+                bail!("there's already a motor group named `{}`", group.name);
+            }
+            options = options.motor_group(group);
+        }
+
+        Ok(SimulatorConfig {
+            options,
+            scenario: self.scenario.map(|scenario| dir.join(scenario)),
+        })
+    }
+}
@@ -0,0 +1,66 @@
+//! Stable API for extending the simulator with custom host functions and devices.
+//!
+//! Everything re-exported here follows semantic versioning, so plugins that only use these items
+//! to work with robot code memory and `errno` won't break in minor releases. The rest of the
+//! simulator's host is an implementation detail and isn't public, apart from what
+//! [`load`](crate::load) needs for scheduling a simulation by hand.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use pros_simulator::extension::*;
+//!
+//! async fn write_greeting(caller: &mut Caller<'_, Host>, ptr: u32) -> anyhow::Result<bool> {
+//!     let res = caller
+//!         .memory()
+//!         .write_relaxed(ptr as usize, b"hello\0")
+//!         .map_err(i32::from);
+//!     res.unwrap_or_errno(caller).await
+//! }
+//! ```
+
+pub use wasmtime::{Caller, SharedMemory};
+
+pub use crate::host::{
+    memory::{GuestStr, OutOfBoundsError, SharedMemoryExt, MAX_C_STR_LEN},
+    ContextExt, Host, ResultExt,
+};
+use crate::{host::HostCtx, interface::SimulatorInterface};
+
+/// The parts of the simulator a custom host function can reach: robot code memory and the
+/// interface events are sent through. This is implemented for [`Host`] and for anything that can
+/// provide one, like a [`Caller`].
+///
+/// The trait is sealed, so new methods can be added in minor releases.
+pub trait ExtensionCtx: sealed::Sealed {
+    /// The memory shared by the robot code's tasks.
+    fn memory(&self) -> SharedMemory;
+    /// The interface used to send events to the simulator's consumer.
+    fn interface(&self) -> SimulatorInterface;
+    /// Reads a null-terminated string from robot code memory, sending a
+    /// [`Warning`](pros_simulator_interface::SimulatorEvent::Warning) if it had to be truncated
+    /// or contained invalid UTF-8.
+    fn read_c_str(&self, ptr: u32) -> Result<String, OutOfBoundsError>;
+}
+
+impl<T: HostCtx> ExtensionCtx for T {
+    fn memory(&self) -> SharedMemory {
+        HostCtx::memory(self)
+    }
+
+    fn interface(&self) -> SimulatorInterface {
+        HostCtx::interface(self)
+    }
+
+    fn read_c_str(&self, ptr: u32) -> Result<String, OutOfBoundsError> {
+        HostCtx::read_c_str(self, ptr)
+    }
+}
+
+mod sealed {
+    use crate::host::HostCtx;
+
+    pub trait Sealed {}
+
+    impl<T: HostCtx> Sealed for T {}
+}
@@ -1,26 +1,90 @@
-pub mod controllers;
-pub mod lcd;
-pub mod memory;
-pub mod multitasking;
-pub mod task;
-pub mod thread_local;
-
-use std::{alloc::Layout, sync::Arc, time::Instant};
+pub(crate) mod abi;
+pub(crate) mod atomics;
+pub(crate) mod backtrace;
+pub(crate) mod breakpoints;
+pub(crate) mod canaries;
+pub(crate) mod chrome_trace;
+pub(crate) mod clock;
+pub(crate) mod compat;
+pub(crate) mod component;
+pub(crate) mod controllers;
+pub(crate) mod coverage;
+pub(crate) mod custom_messages;
+#[cfg(feature = "event-log")]
+pub(crate) mod event_log;
+pub(crate) mod failures;
+pub(crate) mod flash;
+pub(crate) mod heap;
+pub(crate) mod jitter;
+pub(crate) mod lcd;
+pub(crate) mod limits;
+pub(crate) mod memory;
+pub(crate) mod motor_model;
+pub(crate) mod multitasking;
+#[cfg(feature = "otlp")]
+pub(crate) mod otlp;
+pub(crate) mod panic;
+pub(crate) mod plugins;
+pub(crate) mod profiler;
+pub(crate) mod program_info;
+#[cfg(feature = "render")]
+pub(crate) mod render;
+pub(crate) mod screen;
+pub(crate) mod serial;
+pub(crate) mod smart_ports;
+pub(crate) mod task;
+pub(crate) mod thread_local;
+pub(crate) mod timer;
+
+use std::{
+    alloc::Layout,
+    sync::{Arc, Weak},
+    time::Duration,
+};
 
+use anyhow::{bail, Context};
 use async_trait::async_trait;
 use lcd::Lcd;
-use pros_simulator_interface::CompetitionPhase;
+use pros_simulator_interface::{CompetitionPhase, Pose, SimulatorEvent};
+use screen::Screen;
 use tokio::sync::{Mutex, MutexGuard};
 use wasmtime::{
     AsContext, AsContextMut, Caller, Engine, Instance, Module, SharedMemory, TypedFunc,
 };
 
 use self::{
+    abi::{detect_abi, ProgramAbi},
+    atomics::AtomicWaiters,
+    breakpoints::Breakpoints,
+    canaries::{Canaries, CANARY_LEN},
+    chrome_trace::ChromeTrace,
+    clock::Clock,
     controllers::Controllers,
+    coverage::ApiUsage,
+    custom_messages::CustomMessages,
+    failures::InjectedFailures,
+    flash::Flash,
+    heap::HostHeap,
+    jitter::Jitter,
+    limits::Limits,
+    memory::{OutOfBoundsError, SharedMemoryExt},
     multitasking::MutexPool,
-    task::{TaskHandle, TaskPool},
+    plugins::Plugins,
+    profiler::Profiler,
+    serial::SerialPort,
+    smart_ports::SmartPorts,
+    task::{Errno, Task, TaskHandle, TaskPool},
 };
-use crate::interface::SimulatorInterface;
+use crate::{interface::SimulatorInterface, SimulatorOptions};
+
+/// The length of an RTOS tick in milliseconds, matching PROS's `portTICK_PERIOD_MS`.
+///
+/// Like FreeRTOS's tick interrupt, the [`Clock`] is advanced once per tick while the simulation
+/// runs. `millis`, delays and timeouts all count in whole ticks, and at a tick boundary the running
+/// task is preempted so the scheduler can switch between tasks of the same priority round-robin,
+/// even if none of them block or yield (except with [`SimulatorOptions::jitter`]). A task runs
+/// through at least one whole tick before it's preempted.
+pub const TICK_PERIOD_MS: u64 = 1;
 
 /// This struct contains the functions necessary to send buffers to the sandbox.
 /// By letting the sandboxed allocator know that we want to write a buffer
@@ -28,45 +92,81 @@ use crate::interface::SimulatorInterface;
 /// in the sandbox's heap.
 ///
 /// `wasm_memalign` is used to request a place to write a buffer, and `wasm_free` is
-/// used to tell the sandbox that we're done with the buffer.
+/// used to tell the sandbox that we're done with the buffer. Robot code that doesn't export
+/// both (like C and C++ programs that weren't built with pros-rs) gets buffers from a
+/// [`HostHeap`] instead.
 #[derive(Clone)]
-pub struct WasmAllocator {
-    wasm_memalign: TypedFunc<(u32, u32), u32>,
-    wasm_free: TypedFunc<u32, ()>,
+pub enum WasmAllocator {
+    Guest {
+        wasm_memalign: TypedFunc<(u32, u32), u32>,
+        wasm_free: TypedFunc<u32, ()>,
+    },
+    Host(HostHeap),
 }
 
 impl WasmAllocator {
-    pub fn new(mut store: impl AsContextMut, instance: &Instance) -> Self {
-        Self {
-            wasm_memalign: instance
-                .get_typed_func::<(u32, u32), u32>(&mut store, "wasm_memalign")
-                .unwrap(),
-            wasm_free: instance
-                .get_typed_func::<u32, ()>(&mut store, "wasm_free")
-                .unwrap(),
+    pub fn new(mut store: impl AsContextMut<Data = Host>, instance: &Instance) -> Self {
+        let wasm_memalign = instance.get_typed_func::<(u32, u32), u32>(&mut store, "wasm_memalign");
+        let wasm_free = instance.get_typed_func::<u32, ()>(&mut store, "wasm_free");
+        match (wasm_memalign, wasm_free) {
+            (Ok(wasm_memalign), Ok(wasm_free)) => Self::Guest {
+                wasm_memalign,
+                wasm_free,
+            },
+            _ => Self::Host(store.as_context_mut().data().heap()),
         }
     }
 
+    /// Allocates a buffer in robot code memory. If canaries are enabled, the buffer is surrounded
+    /// by them.
+    ///
+    /// Returns an error if robot code is out of memory, or its allocator traps or returns a
+    /// pointer outside of memory.
     pub async fn memalign(
         &self,
-        mut store: impl AsContextMut<Data = impl Send>,
+        mut store: impl AsContextMut<Data = Host>,
         layout: Layout,
-    ) -> u32 {
-        let size = layout.size().try_into().unwrap();
-        let alignment = layout.align().try_into().unwrap();
-        let ptr = self
-            .wasm_memalign
-            .call_async(&mut store, (alignment, size))
-            .await
-            .unwrap();
+    ) -> anyhow::Result<u32> {
+        let canaries = store.as_context().data().canaries();
+        let (layout, offset) = match canaries {
+            Some(_) => Canaries::padded_layout(layout),
+            None => (layout, 0),
+        };
+        let ptr = match self {
+            Self::Guest { wasm_memalign, .. } => {
+                let size = layout.size().try_into().unwrap();
+                let alignment = layout.align().try_into().unwrap();
+                wasm_memalign
+                    .call_async(&mut store, (alignment, size))
+                    .await?
+            }
+            Self::Host(heap) => heap.alloc(layout).unwrap_or(0),
+        };
         if ptr == 0 {
-            panic!("wasm_memalign failed");
+            bail!("robot code is out of memory");
+        }
+        let ptr = ptr
+            .checked_add(offset as u32)
+            .context("robot code's allocator returned an invalid pointer")?;
+        if let Some(canaries) = canaries {
+            let size = layout.size() - offset - CANARY_LEN;
+            canaries.guard(&store.as_context().data().memory(), ptr, size)?;
         }
-        ptr
+        Ok(ptr)
     }
 
-    pub async fn free(&self, mut store: impl AsContextMut<Data = impl Send>, ptr: u32) {
-        self.wasm_free.call_async(&mut store, ptr).await.unwrap()
+    /// Frees a buffer allocated with [`memalign`](Self::memalign). Returns an error if robot
+    /// code's allocator traps.
+    pub async fn free(
+        &self,
+        mut store: impl AsContextMut<Data = impl Send>,
+        ptr: u32,
+    ) -> anyhow::Result<()> {
+        match self {
+            Self::Guest { wasm_free, .. } => wasm_free.call_async(&mut store, ptr).await,
+            // the host heap never reuses memory
+            Self::Host(_) => Ok(()),
+        }
     }
 }
 
@@ -74,15 +174,65 @@ impl WasmAllocator {
 pub struct Host {
     memory: SharedMemory,
     module: Module,
+    /// The SDK the robot code was built against.
+    abi: ProgramAbi,
     /// Interface for simulator output (e.g. log messages)
     interface: SimulatorInterface,
     lcd: Arc<Mutex<Lcd>>,
+    /// What robot code has drawn on the brain's screen, and how it's being touched.
+    screen: Arc<Mutex<Screen>>,
     /// Pointers to mutexes created with mutex_create
     mutexes: Arc<Mutex<MutexPool>>,
     tasks: Arc<Mutex<TaskPool>>,
     controllers: Arc<Mutex<Controllers>>,
+    smart_ports: Arc<Mutex<SmartPorts>>,
     competition_phase: Arc<Mutex<CompetitionPhase>>,
-    start_time: Instant,
+    /// Where the robot was last placed on the field, if it has been.
+    pose: Arc<Mutex<Option<Pose>>>,
+    /// What robot code has saved to flash.
+    flash: Arc<Mutex<Flash>>,
+    /// The ticks since the simulation started.
+    clock: Arc<Clock>,
+    options: Arc<SimulatorOptions>,
+    rng: Arc<Mutex<fastrand::Rng>>,
+    atomic_waiters: Arc<Mutex<AtomicWaiters>>,
+    injected_failures: Arc<Mutex<InjectedFailures>>,
+    custom_messages: Arc<Mutex<CustomMessages>>,
+    /// Where buffers are allocated for robot code without its own allocator.
+    heap: HostHeap,
+    /// The serial connection console output is sent over.
+    serial: SerialPort,
+    /// Samples the robot code's stack, if profiling is enabled.
+    profiler: Option<Profiler>,
+    /// Spans robot code timed itself, if they're being exported.
+    chrome_trace: Option<ChromeTrace>,
+    /// How many times each host function has been called.
+    api_usage: ApiUsage,
+    /// The API calls robot code should be paused at.
+    breakpoints: Breakpoints,
+    /// The buffers guarded by canaries, if they're enabled.
+    canaries: Option<Canaries>,
+    /// The limits on what robot code can use.
+    limits: Limits,
+    /// Functions loaded from plugins, which are linked into robot code alongside the API.
+    plugins: Arc<Plugins>,
+    /// Exports tasks and host calls as spans, if enabled.
+    #[cfg(feature = "otlp")]
+    spans: Option<otlp::SpanExporter>,
+    /// Draws frames of the field, if enabled.
+    #[cfg(feature = "render")]
+    renderer: Option<render::FrameRenderer>,
+    /// The task that owns the store this host belongs to, if any.
+    task: Weak<Mutex<Task>>,
+    /// The `errno` of the task that owns this store, if any, so setting it doesn't need to look
+    /// the task up.
+    errno: Option<Errno>,
+    /// How many host functions this store is currently inside of.
+    host_calls: u32,
+    /// How many host functions deep this store's task runs its robot code, which is where it can
+    /// be preempted at a tick without interrupting a host function. `None` if the task never
+    /// runs robot code directly, like the system daemon.
+    preempt_at: Option<u32>,
 }
 
 impl Host {
@@ -91,43 +241,251 @@ impl Host {
         memory: SharedMemory,
         interface: SimulatorInterface,
         module: Module,
+        options: SimulatorOptions,
     ) -> anyhow::Result<Self> {
-        let lcd = Lcd::new(interface.clone());
+        let lcd = Lcd::new(interface.clone(), options.lcd_selector);
+        let screen = Screen::new(interface.clone());
         let mutexes = MutexPool::default();
-        let tasks = TaskPool::new(engine, memory.clone(), interface.clone())?;
-        let controllers = Controllers::new(None, None);
+        let jitter = options
+            .jitter
+            .map(|max_delay| Jitter::new(options.seed, max_delay));
+        let limits = Limits::new(&options);
+        let plugins = Plugins::load(&engine, &options.plugins, &limits)?;
+        let tasks = TaskPool::new(
+            engine,
+            memory.clone(),
+            interface.clone(),
+            jitter,
+            options.strict_warnings.clone(),
+            options.timeout,
+            options.stop_signal.clone(),
+        )?;
+        #[cfg(feature = "otlp")]
+        let spans = options
+            .otlp_endpoint
+            .as_deref()
+            .map(otlp::SpanExporter::new)
+            .transpose()?;
+        #[cfg(feature = "otlp")]
+        let tasks = tasks.with_span_exporter(spans.clone());
+        #[cfg(feature = "render")]
+        let renderer = options
+            .render_frames
+            .clone()
+            .map(|(output, hz)| render::FrameRenderer::new(output, hz))
+            .transpose()?;
+        let mut controllers = Controllers::new(None, None);
+        for (controller, shaping) in &options.input_shaping {
+            controllers.set_shaping(*controller, *shaping);
+        }
+        let mut smart_ports = SmartPorts::new(options.smart_ports.iter().copied());
+        smart_ports.set_motor_update_rate(options.motor_update_rate);
+        for group in &options.motor_groups {
+            smart_ports
+                .add_motor_group(group.clone())
+                .expect("motor groups are checked when they're added to the options");
+        }
+        let rng = match options.seed {
+            Some(seed) => fastrand::Rng::with_seed(seed),
+            None => fastrand::Rng::new(),
+        };
+
+        let flash = Flash::load(options.flash.clone())?;
+        let heap = HostHeap::new(memory.clone());
+        let serial = SerialPort::new(
+            interface.clone(),
+            options.serial_baud_rate,
+            options.line_buffering,
+        );
+        let profiler = options.profile.is_some().then(Profiler::new);
+        let chrome_trace = options.chrome_trace.is_some().then(ChromeTrace::new);
+        let canaries = options.canaries.then(Canaries::default);
+        let clock = Arc::new(Clock::new());
+        #[cfg(feature = "event-log")]
+        if let Some(event_log) = interface.event_log() {
+            event_log.follow_clock(clock.clone());
+        }
 
         Ok(Self {
             memory,
+            abi: detect_abi(&module),
             module,
             interface,
             lcd: Arc::new(Mutex::new(lcd)),
+            screen: Arc::new(Mutex::new(screen)),
             mutexes: Arc::new(Mutex::new(mutexes)),
             tasks: Arc::new(Mutex::new(tasks)),
             controllers: Arc::new(Mutex::new(controllers)),
+            smart_ports: Arc::new(Mutex::new(smart_ports)),
             competition_phase: Default::default(),
-            start_time: Instant::now(),
+            pose: Default::default(),
+            flash: Arc::new(Mutex::new(flash)),
+            clock,
+            options: Arc::new(options),
+            rng: Arc::new(Mutex::new(rng)),
+            atomic_waiters: Default::default(),
+            injected_failures: Default::default(),
+            custom_messages: Default::default(),
+            heap,
+            serial,
+            profiler,
+            chrome_trace,
+            api_usage: ApiUsage::new(),
+            breakpoints: Breakpoints::default(),
+            canaries,
+            limits,
+            plugins: Arc::new(plugins),
+            #[cfg(feature = "otlp")]
+            spans,
+            #[cfg(feature = "render")]
+            renderer,
+            task: Weak::new(),
+            errno: None,
+            host_calls: 0,
+            preempt_at: None,
         })
     }
+
+    /// The functions loaded from [plugins](crate::SimulatorOptions::plugin).
+    pub(crate) fn plugins(&self) -> Arc<Plugins> {
+        self.plugins.clone()
+    }
 }
 
+/// Access to the simulator state shared by every task. This is implemented for [`Host`] itself
+/// and for anything that can provide one, like a [`Caller`] or [`Store`](wasmtime::Store).
+///
+/// Like [`ExtensionCtx`](crate::extension::ExtensionCtx), the trait is sealed.
 #[async_trait]
-pub trait HostCtx {
+pub trait HostCtx: sealed::Sealed {
+    /// The memory shared by the robot code's tasks.
     fn memory(&self) -> SharedMemory;
+    /// The compiled robot code.
     fn module(&self) -> Module;
+    /// The SDK the robot code was built against.
+    fn abi(&self) -> ProgramAbi;
+    /// The interface used to send events to the simulator's consumer.
     fn interface(&self) -> SimulatorInterface;
     fn lcd(&self) -> Arc<Mutex<Lcd>>;
     async fn lcd_lock(&self) -> MutexGuard<'_, Lcd>;
+    fn screen(&self) -> Arc<Mutex<Screen>>;
+    async fn screen_lock(&self) -> MutexGuard<'_, Screen>;
     fn mutexes(&self) -> Arc<Mutex<MutexPool>>;
     async fn mutexes_lock(&self) -> MutexGuard<'_, MutexPool>;
     fn tasks(&self) -> Arc<Mutex<TaskPool>>;
     async fn tasks_lock(&self) -> MutexGuard<'_, TaskPool>;
-    fn start_time(&self) -> Instant;
+    /// The simulation's clock, which `millis` and delays are relative to.
+    fn clock(&self) -> Arc<Clock>;
+    /// Moves the simulation's clock forward by `duration`, rounded down to whole ticks. Tasks
+    /// delayed until one of the skipped ticks wake up.
+    fn advance_time(&self, duration: Duration) -> anyhow::Result<()> {
+        let ticks = duration.as_millis() as u64 / TICK_PERIOD_MS;
+        self.clock().advance(ticks);
+        Ok(())
+    }
+    /// The number of RTOS ticks since the simulation started. Like on FreeRTOS, `millis`, delays
+    /// and timeouts all advance in whole ticks.
+    fn ticks(&self) -> u64 {
+        self.clock().ticks()
+    }
+    /// The simulated time since the simulation started, including how far into the current tick
+    /// it is.
+    fn elapsed(&self) -> Duration {
+        self.clock().elapsed()
+    }
+    /// The value robot code gets from `millis`, which counts up from
+    /// [`SimulatorOptions::start_millis`] and wraps around.
+    fn millis(&self) -> u32 {
+        let elapsed = self.ticks() * TICK_PERIOD_MS;
+        self.options().start_millis.wrapping_add(elapsed as u32)
+    }
+    /// The task that is currently running.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no task is running, which can only happen outside of a host function.
     async fn current_task(&self) -> TaskHandle;
     fn controllers(&self) -> Arc<Mutex<Controllers>>;
     async fn controllers_lock(&self) -> MutexGuard<'_, Controllers>;
+    fn smart_ports(&self) -> Arc<Mutex<SmartPorts>>;
+    async fn smart_ports_lock(&self) -> MutexGuard<'_, SmartPorts>;
     fn competition_phase(&self) -> Arc<Mutex<CompetitionPhase>>;
     async fn competition_phase_lock(&self) -> MutexGuard<'_, CompetitionPhase>;
+    /// Where the robot was last placed on the field, by
+    /// [`SimulatorMessage::SetPose`](pros_simulator_interface::SimulatorMessage::SetPose) or
+    /// `sim_set_pose`. The simulator doesn't move the robot itself.
+    fn pose(&self) -> Arc<Mutex<Option<Pose>>>;
+    async fn pose_lock(&self) -> MutexGuard<'_, Option<Pose>>;
+    /// What robot code has saved to flash with `sim_flash_write`, which is kept between runs if
+    /// [`SimulatorOptions::flash`](crate::SimulatorOptions::flash) is set.
+    fn flash(&self) -> Arc<Mutex<Flash>>;
+    async fn flash_lock(&self) -> MutexGuard<'_, Flash>;
+    /// The options the simulation was started with.
+    fn options(&self) -> Arc<SimulatorOptions>;
+    /// The random number generator used by `sim_random`. It is seeded by
+    /// [`SimulatorOptions::deterministic`] if set.
+    fn rng(&self) -> Arc<Mutex<fastrand::Rng>>;
+    async fn rng_lock(&self) -> MutexGuard<'_, fastrand::Rng>;
+    /// Tasks blocked on `memory.atomic.wait*` instructions.
+    fn atomic_waiters(&self) -> Arc<Mutex<AtomicWaiters>>;
+    async fn atomic_waiters_lock(&self) -> MutexGuard<'_, AtomicWaiters>;
+    /// Failures that frontends have asked the next calls to host functions to fail with.
+    fn injected_failures(&self) -> Arc<Mutex<InjectedFailures>>;
+    async fn injected_failures_lock(&self) -> MutexGuard<'_, InjectedFailures>;
+    /// Messages that frontends have sent robot code, waiting for it to poll them.
+    fn custom_messages(&self) -> Arc<Mutex<CustomMessages>>;
+    async fn custom_messages_lock(&self) -> MutexGuard<'_, CustomMessages>;
+    /// The program break used by `sbrk`, which also holds the simulator's buffers in robot code
+    /// without its own allocator.
+    fn heap(&self) -> HostHeap;
+    /// The serial connection console output is sent over, which may be throttled by
+    /// [`SimulatorOptions::throttle_serial`].
+    fn serial(&self) -> SerialPort;
+    /// The profiler sampling the robot code's stack, if
+    /// [`SimulatorOptions::profile`] is set.
+    fn profiler(&self) -> Option<Profiler>;
+    /// The spans robot code timed with `sim_profile_begin` and `sim_profile_end`, if
+    /// [`SimulatorOptions::chrome_trace`] is set.
+    fn chrome_trace(&self) -> Option<ChromeTrace>;
+    /// How many times each host function has been called, for the
+    /// [`ApiCoverage`](pros_simulator_interface::SimulatorEvent::ApiCoverage) summary.
+    fn api_usage(&self) -> ApiUsage;
+    /// The API calls robot code should be paused at, added with
+    /// [`SimulatorMessage::BreakOnCall`](pros_simulator_interface::SimulatorMessage::BreakOnCall).
+    fn breakpoints(&self) -> Breakpoints;
+    /// The buffers allocated by the simulator that are guarded by canaries, if
+    /// [`SimulatorOptions::canaries`](crate::SimulatorOptions::canaries) is enabled.
+    fn canaries(&self) -> Option<Canaries>;
+    /// The limits on what robot code can use, set with
+    /// [`SimulatorOptions::max_memory`](crate::SimulatorOptions::max_memory) and friends.
+    fn limits(&self) -> Limits;
+    /// Exports tasks and host calls as OpenTelemetry spans, if
+    /// [`SimulatorOptions::otlp_endpoint`](crate::SimulatorOptions::otlp_endpoint) is set.
+    #[cfg(feature = "otlp")]
+    fn spans(&self) -> Option<otlp::SpanExporter>;
+    /// Draws frames of the field, if
+    /// [`SimulatorOptions::render_frames`](crate::SimulatorOptions::render_frames) is set.
+    #[cfg(feature = "render")]
+    fn renderer(&self) -> Option<render::FrameRenderer>;
+
+    /// Looks up a task by the handle robot code uses for it, where `0` refers to the current task.
+    async fn task_by_handle(&self, task_handle: u32) -> Option<TaskHandle> {
+        if task_handle == 0 {
+            return Some(self.current_task().await);
+        }
+        self.tasks_lock().await.by_id(task_handle)
+    }
+
+    /// Reads a null-terminated string from robot code memory, sending a
+    /// [`Warning`](pros_simulator_interface::SimulatorEvent::Warning) if it had to be truncated
+    /// or contained invalid UTF-8.
+    fn read_c_str(&self, ptr: u32) -> Result<String, OutOfBoundsError> {
+        let string = self.memory().read_c_str(ptr)?;
+        if let Some(warning) = string.warning(ptr) {
+            self.interface().send(SimulatorEvent::Warning(warning));
+        }
+        Ok(string.text)
+    }
 }
 
 #[async_trait]
@@ -140,6 +498,10 @@ impl HostCtx for Host {
         self.module.clone()
     }
 
+    fn abi(&self) -> ProgramAbi {
+        self.abi
+    }
+
     fn interface(&self) -> SimulatorInterface {
         self.interface.clone()
     }
@@ -152,6 +514,14 @@ impl HostCtx for Host {
         self.lcd.lock().await
     }
 
+    fn screen(&self) -> Arc<Mutex<Screen>> {
+        self.screen.clone()
+    }
+
+    async fn screen_lock(&self) -> MutexGuard<'_, Screen> {
+        self.screen.lock().await
+    }
+
     fn mutexes(&self) -> Arc<Mutex<MutexPool>> {
         self.mutexes.clone()
     }
@@ -168,8 +538,8 @@ impl HostCtx for Host {
         self.tasks.lock().await
     }
 
-    fn start_time(&self) -> Instant {
-        self.start_time
+    fn clock(&self) -> Arc<Clock> {
+        self.clock.clone()
     }
 
     async fn current_task(&self) -> TaskHandle {
@@ -184,6 +554,14 @@ impl HostCtx for Host {
         self.controllers.lock().await
     }
 
+    fn smart_ports(&self) -> Arc<Mutex<SmartPorts>> {
+        self.smart_ports.clone()
+    }
+
+    async fn smart_ports_lock(&self) -> MutexGuard<'_, SmartPorts> {
+        self.smart_ports.lock().await
+    }
+
     fn competition_phase(&self) -> Arc<Mutex<CompetitionPhase>> {
         self.competition_phase.clone()
     }
@@ -191,6 +569,100 @@ impl HostCtx for Host {
     async fn competition_phase_lock(&self) -> MutexGuard<'_, CompetitionPhase> {
         self.competition_phase.lock().await
     }
+
+    fn pose(&self) -> Arc<Mutex<Option<Pose>>> {
+        self.pose.clone()
+    }
+
+    async fn pose_lock(&self) -> MutexGuard<'_, Option<Pose>> {
+        self.pose.lock().await
+    }
+
+    fn flash(&self) -> Arc<Mutex<Flash>> {
+        self.flash.clone()
+    }
+
+    async fn flash_lock(&self) -> MutexGuard<'_, Flash> {
+        self.flash.lock().await
+    }
+
+    fn options(&self) -> Arc<SimulatorOptions> {
+        self.options.clone()
+    }
+
+    fn rng(&self) -> Arc<Mutex<fastrand::Rng>> {
+        self.rng.clone()
+    }
+
+    async fn rng_lock(&self) -> MutexGuard<'_, fastrand::Rng> {
+        self.rng.lock().await
+    }
+
+    fn atomic_waiters(&self) -> Arc<Mutex<AtomicWaiters>> {
+        self.atomic_waiters.clone()
+    }
+
+    fn heap(&self) -> HostHeap {
+        self.heap.clone()
+    }
+
+    fn serial(&self) -> SerialPort {
+        self.serial.clone()
+    }
+
+    fn profiler(&self) -> Option<Profiler> {
+        self.profiler.clone()
+    }
+
+    fn chrome_trace(&self) -> Option<ChromeTrace> {
+        self.chrome_trace.clone()
+    }
+
+    fn api_usage(&self) -> ApiUsage {
+        self.api_usage.clone()
+    }
+
+    fn breakpoints(&self) -> Breakpoints {
+        self.breakpoints.clone()
+    }
+
+    fn canaries(&self) -> Option<Canaries> {
+        self.canaries.clone()
+    }
+
+    fn limits(&self) -> Limits {
+        self.limits.clone()
+    }
+
+    #[cfg(feature = "otlp")]
+    fn spans(&self) -> Option<otlp::SpanExporter> {
+        self.spans.clone()
+    }
+
+    #[cfg(feature = "render")]
+    fn renderer(&self) -> Option<render::FrameRenderer> {
+        self.renderer.clone()
+    }
+
+    async fn atomic_waiters_lock(&self) -> MutexGuard<'_, AtomicWaiters> {
+        self.atomic_waiters.lock().await
+    }
+
+    fn injected_failures(&self) -> Arc<Mutex<InjectedFailures>> {
+        self.injected_failures.clone()
+    }
+
+    async fn injected_failures_lock(&self) -> MutexGuard<'_, InjectedFailures> {
+        self.injected_failures.lock().await
+    }
+
+    fn custom_messages(&self) -> Arc<Mutex<CustomMessages>> {
+        self.custom_messages.clone()
+    }
+
+    async fn custom_messages_lock(&self) -> MutexGuard<'_, CustomMessages> {
+        self.custom_messages.lock().await
+    }
 }
 
 #[async_trait]
@@ -206,6 +678,10 @@ where
         self.as_context().data().module()
     }
 
+    fn abi(&self) -> ProgramAbi {
+        self.as_context().data().abi()
+    }
+
     fn interface(&self) -> SimulatorInterface {
         self.as_context().data().interface()
     }
@@ -218,6 +694,14 @@ where
         self.as_context().data().lcd_lock().await
     }
 
+    fn screen(&self) -> Arc<Mutex<Screen>> {
+        self.as_context().data().screen()
+    }
+
+    async fn screen_lock(&self) -> MutexGuard<'_, Screen> {
+        self.as_context().data().screen_lock().await
+    }
+
     fn mutexes(&self) -> Arc<Mutex<MutexPool>> {
         self.as_context().data().mutexes()
     }
@@ -234,12 +718,18 @@ where
         self.as_context().data().tasks_lock().await
     }
 
-    fn start_time(&self) -> Instant {
-        self.as_context().data().start_time()
+    fn clock(&self) -> Arc<Clock> {
+        self.as_context().data().clock()
     }
 
     async fn current_task(&self) -> TaskHandle {
-        self.as_context().data().tasks_lock().await.current()
+        // Host functions always run in the store of the task that called them, which may not be
+        // the scheduler's current task when tasks run on their own threads.
+        let host = self.as_context().data();
+        match host.task.upgrade() {
+            Some(task) => task,
+            None => host.tasks_lock().await.current(),
+        }
     }
 
     fn controllers(&self) -> Arc<Mutex<Controllers>> {
@@ -250,6 +740,14 @@ where
         self.as_context().data().controllers_lock().await
     }
 
+    fn smart_ports(&self) -> Arc<Mutex<SmartPorts>> {
+        self.as_context().data().smart_ports()
+    }
+
+    async fn smart_ports_lock(&self) -> MutexGuard<'_, SmartPorts> {
+        self.as_context().data().smart_ports_lock().await
+    }
+
     fn competition_phase(&self) -> Arc<Mutex<CompetitionPhase>> {
         self.as_context().data().competition_phase()
     }
@@ -257,13 +755,123 @@ where
     async fn competition_phase_lock(&self) -> MutexGuard<'_, CompetitionPhase> {
         self.as_context().data().competition_phase_lock().await
     }
+
+    fn pose(&self) -> Arc<Mutex<Option<Pose>>> {
+        self.as_context().data().pose()
+    }
+
+    async fn pose_lock(&self) -> MutexGuard<'_, Option<Pose>> {
+        self.as_context().data().pose_lock().await
+    }
+
+    fn flash(&self) -> Arc<Mutex<Flash>> {
+        self.as_context().data().flash()
+    }
+
+    async fn flash_lock(&self) -> MutexGuard<'_, Flash> {
+        self.as_context().data().flash_lock().await
+    }
+
+    fn options(&self) -> Arc<SimulatorOptions> {
+        self.as_context().data().options()
+    }
+
+    fn rng(&self) -> Arc<Mutex<fastrand::Rng>> {
+        self.as_context().data().rng()
+    }
+
+    async fn rng_lock(&self) -> MutexGuard<'_, fastrand::Rng> {
+        self.as_context().data().rng_lock().await
+    }
+
+    fn atomic_waiters(&self) -> Arc<Mutex<AtomicWaiters>> {
+        self.as_context().data().atomic_waiters()
+    }
+
+    fn heap(&self) -> HostHeap {
+        self.as_context().data().heap()
+    }
+
+    fn serial(&self) -> SerialPort {
+        self.as_context().data().serial()
+    }
+
+    fn profiler(&self) -> Option<Profiler> {
+        self.as_context().data().profiler()
+    }
+
+    fn chrome_trace(&self) -> Option<ChromeTrace> {
+        self.as_context().data().chrome_trace()
+    }
+
+    fn api_usage(&self) -> ApiUsage {
+        self.as_context().data().api_usage()
+    }
+
+    fn breakpoints(&self) -> Breakpoints {
+        self.as_context().data().breakpoints()
+    }
+
+    fn canaries(&self) -> Option<Canaries> {
+        self.as_context().data().canaries()
+    }
+
+    fn limits(&self) -> Limits {
+        self.as_context().data().limits()
+    }
+
+    #[cfg(feature = "otlp")]
+    fn spans(&self) -> Option<otlp::SpanExporter> {
+        self.as_context().data().spans()
+    }
+
+    #[cfg(feature = "render")]
+    fn renderer(&self) -> Option<render::FrameRenderer> {
+        self.as_context().data().renderer()
+    }
+
+    async fn atomic_waiters_lock(&self) -> MutexGuard<'_, AtomicWaiters> {
+        self.as_context().data().atomic_waiters_lock().await
+    }
+
+    fn injected_failures(&self) -> Arc<Mutex<InjectedFailures>> {
+        self.as_context().data().injected_failures()
+    }
+
+    async fn injected_failures_lock(&self) -> MutexGuard<'_, InjectedFailures> {
+        self.as_context().data().injected_failures_lock().await
+    }
+
+    fn custom_messages(&self) -> Arc<Mutex<CustomMessages>> {
+        self.as_context().data().custom_messages()
+    }
+
+    async fn custom_messages_lock(&self) -> MutexGuard<'_, CustomMessages> {
+        self.as_context().data().custom_messages_lock().await
+    }
 }
 
+mod sealed {
+    use wasmtime::AsContext;
+
+    use super::Host;
+
+    pub trait Sealed {}
+
+    impl Sealed for Host {}
+
+    impl<T: AsContext<Data = Host>> Sealed for T {}
+}
+
+/// Helpers for reading and writing the current task's `errno`.
 #[async_trait]
 pub trait ContextExt {
-    /// Sets the task's errno value to the given code.
-    async fn set_errno(&mut self, code: i32);
-    async fn errno_address(&mut self) -> u32;
+    /// Sets the task's errno value to the given code. Fails if there was no memory left to
+    /// allocate it in.
+    async fn set_errno(&mut self, code: i32) -> anyhow::Result<()>;
+    /// Returns the address of the task's errno value in robot code memory. Fails if there was no
+    /// memory left to allocate it in.
+    async fn errno_address(&mut self) -> anyhow::Result<u32>;
 }
 
 #[async_trait]
@@ -271,59 +879,79 @@ impl<T> ContextExt for T
 where
     T: AsContextMut<Data = Host> + Sync + Send,
 {
-    async fn set_errno(&mut self, code: i32) {
-        let current_task = self.current_task().await;
-        let errno = current_task.lock().await.errno(&mut *self).await;
-        errno.set(&self.memory(), code);
+    async fn set_errno(&mut self, code: i32) -> anyhow::Result<()> {
+        let errno = current_errno(self).await;
+        errno.set(self, code).await
+    }
+    async fn errno_address(&mut self) -> anyhow::Result<u32> {
+        let errno = current_errno(self).await;
+        errno.mark_read();
+        errno.address(self).await
     }
-    async fn errno_address(&mut self) -> u32 {
-        let current_task = self.current_task().await;
-        let errno = current_task.lock().await.errno(self).await;
-        errno.address()
+}
+
+/// The current task's `errno`. Tasks' stores keep a copy of it, so this doesn't lock anything
+/// unless it's called from a store that doesn't belong to a task.
+async fn current_errno(store: &(impl AsContext<Data = Host> + Sync)) -> Errno {
+    if let Some(errno) = &store.as_context().data().errno {
+        return errno.clone();
     }
+    let current_task = store.current_task().await;
+    let errno = current_task.lock().await.errno().clone();
+    errno
 }
 
 #[async_trait]
 pub trait ResultExt<T> {
-    /// If this result is an error, sets the simulator's [`errno`](Host::errno_address) to the Err value.
-    /// Returns `true` if the result was Ok and `false` if it was Err.
+    /// If this result is an error, sets the simulator's [`errno`](ContextExt::errno_address) to the Err value.
+    /// Returns `true` if the result was Ok and `false` if it was Err, or fails if `errno`
+    /// couldn't be set.
     ///
     /// # Example
     ///
     /// ```ignore
     /// let res = lcd.set_line(line, "");
-    /// Ok(res.unwrap_or_errno(&mut caller).await.into())
+    /// Ok(res.unwrap_or_errno(&mut caller).await?.into())
     /// ```
-    async fn unwrap_or_errno(self, caller: &mut Caller<'_, Host>) -> bool;
+    async fn unwrap_or_errno(self, caller: &mut Caller<'_, Host>) -> anyhow::Result<bool>;
 
-    /// If this result is an error, sets the simulator's [`errno`](Host::errno_address) to the Err value.
-    /// Returns the `T` value if the result was Ok and the `error_value` parameter if it was Err.
+    /// If this result is an error, sets the simulator's [`errno`](ContextExt::errno_address) to the Err value.
+    /// Returns the `T` value if the result was Ok and the `error_value` parameter if it was Err,
+    /// or fails if `errno` couldn't be set.
     ///
     /// # Example
     ///
     /// ```ignore
     /// let res = controllers.get_analog(pros_sys::E_CONTROLLER_MASTER);
-    /// Ok(res.unwrap_or_errno_as(&mut caller, 0).await)
+    /// res.unwrap_or_errno_as(&mut caller, 0).await
     /// ```
-    async fn unwrap_or_errno_as(self, caller: &mut Caller<'_, Host>, error_value: T) -> T;
+    async fn unwrap_or_errno_as(
+        self,
+        caller: &mut Caller<'_, Host>,
+        error_value: T,
+    ) -> anyhow::Result<T>;
 }
 
 #[async_trait]
 impl<T: Send> ResultExt<T> for Result<T, i32> {
-    async fn unwrap_or_errno(self, caller: &mut Caller<'_, Host>) -> bool {
+    async fn unwrap_or_errno(self, caller: &mut Caller<'_, Host>) -> anyhow::Result<bool> {
         if let Err(code) = self {
-            caller.set_errno(code).await;
+            caller.set_errno(code).await?;
         }
-        self.is_ok()
+        Ok(self.is_ok())
     }
 
-    async fn unwrap_or_errno_as(self, caller: &mut Caller<'_, Host>, error_value: T) -> T {
+    async fn unwrap_or_errno_as(
+        self,
+        caller: &mut Caller<'_, Host>,
+        error_value: T,
+    ) -> anyhow::Result<T> {
         match self {
             Err(code) => {
-                caller.set_errno(code).await;
-                error_value
+                caller.set_errno(code).await?;
+                Ok(error_value)
             }
-            Ok(value) => value,
+            Ok(value) => Ok(value),
         }
     }
 }
@@ -0,0 +1,37 @@
+//! Detection of which SDK the robot code was built against.
+
+use std::collections::BTreeSet;
+
+pub use pros_simulator_interface::ProgramAbi;
+use wasmtime::Module;
+
+use super::atomics::ATOMICS_MODULE;
+
+/// The import module for the vexide SDK.
+pub const VEX_MODULE: &str = "vex";
+
+/// The modules the robot code imports from that the simulator doesn't provide, like
+/// `wasi_snapshot_preview1`.
+pub fn unsupported_imports(module: &Module) -> BTreeSet<&str> {
+    module
+        .imports()
+        .map(|import| import.module())
+        .filter(|name| !matches!(*name, "env" | VEX_MODULE | ATOMICS_MODULE))
+        .collect()
+}
+
+/// Classifies the module from its imports and exports. Programs with
+/// [unsupported imports](unsupported_imports) are [`ProgramAbi::Unknown`].
+pub fn detect_abi(module: &Module) -> ProgramAbi {
+    if !unsupported_imports(module).is_empty() {
+        ProgramAbi::Unknown
+    } else if module.imports().any(|import| import.module() == VEX_MODULE) {
+        ProgramAbi::Vexide
+    } else if module.get_export("wasm_memalign").is_some()
+        && module.get_export("wasm_free").is_some()
+    {
+        ProgramAbi::ProsRs
+    } else {
+        ProgramAbi::ProsC
+    }
+}
@@ -0,0 +1,173 @@
+//! Support for `memory.atomic.wait32`, `memory.atomic.wait64` and `memory.atomic.notify`.
+//!
+//! By default every task runs on the same thread, so letting wasmtime execute a wait instruction
+//! would block the whole simulator until it timed out. Instead, robot code is instrumented when it's loaded
+//! so that these instructions call host functions which block cooperatively, letting other tasks
+//! run (and notify the waiter) in the meantime.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use anyhow::Context;
+use walrus::{
+    ir::{Call, Const, Instr, InstrSeq, Value, VisitorMut},
+    FunctionId, ModuleConfig, ValType,
+};
+use wasmparser::{Operator, Parser, Payload};
+
+/// The import module used for the host functions that replace atomic instructions.
+pub const ATOMICS_MODULE: &str = "__pros_simulator_atomics";
+
+/// Tasks blocked in `memory.atomic.wait*`, keyed by the address they're waiting on.
+#[derive(Debug, Default)]
+pub struct AtomicWaiters {
+    queues: HashMap<u32, VecDeque<Arc<AtomicBool>>>,
+}
+
+impl AtomicWaiters {
+    /// Adds a waiter for the given address. The returned flag is set once it has been notified.
+    pub fn wait(&mut self, address: u32) -> Arc<AtomicBool> {
+        let woken = Arc::new(AtomicBool::new(false));
+        self.queues
+            .entry(address)
+            .or_default()
+            .push_back(woken.clone());
+        woken
+    }
+
+    /// Removes a waiter that timed out before being notified.
+    pub fn cancel(&mut self, address: u32, waiter: &Arc<AtomicBool>) {
+        if let Some(queue) = self.queues.get_mut(&address) {
+            queue.retain(|other| !Arc::ptr_eq(other, waiter));
+            if queue.is_empty() {
+                self.queues.remove(&address);
+            }
+        }
+    }
+
+    /// Wakes up to `count` waiters on the given address in the order they started waiting,
+    /// returning how many were woken.
+    pub fn notify(&mut self, address: u32, count: u32) -> u32 {
+        let Some(queue) = self.queues.get_mut(&address) else {
+            return 0;
+        };
+        let mut woken = 0;
+        while woken < count {
+            let Some(waiter) = queue.pop_front() else {
+                break;
+            };
+            waiter.store(true, Ordering::Release);
+            woken += 1;
+        }
+        if queue.is_empty() {
+            self.queues.remove(&address);
+        }
+        woken
+    }
+}
+
+/// Whether the module uses any atomic wait or notify instructions.
+fn uses_wait_notify(wasm: &[u8]) -> bool {
+    for payload in Parser::new(0).parse_all(wasm) {
+        let Ok(Payload::CodeSectionEntry(body)) = payload else {
+            continue;
+        };
+        let Ok(operators) = body.get_operators_reader() else {
+            continue;
+        };
+        for operator in operators {
+            if matches!(
+                operator,
+                Ok(Operator::MemoryAtomicWait32 { .. }
+                    | Operator::MemoryAtomicWait64 { .. }
+                    | Operator::MemoryAtomicNotify { .. })
+            ) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Replaces each atomic instruction with a call to the matching host function, passing the
+/// instruction's static offset as an extra parameter.
+struct ReplaceAtomics {
+    wait32: FunctionId,
+    wait64: FunctionId,
+    notify: FunctionId,
+}
+
+impl VisitorMut for ReplaceAtomics {
+    fn start_instr_seq_mut(&mut self, seq: &mut InstrSeq) {
+        let mut instrs = Vec::with_capacity(seq.instrs.len());
+        for (instr, loc) in seq.instrs.drain(..) {
+            let (func, offset) = match &instr {
+                Instr::AtomicWait(wait) if wait.sixty_four => (self.wait64, wait.arg.offset),
+                Instr::AtomicWait(wait) => (self.wait32, wait.arg.offset),
+                Instr::AtomicNotify(notify) => (self.notify, notify.arg.offset),
+                _ => {
+                    instrs.push((instr, loc));
+                    continue;
+                }
+            };
+            instrs.push((
+                Const {
+                    value: Value::I32(offset as i32),
+                }
+                .into(),
+                loc,
+            ));
+            instrs.push((Call { func }.into(), loc));
+        }
+        seq.instrs = instrs;
+    }
+}
+
+/// Rewrites robot code so that atomic wait and notify instructions call into the simulator.
+/// Modules that don't use them are returned unchanged.
+pub fn instrument_atomics(wasm: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+    if !uses_wait_notify(&wasm) {
+        return Ok(wasm);
+    }
+
+    let mut module = ModuleConfig::new()
+        .generate_dwarf(true)
+        .preserve_code_transform(true)
+        .parse(&wasm)
+        .context("Failed to parse robot code for atomics instrumentation")?;
+
+    let wait32_ty = module.types.add(
+        &[ValType::I32, ValType::I32, ValType::I64, ValType::I32],
+        &[ValType::I32],
+    );
+    let wait64_ty = module.types.add(
+        &[ValType::I32, ValType::I64, ValType::I64, ValType::I32],
+        &[ValType::I32],
+    );
+    let notify_ty = module
+        .types
+        .add(&[ValType::I32, ValType::I32, ValType::I32], &[ValType::I32]);
+    let mut visitor = ReplaceAtomics {
+        wait32: module
+            .add_import_func(ATOMICS_MODULE, "memory_atomic_wait32", wait32_ty)
+            .0,
+        wait64: module
+            .add_import_func(ATOMICS_MODULE, "memory_atomic_wait64", wait64_ty)
+            .0,
+        notify: module
+            .add_import_func(ATOMICS_MODULE, "memory_atomic_notify", notify_ty)
+            .0,
+    };
+
+    for (_, func) in module.funcs.iter_local_mut() {
+        let entry = func.entry_block();
+        walrus::ir::dfs_pre_order_mut(&mut visitor, func, entry);
+    }
+
+    Ok(module.emit_wasm())
+}
@@ -0,0 +1,26 @@
+use pros_simulator_interface::{BacktraceFrame, SourceLocation};
+use wasmtime::WasmBacktrace;
+
+/// Converts a backtrace captured by the WebAssembly runtime into frames that can be sent to the
+/// simulator's consumer.
+pub fn backtrace_frames(backtrace: &WasmBacktrace) -> Vec<BacktraceFrame> {
+    backtrace
+        .frames()
+        .iter()
+        .map(|frame| BacktraceFrame {
+            func_index: frame.func_index(),
+            func_name: frame.func_name().map(ToString::to_string),
+            module_offset: frame.module_offset(),
+            symbols: frame
+                .symbols()
+                .iter()
+                .map(|symbol| SourceLocation {
+                    name: symbol.name().map(ToString::to_string),
+                    file: symbol.file().map(ToString::to_string),
+                    line: symbol.line(),
+                    column: symbol.column(),
+                })
+                .collect(),
+        })
+        .collect()
+}
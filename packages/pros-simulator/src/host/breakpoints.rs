@@ -0,0 +1,69 @@
+//! Pausing robot code when it calls an API. See
+//! [`SimulatorMessage::BreakOnCall`](pros_simulator_interface::SimulatorMessage::BreakOnCall).
+
+use std::sync::{Arc, Mutex};
+
+use pros_simulator_interface::{CallCondition, SimulatorEvent};
+use wasmtime::{Caller, WasmBacktrace};
+
+use super::{backtrace::backtrace_frames, task::TaskPool, Host, HostCtx};
+
+#[derive(Debug)]
+struct Breakpoint {
+    api: String,
+    condition: Option<CallCondition>,
+}
+
+/// The API calls robot code should be paused at.
+#[derive(Debug, Clone, Default)]
+pub struct Breakpoints {
+    breakpoints: Arc<Mutex<Vec<Breakpoint>>>,
+}
+
+impl Breakpoints {
+    /// Breaks on calls to `api` that meet the condition, or every call if there isn't one.
+    pub fn add(&self, api: String, condition: Option<CallCondition>) {
+        self.breakpoints
+            .lock()
+            .unwrap()
+            .push(Breakpoint { api, condition });
+    }
+
+    pub fn clear(&self) {
+        self.breakpoints.lock().unwrap().clear();
+    }
+
+    /// Whether a call to `api` with the given arguments should be broken on.
+    pub fn hit(&self, api: &str, args: &[i64]) -> bool {
+        self.breakpoints.lock().unwrap().iter().any(|breakpoint| {
+            breakpoint.api == api
+                && breakpoint
+                    .condition
+                    .is_none_or(|condition| condition.matches(args))
+        })
+    }
+}
+
+/// Tells the frontend robot code hit a breakpoint, and pauses it until the frontend resumes it.
+/// Called by host functions before they run.
+pub async fn pause(caller: &mut Caller<'_, Host>, api: &str, args: Vec<i64>) {
+    let backtrace = backtrace_frames(&WasmBacktrace::force_capture(&*caller));
+    let (task_id, task_name) = {
+        let task = caller.current_task().await;
+        let task = task.lock().await;
+        (task.id(), task.name().to_string())
+    };
+    caller.interface().send(SimulatorEvent::Breakpoint {
+        api: api.to_string(),
+        args,
+        task_id,
+        task_name,
+        backtrace,
+    });
+
+    let paused = caller.tasks_lock().await.pause_robot_code();
+    if paused {
+        // the scheduler doesn't come back to this task until it's resumed
+        TaskPool::yield_now().await;
+    }
+}
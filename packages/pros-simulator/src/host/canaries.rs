@@ -0,0 +1,88 @@
+//! Canary regions around the buffers the simulator allocates in robot code memory, like `errno`,
+//! task-local storage and task names. Robot code that writes past one of these buffers
+//! overwrites a canary, which is noticed the next time they're checked. See
+//! [`SimulatorOptions::canaries`](crate::SimulatorOptions::canaries).
+
+use std::{
+    alloc::Layout,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Context;
+use wasmtime::SharedMemory;
+
+use super::memory::SharedMemoryExt;
+
+/// How many bytes of canary are placed on each side of a buffer.
+pub const CANARY_LEN: usize = 16;
+
+const CANARY: [u8; CANARY_LEN] = [0xA5; CANARY_LEN];
+
+#[derive(Debug)]
+struct GuardedBuffer {
+    address: u32,
+    size: u32,
+}
+
+impl GuardedBuffer {
+    fn canary_addresses(&self) -> [u32; 2] {
+        [self.address - CANARY_LEN as u32, self.address + self.size]
+    }
+}
+
+/// The buffers that are guarded by canaries.
+#[derive(Debug, Clone, Default)]
+pub struct Canaries {
+    buffers: Arc<Mutex<Vec<GuardedBuffer>>>,
+}
+
+impl Canaries {
+    /// The layout to allocate so that a buffer with the given layout fits between two canaries,
+    /// and how far into the allocation the buffer starts.
+    pub fn padded_layout(layout: Layout) -> (Layout, usize) {
+        let offset = CANARY_LEN.next_multiple_of(layout.align());
+        let size = offset + layout.size() + CANARY_LEN;
+        let padded = Layout::from_size_align(size, layout.align()).unwrap();
+        (padded, offset)
+    }
+
+    /// Writes the canaries around a buffer and starts checking them. Returns an
+    /// error if the canaries are outside of memory.
+    pub fn guard(&self, memory: &SharedMemory, address: u32, size: usize) -> anyhow::Result<()> {
+        let buffer = GuardedBuffer {
+            address,
+            size: size as u32,
+        };
+        for canary in buffer.canary_addresses() {
+            memory
+                .write_relaxed(canary as usize, &CANARY)
+                .context("robot code's allocator returned an invalid pointer")?;
+        }
+        self.buffers.lock().unwrap().push(buffer);
+        Ok(())
+    }
+
+    /// Describes each buffer whose canaries have been overwritten since the last check. The
+    /// canaries are restored, so each overwrite is only reported once.
+    pub fn check(&self, memory: &SharedMemory) -> Vec<String> {
+        let buffers = self.buffers.lock().unwrap();
+        let mut corrupted = Vec::new();
+        for buffer in buffers.iter() {
+            let mut overwritten = false;
+            for canary in buffer.canary_addresses() {
+                let bytes = memory.read_relaxed(canary as usize, CANARY_LEN).unwrap();
+                if bytes != CANARY {
+                    overwritten = true;
+                    memory.write_relaxed(canary as usize, &CANARY).unwrap();
+                }
+            }
+            if overwritten {
+                corrupted.push(format!(
+                    "the {}-byte buffer the simulator allocated at {:#x}",
+                    buffer.size, buffer.address
+                ));
+            }
+        }
+        corrupted
+    }
+}
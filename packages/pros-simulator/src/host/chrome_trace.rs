@@ -0,0 +1,143 @@
+//! Sections of robot code timed with `sim_profile_begin` and `sim_profile_end`, exported in the
+//! Chrome trace format. See
+//! [`SimulatorOptions::chrome_trace`](crate::SimulatorOptions::chrome_trace).
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write as _,
+    io::{self, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use pros_sys::EINVAL;
+
+use super::TICK_PERIOD_MS;
+
+/// Named spans recorded by robot code on the simulation's clock, one timeline per task.
+///
+/// Spans on the same task nest: ending a span also ends the spans that were begun inside it and
+/// are still open. Spans that are still open when the simulation stops end when it does.
+#[derive(Debug, Clone, Default)]
+pub struct ChromeTrace {
+    inner: Arc<Mutex<Spans>>,
+}
+
+#[derive(Debug, Default)]
+struct Spans {
+    /// The spans each task has begun but not ended, innermost last, by task ID.
+    open: HashMap<u32, Vec<(String, u64)>>,
+    finished: Vec<Span>,
+    /// The name of each task that has begun a span, by task ID.
+    tasks: BTreeMap<u32, String>,
+}
+
+#[derive(Debug)]
+struct Span {
+    name: String,
+    task_id: u32,
+    start: u64,
+    end: u64,
+}
+
+impl ChromeTrace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Begins a span on a task at the given tick.
+    pub fn begin(&self, task_id: u32, task_name: &str, name: String, tick: u64) {
+        let mut spans = self.inner.lock().unwrap();
+        spans.tasks.insert(task_id, task_name.to_string());
+        spans.open.entry(task_id).or_default().push((name, tick));
+    }
+
+    /// Ends the innermost span with the given name on a task at the given tick, along with the
+    /// spans begun inside it. Fails with `EINVAL` if the task doesn't have a span with that
+    /// name open.
+    pub fn end(&self, task_id: u32, name: &str, tick: u64) -> Result<(), i32> {
+        let mut spans = self.inner.lock().unwrap();
+        let Spans { open, finished, .. } = &mut *spans;
+        let open = open.get_mut(&task_id).ok_or(EINVAL)?;
+        let index = open
+            .iter()
+            .rposition(|(open, _)| open == name)
+            .ok_or(EINVAL)?;
+        for (name, start) in open.drain(index..).rev() {
+            finished.push(Span {
+                name,
+                task_id,
+                start,
+                end: tick,
+            });
+        }
+        Ok(())
+    }
+
+    /// Ends every open span at the given tick, then writes the spans in the Chrome trace event
+    /// format that `chrome://tracing`, Perfetto and Speedscope open, with a thread for each task.
+    pub fn write(&self, path: &Path, end_tick: u64) -> io::Result<()> {
+        let mut spans = self.inner.lock().unwrap();
+        let Spans {
+            open,
+            finished,
+            tasks,
+        } = &mut *spans;
+        for (&task_id, open) in open.iter_mut() {
+            for (name, start) in open.drain(..).rev() {
+                finished.push(Span {
+                    name,
+                    task_id,
+                    start,
+                    end: end_tick,
+                });
+            }
+        }
+
+        let mut events = Vec::new();
+        for (task_id, name) in tasks.iter() {
+            events.push(format!(
+                r#"{{"name":"thread_name","ph":"M","pid":1,"tid":{task_id},"args":{{"name":{}}}}}"#,
+                json_string(name)
+            ));
+        }
+        for span in finished.iter() {
+            events.push(format!(
+                r#"{{"name":{},"ph":"X","pid":1,"tid":{},"ts":{},"dur":{}}}"#,
+                json_string(&span.name),
+                span.task_id,
+                micros(span.start),
+                micros(span.end - span.start)
+            ));
+        }
+
+        let mut file = io::BufWriter::new(std::fs::File::create(path)?);
+        writeln!(file, r#"{{"traceEvents":["#)?;
+        writeln!(file, "{}", events.join(",\n"))?;
+        writeln!(file, "]}}")?;
+        file.flush()
+    }
+}
+
+/// The Chrome trace format's timestamps are in microseconds.
+fn micros(ticks: u64) -> u64 {
+    ticks * TICK_PERIOD_MS * 1000
+}
+
+fn json_string(text: &str) -> String {
+    let mut json = String::with_capacity(text.len() + 2);
+    json.push('"');
+    for char in text.chars() {
+        match char {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            char if char.is_control() => {
+                _ = write!(json, "\\u{:04x}", char as u32);
+            }
+            char => json.push(char),
+        }
+    }
+    json.push('"');
+    json
+}
@@ -0,0 +1,113 @@
+//! The simulation's clock, which counts RTOS ticks like FreeRTOS's tick interrupt.
+//!
+//! While a scheduler runs, a [`TickTimer`] advances the clock every [`TICK_PERIOD_MS`] of real
+//! time and increments the engine's epoch, which lets the scheduler preempt robot code at each
+//! tick boundary.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use wasmtime::Engine;
+
+use super::TICK_PERIOD_MS;
+
+/// The length of a tick.
+pub const TICK_PERIOD: Duration = Duration::from_millis(TICK_PERIOD_MS);
+
+/// Counts the ticks since the simulation started.
+#[derive(Debug)]
+pub struct Clock {
+    /// When the simulation started.
+    origin: Instant,
+    ticks: AtomicU64,
+    /// Ticks added by [`advance`](Self::advance) rather than by a [`TickTimer`].
+    skipped: AtomicU64,
+}
+
+impl Clock {
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+            ticks: AtomicU64::new(0),
+            skipped: AtomicU64::new(0),
+        }
+    }
+
+    /// The number of ticks since the simulation started.
+    pub fn ticks(&self) -> u64 {
+        self.ticks.load(Ordering::Acquire)
+    }
+
+    /// The simulated time since the simulation started: the whole ticks, plus how far into the
+    /// current tick the real time is.
+    pub fn elapsed(&self) -> Duration {
+        let ticks = self.ticks();
+        let into_tick = self
+            .tick_start(ticks)
+            .elapsed()
+            .min(TICK_PERIOD - Duration::from_nanos(1));
+        Duration::from_millis(ticks * TICK_PERIOD_MS) + into_tick
+    }
+
+    /// When the given tick is due to start in real time.
+    pub fn tick_start(&self, tick: u64) -> Instant {
+        let skipped = self.skipped.load(Ordering::Acquire);
+        self.origin + Duration::from_millis(tick.saturating_sub(skipped) * TICK_PERIOD_MS)
+    }
+
+    /// Moves the clock forward by the given number of ticks at once. Tasks blocked until one of
+    /// the skipped ticks wake up.
+    pub fn advance(&self, ticks: u64) {
+        self.skipped.fetch_add(ticks, Ordering::AcqRel);
+        self.ticks.fetch_add(ticks, Ordering::AcqRel);
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Advances a [`Clock`] and the engine's epoch every tick until dropped.
+///
+/// Ticks follow the real time since the clock's origin, so a tick that comes late (for example,
+/// because the timer was started late) is caught up on straight away.
+pub struct TickTimer {
+    stop: Arc<AtomicBool>,
+}
+
+impl TickTimer {
+    pub fn start(clock: Arc<Clock>, engine: Engine) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        thread::Builder::new()
+            .name("pros-simulator tick".into())
+            .spawn({
+                let stop = stop.clone();
+                move || {
+                    while !stop.load(Ordering::Acquire) {
+                        let next = clock.tick_start(clock.ticks() + 1);
+                        if let Some(wait) = next.checked_duration_since(Instant::now()) {
+                            thread::sleep(wait);
+                        }
+                        clock.ticks.fetch_add(1, Ordering::AcqRel);
+                        engine.increment_epoch();
+                    }
+                }
+            })
+            .expect("failed to spawn the tick thread");
+        Self { stop }
+    }
+}
+
+impl Drop for TickTimer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+    }
+}
@@ -0,0 +1,64 @@
+//! Checking robot code's imports against the PROS API. See
+//! [`SimulatorEvent::ApiCompatibility`](pros_simulator_interface::SimulatorEvent::ApiCompatibility).
+//!
+//! The functions in each version of the PROS API are listed in the `manifests` directory, one
+//! per line.
+
+use std::collections::BTreeSet;
+
+use pros_simulator_interface::{ApiCompatibility, ProsVersion};
+use wasmtime::{ExternType, Module};
+
+/// Host functions that robot code can call but that don't simulate anything, because the
+/// simulator doesn't model the hardware behind them.
+pub const STUBBED_APIS: &[&str] = &[
+    "controller_get_battery_capacity",
+    "controller_get_battery_level",
+];
+
+/// The functions in the given version of the PROS API.
+pub fn manifest(version: ProsVersion) -> BTreeSet<&'static str> {
+    let manifest = match version {
+        ProsVersion::V3_8 => include_str!("../../manifests/pros-3.8.txt"),
+    };
+    manifest
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect()
+}
+
+/// Sorts the functions the robot code imports from `env` by how well the simulator supports
+/// them. `is_implemented` reports whether the simulator provides a function.
+pub fn api_compatibility(
+    module: &Module,
+    target: ProsVersion,
+    mut is_implemented: impl FnMut(&str) -> bool,
+) -> ApiCompatibility {
+    let manifest = manifest(target);
+    let mut compatibility = ApiCompatibility {
+        target,
+        ..Default::default()
+    };
+
+    let imports = module
+        .imports()
+        .filter(|import| import.module() == "env")
+        .filter(|import| matches!(import.ty(), ExternType::Func(_)))
+        .map(|import| import.name())
+        .collect::<BTreeSet<_>>();
+    for name in imports {
+        let list = if !manifest.contains(name) {
+            &mut compatibility.not_in_target
+        } else if !is_implemented(name) {
+            &mut compatibility.missing
+        } else if STUBBED_APIS.contains(&name) {
+            &mut compatibility.stubbed
+        } else {
+            &mut compatibility.implemented
+        };
+        list.push(name.to_string());
+    }
+
+    compatibility
+}
@@ -0,0 +1,161 @@
+//! Running robot code built as a WebAssembly component targeting the `robot` world in
+//! `wit/pros.wit`.
+//!
+//! Every task instantiates the robot code again with the same shared memory, but the component
+//! model has no way of sharing memory between instances. Instead of instantiating the component,
+//! the simulator takes out the core module it wraps and runs that like robot code built as a
+//! module: its imports of the world's interfaces are renamed to the `env` functions they
+//! describe, and its own memory is replaced with the shared one.
+//!
+//! Active data segments are copied into the shared memory once, when the robot code is loaded,
+//! since instantiating them with every task would reset the robot code's statics. Globals aren't
+//! shared, so like in core modules each task starts with their initial values.
+
+use anyhow::{bail, Context};
+use walrus::{ActiveDataLocation, DataKind, ExportItem, ModuleConfig};
+use wasmparser::{Parser, Payload};
+
+/// The package the `robot` world's interfaces are in.
+const WIT_PACKAGE: &str = "pros:simulator/";
+
+/// The most pages the shared memory can grow to.
+pub const MAX_PAGES: u32 = 16384;
+
+/// The core module wrapped by a component, prepared to run like robot code built as a module.
+#[derive(Debug)]
+pub struct LoweredComponent {
+    pub wasm: Vec<u8>,
+    /// How many pages the module's memory starts with.
+    pub initial_pages: u32,
+    /// The module's active data segments, as `(address, bytes)` pairs, which have to be copied
+    /// into memory before it's instantiated.
+    pub data: Vec<(u32, Vec<u8>)>,
+}
+
+/// Takes the core module out of a component built for the `robot` world.
+///
+/// Fails if the component wraps more than one core module (e.g. adapters or the shims needed for
+/// non-scalar function parameters), which would have to be instantiated with the component model.
+pub fn lower_component(component: &[u8]) -> anyhow::Result<LoweredComponent> {
+    let modules = core_modules(component)?;
+    let [module] = modules.as_slice() else {
+        bail!(
+            "Robot code is a WebAssembly component made of {} core modules, but the simulator \
+             can only run components that wrap a single module importing the interfaces in \
+             `wit/pros.wit`.",
+            modules.len()
+        );
+    };
+
+    let mut module = ModuleConfig::new()
+        .generate_dwarf(true)
+        .parse(module)
+        .context("Failed to parse the core module of the robot code's component")?;
+
+    for import in module.imports.iter_mut() {
+        if import.module.starts_with(WIT_PACKAGE) {
+            import.module = "env".into();
+            import.name = core_name(&import.name);
+        }
+    }
+
+    let memory = match module.memories.iter().collect::<Vec<_>>().as_slice() {
+        [memory] if memory.import.is_none() => memory.id(),
+        _ => bail!(
+            "Robot code is a WebAssembly component whose core module doesn't define exactly one \
+             memory, so the simulator can't give it shared memory."
+        ),
+    };
+    let import = module.imports.add("env", "memory", memory);
+    let memory = module.memories.get_mut(memory);
+    memory.import = Some(import);
+    memory.shared = true;
+    memory.maximum = Some(MAX_PAGES);
+    memory.data_segments.clear();
+    let initial_pages = memory.initial;
+
+    let mut data = Vec::new();
+    let active = module
+        .data
+        .iter()
+        .filter(|segment| matches!(segment.kind, DataKind::Active(_)))
+        .map(|segment| segment.id())
+        .collect::<Vec<_>>();
+    for id in active {
+        let segment = module.data.get_mut(id);
+        let DataKind::Active(active) = &segment.kind else {
+            unreachable!()
+        };
+        let ActiveDataLocation::Absolute(address) = active.location else {
+            bail!("Robot code has a data segment at a relocatable address, which isn't supported");
+        };
+        data.push((address, std::mem::take(&mut segment.value)));
+        module.data.delete(id);
+    }
+
+    // entrypoints are exported with their WIT names
+    for export in module.exports.iter_mut() {
+        if matches!(export.item, ExportItem::Function(_)) {
+            export.name = export.name.replace('-', "_");
+        }
+    }
+    if module
+        .exports
+        .iter()
+        .all(|export| export.name != "__indirect_function_table")
+    {
+        if let Some(table) = module.tables.main_function_table()? {
+            module.exports.add("__indirect_function_table", table);
+        }
+    }
+
+    Ok(LoweredComponent {
+        wasm: module.emit_wasm(),
+        initial_pages,
+        data,
+    })
+}
+
+/// The core modules defined at the top level of a component.
+fn core_modules(component: &[u8]) -> anyhow::Result<Vec<&[u8]>> {
+    let mut modules = Vec::new();
+    // nested modules and components are parsed inline, between their header and end
+    let mut depth = 0;
+    for payload in Parser::new(0).parse_all(component) {
+        match payload? {
+            Payload::Version { .. } => depth += 1,
+            Payload::End(_) => depth -= 1,
+            Payload::ModuleSection { range, .. } if depth == 1 => {
+                modules.push(&component[range]);
+            }
+            Payload::ComponentSection { .. } if depth == 1 => {
+                bail!(
+                    "Robot code is a WebAssembly component with nested components, which isn't \
+                     supported"
+                );
+            }
+            _ => {}
+        }
+    }
+    Ok(modules)
+}
+
+/// The name a function of the `robot` world is imported as by robot code built as a module.
+fn core_name(name: &str) -> String {
+    match name {
+        "errno" => "__errno".into(),
+        "sys-exit" => "_exit".into(),
+        "pv-task-get-thread-local-storage-pointer" => "pvTaskGetThreadLocalStoragePointer".into(),
+        "v-task-set-thread-local-storage-pointer" => "vTaskSetThreadLocalStoragePointer".into(),
+        // VEX SDK functions are camel case
+        _ if name.starts_with("vex-") => name
+            .split('-')
+            .enumerate()
+            .map(|(i, word)| match i {
+                0 => word.to_string(),
+                _ => word[..1].to_uppercase() + &word[1..],
+            })
+            .collect(),
+        _ => name.replace('-', "_"),
+    }
+}
@@ -1,14 +1,27 @@
-use std::mem;
+use std::{collections::VecDeque, mem, time::Instant};
 
-use pros_simulator_interface::{ControllerState, DigitalControllerState};
+use pros_simulator_interface::{
+    truncate_to_width, CompetitionPhase, CompetitionSwitch, ControllerId, ControllerState,
+    DigitalControllerState, InputShaping, CONTROLLER_SCREEN_HEIGHT, CONTROLLER_SCREEN_WIDTH,
+};
 use pros_sys::{
-    misc::E_CONTROLLER_DIGITAL_R1, EINVAL, E_CONTROLLER_ANALOG_LEFT_X, E_CONTROLLER_ANALOG_LEFT_Y,
-    E_CONTROLLER_ANALOG_RIGHT_X, E_CONTROLLER_ANALOG_RIGHT_Y, E_CONTROLLER_DIGITAL_A,
-    E_CONTROLLER_DIGITAL_B, E_CONTROLLER_DIGITAL_DOWN, E_CONTROLLER_DIGITAL_L1,
-    E_CONTROLLER_DIGITAL_L2, E_CONTROLLER_DIGITAL_LEFT, E_CONTROLLER_DIGITAL_R2,
-    E_CONTROLLER_DIGITAL_RIGHT, E_CONTROLLER_DIGITAL_UP, E_CONTROLLER_DIGITAL_X,
-    E_CONTROLLER_DIGITAL_Y, E_CONTROLLER_MASTER, E_CONTROLLER_PARTNER,
+    misc::E_CONTROLLER_DIGITAL_R1, EAGAIN, EINVAL, E_CONTROLLER_ANALOG_LEFT_X,
+    E_CONTROLLER_ANALOG_LEFT_Y, E_CONTROLLER_ANALOG_RIGHT_X, E_CONTROLLER_ANALOG_RIGHT_Y,
+    E_CONTROLLER_DIGITAL_A, E_CONTROLLER_DIGITAL_B, E_CONTROLLER_DIGITAL_DOWN,
+    E_CONTROLLER_DIGITAL_L1, E_CONTROLLER_DIGITAL_L2, E_CONTROLLER_DIGITAL_LEFT,
+    E_CONTROLLER_DIGITAL_R2, E_CONTROLLER_DIGITAL_RIGHT, E_CONTROLLER_DIGITAL_UP,
+    E_CONTROLLER_DIGITAL_X, E_CONTROLLER_DIGITAL_Y, E_CONTROLLER_MASTER, E_CONTROLLER_PARTNER,
 };
+
+use super::TICK_PERIOD_MS;
+
+/// How long a controller takes to accept another write to its screen or rumble motor, because
+/// they share the controller's radio link with everything else.
+const SCREEN_WRITE_PERIOD_MS: u64 = 50;
+
+/// The longest rumble pattern a controller plays.
+const MAX_RUMBLE_LEN: usize = 8;
+
 struct Controller {
     state: ControllerState,
     new_presses: DigitalControllerState,
@@ -44,10 +57,34 @@ impl Controller {
     }
 }
 
+/// What's on a controller's screen, which is kept while it's disconnected.
+#[derive(Default)]
+struct ControllerScreen {
+    lines: [String; CONTROLLER_SCREEN_HEIGHT as usize],
+    /// The tick of the last write that reached the controller.
+    last_write: Option<u64>,
+}
+
 /// Stores state of VEX V5 master and partner controllers.
 pub struct Controllers {
     master: Option<Controller>,
     partner: Option<Controller>,
+    master_screen: ControllerScreen,
+    partner_screen: ControllerScreen,
+    /// Updates that haven't reached the brain yet, in the order they were sent.
+    pending: VecDeque<PendingUpdate>,
+    /// Shaping applied to each controller's joysticks, which is kept while it's disconnected.
+    master_shaping: InputShaping,
+    partner_shaping: InputShaping,
+    /// The master controller's competition switch as of the last
+    /// [`take_switch_phase`](Self::take_switch_phase).
+    competition_switch: Option<CompetitionSwitch>,
+}
+
+struct PendingUpdate {
+    arrives_at: Instant,
+    master: Option<ControllerState>,
+    partner: Option<ControllerState>,
 }
 
 impl Controllers {
@@ -55,29 +92,110 @@ impl Controllers {
         Self {
             master: master.map(|v| v.into()),
             partner: partner.map(|v| v.into()),
+            master_screen: ControllerScreen::default(),
+            partner_screen: ControllerScreen::default(),
+            pending: VecDeque::new(),
+            master_shaping: InputShaping::default(),
+            partner_shaping: InputShaping::default(),
+            competition_switch: None,
+        }
+    }
+
+    /// Shapes a controller's joysticks before robot code reads them from now on.
+    pub fn set_shaping(&mut self, controller: ControllerId, shaping: InputShaping) {
+        match controller {
+            ControllerId::Master => self.master_shaping = shaping,
+            ControllerId::Partner => self.partner_shaping = shaping,
+        }
+    }
+
+    /// Queues an update to be applied by [`apply_arrived`](Self::apply_arrived) once the given
+    /// time has passed, simulating the delay of the controller's radio link.
+    pub fn send(
+        &mut self,
+        master: Option<ControllerState>,
+        partner: Option<ControllerState>,
+        arrives_at: Instant,
+    ) {
+        self.pending.push_back(PendingUpdate {
+            arrives_at,
+            master,
+            partner,
+        });
+    }
+
+    /// Applies every queued update that has arrived. Returns the controllers that were
+    /// disconnected by them.
+    pub fn apply_arrived(&mut self) -> Vec<ControllerId> {
+        let now = Instant::now();
+        let mut disconnected = Vec::new();
+        while self
+            .pending
+            .front()
+            .is_some_and(|update| update.arrives_at <= now)
+        {
+            let update = self.pending.pop_front().unwrap();
+            disconnected.extend(self.update(update.master, update.partner));
         }
+        disconnected
     }
 
-    /// Update state of both controllers and set new press values.
+    /// Update state of both controllers and set new press values. `None` means the controller is
+    /// disconnected, which clears its state.
+    ///
+    /// Returns the controllers that were connected before this update but aren't anymore.
     pub fn update(
         &mut self,
         new_master: Option<ControllerState>,
         new_partner: Option<ControllerState>,
-    ) {
-        if let Some(new_master) = new_master {
-            if let Some(master) = &mut self.master {
-                master.update(new_master);
-            } else {
-                self.master = Some(new_master.into());
+    ) -> Vec<ControllerId> {
+        let mut disconnected = Vec::new();
+        for (id, controller, new_state) in [
+            (ControllerId::Master, &mut self.master, new_master),
+            (ControllerId::Partner, &mut self.partner, new_partner),
+        ] {
+            match (controller.as_mut(), new_state) {
+                (Some(controller), Some(new_state)) => controller.update(new_state),
+                (None, Some(new_state)) => *controller = Some(new_state.into()),
+                (Some(_), None) => {
+                    *controller = None;
+                    disconnected.push(id);
+                }
+                (None, None) => {}
             }
         }
-        if let Some(new_partner) = new_partner {
-            if let Some(partner) = &mut self.partner {
-                partner.update(new_partner);
-            } else {
-                self.partner = Some(new_partner.into());
-            }
+        disconnected
+    }
+
+    /// Returns the competition phase the master controller's competition switch has changed to
+    /// since this was last called, if it has. Unplugging the switch leaves the robot enabled in
+    /// driver control without a competition connection, like a V5 brain with nothing in the
+    /// competition port. While the master controller is disconnected, the phase is left alone.
+    pub fn take_switch_phase(&mut self) -> Option<CompetitionPhase> {
+        let switch = self.master.as_ref()?.state.competition_switch;
+        if switch == self.competition_switch {
+            return None;
         }
+        self.competition_switch = switch;
+        Some(switch.map_or(
+            CompetitionPhase {
+                autonomous: false,
+                enabled: true,
+                is_competition: false,
+            },
+            |switch| switch.phase(),
+        ))
+    }
+
+    /// The current state of a controller, or `None` if it's disconnected.
+    pub fn state(&self, controller: ControllerId) -> Option<ControllerState> {
+        let controller = match controller {
+            ControllerId::Master => &self.master,
+            ControllerId::Partner => &self.partner,
+        };
+        controller
+            .as_ref()
+            .map(|controller| controller.state.clone())
     }
 
     pub fn is_connected(&self, controller_id: u32) -> Result<bool, i32> {
@@ -113,7 +231,7 @@ impl Controllers {
     ///
     /// This function retrieves the current state of a given analog channel (joystick) on a specific controller.
     /// The state is represented as a integer value in the range [-127, 127] where -127 is full down or left,
-    /// and 127 is full up or right.
+    /// and 127 is full up or right. The controller's [`InputShaping`] is applied to it.
     ///
     /// # Arguments
     ///
@@ -127,13 +245,17 @@ impl Controllers {
     ///
     /// # Example
     ///
-    /// ```
+    /// ```ignore
     /// if controllers.get_analog(pros_sys::E_CONTROLLER_MASTER, pros_sys::E_CONTROLLER_ANALOG_LEFT_X)? > 0 {
     ///     println!("Left joystick is pushed right")
     /// }
     /// ```
     pub fn get_analog(&self, controller_id: u32, channel: u32) -> Result<i32, i32> {
         let controller = self.get_controller_state(controller_id)?;
+        let shaping = match controller_id {
+            E_CONTROLLER_PARTNER => self.partner_shaping,
+            _ => self.master_shaping,
+        };
         if let Some(Controller { state, .. }) = controller {
             match channel {
                 E_CONTROLLER_ANALOG_LEFT_X => Ok(state.analog.left_x),
@@ -145,7 +267,7 @@ impl Controllers {
         } else {
             Ok(0)
         }
-        .map(|v| v as i32)
+        .map(|v| shaping.apply(v) as i32)
     }
 
     /// Returns the current state of a specific button on a specific controller.
@@ -166,7 +288,7 @@ impl Controllers {
     ///
     /// # Example
     ///
-    /// ```
+    /// ```ignore
     /// if controllers.get_digital(pros_sys::E_CONTROLLER_MASTER, pros_sys::E_CONTROLLER_DIGITAL_X)? {
     ///     println!("Button X pressed")
     /// }
@@ -245,4 +367,89 @@ impl Controllers {
             Ok(false)
         }
     }
+
+    /// Writes text to a controller's screen, starting at the given line and column. Like on a
+    /// real controller, the text replaces what was there and is cut off at the edge of the
+    /// screen. Returns the lines on the screen afterwards.
+    ///
+    /// Fails with `EINVAL` if the controller ID, line or column is invalid, and with `EAGAIN` if
+    /// the write can't be sent (see [`start_write`](Self::start_write)).
+    pub fn set_text(
+        &mut self,
+        controller_id: u32,
+        line: u32,
+        col: u32,
+        text: &str,
+        tick: u64,
+    ) -> Result<Vec<String>, i32> {
+        if line >= CONTROLLER_SCREEN_HEIGHT || col >= CONTROLLER_SCREEN_WIDTH {
+            return Err(EINVAL);
+        }
+        let screen = self.start_write(controller_id, tick)?;
+        let mut chars = screen.lines[line as usize].chars().collect::<Vec<_>>();
+        let col = col as usize;
+        let text = truncate_to_width(text, CONTROLLER_SCREEN_WIDTH as usize - col)
+            .chars()
+            .collect::<Vec<_>>();
+        if chars.len() < col + text.len() {
+            chars.resize(col + text.len(), ' ');
+        }
+        chars[col..col + text.len()].copy_from_slice(&text);
+        screen.lines[line as usize] = chars.into_iter().collect();
+        Ok(screen.lines.to_vec())
+    }
+
+    /// Clears a line of a controller's screen. Returns the lines on the screen afterwards.
+    ///
+    /// Fails like [`set_text`](Self::set_text).
+    pub fn clear_line(
+        &mut self,
+        controller_id: u32,
+        line: u32,
+        tick: u64,
+    ) -> Result<Vec<String>, i32> {
+        if line >= CONTROLLER_SCREEN_HEIGHT {
+            return Err(EINVAL);
+        }
+        let screen = self.start_write(controller_id, tick)?;
+        screen.lines[line as usize].clear();
+        Ok(screen.lines.to_vec())
+    }
+
+    /// Clears a controller's whole screen in one write. Returns the lines on the screen
+    /// afterwards.
+    ///
+    /// Fails like [`set_text`](Self::set_text).
+    pub fn clear(&mut self, controller_id: u32, tick: u64) -> Result<Vec<String>, i32> {
+        let screen = self.start_write(controller_id, tick)?;
+        screen.lines = Default::default();
+        Ok(screen.lines.to_vec())
+    }
+
+    /// Rumbles a controller. Returns the pattern it plays, which is cut off after 8 characters.
+    ///
+    /// Fails like [`set_text`](Self::set_text).
+    pub fn rumble(&mut self, controller_id: u32, pattern: &str, tick: u64) -> Result<String, i32> {
+        self.start_write(controller_id, tick)?;
+        Ok(pattern.chars().take(MAX_RUMBLE_LEN).collect())
+    }
+
+    /// Claims the radio link to a controller for a write at the given tick. Fails with `EAGAIN`,
+    /// like PROS, if the controller is disconnected or the last write was less than 50 ms ago,
+    /// in which case the write is dropped.
+    fn start_write(&mut self, controller_id: u32, tick: u64) -> Result<&mut ControllerScreen, i32> {
+        let (connected, screen) = match controller_id {
+            E_CONTROLLER_MASTER => (self.master.is_some(), &mut self.master_screen),
+            E_CONTROLLER_PARTNER => (self.partner.is_some(), &mut self.partner_screen),
+            _ => return Err(EINVAL),
+        };
+        let busy = screen
+            .last_write
+            .is_some_and(|last| (tick - last) * TICK_PERIOD_MS < SCREEN_WRITE_PERIOD_MS);
+        if !connected || busy {
+            return Err(EAGAIN);
+        }
+        screen.last_write = Some(tick);
+        Ok(screen)
+    }
 }
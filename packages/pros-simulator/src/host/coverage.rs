@@ -0,0 +1,87 @@
+//! Tracking which APIs the robot code uses. See
+//! [`SimulatorEvent::ApiCoverage`](pros_simulator_interface::SimulatorEvent::ApiCoverage).
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write as _,
+    sync::{Arc, Mutex},
+};
+
+use pros_simulator_interface::ApiCoverage;
+use wasmtime::{ExternType, Module};
+
+use super::atomics::ATOMICS_MODULE;
+
+/// Counts calls to each host function.
+#[derive(Debug, Clone, Default)]
+pub struct ApiUsage {
+    calls: Arc<Mutex<HashMap<String, u64>>>,
+    unimplemented: Arc<Mutex<Vec<String>>>,
+}
+
+impl ApiUsage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a call to the host function with the given name.
+    pub fn record(&self, api: &str) {
+        let mut calls = self.calls.lock().unwrap();
+        match calls.get_mut(api) {
+            Some(count) => *count += 1,
+            None => {
+                calls.insert(api.to_string(), 1);
+            }
+        }
+    }
+
+    /// Records an import the simulator doesn't implement.
+    pub fn record_unimplemented(&self, api: &str) {
+        self.unimplemented.lock().unwrap().push(api.to_string());
+    }
+
+    /// Summarizes how many times each function imported by the robot code was called. The
+    /// simulator's own instrumentation of atomic instructions isn't included.
+    pub fn coverage(&self, module: &Module) -> ApiCoverage {
+        let calls = self.calls.lock().unwrap();
+        let calls = module
+            .imports()
+            .filter(|import| import.module() != ATOMICS_MODULE)
+            .filter(|import| matches!(import.ty(), ExternType::Func(_)))
+            .map(|import| {
+                let count = calls.get(import.name()).copied().unwrap_or(0);
+                (import.name().to_string(), count)
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        let mut unimplemented = self.unimplemented.lock().unwrap().clone();
+        unimplemented.sort();
+        unimplemented.dedup();
+        ApiCoverage {
+            calls,
+            unimplemented,
+        }
+    }
+}
+
+/// Formats a coverage summary as a plain text report, with the APIs that were never called
+/// listed first.
+pub fn coverage_report(coverage: &ApiCoverage) -> String {
+    let used = coverage.calls.values().filter(|count| **count > 0).count();
+    let mut report = format!(
+        "Robot code called {used} of the {} APIs it imports.\n\n",
+        coverage.calls.len()
+    );
+
+    let mut calls = coverage.calls.iter().collect::<Vec<_>>();
+    calls.sort_by_key(|(name, count)| (**count > 0, *name));
+    for (name, count) in calls {
+        let note = if coverage.unimplemented.contains(name) {
+            " (not implemented)"
+        } else {
+            ""
+        };
+        _ = writeln!(report, "{count:>10}  {name}{note}");
+    }
+    report
+}
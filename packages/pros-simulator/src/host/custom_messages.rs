@@ -0,0 +1,28 @@
+//! Messages sent to robot code by
+//! [`SimulatorMessage::Custom`](pros_simulator_interface::SimulatorMessage::Custom), waiting for
+//! it to poll them with `sim_poll_message`.
+
+use std::collections::{HashMap, VecDeque};
+
+/// The payloads sent with each tag that robot code hasn't polled yet, oldest first.
+#[derive(Debug, Default)]
+pub struct CustomMessages {
+    pending: HashMap<String, VecDeque<Vec<u8>>>,
+}
+
+impl CustomMessages {
+    /// Queues a payload for robot code to poll with `tag`, after any already queued for it.
+    pub fn push(&mut self, tag: String, bytes: Vec<u8>) {
+        self.pending.entry(tag).or_default().push_back(bytes);
+    }
+
+    /// Takes the oldest payload sent with `tag`, if any.
+    pub fn take(&mut self, tag: &str) -> Option<Vec<u8>> {
+        let queue = self.pending.get_mut(tag)?;
+        let bytes = queue.pop_front();
+        if queue.is_empty() {
+            self.pending.remove(tag);
+        }
+        bytes
+    }
+}
@@ -0,0 +1,175 @@
+//! Appends every event the simulation sends to a compressed log on disk as it runs, so the
+//! history is there after a crash or a disconnected frontend. Only built with the `event-log`
+//! feature. See [`SimulatorOptions::event_log`](crate::SimulatorOptions::event_log).
+//!
+//! The log is a gzip file made of many gzip members, one after the other, which `zcat` and
+//! most gzip readers treat as a single file. Events are compressed and written on a thread of
+//! their own, and each batch is written as a complete member, so everything up to the last
+//! batch can be read even if the simulator never gets to close the file. Once decompressed,
+//! each line is a JSON object with the event and when it was sent:
+//!
+//! ```json
+//! {"millis":1520,"time":1700000000123,"event":"RobotCodeFinished"}
+//! ```
+//!
+//! `millis` is the simulated time since robot code started, and `time` is the wall clock time
+//! in milliseconds since the Unix epoch.
+
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use pros_simulator_interface::SimulatorEvent;
+
+use super::clock::Clock;
+
+/// The longest an event waits before it's written to disk.
+const FLUSH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How many bytes of events are batched up before they're written, even if the flush interval
+/// hasn't passed.
+const MAX_BATCH: usize = 64 * 1024;
+
+/// How hard deflate tries to shrink each batch, from 0 to 10.
+const COMPRESSION_LEVEL: u8 = 6;
+
+/// A gzip member header with no file name or modification time, deflate compression and an
+/// unknown OS.
+const GZIP_HEADER: [u8; 10] = [0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+
+/// Writes events to a log file as they're sent.
+#[derive(Clone)]
+pub struct EventLog {
+    inner: Arc<Mutex<Log>>,
+}
+
+struct Log {
+    path: PathBuf,
+    /// The simulation's clock, once the host has been created.
+    clock: Option<Arc<Clock>>,
+    /// `None` once the log has finished.
+    lines: Option<Sender<String>>,
+    writer: Option<JoinHandle<io::Result<()>>>,
+}
+
+impl EventLog {
+    /// Creates the log file, replacing it if it exists.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let file = File::create(path)?;
+        let (lines, rx) = mpsc::channel();
+        let writer = thread::Builder::new()
+            .name("pros-simulator event log".into())
+            .spawn(move || write_batches(rx, file))?;
+        Ok(Self {
+            inner: Arc::new(Mutex::new(Log {
+                path: path.to_path_buf(),
+                clock: None,
+                lines: Some(lines),
+                writer: Some(writer),
+            })),
+        })
+    }
+
+    /// Timestamps events with the simulated time on `clock` from now on. Events sent before this
+    /// are logged at 0 milliseconds.
+    pub fn follow_clock(&self, clock: Arc<Clock>) {
+        self.inner.lock().unwrap().clock = Some(clock);
+    }
+
+    /// Queues an event to be written.
+    pub fn record(&self, event: &SimulatorEvent) {
+        let inner = self.inner.lock().unwrap();
+        let Some(lines) = &inner.lines else {
+            return;
+        };
+        let millis = inner
+            .clock
+            .as_ref()
+            .map_or(0, |clock| clock.elapsed().as_millis());
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let event = serde_json::to_string(event).expect("events can be serialized");
+        // the writer only stops early if writing failed, which `finish` reports
+        _ = lines.send(format!(
+            r#"{{"millis":{millis},"time":{time},"event":{event}}}"#
+        ));
+    }
+
+    /// Waits for every event to be written and closes the log. Events sent after this aren't
+    /// logged. Returns why they couldn't all be written, if they couldn't.
+    pub fn finish(&self) -> Result<(), String> {
+        self.inner.lock().unwrap().finish()
+    }
+}
+
+impl Log {
+    fn finish(&mut self) -> Result<(), String> {
+        self.lines = None;
+        let Some(writer) = self.writer.take() else {
+            return Ok(());
+        };
+        let path = self.path.display();
+        match writer.join() {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(err)) => Err(format!("Failed to write the event log to {path}: {err}")),
+            Err(_) => Err(format!("Failed to write the event log to {path}")),
+        }
+    }
+}
+
+impl Drop for Log {
+    /// Makes sure the last batch is written if the simulation is dropped without finishing, e.g.
+    /// because the robot code couldn't be loaded.
+    fn drop(&mut self) {
+        _ = self.finish();
+    }
+}
+
+fn write_batches(lines: Receiver<String>, mut file: File) -> io::Result<()> {
+    let mut batch = String::new();
+    let mut last_write = Instant::now();
+    loop {
+        let timeout = FLUSH_INTERVAL.saturating_sub(last_write.elapsed());
+        let done = match lines.recv_timeout(timeout) {
+            Ok(line) => {
+                batch.push_str(&line);
+                batch.push('\n');
+                false
+            }
+            Err(RecvTimeoutError::Timeout) => false,
+            Err(RecvTimeoutError::Disconnected) => true,
+        };
+        if done || batch.len() >= MAX_BATCH || last_write.elapsed() >= FLUSH_INTERVAL {
+            if !batch.is_empty() {
+                file.write_all(&gzip_member(batch.as_bytes()))?;
+                batch.clear();
+            }
+            last_write = Instant::now();
+        }
+        if done {
+            return file.sync_all();
+        }
+    }
+}
+
+/// Compresses `data` as a complete gzip member.
+fn gzip_member(data: &[u8]) -> Vec<u8> {
+    let mut member = GZIP_HEADER.to_vec();
+    member.extend(miniz_oxide::deflate::compress_to_vec(
+        data,
+        COMPRESSION_LEVEL,
+    ));
+    member.extend(crc32fast::hash(data).to_le_bytes());
+    member.extend((data.len() as u32).to_le_bytes());
+    member
+}
@@ -0,0 +1,28 @@
+//! Failures injected into host functions by
+//! [`SimulatorMessage::FailNextCall`](pros_simulator_interface::SimulatorMessage::FailNextCall).
+
+use std::collections::{HashMap, VecDeque};
+
+/// The `errno` values that the next calls to each API should fail with, in the order they were
+/// requested.
+#[derive(Debug, Default)]
+pub struct InjectedFailures {
+    pending: HashMap<String, VecDeque<i32>>,
+}
+
+impl InjectedFailures {
+    /// Makes a future call to `api` fail with `errno`, after any failures already queued for it.
+    pub fn push(&mut self, api: String, errno: i32) {
+        self.pending.entry(api).or_default().push_back(errno);
+    }
+
+    /// Takes the `errno` the current call to `api` should fail with, if any.
+    pub fn take(&mut self, api: &str) -> Option<i32> {
+        let queue = self.pending.get_mut(api)?;
+        let errno = queue.pop_front();
+        if queue.is_empty() {
+            self.pending.remove(api);
+        }
+        errno
+    }
+}
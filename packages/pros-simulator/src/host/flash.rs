@@ -0,0 +1,127 @@
+//! A small key-value store standing in for the brain's flash, which keeps what robot code saves
+//! between runs. See [`SimulatorOptions::flash`](crate::SimulatorOptions::flash).
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context};
+
+/// How many bytes of keys and values can be stored.
+pub const FLASH_CAPACITY: usize = 32 * 1024;
+
+/// Saved values by key, and the file they're kept in between runs.
+///
+/// The file has one `key=value` line per entry, with the value written in hex since robot code
+/// can store any bytes. Without a file, values are only kept until the simulation stops.
+#[derive(Debug, Default)]
+pub struct Flash {
+    entries: BTreeMap<String, Vec<u8>>,
+    path: Option<PathBuf>,
+}
+
+/// Returned when a value doesn't fit in [`FLASH_CAPACITY`].
+#[derive(Debug)]
+pub struct FlashFull;
+
+impl Flash {
+    /// Loads the values saved in the given file. A file that doesn't exist yet is created the
+    /// first time a value is saved.
+    pub fn load(path: Option<PathBuf>) -> anyhow::Result<Self> {
+        let Some(path) = path else {
+            return Ok(Self::default());
+        };
+        let entries = match std::fs::read_to_string(&path) {
+            Ok(text) => parse(&text)
+                .with_context(|| format!("Couldn't read the flash file {}", path.display()))?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("Couldn't read the flash file {}", path.display()))
+            }
+        };
+        Ok(Self {
+            entries,
+            path: Some(path),
+        })
+    }
+
+    /// Whether robot code can use `key`: it can't be empty, or contain `=` or line breaks.
+    pub fn is_valid_key(key: &str) -> bool {
+        !key.is_empty() && !key.contains(['=', '\n', '\r'])
+    }
+
+    pub fn get(&self, key: &str) -> Option<&[u8]> {
+        self.entries.get(key).map(Vec::as_slice)
+    }
+
+    /// Stores a value, replacing any value already stored under `key`.
+    pub fn set(&mut self, key: &str, value: Vec<u8>) -> Result<(), FlashFull> {
+        let replaced = self.entries.get(key).map_or(0, |old| key.len() + old.len());
+        if self.used() - replaced + key.len() + value.len() > FLASH_CAPACITY {
+            return Err(FlashFull);
+        }
+        self.entries.insert(key.to_string(), value);
+        Ok(())
+    }
+
+    /// Removes a value, returning whether there was one.
+    pub fn remove(&mut self, key: &str) -> bool {
+        self.entries.remove(key).is_some()
+    }
+
+    /// How many bytes of keys and values are stored.
+    pub fn used(&self) -> usize {
+        self.entries
+            .iter()
+            .map(|(key, value)| key.len() + value.len())
+            .sum()
+    }
+
+    /// The file values are kept in, if any.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Writes every value to the file, if there is one.
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut text = String::new();
+        for (key, value) in &self.entries {
+            text.push_str(key);
+            text.push('=');
+            for byte in value {
+                write!(text, "{byte:02x}").unwrap();
+            }
+            text.push('\n');
+        }
+        std::fs::write(path, text)
+    }
+}
+
+fn parse(text: &str) -> anyhow::Result<BTreeMap<String, Vec<u8>>> {
+    let mut entries = BTreeMap::new();
+    for (number, line) in text.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, hex)) = line.split_once('=') else {
+            bail!("line {} isn't a `key=value` pair", number + 1);
+        };
+        let value = (0..hex.len())
+            .step_by(2)
+            .map(|i| {
+                hex.get(i..i + 2)
+                    .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+            })
+            .collect::<Option<Vec<_>>>()
+            .with_context(|| format!("the value on line {} isn't hex", number + 1))?;
+        entries.insert(key.to_string(), value);
+    }
+    Ok(entries)
+}
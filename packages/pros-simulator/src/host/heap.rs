@@ -0,0 +1,103 @@
+//! A heap managed by the simulator, for robot code that doesn't bring its own allocator.
+
+use std::{
+    alloc::Layout,
+    sync::{Arc, Mutex},
+};
+
+use wasmtime::SharedMemory;
+
+const PAGE_SIZE: u64 = 65536;
+
+/// A program break shared by `sbrk` and the simulator's own buffers (like `errno` and task
+/// names) in robot code that doesn't export `wasm_memalign` and `wasm_free`.
+///
+/// The break starts at the robot code's `__heap_base` if it calls `sbrk`, or at the end of
+/// memory otherwise, and memory grows as it moves forward. If the robot code grew memory itself
+/// in the meantime, the break skips past those pages, so newlib's `malloc` sees a
+/// non-contiguous `sbrk` and other allocators never have their pages handed out twice. The heap
+/// is shared by every task, since they all use the same memory.
+#[derive(Debug, Clone)]
+pub struct HostHeap {
+    memory: SharedMemory,
+    region: Arc<Mutex<Option<Region>>>,
+}
+
+#[derive(Debug)]
+struct Region {
+    /// The current program break.
+    brk: u64,
+    /// The end of the memory the break may move into without growing memory.
+    end: u64,
+}
+
+impl HostHeap {
+    pub fn new(memory: SharedMemory) -> Self {
+        Self {
+            memory,
+            region: Default::default(),
+        }
+    }
+
+    /// Starts the program break at the robot code's `__heap_base`, unless the heap has already
+    /// been used.
+    pub fn set_heap_base(&self, heap_base: u32) {
+        let mut region = self.region.lock().unwrap();
+        region.get_or_insert_with(|| Region {
+            brk: heap_base.into(),
+            end: self.memory.data_size() as u64,
+        });
+    }
+
+    /// Moves the program break like `sbrk`, returning where the new memory starts. Returns
+    /// `None` if memory can't grow any further.
+    pub fn sbrk(&self, increment: i32) -> Option<u32> {
+        let mut region = self.region.lock().unwrap();
+        let region = self.region(&mut region);
+        if increment <= 0 {
+            let old_brk = region.brk;
+            region.brk = old_brk.checked_add_signed(increment.into())?;
+            return u32::try_from(old_brk).ok();
+        }
+        self.extend(region, 1, increment as u64)
+    }
+
+    /// Allocates a buffer by moving the program break past it. Memory is never freed.
+    pub fn alloc(&self, layout: Layout) -> Option<u32> {
+        let mut region = self.region.lock().unwrap();
+        let region = self.region(&mut region);
+        self.extend(region, layout.align() as u64, layout.size() as u64)
+    }
+
+    fn region<'a>(&self, region: &'a mut Option<Region>) -> &'a mut Region {
+        region.get_or_insert_with(|| {
+            let end = self.memory.data_size() as u64;
+            Region { brk: end, end }
+        })
+    }
+
+    /// Moves the break past a buffer of the given size and alignment, growing memory if needed.
+    fn extend(&self, region: &mut Region, align: u64, size: u64) -> Option<u32> {
+        let mut ptr = region.brk.next_multiple_of(align);
+        if ptr + size > region.end {
+            let pages = (ptr + size - region.end).div_ceil(PAGE_SIZE);
+            let start = self.memory.grow(pages).ok()? * PAGE_SIZE;
+            if start == region.end {
+                region.end += pages * PAGE_SIZE;
+            } else {
+                // the robot code grew memory since the break last moved, so skip its pages
+                ptr = start;
+                region.end = start + pages * PAGE_SIZE;
+                if ptr + size > region.end {
+                    let pages = (ptr + size - region.end).div_ceil(PAGE_SIZE);
+                    if self.memory.grow(pages).ok()? * PAGE_SIZE != region.end {
+                        return None;
+                    }
+                    region.end += pages * PAGE_SIZE;
+                }
+            }
+        }
+        region.brk = ptr + size;
+        u32::try_from(ptr).ok()
+    }
+}
@@ -0,0 +1,35 @@
+//! Random perturbations of task scheduling, for flushing out race conditions. See
+//! [`SimulatorOptions::jitter`](crate::SimulatorOptions::jitter).
+
+use std::time::Duration;
+
+/// Picks which task runs next and how much longer delays last, using its own random number
+/// generator so that enabling jitter doesn't change the values robot code gets from
+/// `sim_random`.
+pub struct Jitter {
+    rng: fastrand::Rng,
+    max_delay: Duration,
+}
+
+impl Jitter {
+    /// Creates a generator seeded by `seed`, or randomly if it's `None`.
+    pub fn new(seed: Option<u64>, max_delay: Duration) -> Self {
+        let rng = match seed {
+            // don't use the same sequence as `sim_random`
+            Some(seed) => fastrand::Rng::with_seed(!seed),
+            None => fastrand::Rng::new(),
+        };
+        Self { rng, max_delay }
+    }
+
+    /// Picks one of the tasks that are ready to run.
+    pub fn pick(&mut self, candidates: &[u32]) -> Option<u32> {
+        self.rng.choice(candidates).copied()
+    }
+
+    /// How much longer than requested a delay or timeout should last.
+    pub fn extra_delay(&mut self) -> Duration {
+        let max_nanos = self.max_delay.as_nanos() as u64;
+        Duration::from_nanos(self.rng.u64(..=max_nanos))
+    }
+}
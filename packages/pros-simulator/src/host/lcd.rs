@@ -1,18 +1,58 @@
+//! Simulated LLEMU (Legacy LCD Emulator) state.
+//!
+//! Like on a real brain, LLEMU is drawn onto the [`Screen`] once it's initialized, so frontends
+//! that show the screen show it too. Changes are also reported with the `Lcd*`
+//! [`SimulatorEvent`]s, for simpler frontends that only show the LCD's text.
+
 use std::mem::replace;
 
-use pros_simulator_interface::{LcdLines, SimulatorEvent, LCD_HEIGHT, LCD_WIDTH};
+use pros_simulator_interface::{
+    truncate_to_width, LcdLine, LcdLines, LcdSelector, LcdSelectorButton, LcdSelectorRole,
+    SimulatorEvent, LCD_HEIGHT, LCD_WIDTH,
+};
 use pros_sys::error as errno;
 use tokio::sync::Mutex;
-use wasmtime::{AsContextMut, Table};
+use wasmtime::{AsContextMut, Table, TypedFunc};
 
+use super::screen::{Screen, TEXT_COLUMN_WIDTH, TEXT_HEIGHT};
 use crate::interface::SimulatorInterface;
 
 #[derive(Debug)]
 pub struct AlreadyInitializedError;
 
-pub struct LcdColors {
-    pub background: u32,
-    pub foreground: u32,
+/// The most times [`Lcd::choose`] taps a button looking for a choice.
+const MAX_SELECTOR_TAPS: usize = 100;
+
+/// LLEMU's default colors, as `0xRRGGBB`.
+const BACKGROUND: u32 = 0x5abc03;
+const FOREGROUND: u32 = 0x000000;
+/// The color of the buttons drawn under the lines.
+const BUTTON_COLOR: u32 = 0x3d8002;
+/// Pixels from the top of one line on the screen to the next.
+const LINE_PITCH: u32 = 24;
+/// Pixels above the first line.
+const TOP_MARGIN: u32 = 8;
+/// Pixels between the buttons, and around them.
+const BUTTON_GAP: u32 = 12;
+
+/// A callback registered with `lcd_register_btnN_cb`.
+#[derive(Debug, Clone)]
+struct ButtonCallback {
+    /// Index of the function in the robot code's function table.
+    index: u32,
+    /// Name of the task that registered it.
+    task_name: String,
+}
+
+/// What's been learned about an autonomous selector from watching the LCD.
+#[derive(Debug, Default)]
+struct SelectorTracker {
+    /// The line that changed the last time the left or right button was tapped.
+    choice_line: Option<usize>,
+    /// The text shown on that line so far.
+    choices: Vec<String>,
+    /// The state last sent to the frontend.
+    sent: Option<LcdSelector>,
 }
 
 pub struct Lcd {
@@ -20,17 +60,21 @@ pub struct Lcd {
     interface: SimulatorInterface,
     initialized: bool,
     button_presses: [bool; 3],
-    button_callbacks: [Option<u32>; 3],
+    button_callbacks: [Option<ButtonCallback>; 3],
+    /// Tracks the autonomous selector, if enabled with
+    /// [`SimulatorOptions::lcd_selector`](crate::SimulatorOptions::lcd_selector).
+    selector: Option<SelectorTracker>,
 }
 
 impl Lcd {
-    pub fn new(interface: SimulatorInterface) -> Self {
+    pub fn new(interface: SimulatorInterface, track_selector: bool) -> Self {
         Self {
             lines: Default::default(),
             interface,
             initialized: false,
             button_presses: [false; 3],
-            button_callbacks: [None; 3],
+            button_callbacks: Default::default(),
+            selector: track_selector.then(SelectorTracker::default),
         }
     }
 
@@ -50,14 +94,6 @@ impl Lcd {
         Ok(())
     }
 
-    fn assert_text_length_in_bounds(&self, text: &str) -> Result<(), i32> {
-        if text.len() > LCD_WIDTH as usize {
-            tracing::error!("Text too long for LCD");
-            return Err(errno::EINVAL);
-        }
-        Ok(())
-    }
-
     pub fn initialize(&mut self) -> Result<(), AlreadyInitializedError> {
         if self.initialized {
             return Err(AlreadyInitializedError);
@@ -69,24 +105,28 @@ impl Lcd {
         Ok(())
     }
 
+    /// Sets the text of a line. Like LLEMU, text that doesn't fit on the line is cut off at the
+    /// edge of the display.
     pub fn set_line(&mut self, line: i32, text: &str) -> Result<(), i32> {
         self.assert_initialized()?;
         self.assert_line_in_bounds(line)?;
-        self.assert_text_length_in_bounds(text)?;
 
-        self.lines[line as usize] = text.to_string();
+        let text = truncate_to_width(text, LCD_WIDTH as usize);
+        self.lines[line as usize].text = text.to_string();
         self.interface
             .send(SimulatorEvent::LcdUpdated(self.lines.clone()));
+        self.update_selector();
         Ok(())
     }
 
     pub fn clear(&mut self) -> Result<(), i32> {
         self.assert_initialized()?;
         for line in &mut self.lines {
-            line.clear();
+            line.text.clear();
         }
         self.interface
             .send(SimulatorEvent::LcdUpdated(self.lines.clone()));
+        self.update_selector();
         Ok(())
     }
 
@@ -94,43 +134,237 @@ impl Lcd {
         self.assert_initialized()?;
         self.assert_line_in_bounds(line)?;
 
-        self.lines[line as usize] = String::new();
+        self.lines[line as usize] = LcdLine::default();
         self.interface
             .send(SimulatorEvent::LcdUpdated(self.lines.clone()));
+        self.update_selector();
         Ok(())
     }
 
-    pub fn set_btn_press_callback(&mut self, button: usize, callback: u32) -> Result<(), i32> {
+    /// Draws the whole LCD onto the screen: its background, every line and the three buttons
+    /// under them.
+    pub fn draw(&self, screen: &mut Screen) {
+        screen.fill_rect_with(0, 0, i32::MAX, i32::MAX, BACKGROUND);
+        for line in 0..LCD_HEIGHT as usize {
+            self.draw_line(line, screen);
+        }
+        let top = TOP_MARGIN + LCD_HEIGHT * LINE_PITCH + BUTTON_GAP;
+        let width = (LCD_WIDTH * TEXT_COLUMN_WIDTH - 4 * BUTTON_GAP) / 3;
+        for button in 0..3 {
+            let left = BUTTON_GAP + button * (width + BUTTON_GAP);
+            screen.fill_rect_with(
+                left as i32,
+                top as i32,
+                (left + width - 1) as i32,
+                i32::MAX,
+                BUTTON_COLOR,
+            );
+        }
+    }
+
+    /// Draws a line onto the screen, over whatever it showed before. Does nothing if the LCD
+    /// isn't initialized, since it isn't on the screen.
+    pub fn draw_line(&self, line: usize, screen: &mut Screen) {
+        if !self.initialized || line >= LCD_HEIGHT as usize {
+            return;
+        }
+        let top = (TOP_MARGIN + line as u32 * LINE_PITCH) as i32;
+        let bottom = top + TEXT_HEIGHT as i32 - 1;
+        screen.fill_rect_with(0, top, i32::MAX, bottom, BACKGROUND);
+        screen.draw_text(0, top, &self.lines[line].text, FOREGROUND);
+    }
+
+    /// Sets the function called when a button is pressed, or clears it if `callback` is `None`.
+    /// `is_valid` reports whether the callback is a function that can be called with no
+    /// arguments, and is only checked once the LCD is known to be initialized.
+    pub fn set_btn_press_callback(
+        &mut self,
+        button: usize,
+        callback: Option<u32>,
+        task_name: &str,
+        is_valid: impl FnOnce(u32) -> bool,
+    ) -> Result<(), i32> {
         self.assert_initialized()?;
+        if callback.is_some_and(|callback| !is_valid(callback)) {
+            tracing::error!("Invalid LCD button callback");
+            return Err(errno::EINVAL);
+        }
 
-        self.button_callbacks[button] = Some(callback);
+        self.button_callbacks[button] = callback.map(|index| ButtonCallback {
+            index,
+            task_name: task_name.to_string(),
+        });
+        self.update_selector();
         Ok(())
     }
 
+    /// The autonomous selector as it looks now.
+    fn selector_state(&self, tracker: &SelectorTracker) -> LcdSelector {
+        LcdSelector {
+            choice: tracker
+                .choice_line
+                .map(|line| self.lines[line].text.clone()),
+            choices: tracker.choices.clone(),
+            buttons: [0, 1, 2].map(|button| {
+                let callback = self.button_callbacks[button].as_ref()?;
+                Some(LcdSelectorButton {
+                    role: LcdSelectorRole::BUTTONS[button],
+                    task_name: callback.task_name.clone(),
+                })
+            }),
+        }
+    }
+
+    /// Learns the choice on the choice line, and tells the frontend if the selector changed.
+    /// Nothing is sent until a button callback has been registered.
+    fn update_selector(&mut self) {
+        let Some(mut tracker) = self.selector.take() else {
+            return;
+        };
+        if let Some(line) = tracker.choice_line {
+            let text = &self.lines[line].text;
+            if !text.is_empty() && !tracker.choices.contains(text) {
+                tracker.choices.push(text.clone());
+            }
+        }
+        let state = self.selector_state(&tracker);
+        let registered = self.button_callbacks.iter().any(Option::is_some);
+        if (registered || tracker.sent.is_some()) && tracker.sent.as_ref() != Some(&state) {
+            self.interface
+                .send(SimulatorEvent::LcdSelectorUpdated(state.clone()));
+            tracker.sent = Some(state);
+        }
+        self.selector = Some(tracker);
+    }
+
+    /// Called after the left or right button's callback ran, to find the line showing the
+    /// choice: the first one the callback changed.
+    fn selector_tapped(&mut self, lines_before: &LcdLines) {
+        let Some(tracker) = &mut self.selector else {
+            return;
+        };
+        let changed =
+            (0..LCD_HEIGHT as usize).find(|&line| self.lines[line].text != lines_before[line].text);
+        if let Some(line) = changed {
+            if tracker.choice_line != Some(line) {
+                tracker.choice_line = Some(line);
+                tracker.choices.clear();
+                // the text that was shown before the tap was a choice too
+                let before = &lines_before[line].text;
+                if !before.is_empty() {
+                    tracker.choices.push(before.clone());
+                }
+            }
+        }
+        self.update_selector();
+    }
+
+    /// The choice the autonomous selector is showing, if the choice line is known.
+    fn selector_choice(&self) -> Option<String> {
+        let line = self.selector.as_ref()?.choice_line?;
+        Some(self.lines[line].text.clone())
+    }
+
+    /// Looks up a button callback in a task's function table, returning `None` if the index is
+    /// out of bounds or isn't a function with no arguments or return value, or the robot code
+    /// has no function table.
+    pub fn callback(
+        mut store: impl AsContextMut,
+        callback_table: Option<Table>,
+        index: u32,
+    ) -> Option<TypedFunc<(), ()>> {
+        let callback = callback_table?.get(&mut store, index)?;
+        let callback = *callback.funcref()??;
+        callback.typed::<(), ()>(&store).ok()
+    }
+
     /// Marks certain LCD buttons as being pressed. If a button was not pressed before
     /// but is now, the callback for that button will be called.
     pub async fn press(
         lcd: &Mutex<Self>,
         mut store: impl AsContextMut<Data = impl Send>,
-        callback_table: Table,
+        callback_table: Option<Table>,
         buttons: [bool; 3],
     ) -> anyhow::Result<()> {
-        let mut lcd = lcd.lock().await;
-        let previous_presses = replace(&mut lcd.button_presses, buttons);
-        let callbacks = lcd.button_callbacks;
-        drop(lcd);
+        let (previous_presses, callbacks, interface) = {
+            let mut lcd = lcd.lock().await;
+            let previous_presses = replace(&mut lcd.button_presses, buttons);
+            (
+                previous_presses,
+                lcd.button_callbacks.clone(),
+                lcd.interface.clone(),
+            )
+        };
 
         for (index, button_pressed) in buttons.iter().enumerate() {
             if *button_pressed && !previous_presses[index] {
-                if let Some(cb_index) = &callbacks[index] {
-                    let callback = callback_table.get(&mut store, *cb_index).unwrap();
-                    let callback = callback.funcref().unwrap().unwrap();
-                    let callback = callback.typed::<(), ()>(&mut store).unwrap();
+                if let Some(ButtonCallback {
+                    index: cb_index, ..
+                }) = callbacks[index]
+                {
+                    let Some(callback) = Self::callback(&mut store, callback_table, cb_index)
+                    else {
+                        interface.send(SimulatorEvent::Warning(format!(
+                            "LCD button {index} callback {cb_index} isn't a valid function"
+                        )));
+                        continue;
+                    };
+                    let lines_before = lcd.lock().await.lines.clone();
                     callback.call_async(&mut store, ()).await?;
+                    if LcdSelectorRole::BUTTONS[index] != LcdSelectorRole::Select {
+                        lcd.lock().await.selector_tapped(&lines_before);
+                    }
                 }
             }
         }
 
         Ok(())
     }
+
+    /// Taps the next button, or the previous button if there isn't a next button, until the
+    /// autonomous selector shows `choice`. Sends a warning if the choices cycle back around
+    /// without showing it.
+    pub async fn choose(
+        lcd: &Mutex<Self>,
+        mut store: impl AsContextMut<Data = impl Send>,
+        callback_table: Option<Table>,
+        choice: &str,
+    ) -> anyhow::Result<()> {
+        let (button, interface) = {
+            let lcd = lcd.lock().await;
+            let button = [2, 0]
+                .into_iter()
+                .find(|&button| lcd.button_callbacks[button].is_some());
+            (button, lcd.interface.clone())
+        };
+        let Some(button) = button else {
+            interface.send(SimulatorEvent::Warning(format!(
+                "Can't choose `{choice}` on the LCD because robot code hasn't registered a \
+                 callback for the left or right button"
+            )));
+            return Ok(());
+        };
+
+        let mut shown = Vec::new();
+        for _ in 0..MAX_SELECTOR_TAPS {
+            let current = lcd.lock().await.selector_choice();
+            if let Some(current) = current {
+                if current == choice {
+                    return Ok(());
+                }
+                if shown.contains(&current) {
+                    break;
+                }
+                shown.push(current);
+            }
+            let mut pressed = [false; 3];
+            pressed[button] = true;
+            Self::press(lcd, &mut store, callback_table, pressed).await?;
+            Self::press(lcd, &mut store, callback_table, [false; 3]).await?;
+        }
+        interface.send(SimulatorEvent::Warning(format!(
+            "The LCD never showed `{choice}`"
+        )));
+        Ok(())
+    }
 }
@@ -0,0 +1,115 @@
+//! Limits on the resources robot code can use, for simulating untrusted code like students'
+//! submissions in a grading service. See
+//! [`SimulatorOptions::max_memory`](crate::SimulatorOptions::max_memory),
+//! [`SimulatorOptions::max_tasks`](crate::SimulatorOptions::max_tasks) and
+//! [`SimulatorOptions::max_event_rate`](crate::SimulatorOptions::max_event_rate).
+//!
+//! The first limit robot code exceeds is recorded, and the scheduler stops the simulation with
+//! [`StopReason::LimitExceeded`] the next time a task yields.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::anyhow;
+use pros_simulator_interface::{ResourceLimit, SimulatorEvent};
+use wasmtime::ResourceLimiter;
+
+use crate::{interface::SimulatorInterface, SimulatorOptions, StopReason};
+
+/// The limits robot code runs under, and the first one it exceeded.
+#[derive(Debug, Clone)]
+pub struct Limits {
+    max_memory: Option<usize>,
+    max_tasks: Option<u32>,
+    max_event_rate: Option<u32>,
+    exceeded: Arc<Mutex<Option<ResourceLimit>>>,
+    /// When the current second of events started, and how many had been sent by then.
+    event_window: Arc<Mutex<(Instant, u64)>>,
+}
+
+impl Limits {
+    pub fn new(options: &SimulatorOptions) -> Self {
+        Self {
+            max_memory: options.max_memory,
+            max_tasks: options.max_tasks,
+            max_event_rate: options.max_event_rate,
+            exceeded: Arc::default(),
+            event_window: Arc::new(Mutex::new((Instant::now(), 0))),
+        }
+    }
+
+    /// Whether robot code memory is limited, so stores need this as their limiter.
+    pub fn limits_memory(&self) -> bool {
+        self.max_memory.is_some()
+    }
+
+    /// The most robot code tasks that can exist at once, if that's limited.
+    pub fn max_tasks(&self) -> Option<u32> {
+        self.max_tasks
+    }
+
+    /// The limit on a resource, in its unit.
+    fn max(&self, limit: ResourceLimit) -> u64 {
+        match limit {
+            ResourceLimit::Memory => self.max_memory.map(|max| max as u64),
+            ResourceLimit::Tasks => self.max_tasks.map(u64::from),
+            ResourceLimit::EventRate => self.max_event_rate.map(u64::from),
+        }
+        .unwrap_or(u64::MAX)
+    }
+
+    /// Records that robot code exceeded a limit. Only the first limit exceeded is kept.
+    pub fn exceed(&self, limit: ResourceLimit) {
+        self.exceeded.lock().unwrap().get_or_insert(limit);
+    }
+
+    /// Checks whether a limit has been exceeded, counting the events sent through `interface`
+    /// towards the event rate. If one has, tells the frontend and returns why the simulation
+    /// should stop.
+    pub fn check(&self, interface: &SimulatorInterface) -> Option<StopReason> {
+        if let Some(max) = self.max_event_rate {
+            let sent = interface.stats().sent;
+            let mut window = self.event_window.lock().unwrap();
+            if sent - window.1 > u64::from(max) {
+                self.exceed(ResourceLimit::EventRate);
+            }
+            if window.0.elapsed() >= Duration::from_secs(1) {
+                *window = (Instant::now(), sent);
+            }
+        }
+
+        let limit = (*self.exceeded.lock().unwrap())?;
+        interface.send(SimulatorEvent::ResourceLimitExceeded {
+            limit,
+            max: self.max(limit),
+        });
+        Some(StopReason::LimitExceeded(limit))
+    }
+}
+
+impl ResourceLimiter for Limits {
+    fn memory_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> anyhow::Result<bool> {
+        if self.max_memory.is_some_and(|max| desired > max) {
+            self.exceed(ResourceLimit::Memory);
+            // trap so that the task stops right away, rather than carrying on without memory
+            return Err(anyhow!("Robot code ran out of memory"));
+        }
+        Ok(true)
+    }
+
+    fn table_growing(
+        &mut self,
+        _current: u32,
+        _desired: u32,
+        _maximum: Option<u32>,
+    ) -> anyhow::Result<bool> {
+        Ok(true)
+    }
+}
@@ -1,38 +1,106 @@
-use anyhow::Context;
+use pros_sys::EFAULT;
 use snafu::Snafu;
 use wasmtime::SharedMemory;
 
+/// A read or write went past the end of robot code memory.
+///
+/// Host functions should report this to robot code by setting `errno` to `EFAULT`, which this
+/// converts into, rather than panicking.
 #[derive(Debug, Snafu)]
+#[snafu(display("Pointer is outside of robot code memory"))]
 pub struct OutOfBoundsError;
 
+impl From<OutOfBoundsError> for i32 {
+    fn from(_: OutOfBoundsError) -> Self {
+        EFAULT
+    }
+}
+
+/// The longest string [`SharedMemoryExt::read_c_str`] will read, not including the null
+/// terminator. Longer strings are truncated.
+pub const MAX_C_STR_LEN: usize = 64 * 1024;
+
+/// A string read from robot code memory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GuestStr {
+    pub text: String,
+    /// The string wasn't null-terminated within [`MAX_C_STR_LEN`] bytes, so it was truncated.
+    pub truncated: bool,
+    /// The string contained invalid UTF-8, which was replaced with `U+FFFD`.
+    pub lossy: bool,
+}
+
+impl GuestStr {
+    /// Describes what was wrong with the string read from `ptr`, if anything.
+    pub fn warning(&self, ptr: u32) -> Option<String> {
+        match (self.truncated, self.lossy) {
+            (false, false) => None,
+            (true, false) => Some(format!(
+                "String at {ptr:#x} is longer than {MAX_C_STR_LEN} bytes and was truncated"
+            )),
+            (false, true) => Some(format!(
+                "String at {ptr:#x} is not valid UTF-8"
+            )),
+            (true, true) => Some(format!(
+                "String at {ptr:#x} is longer than {MAX_C_STR_LEN} bytes and was truncated, and is not valid UTF-8"
+            )),
+        }
+    }
+}
+
+/// Helpers for accessing robot code memory from the host.
+///
+/// Reads and writes are not atomic, so callers must make sure robot code isn't using the same
+/// memory concurrently (which is always the case inside host functions).
 pub trait SharedMemoryExt {
-    fn read_c_str(&self, ptr: u32) -> anyhow::Result<String>;
+    /// Reads a null-terminated UTF-8 string starting at `ptr`, reading at most
+    /// [`MAX_C_STR_LEN`] bytes. Fails if memory ends before the string does.
+    ///
+    /// Host functions should usually use [`HostCtx::read_c_str`](super::HostCtx::read_c_str)
+    /// instead, which warns the user if the string was truncated or invalid.
+    fn read_c_str(&self, ptr: u32) -> Result<GuestStr, OutOfBoundsError>;
+    /// Copies `buffer` into memory starting at `offset`.
     fn write_relaxed(&self, offset: usize, buffer: &[u8]) -> Result<(), OutOfBoundsError>;
+    /// Copies `length` bytes of memory starting at `offset` into a new buffer.
     fn read_relaxed(&self, offset: usize, length: usize) -> Result<Vec<u8>, OutOfBoundsError>;
 }
 
 impl SharedMemoryExt for SharedMemory {
-    fn read_c_str(&self, ptr: u32) -> anyhow::Result<String> {
-        let data = self
-            .data()
-            .get(ptr as usize..)
-            .with_context(|| format!("invalid pointer: {}", ptr))?;
-        for (index, cell) in data.iter().enumerate() {
-            if unsafe { cell.get().read() } == 0 {
-                return Ok(String::from_utf8(
-                    data[..index]
-                        .iter()
-                        .map(|c| unsafe { c.get().read() })
-                        .collect::<Vec<_>>(),
-                )
-                .expect("invalid UTF-8 string"));
+    fn read_c_str(&self, ptr: u32) -> Result<GuestStr, OutOfBoundsError> {
+        let Some(data) = self.data().get(ptr as usize..) else {
+            return Err(OutOfBoundsError);
+        };
+        let mut bytes = Vec::new();
+        let mut truncated = true;
+        for cell in data.iter().take(MAX_C_STR_LEN + 1) {
+            let byte = unsafe { cell.get().read() };
+            if byte == 0 {
+                truncated = false;
+                break;
             }
+            bytes.push(byte);
+        }
+        if truncated {
+            if bytes.len() <= MAX_C_STR_LEN {
+                // memory ended before the string did
+                return Err(OutOfBoundsError);
+            }
+            bytes.truncate(MAX_C_STR_LEN);
         }
 
-        Err(anyhow::anyhow!("C string must be null-terminated"))
+        let (text, lossy) = match String::from_utf8(bytes) {
+            Ok(text) => (text, false),
+            Err(err) => (String::from_utf8_lossy(err.as_bytes()).into_owned(), true),
+        };
+        Ok(GuestStr {
+            text,
+            truncated,
+            lossy,
+        })
     }
     fn write_relaxed(&self, offset: usize, buffer: &[u8]) -> Result<(), OutOfBoundsError> {
-        let Some(data) = self.data().get(offset..offset + buffer.len()) else {
+        let end = offset.checked_add(buffer.len()).ok_or(OutOfBoundsError)?;
+        let Some(data) = self.data().get(offset..end) else {
             return Err(OutOfBoundsError);
         };
         for (cell, byte) in data.iter().zip(buffer) {
@@ -41,7 +109,8 @@ impl SharedMemoryExt for SharedMemory {
         Ok(())
     }
     fn read_relaxed(&self, offset: usize, length: usize) -> Result<Vec<u8>, OutOfBoundsError> {
-        let Some(data) = self.data().get(offset..offset + length) else {
+        let end = offset.checked_add(length).ok_or(OutOfBoundsError)?;
+        let Some(data) = self.data().get(offset..end) else {
             return Err(OutOfBoundsError);
         };
         let mut buffer = Vec::with_capacity(length);
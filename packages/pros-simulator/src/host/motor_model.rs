@@ -0,0 +1,63 @@
+//! A model of an unloaded V5 smart motor with the 200 RPM (green) cartridge, used to report
+//! what motors are doing in [`SimulatorEvent::MotorUpdated`](pros_simulator_interface::SimulatorEvent::MotorUpdated).
+//!
+//! The motor is a DC motor whose speed approaches its free speed at the voltage it's driven at,
+//! with a fixed time constant. There's nothing attached to it, so it never stalls.
+
+use super::smart_ports::MAX_MOTOR_MILLIVOLTS;
+
+/// The motor's speed when it's driven at 12 volts, in RPM.
+const FREE_SPEED_RPM: f64 = 200.0;
+
+/// How long the motor takes to get about two thirds of the way to a new speed, in seconds.
+const TIME_CONSTANT: f64 = 0.05;
+
+/// The current the motor draws when it's stalled at 12 volts, in milliamps, which is also as much
+/// as the V5 lets a motor draw.
+const STALL_CURRENT_MA: f64 = 2500.0;
+
+/// How fast and how far a motor has turned.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MotorModel {
+    /// The motor's speed, in RPM.
+    velocity: f64,
+    /// How far the motor has turned since the simulation started, in degrees.
+    position: f64,
+}
+
+impl MotorModel {
+    /// Runs the motor for `seconds` at `millivolts`.
+    pub fn advance(&mut self, millivolts: i32, seconds: f64) {
+        let target = target_velocity(millivolts);
+        let decay = (-seconds / TIME_CONSTANT).exp();
+        // the integral of the velocity over the interval, in revolutions per minute times seconds
+        let distance = target * seconds + (self.velocity - target) * TIME_CONSTANT * (1.0 - decay);
+        self.position += distance * 360.0 / 60.0;
+        self.velocity = target + (self.velocity - target) * decay;
+    }
+
+    /// The motor's speed, in RPM.
+    pub fn velocity(&self) -> f64 {
+        self.velocity
+    }
+
+    /// How far the motor has turned since the simulation started, in degrees.
+    pub fn position(&self) -> f64 {
+        self.position
+    }
+
+    /// The current the motor draws at `millivolts`, in milliamps. The faster it's turning, the
+    /// more of the voltage is cancelled out by its back EMF.
+    pub fn current(&self, millivolts: i32) -> f64 {
+        let voltage = f64::from(millivolts) / f64::from(MAX_MOTOR_MILLIVOLTS);
+        let back_emf = self.velocity / FREE_SPEED_RPM;
+        (STALL_CURRENT_MA * (voltage - back_emf))
+            .abs()
+            .min(STALL_CURRENT_MA)
+    }
+}
+
+/// The speed a motor settles at when it's driven at `millivolts`, in RPM.
+pub fn target_velocity(millivolts: i32) -> f64 {
+    FREE_SPEED_RPM * f64::from(millivolts) / f64::from(MAX_MOTOR_MILLIVOLTS)
+}
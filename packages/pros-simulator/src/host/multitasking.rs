@@ -1,7 +1,8 @@
 // use std::sync::Mutex;
-use std::{sync::Arc, time::Instant};
+use std::{future::Future, sync::Arc};
 
 use futures::{future::pending, FutureExt};
+use pros_sys::EINVAL;
 use slab::Slab;
 use tokio::sync::{Mutex, OwnedMutexGuard};
 
@@ -9,6 +10,29 @@ use tokio::sync::{Mutex, OwnedMutexGuard};
 pub struct HostMutex {
     inner: Arc<Mutex<()>>,
     lock: Option<OwnedMutexGuard<()>>,
+    holder: Option<MutexHolder>,
+}
+
+/// The task holding a mutex, for reporting contention.
+#[derive(Debug, Clone)]
+pub struct MutexHolder {
+    pub task_id: u32,
+    pub task_name: String,
+    /// The tick the mutex was taken on.
+    pub since: u64,
+    /// Whether it has been reported for being held too long.
+    reported: bool,
+}
+
+impl MutexHolder {
+    pub fn new(task_id: u32, task_name: impl Into<String>, since: u64) -> Self {
+        Self {
+            task_id,
+            task_name: task_name.into(),
+            since,
+            reported: false,
+        }
+    }
 }
 
 #[derive(Debug, Default)]
@@ -21,32 +45,84 @@ impl MutexPool {
     pub fn create_mutex(&mut self) -> usize {
         self.mutexes.insert(HostMutex::default())
     }
-    /// Creates a mutex, returning its ID.
-    pub fn delete_mutex(&mut self, mutex_id: usize) {
-        self.mutexes.remove(mutex_id);
+    /// Deletes a mutex, failing with `EINVAL` if there isn't one with this ID.
+    pub fn delete_mutex(&mut self, mutex_id: usize) -> Result<(), i32> {
+        self.mutexes.try_remove(mutex_id).map(drop).ok_or(EINVAL)
     }
 
-    /// Locks a mutex by ID, cancelling on timeout, and returning a boolean of whether the lock was
-    /// successful.
-    pub async fn lock(&mut self, mutex_id: usize, timeout: Option<Instant>) -> bool {
-        let sleep = timeout.map_or_else(
-            || pending().boxed(),
-            |i| tokio::time::sleep_until(i.into()).boxed(),
-        );
+    /// Whether there's a mutex with this ID.
+    pub fn contains(&self, mutex_id: usize) -> bool {
+        self.mutexes.contains(mutex_id)
+    }
 
-        let mutex = self.mutexes.get_mut(mutex_id).unwrap();
-        tokio::select! {
-            biased;
-            lock = mutex.inner.clone().lock_owned() => {
-                mutex.lock = Some(lock);
-                true
+    /// The task holding a mutex, if any. Fails with `EINVAL` if there isn't one with this ID.
+    pub fn holder(&self, mutex_id: usize) -> Result<Option<&MutexHolder>, i32> {
+        let mutex = self.mutexes.get(mutex_id).ok_or(EINVAL)?;
+        Ok(mutex.holder.as_ref())
+    }
+
+    /// Holders that have had their mutex since before the tick `deadline` and haven't been
+    /// returned from here yet, along with the mutex they're holding.
+    pub fn take_overdue(&mut self, deadline: u64) -> Vec<(usize, MutexHolder)> {
+        let mut overdue = Vec::new();
+        for (mutex_id, mutex) in &mut self.mutexes {
+            if let Some(holder) = &mut mutex.holder {
+                if !holder.reported && holder.since < deadline {
+                    holder.reported = true;
+                    overdue.push((mutex_id, holder.clone()));
+                }
             }
-            _ = sleep => false,
         }
+        overdue
     }
 
-    pub fn unlock(&mut self, mutex_id: usize) {
-        let mutex = self.mutexes.get_mut(mutex_id).unwrap();
-        mutex.lock.take().unwrap();
+    /// Records which task took a mutex, after [`lock`](Self::lock) succeeds.
+    pub fn set_holder(&mut self, mutex_id: usize, holder: MutexHolder) {
+        if let Some(mutex) = self.mutexes.get_mut(mutex_id) {
+            mutex.holder = Some(holder);
+        }
+    }
+
+    /// Locks a mutex by ID, cancelling once `timeout` completes, and returning a boolean of whether
+    /// the lock was successful. Fails with `EINVAL` if there isn't a mutex with this ID, or it's
+    /// deleted while waiting.
+    ///
+    /// The pool is only locked while looking up the mutex and storing the guard, so that other
+    /// tasks can give the mutex back in the meantime.
+    pub async fn lock(
+        pool: &Mutex<Self>,
+        mutex_id: usize,
+        timeout: Option<impl Future<Output = ()> + Send>,
+    ) -> Result<bool, i32> {
+        let sleep = timeout.map_or_else(|| pending().boxed(), |sleep| sleep.boxed());
+
+        let inner = {
+            let pool = pool.lock().await;
+            pool.mutexes.get(mutex_id).ok_or(EINVAL)?.inner.clone()
+        };
+        let guard = tokio::select! {
+            biased;
+            lock = inner.lock_owned() => lock,
+            _ = sleep => return Ok(false),
+        };
+        let mut pool = pool.lock().await;
+        pool.mutexes.get_mut(mutex_id).ok_or(EINVAL)?.lock = Some(guard);
+        Ok(true)
+    }
+
+    /// Gives back a mutex held by the task `task_id`. Fails with `EINVAL` if there isn't a mutex
+    /// with this ID, or it isn't held by that task.
+    pub fn unlock(&mut self, mutex_id: usize, task_id: u32) -> Result<(), i32> {
+        let mutex = self.mutexes.get_mut(mutex_id).ok_or(EINVAL)?;
+        let held = mutex
+            .holder
+            .as_ref()
+            .map_or(mutex.lock.is_some(), |holder| holder.task_id == task_id);
+        if !held {
+            return Err(EINVAL);
+        }
+        mutex.lock = None;
+        mutex.holder = None;
+        Ok(())
     }
 }
@@ -0,0 +1,339 @@
+//! Exports a simulation as OpenTelemetry spans, so long runs can be analyzed in standard
+//! observability tools like Jaeger or Grafana Tempo. Only built with the `otlp` feature. See
+//! [`SimulatorOptions::otlp_endpoint`](crate::SimulatorOptions::otlp_endpoint).
+//!
+//! Each simulation is one trace. Its root span lasts for the whole simulation, each task's
+//! lifetime is a child of the root, and each host call robot code makes is a child of the task
+//! that made it. Finished spans are posted to the collector once a second using OTLP's JSON
+//! encoding over plain HTTP.
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader, Write},
+    net::TcpStream,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, Weak,
+    },
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::bail;
+use serde_json::{json, Value};
+
+use super::HostCtx;
+
+/// How often finished spans are sent to the collector.
+const EXPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long the collector has to accept a batch of spans.
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The most finished spans kept while waiting to be sent. Any more are dropped, so a collector
+/// that can't keep up doesn't use up all the simulator's memory.
+const MAX_PENDING_SPANS: usize = 100_000;
+
+/// Records the spans of a simulation and sends them to an OTLP collector.
+#[derive(Clone)]
+pub struct SpanExporter {
+    inner: Arc<Exporter>,
+}
+
+struct Exporter {
+    collector: Collector,
+    trace_id: u128,
+    root: OpenSpan,
+    rng: Mutex<fastrand::Rng>,
+    /// The spans of tasks that haven't stopped yet, by task ID.
+    tasks: Mutex<HashMap<u32, OpenSpan>>,
+    pending: Mutex<Vec<Span>>,
+    dropped: AtomicU64,
+    /// The first error sending spans, which is reported once the simulation finishes.
+    error: Mutex<Option<String>>,
+}
+
+#[derive(Clone)]
+struct OpenSpan {
+    id: u64,
+    name: String,
+    start: SystemTime,
+    attributes: Vec<(&'static str, Value)>,
+}
+
+struct Span {
+    open: OpenSpan,
+    parent: Option<u64>,
+    end: SystemTime,
+}
+
+impl SpanExporter {
+    /// Starts a trace to send to the collector at `endpoint`, e.g. `http://localhost:4318`.
+    /// Spans are posted to `/v1/traces` unless the endpoint has a path of its own.
+    pub fn new(endpoint: &str) -> anyhow::Result<Self> {
+        let collector = Collector::parse(endpoint)?;
+        let mut rng = fastrand::Rng::new();
+        let inner = Arc::new(Exporter {
+            collector,
+            trace_id: rng.u128(1..),
+            root: OpenSpan {
+                id: rng.u64(1..),
+                name: "simulation".into(),
+                start: SystemTime::now(),
+                attributes: Vec::new(),
+            },
+            rng: Mutex::new(rng),
+            tasks: Mutex::default(),
+            pending: Mutex::default(),
+            dropped: AtomicU64::new(0),
+            error: Mutex::default(),
+        });
+        thread::Builder::new()
+            .name("pros-simulator otlp".into())
+            .spawn({
+                let inner = Arc::downgrade(&inner);
+                move || export_every_interval(inner)
+            })?;
+        Ok(Self { inner })
+    }
+
+    /// Opens the span of a task that was just created.
+    pub fn start_task(&self, task_id: u32, name: &str) {
+        let span = OpenSpan {
+            id: self.inner.rng.lock().unwrap().u64(1..),
+            name: name.to_string(),
+            start: SystemTime::now(),
+            attributes: vec![("task.id", task_id.into()), ("task.name", name.into())],
+        };
+        self.inner.tasks.lock().unwrap().insert(task_id, span);
+    }
+
+    /// Closes the span of a task that finished or was deleted.
+    pub fn end_task(&self, task_id: u32) {
+        if let Some(span) = self.inner.tasks.lock().unwrap().remove(&task_id) {
+            self.inner.push(span, Some(self.inner.root.id));
+        }
+    }
+
+    /// Records a host call the given task made, which started at `start` and just returned.
+    pub fn host_call(&self, task_id: u32, name: &str, start: SystemTime) {
+        let parent = self
+            .inner
+            .tasks
+            .lock()
+            .unwrap()
+            .get(&task_id)
+            .map(|task| task.id);
+        let span = OpenSpan {
+            id: self.inner.rng.lock().unwrap().u64(1..),
+            name: name.to_string(),
+            start,
+            attributes: vec![("task.id", task_id.into())],
+        };
+        self.inner.push(span, parent.or(Some(self.inner.root.id)));
+    }
+
+    /// Closes the spans of every task still running and the simulation's own span, and sends
+    /// what hasn't been sent yet. Returns the first error sending spans, if there was one.
+    pub fn finish(&self) -> Result<(), String> {
+        let tasks = std::mem::take(&mut *self.inner.tasks.lock().unwrap());
+        for span in tasks.into_values() {
+            self.inner.push(span, Some(self.inner.root.id));
+        }
+        self.inner.push(self.inner.root.clone(), None);
+        self.inner.export();
+
+        if let Some(err) = self.inner.error.lock().unwrap().take() {
+            return Err(err);
+        }
+        match self.inner.dropped.load(Ordering::Relaxed) {
+            0 => Ok(()),
+            dropped => Err(format!(
+                "{dropped} spans were dropped because the collector couldn't keep up"
+            )),
+        }
+    }
+}
+
+impl Exporter {
+    fn push(&self, open: OpenSpan, parent: Option<u64>) {
+        let mut pending = self.pending.lock().unwrap();
+        if pending.len() >= MAX_PENDING_SPANS {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        pending.push(Span {
+            open,
+            parent,
+            end: SystemTime::now(),
+        });
+    }
+
+    /// Sends every finished span to the collector.
+    fn export(&self) {
+        let spans = std::mem::take(&mut *self.pending.lock().unwrap());
+        if spans.is_empty() {
+            return;
+        }
+        let body = self.request(&spans).to_string();
+        if let Err(err) = self.collector.post(body.as_bytes()) {
+            self.error.lock().unwrap().get_or_insert(format!(
+                "Failed to send spans to the collector at {}: {err}",
+                self.collector
+            ));
+        }
+    }
+
+    /// An `ExportTraceServiceRequest` holding the given spans.
+    fn request(&self, spans: &[Span]) -> Value {
+        let trace_id = format!("{:032x}", self.trace_id);
+        let spans = spans
+            .iter()
+            .map(|span| {
+                let mut value = json!({
+                    "traceId": trace_id,
+                    "spanId": format!("{:016x}", span.open.id),
+                    "name": span.open.name,
+                    // SPAN_KIND_INTERNAL
+                    "kind": 1,
+                    "startTimeUnixNano": unix_nanos(span.open.start),
+                    "endTimeUnixNano": unix_nanos(span.end),
+                    "attributes": span.open.attributes.iter().map(|(key, value)| {
+                        json!({ "key": key, "value": attribute_value(value) })
+                    }).collect::<Vec<_>>(),
+                });
+                if let Some(parent) = span.parent {
+                    value["parentSpanId"] = format!("{parent:016x}").into();
+                }
+                value
+            })
+            .collect::<Vec<_>>();
+        json!({
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": [
+                        { "key": "service.name", "value": { "stringValue": "pros-simulator" } },
+                    ],
+                },
+                "scopeSpans": [{
+                    "scope": { "name": "pros-simulator", "version": env!("CARGO_PKG_VERSION") },
+                    "spans": spans,
+                }],
+            }],
+        })
+    }
+}
+
+fn export_every_interval(exporter: Weak<Exporter>) {
+    loop {
+        thread::sleep(EXPORT_INTERVAL);
+        let Some(exporter) = exporter.upgrade() else {
+            break;
+        };
+        exporter.export();
+    }
+}
+
+/// Times are sent as strings, since JSON numbers can't hold every 64-bit integer.
+fn unix_nanos(time: SystemTime) -> String {
+    let nanos = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    nanos.to_string()
+}
+
+/// An OTLP `AnyValue`.
+fn attribute_value(value: &Value) -> Value {
+    match value {
+        Value::Number(number) => json!({ "intValue": number.to_string() }),
+        Value::String(string) => json!({ "stringValue": string }),
+        value => json!({ "stringValue": value.to_string() }),
+    }
+}
+
+/// Where spans are posted.
+struct Collector {
+    /// The `host:port` to connect to.
+    authority: String,
+    path: String,
+}
+
+impl Collector {
+    fn parse(endpoint: &str) -> anyhow::Result<Self> {
+        if endpoint.starts_with("https://") {
+            bail!("OTLP endpoints have to use http, as the simulator doesn't support TLS");
+        }
+        let Some(rest) = endpoint.strip_prefix("http://") else {
+            bail!("`{endpoint}` isn't an http URL, like `http://localhost:4318`");
+        };
+        let (authority, path) = match rest.split_once('/') {
+            Some((authority, path)) if !path.is_empty() => (authority, format!("/{path}")),
+            Some((authority, _)) => (authority, "/v1/traces".into()),
+            None => (rest, "/v1/traces".into()),
+        };
+        let authority = if authority.contains(':') {
+            authority.to_string()
+        } else {
+            format!("{authority}:80")
+        };
+        Ok(Self { authority, path })
+    }
+
+    fn post(&self, body: &[u8]) -> io::Result<()> {
+        let mut stream = TcpStream::connect(&self.authority)?;
+        stream.set_read_timeout(Some(EXPORT_TIMEOUT))?;
+        stream.set_write_timeout(Some(EXPORT_TIMEOUT))?;
+        write!(
+            stream,
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n",
+            self.path,
+            self.authority,
+            body.len()
+        )?;
+        stream.write_all(body)?;
+
+        let mut status = String::new();
+        BufReader::new(stream).read_line(&mut status)?;
+        match status.split_whitespace().nth(1) {
+            Some(code) if code.starts_with('2') => Ok(()),
+            _ => Err(io::Error::other(format!(
+                "the collector responded with `{}`",
+                status.trim()
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for Collector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "http://{}{}", self.authority, self.path)
+    }
+}
+
+/// A host call being timed, which becomes a span once it returns.
+pub struct HostCall {
+    spans: SpanExporter,
+    task_id: u32,
+    name: &'static str,
+    start: SystemTime,
+}
+
+impl HostCall {
+    /// Starts timing a call to the given host function, if spans are being exported.
+    pub async fn start(ctx: &(impl HostCtx + Sync), name: &'static str) -> Option<Self> {
+        let spans = ctx.spans()?;
+        let task_id = ctx.current_task().await.lock().await.id();
+        Some(Self {
+            spans,
+            task_id,
+            name,
+            start: SystemTime::now(),
+        })
+    }
+
+    pub fn end(self) {
+        self.spans.host_call(self.task_id, self.name, self.start);
+    }
+}
@@ -0,0 +1,38 @@
+use pros_simulator_interface::RobotCodePanic;
+
+/// Extracts the message and location of a Rust panic from the text robot code passed to
+/// `sim_abort`.
+///
+/// Both the current `panicked at src/main.rs:1:2:\nmessage` format and the older
+/// `panicked at 'message', src/main.rs:1:2` format are supported. Anything before
+/// `panicked at` (like the task name printed by `pros`) is ignored.
+pub fn parse_panic(abort_message: &str) -> Option<RobotCodePanic> {
+    let (_, info) = abort_message.split_once("panicked at ")?;
+
+    if let Some(info) = info.strip_prefix('\'') {
+        let (message, location) = info.rsplit_once("', ")?;
+        let (file, line, column) = parse_location(location.lines().next()?)?;
+        return Some(RobotCodePanic {
+            message: message.to_string(),
+            file,
+            line,
+            column,
+        });
+    }
+
+    let (location, message) = info.split_once(":\n").unwrap_or((info.trim_end(), ""));
+    let (file, line, column) = parse_location(location.strip_suffix(':').unwrap_or(location))?;
+    Some(RobotCodePanic {
+        message: message.trim_end().to_string(),
+        file,
+        line,
+        column,
+    })
+}
+
+/// Parses a `file:line:column` location.
+fn parse_location(location: &str) -> Option<(String, u32, u32)> {
+    let (rest, column) = location.rsplit_once(':')?;
+    let (file, line) = rest.rsplit_once(':')?;
+    Some((file.to_string(), line.parse().ok()?, column.parse().ok()?))
+}
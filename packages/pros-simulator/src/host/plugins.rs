@@ -0,0 +1,159 @@
+//! Simulated devices and other host functions loaded from WebAssembly modules when the
+//! simulation starts, so they can be distributed without recompiling the simulator. See
+//! [`SimulatorOptions::plugin`](crate::SimulatorOptions::plugin).
+//!
+//! A plugin is a core WebAssembly module that doesn't import anything. Every function it
+//! exports is linked into robot code as an import from `env`, which is where C and Rust code
+//! imports `extern` functions from, so robot code calls it like any other API. A plugin can't
+//! replace an API the simulator already provides.
+//!
+//! Each plugin is instantiated once and shared by every task, so a device's state lives in
+//! the plugin's own memory and globals, and calls into it are made one at a time. Plugins
+//! can't see robot code memory, so their functions can only take and return numbers.
+//!
+//! Plugins run under the same limits as robot code: their memory can't grow past
+//! [`SimulatorOptions::max_memory`](crate::SimulatorOptions::max_memory), and one that never
+//! returns is stopped by the simulation's timeout.
+
+use std::{path::Path, sync::Arc};
+
+use anyhow::{bail, Context};
+use futures::executor::block_on;
+use tokio::sync::Mutex;
+use wasmtime::{Engine, ExternType, Func, FuncType, Linker, Module, Store, ValType};
+
+use super::{limits::Limits, Host, HostCtx};
+
+/// The plugins a simulation was started with.
+#[derive(Default)]
+pub struct Plugins {
+    functions: Vec<PluginFunction>,
+}
+
+/// A function exported by a plugin.
+struct PluginFunction {
+    plugin: Arc<Plugin>,
+    name: String,
+    func: Func,
+    ty: FuncType,
+}
+
+struct Plugin {
+    /// The plugin's file name, without the extension.
+    name: String,
+    store: Mutex<Store<Limits>>,
+}
+
+impl Plugins {
+    /// Compiles and instantiates the plugins at `paths` under the same `limits` as robot code,
+    /// returning an error if one can't be read or doesn't meet the requirements of a plugin.
+    pub fn load(
+        engine: &Engine,
+        paths: &[impl AsRef<Path>],
+        limits: &Limits,
+    ) -> anyhow::Result<Self> {
+        let mut functions = Vec::new();
+        for path in paths {
+            let path = path.as_ref();
+            let name = path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned();
+            let module = Module::from_file(engine, path)
+                .with_context(|| format!("Couldn't load the `{name}` plugin"))?;
+            if let Some(import) = module.imports().next() {
+                bail!(
+                    "The `{name}` plugin imports `{}::{}`, but plugins can't import anything",
+                    import.module(),
+                    import.name()
+                );
+            }
+
+            let mut store = Store::new(engine, limits.clone());
+            if limits.limits_memory() {
+                store.limiter(|limits| limits);
+            }
+            // the epoch is incremented every tick, so the scheduler gets a chance to stop the
+            // simulation once its deadline has passed, even if the plugin never returns
+            store.epoch_deadline_async_yield_and_update(1);
+            let instance = block_on(Linker::new(engine).instantiate_async(&mut store, &module))
+                .with_context(|| format!("Couldn't start the `{name}` plugin"))?;
+
+            let mut exports = Vec::new();
+            for export in module.exports() {
+                let ExternType::Func(ty) = export.ty() else {
+                    continue;
+                };
+                let numeric = |ty: ValType| {
+                    matches!(
+                        ty,
+                        ValType::I32 | ValType::I64 | ValType::F32 | ValType::F64
+                    )
+                };
+                if !ty.params().chain(ty.results()).all(numeric) {
+                    bail!(
+                        "The `{name}` plugin's `{}` function takes or returns something other \
+                         than a number",
+                        export.name()
+                    );
+                }
+                let func = instance
+                    .get_func(&mut store, export.name())
+                    .expect("exported functions exist");
+                exports.push((export.name().to_string(), func, ty));
+            }
+
+            let plugin = Arc::new(Plugin {
+                name,
+                store: Mutex::new(store),
+            });
+            functions.extend(exports.into_iter().map(|(name, func, ty)| PluginFunction {
+                plugin: plugin.clone(),
+                name,
+                func,
+                ty,
+            }));
+        }
+        Ok(Self { functions })
+    }
+
+    /// Links every plugin function into robot code as an import from `env`, after the
+    /// simulator's own API.
+    pub fn link(&self, linker: &mut Linker<Host>, store: &mut Store<Host>) -> anyhow::Result<()> {
+        for function in &self.functions {
+            let PluginFunction {
+                plugin,
+                name,
+                func,
+                ty,
+            } = function;
+            if linker.get(&mut *store, "env", name).is_some() {
+                bail!(
+                    "The `{}` plugin exports `{name}`, which the simulator or another plugin already provides",
+                    plugin.name
+                );
+            }
+            let (plugin, name, func) = (plugin.clone(), name.clone(), *func);
+            linker.func_new_async(
+                "env",
+                &name.clone(),
+                ty.clone(),
+                move |caller, params, results| {
+                    let plugin = plugin.clone();
+                    let name = name.clone();
+                    Box::new(async move {
+                        caller.api_usage().record(&name);
+                        let mut store = plugin.store.lock().await;
+                        func.call_async(&mut *store, params, results)
+                            .await
+                            .with_context(|| {
+                                format!("The `{}` plugin failed in `{name}`", plugin.name)
+                            })
+                    })
+                },
+            )?;
+        }
+        Ok(())
+    }
+}
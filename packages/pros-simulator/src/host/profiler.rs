@@ -0,0 +1,58 @@
+//! A sampling profiler for robot code. See
+//! [`SimulatorOptions::profile`](crate::SimulatorOptions::profile).
+
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use wasmtime::{AsContext, WasmBacktrace};
+
+/// Counts how many times each robot code stack was seen while sampling.
+///
+/// Samples are taken whenever the engine's epoch is incremented, which happens every
+/// [tick](crate::host::TICK_PERIOD_MS). Time spent in host functions (like waiting in `delay`)
+/// isn't sampled, so the profile only shows where the robot code itself is busy.
+#[derive(Debug, Clone, Default)]
+pub struct Profiler {
+    /// Sample counts keyed by stack, outermost function first and separated by `;`.
+    stacks: Arc<Mutex<BTreeMap<String, u64>>>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the robot code's current stack.
+    pub fn sample(&self, store: impl AsContext) {
+        let backtrace = WasmBacktrace::force_capture(store);
+        let frames = backtrace.frames();
+        if frames.is_empty() {
+            return;
+        }
+        let stack = frames
+            .iter()
+            .rev()
+            .map(|frame| match frame.func_name() {
+                // `;` separates frames in the folded format
+                Some(name) => name.replace(';', ":"),
+                None => format!("wasm-function[{}]", frame.func_index()),
+            })
+            .collect::<Vec<_>>()
+            .join(";");
+        *self.stacks.lock().unwrap().entry(stack).or_default() += 1;
+    }
+
+    /// Writes the samples in the folded stack format used by `flamegraph.pl` and `inferno`, with
+    /// one `stack count` line per stack.
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let mut file = io::BufWriter::new(std::fs::File::create(path)?);
+        for (stack, count) in self.stacks.lock().unwrap().iter() {
+            writeln!(file, "{stack} {count}")?;
+        }
+        file.flush()
+    }
+}
@@ -0,0 +1,88 @@
+//! Reading what a robot program says about itself. See
+//! [`ProgramInfo`](pros_simulator_interface::ProgramInfo).
+
+use pros_simulator_interface::{ProgramAbi, ProgramInfo};
+use wasmparser::{Name, NameSectionReader, Parser, Payload};
+
+/// The custom section robot code describes itself in, with one `key=value` pair per line. If
+/// several statics are placed in the section, the linker concatenates them.
+pub const PROGRAM_INFO_SECTION: &str = "pros_program";
+
+/// The program slots on a V5 brain.
+const SLOTS: std::ops::RangeInclusive<u8> = 1..=8;
+
+/// Reads the `pros_program` and `name` custom sections of the robot code, before it's
+/// compiled. The returned info is filled in with `abi` once that's known. Problems with the
+/// section are returned as warnings rather than errors, since the simulator can run the robot
+/// code without it.
+pub fn read_program_info(wasm: &[u8]) -> (ProgramInfo, Vec<String>) {
+    let mut info = ProgramInfo::new(ProgramAbi::Unknown);
+    let mut module_name = None;
+    let mut warnings = Vec::new();
+
+    for payload in Parser::new(0).parse_all(wasm) {
+        let Ok(Payload::CustomSection(section)) = payload else {
+            continue;
+        };
+        match section.name() {
+            PROGRAM_INFO_SECTION => {
+                let Ok(text) = std::str::from_utf8(section.data()) else {
+                    warnings.push(format!(
+                        "Ignoring the `{PROGRAM_INFO_SECTION}` section because it isn't UTF-8."
+                    ));
+                    continue;
+                };
+                parse_section(text, &mut info, &mut warnings);
+            }
+            "name" => {
+                let names = NameSectionReader::new(section.data(), section.data_offset());
+                module_name = names.into_iter().find_map(|name| match name {
+                    Ok(Name::Module { name, .. }) => Some(name.to_string()),
+                    _ => None,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    if info.name.is_none() {
+        info.name = module_name;
+    }
+    (info, warnings)
+}
+
+fn parse_section(text: &str, info: &mut ProgramInfo, warnings: &mut Vec<String>) {
+    // Statics are often padded with NUL bytes to a fixed size.
+    let lines = text
+        .split(['\n', '\0'])
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+    for line in lines {
+        let Some((key, value)) = line.split_once('=') else {
+            warnings.push(format!(
+                "Ignoring `{line}` in the `{PROGRAM_INFO_SECTION}` section, which isn't a \
+                 `key=value` pair."
+            ));
+            continue;
+        };
+        let value = value.trim().to_string();
+        match key.trim() {
+            "name" => info.name = Some(value),
+            "version" => info.version = Some(value),
+            "icon" => info.icon = Some(value),
+            "description" => info.description = Some(value),
+            "slot" => match value.parse() {
+                Ok(slot) if SLOTS.contains(&slot) => info.slot = Some(slot),
+                _ => warnings.push(format!(
+                    "Ignoring slot `{value}` in the `{PROGRAM_INFO_SECTION}` section, which \
+                     isn't between {} and {}.",
+                    SLOTS.start(),
+                    SLOTS.end()
+                )),
+            },
+            key => warnings.push(format!(
+                "Ignoring unknown key `{key}` in the `{PROGRAM_INFO_SECTION}` section."
+            )),
+        }
+    }
+}
@@ -0,0 +1,387 @@
+//! Draws the field, the robot and the game objects on it into image files, so CI can keep a
+//! picture of each run without a frontend. Only built with the `render` feature. See
+//! [`SimulatorOptions::render_frames`](crate::SimulatorOptions::render_frames).
+//!
+//! The field is drawn as a 12 foot square centered on the origin, with +y at the top of the
+//! image, at 4 pixels per inch. Frames are taken at a fixed rate of simulated time, and drawn
+//! frames are encoded and written on a thread of their own so the simulation doesn't wait for
+//! them.
+
+mod jpeg;
+mod png;
+
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Receiver, SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+
+use pros_simulator_interface::{GameObject, Pose, ScoringZone, ZoneShape};
+
+/// Where rendered frames are written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrameOutput {
+    /// A directory that each frame is written to as a PNG, named `frame-000001.png` and so on.
+    /// The directory is created if it doesn't exist.
+    Png(PathBuf),
+    /// A file that frames are written to one after the other as JPEGs, which is a Motion JPEG
+    /// video that tools like `ffmpeg` and VLC can play.
+    Mjpeg(PathBuf),
+}
+
+/// How long the field is on each side, in inches.
+const FIELD_SIZE: f64 = 144.0;
+
+/// How many pixels each inch of the field takes up.
+const PIXELS_PER_INCH: f64 = 4.0;
+
+/// How many inches apart the lines between the field's tiles are.
+const TILE_SIZE: f64 = 24.0;
+
+/// The most drawn frames kept while waiting to be written. Any more are dropped, so a slow disk
+/// doesn't hold up the simulation.
+const MAX_PENDING_FRAMES: usize = 64;
+
+/// The quality MJPEG frames are encoded at, from 1 to 100.
+const JPEG_QUALITY: u8 = 90;
+
+type Rgb = [u8; 3];
+
+const FIELD_COLOR: Rgb = [88, 88, 92];
+const TILE_LINE_COLOR: Rgb = [110, 110, 116];
+const ZONE_COLOR: Rgb = [230, 200, 60];
+const SCORED_ZONE_COLOR: Rgb = [90, 210, 90];
+const ROBOT_COLOR: Rgb = [50, 110, 220];
+const HEADING_COLOR: Rgb = [255, 255, 255];
+const OBJECT_COLORS: [Rgb; 6] = [
+    [220, 60, 60],
+    [240, 150, 40],
+    [170, 80, 200],
+    [40, 190, 190],
+    [230, 110, 170],
+    [150, 200, 60],
+];
+
+/// What's on the field when a frame is drawn.
+pub struct Scene<'a> {
+    /// Where the robot is, or `None` if it hasn't been placed, in which case it isn't drawn.
+    pub pose: Option<Pose>,
+    pub robot_radius: f64,
+    pub objects: &'a [GameObject],
+    /// Each scoring zone and whether its points are currently scored.
+    pub zones: Vec<(&'a ScoringZone, bool)>,
+}
+
+/// Draws frames of the field at a fixed rate and writes them out.
+#[derive(Clone)]
+pub struct FrameRenderer {
+    inner: Arc<Mutex<Renderer>>,
+}
+
+struct Renderer {
+    hz: u32,
+    /// The number of the next frame to draw, counting from 0 at the start of the simulation.
+    next_frame: u64,
+    /// `None` once the renderer has finished.
+    frames: Option<SyncSender<Frame>>,
+    writer: Option<JoinHandle<io::Result<()>>>,
+    output: FrameOutput,
+    dropped: u64,
+}
+
+/// A drawn frame, which is written `count` times to keep the output in step with simulated time
+/// when frames were skipped.
+struct Frame {
+    canvas: Canvas,
+    count: u64,
+}
+
+impl FrameRenderer {
+    /// Prepares to write `hz` frames per second of simulated time to `output`.
+    pub fn new(output: FrameOutput, hz: u32) -> io::Result<Self> {
+        let sink = match &output {
+            FrameOutput::Png(dir) => {
+                fs::create_dir_all(dir)?;
+                Sink::Png {
+                    dir: dir.clone(),
+                    written: 0,
+                }
+            }
+            FrameOutput::Mjpeg(path) => Sink::Mjpeg(BufWriter::new(File::create(path)?)),
+        };
+        let (frames, rx) = mpsc::sync_channel(MAX_PENDING_FRAMES);
+        let writer = thread::Builder::new()
+            .name("pros-simulator render".into())
+            .spawn(move || write_frames(rx, sink))?;
+        Ok(Self {
+            inner: Arc::new(Mutex::new(Renderer {
+                hz,
+                next_frame: 0,
+                frames: Some(frames),
+                writer: Some(writer),
+                output,
+                dropped: 0,
+            })),
+        })
+    }
+
+    /// Whether a frame is due at `millis` of simulated time.
+    pub fn frame_due(&self, millis: u64) -> bool {
+        let inner = self.inner.lock().unwrap();
+        inner.frames.is_some() && millis * inner.hz as u64 >= inner.next_frame * 1000
+    }
+
+    /// Draws the scene as the frame for `millis` of simulated time, standing in for any frames
+    /// since the last one that were missed.
+    pub fn render(&self, millis: u64, scene: &Scene) {
+        let mut inner = self.inner.lock().unwrap();
+        let Some(frames) = &inner.frames else {
+            return;
+        };
+        let next_frame = millis * inner.hz as u64 / 1000 + 1;
+        let frame = Frame {
+            canvas: draw(scene),
+            count: next_frame.saturating_sub(inner.next_frame).max(1),
+        };
+        if let Err(TrySendError::Full(frame)) = frames.try_send(frame) {
+            inner.dropped += frame.count;
+        }
+        inner.next_frame = next_frame;
+    }
+
+    /// Waits for every frame to be written. Returns why they couldn't all be written, if they
+    /// couldn't.
+    pub fn finish(&self) -> Result<(), String> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.frames = None;
+        let Some(writer) = inner.writer.take() else {
+            return Ok(());
+        };
+        let path = match &inner.output {
+            FrameOutput::Png(path) | FrameOutput::Mjpeg(path) => path.display().to_string(),
+        };
+        match writer.join() {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => {
+                return Err(format!("Failed to write rendered frames to {path}: {err}"))
+            }
+            Err(_) => return Err(format!("Failed to write rendered frames to {path}")),
+        }
+        match inner.dropped {
+            0 => Ok(()),
+            dropped => Err(format!(
+                "{dropped} rendered frames were dropped because they couldn't be written to \
+                 {path} fast enough"
+            )),
+        }
+    }
+}
+
+enum Sink {
+    Png { dir: PathBuf, written: u64 },
+    Mjpeg(BufWriter<File>),
+}
+
+fn write_frames(frames: Receiver<Frame>, mut sink: Sink) -> io::Result<()> {
+    for Frame { canvas, count } in frames {
+        match &mut sink {
+            Sink::Png { dir, written } => {
+                let image = png::encode(canvas.width, canvas.height, &canvas.pixels);
+                for _ in 0..count {
+                    *written += 1;
+                    fs::write(dir.join(format!("frame-{written:06}.png")), &image)?;
+                }
+            }
+            Sink::Mjpeg(file) => {
+                let image = jpeg::encode(canvas.width, canvas.height, &canvas.pixels, JPEG_QUALITY);
+                for _ in 0..count {
+                    file.write_all(&image)?;
+                }
+            }
+        }
+    }
+    if let Sink::Mjpeg(file) = &mut sink {
+        file.flush()?;
+    }
+    Ok(())
+}
+
+/// Draws a frame of the scene.
+fn draw(scene: &Scene) -> Canvas {
+    let size = (FIELD_SIZE * PIXELS_PER_INCH) as usize;
+    let mut canvas = Canvas::new(size, size, FIELD_COLOR);
+
+    let tiles = (FIELD_SIZE / TILE_SIZE) as usize;
+    for tile in 1..tiles {
+        let offset = tile as f64 * TILE_SIZE - FIELD_SIZE / 2.0;
+        canvas.line(
+            (offset, -FIELD_SIZE),
+            (offset, FIELD_SIZE),
+            0.25,
+            TILE_LINE_COLOR,
+        );
+        canvas.line(
+            (-FIELD_SIZE, offset),
+            (FIELD_SIZE, offset),
+            0.25,
+            TILE_LINE_COLOR,
+        );
+    }
+
+    for (zone, scored) in &scene.zones {
+        let color = if *scored {
+            SCORED_ZONE_COLOR
+        } else {
+            ZONE_COLOR
+        };
+        match zone.shape {
+            ZoneShape::Rectangle {
+                min_x,
+                min_y,
+                max_x,
+                max_y,
+            } => canvas.rectangle((min_x, min_y), (max_x, max_y), color, 0.4),
+            ZoneShape::Circle { x, y, radius } => canvas.circle((x, y), radius, color, 0.4),
+        }
+    }
+
+    // held objects are drawn on top of the robot
+    let (held, loose) = scene
+        .objects
+        .iter()
+        .partition::<Vec<_>, _>(|object| object.held_by.is_some());
+    for object in loose {
+        draw_object(&mut canvas, object);
+    }
+    if let Some(pose) = scene.pose {
+        let center = (pose.x, pose.y);
+        canvas.circle(center, scene.robot_radius, ROBOT_COLOR, 1.0);
+        let heading = pose.heading.to_radians();
+        let front = (
+            pose.x + heading.sin() * scene.robot_radius,
+            pose.y + heading.cos() * scene.robot_radius,
+        );
+        canvas.line(center, front, 0.75, HEADING_COLOR);
+    }
+    for object in held {
+        draw_object(&mut canvas, object);
+    }
+
+    canvas
+}
+
+/// Draws an object in a color picked by its kind, so objects of the same kind match. Fixed
+/// objects are drawn as rings.
+fn draw_object(canvas: &mut Canvas, object: &GameObject) {
+    let kind = object.kind.bytes().fold(0usize, |hash, byte| {
+        hash.wrapping_mul(31).wrapping_add(byte as usize)
+    });
+    let color = OBJECT_COLORS[kind % OBJECT_COLORS.len()];
+    let center = (object.x, object.y);
+    canvas.circle(center, object.radius, color, 1.0);
+    if object.fixed {
+        canvas.circle(center, object.radius * 0.6, FIELD_COLOR, 1.0);
+    }
+}
+
+/// An RGB image that shapes are drawn onto in field coordinates. Shape edges are antialiased by
+/// how much of each pixel they cover.
+struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize, background: Rgb) -> Self {
+        Self {
+            width,
+            height,
+            pixels: background.repeat(width * height),
+        }
+    }
+
+    /// The pixel coordinates of a point on the field.
+    fn to_pixels(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        (
+            x * PIXELS_PER_INCH + self.width as f64 / 2.0,
+            self.height as f64 / 2.0 - y * PIXELS_PER_INCH,
+        )
+    }
+
+    /// Blends `color` into the pixels around the field points `a` and `b`, out to `margin`
+    /// pixels past them, by how much of each pixel `coverage` says the shape covers given the
+    /// pixel's center.
+    fn fill(
+        &mut self,
+        a: (f64, f64),
+        b: (f64, f64),
+        margin: f64,
+        color: Rgb,
+        coverage: impl Fn(f64, f64) -> f64,
+    ) {
+        let (ax, ay) = self.to_pixels(a);
+        let (bx, by) = self.to_pixels(b);
+        let clamp = |value: f64, max: usize| value.clamp(0.0, max as f64) as usize;
+        let (min_x, max_x) = (
+            clamp(ax.min(bx) - margin, self.width),
+            clamp((ax.max(bx) + margin).ceil(), self.width),
+        );
+        let (min_y, max_y) = (
+            clamp(ay.min(by) - margin, self.height),
+            clamp((ay.max(by) + margin).ceil(), self.height),
+        );
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let alpha = coverage(x as f64 + 0.5, y as f64 + 0.5).clamp(0.0, 1.0);
+                if alpha <= 0.0 {
+                    continue;
+                }
+                let pixel = &mut self.pixels[(y * self.width + x) * 3..][..3];
+                for (channel, target) in pixel.iter_mut().zip(color) {
+                    *channel =
+                        (*channel as f64 * (1.0 - alpha) + target as f64 * alpha).round() as u8;
+                }
+            }
+        }
+    }
+
+    fn circle(&mut self, center: (f64, f64), radius: f64, color: Rgb, opacity: f64) {
+        let (cx, cy) = self.to_pixels(center);
+        let radius = radius * PIXELS_PER_INCH;
+        self.fill(center, center, radius + 1.0, color, |x, y| {
+            (radius - (x - cx).hypot(y - cy) + 0.5).clamp(0.0, 1.0) * opacity
+        });
+    }
+
+    fn rectangle(&mut self, min: (f64, f64), max: (f64, f64), color: Rgb, opacity: f64) {
+        let (ax, ay) = self.to_pixels(min);
+        let (bx, by) = self.to_pixels(max);
+        let (left, right) = (ax.min(bx), ax.max(bx));
+        let (top, bottom) = (ay.min(by), ay.max(by));
+        self.fill(min, max, 1.0, color, |x, y| {
+            let horizontal = (x - left + 0.5).min(right - x + 0.5).clamp(0.0, 1.0);
+            let vertical = (y - top + 0.5).min(bottom - y + 0.5).clamp(0.0, 1.0);
+            horizontal * vertical * opacity
+        });
+    }
+
+    /// Draws a line `width` inches wide.
+    fn line(&mut self, a: (f64, f64), b: (f64, f64), width: f64, color: Rgb) {
+        let (ax, ay) = self.to_pixels(a);
+        let (bx, by) = self.to_pixels(b);
+        let half_width = width * PIXELS_PER_INCH / 2.0;
+        let (dx, dy) = (bx - ax, by - ay);
+        let len_squared = (dx * dx + dy * dy).max(f64::EPSILON);
+        self.fill(a, b, half_width + 1.0, color, |x, y| {
+            let t = (((x - ax) * dx + (y - ay) * dy) / len_squared).clamp(0.0, 1.0);
+            let distance = (x - ax - t * dx).hypot(y - ay - t * dy);
+            half_width - distance + 0.5
+        });
+    }
+}
@@ -0,0 +1,309 @@
+//! Encodes frames as baseline JPEGs with the example quantization and Huffman tables from the
+//! JPEG standard (ITU T.81, Annex K), which is all a Motion JPEG player needs.
+
+use std::f64::consts::PI;
+
+/// The order coefficients of a block are written in, as indices into the block.
+const ZIGZAG: [usize; 64] = [
+    0, 1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5, 12, 19, 26, 33, 40, 48, 41, 34, 27, 20,
+    13, 6, 7, 14, 21, 28, 35, 42, 49, 56, 57, 50, 43, 36, 29, 22, 15, 23, 30, 37, 44, 51, 58, 59,
+    52, 45, 38, 31, 39, 46, 53, 60, 61, 54, 47, 55, 62, 63,
+];
+
+const LUMA_QUANTIZATION: [u16; 64] = [
+    16, 11, 10, 16, 24, 40, 51, 61, 12, 12, 14, 19, 26, 58, 60, 55, 14, 13, 16, 24, 40, 57, 69, 56,
+    14, 17, 22, 29, 51, 87, 80, 62, 18, 22, 37, 56, 68, 109, 103, 77, 24, 35, 55, 64, 81, 104, 113,
+    92, 49, 64, 78, 87, 103, 121, 120, 101, 72, 92, 95, 98, 112, 100, 103, 99,
+];
+
+const CHROMA_QUANTIZATION: [u16; 64] = [
+    17, 18, 24, 47, 99, 99, 99, 99, 18, 21, 26, 66, 99, 99, 99, 99, 24, 26, 56, 99, 99, 99, 99, 99,
+    47, 66, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99,
+];
+
+/// A Huffman table as it's written in a JPEG: how many codes there are of each length from 1
+/// to 16 bits, then the values they stand for from the shortest code to the longest.
+struct HuffmanSpec {
+    counts: [u8; 16],
+    values: &'static [u8],
+}
+
+const LUMA_DC: HuffmanSpec = HuffmanSpec {
+    counts: [0, 1, 5, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0],
+    values: &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+};
+
+const CHROMA_DC: HuffmanSpec = HuffmanSpec {
+    counts: [0, 3, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0],
+    values: &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+};
+
+const LUMA_AC: HuffmanSpec = HuffmanSpec {
+    counts: [0, 2, 1, 3, 3, 2, 4, 3, 5, 5, 4, 4, 0, 0, 1, 0x7d],
+    values: &[
+        0x01, 0x02, 0x03, 0x00, 0x04, 0x11, 0x05, 0x12, 0x21, 0x31, 0x41, 0x06, 0x13, 0x51, 0x61,
+        0x07, 0x22, 0x71, 0x14, 0x32, 0x81, 0x91, 0xa1, 0x08, 0x23, 0x42, 0xb1, 0xc1, 0x15, 0x52,
+        0xd1, 0xf0, 0x24, 0x33, 0x62, 0x72, 0x82, 0x09, 0x0a, 0x16, 0x17, 0x18, 0x19, 0x1a, 0x25,
+        0x26, 0x27, 0x28, 0x29, 0x2a, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3a, 0x43, 0x44, 0x45,
+        0x46, 0x47, 0x48, 0x49, 0x4a, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x63, 0x64,
+        0x65, 0x66, 0x67, 0x68, 0x69, 0x6a, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7a, 0x83,
+        0x84, 0x85, 0x86, 0x87, 0x88, 0x89, 0x8a, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99,
+        0x9a, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6, 0xa7, 0xa8, 0xa9, 0xaa, 0xb2, 0xb3, 0xb4, 0xb5, 0xb6,
+        0xb7, 0xb8, 0xb9, 0xba, 0xc2, 0xc3, 0xc4, 0xc5, 0xc6, 0xc7, 0xc8, 0xc9, 0xca, 0xd2, 0xd3,
+        0xd4, 0xd5, 0xd6, 0xd7, 0xd8, 0xd9, 0xda, 0xe1, 0xe2, 0xe3, 0xe4, 0xe5, 0xe6, 0xe7, 0xe8,
+        0xe9, 0xea, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9, 0xfa,
+    ],
+};
+
+const CHROMA_AC: HuffmanSpec = HuffmanSpec {
+    counts: [0, 2, 1, 2, 4, 4, 3, 4, 7, 5, 4, 4, 0, 1, 2, 0x77],
+    values: &[
+        0x00, 0x01, 0x02, 0x03, 0x11, 0x04, 0x05, 0x21, 0x31, 0x06, 0x12, 0x41, 0x51, 0x07, 0x61,
+        0x71, 0x13, 0x22, 0x32, 0x81, 0x08, 0x14, 0x42, 0x91, 0xa1, 0xb1, 0xc1, 0x09, 0x23, 0x33,
+        0x52, 0xf0, 0x15, 0x62, 0x72, 0xd1, 0x0a, 0x16, 0x24, 0x34, 0xe1, 0x25, 0xf1, 0x17, 0x18,
+        0x19, 0x1a, 0x26, 0x27, 0x28, 0x29, 0x2a, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3a, 0x43, 0x44,
+        0x45, 0x46, 0x47, 0x48, 0x49, 0x4a, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x63,
+        0x64, 0x65, 0x66, 0x67, 0x68, 0x69, 0x6a, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7a,
+        0x82, 0x83, 0x84, 0x85, 0x86, 0x87, 0x88, 0x89, 0x8a, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97,
+        0x98, 0x99, 0x9a, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6, 0xa7, 0xa8, 0xa9, 0xaa, 0xb2, 0xb3, 0xb4,
+        0xb5, 0xb6, 0xb7, 0xb8, 0xb9, 0xba, 0xc2, 0xc3, 0xc4, 0xc5, 0xc6, 0xc7, 0xc8, 0xc9, 0xca,
+        0xd2, 0xd3, 0xd4, 0xd5, 0xd6, 0xd7, 0xd8, 0xd9, 0xda, 0xe2, 0xe3, 0xe4, 0xe5, 0xe6, 0xe7,
+        0xe8, 0xe9, 0xea, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9, 0xfa,
+    ],
+};
+
+/// Encodes an image with 3 bytes of RGB per pixel, row by row from the top, at a quality from 1
+/// to 100.
+pub fn encode(width: usize, height: usize, pixels: &[u8], quality: u8) -> Vec<u8> {
+    let tables = [
+        scale_quantization(&LUMA_QUANTIZATION, quality),
+        scale_quantization(&CHROMA_QUANTIZATION, quality),
+    ];
+
+    let mut jpeg = vec![0xff, 0xd8];
+    // JFIF header with a 1:1 pixel aspect ratio
+    segment(&mut jpeg, 0xe0, b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0");
+
+    let mut quantization = Vec::new();
+    for (id, table) in tables.iter().enumerate() {
+        quantization.push(id as u8);
+        quantization.extend(ZIGZAG.map(|index| table[index] as u8));
+    }
+    segment(&mut jpeg, 0xdb, &quantization);
+
+    let mut frame = vec![8];
+    frame.extend((height as u16).to_be_bytes());
+    frame.extend((width as u16).to_be_bytes());
+    // Y, Cb and Cr at full resolution, with the chroma components sharing a table
+    frame.extend([3, 1, 0x11, 0, 2, 0x11, 1, 3, 0x11, 1]);
+    segment(&mut jpeg, 0xc0, &frame);
+
+    let mut huffman = Vec::new();
+    for (class_and_id, spec) in [
+        (0x00, &LUMA_DC),
+        (0x10, &LUMA_AC),
+        (0x01, &CHROMA_DC),
+        (0x11, &CHROMA_AC),
+    ] {
+        huffman.push(class_and_id);
+        huffman.extend(spec.counts);
+        huffman.extend(spec.values);
+    }
+    segment(&mut jpeg, 0xc4, &huffman);
+
+    segment(&mut jpeg, 0xda, &[3, 1, 0x00, 2, 0x11, 3, 0x11, 0, 63, 0]);
+
+    let codes = [
+        (HuffmanCodes::new(&LUMA_DC), HuffmanCodes::new(&LUMA_AC)),
+        (HuffmanCodes::new(&CHROMA_DC), HuffmanCodes::new(&CHROMA_AC)),
+    ];
+    let mut bits = BitWriter::new(jpeg);
+    let mut previous_dc = [0; 3];
+    let mut block = [[0.0; 64]; 3];
+    for block_y in (0..height).step_by(8) {
+        for block_x in (0..width).step_by(8) {
+            for (index, samples) in (0..64).map(|index| {
+                // blocks that run off the edge repeat the last row or column
+                let x = (block_x + index % 8).min(width - 1);
+                let y = (block_y + index / 8).min(height - 1);
+                (index, &pixels[(y * width + x) * 3..][..3])
+            }) {
+                let [r, g, b] = [0, 1, 2].map(|channel| samples[channel] as f64);
+                block[0][index] = 0.299 * r + 0.587 * g + 0.114 * b - 128.0;
+                block[1][index] = -0.168736 * r - 0.331264 * g + 0.5 * b;
+                block[2][index] = 0.5 * r - 0.418688 * g - 0.081312 * b;
+            }
+            for component in 0..3 {
+                let table = usize::from(component > 0);
+                let coefficients = quantize(&dct(&block[component]), &tables[table]);
+                let (dc, ac) = &codes[table];
+                encode_block(
+                    &mut bits,
+                    &coefficients,
+                    &mut previous_dc[component],
+                    dc,
+                    ac,
+                );
+            }
+        }
+    }
+
+    let mut jpeg = bits.finish();
+    jpeg.extend([0xff, 0xd9]);
+    jpeg
+}
+
+/// Writes a marker segment, whose length includes the length itself.
+fn segment(jpeg: &mut Vec<u8>, marker: u8, data: &[u8]) {
+    jpeg.extend([0xff, marker]);
+    jpeg.extend((data.len() as u16 + 2).to_be_bytes());
+    jpeg.extend(data);
+}
+
+/// Scales a quantization table the way libjpeg does for a quality setting.
+fn scale_quantization(table: &[u16; 64], quality: u8) -> [u16; 64] {
+    let quality = quality.clamp(1, 100) as u32;
+    let scale = if quality < 50 {
+        5000 / quality
+    } else {
+        200 - quality * 2
+    };
+    table.map(|value| ((value as u32 * scale + 50) / 100).clamp(1, 255) as u16)
+}
+
+/// The 2D discrete cosine transform of a block of samples.
+fn dct(block: &[f64; 64]) -> [f64; 64] {
+    let cosines: [[f64; 8]; 8] = std::array::from_fn(|x| {
+        std::array::from_fn(|u| ((2 * x + 1) as f64 * u as f64 * PI / 16.0).cos())
+    });
+    let scale = |u: usize| if u == 0 { 0.5 / 2f64.sqrt() } else { 0.5 };
+
+    // transform the rows, then the columns
+    let mut rows = [0.0; 64];
+    for y in 0..8 {
+        for u in 0..8 {
+            let sum: f64 = (0..8).map(|x| block[y * 8 + x] * cosines[x][u]).sum();
+            rows[y * 8 + u] = scale(u) * sum;
+        }
+    }
+    let mut coefficients = [0.0; 64];
+    for u in 0..8 {
+        for v in 0..8 {
+            let sum: f64 = (0..8).map(|y| rows[y * 8 + u] * cosines[y][v]).sum();
+            coefficients[v * 8 + u] = scale(v) * sum;
+        }
+    }
+    coefficients
+}
+
+/// Divides each coefficient by its quantizer, in zigzag order.
+fn quantize(coefficients: &[f64; 64], table: &[u16; 64]) -> [i32; 64] {
+    ZIGZAG.map(|index| (coefficients[index] / table[index] as f64).round() as i32)
+}
+
+/// The code and its length in bits for each value of a Huffman table.
+struct HuffmanCodes([(u16, u8); 256]);
+
+impl HuffmanCodes {
+    fn new(spec: &HuffmanSpec) -> Self {
+        let mut codes = [(0, 0); 256];
+        let mut values = spec.values.iter();
+        let mut code = 0u16;
+        for (len, count) in spec.counts.iter().enumerate() {
+            for value in values.by_ref().take(*count as usize) {
+                codes[*value as usize] = (code, len as u8 + 1);
+                code += 1;
+            }
+            code <<= 1;
+        }
+        Self(codes)
+    }
+
+    fn write(&self, bits: &mut BitWriter, value: u8) {
+        let (code, len) = self.0[value as usize];
+        bits.write(code as u32, len);
+    }
+}
+
+/// How many bits a coefficient takes, and the bits themselves. Negative numbers are written as
+/// one less than themselves, so their top bit is 0.
+fn magnitude(value: i32) -> (u8, u32) {
+    let size = 32 - value.unsigned_abs().leading_zeros();
+    let bits = if value < 0 { value - 1 } else { value };
+    (size as u8, bits as u32 & ((1 << size) - 1))
+}
+
+fn encode_block(
+    bits: &mut BitWriter,
+    coefficients: &[i32; 64],
+    previous_dc: &mut i32,
+    dc: &HuffmanCodes,
+    ac: &HuffmanCodes,
+) {
+    let (size, value) = magnitude(coefficients[0] - *previous_dc);
+    *previous_dc = coefficients[0];
+    dc.write(bits, size);
+    bits.write(value, size);
+
+    let mut zeros = 0;
+    for &coefficient in &coefficients[1..] {
+        if coefficient == 0 {
+            zeros += 1;
+            continue;
+        }
+        while zeros > 15 {
+            // sixteen zeros
+            ac.write(bits, 0xf0);
+            zeros -= 16;
+        }
+        let (size, value) = magnitude(coefficient);
+        ac.write(bits, (zeros << 4) | size);
+        bits.write(value, size);
+        zeros = 0;
+    }
+    if zeros > 0 {
+        // the rest of the block is zeros
+        ac.write(bits, 0x00);
+    }
+}
+
+/// Packs bits into bytes from the most significant bit down, following each `0xff` byte with a
+/// `0x00` so it isn't mistaken for a marker.
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    len: u8,
+}
+
+impl BitWriter {
+    fn new(bytes: Vec<u8>) -> Self {
+        Self {
+            bytes,
+            buffer: 0,
+            len: 0,
+        }
+    }
+
+    fn write(&mut self, bits: u32, len: u8) {
+        self.buffer = (self.buffer << len) | bits;
+        self.len += len;
+        while self.len >= 8 {
+            self.len -= 8;
+            let byte = (self.buffer >> self.len) as u8;
+            self.bytes.push(byte);
+            if byte == 0xff {
+                self.bytes.push(0);
+            }
+        }
+        self.buffer &= (1 << self.len) - 1;
+    }
+
+    /// Pads the last byte with 1s and returns the bytes.
+    fn finish(mut self) -> Vec<u8> {
+        if self.len > 0 {
+            self.write((1 << (8 - self.len)) - 1, 8 - self.len);
+        }
+        self.bytes
+    }
+}
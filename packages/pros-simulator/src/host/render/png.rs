@@ -0,0 +1,38 @@
+//! Encodes frames as 8-bit RGB PNGs.
+
+const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// How hard zlib tries to shrink each frame, from 0 to 10.
+const COMPRESSION_LEVEL: u8 = 6;
+
+/// Encodes an image with 3 bytes of RGB per pixel, row by row from the top.
+pub fn encode(width: usize, height: usize, pixels: &[u8]) -> Vec<u8> {
+    let mut header = Vec::with_capacity(13);
+    header.extend((width as u32).to_be_bytes());
+    header.extend((height as u32).to_be_bytes());
+    // 8 bits per channel, truecolor, deflate, adaptive filtering, no interlacing
+    header.extend([8, 2, 0, 0, 0]);
+
+    // each row starts with the filter it uses, which is always none
+    let mut rows = Vec::with_capacity((width * 3 + 1) * height);
+    for row in pixels.chunks(width * 3) {
+        rows.push(0);
+        rows.extend(row);
+    }
+    let data = miniz_oxide::deflate::compress_to_vec_zlib(&rows, COMPRESSION_LEVEL);
+
+    let mut png = SIGNATURE.to_vec();
+    chunk(&mut png, b"IHDR", &header);
+    chunk(&mut png, b"IDAT", &data);
+    chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend(kind);
+    png.extend(data);
+    let crc = crc32fast::hash(&png[start..]);
+    png.extend(crc.to_be_bytes());
+}
@@ -0,0 +1,272 @@
+//! The brain's screen, which robot code draws on through the VEX SDK's `vexDisplay*` functions,
+//! usually from an LVGL display driver. Drawn areas are sent to frontends as
+//! [`SimulatorEvent::ScreenUpdated`] regions.
+
+mod font;
+
+use pros_simulator_interface::{
+    char_width, ScreenRegion, SimulatorEvent, SCREEN_HEIGHT, SCREEN_WIDTH,
+};
+
+use super::TICK_PERIOD_MS;
+use crate::interface::SimulatorInterface;
+
+/// How often changes to the screen are sent, like the V5 screen's refresh rate.
+const REFRESH_PERIOD_MS: u64 = 16;
+
+/// How many times larger than the font text is drawn.
+const TEXT_SCALE: u32 = 2;
+/// Pixels across each column of text: a glyph and the space after it.
+pub const TEXT_COLUMN_WIDTH: u32 = (font::GLYPH_WIDTH + 1) * TEXT_SCALE;
+/// Pixels down a line of text.
+pub const TEXT_HEIGHT: u32 = font::GLYPH_HEIGHT * TEXT_SCALE;
+
+/// What `vexTouchDataGet` reports the last touch was.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u32)]
+pub enum TouchEvent {
+    #[default]
+    Release = 0,
+    Press = 1,
+    /// The screen is still being pressed after the press was reported.
+    PressAuto = 2,
+}
+
+/// The touchscreen's state, laid out like the SDK's `V5_TouchStatus`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TouchStatus {
+    pub last_event: TouchEvent,
+    pub x: i16,
+    pub y: i16,
+    pub press_count: i32,
+    pub release_count: i32,
+}
+
+impl TouchStatus {
+    /// The 16 bytes robot code reads: the event as a 32-bit enum, the position as two 16-bit
+    /// integers, then the press and release counts, all little endian.
+    pub fn to_bytes(self) -> [u8; 16] {
+        let mut bytes = [0; 16];
+        bytes[0..4].copy_from_slice(&(self.last_event as u32).to_le_bytes());
+        bytes[4..6].copy_from_slice(&self.x.to_le_bytes());
+        bytes[6..8].copy_from_slice(&self.y.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.press_count.to_le_bytes());
+        bytes[12..16].copy_from_slice(&self.release_count.to_le_bytes());
+        bytes
+    }
+}
+
+/// A rectangle of pixels, with inclusive corners like the SDK's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x1: u32,
+    pub y1: u32,
+    pub x2: u32,
+    pub y2: u32,
+}
+
+impl Rect {
+    /// The part of the rectangle from `(x1, y1)` to `(x2, y2)` that's on the screen, if any. The
+    /// corners can be given in either order.
+    pub fn clipped(x1: i32, y1: i32, x2: i32, y2: i32) -> Option<Self> {
+        let (x1, x2) = (x1.min(x2).max(0), x1.max(x2).min(SCREEN_WIDTH as i32 - 1));
+        let (y1, y2) = (y1.min(y2).max(0), y1.max(y2).min(SCREEN_HEIGHT as i32 - 1));
+        (x1 <= x2 && y1 <= y2).then_some(Self {
+            x1: x1 as u32,
+            y1: y1 as u32,
+            x2: x2 as u32,
+            y2: y2 as u32,
+        })
+    }
+
+    fn union(self, other: Self) -> Self {
+        Self {
+            x1: self.x1.min(other.x1),
+            y1: self.y1.min(other.y1),
+            x2: self.x2.max(other.x2),
+            y2: self.y2.max(other.y2),
+        }
+    }
+}
+
+pub struct Screen {
+    interface: SimulatorInterface,
+    /// Each pixel as `0x00RRGGBB`, row by row from the top.
+    pixels: Vec<u32>,
+    foreground: u32,
+    background: u32,
+    /// The area drawn on since changes were last sent.
+    dirty: Option<Rect>,
+    /// The tick changes were last sent at.
+    last_sent: Option<u64>,
+    /// Whether robot code has called `vexDisplayRender`, after which changes are only sent when
+    /// it calls it again.
+    double_buffered: bool,
+    touch: TouchStatus,
+}
+
+impl Screen {
+    pub fn new(interface: SimulatorInterface) -> Self {
+        Self {
+            interface,
+            pixels: vec![0; (SCREEN_WIDTH * SCREEN_HEIGHT) as usize],
+            foreground: 0xffffff,
+            background: 0x000000,
+            dirty: None,
+            last_sent: None,
+            double_buffered: false,
+            touch: TouchStatus::default(),
+        }
+    }
+
+    pub fn set_foreground(&mut self, color: u32) {
+        self.foreground = color & 0xffffff;
+    }
+
+    pub fn set_background(&mut self, color: u32) {
+        self.background = color & 0xffffff;
+    }
+
+    /// Fills the whole screen with the background color.
+    pub fn erase(&mut self) {
+        self.pixels.fill(self.background);
+        self.mark_dirty(Rect {
+            x1: 0,
+            y1: 0,
+            x2: SCREEN_WIDTH - 1,
+            y2: SCREEN_HEIGHT - 1,
+        });
+    }
+
+    /// Fills a rectangle with the foreground color. Parts off the screen are ignored.
+    pub fn fill_rect(&mut self, x1: i32, y1: i32, x2: i32, y2: i32) {
+        self.fill_rect_with(x1, y1, x2, y2, self.foreground);
+    }
+
+    /// Fills a rectangle with a color, leaving the foreground color robot code set alone.
+    pub fn fill_rect_with(&mut self, x1: i32, y1: i32, x2: i32, y2: i32, color: u32) {
+        let Some(rect) = Rect::clipped(x1, y1, x2, y2) else {
+            return;
+        };
+        for y in rect.y1..=rect.y2 {
+            let row = (y * SCREEN_WIDTH) as usize;
+            self.pixels[row + rect.x1 as usize..=row + rect.x2 as usize].fill(color & 0xffffff);
+        }
+        self.mark_dirty(rect);
+    }
+
+    /// Draws text with its top left corner at `(x, y)`, in columns [`TEXT_COLUMN_WIDTH`] pixels
+    /// wide. Wide characters take up two columns, and characters the simulator's font doesn't
+    /// have are drawn as boxes. Only the glyphs' pixels are drawn, so the text should go on a
+    /// filled background.
+    pub fn draw_text(&mut self, x: i32, y: i32, text: &str, color: u32) {
+        let mut left = x;
+        for char in text.chars() {
+            let columns = char_width(char) as i32;
+            if columns == 0 {
+                continue;
+            }
+            // wide characters are centered in their two columns
+            let offset = (columns - 1) * TEXT_COLUMN_WIDTH as i32 / 2;
+            for glyph_y in 0..font::GLYPH_HEIGHT {
+                for glyph_x in 0..font::GLYPH_WIDTH {
+                    if !font::pixel(char, glyph_x, glyph_y) {
+                        continue;
+                    }
+                    let px = left + offset + (glyph_x * TEXT_SCALE) as i32;
+                    let py = y + (glyph_y * TEXT_SCALE) as i32;
+                    let scale = TEXT_SCALE as i32;
+                    self.fill_rect_with(px, py, px + scale - 1, py + scale - 1, color);
+                }
+            }
+            left += columns * TEXT_COLUMN_WIDTH as i32;
+        }
+    }
+
+    /// Copies pixels into `rect` from `source`, which holds rows of `stride` pixels stored by
+    /// robot code as `0x00RRGGBB`, starting at the rectangle's top left corner.
+    pub fn copy_rect(&mut self, rect: Rect, source: &[u8], stride: usize) {
+        for y in rect.y1..=rect.y2 {
+            for x in rect.x1..=rect.x2 {
+                let index = (y - rect.y1) as usize * stride + (x - rect.x1) as usize;
+                let Some(pixel) = source.get(index * 4..index * 4 + 4) else {
+                    continue;
+                };
+                let pixel = u32::from_le_bytes(pixel.try_into().unwrap());
+                self.pixels[(y * SCREEN_WIDTH + x) as usize] = pixel & 0xffffff;
+            }
+        }
+        self.mark_dirty(rect);
+    }
+
+    fn mark_dirty(&mut self, rect: Rect) {
+        self.dirty = Some(match self.dirty {
+            Some(dirty) => dirty.union(rect),
+            None => rect,
+        });
+    }
+
+    /// Sends what's been drawn since the last update right away, and from now on only when this
+    /// is called, like VEXos's double buffering.
+    pub fn render(&mut self) {
+        self.double_buffered = true;
+        self.send_dirty();
+    }
+
+    /// Sends what's been drawn since the last update, if the screen is due to refresh.
+    pub fn tick(&mut self, tick: u64) {
+        if self.double_buffered || self.dirty.is_none() {
+            return;
+        }
+        if self
+            .last_sent
+            .is_some_and(|last| (tick - last) * TICK_PERIOD_MS < REFRESH_PERIOD_MS)
+        {
+            return;
+        }
+        self.last_sent = Some(tick);
+        self.send_dirty();
+    }
+
+    fn send_dirty(&mut self) {
+        let Some(rect) = self.dirty.take() else {
+            return;
+        };
+        let (width, height) = (rect.x2 - rect.x1 + 1, rect.y2 - rect.y1 + 1);
+        let mut rgb = Vec::with_capacity((width * height * 3) as usize);
+        for y in rect.y1..=rect.y2 {
+            let row = (y * SCREEN_WIDTH) as usize;
+            for pixel in &self.pixels[row + rect.x1 as usize..=row + rect.x2 as usize] {
+                rgb.extend(&pixel.to_be_bytes()[1..]);
+            }
+        }
+        self.interface
+            .send(SimulatorEvent::ScreenUpdated(ScreenRegion::new(
+                rect.x1, rect.y1, width, height, &rgb,
+            )));
+    }
+
+    /// Presses or releases the touchscreen. Positions off the screen are clamped to its edges.
+    pub fn touch(&mut self, x: i16, y: i16, pressed: bool) {
+        self.touch.x = x.clamp(0, SCREEN_WIDTH as i16 - 1);
+        self.touch.y = y.clamp(0, SCREEN_HEIGHT as i16 - 1);
+        let was_pressed = self.touch.last_event != TouchEvent::Release;
+        if pressed && !was_pressed {
+            self.touch.last_event = TouchEvent::Press;
+            self.touch.press_count += 1;
+        } else if !pressed && was_pressed {
+            self.touch.last_event = TouchEvent::Release;
+            self.touch.release_count += 1;
+        }
+    }
+
+    /// The touchscreen's state for `vexTouchDataGet`. A press is reported once, then as
+    /// [`TouchEvent::PressAuto`] while the screen is held.
+    pub fn touch_status(&mut self) -> TouchStatus {
+        let status = self.touch;
+        if status.last_event == TouchEvent::Press {
+            self.touch.last_event = TouchEvent::PressAuto;
+        }
+        status
+    }
+}
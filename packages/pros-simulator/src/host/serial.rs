@@ -0,0 +1,172 @@
+//! The serial connection console output is sent over.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use pros_simulator_interface::{ConsoleOutput, SimulatorEvent};
+
+use crate::interface::SimulatorInterface;
+
+/// How many bytes of console output can be waiting to be sent before writes block, matching the
+/// size of VEXos's serial transmit buffer.
+pub const SERIAL_BUFFER_SIZE: u32 = 2048;
+
+/// How many bytes of stdout newlib holds back before writing them when output is line buffered,
+/// matching its `BUFSIZ`.
+pub const LINE_BUFFER_SIZE: usize = 1024;
+
+/// Delivers console output as
+/// [`ConsoleMessage`](pros_simulator_interface::SimulatorEvent::ConsoleMessage) events, tagged
+/// with the tasks that wrote them.
+///
+/// By default, output is delivered as soon as it's written. If
+/// [`SimulatorOptions::throttle_serial`](crate::SimulatorOptions::throttle_serial) is set,
+/// output is instead sent one byte at a time at the given baud rate (with 8 data bits, no parity
+/// and one stop bit, so 10 bits per byte) and each message is delivered once its last byte has
+/// been sent. Like on the V5, writes block once more than [`SERIAL_BUFFER_SIZE`] bytes are
+/// waiting to be sent.
+///
+/// If [`SimulatorOptions::line_buffering`](crate::SimulatorOptions::line_buffering) is set,
+/// stdout is also buffered for each task like newlib does, and only written once a newline is
+/// written or [`LINE_BUFFER_SIZE`] bytes are waiting.
+#[derive(Clone)]
+pub struct SerialPort {
+    interface: SimulatorInterface,
+    /// How long it takes to send one byte, if output is throttled.
+    byte_time: Option<Duration>,
+    line: Arc<Mutex<Line>>,
+    /// Stdout that hasn't been written yet, if it's line buffered.
+    line_buffers: Option<Arc<Mutex<LineBuffers>>>,
+}
+
+/// Each task's buffered stdout, keyed by task ID.
+type LineBuffers = BTreeMap<u32, (Writer, String)>;
+
+/// The task that wrote some console output.
+#[derive(Debug, Clone)]
+pub struct Writer {
+    pub task_id: u32,
+    pub task_name: String,
+}
+
+#[derive(Default)]
+struct Line {
+    /// Messages waiting to be delivered, with the task that wrote them and the time their last
+    /// byte will have been sent.
+    queue: VecDeque<(Instant, Writer, String)>,
+    /// When every queued byte will have been sent.
+    idle_at: Option<Instant>,
+}
+
+impl SerialPort {
+    pub fn new(interface: SimulatorInterface, baud_rate: Option<u32>, line_buffered: bool) -> Self {
+        let byte_time = baud_rate.map(|baud_rate| Duration::from_secs(10) / baud_rate.max(1));
+        Self {
+            interface,
+            byte_time,
+            line: Default::default(),
+            line_buffers: line_buffered.then(Default::default),
+        }
+    }
+
+    /// Writes to stdout, which is held back until it's flushed if it's line buffered. Returns
+    /// when the task that wrote it may continue like [`write`](Self::write).
+    pub fn write_stdout(&self, writer: Writer, message: String) -> Option<Instant> {
+        let Some(buffers) = &self.line_buffers else {
+            return self.write(writer, message);
+        };
+        let flushed = {
+            let mut buffers = buffers.lock().unwrap();
+            let (_, buffer) = buffers
+                .entry(writer.task_id)
+                .or_insert_with(|| (writer.clone(), String::new()));
+            buffer.push_str(&message);
+            if buffer.len() >= LINE_BUFFER_SIZE {
+                Some(std::mem::take(buffer))
+            } else {
+                buffer.rfind('\n').map(|end| buffer.drain(..=end).collect())
+            }
+        };
+        flushed.and_then(|flushed| self.write(writer, flushed))
+    }
+
+    /// Writes every task's line buffered stdout, like newlib does when robot code exits.
+    pub fn flush_stdout(&self) {
+        let Some(buffers) = &self.line_buffers else {
+            return;
+        };
+        let buffers = std::mem::take(&mut *buffers.lock().unwrap());
+        for (writer, buffer) in buffers.into_values() {
+            if !buffer.is_empty() {
+                self.write(writer, buffer);
+            }
+        }
+    }
+
+    /// Queues a message to be sent, returning when the task that wrote it may continue, or
+    /// `None` if it doesn't need to wait.
+    pub fn write(&self, writer: Writer, message: String) -> Option<Instant> {
+        let mut line = self.line.lock().unwrap();
+        let Some(byte_time) = self.byte_time else {
+            self.deliver(writer, message);
+            return None;
+        };
+
+        let now = Instant::now();
+        let start = line.idle_at.map_or(now, |idle_at| idle_at.max(now));
+        let end = start + byte_time * message.len() as u32;
+        line.idle_at = Some(end);
+        line.queue.push_back((end, writer, message));
+
+        // the writer waits until the rest of its message fits in the transmit buffer
+        let buffer_time = byte_time * SERIAL_BUFFER_SIZE;
+        end.checked_sub(buffer_time).filter(|resume| *resume > now)
+    }
+
+    /// How many bytes can be written without blocking.
+    pub fn free(&self) -> u32 {
+        let Some(byte_time) = self.byte_time else {
+            return SERIAL_BUFFER_SIZE;
+        };
+        let line = self.line.lock().unwrap();
+        let pending = line.idle_at.map_or(Duration::ZERO, |idle_at| {
+            idle_at - Instant::now().min(idle_at)
+        });
+        let pending_bytes = pending.as_nanos().div_ceil(byte_time.as_nanos()) as u32;
+        SERIAL_BUFFER_SIZE.saturating_sub(pending_bytes)
+    }
+
+    /// Delivers every message that has finished sending.
+    pub fn flush(&self) {
+        let now = Instant::now();
+        let mut line = self.line.lock().unwrap();
+        while line.queue.front().is_some_and(|(end, ..)| *end <= now) {
+            let (_, writer, message) = line.queue.pop_front().unwrap();
+            self.deliver(writer, message);
+        }
+    }
+
+    /// Delivers every queued message immediately, so that no output is lost when the
+    /// simulation stops.
+    pub fn flush_all(&self) {
+        self.flush_stdout();
+        let mut line = self.line.lock().unwrap();
+        while let Some((_, writer, message)) = line.queue.pop_front() {
+            self.deliver(writer, message);
+        }
+        line.idle_at = None;
+    }
+
+    /// Sends a message, tagged with the task that wrote it.
+    fn deliver(&self, writer: Writer, message: String) {
+        self.interface
+            .send(SimulatorEvent::ConsoleMessage(ConsoleOutput {
+                text: message,
+                task_id: Some(writer.task_id),
+                task_name: Some(writer.task_name),
+            }));
+    }
+}
@@ -0,0 +1,365 @@
+//! The V5 brain's smart ports and the PROS device registry.
+
+use std::time::{Duration, Instant};
+
+use pros_simulator_interface::{
+    DeviceType, DrivetrainConfig, MotorGroup, MotorState, SimulatorEvent,
+};
+use pros_sys::{
+    apix::{
+        v5_device_e_t, E_DEVICE_ADI, E_DEVICE_DISTANCE, E_DEVICE_GPS, E_DEVICE_IMU, E_DEVICE_MOTOR,
+        E_DEVICE_NONE, E_DEVICE_OPTICAL, E_DEVICE_RADIO, E_DEVICE_ROTATION, E_DEVICE_SERIAL,
+        E_DEVICE_VISION,
+    },
+    ENODEV, ENXIO,
+};
+
+use super::{
+    motor_model::{target_velocity, MotorModel},
+    TICK_PERIOD_MS,
+};
+
+/// The `errno` value for a port that's already registered, from newlib's `errno.h`.
+pub const EADDRINUSE: i32 = 112;
+
+/// How many smart ports the V5 brain has.
+pub const NUM_SMART_PORTS: usize = 21;
+
+/// The highest voltage a V5 motor can be driven at, in millivolts.
+pub const MAX_MOTOR_MILLIVOLTS: i32 = 12_000;
+
+/// The devices plugged into each smart port, and the devices robot code has registered to them.
+///
+/// Ports are zero-indexed, like in the PROS registry API. Devices are plugged in when the
+/// simulation starts with [`SimulatorOptions::smart_port`](crate::SimulatorOptions::smart_port),
+/// and apart from motors the simulator doesn't model their behavior yet.
+#[derive(Debug, Default)]
+pub struct SmartPorts {
+    plugged: [Option<DeviceType>; NUM_SMART_PORTS],
+    bound: [Option<DeviceType>; NUM_SMART_PORTS],
+    /// The voltage each motor was last driven at, in millivolts.
+    motor_voltages: [i32; NUM_SMART_PORTS],
+    motors: [MotorModel; NUM_SMART_PORTS],
+    /// The tick the motors were last run until.
+    motors_run_until: u64,
+    /// What the frontend was last told about each motor, and when.
+    reported_motors: [(ReportedMotor, Option<Instant>); NUM_SMART_PORTS],
+    /// The shortest time between updates about the same motor, if they're rate limited.
+    motor_update_period: Option<Duration>,
+    motor_groups: Vec<ReportedGroup>,
+}
+
+/// A motor's state as it's sent to the frontend in
+/// [`MotorUpdated`](SimulatorEvent::MotorUpdated). The default is a motor at rest.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct ReportedMotor {
+    millivolts: i32,
+    target_velocity: i32,
+    actual_velocity: i32,
+    position: i32,
+    current: i32,
+}
+
+impl ReportedMotor {
+    fn new(millivolts: i32, motor: &MotorModel) -> Self {
+        Self {
+            millivolts,
+            target_velocity: target_velocity(millivolts).round() as i32,
+            actual_velocity: motor.velocity().round() as i32,
+            position: motor.position().round() as i32,
+            current: motor.current(millivolts).round() as i32,
+        }
+    }
+}
+
+/// A motor group and the voltages of its motors the frontend was last told about.
+#[derive(Debug)]
+struct ReportedGroup {
+    group: MotorGroup,
+    reported: Vec<i32>,
+}
+
+impl SmartPorts {
+    /// Creates smart ports with the given devices plugged in, keyed by zero-indexed port.
+    pub fn new(devices: impl IntoIterator<Item = (usize, DeviceType)>) -> Self {
+        let mut ports = Self::default();
+        for (port, device) in devices {
+            ports.plugged[port] = Some(device);
+        }
+        ports
+    }
+
+    fn check_port(port: u32) -> Result<usize, i32> {
+        let port = port as usize;
+        if port >= NUM_SMART_PORTS {
+            tracing::error!("Port {port} isn't a smart port");
+            return Err(ENXIO);
+        }
+        Ok(port)
+    }
+
+    /// The `v5_device_e_t` of the device plugged into the port.
+    pub fn plugged_type(&self, port: u32) -> Result<v5_device_e_t, i32> {
+        let port = Self::check_port(port)?;
+        Ok(device_code(self.plugged[port]))
+    }
+
+    /// The `v5_device_e_t` of the device registered to the port.
+    pub fn bound_type(&self, port: u32) -> Result<v5_device_e_t, i32> {
+        let port = Self::check_port(port)?;
+        Ok(device_code(self.bound[port]))
+    }
+
+    /// Registers the device plugged into the port, which must have the given `v5_device_e_t`.
+    pub fn bind(&mut self, port: u32, device_type: v5_device_e_t) -> Result<(), i32> {
+        let port = Self::check_port(port)?;
+        let Some(device) =
+            self.plugged[port].filter(|device| device_code(Some(*device)) == device_type)
+        else {
+            tracing::error!("No device of type {device_type} is plugged into port {port}");
+            return Err(ENXIO);
+        };
+        if self.bound[port].is_some() {
+            tracing::error!("Port {port} is already registered");
+            return Err(EADDRINUSE);
+        }
+        self.bound[port] = Some(device);
+        Ok(())
+    }
+
+    /// Removes the device registered to the port, if there is one.
+    pub fn unbind(&mut self, port: u32) -> Result<(), i32> {
+        let port = Self::check_port(port)?;
+        self.bound[port] = None;
+        Ok(())
+    }
+
+    /// Drives the motor plugged into the port at the given voltage from the given tick on.
+    /// Unlike the registry, the motor API numbers ports from 1.
+    pub fn set_motor_voltage(&mut self, port: u32, millivolts: i32, tick: u64) -> Result<(), i32> {
+        if !(1..=NUM_SMART_PORTS as u32).contains(&port) {
+            tracing::error!("Port {port} isn't a smart port");
+            return Err(ENXIO);
+        }
+        let index = port as usize - 1;
+        if self.plugged[index] != Some(DeviceType::Motor) {
+            tracing::error!("No motor is plugged into port {port}");
+            return Err(ENODEV);
+        }
+        self.run_motors(tick);
+        self.motor_voltages[index] = millivolts;
+        Ok(())
+    }
+
+    /// The voltage the motor on the port was last driven at, numbering ports from 1 like the
+    /// motor API. Ports without a motor read 0.
+    pub fn motor_voltage(&self, port: u8) -> i32 {
+        let index = usize::from(port).wrapping_sub(1);
+        self.motor_voltages.get(index).copied().unwrap_or(0)
+    }
+
+    /// The state of each motor plugged in, run until `tick`, for a
+    /// [`Telemetry`](pros_simulator_interface::Telemetry) snapshot.
+    pub fn motor_states(&mut self, tick: u64) -> Vec<MotorState> {
+        self.run_motors(tick);
+        (0..NUM_SMART_PORTS)
+            .filter(|&index| self.plugged[index] == Some(DeviceType::Motor))
+            .map(|index| {
+                let millivolts = self.motor_voltages[index];
+                let motor = ReportedMotor::new(millivolts, &self.motors[index]);
+                MotorState {
+                    port: index as u8 + 1,
+                    millivolts,
+                    velocity: motor.actual_velocity,
+                    position: motor.position,
+                    current: motor.current,
+                }
+            })
+            .collect()
+    }
+
+    /// Reports the voltages of a group of motors together from now on, or returns why it can't.
+    pub fn add_motor_group(&mut self, group: MotorGroup) -> Result<(), String> {
+        let ignored = |reason| format!("Motor group `{}` was ignored because {reason}", group.name);
+        check_motor_group(&group).map_err(ignored)?;
+        if self.motor_groups.iter().any(|g| g.group.name == group.name) {
+            return Err(ignored("there's already a motor group with that name"));
+        }
+        self.motor_groups.push(ReportedGroup {
+            reported: vec![0; group.ports.len()],
+            group,
+        });
+        Ok(())
+    }
+
+    /// Limits updates about each motor to `hz` per second, or sends every change if `hz` is
+    /// `None`.
+    pub fn set_motor_update_rate(&mut self, hz: Option<u32>) {
+        self.motor_update_period = hz.map(|hz| Duration::from_secs(1) / hz.max(1));
+    }
+
+    /// [`MotorUpdated`](SimulatorEvent::MotorUpdated) events for the motors whose voltage or
+    /// modeled state has changed by `tick` since the frontend was last told about them. Motors
+    /// that were reported too recently are left for a later call. `millis` is the time robot
+    /// code sees, which the events are stamped with.
+    pub fn take_motor_updates(&mut self, millis: u32, tick: u64) -> Vec<SimulatorEvent> {
+        self.motor_updates(self.motor_update_period, millis, tick)
+    }
+
+    /// Like [`take_motor_updates`](Self::take_motor_updates), but ignores the rate limit, so the
+    /// frontend ends up with every motor's final state.
+    pub fn flush_motor_updates(&mut self, millis: u32, tick: u64) -> Vec<SimulatorEvent> {
+        self.motor_updates(None, millis, tick)
+    }
+
+    /// Runs every motor at the voltage it's driven at until `tick`. Time is counted in ticks
+    /// rather than `millis`, which wraps around.
+    fn run_motors(&mut self, tick: u64) {
+        let elapsed = tick.saturating_sub(self.motors_run_until) * TICK_PERIOD_MS;
+        let seconds = elapsed as f64 / 1000.0;
+        self.motors_run_until = self.motors_run_until.max(tick);
+        for (motor, &millivolts) in self.motors.iter_mut().zip(&self.motor_voltages) {
+            motor.advance(millivolts, seconds);
+        }
+    }
+
+    fn motor_updates(
+        &mut self,
+        period: Option<Duration>,
+        millis: u32,
+        tick: u64,
+    ) -> Vec<SimulatorEvent> {
+        self.run_motors(tick);
+        let now = Instant::now();
+        let mut updates = Vec::new();
+        for (index, (reported, reported_at)) in self.reported_motors.iter_mut().enumerate() {
+            let state = ReportedMotor::new(self.motor_voltages[index], &self.motors[index]);
+            let too_soon =
+                matches!((period, *reported_at), (Some(period), Some(at)) if now < at + period);
+            if *reported == state || too_soon {
+                continue;
+            }
+            *reported = state;
+            *reported_at = Some(now);
+            updates.push(SimulatorEvent::MotorUpdated {
+                port: index as u8 + 1,
+                millivolts: state.millivolts,
+                millis,
+                target_velocity: state.target_velocity,
+                actual_velocity: state.actual_velocity,
+                position: state.position,
+                current: state.current,
+            });
+        }
+        // groups are reported from what the frontend knows about their motors, so that they're
+        // rate limited along with them
+        for ReportedGroup { group, reported } in &mut self.motor_groups {
+            let motors = group
+                .ports
+                .iter()
+                .map(|&port| {
+                    let (reported, _) = self.reported_motors[usize::from(port.unsigned_abs()) - 1];
+                    let millivolts = reported.millivolts;
+                    if port < 0 {
+                        -millivolts
+                    } else {
+                        millivolts
+                    }
+                })
+                .collect::<Vec<_>>();
+            if *reported == motors {
+                continue;
+            }
+            let total = motors
+                .iter()
+                .map(|&millivolts| i64::from(millivolts))
+                .sum::<i64>();
+            updates.push(SimulatorEvent::MotorGroupUpdated {
+                name: group.name.clone(),
+                millivolts: (total / motors.len() as i64) as i32,
+                motors: motors.clone(),
+            });
+            *reported = motors;
+        }
+        updates
+    }
+}
+
+/// Checks that a motor group has at least one motor, and that each is on a different smart
+/// port, or returns why it doesn't.
+pub fn check_motor_group(group: &MotorGroup) -> Result<(), &'static str> {
+    if group.ports.is_empty() {
+        return Err("it has no motors");
+    }
+    let mut seen = [false; NUM_SMART_PORTS];
+    for port in &group.ports {
+        let Some(seen) = usize::from(port.unsigned_abs())
+            .checked_sub(1)
+            .and_then(|index| seen.get_mut(index))
+        else {
+            return Err("one of its ports isn't a smart port (1 to 21, or -1 to -21 if reversed)");
+        };
+        if *seen {
+            return Err("it has more than one motor on the same port");
+        }
+        *seen = true;
+    }
+    Ok(())
+}
+
+/// The motor groups a drivetrain's motors are reported as: `left drive`, `right drive` and, if
+/// it has any, `strafe`.
+pub fn drivetrain_groups(drivetrain: &DrivetrainConfig) -> Vec<MotorGroup> {
+    [
+        ("left drive", &drivetrain.left_motors),
+        ("right drive", &drivetrain.right_motors),
+        ("strafe", &drivetrain.strafe_motors),
+    ]
+    .into_iter()
+    .filter(|(_, motors)| !motors.is_empty())
+    .map(|(name, motors)| MotorGroup {
+        name: name.to_string(),
+        ports: motors.clone(),
+    })
+    .collect()
+}
+
+/// Checks that a drivetrain's measurements are positive, that both of its sides have motors,
+/// and that each of its motors is on a different smart port, or returns why it doesn't.
+pub fn check_drivetrain(drivetrain: &DrivetrainConfig) -> Result<(), &'static str> {
+    let measurements = [
+        drivetrain.wheel_diameter,
+        drivetrain.track_width,
+        drivetrain.gear_ratio,
+    ];
+    if !measurements.iter().all(|x| x.is_finite() && *x > 0.0) {
+        return Err("its wheel diameter, track width and gear ratio must be positive");
+    }
+    if drivetrain.left_motors.is_empty() || drivetrain.right_motors.is_empty() {
+        return Err("both of its sides need at least one motor");
+    }
+    let motors = drivetrain_groups(drivetrain)
+        .into_iter()
+        .flat_map(|group| group.ports)
+        .collect();
+    check_motor_group(&MotorGroup {
+        name: String::new(),
+        ports: motors,
+    })
+}
+
+fn device_code(device: Option<DeviceType>) -> v5_device_e_t {
+    match device {
+        None => E_DEVICE_NONE,
+        Some(DeviceType::Motor) => E_DEVICE_MOTOR,
+        Some(DeviceType::Rotation) => E_DEVICE_ROTATION,
+        Some(DeviceType::Imu) => E_DEVICE_IMU,
+        Some(DeviceType::Distance) => E_DEVICE_DISTANCE,
+        Some(DeviceType::Radio) => E_DEVICE_RADIO,
+        Some(DeviceType::Vision) => E_DEVICE_VISION,
+        Some(DeviceType::Adi) => E_DEVICE_ADI,
+        Some(DeviceType::Optical) => E_DEVICE_OPTICAL,
+        Some(DeviceType::Gps) => E_DEVICE_GPS,
+        Some(DeviceType::Serial) => E_DEVICE_SERIAL,
+    }
+}
@@ -1,21 +1,39 @@
 use std::{
-    collections::{HashMap, HashSet},
+    alloc::Layout,
+    collections::{BTreeMap, HashMap, HashSet},
+    ffi::CString,
     future::Future,
+    mem::size_of,
     pin::Pin,
-    sync::Arc,
-    task::Poll,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+        mpsc, Arc, OnceLock, Weak,
+    },
+    task::{self, Poll, Waker},
+    thread::JoinHandle,
+    time::{Duration, Instant},
 };
 
 use anyhow::{bail, Context};
-use pros_simulator_interface::SimulatorEvent;
-use tokio::sync::{Mutex, MutexGuard};
+use futures::executor::block_on;
+use pros_simulator_interface::{DataAbortScreen, SchedulerInvariant, SimulatorEvent, TaskInfo};
+use tokio::sync::{Mutex, MutexGuard, OnceCell};
 use wasmtime::{
-    AsContextMut, Caller, Engine, Func, Instance, Linker, Module, SharedMemory, Store, Table,
-    TypedFunc, WasmParams,
+    AsContextMut, CallHook, Caller, Engine, Extern, FrameInfo, Func, Instance, InstancePre, Linker,
+    Module, SharedMemory, Store, Table, Trap, TypedFunc, UnknownImportError, UpdateDeadline,
+    WasmBacktrace, WasmParams,
 };
 
-use super::{memory::SharedMemoryExt, thread_local::TaskStorage, Host, HostCtx, WasmAllocator};
-use crate::{api::configure_api, interface::SimulatorInterface};
+use super::{
+    abi::ProgramAbi, backtrace::backtrace_frames, clock::TickTimer, compat::api_compatibility,
+    jitter::Jitter, memory::SharedMemoryExt, panic::parse_panic, thread_local::TaskStorage, timer,
+    Host, HostCtx, WasmAllocator,
+};
+use crate::{
+    api::{configure_api, stub_unknown_imports},
+    interface::SimulatorInterface,
+    StopReason, Timeout, WarningKind,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TaskState {
@@ -32,6 +50,12 @@ pub enum TaskState {
 
 pub const TASK_PRIORITIES: u32 = 16;
 
+/// Why robot code without an exported function table can't use function pointers, e.g. to
+/// create tasks.
+pub const NO_FUNCTION_TABLE: &str = "robot code doesn't export its function table \
+     (`__indirect_function_table`), so it can't pass function pointers to the simulator. Link \
+     it with `-Wl,--export-table` to export it";
+
 pub struct TaskOptions {
     priority: u32,
     store: Store<Host>,
@@ -39,6 +63,13 @@ pub struct TaskOptions {
     name: Option<String>,
 }
 
+/// Lets the task be preempted at a tick while it runs robot code called from the current host
+/// function, but not while that robot code is calling back into the simulator.
+fn preempt_here(caller: &mut Caller<'_, Host>) {
+    let host_calls = caller.data().host_calls;
+    caller.data_mut().preempt_at = Some(host_calls);
+}
+
 impl TaskOptions {
     /// Create options for a task who's entrypoint is a function from robot code.
     ///
@@ -65,6 +96,7 @@ impl TaskOptions {
                     let current_task = task_handle.lock().await;
                     current_task
                         .indirect_call_table
+                        .context(NO_FUNCTION_TABLE)?
                         .get(&mut caller, task_start)
                         .context("Task entrypoint is out of bounds")?
                 };
@@ -76,6 +108,7 @@ impl TaskOptions {
                     .typed::<P, ()>(&mut caller)
                     .context("Task entrypoint has invalid signature")?;
 
+                preempt_here(&mut caller);
                 entrypoint
                     .call_async(&mut caller, args.lock().await.take().unwrap())
                     .await?;
@@ -122,8 +155,9 @@ impl TaskOptions {
     pub fn new_global(
         pool: &mut TaskPool,
         host: &Host,
-        func_name: &'static str,
+        func_name: impl Into<String>,
     ) -> anyhow::Result<Self> {
+        let func_name = func_name.into();
         Self::new_closure(pool, host, move |mut caller| {
             Box::new(async move {
                 let instance = {
@@ -132,13 +166,16 @@ impl TaskOptions {
                     this_task.instance
                 };
 
-                let func = instance.get_func(&mut caller, func_name).with_context(|| {
-                    format!("entrypoint missing: expected {func_name} to be defined")
-                })?;
+                let func = instance
+                    .get_func(&mut caller, &func_name)
+                    .with_context(|| {
+                        format!("entrypoint missing: expected {func_name} to be defined")
+                    })?;
                 let func = func
                     .typed(&mut caller)
                     .with_context(|| format!("invalid {func_name} signature: expected () -> ()"))?;
 
+                preempt_here(&mut caller);
                 func.call_async(&mut caller, ()).await
             })
         })
@@ -159,16 +196,29 @@ impl TaskOptions {
 pub struct Task {
     id: u32,
     name: String,
+    /// The task's name as a C string in robot code memory, once `task_get_name` has asked for it.
+    c_name: Option<u32>,
     local_storage: Option<TaskStorage>,
     task_impl: TypedFunc<(), ()>,
     priority: u32,
-    errno: Option<Errno>,
+    errno: Errno,
+    /// Whether the task has been warned about reading `errno` before it was set.
+    warned_unset_errno: bool,
     pub instance: Instance,
     allocator: WasmAllocator,
-    pub indirect_call_table: Table,
+    /// The robot code's function table, which function pointers index into. wasm-ld only
+    /// exports it with `--export-table`, so robot code can run without one until it passes the
+    /// simulator a function pointer.
+    pub indirect_call_table: Option<Table>,
     store: Arc<Mutex<Store<Host>>>,
     state: TaskState,
+    /// Why the task crashed, if it did and the simulation carried on.
+    error: Option<String>,
     marked_for_delete: bool,
+    /// Stops the task's thread the next time it yields, when running in threaded mode.
+    cancelled: Arc<AtomicBool>,
+    /// The tick the task last started waiting in `delay` or `task_delay_until`, if it has.
+    delay_started: Option<u64>,
 }
 
 impl Task {
@@ -177,45 +227,82 @@ impl Task {
         name: String,
         mut store: Store<Host>,
         instance: Instance,
+        allocator: WasmAllocator,
+        errno: Errno,
         task_impl: TypedFunc<(), ()>,
     ) -> Self {
         Self {
             id,
             name,
+            c_name: None,
             local_storage: None,
             task_impl,
             priority: 0,
-            errno: None,
-            allocator: WasmAllocator::new(&mut store, &instance),
-            indirect_call_table: instance
-                .get_table(&mut store, "__indirect_function_table")
-                .unwrap(),
+            errno,
+            warned_unset_errno: false,
+            allocator,
+            indirect_call_table: instance.get_table(&mut store, "__indirect_function_table"),
             instance,
             store: Arc::new(Mutex::new(store)),
             state: TaskState::Ready,
+            error: None,
             marked_for_delete: false,
+            cancelled: Default::default(),
+            delay_started: None,
         }
     }
 
     pub async fn local_storage(
         &mut self,
-        store: impl AsContextMut<Data = impl Send>,
-    ) -> TaskStorage {
+        store: impl AsContextMut<Data = Host>,
+    ) -> anyhow::Result<TaskStorage> {
         if let Some(storage) = self.local_storage {
-            return storage;
+            return Ok(storage);
         }
-        let storage = TaskStorage::new(store, &self.allocator).await;
+        let storage = TaskStorage::new(store, &self.allocator).await?;
         self.local_storage = Some(storage);
-        storage
+        Ok(storage)
     }
 
-    pub async fn errno(&mut self, store: impl AsContextMut<Data = impl Send>) -> Errno {
-        if let Some(errno) = self.errno {
-            return errno;
+    /// Returns a pointer to the task's name as a C string, copying it into robot code memory the
+    /// first time. Since tasks can't be renamed, later calls return the same pointer.
+    ///
+    /// The string is allocated with the given allocator, which must belong to `store`.
+    pub async fn c_name(
+        &mut self,
+        mut store: impl AsContextMut<Data = Host>,
+        allocator: &WasmAllocator,
+    ) -> anyhow::Result<u32> {
+        if let Some(ptr) = self.c_name {
+            return Ok(ptr);
         }
-        let errno = Errno::new(store, &self.allocator).await;
-        self.errno = Some(errno);
-        errno
+        let c_name = CString::new(self.name.as_str()).unwrap();
+        let name_bytes = c_name.as_bytes_with_nul();
+        let ptr = allocator
+            .memalign(&mut store, Layout::for_value(name_bytes))
+            .await
+            .context("couldn't allocate the task's name")?;
+        store
+            .as_context()
+            .data()
+            .memory()
+            .write_relaxed(ptr as usize, name_bytes)?;
+        self.c_name = Some(ptr);
+        Ok(ptr)
+    }
+
+    pub fn errno(&self) -> &Errno {
+        &self.errno
+    }
+
+    /// Whether the task should be warned about reading `errno` now, which is the first time it
+    /// reads it before any API call has set it.
+    pub fn should_warn_unset_errno(&mut self) -> bool {
+        if self.errno.is_set() || self.warned_unset_errno {
+            return false;
+        }
+        self.warned_unset_errno = true;
+        true
     }
 
     pub fn id(&self) -> u32 {
@@ -231,17 +318,135 @@ impl Task {
         }
     }
 
+    /// Starts running this task on a new OS thread, which sends the task's result to `finished`
+    /// when it stops.
+    ///
+    /// While another task has the scheduler suspended (`suspended_by` holds its ID), or robot
+    /// code is paused at a breakpoint (`paused` is set), this task is paused at its next yield
+    /// point, and its thread sleeps until `parked` wakes it. `parked` also wakes the thread when
+    /// the task is cancelled. Time spent running the task is added to `busy`, if given.
+    fn spawn_thread(
+        &mut self,
+        suspended_by: Arc<AtomicU32>,
+        paused: Option<Arc<AtomicBool>>,
+        parked: Arc<ParkedTasks>,
+        busy: Option<Arc<AtomicU64>>,
+        finished: mpsc::Sender<(u32, anyhow::Result<()>)>,
+    ) -> std::io::Result<JoinHandle<()>> {
+        let id = self.id;
+        let cancelled = self.cancelled.clone();
+        let mut future = Box::pin(self.start());
+        let can_run = move || {
+            let holder = suspended_by.load(Ordering::Acquire);
+            let paused = paused
+                .as_ref()
+                .is_some_and(|paused| paused.load(Ordering::Acquire));
+            (holder == 0 || holder == id) && !paused
+        };
+        let cancellation = {
+            let parked = parked.clone();
+            futures_util::future::poll_fn(move |cx| {
+                parked.poll_until(cx, || cancelled.load(Ordering::Acquire))
+            })
+        };
+        let future = futures_util::future::poll_fn(move |cx| {
+            if parked.poll_until(cx, &can_run).is_pending() {
+                return Poll::Pending;
+            }
+            let started = Instant::now();
+            let poll = future.as_mut().poll(cx);
+            if let Some(busy) = &busy {
+                busy.fetch_add(started.elapsed().as_nanos() as u64, Ordering::Relaxed);
+            }
+            poll
+        });
+        std::thread::Builder::new()
+            .name(self.name.clone())
+            .spawn(move || {
+                let result = block_on(async move {
+                    tokio::select! {
+                        result = future => result,
+                        _ = cancellation => Ok(()),
+                    }
+                });
+                _ = finished.send((id, result));
+            })
+    }
+
     pub fn state(&self) -> TaskState {
         self.state
     }
 
+    /// Why the task crashed, if it did without stopping the simulation.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
 
+    /// Records that the task started waiting in `delay` or `task_delay_until` at `tick`, which
+    /// is where a periodic loop starts each iteration.
+    pub fn start_delay(&mut self, tick: u64) {
+        self.delay_started = Some(tick);
+    }
+
+    /// The tick the task last started waiting in `delay` or `task_delay_until`, if it has.
+    pub fn delay_started(&self) -> Option<u64> {
+        self.delay_started
+    }
+
     pub fn allocator(&self) -> WasmAllocator {
         self.allocator.clone()
     }
+
+    /// Describes a call to an unimplemented import, if that's what caused this task to stop.
+    /// These only stop the task that made the call, rather than the whole simulation.
+    fn unimplemented_call(err: &anyhow::Error) -> Option<SimulatorEvent> {
+        let import = err.downcast_ref::<UnknownImportError>()?;
+        Some(SimulatorEvent::UnimplementedCall {
+            name: import.name().to_string(),
+            backtrace: err
+                .downcast_ref::<WasmBacktrace>()
+                .map(backtrace_frames)
+                .unwrap_or_default(),
+        })
+    }
+
+    /// What the brain's data abort screen would show for an error that caused this task to stop,
+    /// if it was a trap.
+    fn data_abort(&self, memory: &SharedMemory, err: &anyhow::Error) -> Option<SimulatorEvent> {
+        let trap = err.downcast_ref::<Trap>()?;
+        let backtrace = err.downcast_ref::<WasmBacktrace>();
+        let frames = backtrace.map_or(&[][..], WasmBacktrace::frames);
+        Some(SimulatorEvent::DataAbort(DataAbortScreen {
+            pc: frames.first().and_then(FrameInfo::module_offset),
+            task_id: self.id,
+            task_name: self.name.clone(),
+            func_index: frames.first().map(FrameInfo::func_index),
+            trap: trap.to_string(),
+            errno: self.errno.value(memory),
+            stack_trace: frames.iter().filter_map(FrameInfo::module_offset).collect(),
+        }))
+    }
+
+    /// Describes an error that caused this task to stop.
+    fn robot_code_error(&self, memory: &SharedMemory, err: &anyhow::Error) -> SimulatorEvent {
+        let message = err.root_cause().to_string();
+        SimulatorEvent::RobotCodeError {
+            panic: parse_panic(&message),
+            message,
+            task_id: self.id,
+            task_name: self.name.clone(),
+            errno: self.errno.value(memory),
+            trap: err.downcast_ref::<Trap>().map(ToString::to_string),
+            backtrace: err
+                .downcast_ref::<WasmBacktrace>()
+                .map(backtrace_frames)
+                .unwrap_or_default(),
+        }
+    }
 }
 impl PartialEq for Task {
     fn eq(&self, other: &Self) -> bool {
@@ -253,16 +458,47 @@ impl Eq for Task {}
 pub type TaskHandle = Arc<Mutex<Task>>;
 
 pub struct TaskPool {
-    pool: HashMap<u32, TaskHandle>,
+    /// Tasks by ID, kept in order so tasks are always scheduled and spawned in the same order.
+    pool: BTreeMap<u32, TaskHandle>,
     deleted_tasks: HashSet<u32>,
     newest_task_id: u32,
     current_task: Option<TaskHandle>,
     engine: Engine,
     shared_memory: SharedMemory,
+    /// The robot code linked against the simulator's API, once the first task has been created.
+    instance_pre: Option<InstancePre<Host>>,
     scheduler_suspended: u32,
+    /// The ID of the task that called `rtos_suspend_all`, or 0 if the scheduler isn't suspended.
+    suspended_by: Arc<AtomicU32>,
     yield_pending: bool,
-    shutdown_pending: bool,
+    /// Set when the simulation should stop before all tasks have finished.
+    shutdown: Option<StopReason>,
     interface: SimulatorInterface,
+    /// Random perturbations of the schedule, if enabled.
+    jitter: Option<Jitter>,
+    /// Kinds of warning that stop the simulation.
+    strict_warnings: Vec<WarningKind>,
+    /// How many `sim_assert`s have failed.
+    failed_assertions: u32,
+    /// The system daemon, which keeps running while robot code is paused.
+    daemon: Option<u32>,
+    /// Set while robot code is paused at a breakpoint.
+    robot_code_paused: Arc<AtomicBool>,
+    /// The threads of tasks waiting for the scheduler or robot code to be resumed.
+    parked: Arc<ParkedTasks>,
+    /// Nanoseconds spent running robot code, not counting the system daemon.
+    busy: Arc<AtomicU64>,
+    /// The tick the cooperative scheduler last started running a task at. The task is only
+    /// preempted at a tick once it's run through a whole tick, since a task switched in partway
+    /// through a tick (or held up by the host's own scheduling) would otherwise barely run.
+    slice_start: Arc<AtomicU64>,
+    /// The scheduler invariants that have been reported broken, so each is only reported once.
+    broken_invariants: Vec<SchedulerInvariant>,
+    /// When the simulation has to stop, if it has a timeout.
+    deadline: Option<Deadline>,
+    /// Exports each task's lifetime as a span, if enabled.
+    #[cfg(feature = "otlp")]
+    spans: Option<super::otlp::SpanExporter>,
 }
 
 impl TaskPool {
@@ -270,52 +506,198 @@ impl TaskPool {
         engine: Engine,
         shared_memory: SharedMemory,
         interface: SimulatorInterface,
+        jitter: Option<Jitter>,
+        strict_warnings: Vec<WarningKind>,
+        timeout: Option<Timeout>,
+        stop_signal: Option<Arc<AtomicBool>>,
     ) -> anyhow::Result<Self> {
         Ok(Self {
-            pool: HashMap::new(),
+            pool: BTreeMap::new(),
             deleted_tasks: HashSet::new(),
             newest_task_id: 0,
             current_task: None,
             engine,
             shared_memory,
+            instance_pre: None,
             scheduler_suspended: 0,
+            suspended_by: Default::default(),
             yield_pending: false,
-            shutdown_pending: false,
+            shutdown: None,
             interface,
+            jitter,
+            strict_warnings,
+            failed_assertions: 0,
+            daemon: None,
+            robot_code_paused: Default::default(),
+            parked: Default::default(),
+            busy: Default::default(),
+            slice_start: Default::default(),
+            broken_invariants: Vec::new(),
+            deadline: Deadline::new(timeout, stop_signal),
+            #[cfg(feature = "otlp")]
+            spans: None,
         })
     }
 
+    /// Exports each task's lifetime as a span with the given exporter.
+    #[cfg(feature = "otlp")]
+    pub fn with_span_exporter(mut self, spans: Option<super::otlp::SpanExporter>) -> Self {
+        self.spans = spans;
+        self
+    }
+
+    /// Marks a task as the system daemon, which keeps running while robot code is paused.
+    pub fn set_daemon(&mut self, task_id: u32) {
+        self.daemon = Some(task_id);
+    }
+
+    /// Stops running every task except the system daemon until
+    /// [`resume_robot_code`](Self::resume_robot_code) is called. The current task is paused the
+    /// next time it yields.
+    ///
+    /// Returns false without pausing if there's no system daemon to resume robot code, or robot
+    /// code has the scheduler suspended.
+    pub fn pause_robot_code(&mut self) -> bool {
+        if self.daemon.is_none() || self.suspended_by().is_some() {
+            return false;
+        }
+        self.robot_code_paused.store(true, Ordering::Release);
+        true
+    }
+
+    /// Lets robot code paused by [`pause_robot_code`](Self::pause_robot_code) run again.
+    pub fn resume_robot_code(&mut self) {
+        self.robot_code_paused.store(false, Ordering::Release);
+        self.parked.unpark_all();
+    }
+
     pub fn create_store(&mut self, host: &Host) -> anyhow::Result<Store<Host>> {
-        let store = Store::new(&self.engine, host.clone());
+        let mut host = host.clone();
+        host.task = Weak::new();
+        host.errno = None;
+        let mut store = Store::new(&self.engine, host);
+        if store.data().limits.limits_memory() {
+            store.limiter(|host| &mut host.limits);
+        }
+        let threaded = store.data().options().threaded;
+        let profiler = store.data().profiler();
+        let deadline = self.deadline.clone();
+        // where a tick lands in the robot code changes from run to run, so preempting at ticks
+        // would make jittered schedules impossible to reproduce
+        let preemptive = self.jitter.is_none();
+        let slice_start = self.slice_start.clone();
+        store.call_hook(|host, hook| {
+            match hook {
+                CallHook::CallingHost => host.host_calls += 1,
+                CallHook::ReturningFromHost => host.host_calls -= 1,
+                CallHook::CallingWasm | CallHook::ReturningFromWasm => {}
+            }
+            Ok(())
+        });
+        // the epoch is incremented every tick
+        store.epoch_deadline_callback(move |store| {
+            if let Some(profiler) = &profiler {
+                profiler.sample(&store);
+            }
+            // Yield back to the task's thread every tick. On the cooperative scheduler, preempt
+            // the task so the next task of the same priority can run, unless it's in the middle
+            // of a host function (which may be holding the scheduler's locks). Also yield once
+            // the timeout has passed or the simulation was asked to stop, so it can be stopped
+            // even if the robot code never calls into the simulator.
+            let host = store.data();
+            let preempt = preemptive
+                && host.preempt_at == Some(host.host_calls)
+                && host.ticks() > slice_start.load(Ordering::Acquire) + 1;
+            let passed = deadline
+                .as_ref()
+                .is_some_and(|deadline| deadline.passed(host).is_some());
+            Ok(if threaded || preempt || passed {
+                UpdateDeadline::Yield(1)
+            } else {
+                UpdateDeadline::Continue(1)
+            })
+        });
         Ok(store)
     }
 
+    /// Creates a new instance of the robot code in the given store.
+    ///
+    /// The API is linked against the module the first time this is called, which sends a
+    /// warning for every import the simulator doesn't implement. Later calls reuse that linking.
     pub async fn instantiate(
         &mut self,
         store: &mut Store<Host>,
         module: &Module,
         interface: &SimulatorInterface,
     ) -> anyhow::Result<Instance> {
+        let instance_pre = match &self.instance_pre {
+            Some(instance_pre) => instance_pre,
+            None => {
+                let instance_pre = self.link(store, module, interface)?;
+                self.instance_pre.insert(instance_pre)
+            }
+        };
+
+        instance_pre.instantiate_async(store).await
+    }
+
+    fn link(
+        &self,
+        store: &mut Store<Host>,
+        module: &Module,
+        interface: &SimulatorInterface,
+    ) -> anyhow::Result<InstancePre<Host>> {
         let mut linker = Linker::<Host>::new(&self.engine);
 
         configure_api(&mut linker, store, self.shared_memory.clone())?;
 
+        if matches!(store.data().abi(), ProgramAbi::ProsRs | ProgramAbi::ProsC) {
+            let target = store.data().options().target_pros_version;
+            let compatibility = api_compatibility(module, target, |name| {
+                linker.get(&mut *store, "env", name).is_some()
+            });
+            interface.send(SimulatorEvent::ApiCompatibility(compatibility));
+        }
+
+        let stubbed = if store.data().options().permissive {
+            stub_unknown_imports(&mut linker, store, module)?
+        } else {
+            Vec::new()
+        };
+
         for import in module.imports() {
-            if linker
+            let consequence = if stubbed.iter().any(|name| name == import.name()) {
+                "calls to it will fail with ENOSYS"
+            } else if linker
                 .get(&mut *store, import.module(), import.name())
                 .is_none()
             {
-                interface.send(SimulatorEvent::Warning(format!(
-                    "Unimplemented API `{}` (Robot code will crash if this is used)",
-                    import.name()
-                )));
-            }
+                "tasks that call it will be stopped"
+            } else {
+                continue;
+            };
+            interface.send(SimulatorEvent::Warning(format!(
+                "Unimplemented API `{}` ({consequence})",
+                import.name()
+            )));
+            store.data().api_usage().record_unimplemented(import.name());
         }
 
         linker.define_unknown_imports_as_traps(module)?;
-        let instance = linker.instantiate_async(store, module).await?;
+        linker.instantiate_pre(module)
+    }
 
-        Ok(instance)
+    /// The functions the simulator provides to robot code built like the host's, as
+    /// `(module, name)` pairs, not counting stubs for functions it doesn't implement.
+    pub fn api_functions(&self, store: &mut Store<Host>) -> anyhow::Result<Vec<(String, String)>> {
+        let mut linker = Linker::<Host>::new(&self.engine);
+        configure_api(&mut linker, store, self.shared_memory.clone())?;
+        let functions = linker
+            .iter(&mut *store)
+            .filter(|(_, _, definition)| matches!(definition, Extern::Func(_)))
+            .map(|(module, name, _)| (module.to_string(), name.to_string()))
+            .collect();
+        Ok(functions)
     }
 
     pub async fn spawn(
@@ -336,16 +718,26 @@ impl TaskPool {
 
         self.newest_task_id += 1;
         let id = self.newest_task_id;
+        let name = name.unwrap_or_else(|| format!("task {id}"));
+        #[cfg(feature = "otlp")]
+        if let Some(spans) = &self.spans {
+            spans.start_task(id, &name);
+        }
 
-        let mut task = Task::new(
-            id,
-            name.unwrap_or_else(|| format!("task {id}")),
-            store,
-            instance,
-            entrypoint,
-        );
+        // allocated up front so failing API calls don't have to
+        let allocator = WasmAllocator::new(&mut store, &instance);
+        let errno = Errno::new(&mut store, &allocator)
+            .await
+            .context("Couldn't allocate the task's errno")?;
+        store.data_mut().errno = Some(errno.clone());
+
+        let mut task = Task::new(id, name, store, instance, allocator, errno, entrypoint);
         task.priority = priority;
         let task = Arc::new(Mutex::new(task));
+        {
+            let task_ref = task.lock().await;
+            task_ref.store.lock().await.data_mut().task = Arc::downgrade(&task);
+        }
         self.pool.insert(id, task.clone());
         Ok(task)
     }
@@ -373,32 +765,83 @@ impl TaskPool {
 
     #[inline]
     pub async fn yield_now() {
-        futures_util::pending!();
+        let mut yielded = false;
+        futures_util::future::poll_fn(|cx| {
+            if yielded {
+                return Poll::Ready(());
+            }
+            yielded = true;
+            // Tasks running on their own thread are only polled again once they're woken.
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        })
+        .await
     }
 
-    /// Prevent context switches from happening until `resume_all` is called.
-    pub fn suspend_all(&mut self) {
-        self.scheduler_suspended += 1;
+    /// Prevent context switches away from the given task until `resume_all` is called.
+    ///
+    /// Returns false without suspending the scheduler if another task already has it suspended,
+    /// in which case the caller should yield and try again.
+    pub fn suspend_all(&mut self, task_id: u32) -> bool {
+        match self.suspended_by() {
+            Some(holder) if holder != task_id => false,
+            _ => {
+                self.scheduler_suspended += 1;
+                self.suspended_by.store(task_id, Ordering::Release);
+                true
+            }
+        }
     }
 
-    /// Resumes the scheduler, causing a yield if one is pending
+    /// Resumes the scheduler if every `suspend_all` call has been matched.
     ///
-    /// Returns whether resuming the scheduler caused a yield.
-    pub async fn resume_all(&mut self) -> anyhow::Result<bool> {
+    /// Returns whether a yield was requested while the scheduler was suspended, in which case
+    /// the caller should yield once it has released the task pool.
+    pub fn resume_all(&mut self) -> anyhow::Result<bool> {
         if self.scheduler_suspended == 0 {
             bail!("rtos_resume_all called without a matching rtos_suspend_all");
         }
 
         self.scheduler_suspended -= 1;
+        if self.scheduler_suspended != 0 {
+            return Ok(false);
+        }
 
-        if self.yield_pending && self.scheduler_suspended == 0 {
-            Self::yield_now().await;
-            Ok(true)
-        } else {
-            Ok(false)
+        self.suspended_by.store(0, Ordering::Release);
+        self.parked.unpark_all();
+        Ok(std::mem::take(&mut self.yield_pending))
+    }
+
+    /// The ID of the task that has the scheduler suspended, if any.
+    pub fn suspended_by(&self) -> Option<u32> {
+        match self.suspended_by.load(Ordering::Acquire) {
+            0 => None,
+            id => Some(id),
+        }
+    }
+
+    /// Cleans up after a task that finished or was deleted, resuming the scheduler if it ended
+    /// without calling `rtos_resume_all`.
+    fn task_ended(&mut self, task: &Task) {
+        #[cfg(feature = "otlp")]
+        if let Some(spans) = &self.spans {
+            spans.end_task(task.id);
         }
+        if self.suspended_by() != Some(task.id) {
+            return;
+        }
+        self.interface.send(SimulatorEvent::Warning(format!(
+            "Task `{}` (#{}) exited with scheduler in suspended state",
+            &task.name, task.id,
+        )));
+        self.scheduler_suspended = 0;
+        self.suspended_by.store(0, Ordering::Release);
+        self.parked.unpark_all();
+        self.yield_pending = false;
+        self.fail_if_strict(WarningKind::SuspendedAtExit);
     }
 
+    /// The IDs of the highest priority tasks, in ascending order.
     async fn highest_priority_task_ids(&self) -> Vec<u32> {
         let mut highest_priority = 0;
         let mut highest_priority_tasks = vec![];
@@ -412,7 +855,6 @@ impl TaskPool {
                 highest_priority_tasks.push(task.id);
             }
         }
-        highest_priority_tasks.sort();
         highest_priority_tasks
     }
 
@@ -433,75 +875,276 @@ impl TaskPool {
         }
         self.yield_pending = false;
 
-        let task_candidates = self.highest_priority_task_ids().await;
+        let task_candidates = match self.daemon {
+            Some(daemon) if self.robot_code_paused.load(Ordering::Acquire) => vec![daemon],
+            _ => self.highest_priority_task_ids().await,
+        };
         let current_task_id = if let Some(task) = &self.current_task {
             task.lock().await.id
         } else {
             0
         };
-        let next_task = task_candidates
-            .iter()
-            .find(|id| **id > current_task_id)
-            .or_else(|| task_candidates.first())
-            .and_then(|id| self.by_id(*id));
+        let next_task_id = match &mut self.jitter {
+            Some(jitter) => jitter.pick(&task_candidates),
+            None => task_candidates
+                .iter()
+                .find(|id| **id > current_task_id)
+                .or_else(|| task_candidates.first())
+                .copied(),
+        };
+        let next_task = next_task_id.and_then(|id| self.by_id(id));
         self.current_task = next_task;
         self.current_task.is_some()
     }
 
-    pub async fn run_to_completion(host: &Host) -> anyhow::Result<()> {
-        let mut futures =
-            HashMap::<u32, Pin<Box<dyn Future<Output = anyhow::Result<()>> + Send>>>::new();
-        loop {
-            let mut tasks = host.tasks_lock().await;
-            let running = tasks.cycle_tasks().await;
-            if !running {
-                break Ok(());
+    /// Checks the invariants of the cooperative scheduler once it's picked the task to run
+    /// next, reporting each one the first time it's broken. See
+    /// [`SimulatorOptions::check_scheduler`](crate::SimulatorOptions::check_scheduler).
+    async fn check_invariants(&mut self) {
+        let mut broken = Vec::new();
+
+        let current_id = match &self.current_task {
+            Some(current) => {
+                let id = current.lock().await.id;
+                if !self
+                    .pool
+                    .get(&id)
+                    .is_some_and(|task| Arc::ptr_eq(task, current))
+                {
+                    broken.push((
+                        SchedulerInvariant::CurrentTask,
+                        format!("Task #{id} was chosen to run, but it isn't in the task table"),
+                    ));
+                }
+                Some(id)
+            }
+            None => {
+                broken.push((
+                    SchedulerInvariant::CurrentTask,
+                    "The scheduler is running, but no task was chosen to run".to_string(),
+                ));
+                None
+            }
+        };
+
+        for (id, task) in &self.pool {
+            let task = task.lock().await;
+            if matches!(task.state, TaskState::Finished | TaskState::Deleted)
+                || self.deleted_tasks.contains(id)
+            {
+                broken.push((
+                    SchedulerInvariant::NoFinishedTasks,
+                    format!(
+                        "Task `{}` (#{id}) is still in the task table after it {}",
+                        task.name,
+                        match task.state {
+                            TaskState::Finished => "finished",
+                            _ => "was deleted",
+                        }
+                    ),
+                ));
             }
+        }
 
-            let mut task = tasks.current_lock().await;
-            let id = task.id();
-            let future = futures.entry(id).or_insert_with(|| Box::pin(task.start()));
-            drop(task);
-            drop(tasks);
+        let holder = self.suspended_by();
+        if (self.scheduler_suspended == 0) != holder.is_none() {
+            broken.push((
+                SchedulerInvariant::SuspendBalance,
+                format!(
+                    "The scheduler has {} unmatched `rtos_suspend_all` calls, but {}",
+                    self.scheduler_suspended,
+                    match holder {
+                        Some(holder) => format!("task #{holder} holds it suspended"),
+                        None => "no task holds it suspended".to_string(),
+                    }
+                ),
+            ));
+        } else if let Some(holder) = holder {
+            if !self.pool.contains_key(&holder) || current_id != Some(holder) {
+                broken.push((
+                    SchedulerInvariant::SuspendBalance,
+                    format!(
+                        "Task #{holder} holds the scheduler suspended, but it isn't the task \
+                         that's running"
+                    ),
+                ));
+            }
+        }
 
-            let result = futures::poll!(future);
+        for (invariant, message) in broken {
+            if self.broken_invariants.contains(&invariant) {
+                continue;
+            }
+            self.broken_invariants.push(invariant);
+            self.interface
+                .send(SimulatorEvent::SchedulerInvariantViolated { invariant, message });
+        }
+    }
 
-            let tasks = host.tasks();
-            let mut tasks = tasks
-                .try_lock()
-                .expect("attempt to yield while task mutex is locked");
-            let task = tasks.current();
-            let mut task = task
-                .try_lock()
-                .expect("attempt to yield while current task is locked");
+    /// Runs tasks until they have all finished or the simulation is stopped.
+    pub async fn run_to_completion(host: &Host) -> StopReason {
+        if host.options().threaded {
+            return Self::run_threaded(host).await;
+        }
 
-            if tasks.shutdown_pending {
-                break Ok(());
+        let mut scheduler = Scheduler::new(host).await;
+        loop {
+            if let Some(reason) = scheduler.cycle(host).await {
+                break reason;
             }
 
-            if let Poll::Ready(result) = result {
-                task.marked_for_delete = true;
+            // Give the executor a chance to run other work, or to drop the simulation.
+            Self::yield_now().await;
+            host.interface().wait_for_unpause().await;
+        }
+    }
+
+    /// Runs each task on its own OS thread until they have all finished or the simulation is
+    /// stopped. See [`SimulatorOptions::threaded`](crate::SimulatorOptions::threaded).
+    async fn run_threaded(host: &Host) -> StopReason {
+        let (finished_tx, finished_rx) = mpsc::channel();
+        let mut threads = HashMap::<u32, (Arc<AtomicBool>, JoinHandle<()>)>::new();
+        let deadline = host.tasks_lock().await.deadline.clone();
+        if let Some(deadline) = &deadline {
+            deadline.start();
+        }
+        let (engine, parked) = {
+            let tasks = host.tasks_lock().await;
+            (tasks.engine.clone(), tasks.parked.clone())
+        };
+        let ticker = TickTimer::start(host.clock(), engine.clone());
+        let reason = 'scheduler: loop {
+            if let Some(reason) = deadline.as_ref().and_then(|deadline| deadline.passed(host)) {
+                break reason;
+            }
+            // before finished tasks are handled, so a task stopped by a limit isn't a crash
+            if let Some(reason) = host.limits().check(&host.interface()) {
+                break reason;
+            }
+
+            let mut tasks = host.tasks_lock().await;
+            if let Some(reason) = tasks.shutdown.take() {
+                break reason;
+            }
+
+            while let Ok((id, result)) = finished_rx.try_recv() {
+                threads.remove(&id);
+                // deleted tasks have already been removed from the pool
+                let Some(task) = tasks.pool.remove(&id) else {
+                    continue;
+                };
+                let mut task = task.lock().await;
                 task.state = TaskState::Finished;
-                result?;
-            } else if task.marked_for_delete {
-                task.state = TaskState::Deleted;
+                tasks.task_ended(&task);
+                if let Err(err) = result {
+                    if let Some(event) = Task::unimplemented_call(&err) {
+                        tasks.interface.send(event);
+                        tasks.fail_if_strict(WarningKind::UnimplementedCall);
+                        continue;
+                    }
+                    tasks.report_crash(&task, &err);
+                    if !tasks.isolate_crash(&mut task, host, &err) {
+                        break 'scheduler StopReason::Crashed(err);
+                    }
+                }
+            }
+
+            for (id, task) in &tasks.pool {
+                if threads.contains_key(id) {
+                    continue;
+                }
+                let mut task = task.lock().await;
+                let robot_code = tasks.daemon != Some(*id);
+                let paused = robot_code.then(|| tasks.robot_code_paused.clone());
+                let busy = robot_code.then(|| tasks.busy.clone());
+                match task.spawn_thread(
+                    tasks.suspended_by.clone(),
+                    paused,
+                    tasks.parked.clone(),
+                    busy,
+                    finished_tx.clone(),
+                ) {
+                    Ok(thread) => threads.insert(*id, (task.cancelled.clone(), thread)),
+                    Err(err) => break 'scheduler StopReason::Crashed(err.into()),
+                };
             }
 
-            if task.marked_for_delete {
-                if tasks.scheduler_suspended != 0 {
-                    // task called rtos_suspend_all and ended before calling rtos_resume_all
+            if threads.is_empty() {
+                break StopReason::Finished;
+            }
+
+            if let Some(canaries) = host.canaries() {
+                for buffer in canaries.check(&tasks.shared_memory) {
                     tasks.interface.send(SimulatorEvent::Warning(format!(
-                        "Task `{}` (#{}) exited with scheduler in suspended state",
-                        &task.name, task.id,
+                        "Robot code wrote out of bounds of {buffer}"
                     )));
+                    tasks.fail_if_strict(WarningKind::HeapCorruption);
                 }
-                drop(task);
-
-                tasks.scheduler_suspended = 0;
-                futures.remove(&id);
-                tasks.pool.remove(&id);
             }
+
+            drop(tasks);
+
+            host.interface().wait_for_unpause().await;
+            timer::sleep(Duration::from_millis(1)).await;
+            host.serial().flush();
+        };
+
+        drop(ticker);
+        for (cancelled, _) in threads.values() {
+            cancelled.store(true, Ordering::Release);
         }
+        parked.unpark_all();
+        while threads.values().any(|(_, thread)| !thread.is_finished()) {
+            engine.increment_epoch();
+            timer::sleep(Duration::from_millis(1)).await;
+        }
+
+        reason
+    }
+
+    /// How long robot code has spent running since the simulation started, not counting the
+    /// system daemon. The rest of the time, the brain's CPU would be idle. In threaded mode, tasks
+    /// running at the same time are each counted.
+    pub fn busy_time(&self) -> Duration {
+        Duration::from_nanos(self.busy.load(Ordering::Relaxed))
+    }
+
+    /// The number of tasks that haven't finished or been deleted.
+    pub fn task_count(&self) -> usize {
+        self.pool.len()
+    }
+
+    /// The number of tasks running robot code, which is all of them but the system daemon.
+    pub fn robot_code_task_count(&self) -> usize {
+        let daemon = self.daemon.is_some_and(|id| self.pool.contains_key(&id));
+        self.pool.len() - usize::from(daemon)
+    }
+
+    /// Describes every task in the pool, in the order they're scheduled.
+    pub async fn task_list(&self) -> Vec<TaskInfo> {
+        let current_id = match &self.current_task {
+            Some(task) => Some(task.lock().await.id),
+            None => None,
+        };
+        let mut list = Vec::with_capacity(self.pool.len());
+        for task in self.pool.values() {
+            let task = task.lock().await;
+            let state = match task.state {
+                TaskState::Finished | TaskState::Deleted => {
+                    pros_simulator_interface::TaskState::Finished
+                }
+                _ if current_id == Some(task.id) => pros_simulator_interface::TaskState::Running,
+                _ => pros_simulator_interface::TaskState::Ready,
+            };
+            list.push(TaskInfo {
+                id: task.id,
+                name: task.name.clone(),
+                priority: task.priority,
+                state,
+                system: self.daemon == Some(task.id),
+            });
+        }
+        list
     }
 
     pub async fn task_state(&self, task_id: u32) -> Option<TaskState> {
@@ -517,7 +1160,7 @@ impl TaskPool {
     }
 
     pub async fn delete_task(&mut self, task_id: u32) {
-        let task = self.pool.get(&task_id);
+        let task = self.pool.get(&task_id).cloned();
         if let Some(task) = task {
             let mut task = task.lock().await;
             if task.state == TaskState::Running {
@@ -527,39 +1170,370 @@ impl TaskPool {
             }
 
             task.state = TaskState::Deleted;
+            task.cancelled.store(true, Ordering::Release);
+            self.parked.unpark_all();
+            self.task_ended(&task);
             drop(task);
             self.pool.remove(&task_id).unwrap();
             self.deleted_tasks.insert(task_id);
         }
     }
 
-    pub fn start_shutdown(&mut self) {
-        self.shutdown_pending = true;
+    /// How much longer than requested a delay or timeout should last, which is always zero
+    /// unless jitter is enabled.
+    pub fn extra_delay(&mut self) -> Duration {
+        self.jitter
+            .as_mut()
+            .map_or(Duration::ZERO, Jitter::extra_delay)
+    }
+
+    /// Stops the simulation the next time the current task yields. If the simulation is already
+    /// stopping, the first reason is kept.
+    pub fn start_shutdown(&mut self, reason: StopReason) {
+        self.shutdown.get_or_insert(reason);
+    }
+
+    /// Tells the frontend that a task stopped because of an error.
+    fn report_crash(&self, task: &Task, err: &anyhow::Error) {
+        self.interface
+            .send(task.robot_code_error(&self.shared_memory, err));
+        if let Some(event) = task.data_abort(&self.shared_memory, err) {
+            self.interface.send(event);
+        }
+    }
+
+    /// Whether the simulation should carry on after a task crashed, which it does if
+    /// [`SimulatorOptions::isolate_crashes`](crate::SimulatorOptions::isolate_crashes) is
+    /// enabled or tests are being run, and the task isn't the system daemon. Tells the frontend
+    /// and records why the task crashed if it does.
+    fn isolate_crash(&self, task: &mut Task, host: &Host, err: &anyhow::Error) -> bool {
+        let options = host.options();
+        let isolated = options.isolate_crashes || options.test_pattern.is_some();
+        if !isolated || self.daemon == Some(task.id) {
+            return false;
+        }
+        task.error = Some(err.root_cause().to_string());
+        self.interface.send(SimulatorEvent::TaskCrashed {
+            task_id: task.id,
+            task_name: task.name.clone(),
+        });
+        true
+    }
+
+    /// Counts a failed `sim_assert`, stopping the simulation if failed assertions were made
+    /// strict. Call this after sending the
+    /// [`AssertionFailed`](SimulatorEvent::AssertionFailed) event.
+    pub fn assertion_failed(&mut self) {
+        self.failed_assertions += 1;
+        self.fail_if_strict(WarningKind::FailedAssertion);
+    }
+
+    /// How many `sim_assert`s have failed so far.
+    pub fn failed_assertions(&self) -> u32 {
+        self.failed_assertions
+    }
+
+    /// Stops the simulation with [`StopReason::StrictWarning`] if the given kind of warning was
+    /// made strict with [`SimulatorOptions::strict`](crate::SimulatorOptions::strict). Call this
+    /// after sending the warning.
+    pub fn fail_if_strict(&mut self, kind: WarningKind) {
+        if self.strict_warnings.contains(&kind) {
+            self.start_shutdown(StopReason::StrictWarning(kind));
+        }
+    }
+}
+
+/// The wakers of task threads that are paused until the scheduler or robot code is resumed, or
+/// until the task is cancelled.
+#[derive(Debug, Default)]
+struct ParkedTasks {
+    wakers: std::sync::Mutex<Vec<Waker>>,
+}
+
+impl ParkedTasks {
+    /// Returns ready once `ready` returns true, checking again each time the task's thread is
+    /// woken by [`unpark_all`](Self::unpark_all).
+    fn poll_until(&self, cx: &mut task::Context<'_>, ready: impl Fn() -> bool) -> Poll<()> {
+        if ready() {
+            return Poll::Ready(());
+        }
+        {
+            let mut wakers = self.wakers.lock().unwrap();
+            if !wakers.iter().any(|parked| parked.will_wake(cx.waker())) {
+                wakers.push(cx.waker().clone());
+            }
+        }
+        // checked again in case of an unpark between the first check and parking
+        if ready() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn unpark_all(&self) {
+        let wakers = std::mem::take(&mut *self.wakers.lock().unwrap());
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+/// When a simulation has to stop, even if its robot code never yields: once its [`Timeout`]
+/// passes, or once its [stop signal](crate::SimulatorOptions::stop_signal) is set. Clones share
+/// the same start time.
+#[derive(Debug, Clone)]
+struct Deadline {
+    timeout: Option<Timeout>,
+    stop_signal: Option<Arc<AtomicBool>>,
+    /// When tasks were first scheduled, for [`Timeout::RealTime`].
+    real_start_time: Arc<OnceLock<Instant>>,
+}
+
+impl Deadline {
+    fn new(timeout: Option<Timeout>, stop_signal: Option<Arc<AtomicBool>>) -> Option<Self> {
+        (timeout.is_some() || stop_signal.is_some()).then(|| Self {
+            timeout,
+            stop_signal,
+            real_start_time: Default::default(),
+        })
+    }
+
+    /// Starts the real time clock, if it hasn't been started.
+    fn start(&self) {
+        self.real_start_time.get_or_init(Instant::now);
+    }
+
+    /// Why the simulation has to stop, if it does.
+    fn passed(&self, host: &Host) -> Option<StopReason> {
+        if self
+            .stop_signal
+            .as_ref()
+            .is_some_and(|stop| stop.load(Ordering::Acquire))
+        {
+            return Some(StopReason::Cancelled);
+        }
+        let timed_out = match self.timeout? {
+            Timeout::Simulated(limit) => host.elapsed() > limit,
+            Timeout::RealTime(limit) => self
+                .real_start_time
+                .get()
+                .is_some_and(|start| start.elapsed() > limit),
+        };
+        timed_out.then_some(StopReason::TimedOut)
+    }
+}
+
+/// The cooperative scheduler's state between cycles, which run one task until it yields.
+pub(crate) struct Scheduler {
+    futures: HashMap<u32, Pin<Box<dyn Future<Output = anyhow::Result<()>> + Send>>>,
+    _ticker: TickTimer,
+    deadline: Option<Deadline>,
+}
+
+impl Scheduler {
+    pub async fn new(host: &Host) -> Self {
+        let tasks = host.tasks_lock().await;
+        let deadline = tasks.deadline.clone();
+        if let Some(deadline) = &deadline {
+            deadline.start();
+        }
+        // every tick, robot code that never yields is interrupted by the epoch to be sampled,
+        // preempted, or stopped once it runs out of time or is asked to stop
+        let _ticker = TickTimer::start(host.clock(), tasks.engine.clone());
+        Self {
+            futures: HashMap::new(),
+            _ticker,
+            deadline,
+        }
+    }
+
+    /// Runs the next task until it yields, returning why the simulation stopped if it did.
+    pub async fn cycle(&mut self, host: &Host) -> Option<StopReason> {
+        if let Some(reason) = self
+            .deadline
+            .as_ref()
+            .and_then(|deadline| deadline.passed(host))
+        {
+            return Some(reason);
+        }
+
+        let mut tasks = host.tasks_lock().await;
+        let running = tasks.cycle_tasks().await;
+        if !running {
+            return Some(StopReason::Finished);
+        }
+        if host.options().check_scheduler {
+            tasks.check_invariants().await;
+        }
+
+        let mut task = tasks.current_lock().await;
+        let id = task.id();
+        let busy = (tasks.daemon != Some(id)).then(|| tasks.busy.clone());
+        tasks.slice_start.store(host.ticks(), Ordering::Release);
+        let future = self
+            .futures
+            .entry(id)
+            .or_insert_with(|| Box::pin(task.start()));
+        drop(task);
+        drop(tasks);
+
+        let started = Instant::now();
+        let result = futures::poll!(future);
+        if let Some(busy) = busy {
+            busy.fetch_add(started.elapsed().as_nanos() as u64, Ordering::Relaxed);
+        }
+        host.serial().flush();
+
+        let tasks = host.tasks();
+        let mut tasks = tasks
+            .try_lock()
+            .expect("attempt to yield while task mutex is locked");
+        let task = tasks.current();
+        let mut task = task
+            .try_lock()
+            .expect("attempt to yield while current task is locked");
+
+        if let Some(canaries) = host.canaries() {
+            for buffer in canaries.check(&tasks.shared_memory) {
+                tasks.interface.send(SimulatorEvent::Warning(format!(
+                    "Task `{}` wrote out of bounds of {buffer}",
+                    task.name
+                )));
+                tasks.fail_if_strict(WarningKind::HeapCorruption);
+            }
+        }
+
+        // before the task's result is handled, so a task stopped by a limit isn't a crash
+        if let Some(reason) = host.limits().check(&tasks.interface) {
+            return Some(reason);
+        }
+        if let Some(reason) = tasks.shutdown.take() {
+            return Some(reason);
+        }
+
+        if let Poll::Ready(result) = result {
+            task.marked_for_delete = true;
+            task.state = TaskState::Finished;
+            if let Err(err) = result {
+                if let Some(event) = Task::unimplemented_call(&err) {
+                    tasks.interface.send(event);
+                    tasks.fail_if_strict(WarningKind::UnimplementedCall);
+                } else {
+                    tasks.report_crash(&task, &err);
+                    if !tasks.isolate_crash(&mut task, host, &err) {
+                        return Some(StopReason::Crashed(err));
+                    }
+                }
+            }
+        } else if task.marked_for_delete {
+            task.state = TaskState::Deleted;
+        }
+
+        if task.marked_for_delete {
+            tasks.task_ended(&task);
+            drop(task);
+
+            self.futures.remove(&id);
+            tasks.pool.remove(&id);
+        }
+        tasks.shutdown.take()
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// A task's `errno`. Clones share the same value.
+///
+/// It's allocated when the task is spawned if robot code has its own allocator, so failing API
+/// calls don't need to. Otherwise it's allocated from the [`HostHeap`](super::heap::HostHeap)
+/// the first time it's used, since allocating from the heap before robot code calls `sbrk`
+/// would keep the program break from starting at `__heap_base`.
+#[derive(Clone)]
 pub struct Errno {
-    address: u32,
+    inner: Arc<ErrnoState>,
+}
+
+struct ErrnoState {
+    allocator: WasmAllocator,
+    address: OnceCell<u32>,
+    /// Whether an API call has failed and set it.
+    set: AtomicBool,
+    /// Whether robot code has asked for its address.
+    read: AtomicBool,
 }
 
 impl Errno {
     pub async fn new(
-        store: impl AsContextMut<Data = impl Send>,
+        store: impl AsContextMut<Data = Host>,
         allocator: &WasmAllocator,
-    ) -> Self {
-        let address = allocator
-            .memalign(store, std::alloc::Layout::new::<i32>())
-            .await;
-        Self { address }
+    ) -> anyhow::Result<Self> {
+        let errno = Self {
+            inner: Arc::new(ErrnoState {
+                allocator: allocator.clone(),
+                address: OnceCell::new(),
+                set: AtomicBool::new(false),
+                read: AtomicBool::new(false),
+            }),
+        };
+        if let WasmAllocator::Guest { .. } = allocator {
+            errno.allocate(store).await?;
+        }
+        Ok(errno)
     }
-    pub fn address(&self) -> u32 {
-        self.address
+
+    /// Returns the address, allocating it if it hasn't been.
+    async fn allocate(&self, store: impl AsContextMut<Data = Host>) -> anyhow::Result<u32> {
+        let allocator = &self.inner.allocator;
+        self.inner
+            .address
+            .get_or_try_init(|| allocator.memalign(store, Layout::new::<i32>()))
+            .await
+            .copied()
     }
-    pub fn set(&self, memory: &SharedMemory, new_errno: i32) {
-        let buffer = new_errno.to_le_bytes();
-        memory
-            .write_relaxed(self.address as usize, &buffer)
+
+    /// The address robot code reads and writes it at, allocating it if it hasn't been. Fails if
+    /// it couldn't be allocated.
+    pub async fn address(&self, store: impl AsContextMut<Data = Host>) -> anyhow::Result<u32> {
+        self.allocate(store)
+            .await
+            .context("Couldn't allocate errno")
+    }
+
+    /// Records that robot code has asked for the address, so it may have read or written it.
+    pub fn mark_read(&self) {
+        self.inner.read.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether an API call has set it yet.
+    pub fn is_set(&self) -> bool {
+        self.inner.set.load(Ordering::Relaxed)
+    }
+
+    /// The current value, if an API call has set it or robot code has read it.
+    pub fn value(&self, memory: &SharedMemory) -> Option<i32> {
+        if !self.is_set() && !self.inner.read.load(Ordering::Relaxed) {
+            return None;
+        }
+        let address = *self.inner.address.get()?;
+        let buffer = memory
+            .read_relaxed(address as usize, size_of::<i32>())
             .unwrap();
+        Some(i32::from_le_bytes(buffer.try_into().unwrap()))
+    }
+
+    /// Sets the value. Fails if it couldn't be allocated.
+    pub async fn set(
+        &self,
+        mut store: impl AsContextMut<Data = Host>,
+        new_errno: i32,
+    ) -> anyhow::Result<()> {
+        let address = self.address(&mut store).await?;
+        self.inner.set.store(true, Ordering::Relaxed);
+        let buffer = new_errno.to_le_bytes();
+        store
+            .as_context()
+            .data()
+            .memory()
+            .write_relaxed(address as usize, &buffer)?;
+        Ok(())
     }
 }
@@ -1,9 +1,10 @@
 use std::mem::size_of;
 
+use anyhow::{ensure, Context};
 use async_trait::async_trait;
 use wasmtime::{AsContextMut, SharedMemory};
 
-use super::{memory::SharedMemoryExt, HostCtx, WasmAllocator};
+use super::{memory::SharedMemoryExt, Host, HostCtx, WasmAllocator};
 
 pub const NUM_THREAD_LOCAL_STORAGE_POINTERS: usize = 5;
 
@@ -36,66 +37,62 @@ pub struct TaskStorage {
 
 impl TaskStorage {
     pub async fn new(
-        store: impl AsContextMut<Data = impl Send>,
+        store: impl AsContextMut<Data = Host>,
         allocator: &WasmAllocator,
-    ) -> Self {
+    ) -> anyhow::Result<Self> {
         let base_ptr = allocator
             .memalign(
                 store,
                 std::alloc::Layout::new::<[u32; NUM_THREAD_LOCAL_STORAGE_POINTERS]>(),
             )
-            .await;
-        Self { base_ptr }
+            .await
+            .context("couldn't allocate the task's thread local storage")?;
+        Ok(Self { base_ptr })
     }
 
-    fn assert_in_bounds(index: i32) {
-        if index < 0 || index as usize >= NUM_THREAD_LOCAL_STORAGE_POINTERS {
-            panic!(
-                "Thread local storage index out of bounds:\n\
-                index {index} should be more than 0 and less than {NUM_THREAD_LOCAL_STORAGE_POINTERS}."
-            );
-        }
+    fn check_in_bounds(index: i32) -> anyhow::Result<()> {
+        ensure!(
+            index >= 0 && (index as usize) < NUM_THREAD_LOCAL_STORAGE_POINTERS,
+            "Thread local storage index out of bounds:\n\
+            index {index} should be more than 0 and less than {NUM_THREAD_LOCAL_STORAGE_POINTERS}."
+        );
+        Ok(())
     }
-    pub fn get_address(&self, index: i32) -> u32 {
-        Self::assert_in_bounds(index);
+    pub fn get_address(&self, index: i32) -> anyhow::Result<u32> {
+        Self::check_in_bounds(index)?;
 
-        self.base_ptr + (index as u32 * size_of::<u32>() as u32)
+        Ok(self.base_ptr + (index as u32 * size_of::<u32>() as u32))
     }
-    pub fn get(&self, memory: SharedMemory, index: i32) -> u32 {
-        Self::assert_in_bounds(index);
-        let address = self.get_address(index);
-        let buffer = memory
-            .read_relaxed(address as usize, size_of::<u32>())
-            .unwrap();
-        u32::from_le_bytes(buffer.try_into().unwrap())
+    pub fn get(&self, memory: SharedMemory, index: i32) -> anyhow::Result<u32> {
+        let address = self.get_address(index)?;
+        let buffer = memory.read_relaxed(address as usize, size_of::<u32>())?;
+        Ok(u32::from_le_bytes(buffer.try_into().unwrap()))
     }
-    pub fn set(&mut self, memory: SharedMemory, index: i32, value: u32) {
-        Self::assert_in_bounds(index);
-        let address = self.get_address(index);
+    pub fn set(&mut self, memory: SharedMemory, index: i32, value: u32) -> anyhow::Result<()> {
+        let address = self.get_address(index)?;
         let buffer = value.to_le_bytes();
-        memory.write_relaxed(address as usize, &buffer).unwrap();
+        memory.write_relaxed(address as usize, &buffer)?;
+        Ok(())
     }
 }
 
 #[async_trait]
 pub trait GetTaskStorage {
-    async fn task_storage(&mut self, task_handle: u32) -> TaskStorage;
+    /// The thread local storage of the task with this handle, or the current task if it's 0.
+    /// Returns `None` if there isn't a task with this handle.
+    async fn task_storage(&mut self, task_handle: u32) -> anyhow::Result<Option<TaskStorage>>;
 }
 
 #[async_trait]
-impl<T, D> GetTaskStorage for T
+impl<T> GetTaskStorage for T
 where
-    T: HostCtx + wasmtime::AsContextMut<Data = D> + Send,
-    D: Send,
+    T: HostCtx + wasmtime::AsContextMut<Data = Host> + Send + Sync,
 {
-    async fn task_storage(&mut self, task_handle: u32) -> TaskStorage {
-        let task = self
-            .tasks_lock()
-            .await
-            .by_id(task_handle)
-            .expect("invalid task handle");
-
+    async fn task_storage(&mut self, task_handle: u32) -> anyhow::Result<Option<TaskStorage>> {
+        let Some(task) = self.task_by_handle(task_handle).await else {
+            return Ok(None);
+        };
         let mut task = task.lock().await;
-        task.local_storage(self).await
+        task.local_storage(self).await.map(Some)
     }
 }
@@ -0,0 +1,108 @@
+//! Delays that work under any async executor, so the simulator doesn't depend on Tokio's timers.
+//!
+//! A single background thread wakes futures once their deadline has passed. A delay also checks
+//! the time whenever it's polled, so it ends on time under the cooperative scheduler, which polls
+//! every task each cycle regardless of whether it was woken.
+
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    future::poll_fn,
+    sync::{Condvar, Mutex, OnceLock},
+    task::{Poll, Waker},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Wakers waiting on a deadline, soonest first.
+#[derive(Default)]
+struct Timers {
+    queue: Mutex<BinaryHeap<Reverse<Deadline>>>,
+    changed: Condvar,
+}
+
+struct Deadline(Instant, Waker);
+
+impl PartialEq for Deadline {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for Deadline {}
+
+impl PartialOrd for Deadline {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Deadline {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+fn timers() -> &'static Timers {
+    static TIMERS: OnceLock<&'static Timers> = OnceLock::new();
+    TIMERS.get_or_init(|| {
+        let timers: &'static Timers = Box::leak(Box::default());
+        thread::Builder::new()
+            .name("pros-simulator timer".into())
+            .spawn(|| run_timers(timers))
+            .expect("failed to spawn the timer thread");
+        timers
+    })
+}
+
+fn run_timers(timers: &Timers) {
+    let mut queue = timers.queue.lock().unwrap();
+    loop {
+        let now = Instant::now();
+        while queue
+            .peek()
+            .is_some_and(|Reverse(deadline)| deadline.0 <= now)
+        {
+            let Reverse(Deadline(_, waker)) = queue.pop().unwrap();
+            waker.wake();
+        }
+        queue = match queue.peek() {
+            Some(Reverse(Deadline(at, _))) => {
+                let timeout = at.saturating_duration_since(now);
+                timers.changed.wait_timeout(queue, timeout).unwrap().0
+            }
+            None => timers.changed.wait(queue).unwrap(),
+        };
+    }
+}
+
+/// Waits until the given time.
+pub async fn sleep_until(end: Instant) {
+    let mut registered = None::<Waker>;
+    poll_fn(|cx| {
+        if Instant::now() >= end {
+            return Poll::Ready(());
+        }
+        // register again if the future has moved to another executor task since it last did
+        if !registered
+            .as_ref()
+            .is_some_and(|waker| waker.will_wake(cx.waker()))
+        {
+            registered = Some(cx.waker().clone());
+            let timers = timers();
+            timers
+                .queue
+                .lock()
+                .unwrap()
+                .push(Reverse(Deadline(end, cx.waker().clone())));
+            timers.changed.notify_one();
+        }
+        Poll::Pending
+    })
+    .await
+}
+
+/// Waits for the given amount of time.
+pub async fn sleep(duration: Duration) {
+    sleep_until(Instant::now() + duration).await
+}
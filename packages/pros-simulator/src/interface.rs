@@ -1,10 +1,31 @@
-use std::sync::{Arc, Mutex};
+use std::sync::{
+    atomic::{AtomicU32, AtomicU64, Ordering},
+    Arc, Mutex,
+};
 
-use pros_simulator_interface::SimulatorEvent;
+use pros_simulator_interface::{ChannelStats, SimulatorEvent};
+use tokio::sync::oneshot;
 
+#[cfg(feature = "event-log")]
+use crate::host::event_log::EventLog;
+use crate::trace::trace_event;
+
+type Callback = Arc<Mutex<dyn FnMut(SimulatorEvent) + Send>>;
+
+/// Where a simulation sends its events: the callback it was made from, and any
+/// [subscribers](Self::with_subscriber) added to it, e.g. to record events to a file or collect
+/// metrics alongside a frontend.
 #[derive(Clone)]
 pub struct SimulatorInterface {
-    callback: Arc<Mutex<dyn FnMut(SimulatorEvent) + Send>>,
+    callback: Callback,
+    subscribers: Vec<Subscriber>,
+    pauses: PauseQueue,
+    counters: Arc<ChannelCounters>,
+    /// Whether events are also recorded with `tracing`.
+    trace: bool,
+    /// Where events are also written to disk, if anywhere.
+    #[cfg(feature = "event-log")]
+    event_log: Option<EventLog>,
 }
 
 impl<T> From<T> for SimulatorInterface
@@ -14,13 +35,156 @@ where
     fn from(callback: T) -> Self {
         Self {
             callback: Arc::new(Mutex::new(callback)),
+            subscribers: Vec::new(),
+            pauses: PauseQueue::default(),
+            counters: Arc::default(),
+            trace: false,
+            #[cfg(feature = "event-log")]
+            event_log: None,
         }
     }
 }
 
+/// A callback that gets the events its filter accepts.
+#[derive(Clone)]
+struct Subscriber {
+    filter: Arc<dyn Fn(&SimulatorEvent) -> bool + Send + Sync>,
+    callback: Callback,
+}
+
 impl SimulatorInterface {
-    pub(crate) fn send(&self, event: SimulatorEvent) {
+    /// Also send the events `filter` accepts to `callback`. Each event goes to the subscribers
+    /// that want it in the order they were added, then to the callback the interface was made
+    /// from. Only that callback can pause the simulation or see its events queued.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn run() -> anyhow::Result<()> {
+    /// use std::{path::Path, sync::mpsc};
+    ///
+    /// use pros_simulator::{interface::SimulatorInterface, SimulatorOptions};
+    /// use pros_simulator_interface::SimulatorEvent;
+    ///
+    /// let interface = SimulatorInterface::from(|event| println!("{event:?}")).with_subscriber(
+    ///     |event| matches!(event, SimulatorEvent::Warning(_)),
+    ///     |event| eprintln!("{event:?}"),
+    /// );
+    /// let (_messages, rx) = mpsc::channel();
+    /// pros_simulator::simulate(Path::new("robot.wasm"), SimulatorOptions::new(), interface, rx)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_subscriber(
+        mut self,
+        filter: impl Fn(&SimulatorEvent) -> bool + Send + Sync + 'static,
+        callback: impl FnMut(SimulatorEvent) + Send + 'static,
+    ) -> Self {
+        self.subscribers.push(Subscriber {
+            filter: Arc::new(filter),
+            callback: Arc::new(Mutex::new(callback)),
+        });
+        self
+    }
+
+    /// Sends an event to the frontend and any subscribers that want it, e.g. from a host-side
+    /// task.
+    pub fn send(&self, event: SimulatorEvent) {
+        self.counters.sent.fetch_add(1, Ordering::Relaxed);
+        if self.trace {
+            trace_event(&event);
+        }
+        #[cfg(feature = "event-log")]
+        if let Some(event_log) = &self.event_log {
+            event_log.record(&event);
+        }
+        for subscriber in &self.subscribers {
+            if (subscriber.filter)(&event) {
+                (subscriber.callback.lock().unwrap())(event.clone());
+            }
+        }
         let mut callback = self.callback.lock().unwrap();
         callback(event);
     }
+
+    /// How many events have been sent, and what the event queue has done with them.
+    pub fn stats(&self) -> ChannelStats {
+        self.counters.stats()
+    }
+
+    /// Use the given queue to let the event callback pause the simulation.
+    pub(crate) fn with_pauses(mut self, pauses: PauseQueue) -> Self {
+        self.pauses = pauses;
+        self
+    }
+
+    /// Use the given counters, which the event callback's queue also updates.
+    pub(crate) fn with_counters(mut self, counters: Arc<ChannelCounters>) -> Self {
+        self.counters = counters;
+        self
+    }
+
+    /// Also record every event sent through this interface with `tracing`.
+    pub(crate) fn with_tracing(mut self, trace: bool) -> Self {
+        self.trace = trace;
+        self
+    }
+
+    /// Also write every event sent through this interface to the given log.
+    #[cfg(feature = "event-log")]
+    pub(crate) fn with_event_log(mut self, event_log: Option<EventLog>) -> Self {
+        self.event_log = event_log;
+        self
+    }
+
+    /// The log events are written to, if any.
+    #[cfg(feature = "event-log")]
+    pub(crate) fn event_log(&self) -> Option<&EventLog> {
+        self.event_log.as_ref()
+    }
+
+    /// Waits until every pause requested by the event callback has been lifted.
+    pub(crate) async fn wait_for_unpause(&self) {
+        loop {
+            let Some(unpause) = self.pauses.0.lock().unwrap().pop() else {
+                break;
+            };
+            _ = unpause.await;
+        }
+    }
+}
+
+/// Pauses requested by an event callback. Because callbacks can't block the async scheduler,
+/// they push the receiving end of a channel here instead and the scheduler waits for it to
+/// resolve before running any more robot code.
+#[derive(Clone, Default)]
+pub(crate) struct PauseQueue(Arc<Mutex<Vec<oneshot::Receiver<()>>>>);
+
+impl PauseQueue {
+    /// Pause the simulation until `unpause` is sent to or dropped.
+    pub fn push(&self, unpause: oneshot::Receiver<()>) {
+        self.0.lock().unwrap().push(unpause);
+    }
+}
+
+/// Counts of the events sent through an interface. Frontends that queue events update the
+/// queue's counts.
+#[derive(Default)]
+pub(crate) struct ChannelCounters {
+    sent: AtomicU64,
+    pub queued: AtomicU32,
+    pub dropped: AtomicU64,
+    pub coalesced: AtomicU64,
+}
+
+impl ChannelCounters {
+    fn stats(&self) -> ChannelStats {
+        ChannelStats {
+            sent: self.sent.load(Ordering::Relaxed),
+            queued: self.queued.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            coalesced: self.coalesced.load(Ordering::Relaxed),
+        }
+    }
 }
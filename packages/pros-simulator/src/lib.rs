@@ -1,61 +1,328 @@
 use std::{path::Path, sync::mpsc::Receiver};
 
-use anyhow::Result;
-use host::{task::TaskPool, Host};
+use anyhow::{bail, Context, Result};
+pub use config::SimulatorConfig;
+#[cfg(feature = "render")]
+pub use host::render::FrameOutput;
+use host::{
+    abi::{unsupported_imports, ProgramAbi, VEX_MODULE},
+    atomics::instrument_atomics,
+    component::{lower_component, MAX_PAGES},
+    coverage::coverage_report,
+    memory::SharedMemoryExt,
+    Host,
+};
+pub use host::{
+    program_info::read_program_info,
+    task::{TaskOptions, TaskPool},
+    HostCtx,
+};
 use interface::SimulatorInterface;
-use pros_simulator_interface::{SimulatorEvent, SimulatorMessage};
+pub use options::{MatchTiming, OverflowPolicy, SimulatorOptions, StartKind, Timeout, WarningKind};
+pub use outcome::{SimulationOutcome, StopReason};
+use pros_simulator_interface::{DisplayGeometry, SimulatorEvent, SimulatorMessage};
+pub use simulation::{Simulation, StepResult};
+pub use step_response::StepResponse;
+pub use sweep::{Sweep, SweepRun};
+pub use timing::{TimedEvent, Timeline};
 use wasmtime::*;
 
-use crate::system::system_daemon::system_daemon_initialize;
+use crate::system::{
+    system_daemon::system_daemon_initialize, telemetry::brain_header, test_runner::test_names,
+};
 
 mod api;
-pub mod host;
+mod config;
+pub mod extension;
+mod host;
 pub mod interface;
+mod options;
+mod outcome;
+mod simulation;
+mod step_response;
 pub mod stream;
+mod sweep;
 mod system;
+mod timing;
+mod trace;
 
 /// Simulate the WebAssembly robot program at the given path.
 ///
 /// # Arguments
 ///
 /// - `robot_code`: The path to the robot program to simulate.
+/// - `options`: Configuration for the simulation.
 /// - `interface`: A callback function that will be invoked with any events that occur during
 ///   simulation.
 /// - `messages`: Input message stream to send to the robot program. This can be used to simulate
-///  controller input, LCD touch events, and more.
+///   controller input, LCD touch events, and more.
+///
+/// Returns how the robot code stopped, or an error if it couldn't be loaded. This is shorthand
+/// for [`load`], [`start`], [`TaskPool::run_to_completion`] and [`finish`], which can be called
+/// separately to schedule the simulation by hand. [`Simulation`] does the same one scheduler
+/// cycle at a time, for frontends that step it from a synchronous loop.
 pub async fn simulate(
     robot_code: &Path,
+    options: SimulatorOptions,
     interface: impl Into<SimulatorInterface>,
     messages: Receiver<SimulatorMessage>,
-) -> Result<()> {
+) -> Result<SimulationOutcome> {
+    let host = load(robot_code, options, interface)?;
+    start(&host, messages).await?;
+    let reason = TaskPool::run_to_completion(&host).await;
+    Ok(finish(&host, reason))
+}
+
+/// Compile the WebAssembly robot program at the given path and create the simulator state it
+/// runs against, without starting it.
+///
+/// The returned [`Host`] owns every task's state, including the [`TaskPool`] that schedules them.
+/// Embedders can use it to spawn their own host-side tasks with
+/// [`TaskOptions::new_closure`], which are scheduled
+/// alongside the robot code's tasks.
+///
+/// # Example
+///
+/// ```no_run
+/// # async fn run() -> anyhow::Result<()> {
+/// use std::{path::Path, sync::mpsc};
+///
+/// use pros_simulator::{HostCtx, SimulatorOptions, TaskOptions, TaskPool};
+///
+/// let (_messages, rx) = mpsc::channel();
+/// let host = pros_simulator::load(
+///     Path::new("robot.wasm"),
+///     SimulatorOptions::new(),
+///     |event| println!("{event:?}"),
+/// )?;
+/// pros_simulator::start(&host, rx).await?;
+///
+/// {
+///     let mut tasks = host.tasks_lock().await;
+///     let watchdog = TaskOptions::new_closure(&mut tasks, &host, |caller| {
+///         Box::new(async move {
+///             println!("robot code has been running for {} ms", caller.millis());
+///             Ok(())
+///         })
+///     })?
+///     .name("Watchdog");
+///     tasks.spawn(watchdog, &host.module(), &host.interface()).await?;
+/// }
+///
+/// let reason = TaskPool::run_to_completion(&host).await;
+/// let outcome = pros_simulator::finish(&host, reason);
+/// # Ok(())
+/// # }
+/// ```
+pub fn load(
+    robot_code: &Path,
+    options: SimulatorOptions,
+    interface: impl Into<SimulatorInterface>,
+) -> Result<Host> {
     let interface: SimulatorInterface = interface.into();
+    let interface = interface.with_tracing(options.trace_events);
+    #[cfg(feature = "event-log")]
+    let interface = interface.with_event_log(
+        options
+            .event_log
+            .as_deref()
+            .map(host::event_log::EventLog::create)
+            .transpose()?,
+    );
+    let mut wasm = std::fs::read(robot_code)?;
+    let mut initial_pages = 18;
+    let mut data = Vec::new();
+    if wasmparser::Parser::is_component(&wasm) {
+        let component = lower_component(&wasm)?;
+        wasm = component.wasm;
+        initial_pages = component.initial_pages.max(initial_pages);
+        data = component.data;
+    }
+    let (mut info, info_warnings) = read_program_info(&wasm);
+    let wasm = instrument_atomics(wasm)?;
+
     tracing::info!("Initializing WASM runtime");
-    let engine = Engine::new(
-        Config::new()
-            .async_support(true)
-            .wasm_threads(true)
-            .debug_info(true)
-            .wasm_backtrace_details(WasmBacktraceDetails::Enable),
-    )
-    .unwrap();
+    let mut config = Config::new();
+    config
+        .async_support(true)
+        .wasm_threads(true)
+        // tasks are preempted at every tick
+        .epoch_interruption(true)
+        .debug_info(true)
+        .wasm_backtrace_details(WasmBacktraceDetails::Enable);
+    if let Some(max_tasks) = options.instance_pool {
+        config.allocation_strategy(InstanceAllocationStrategy::Pooling(pooling_config(
+            &wasm, max_tasks,
+        )));
+    }
+    let engine = Engine::new(&config)?;
 
     tracing::info!("JIT compiling your robot code... 🚀");
     interface.send(SimulatorEvent::RobotCodeLoading);
 
-    let module = Module::from_file(&engine, robot_code)?;
+    let module = Module::new(&engine, wasm)?;
+
+    let shared_memory = SharedMemory::new(&engine, MemoryType::shared(initial_pages, MAX_PAGES))?;
+    for (address, bytes) in data {
+        shared_memory
+            .write_relaxed(address as usize, &bytes)
+            .context("Robot code has a data segment outside of its memory")?;
+    }
+    let host = Host::new(engine, shared_memory, interface.clone(), module, options)?;
 
-    let shared_memory = SharedMemory::new(&engine, MemoryType::shared(18, 16384))?;
-    let host = Host::new(
-        engine.clone(),
-        shared_memory.clone(),
-        interface.clone(),
-        module.clone(),
-    )?;
+    let abi = host.abi();
+    info.abi = abi;
+    info.display = Some(DisplayGeometry::default());
+    interface.send(SimulatorEvent::ProgramInfo(info));
+    for warning in info_warnings {
+        interface.send(SimulatorEvent::Warning(warning));
+    }
+    if abi == ProgramAbi::Unknown {
+        let module = host.module();
+        let modules = unsupported_imports(&module)
+            .into_iter()
+            .map(|module| format!("`{module}`"))
+            .collect::<Vec<_>>();
+        bail!(
+            "Robot code imports from {}, so it doesn't look like it was built for PROS or \
+             vexide. The simulator can run programs built with pros-rs, the C/C++ PROS \
+             template or vexide, which import from `env` or `{VEX_MODULE}`.",
+            modules.join(", ")
+        );
+    }
+    Ok(host)
+}
+
+/// Spawn the system daemon of a [`load`]ed simulation, which runs the robot code's entrypoints
+/// and applies `messages` as they arrive. Tasks don't run until the [`TaskPool`] is scheduled,
+/// e.g. with [`TaskPool::run_to_completion`].
+pub async fn start(host: &Host, messages: Receiver<SimulatorMessage>) -> Result<()> {
+    system_daemon_initialize(host, messages).await?;
+    if let Some(drivetrain) = &host.options().drivetrain {
+        host.interface()
+            .send(SimulatorEvent::DrivetrainConfigured(drivetrain.clone()));
+    }
+    host.interface().send(SimulatorEvent::RobotCodeStarting);
+    host.interface()
+        .send(SimulatorEvent::BrainHeader(brain_header(host, true)));
+    Ok(())
+}
+
+/// Finish a simulation that stopped for the given reason: flush console output, write the
+/// reports requested in its [`SimulatorOptions`] and send the final events.
+pub fn finish(host: &Host, reason: StopReason) -> SimulationOutcome {
+    let interface = host.interface();
+    host.serial().flush_all();
+    // every task has stopped, so nothing else can be holding the lock
+    if let Ok(mut ports) = host.smart_ports().try_lock() {
+        for event in ports.flush_motor_updates(host.millis(), host.ticks()) {
+            interface.send(event);
+        }
+    }
+    let coverage = host.api_usage().coverage(&host.module());
+    if let Some(path) = &host.options().coverage_report {
+        if let Err(err) = std::fs::write(path, coverage_report(&coverage)) {
+            interface.send(SimulatorEvent::Warning(format!(
+                "Failed to write the coverage report to {}: {err}",
+                path.display()
+            )));
+        }
+    }
+    interface.send(SimulatorEvent::ApiCoverage(coverage));
+    if let (Some(profiler), Some(path)) = (host.profiler(), &host.options().profile) {
+        if let Err(err) = profiler.write(path) {
+            interface.send(SimulatorEvent::Warning(format!(
+                "Failed to write the profile to {}: {err}",
+                path.display()
+            )));
+        }
+    }
+    if let (Some(trace), Some(path)) = (host.chrome_trace(), &host.options().chrome_trace) {
+        if let Err(err) = trace.write(path, host.ticks()) {
+            interface.send(SimulatorEvent::Warning(format!(
+                "Failed to write the Chrome trace to {}: {err}",
+                path.display()
+            )));
+        }
+    }
+    #[cfg(feature = "otlp")]
+    if let Some(spans) = host.spans() {
+        if let Err(err) = spans.finish() {
+            interface.send(SimulatorEvent::Warning(err));
+        }
+    }
+    #[cfg(feature = "render")]
+    if let Some(renderer) = host.renderer() {
+        if let Err(err) = renderer.finish() {
+            interface.send(SimulatorEvent::Warning(err));
+        }
+    }
+    interface.send(SimulatorEvent::BrainHeader(brain_header(host, false)));
+    if !matches!(reason, StopReason::Crashed(_)) {
+        interface.send(SimulatorEvent::RobotCodeFinished);
+    }
+    #[cfg(feature = "event-log")]
+    if let Some(event_log) = interface.event_log() {
+        if let Err(err) = event_log.finish() {
+            interface.send(SimulatorEvent::Warning(err));
+        }
+    }
+
+    SimulationOutcome {
+        reason,
+        simulated_time: host.elapsed(),
+    }
+}
 
-    system_daemon_initialize(&host, messages).await?;
+/// Compile the WebAssembly robot program at the given path and link it against the simulator's
+/// implementation of the PROS API without running it.
+///
+/// A [`SimulatorEvent::Warning`] is sent for every API the robot code imports that the simulator
+/// does not implement.
+pub async fn check(robot_code: &Path, interface: impl Into<SimulatorInterface>) -> Result<()> {
+    let interface: SimulatorInterface = interface.into();
+    let host = load(robot_code, SimulatorOptions::default(), interface.clone())?;
 
-    TaskPool::run_to_completion(&host).await?;
-    interface.send(SimulatorEvent::RobotCodeFinished);
+    let mut tasks = host.tasks_lock().await;
+    let mut store = tasks.create_store(&host)?;
+    tasks
+        .instantiate(&mut store, &host.module(), &interface)
+        .await?;
 
     Ok(())
 }
+
+/// Compile the WebAssembly robot program at the given path and list the tests that
+/// [`SimulatorOptions::run_tests`] would run with the given pattern, in the order it would run
+/// them. Frontends can use this to run each test in a simulation of its own, e.g. in parallel.
+pub fn list_tests(robot_code: &Path, pattern: &str) -> Result<Vec<String>> {
+    let host = load(robot_code, SimulatorOptions::default(), |_| {})?;
+    Ok(test_names(&host.module(), pattern))
+}
+
+/// Sizes the pooling instance allocator for `max_tasks` instances of the robot code. Each task
+/// instantiates the module with its own function table, but they all import the same memory, so
+/// the pool doesn't need to reserve any. Host functions can call back into robot code (e.g. to
+/// allocate memory) while a task is running, which takes a second stack.
+fn pooling_config(wasm: &[u8], max_tasks: u32) -> PoolingAllocationConfig {
+    let largest_table = wasmparser::Parser::new(0)
+        .parse_all(wasm)
+        .filter_map(|payload| match payload {
+            Ok(wasmparser::Payload::TableSection(tables)) => Some(tables),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|table| Some(table.ok()?.ty.initial))
+        .max()
+        .unwrap_or(0);
+
+    let mut config = PoolingAllocationConfig::default();
+    config
+        .total_core_instances(max_tasks)
+        .total_stacks(max_tasks * 2)
+        .total_tables(max_tasks)
+        .table_elements(largest_table.max(10_000))
+        .total_memories(0)
+        .max_memories_per_module(0);
+    config
+}
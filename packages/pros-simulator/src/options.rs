@@ -0,0 +1,788 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    path::PathBuf,
+    str::FromStr,
+    sync::{atomic::AtomicBool, Arc},
+    time::Duration,
+};
+
+use pros_simulator_interface::{
+    ControllerId, DeviceType, DrivetrainConfig, GameObject, InputShaping, Mechanism, MotorGroup,
+    ProsVersion, ScoringZone,
+};
+
+#[cfg(feature = "render")]
+use crate::host::render::FrameOutput;
+use crate::{
+    host::smart_ports::{check_drivetrain, check_motor_group, drivetrain_groups, NUM_SMART_PORTS},
+    system::{field::check_object, mechanisms::check_mechanism, scoring::check_shape},
+};
+
+/// Options for configuring how robot code is simulated.
+///
+/// # Example
+///
+/// ```
+/// # use std::{path::PathBuf, time::Duration};
+/// # use pros_simulator::{SimulatorOptions, Timeout};
+/// let options = SimulatorOptions::new().timeout(Timeout::RealTime(Duration::from_secs(30)));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SimulatorOptions {
+    pub(crate) timeout: Option<Timeout>,
+    pub(crate) stop_signal: Option<Arc<AtomicBool>>,
+    pub(crate) seed: Option<u64>,
+    pub(crate) threaded: bool,
+    pub(crate) instance_pool: Option<u32>,
+    pub(crate) start_millis: u32,
+    pub(crate) serial_baud_rate: Option<u32>,
+    pub(crate) telemetry_rate: Option<u32>,
+    pub(crate) motor_update_rate: Option<u32>,
+    pub(crate) channel_stats_rate: Option<u32>,
+    pub(crate) frame_rate: Option<u32>,
+    pub(crate) event_queue: Option<(usize, OverflowPolicy)>,
+    pub(crate) controller_latency: Duration,
+    pub(crate) input_shaping: Vec<(ControllerId, InputShaping)>,
+    pub(crate) jitter: Option<Duration>,
+    pub(crate) profile: Option<PathBuf>,
+    pub(crate) chrome_trace: Option<PathBuf>,
+    pub(crate) coverage_report: Option<PathBuf>,
+    pub(crate) target_pros_version: ProsVersion,
+    pub(crate) permissive: bool,
+    /// Devices plugged into the smart ports, keyed by zero-indexed port.
+    pub(crate) smart_ports: Vec<(usize, DeviceType)>,
+    pub(crate) match_timing: Option<MatchTiming>,
+    pub(crate) strict_warnings: Vec<WarningKind>,
+    pub(crate) check_errno: bool,
+    pub(crate) start_kind: StartKind,
+    pub(crate) canaries: bool,
+    pub(crate) check_scheduler: bool,
+    pub(crate) isolate_crashes: bool,
+    pub(crate) lcd_selector: bool,
+    pub(crate) test_build: bool,
+    pub(crate) test_config: BTreeMap<String, String>,
+    pub(crate) test_pattern: Option<String>,
+    pub(crate) mutex_hold_threshold: Option<Duration>,
+    pub(crate) task_stats: bool,
+    pub(crate) line_buffering: bool,
+    pub(crate) flash: Option<PathBuf>,
+    pub(crate) max_memory: Option<usize>,
+    pub(crate) max_tasks: Option<u32>,
+    pub(crate) max_event_rate: Option<u32>,
+    pub(crate) trace_events: bool,
+    pub(crate) scoring_zones: Vec<ScoringZone>,
+    pub(crate) game_objects: Vec<GameObject>,
+    pub(crate) mechanisms: Vec<Mechanism>,
+    pub(crate) motor_groups: Vec<MotorGroup>,
+    pub(crate) drivetrain: Option<DrivetrainConfig>,
+    pub(crate) robot_radius: Option<f64>,
+    pub(crate) plugins: Vec<PathBuf>,
+    #[cfg(feature = "otlp")]
+    pub(crate) otlp_endpoint: Option<String>,
+    #[cfg(feature = "render")]
+    pub(crate) render_frames: Option<(FrameOutput, u32)>,
+    #[cfg(feature = "event-log")]
+    pub(crate) event_log: Option<PathBuf>,
+}
+
+impl SimulatorOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stop the simulation with [`StopReason::TimedOut`](crate::StopReason::TimedOut) if the
+    /// robot code is still running after the given amount of time. Robot code stuck in a loop
+    /// that never calls into the simulator is interrupted too, by checking the time every
+    /// millisecond.
+    pub fn timeout(mut self, timeout: Timeout) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Stop the simulation with [`StopReason::Cancelled`](crate::StopReason::Cancelled) once
+    /// `stop` is set, from any thread. Unlike
+    /// [`SimulatorMessage::Stop`](pros_simulator_interface::SimulatorMessage::Stop), which is
+    /// handled when the system daemon next runs, this also interrupts robot code stuck in a
+    /// loop that never calls into the simulator, by checking the signal every millisecond.
+    pub fn stop_signal(mut self, stop: Arc<AtomicBool>) -> Self {
+        self.stop_signal = Some(stop);
+        self
+    }
+
+    /// Seed the random number generator exposed to robot code through `sim_random`, so that
+    /// runs are reproducible. By default, the generator is seeded randomly.
+    pub fn deterministic(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Run each task on its own OS thread instead of scheduling them cooperatively on a single
+    /// thread, for robot code that relies on tasks genuinely running in parallel.
+    ///
+    /// Tasks still share the same memory, but are no longer scheduled by priority. While a task
+    /// has called `rtos_suspend_all`, other tasks are paused the next time they yield, which may
+    /// be up to a millisecond later. Runs are not reproducible in this mode, even with
+    /// [`deterministic`](Self::deterministic).
+    pub fn threaded(mut self, threaded: bool) -> Self {
+        self.threaded = threaded;
+        self
+    }
+
+    /// Preallocate the resources needed to run up to `max_tasks` tasks at once, which makes
+    /// spawning a task much cheaper in programs that create lots of them.
+    ///
+    /// Creating a task fails if `max_tasks` tasks (including the simulator's own system daemon)
+    /// are already running. By default, resources are allocated separately for each task.
+    ///
+    /// Each task has its own instance of the robot code because it has its own store: a task
+    /// that yields is suspended in the middle of a call into robot code, which holds on to its
+    /// store until it resumes, so tasks can't take turns running in the same one. Memory is
+    /// shared between the instances like on a real brain, so only the globals (which hold little
+    /// more than the stack pointer) and function table are duplicated.
+    pub fn instance_pool(mut self, max_tasks: u32) -> Self {
+        self.instance_pool = Some(max_tasks);
+        self
+    }
+
+    /// Start the clock returned by `millis` at the given value instead of 0. Starting it just
+    /// before `u32::MAX` lets robot code test how its timers handle the clock wrapping around.
+    pub fn start_millis(mut self, millis: u32) -> Self {
+        self.start_millis = millis;
+        self
+    }
+
+    /// Send console output at the given baud rate instead of instantly, to show how print-heavy
+    /// robot code behaves on a real brain. The V5's USB serial connection runs at 115200 baud.
+    ///
+    /// Output is delivered once it has been sent, and tasks block while writing if too much of it
+    /// is waiting to be sent. Anything still waiting is delivered when the simulation stops.
+    pub fn throttle_serial(mut self, baud_rate: u32) -> Self {
+        self.serial_baud_rate = Some(baud_rate);
+        self
+    }
+
+    /// Hold back each task's stdout until it writes a newline or fills a 1024 byte buffer, like
+    /// newlib does on the brain, instead of sending it as soon as it's written. Output that
+    /// depends on when it's printed then behaves like it does on hardware. Stderr isn't buffered, and whatever is
+    /// left is written when the robot code exits.
+    pub fn line_buffering(mut self, line_buffering: bool) -> Self {
+        self.line_buffering = line_buffering;
+        self
+    }
+
+    /// Send a [`SimulatorEvent::Telemetry`](pros_simulator_interface::SimulatorEvent::Telemetry)
+    /// snapshot of the robot's state this many times per second. By default, no telemetry is
+    /// sent.
+    pub fn telemetry(mut self, hz: u32) -> Self {
+        self.telemetry_rate = Some(hz);
+        self
+    }
+
+    /// Send at most this many
+    /// [`SimulatorEvent::MotorUpdated`](pros_simulator_interface::SimulatorEvent::MotorUpdated)
+    /// events per second for each motor. Changes in between are combined, so the frontend
+    /// always ends up with the latest state. By default, every change to a motor's voltage or
+    /// modeled state is sent right away.
+    pub fn motor_update_rate(mut self, hz: u32) -> Self {
+        self.motor_update_rate = Some(hz);
+        self
+    }
+
+    /// Send a
+    /// [`SimulatorEvent::ChannelStats`](pros_simulator_interface::SimulatorEvent::ChannelStats)
+    /// count of the events sent so far this many times per second. By default, none are sent.
+    pub fn channel_stats(mut self, hz: u32) -> Self {
+        self.channel_stats_rate = Some(hz);
+        self
+    }
+
+    /// Send a [`SimulatorEvent::Frame`](pros_simulator_interface::SimulatorEvent::Frame) marker
+    /// this many times per second of simulated time, so that a frontend recording video of
+    /// itself can line the footage up with the event log afterwards. Usually this is the video's
+    /// frame rate. By default, no markers are sent.
+    pub fn frame_markers(mut self, hz: u32) -> Self {
+        self.frame_rate = Some(hz);
+        self
+    }
+
+    /// Send a [`SimulatorEvent::TaskStats`](pros_simulator_interface::SimulatorEvent::TaskStats)
+    /// every second of simulated time, with how much of it robot code spent running rather than
+    /// waiting, approximating the CPU usage the brain reports. By default, none are sent.
+    pub fn task_stats(mut self, task_stats: bool) -> Self {
+        self.task_stats = task_stats;
+        self
+    }
+
+    /// Queue at most `capacity` events for a frontend reading them from
+    /// [`stream::start_simulator`](crate::stream::start_simulator), handling any more with the
+    /// given policy while the queue is full. By default, the queue grows as large as it needs to.
+    ///
+    /// Events passed to the callback given to [`simulate`](crate::simulate) aren't queued.
+    pub fn event_queue(mut self, capacity: usize, policy: OverflowPolicy) -> Self {
+        self.event_queue = Some((capacity.max(1), policy));
+        self
+    }
+
+    /// Delay controller updates by the given amount of time before robot code sees them, like
+    /// the V5's radio link does. Something like 10 ms is realistic, so that control loops tuned
+    /// in the simulator don't fall apart on a real robot. By default, updates are applied
+    /// immediately.
+    pub fn controller_latency(mut self, latency: Duration) -> Self {
+        self.controller_latency = latency;
+        self
+    }
+
+    /// Shape a controller's joysticks from the start of the simulation, like a
+    /// [`SimulatorMessage::SetInputShaping`](pros_simulator_interface::SimulatorMessage::SetInputShaping)
+    /// sent before the robot code runs. By default, joysticks aren't shaped.
+    pub fn input_shaping(mut self, controller: ControllerId, shaping: InputShaping) -> Self {
+        self.input_shaping.push((controller, shaping));
+        self
+    }
+
+    /// Randomly perturb the schedule to flush out race conditions that only show up under timing
+    /// variance: tasks of the same priority run in a random order instead of taking turns, and
+    /// delays and mutex timeouts last up to `max_delay` longer than requested.
+    ///
+    /// The perturbations are seeded by [`deterministic`](Self::deterministic). Tasks waiting for
+    /// the clock to advance are scheduled however many times they happen to be before it does,
+    /// so runs are only reproducible if the robot code's timing doesn't depend on the clock. For
+    /// the same reason, tasks aren't preempted at every tick while jittering, so they only switch
+    /// when they block or yield.
+    pub fn jitter(mut self, max_delay: Duration) -> Self {
+        self.jitter = Some(max_delay);
+        self
+    }
+
+    /// Sample the robot code's stack every millisecond and write the samples to the given file
+    /// when the simulation stops, in the folded stack format that `flamegraph.pl` and `inferno`
+    /// turn into flame graphs. Robot code built with debug info or a name section shows function
+    /// names instead of indices.
+    pub fn profile(mut self, output: impl Into<PathBuf>) -> Self {
+        self.profile = Some(output.into());
+        self
+    }
+
+    /// Record the sections robot code times with `sim_profile_begin` and `sim_profile_end`, and
+    /// write them to the given file when the simulation stops, in the Chrome trace format that
+    /// `chrome://tracing` and Perfetto open. Spans are timed by the simulation's clock, with a
+    /// timeline for each task. Without this, those functions do nothing.
+    pub fn chrome_trace(mut self, output: impl Into<PathBuf>) -> Self {
+        self.chrome_trace = Some(output.into());
+        self
+    }
+
+    /// Write a plain text report of which APIs the robot code called to the given file when the
+    /// simulation stops. The same summary is always sent as a
+    /// [`SimulatorEvent::ApiCoverage`](pros_simulator_interface::SimulatorEvent::ApiCoverage)
+    /// event.
+    pub fn coverage_report(mut self, output: impl Into<PathBuf>) -> Self {
+        self.coverage_report = Some(output.into());
+        self
+    }
+
+    /// Check the robot code's imports against the given version of the PROS API when loading
+    /// it, and report which of them the simulator implements, stubs or is missing in a
+    /// [`SimulatorEvent::ApiCompatibility`](pros_simulator_interface::SimulatorEvent::ApiCompatibility)
+    /// event. Defaults to the newest version the simulator has a manifest for.
+    pub fn target_pros_version(mut self, version: ProsVersion) -> Self {
+        self.target_pros_version = version;
+        self
+    }
+
+    /// Link PROS APIs the simulator doesn't implement to stubs that set `errno` to `ENOSYS` and
+    /// return `PROS_ERR`, instead of stopping any task that calls one. This lets robot code that
+    /// mostly works in the simulator run from start to finish. Only functions that return
+    /// nothing, an integer or pointer, or a `double` can be stubbed.
+    pub fn permissive(mut self, permissive: bool) -> Self {
+        self.permissive = permissive;
+        self
+    }
+
+    /// Plug a device into one of the brain's smart ports, numbered from 1 to 21 like on the
+    /// brain itself. Robot code can see which device is plugged in with the PROS registry API,
+    /// but the device itself isn't simulated yet.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `port` isn't between 1 and 21.
+    pub fn smart_port(mut self, port: u8, device: DeviceType) -> Self {
+        assert!(
+            (1..=NUM_SMART_PORTS).contains(&(port as usize)),
+            "smart port {port} doesn't exist"
+        );
+        self.smart_ports.push((port as usize - 1, device));
+        self
+    }
+
+    /// Run a match once the robot code has initialized, like field control would: autonomous,
+    /// then driver control, then the simulation stops with
+    /// [`StopReason::Finished`](crate::StopReason::Finished). The competition phase can still
+    /// be changed with messages during the match, but it's overwritten when the next period
+    /// starts.
+    pub fn run_match(mut self, timing: MatchTiming) -> Self {
+        self.match_timing = Some(timing);
+        self
+    }
+
+    /// Stop the simulation with [`StopReason::StrictWarning`](crate::StopReason::StrictWarning)
+    /// when this kind of warning is sent, instead of letting the robot code carry on. Can be
+    /// called more than once to make several kinds strict.
+    pub fn strict(mut self, kind: WarningKind) -> Self {
+        if !self.strict_warnings.contains(&kind) {
+            self.strict_warnings.push(kind);
+        }
+        self
+    }
+
+    /// Warn the first time each task asks for `errno` before any API call has failed in it.
+    /// Robot code that checks `errno` without checking the return value first only works in
+    /// the simulator because memory starts out zeroed.
+    ///
+    /// Code that clears `errno` before making a call also asks for it, so this is off by
+    /// default.
+    pub fn check_errno(mut self, check_errno: bool) -> Self {
+        self.check_errno = check_errno;
+        self
+    }
+
+    /// Simulate a cold or hot start, which decides which of the robot code's `cold_init` and
+    /// `hot_init` hooks run before `initialize`. Defaults to [`StartKind::Cold`].
+    pub fn start_kind(mut self, kind: StartKind) -> Self {
+        self.start_kind = kind;
+        self
+    }
+
+    /// Surround the buffers the simulator allocates in robot code memory (like `errno`,
+    /// task-local storage and task names) with canaries, and warn when robot code overwrites
+    /// them by writing out of bounds.
+    ///
+    /// The canaries are checked every time a task yields, so the warning names the task that
+    /// overwrote them. In [`threaded`](Self::threaded) mode, they're checked every millisecond
+    /// instead and the task can't be named.
+    pub fn canaries(mut self, canaries: bool) -> Self {
+        self.canaries = canaries;
+        self
+    }
+
+    /// Check the scheduler's invariants every time it picks a task to run, and send a
+    /// [`SimulatorEvent::SchedulerInvariantViolated`](pros_simulator_interface::SimulatorEvent::SchedulerInvariantViolated)
+    /// event the first time each one is broken. This is for catching bugs in the simulator, e.g.
+    /// in its own tests or while working on the scheduler, and slows every task switch down.
+    ///
+    /// The [`threaded`](Self::threaded) scheduler isn't checked.
+    pub fn check_scheduler(mut self, check_scheduler: bool) -> Self {
+        self.check_scheduler = check_scheduler;
+        self
+    }
+
+    /// When a task faults, stop only that task and keep running the rest of the robot code,
+    /// like a brain being debugged often does, instead of stopping the simulation with
+    /// [`StopReason::Crashed`](crate::StopReason::Crashed). The fault is still reported, followed
+    /// by a
+    /// [`SimulatorEvent::TaskCrashed`](pros_simulator_interface::SimulatorEvent::TaskCrashed)
+    /// event. Faults in the simulator's own system daemon always stop the simulation.
+    pub fn isolate_crashes(mut self, isolate_crashes: bool) -> Self {
+        self.isolate_crashes = isolate_crashes;
+        self
+    }
+
+    /// Work out what an autonomous selector drawn on the LCD offers, and send it in
+    /// [`SimulatorEvent::LcdSelectorUpdated`](pros_simulator_interface::SimulatorEvent::LcdSelectorUpdated)
+    /// events so frontends can show its choices as buttons. Choices can then be picked with
+    /// [`SimulatorMessage::LcdSelectorChoose`](pros_simulator_interface::SimulatorMessage::LcdSelectorChoose).
+    pub fn lcd_selector(mut self, lcd_selector: bool) -> Self {
+        self.lcd_selector = lcd_selector;
+        self
+    }
+
+    /// Keep what robot code saves to flash with `sim_flash_write` in the given file, so that
+    /// things like autonomous selections and odometry calibration can be tested across runs. The
+    /// file is created the first time a value is saved. By default, saved values are forgotten
+    /// when the simulation stops.
+    pub fn flash(mut self, path: impl Into<PathBuf>) -> Self {
+        self.flash = Some(path.into());
+        self
+    }
+
+    /// Let robot code control the simulation with `sim_set_pose`, `sim_advance_time`,
+    /// `sim_config_get` and `sim_sensor_trace`, for unit tests compiled into the robot code
+    /// itself. Calling them otherwise stops the robot code, so they can't be left in a program
+    /// by accident. Test builds also accept
+    /// [`SimulatorMessage::WriteMemory`](pros_simulator_interface::SimulatorMessage::WriteMemory).
+    pub fn test_build(mut self, test_build: bool) -> Self {
+        self.test_build = test_build;
+        self
+    }
+
+    /// Set a value that robot code in a [test build](Self::test_build) can read with
+    /// `sim_config_get`, e.g. which test to run.
+    pub fn test_config(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.test_config.insert(key.into(), value.into());
+        self
+    }
+
+    /// Run the robot code's tests instead of its competition functions: every exported function
+    /// that takes no arguments and whose name matches `pattern`, where `*` matches any number
+    /// of characters (e.g. `sim_test_*`). Tests run one at a time in alphabetical order, each
+    /// in a fresh task, and fail if they crash or a `sim_assert` fails while they run.
+    ///
+    /// Each test sends a [`TestStarted`](pros_simulator_interface::SimulatorEvent::TestStarted)
+    /// and a [`TestFinished`](pros_simulator_interface::SimulatorEvent::TestFinished) event, and
+    /// the simulation stops with [`StopReason::TestsFailed`](crate::StopReason::TestsFailed) if
+    /// any failed. Neither `initialize` nor the other lifecycle hooks run, and tasks a test
+    /// leaves running are stopped when the last test finishes. Implies
+    /// [`test_build`](Self::test_build).
+    pub fn run_tests(mut self, pattern: impl Into<String>) -> Self {
+        self.test_pattern = Some(pattern.into());
+        self.test_build = true;
+        self
+    }
+
+    /// Send events when robot code creates a mutex, when a task has to wait for a mutex another
+    /// task is holding, and when a task holds one for longer than `hold_threshold`, so that
+    /// synchronization bugs show up on a frontend's timeline. See
+    /// [`SimulatorEvent::MutexContended`](pros_simulator_interface::SimulatorEvent::MutexContended).
+    /// By default, none are sent.
+    pub fn mutex_events(mut self, hold_threshold: Duration) -> Self {
+        self.mutex_hold_threshold = Some(hold_threshold);
+        self
+    }
+
+    /// Stop the simulation with [`StopReason::LimitExceeded`](crate::StopReason::LimitExceeded)
+    /// if robot code or a [plugin](Self::plugin) grows its memory past this many bytes. The
+    /// task growing it stops right away. Together with the other limits and a [`Timeout`], this lets untrusted robot code be
+    /// simulated safely, e.g. to grade students' submissions.
+    pub fn max_memory(mut self, bytes: usize) -> Self {
+        self.max_memory = Some(bytes);
+        self
+    }
+
+    /// Stop the simulation with [`StopReason::LimitExceeded`](crate::StopReason::LimitExceeded)
+    /// if robot code tries to have more than this many tasks at once, not counting the
+    /// simulator's own. The `task_create` call that would go over fails.
+    pub fn max_tasks(mut self, max_tasks: u32) -> Self {
+        self.max_tasks = Some(max_tasks);
+        self
+    }
+
+    /// Stop the simulation with [`StopReason::LimitExceeded`](crate::StopReason::LimitExceeded)
+    /// if more than this many events are sent in a second, e.g. by robot code printing in a
+    /// loop. The simulation stops the next time a task yields.
+    pub fn max_event_rate(mut self, events_per_second: u32) -> Self {
+        self.max_event_rate = Some(events_per_second);
+        self
+    }
+
+    /// Also record every event sent to the frontend as a `tracing` event with the
+    /// `pros_simulator::events` target, with a field for each of its values, so embedders that
+    /// already collect tracing output get the simulator's events without a custom callback.
+    /// Faults are recorded as errors and frequent events like motor updates as traces.
+    pub fn trace_events(mut self, trace_events: bool) -> Self {
+        self.trace_events = trace_events;
+        self
+    }
+
+    /// Score points when the robot reaches a part of the field, sending a
+    /// [`SimulatorEvent::ScoreChanged`](pros_simulator_interface::SimulatorEvent::ScoreChanged)
+    /// each time the score changes. Zones can also be added while the simulation runs with
+    /// [`SimulatorMessage::AddScoringZone`](pros_simulator_interface::SimulatorMessage::AddScoringZone).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the zone's shape doesn't cover part of the field, e.g. if it has a negative
+    /// radius.
+    pub fn scoring_zone(mut self, zone: ScoringZone) -> Self {
+        if let Err(reason) = check_shape(&zone.shape) {
+            panic!("scoring zone `{}` is invalid: {reason}", zone.name);
+        }
+        self.scoring_zones.push(zone);
+        self
+    }
+
+    /// Place a game object on the field, which the robot pushes out of its way as its pose
+    /// changes. A
+    /// [`SimulatorEvent::GameObjectUpdated`](pros_simulator_interface::SimulatorEvent::GameObjectUpdated)
+    /// is sent when it's placed and each time it moves. Objects can also be added while the
+    /// simulation runs with
+    /// [`SimulatorMessage::AddGameObject`](pros_simulator_interface::SimulatorMessage::AddGameObject).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the object's position or radius isn't finite, or its radius is negative.
+    pub fn game_object(mut self, object: GameObject) -> Self {
+        if let Err(reason) = check_object(&object) {
+            panic!("game object `{}` is invalid: {reason}", object.name);
+        }
+        self.game_objects.push(object);
+        self
+    }
+
+    /// Model the motor on a smart port as driving an intake or lift, so the robot can pick up
+    /// game objects. The motor has to be plugged in with [`smart_port`](Self::smart_port). A
+    /// [`SimulatorEvent::MechanismUpdated`](pros_simulator_interface::SimulatorEvent::MechanismUpdated)
+    /// is sent whenever the mechanism picks up or lets out an object, or moves. Mechanisms can
+    /// also be added while the simulation runs with
+    /// [`SimulatorMessage::AddMechanism`](pros_simulator_interface::SimulatorMessage::AddMechanism).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the mechanism's port isn't a smart port, its dimensions are negative or not
+    /// finite, or another mechanism has the same name.
+    pub fn mechanism(mut self, mechanism: Mechanism) -> Self {
+        if let Err(reason) = check_mechanism(&mechanism) {
+            panic!("mechanism `{}` is invalid: {reason}", mechanism.name);
+        }
+        assert!(
+            !self.mechanisms.iter().any(|m| m.name == mechanism.name),
+            "there's already a mechanism named `{}`",
+            mechanism.name
+        );
+        self.mechanisms.push(mechanism);
+        self
+    }
+
+    /// Report the voltages of a group of motors together, like one side of a drivetrain. A
+    /// [`SimulatorEvent::MotorGroupUpdated`](pros_simulator_interface::SimulatorEvent::MotorGroupUpdated)
+    /// is sent whenever one of the group's motors changes voltage. Groups can also be added while
+    /// the simulation runs with
+    /// [`SimulatorMessage::AddMotorGroup`](pros_simulator_interface::SimulatorMessage::AddMotorGroup).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the group has no motors, one of its ports isn't a smart port or has more than
+    /// one of its motors, or another group has the same name.
+    pub fn motor_group(mut self, group: MotorGroup) -> Self {
+        if let Err(reason) = check_motor_group(&group) {
+            panic!("motor group `{}` is invalid: {reason}", group.name);
+        }
+        assert!(
+            !self.motor_groups.iter().any(|g| g.name == group.name),
+            "there's already a motor group named `{}`",
+            group.name
+        );
+        self.motor_groups.push(group);
+        self
+    }
+
+    /// Describe the robot's drivetrain, which is sent to frontends in a
+    /// [`SimulatorEvent::DrivetrainConfigured`](pros_simulator_interface::SimulatorEvent::DrivetrainConfigured)
+    /// when the robot code starts. Its motors are plugged into their smart ports and reported as
+    /// the `left drive`, `right drive` and `strafe` [motor groups](Self::motor_group).
+    ///
+    /// # Panics
+    ///
+    /// Panics if one of its measurements isn't positive, one of its sides has no motors, one of
+    /// its ports isn't a smart port or has more than one of its motors, the drivetrain was
+    /// already set, or another motor group has one of its groups' names.
+    pub fn drivetrain(mut self, drivetrain: DrivetrainConfig) -> Self {
+        if let Err(reason) = check_drivetrain(&drivetrain) {
+            panic!("the drivetrain is invalid: {reason}");
+        }
+        assert!(self.drivetrain.is_none(), "the drivetrain is already set");
+        for group in drivetrain_groups(&drivetrain) {
+            for port in &group.ports {
+                self = self.smart_port(port.unsigned_abs(), DeviceType::Motor);
+            }
+            self = self.motor_group(group);
+        }
+        self.drivetrain = Some(drivetrain);
+        self
+    }
+
+    /// The radius of the circle the robot pushes game objects with, in inches. By default it's
+    /// 9 inches, which fits an 18 inch robot.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `inches` is negative or isn't finite.
+    pub fn robot_radius(mut self, inches: f64) -> Self {
+        assert!(
+            inches.is_finite() && inches >= 0.0,
+            "the robot's radius can't be {inches} inches"
+        );
+        self.robot_radius = Some(inches);
+        self
+    }
+
+    /// Load a plugin from the WebAssembly module at the given path, letting robot code call the
+    /// functions it exports, e.g. to simulate a device the simulator doesn't support. Can be
+    /// called more than once to load several plugins. Plugins are loaded when the simulation
+    /// starts, and it fails to start if one can't be.
+    ///
+    /// A plugin is a core WebAssembly module that doesn't import anything. Every function it
+    /// exports is linked into robot code as an import from `env`, unless the simulator already
+    /// provides an API with that name. Each plugin is instantiated once and shared by every
+    /// task, and can't see robot code memory, so its functions can only take and return numbers.
+    pub fn plugin(mut self, path: impl Into<PathBuf>) -> Self {
+        self.plugins.push(path.into());
+        self
+    }
+
+    /// Export the simulation to an OpenTelemetry collector at the given URL, e.g.
+    /// `http://localhost:4318`, as a trace with a span for each task's lifetime and each host
+    /// call robot code makes. Spans are sent every second over OTLP/HTTP with JSON encoding, to
+    /// the URL's path or `/v1/traces` if it has none. Only `http` URLs are supported. A
+    /// [`SimulatorEvent::Warning`](pros_simulator_interface::SimulatorEvent::Warning) is sent
+    /// at the end of the simulation if spans couldn't be exported.
+    #[cfg(feature = "otlp")]
+    pub fn otlp_endpoint(mut self, url: impl Into<String>) -> Self {
+        self.otlp_endpoint = Some(url.into());
+        self
+    }
+
+    /// Draw the field `hz` times per second of simulated time and write the frames to `output`,
+    /// so a run can be looked at afterwards without a frontend. Frames show the scoring zones,
+    /// the game objects and the robot once it has a pose, on a 12 foot field centered on the
+    /// origin. A [`SimulatorEvent::Warning`](pros_simulator_interface::SimulatorEvent::Warning)
+    /// is sent at the end of the simulation if frames couldn't be written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `hz` is 0.
+    #[cfg(feature = "render")]
+    pub fn render_frames(mut self, output: FrameOutput, hz: u32) -> Self {
+        assert!(hz > 0, "frames can't be rendered 0 times per second");
+        self.render_frames = Some((output, hz));
+        self
+    }
+
+    /// Append every event the simulation sends to a gzip-compressed log at `path` as it runs,
+    /// one JSON object per line with the event, the simulated time in `millis` and the wall
+    /// clock time in `time`. Events are written at least every quarter second as complete gzip
+    /// members, so the log can be read with `zcat` even if the simulator crashes or the
+    /// frontend disconnects. A
+    /// [`SimulatorEvent::Warning`](pros_simulator_interface::SimulatorEvent::Warning) is sent
+    /// after the last event if the log couldn't be written.
+    #[cfg(feature = "event-log")]
+    pub fn event_log(mut self, path: impl Into<PathBuf>) -> Self {
+        self.event_log = Some(path.into());
+        self
+    }
+}
+
+/// How the robot code was started, following PROS's hot/cold linking: libraries live in a "cold"
+/// image that is only uploaded when it changes, and user code in a "hot" image that is uploaded
+/// every time.
+///
+/// Robot code can export `cold_init` and `hot_init` functions that take no arguments, which run
+/// one after the other before `initialize` when they apply. Robot code can also ask which kind
+/// of start it is with `sim_capability("hot-start")`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StartKind {
+    /// The brain was just turned on, or both images were uploaded: `cold_init` and then
+    /// `hot_init` run.
+    #[default]
+    Cold,
+    /// Only the hot image was uploaded, and the cold image's state is left over from an earlier
+    /// run: only `hot_init` runs.
+    Hot,
+}
+
+/// A limit on how long a simulation can run for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timeout {
+    /// Limit the amount of time that passes inside the simulation.
+    Simulated(Duration),
+    /// Limit the amount of time that passes in the real world, regardless of how fast the
+    /// simulation is running.
+    RealTime(Duration),
+}
+
+/// How long each period of a match run by
+/// [`SimulatorOptions::run_match`](SimulatorOptions::run_match) lasts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchTiming {
+    pub autonomous: Duration,
+    pub driver_control: Duration,
+}
+
+impl Default for MatchTiming {
+    /// The timing of a VEX Robotics Competition match: 15 seconds of autonomous, then 1 minute
+    /// 45 seconds of driver control.
+    fn default() -> Self {
+        Self {
+            autonomous: Duration::from_secs(15),
+            driver_control: Duration::from_secs(105),
+        }
+    }
+}
+
+/// What to do with events sent while the queue set up by
+/// [`SimulatorOptions::event_queue`](SimulatorOptions::event_queue) is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Queue the event anyway, and pause the simulation until the frontend has caught up.
+    Pause,
+    /// Replace the newest queued event of the same kind (and motor, for motor updates) with the
+    /// event, or queue it if there isn't one. Only periodic events that are superseded by the
+    /// next one of their kind are coalesced: telemetry, competition timers, motor updates and
+    /// channel stats. Other events are always queued.
+    Coalesce,
+    /// Drop the event if it's one of the periodic events that [`Coalesce`](Self::Coalesce)
+    /// would coalesce. Other events are always queued.
+    Drop,
+}
+
+/// Kinds of warning that can stop the simulation with
+/// [`SimulatorOptions::strict`](SimulatorOptions::strict).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarningKind {
+    /// A task called an API the simulator doesn't implement, which stops the task. Calls to the
+    /// stubs linked in [permissive](SimulatorOptions::permissive) mode don't count.
+    UnimplementedCall,
+    /// `motor_move` was called with a value outside of -127 to 127, or `motor_move_voltage` with
+    /// one outside of -12000 to 12000, and it was clamped.
+    MotorVoltageClamped,
+    /// A task ended while it had the scheduler suspended with `rtos_suspend_all`.
+    SuspendedAtExit,
+    /// Robot code called `sim_assert` with a false condition.
+    FailedAssertion,
+    /// A task asked for `errno` before any API call had set it. Only sent with
+    /// [`SimulatorOptions::check_errno`](SimulatorOptions::check_errno).
+    UnsetErrno,
+    /// Robot code wrote out of bounds of a buffer allocated by the simulator. Only sent with
+    /// [`SimulatorOptions::canaries`](SimulatorOptions::canaries).
+    HeapCorruption,
+    /// Robot code passed an invalid argument to an API function that reports errors with
+    /// `errno`.
+    InvalidArgument,
+}
+
+impl WarningKind {
+    pub const ALL: [Self; 7] = [
+        Self::UnimplementedCall,
+        Self::MotorVoltageClamped,
+        Self::SuspendedAtExit,
+        Self::FailedAssertion,
+        Self::UnsetErrno,
+        Self::HeapCorruption,
+        Self::InvalidArgument,
+    ];
+}
+
+impl Display for WarningKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::UnimplementedCall => "unimplemented-call",
+            Self::MotorVoltageClamped => "motor-voltage-clamped",
+            Self::SuspendedAtExit => "suspended-at-exit",
+            Self::FailedAssertion => "failed-assertion",
+            Self::UnsetErrno => "unset-errno",
+            Self::HeapCorruption => "heap-corruption",
+            Self::InvalidArgument => "invalid-argument",
+        })
+    }
+}
+
+impl FromStr for WarningKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.to_string() == s)
+            .ok_or(format!("unknown warning kind `{s}`"))
+    }
+}
@@ -0,0 +1,48 @@
+use std::time::Duration;
+
+use pros_simulator_interface::ResourceLimit;
+
+use crate::WarningKind;
+
+/// How a simulation ended.
+#[derive(Debug)]
+pub struct SimulationOutcome {
+    pub reason: StopReason,
+    /// How long the robot code ran for, in simulated time.
+    pub simulated_time: Duration,
+}
+
+impl SimulationOutcome {
+    /// Whether the robot code finished without faulting or exiting with a non-zero code.
+    pub fn is_success(&self) -> bool {
+        matches!(self.reason, StopReason::Finished | StopReason::Exited(0))
+    }
+}
+
+/// The reason a simulation stopped.
+#[derive(Debug)]
+pub enum StopReason {
+    /// Every task finished executing.
+    Finished,
+    /// The robot code called `exit` with the given code.
+    Exited(i32),
+    /// The robot code faulted. A [`RobotCodeError`](pros_simulator_interface::SimulatorEvent::RobotCodeError)
+    /// event describing the fault is sent before the simulation stops.
+    Crashed(anyhow::Error),
+    /// The simulation ran for longer than its [`Timeout`](crate::Timeout).
+    TimedOut,
+    /// The simulation was stopped by a
+    /// [`SimulatorMessage::Stop`](pros_simulator_interface::SimulatorMessage::Stop).
+    Cancelled,
+    /// A warning of a kind made strict with [`SimulatorOptions::strict`](crate::SimulatorOptions::strict)
+    /// was sent.
+    StrictWarning(WarningKind),
+    /// Robot code used more of a resource than it was allowed to, e.g. with
+    /// [`SimulatorOptions::max_memory`](crate::SimulatorOptions::max_memory). A
+    /// [`ResourceLimitExceeded`](pros_simulator_interface::SimulatorEvent::ResourceLimitExceeded)
+    /// event is sent before the simulation stops.
+    LimitExceeded(ResourceLimit),
+    /// Some of the robot code's tests failed, when running them with
+    /// [`SimulatorOptions::run_tests`](crate::SimulatorOptions::run_tests).
+    TestsFailed { failed: u32, total: u32 },
+}
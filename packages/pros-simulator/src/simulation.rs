@@ -0,0 +1,104 @@
+//! Stepping a simulation by hand. See [`Simulation`].
+
+use std::{path::Path, sync::mpsc::Receiver};
+
+use anyhow::{bail, Result};
+use futures::executor::block_on;
+use pros_simulator_interface::SimulatorMessage;
+
+use crate::{
+    finish,
+    host::{task::Scheduler, Host},
+    interface::SimulatorInterface,
+    load, start, SimulationOutcome, SimulatorOptions,
+};
+
+/// A simulation that runs one scheduler cycle each time it's [polled](Self::poll), as an
+/// alternative to awaiting [`simulate`](crate::simulate), so that a frontend can step it from its
+/// own frame loop on one thread. It doesn't need an async runtime.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn run() -> anyhow::Result<()> {
+/// use std::{path::Path, sync::mpsc};
+///
+/// use pros_simulator::{Simulation, SimulatorOptions, StepResult};
+///
+/// let (_messages, rx) = mpsc::channel();
+/// let mut simulation = Simulation::new(
+///     Path::new("robot.wasm"),
+///     SimulatorOptions::new(),
+///     |event| println!("{event:?}"),
+///     rx,
+/// )?;
+/// let outcome = loop {
+///     // draw a frame, handle input, ...
+///     if let StepResult::Stopped(outcome) = simulation.poll() {
+///         break outcome;
+///     }
+/// };
+/// # Ok(())
+/// # }
+/// ```
+pub struct Simulation {
+    host: Host,
+    scheduler: Scheduler,
+    stopped: bool,
+}
+
+/// What happened during one [`Simulation::poll`].
+#[derive(Debug)]
+pub enum StepResult {
+    /// A task ran until it yielded, and the simulation is still running.
+    Running,
+    /// The simulation stopped, and its final events have been sent.
+    Stopped(SimulationOutcome),
+}
+
+impl Simulation {
+    /// Load the WebAssembly robot program at the given path and start its system daemon. See
+    /// [`simulate`](crate::simulate) for what the arguments mean.
+    ///
+    /// Returns an error if the robot code couldn't be loaded, or if `options` enables the
+    /// [threaded](SimulatorOptions::threaded) scheduler, which can't be stepped.
+    pub fn new(
+        robot_code: &Path,
+        options: SimulatorOptions,
+        interface: impl Into<SimulatorInterface>,
+        messages: Receiver<SimulatorMessage>,
+    ) -> Result<Self> {
+        if options.threaded {
+            bail!("The threaded scheduler runs tasks on their own threads, so it can't be polled");
+        }
+        let host = load(robot_code, options, interface)?;
+        block_on(start(&host, messages))?;
+        let scheduler = block_on(Scheduler::new(&host));
+        Ok(Self {
+            host,
+            scheduler,
+            stopped: false,
+        })
+    }
+
+    /// The simulator state, e.g. for spawning host-side tasks.
+    pub fn host(&self) -> &Host {
+        &self.host
+    }
+
+    /// Run the next task until it yields.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the simulation has already stopped.
+    pub fn poll(&mut self) -> StepResult {
+        assert!(!self.stopped, "simulation polled after it stopped");
+        match block_on(self.scheduler.cycle(&self.host)) {
+            None => StepResult::Running,
+            Some(reason) => {
+                self.stopped = true;
+                StepResult::Stopped(finish(&self.host, reason))
+            }
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Measuring how a simulated motor responds to a step in its commanded speed, for quantitative
+//! feedback on PID tuning. See [`StepResponse`].
+
+use std::time::Duration;
+
+use pros_simulator_interface::SimulatorEvent;
+
+use crate::TimedEvent;
+
+/// How far along the step the response has to be to start rising, as a fraction of its size.
+const RISE_START: f64 = 0.1;
+/// How far along the step the response has to be to finish rising.
+const RISE_END: f64 = 0.9;
+/// How close to the target the response has to stay to have settled, as a fraction of the
+/// step's size.
+const SETTLING_BAND: f64 = 0.02;
+
+/// Rise time, overshoot and settling time of a response to a step input.
+///
+/// Usually measured from a motor's [`MotorUpdated`](SimulatorEvent::MotorUpdated) events with
+/// [`Timeline::step_response`](crate::Timeline::step_response), but [`analyze`](Self::analyze)
+/// works on any samples, e.g. a pose or sensor value reported with `sim_emit_event`.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn run() -> anyhow::Result<()> {
+/// use std::{path::Path, sync::mpsc};
+///
+/// use pros_simulator::{SimulatorOptions, Timeline};
+/// use pros_simulator_interface::DeviceType;
+///
+/// let (_messages, rx) = mpsc::channel();
+/// let options = SimulatorOptions::new().smart_port(1, DeviceType::Motor);
+/// let timeline = Timeline::record(Path::new("robot.wasm"), options, rx)?;
+/// let response = timeline.step_response(1).expect("the motor was never driven");
+/// assert!(response.overshoot < 0.1);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StepResponse {
+    /// The value the response started from.
+    pub initial: f64,
+    /// The value the step commanded.
+    pub target: f64,
+    /// How long the response took to go from 10% to 90% of the way to the target, or `None`
+    /// if it never got 90% of the way.
+    pub rise_time: Option<Duration>,
+    /// How far the response went past the target, as a fraction of the step's size. 0 if it
+    /// never did.
+    pub overshoot: f64,
+    /// How long after the step the response stayed within 2% of the step's size of the target,
+    /// or `None` if it was still outside that band at the last sample.
+    pub settling_time: Option<Duration>,
+}
+
+impl StepResponse {
+    /// Measures a response to a step from `initial` to `target` at time 0, from samples of
+    /// `(milliseconds since the step, value)` in time order. Returns `None` if there are no
+    /// samples or the step has no size.
+    pub fn analyze(initial: f64, target: f64, samples: &[(u32, f64)]) -> Option<Self> {
+        let size = target - initial;
+        if samples.is_empty() || size == 0.0 {
+            return None;
+        }
+        // how far along the step each sample is, so steps down work like steps up
+        let progress = |value: f64| (value - initial) / size;
+        let reached = |fraction: f64| {
+            samples
+                .iter()
+                .find(|(_, value)| progress(*value) >= fraction)
+                .map(|(millis, _)| *millis)
+        };
+        let rise_time = reached(RISE_START)
+            .zip(reached(RISE_END))
+            .map(|(start, end)| Duration::from_millis(end.saturating_sub(start).into()));
+        let overshoot = samples
+            .iter()
+            .map(|(_, value)| progress(*value) - 1.0)
+            .fold(0.0, f64::max);
+        let settling_time = match samples
+            .iter()
+            .rposition(|(_, value)| (progress(*value) - 1.0).abs() > SETTLING_BAND)
+        {
+            None => Some(Duration::ZERO),
+            Some(last) => samples
+                .get(last + 1)
+                .map(|(millis, _)| Duration::from_millis((*millis).into())),
+        };
+        Some(Self {
+            initial,
+            target,
+            rise_time,
+            overshoot,
+            settling_time,
+        })
+    }
+
+    /// Measures the response of the motor on `port` (1 to 21) to the first change in its target
+    /// velocity, from its [`MotorUpdated`](SimulatorEvent::MotorUpdated) events up to the next
+    /// change. Values are in RPM. Returns `None` if the motor's target velocity never changed.
+    pub fn from_motor_updates<'a>(
+        events: impl IntoIterator<Item = &'a TimedEvent>,
+        port: u8,
+    ) -> Option<Self> {
+        let mut updates = events.into_iter().filter_map(|event| match event.event {
+            SimulatorEvent::MotorUpdated {
+                port: updated,
+                target_velocity,
+                actual_velocity,
+                ..
+            } if updated == port => Some((
+                event.millis,
+                f64::from(target_velocity),
+                f64::from(actual_velocity),
+            )),
+            _ => None,
+        });
+        let (start, target, initial) = updates.find(|(_, target, _)| *target != 0.0)?;
+        let samples = std::iter::once((0, initial))
+            .chain(
+                updates
+                    .take_while(|(_, commanded, _)| *commanded == target)
+                    .map(|(millis, _, actual)| (millis - start, actual)),
+            )
+            .collect::<Vec<_>>();
+        Self::analyze(initial, target, &samples)
+    }
+}
@@ -1,22 +1,21 @@
 use std::{
+    collections::VecDeque,
+    mem::{discriminant, Discriminant},
     path::PathBuf,
     pin::Pin,
-    sync::{mpsc::Receiver, Arc, Mutex},
+    sync::{atomic::Ordering, mpsc::Receiver, Arc, Mutex},
     task::{Context, Poll},
 };
 
 use anyhow::Result;
-use futures::{executor::block_on, FutureExt, Stream};
+use futures::{task::AtomicWaker, FutureExt, Stream};
 use pros_simulator_interface::{SimulatorEvent, SimulatorMessage};
-use tokio::{
-    sync::{
-        mpsc::{self, UnboundedReceiver},
-        oneshot,
-    },
-    task::JoinHandle,
-};
+use tokio::{sync::oneshot, task::JoinHandle};
 
-use crate::simulate;
+use crate::{
+    interface::{ChannelCounters, PauseQueue, SimulatorInterface},
+    simulate, OverflowPolicy, SimulationOutcome, SimulatorOptions, StopReason,
+};
 
 pub struct StreamedSimulatorEvent {
     pub inner: SimulatorEvent,
@@ -24,55 +23,94 @@ pub struct StreamedSimulatorEvent {
 }
 
 /// Start a simulator in a new tokio task and stream the events from it.
+///
+/// The simulator runs on the current tokio runtime alongside any other tasks; it does not need a
+/// dedicated thread.
+///
+/// # Arguments
+///
+/// - `robot_code`: The path to the robot program to simulate.
+/// - `options`: Configuration for the simulation.
+/// - `require_unpause`: Whether the simulator should wait for each event's `unpause` sender to be
+///   used (or dropped) before running any more robot code.
+/// - `messages`: Input message stream to send to the robot program. Keep the matching
+///   [`Sender`](std::sync::mpsc::Sender) around to simulate controller input, LCD touch events,
+///   competition phase changes, and more while the stream is running.
+///
+/// Events are queued until the stream is polled. The queue is unbounded unless it's limited with
+/// [`SimulatorOptions::event_queue`].
 pub fn start_simulator(
     robot_code: PathBuf,
+    options: SimulatorOptions,
     require_unpause: bool,
     messages: Receiver<SimulatorMessage>,
 ) -> impl Stream<Item = Result<StreamedSimulatorEvent>> {
-    let (tx, rx) = mpsc::unbounded_channel();
+    let pauses = PauseQueue::default();
+    let counters = Arc::<ChannelCounters>::default();
+    let queue = Arc::new(EventQueue {
+        events: Mutex::default(),
+        waker: AtomicWaker::new(),
+        limit: options.event_queue,
+        counters: counters.clone(),
+        pauses: pauses.clone(),
+        caught_up: Mutex::default(),
+    });
+
+    let interface = SimulatorInterface::from({
+        let queue = queue.clone();
+        let pauses = pauses.clone();
+        move |inner| {
+            if require_unpause {
+                let (tx_unpause, rx_unpause) = oneshot::channel();
+                let event = StreamedSimulatorEvent {
+                    inner,
+                    unpause: Some(tx_unpause),
+                };
+                queue.push(Ok(event));
+                pauses.push(rx_unpause);
+            } else {
+                let event = StreamedSimulatorEvent {
+                    inner,
+                    unpause: None,
+                };
+                queue.push(Ok(event));
+            }
+        }
+    })
+    .with_pauses(pauses)
+    .with_counters(counters);
 
     SimulatorStream {
         finished: false,
-        rx,
-        future: tokio::task::spawn_blocking(move || {
-            let tx = Arc::new(Mutex::new(tx));
-            let res = block_on(simulate(
-                &robot_code,
-                {
-                    let tx = tx.clone();
-                    move |inner| {
-                        if require_unpause {
-                            let (tx_unpause, rx_unpause) = oneshot::channel();
-                            let event = StreamedSimulatorEvent {
-                                inner,
-                                unpause: Some(tx_unpause),
-                            };
-                            tx.lock().unwrap().send(Ok(event)).unwrap();
-                            _ = rx_unpause.blocking_recv();
-                        } else {
-                            let event = StreamedSimulatorEvent {
-                                inner,
-                                unpause: None,
-                            };
-                            tx.lock().unwrap().send(Ok(event)).unwrap();
-                        }
-                    }
-                },
-                messages,
-            ));
-            if let Err(e) = res {
-                tx.lock().unwrap().send(Err(e)).unwrap();
+        queue: queue.clone(),
+        future: tokio::spawn(async move {
+            let res = simulate(&robot_code, options, interface, messages).await;
+            match res {
+                Ok(SimulationOutcome {
+                    reason: StopReason::Crashed(e),
+                    ..
+                })
+                | Err(e) => queue.push(Err(e)),
+                Ok(_) => {}
             }
         }),
     }
 }
 
+/// Stream of simulator events. The simulation is cancelled when this is dropped.
 struct SimulatorStream {
-    rx: UnboundedReceiver<Result<StreamedSimulatorEvent>>,
+    queue: Arc<EventQueue>,
     finished: bool,
     future: JoinHandle<()>,
 }
 
+impl Drop for SimulatorStream {
+    fn drop(&mut self) {
+        // Dropping the simulation unwinds all of its tasks.
+        self.future.abort();
+    }
+}
+
 impl Stream for SimulatorStream {
     type Item = Result<StreamedSimulatorEvent>;
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
@@ -80,13 +118,108 @@ impl Stream for SimulatorStream {
 
         if !sim.finished {
             if let Poll::Ready(res) = sim.future.poll_unpin(cx) {
+                sim.finished = true;
                 if let Err(err) = res {
                     return Poll::Ready(Some(Err(err.into())));
                 }
-                sim.finished = true;
             }
         }
 
-        sim.rx.poll_recv(cx)
+        sim.queue.waker.register(cx.waker());
+        match sim.queue.pop() {
+            Some(event) => Poll::Ready(Some(event)),
+            // The simulator can't send any more events, so end the stream once they've been read.
+            None if sim.finished => Poll::Ready(None),
+            None => Poll::Pending,
+        }
     }
 }
+
+/// Events waiting for the stream to be polled.
+struct EventQueue {
+    events: Mutex<VecDeque<Result<StreamedSimulatorEvent>>>,
+    waker: AtomicWaker,
+    /// The most events to queue, and what to do with the rest.
+    limit: Option<(usize, OverflowPolicy)>,
+    counters: Arc<ChannelCounters>,
+    pauses: PauseQueue,
+    /// Lifts the pause started when the queue filled up, under [`OverflowPolicy::Pause`].
+    caught_up: Mutex<Option<oneshot::Sender<()>>>,
+}
+
+impl EventQueue {
+    fn push(&self, event: Result<StreamedSimulatorEvent>) {
+        let mut events = self.events.lock().unwrap();
+        if let Some((capacity, policy)) = self.limit {
+            if events.len() >= capacity {
+                match policy {
+                    OverflowPolicy::Pause => {
+                        let mut caught_up = self.caught_up.lock().unwrap();
+                        if caught_up.is_none() {
+                            let (tx, rx) = oneshot::channel();
+                            *caught_up = Some(tx);
+                            self.pauses.push(rx);
+                        }
+                    }
+                    OverflowPolicy::Coalesce => {
+                        let kind = coalesce_kind(&event);
+                        let older = events
+                            .iter_mut()
+                            .rev()
+                            .find(|older| kind.is_some() && coalesce_kind(older) == kind);
+                        if let Some(older) = older {
+                            *older = event;
+                            self.counters.coalesced.fetch_add(1, Ordering::Relaxed);
+                            return;
+                        }
+                    }
+                    OverflowPolicy::Drop => {
+                        if coalesce_kind(&event).is_some() {
+                            self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+        events.push_back(event);
+        self.counters
+            .queued
+            .store(events.len() as u32, Ordering::Relaxed);
+        drop(events);
+        self.waker.wake();
+    }
+
+    fn pop(&self) -> Option<Result<StreamedSimulatorEvent>> {
+        let mut events = self.events.lock().unwrap();
+        let event = events.pop_front()?;
+        self.counters
+            .queued
+            .store(events.len() as u32, Ordering::Relaxed);
+        if self
+            .limit
+            .is_some_and(|(capacity, _)| events.len() < capacity)
+        {
+            if let Some(caught_up) = self.caught_up.lock().unwrap().take() {
+                _ = caught_up.send(());
+            }
+        }
+        Some(event)
+    }
+}
+
+/// Events that are superseded by the next one of their kind, which can be coalesced, keyed by
+/// their kind (and port, for motors).
+fn coalesce_kind(
+    event: &Result<StreamedSimulatorEvent>,
+) -> Option<(Discriminant<SimulatorEvent>, u8)> {
+    let event = &event.as_ref().ok()?.inner;
+    let port = match event {
+        SimulatorEvent::MotorUpdated { port, .. } => *port,
+        SimulatorEvent::Telemetry(_)
+        | SimulatorEvent::CompetitionTimer { .. }
+        | SimulatorEvent::ChannelStats(_) => 0,
+        _ => return None,
+    };
+    Some((discriminant(event), port))
+}
@@ -0,0 +1,183 @@
+//! Running a scenario over and over with a different value of a robot code constant each time.
+//! See [`Sweep`].
+
+use std::{
+    path::PathBuf,
+    sync::{mpsc, Arc, Mutex},
+};
+
+use anyhow::Result;
+use pros_simulator_interface::{
+    MemoryLocation, SimulatorEvent, SimulatorMessage, Telemetry, ValueType,
+};
+
+use crate::{simulate, SimulationOutcome, SimulatorOptions};
+
+/// How many telemetry snapshots are taken per second when the options don't say.
+const DEFAULT_TELEMETRY_RATE: u32 = 50;
+
+/// Simulates the same robot code and scenario once for each value of a constant in robot code
+/// memory, scoring each run with a metric computed from its telemetry, e.g. to tune PID
+/// constants automatically.
+///
+/// Each value is written with [`SimulatorMessage::WriteMemory`] before the scenario's messages
+/// are sent, so the robot code has to read the constant after it starts rather than copying it
+/// while it's being loaded. Runs are [test builds](SimulatorOptions::test_build), and send
+/// telemetry 50 times per second unless the options set another
+/// [rate](SimulatorOptions::telemetry).
+///
+/// # Example
+///
+/// ```no_run
+/// # async fn run() -> anyhow::Result<()> {
+/// use pros_simulator::Sweep;
+/// use pros_simulator_interface::{MemoryLocation, ValueType};
+///
+/// let runs = Sweep::new(
+///     "robot.wasm",
+///     MemoryLocation::Symbol("DRIVE_KP".into()),
+///     ValueType::F32,
+/// )
+/// .range(0.5, 2.0, 16)
+/// .run(|telemetry| {
+///     // how far from 24 inches the robot ended up
+///     let pose = telemetry.last().and_then(|snapshot| snapshot.pose);
+///     pose.map_or(f64::INFINITY, |pose| (pose.x - 24.0).abs())
+/// })
+/// .await?;
+/// let best = runs.iter().min_by(|a, b| a.metric.total_cmp(&b.metric));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Sweep {
+    robot_code: PathBuf,
+    options: SimulatorOptions,
+    location: MemoryLocation,
+    value_type: ValueType,
+    values: Vec<f64>,
+    messages: Vec<SimulatorMessage>,
+}
+
+/// One run of a [`Sweep`].
+#[derive(Debug)]
+pub struct SweepRun {
+    /// The constant's value during the run.
+    pub value: f64,
+    /// What the metric made of the run's telemetry.
+    pub metric: f64,
+    /// How the robot code stopped.
+    pub outcome: SimulationOutcome,
+    /// The warnings sent during the run, including one if the constant couldn't be written.
+    pub warnings: Vec<String>,
+}
+
+impl Sweep {
+    /// Sweeps the constant of the given type at `location` in the robot code at `robot_code`.
+    /// Set the values to try with [`values`](Self::values) or [`range`](Self::range).
+    pub fn new(
+        robot_code: impl Into<PathBuf>,
+        location: MemoryLocation,
+        value_type: ValueType,
+    ) -> Self {
+        Self {
+            robot_code: robot_code.into(),
+            options: SimulatorOptions::new(),
+            location,
+            value_type,
+            values: Vec::new(),
+            messages: Vec::new(),
+        }
+    }
+
+    /// Simulate each run with these options. A [timeout](SimulatorOptions::timeout) keeps
+    /// values that make the robot code run forever from stalling the sweep.
+    pub fn options(mut self, options: SimulatorOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Try each of these values, in order.
+    pub fn values(mut self, values: impl IntoIterator<Item = f64>) -> Self {
+        self.values = values.into_iter().collect();
+        self
+    }
+
+    /// Try `steps` evenly spaced values from `start` to `end`, including both.
+    pub fn range(mut self, start: f64, end: f64, steps: usize) -> Self {
+        self.values = match steps {
+            0 => vec![],
+            1 => vec![start],
+            _ => (0..steps)
+                .map(|step| start + (end - start) * step as f64 / (steps - 1) as f64)
+                .collect(),
+        };
+        self
+    }
+
+    /// Send these messages to the robot code at the start of each run, like a scenario file,
+    /// e.g. to start autonomous or place the robot on the field.
+    pub fn messages(mut self, messages: impl IntoIterator<Item = SimulatorMessage>) -> Self {
+        self.messages = messages.into_iter().collect();
+        self
+    }
+
+    /// Simulates a run for each value, one after the other, and scores each with `metric`,
+    /// which is given the run's telemetry snapshots in the order they were taken. Returns an
+    /// error if the robot code couldn't be loaded.
+    pub async fn run(&self, mut metric: impl FnMut(&[Telemetry]) -> f64) -> Result<Vec<SweepRun>> {
+        let mut runs = Vec::with_capacity(self.values.len());
+        for &value in &self.values {
+            let (telemetry, warnings, outcome) = self.simulate(value).await?;
+            runs.push(SweepRun {
+                value,
+                metric: metric(&telemetry),
+                outcome,
+                warnings,
+            });
+        }
+        Ok(runs)
+    }
+
+    async fn simulate(
+        &self,
+        value: f64,
+    ) -> Result<(Vec<Telemetry>, Vec<String>, SimulationOutcome)> {
+        let mut options = self.options.clone().test_build(true);
+        if options.telemetry_rate.is_none() {
+            options = options.telemetry(DEFAULT_TELEMETRY_RATE);
+        }
+
+        let (tx, rx) = mpsc::channel();
+        _ = tx.send(SimulatorMessage::WriteMemory {
+            location: self.location.clone(),
+            bytes: self.value_type.encode(value),
+        });
+        for message in &self.messages {
+            _ = tx.send(message.clone());
+        }
+        drop(tx);
+
+        let telemetry = Arc::new(Mutex::new(Vec::new()));
+        let warnings = Arc::new(Mutex::new(Vec::new()));
+        let outcome = simulate(
+            &self.robot_code,
+            options,
+            {
+                let telemetry = telemetry.clone();
+                let warnings = warnings.clone();
+                move |event| match event {
+                    SimulatorEvent::Telemetry(snapshot) => telemetry.lock().unwrap().push(snapshot),
+                    SimulatorEvent::Warning(warning) => warnings.lock().unwrap().push(warning),
+                    _ => {}
+                }
+            },
+            rx,
+        )
+        .await?;
+
+        let telemetry = std::mem::take(&mut *telemetry.lock().unwrap());
+        let warnings = std::mem::take(&mut *warnings.lock().unwrap());
+        Ok((telemetry, warnings, outcome))
+    }
+}
@@ -1 +1,8 @@
+pub mod field;
+pub mod match_automation;
+pub mod mechanisms;
+pub mod scoring;
 pub mod system_daemon;
+pub mod telemetry;
+pub mod test_runner;
+pub mod watches;
@@ -0,0 +1,254 @@
+//! Game objects the robot pushes around the field, and the mechanisms it picks them up with. See
+//! [`SimulatorMessage::AddGameObject`](pros_simulator_interface::SimulatorMessage::AddGameObject).
+//!
+//! This isn't a physics engine: objects have no mass or velocity, and are only moved far enough
+//! that they stop overlapping the robot and each other.
+
+use std::time::Duration;
+
+use pros_simulator_interface::{GameObject, Mechanism, Pose, SimulatorEvent};
+
+use super::mechanisms::{check_mechanism, facing, MechanismModel};
+use crate::host::{HostCtx, TICK_PERIOD_MS};
+
+/// The robot's collision radius if [`SimulatorOptions::robot_radius`] isn't set, which fits an
+/// 18 inch robot.
+///
+/// [`SimulatorOptions::robot_radius`]: crate::SimulatorOptions::robot_radius
+pub const DEFAULT_ROBOT_RADIUS: f64 = 9.0;
+
+/// How many times the robot pushes objects and overlapping objects are pushed apart each tick. A
+/// pile of objects can still overlap a little afterwards, which is fine for drawing them.
+const SEPARATION_PASSES: usize = 4;
+
+/// The game objects on the field and the robot pushing them.
+pub struct Field {
+    objects: Vec<GameObject>,
+    mechanisms: Vec<MechanismModel>,
+    robot_radius: f64,
+    /// The pose objects were last pushed out of the way of.
+    pose: Option<Pose>,
+    /// Objects to send a [`SimulatorEvent::GameObjectUpdated`] for, by index.
+    updated: Vec<usize>,
+    /// Whether objects were added or let out since they were last pushed apart.
+    unsettled: bool,
+    /// The tick mechanisms were last run at.
+    last_tick: Option<u64>,
+}
+
+impl Field {
+    pub fn new(objects: Vec<GameObject>, mechanisms: Vec<Mechanism>, robot_radius: f64) -> Self {
+        Self {
+            updated: (0..objects.len()).collect(),
+            unsettled: !objects.is_empty(),
+            objects,
+            mechanisms: mechanisms.into_iter().map(MechanismModel::new).collect(),
+            robot_radius,
+            pose: None,
+            last_tick: None,
+        }
+    }
+
+    /// The objects on the field, for drawing them.
+    #[cfg(feature = "render")]
+    pub fn objects(&self) -> &[GameObject] {
+        &self.objects
+    }
+
+    #[cfg(feature = "render")]
+    pub fn robot_radius(&self) -> f64 {
+        self.robot_radius
+    }
+
+    /// Adds a mechanism to the robot, or returns why it can't be added.
+    pub fn add_mechanism(&mut self, mechanism: Mechanism) -> Result<(), String> {
+        let ignored = |reason| {
+            format!(
+                "Mechanism `{}` was ignored because {reason}",
+                mechanism.name
+            )
+        };
+        check_mechanism(&mechanism).map_err(ignored)?;
+        if self.mechanisms.iter().any(|m| m.name() == mechanism.name) {
+            return Err(ignored("the robot already has a mechanism with that name"));
+        }
+        self.mechanisms.push(MechanismModel::new(mechanism));
+        Ok(())
+    }
+
+    /// Places an object on the field, replacing any object with the same name, or returns why it
+    /// can't be placed.
+    pub fn add(&mut self, object: GameObject) -> Result<(), String> {
+        check_object(&object).map_err(|reason| {
+            format!("Game object `{}` was ignored because {reason}", object.name)
+        })?;
+        let index = match self.objects.iter().position(|o| o.name == object.name) {
+            Some(index) => {
+                self.objects[index] = object;
+                index
+            }
+            None => {
+                self.objects.push(object);
+                self.objects.len() - 1
+            }
+        };
+        self.updated.push(index);
+        self.unsettled = true;
+        Ok(())
+    }
+
+    /// Pushes objects out of the robot's way if it has moved and runs its mechanisms, then sends a
+    /// [`SimulatorEvent::GameObjectUpdated`] for each object that was placed or moved and a
+    /// [`SimulatorEvent::MechanismUpdated`] for each mechanism that changed.
+    pub async fn tick(&mut self, host: &(impl HostCtx + Sync)) {
+        if self.objects.is_empty() && self.mechanisms.is_empty() {
+            return;
+        }
+        let pose = *host.pose_lock().await;
+        if pose != self.pose || self.unsettled {
+            self.pose = pose;
+            self.unsettled = false;
+            self.settle();
+        }
+
+        let tick = host.ticks();
+        let elapsed = self.last_tick.map_or(0, |last| tick.saturating_sub(last));
+        let elapsed = Duration::from_millis(elapsed * TICK_PERIOD_MS);
+        self.last_tick = Some(tick);
+        let voltages = {
+            let smart_ports = host.smart_ports_lock().await;
+            self.mechanisms
+                .iter()
+                .map(|mechanism| smart_ports.motor_voltage(mechanism.port()))
+                .collect::<Vec<_>>()
+        };
+        for (mechanism, millivolts) in self.mechanisms.iter_mut().zip(voltages) {
+            let held = mechanism.holding();
+            let changed = mechanism.run(
+                millivolts,
+                elapsed,
+                self.pose,
+                self.robot_radius,
+                &mut self.objects,
+                &mut self.updated,
+            );
+            if changed {
+                // objects that were let out might overlap others
+                self.unsettled |= mechanism.holding() < held;
+                host.interface()
+                    .send(SimulatorEvent::MechanismUpdated(mechanism.state()));
+            }
+        }
+
+        self.updated.sort_unstable();
+        self.updated.dedup();
+        for index in self.updated.drain(..) {
+            host.interface().send(SimulatorEvent::GameObjectUpdated(
+                self.objects[index].clone(),
+            ));
+        }
+    }
+
+    /// Moves objects until they don't overlap the robot or each other, remembering which moved.
+    fn settle(&mut self) {
+        let before = self
+            .objects
+            .iter()
+            .map(|object| (object.x, object.y))
+            .collect::<Vec<_>>();
+
+        if let Some(pose) = self.pose {
+            // held objects ride along with the robot
+            for object in &mut self.objects {
+                if object.held_by.is_some() {
+                    object.x = pose.x;
+                    object.y = pose.y;
+                }
+            }
+        }
+
+        for _ in 0..SEPARATION_PASSES {
+            if let Some(pose) = self.pose {
+                for object in self.objects.iter_mut().filter(|object| pushable(object)) {
+                    let min = self.robot_radius + object.radius;
+                    let robot = (pose.x, pose.y);
+                    if let Some((dx, dy)) =
+                        separation(robot, (object.x, object.y), min, pose.heading)
+                    {
+                        object.x += dx;
+                        object.y += dy;
+                    }
+                }
+            }
+            for i in 0..self.objects.len() {
+                for j in i + 1..self.objects.len() {
+                    self.separate(i, j);
+                }
+            }
+        }
+
+        for (index, (object, (x, y))) in self.objects.iter().zip(before).enumerate() {
+            if object.x != x || object.y != y {
+                self.updated.push(index);
+            }
+        }
+    }
+
+    /// Pushes two objects apart if they overlap. Objects that aren't fixed share the push, and
+    /// held objects don't collide.
+    fn separate(&mut self, i: usize, j: usize) {
+        let (a, b) = (&self.objects[i], &self.objects[j]);
+        if a.held_by.is_some() || b.held_by.is_some() {
+            return;
+        }
+        let share = match (a.fixed, b.fixed) {
+            (true, true) => return,
+            (true, false) => (0.0, 1.0),
+            (false, true) => (1.0, 0.0),
+            (false, false) => (0.5, 0.5),
+        };
+        let heading = self.pose.map_or(0.0, |pose| pose.heading);
+        let min = a.radius + b.radius;
+        let Some((dx, dy)) = separation((a.x, a.y), (b.x, b.y), min, heading) else {
+            return;
+        };
+        self.objects[i].x -= dx * share.0;
+        self.objects[i].y -= dy * share.0;
+        self.objects[j].x += dx * share.1;
+        self.objects[j].y += dy * share.1;
+    }
+}
+
+/// How far a circle at `b` has to move to be `min` inches from one at `a`, or `None` if it
+/// already is. Circles on top of each other are separated in the direction of `heading`.
+fn separation(a: (f64, f64), b: (f64, f64), min: f64, heading: f64) -> Option<(f64, f64)> {
+    let distance = (b.0 - a.0).hypot(b.1 - a.1);
+    if distance >= min {
+        return None;
+    }
+    let (x, y) = if distance > f64::EPSILON {
+        ((b.0 - a.0) / distance, (b.1 - a.1) / distance)
+    } else {
+        facing(heading)
+    };
+    Some((x * (min - distance), y * (min - distance)))
+}
+
+/// Whether the robot and other objects can push an object.
+fn pushable(object: &GameObject) -> bool {
+    !object.fixed && object.held_by.is_none()
+}
+
+/// Checks that an object has a place on the field, returning why it doesn't if it doesn't.
+pub fn check_object(object: &GameObject) -> Result<(), &'static str> {
+    if ![object.x, object.y, object.radius]
+        .iter()
+        .all(|value| value.is_finite())
+    {
+        return Err("its position or radius isn't a finite number");
+    }
+    if object.radius < 0.0 {
+        return Err("its radius is negative");
+    }
+    Ok(())
+}
@@ -0,0 +1,100 @@
+//! Running a match like field control would. See
+//! [`SimulatorOptions::run_match`](crate::SimulatorOptions::run_match).
+
+use std::time::{Duration, Instant};
+
+use pros_simulator_interface::{CompetitionPhase, SimulatorEvent};
+
+use crate::{host::HostCtx, MatchTiming, StopReason};
+
+/// The time between [`SimulatorEvent::CompetitionTimer`] events during a period.
+const TIMER_PERIOD: Duration = Duration::from_millis(100);
+
+const AUTONOMOUS: CompetitionPhase = CompetitionPhase {
+    autonomous: true,
+    enabled: true,
+    is_competition: true,
+};
+
+const DRIVER_CONTROL: CompetitionPhase = CompetitionPhase {
+    autonomous: false,
+    enabled: true,
+    is_competition: true,
+};
+
+const MATCH_OVER: CompetitionPhase = CompetitionPhase {
+    autonomous: false,
+    enabled: false,
+    is_competition: true,
+};
+
+/// Moves the competition phase through a match and sends the match clock to the frontend.
+pub struct MatchAutomation {
+    /// The match's timing, or `None` if no match is being run.
+    timing: Option<MatchTiming>,
+    /// When the match started, or `None` if it hasn't yet.
+    started: Option<Instant>,
+    /// The phase of the current period, so the phase is only set when a period starts.
+    phase: Option<CompetitionPhase>,
+    next_timer: Instant,
+}
+
+impl MatchAutomation {
+    pub fn new(timing: Option<MatchTiming>) -> Self {
+        Self {
+            timing,
+            started: None,
+            phase: None,
+            next_timer: Instant::now(),
+        }
+    }
+
+    /// Starts the match, if one is being run.
+    pub fn start(&mut self) {
+        if self.timing.is_some() {
+            self.started = Some(Instant::now());
+        }
+    }
+
+    /// Starts the next period if the current one is over, and sends the match clock if it's due.
+    pub async fn tick(&mut self, host: &(impl HostCtx + Sync)) {
+        let (Some(timing), Some(started)) = (self.timing, self.started) else {
+            return;
+        };
+        let elapsed = started.elapsed();
+        let (phase, remaining) = if elapsed < timing.autonomous {
+            (AUTONOMOUS, timing.autonomous - elapsed)
+        } else if elapsed < timing.autonomous + timing.driver_control {
+            (
+                DRIVER_CONTROL,
+                timing.autonomous + timing.driver_control - elapsed,
+            )
+        } else {
+            (MATCH_OVER, Duration::ZERO)
+        };
+
+        let now = Instant::now();
+        if self.phase != Some(phase) {
+            self.phase = Some(phase);
+            *host.competition_phase_lock().await = phase;
+            self.next_timer = now;
+        }
+        if now < self.next_timer {
+            return;
+        }
+        // skip updates that were missed instead of sending them all at once
+        while self.next_timer <= now {
+            self.next_timer += TIMER_PERIOD;
+        }
+
+        host.interface().send(SimulatorEvent::CompetitionTimer {
+            phase,
+            remaining_ms: remaining.as_millis() as u32,
+        });
+
+        if phase == MATCH_OVER {
+            self.timing = None;
+            host.tasks_lock().await.start_shutdown(StopReason::Finished);
+        }
+    }
+}
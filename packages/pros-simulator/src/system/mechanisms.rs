@@ -0,0 +1,185 @@
+//! Intakes and lifts driven by motors, which let the robot pick up game objects without a 3D
+//! physics model. See
+//! [`SimulatorMessage::AddMechanism`](pros_simulator_interface::SimulatorMessage::AddMechanism).
+
+use std::time::Duration;
+
+use pros_simulator_interface::{GameObject, Mechanism, MechanismKind, MechanismState, Pose};
+
+use crate::host::smart_ports::{MAX_MOTOR_MILLIVOLTS, NUM_SMART_PORTS};
+
+/// The voltage a motor has to be driven at to run its mechanism, a quarter of its maximum.
+const RUNNING_MILLIVOLTS: i32 = MAX_MOTOR_MILLIVOLTS / 4;
+
+/// How far a lift moves before its height is sent again, in inches.
+const HEIGHT_STEP: f64 = 0.1;
+
+/// A mechanism and what it's doing.
+pub struct MechanismModel {
+    mechanism: Mechanism,
+    /// The objects an intake is holding, in the order it picked them up.
+    held: Vec<String>,
+    height: f64,
+    reported_height: f64,
+}
+
+impl MechanismModel {
+    pub fn new(mechanism: Mechanism) -> Self {
+        Self {
+            mechanism,
+            held: Vec::new(),
+            height: 0.0,
+            reported_height: 0.0,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.mechanism.name
+    }
+
+    /// The smart port of the mechanism's motor, numbered from 1.
+    pub fn port(&self) -> u8 {
+        self.mechanism.port.unsigned_abs()
+    }
+
+    /// How many objects an intake is holding.
+    pub fn holding(&self) -> usize {
+        self.held.len()
+    }
+
+    pub fn state(&self) -> MechanismState {
+        MechanismState {
+            name: self.mechanism.name.clone(),
+            held: self.held.clone(),
+            height: self.height,
+        }
+    }
+
+    /// Runs the mechanism for `elapsed` with its motor driven at `millivolts`, picking up or
+    /// letting out objects from the robot at `pose`. Objects that moved are added to `moved` by
+    /// index. Returns whether the mechanism's [`state`](Self::state) should be sent.
+    pub fn run(
+        &mut self,
+        millivolts: i32,
+        elapsed: Duration,
+        pose: Option<Pose>,
+        robot_radius: f64,
+        objects: &mut [GameObject],
+        moved: &mut Vec<usize>,
+    ) -> bool {
+        let millivolts = millivolts * self.mechanism.port.signum() as i32;
+        match self.mechanism.kind {
+            MechanismKind::Intake { reach, capacity } => {
+                let Some(pose) = pose else {
+                    return false;
+                };
+                if millivolts >= RUNNING_MILLIVOLTS {
+                    self.pick_up(pose, robot_radius + reach, capacity, objects, moved)
+                } else if millivolts <= -RUNNING_MILLIVOLTS {
+                    self.let_out(pose, robot_radius, objects, moved)
+                } else {
+                    false
+                }
+            }
+            MechanismKind::Lift { max_height, speed } => {
+                if millivolts.abs() >= RUNNING_MILLIVOLTS {
+                    let rate = speed * f64::from(millivolts) / f64::from(MAX_MOTOR_MILLIVOLTS);
+                    self.height =
+                        (self.height + rate * elapsed.as_secs_f64()).clamp(0.0, max_height);
+                }
+                let at_end = self.height == 0.0 || self.height == max_height;
+                let changed = (self.height - self.reported_height).abs() >= HEIGHT_STEP
+                    || (at_end && self.height != self.reported_height);
+                if changed {
+                    self.reported_height = self.height;
+                }
+                changed
+            }
+        }
+    }
+
+    /// Picks up loose objects in front of the robot whose edges are within `reach` inches of
+    /// its center, until the intake is full.
+    fn pick_up(
+        &mut self,
+        pose: Pose,
+        reach: f64,
+        capacity: u32,
+        objects: &mut [GameObject],
+        moved: &mut Vec<usize>,
+    ) -> bool {
+        let (forward_x, forward_y) = facing(pose.heading);
+        let mut picked_up = false;
+        for (index, object) in objects.iter_mut().enumerate() {
+            if self.held.len() >= capacity as usize {
+                break;
+            }
+            if object.fixed || object.held_by.is_some() {
+                continue;
+            }
+            let (dx, dy) = (object.x - pose.x, object.y - pose.y);
+            let in_front = dx * forward_x + dy * forward_y > 0.0;
+            if !in_front || dx.hypot(dy) > reach + object.radius {
+                continue;
+            }
+            object.held_by = Some(self.mechanism.name.clone());
+            object.x = pose.x;
+            object.y = pose.y;
+            self.held.push(object.name.clone());
+            moved.push(index);
+            picked_up = true;
+        }
+        picked_up
+    }
+
+    /// Puts every held object down touching the front of the robot.
+    fn let_out(
+        &mut self,
+        pose: Pose,
+        robot_radius: f64,
+        objects: &mut [GameObject],
+        moved: &mut Vec<usize>,
+    ) -> bool {
+        if self.held.is_empty() {
+            return false;
+        }
+        let (forward_x, forward_y) = facing(pose.heading);
+        for name in self.held.drain(..) {
+            let Some(index) = objects.iter().position(|object| object.name == name) else {
+                continue;
+            };
+            let object = &mut objects[index];
+            let distance = robot_radius + object.radius;
+            object.held_by = None;
+            object.x = pose.x + forward_x * distance;
+            object.y = pose.y + forward_y * distance;
+            moved.push(index);
+        }
+        true
+    }
+}
+
+/// The direction a heading faces, as a unit vector. Headings are clockwise from the positive y
+/// axis.
+pub fn facing(heading: f64) -> (f64, f64) {
+    let heading = heading.to_radians();
+    (heading.sin(), heading.cos())
+}
+
+/// Checks that a mechanism has a motor and sensible dimensions, returning why it doesn't if it
+/// doesn't.
+pub fn check_mechanism(mechanism: &Mechanism) -> Result<(), &'static str> {
+    if !(1..=NUM_SMART_PORTS).contains(&usize::from(mechanism.port.unsigned_abs())) {
+        return Err("its port isn't a smart port (1 to 21, or -1 to -21 if reversed)");
+    }
+    let dimensions_valid = match mechanism.kind {
+        MechanismKind::Intake { reach, .. } => reach.is_finite() && reach >= 0.0,
+        MechanismKind::Lift { max_height, speed } => {
+            max_height.is_finite() && max_height >= 0.0 && speed.is_finite() && speed >= 0.0
+        }
+    };
+    if !dimensions_valid {
+        return Err("its dimensions aren't finite, non-negative numbers");
+    }
+    Ok(())
+}
@@ -0,0 +1,123 @@
+//! Scoring points as the robot reaches parts of the field, for evaluating autonomous routines
+//! automatically. See
+//! [`SimulatorMessage::AddScoringZone`](pros_simulator_interface::SimulatorMessage::AddScoringZone).
+
+use pros_simulator_interface::{ScoringRule, ScoringZone, SimulatorEvent, ZoneShape};
+
+use crate::host::HostCtx;
+
+struct Zone {
+    zone: ScoringZone,
+    /// Whether an `Enter` zone has been scored, or an `Inside` zone's points are counted.
+    scored: bool,
+}
+
+/// The zones a scenario has defined and the points scored in them.
+#[derive(Default)]
+pub struct Scoreboard {
+    zones: Vec<Zone>,
+    score: i32,
+}
+
+impl Scoreboard {
+    pub fn new(zones: Vec<ScoringZone>) -> Self {
+        Self {
+            zones: zones
+                .into_iter()
+                .map(|zone| Zone {
+                    zone,
+                    scored: false,
+                })
+                .collect(),
+            score: 0,
+        }
+    }
+
+    /// Each zone and whether its points are currently scored, for drawing them.
+    #[cfg(feature = "render")]
+    pub fn zones(&self) -> impl Iterator<Item = (&ScoringZone, bool)> {
+        self.zones.iter().map(|zone| (&zone.zone, zone.scored))
+    }
+
+    /// Starts scoring a zone, or returns why it can't be scored.
+    pub fn add(&mut self, zone: ScoringZone) -> Result<(), String> {
+        check_shape(&zone.shape).map_err(|reason| {
+            format!("Scoring zone `{}` was ignored because {reason}", zone.name)
+        })?;
+        self.zones.push(Zone {
+            zone,
+            scored: false,
+        });
+        Ok(())
+    }
+
+    /// Scores the zones the robot has entered or left since the last tick, sending a
+    /// [`SimulatorEvent::ScoreChanged`] for each. Nothing is scored until the robot has a pose.
+    pub async fn tick(&mut self, host: &(impl HostCtx + Sync)) {
+        if self.zones.is_empty() {
+            return;
+        }
+        let Some(pose) = *host.pose_lock().await else {
+            return;
+        };
+        let phase = *host.competition_phase_lock().await;
+        let autonomous = phase.autonomous && phase.enabled;
+
+        for Zone { zone, scored } in &mut self.zones {
+            if zone.autonomous_only && !autonomous {
+                continue;
+            }
+            let inside = zone.shape.contains(&pose);
+            let points = match zone.rule {
+                ScoringRule::Enter if inside && !*scored => zone.points,
+                ScoringRule::Inside if inside != *scored => {
+                    if inside {
+                        zone.points
+                    } else {
+                        -zone.points
+                    }
+                }
+                _ => continue,
+            };
+            *scored = !*scored;
+            self.score += points;
+            host.interface().send(SimulatorEvent::ScoreChanged {
+                zone: zone.name.clone(),
+                points,
+                score: self.score,
+                millis: host.millis(),
+            });
+        }
+    }
+}
+
+/// Checks that a shape covers part of the field, returning why it doesn't if it doesn't.
+pub fn check_shape(shape: &ZoneShape) -> Result<(), &'static str> {
+    match *shape {
+        ZoneShape::Rectangle {
+            min_x,
+            min_y,
+            max_x,
+            max_y,
+        } => {
+            if ![min_x, min_y, max_x, max_y]
+                .iter()
+                .all(|value| value.is_finite())
+            {
+                return Err("its corners aren't finite numbers");
+            }
+            if min_x > max_x || min_y > max_y {
+                return Err("its minimum corner is past its maximum corner");
+            }
+        }
+        ZoneShape::Circle { x, y, radius } => {
+            if ![x, y, radius].iter().all(|value| value.is_finite()) {
+                return Err("its center or radius isn't a finite number");
+            }
+            if radius < 0.0 {
+                return Err("its radius is negative");
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,20 +1,34 @@
 use std::{
     sync::{mpsc::Receiver, Arc},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
-use pros_simulator_interface::{CompetitionPhase, SimulatorMessage};
-use pros_sys::{COMPETITION_AUTONOMOUS, COMPETITION_CONNECTED, COMPETITION_DISABLED};
-use tokio::{
-    sync::Mutex,
-    time::{interval, sleep},
+use pros_simulator_interface::{
+    BrainButton, CompetitionPhase, SimulatorEvent, SimulatorMessage, TestResult,
 };
+use pros_sys::{COMPETITION_AUTONOMOUS, COMPETITION_CONNECTED, COMPETITION_DISABLED};
+use tokio::sync::Mutex;
 use wasmtime::Caller;
 
-use crate::host::{
-    lcd::Lcd,
-    task::{Task, TaskOptions, TaskState},
-    Host, HostCtx,
+use super::{
+    field::{Field, DEFAULT_ROBOT_RADIUS},
+    match_automation::MatchAutomation,
+    scoring::Scoreboard,
+    telemetry::TelemetryTimer,
+    test_runner::test_names,
+    watches::{read_memory, write_memory, Watches},
+};
+#[cfg(feature = "render")]
+use crate::host::render::Scene;
+use crate::{
+    host::{
+        abi::ProgramAbi,
+        lcd::Lcd,
+        task::{Task, TaskOptions, TaskState},
+        timer::sleep,
+        Host, HostCtx, TICK_PERIOD_MS,
+    },
+    StartKind, StopReason,
 };
 
 enum UserTask {
@@ -43,21 +57,54 @@ async fn spawn_user_code(
         UserTask::CompInit => "User Comp. Init. (PROS)",
     };
 
+    spawn_entrypoint(caller, host, entrypoint, name).await
+}
+
+/// Spawns a task that runs a function exported by the robot code.
+async fn spawn_entrypoint(
+    caller: &mut Caller<'_, Host>,
+    host: &Host,
+    entrypoint: &str,
+    name: &str,
+) -> anyhow::Result<Arc<Mutex<Task>>> {
     let mut pool = caller.tasks_lock().await;
-    let init_options = TaskOptions::new_global(&mut pool, host, entrypoint)?.name(name);
-    pool.spawn(init_options, &host.module(), &host.interface())
-        .await
+    let options = TaskOptions::new_global(&mut pool, host, entrypoint)?.name(name);
+    pool.spawn(options, &host.module(), &host.interface()).await
+}
+
+/// The functions to run before the competition phases start, in order: the lifecycle hooks that
+/// apply to this kind of start and that the robot code exports, then `initialize`.
+fn initialization_entrypoints(host: &Host) -> Vec<(&'static str, &'static str)> {
+    let mut hooks = vec![];
+    if host.options().start_kind == StartKind::Cold {
+        hooks.push(("cold_init", "Cold Initialization (PROS)"));
+    }
+    hooks.push(("hot_init", "Hot Initialization (PROS)"));
+
+    let module = host.module();
+    let mut entrypoints = hooks
+        .into_iter()
+        .filter(|(entrypoint, _)| module.get_export(entrypoint).is_some())
+        .collect::<Vec<_>>();
+    entrypoints.push(("initialize", "User Initialization (PROS)"));
+    entrypoints
 }
 
 async fn do_background_operations(
     caller: &mut Caller<'_, Host>,
     messages: &mut Receiver<SimulatorMessage>,
+    telemetry: &mut TelemetryTimer,
+    automation: &mut MatchAutomation,
+    watches: &mut Watches,
+    scoreboard: &mut Scoreboard,
+    field: &mut Field,
 ) -> anyhow::Result<()> {
     while let Ok(message) = messages.try_recv() {
         match message {
             SimulatorMessage::ControllerUpdate(master, partner) => {
+                let arrives_at = Instant::now() + caller.options().controller_latency;
                 let mut controllers = caller.controllers_lock().await;
-                controllers.update(master, partner);
+                controllers.send(master, partner, arrives_at);
             }
             SimulatorMessage::LcdButtonsUpdate(btns) => {
                 let cb_table = {
@@ -68,16 +115,243 @@ async fn do_background_operations(
 
                 Lcd::press(&caller.lcd(), &mut *caller, cb_table, btns).await?;
             }
+            SimulatorMessage::LcdSelectorChoose(choice) => {
+                let cb_table = caller.current_task().await.lock().await.indirect_call_table;
+                Lcd::choose(&caller.lcd(), &mut *caller, cb_table, &choice).await?;
+            }
             SimulatorMessage::PhaseChange(new_phase) => {
                 let mut phase = caller.competition_phase_lock().await;
                 *phase = new_phase;
             }
+            SimulatorMessage::Stop => {
+                caller
+                    .tasks_lock()
+                    .await
+                    .start_shutdown(StopReason::Cancelled);
+            }
+            SimulatorMessage::ListTasks => {
+                let tasks = caller.tasks_lock().await.task_list().await;
+                caller.interface().send(SimulatorEvent::TaskList(tasks));
+            }
+            SimulatorMessage::SetRates(rates) => {
+                telemetry.set_rates(&rates);
+                caller
+                    .smart_ports_lock()
+                    .await
+                    .set_motor_update_rate(rates.motor_updates);
+            }
+            SimulatorMessage::FailNextCall { api, errno } => {
+                let module = caller.module();
+                if !module.imports().any(|import| import.name() == api) {
+                    caller.interface().send(SimulatorEvent::Warning(format!(
+                        "Asked to fail the next call to `{api}`, but the robot code doesn't \
+                         import it"
+                    )));
+                }
+                caller.injected_failures_lock().await.push(api, errno);
+            }
+            SimulatorMessage::ReadMemory { location, len } => {
+                let event = match read_memory(caller, &location, len).await {
+                    Ok((address, bytes)) => SimulatorEvent::MemoryValue {
+                        location,
+                        address,
+                        bytes,
+                    },
+                    Err(err) => SimulatorEvent::Warning(format!(
+                        "Couldn't read {len} bytes of memory at {location}: {err}"
+                    )),
+                };
+                caller.interface().send(event);
+            }
+            SimulatorMessage::WriteMemory { location, bytes } => {
+                let len = bytes.len();
+                let result = if caller.options().test_build {
+                    write_memory(caller, &location, &bytes).await
+                } else {
+                    Err("memory can only be written in a test build. Use \
+                         `SimulatorOptions::test_build` or `--test-build` to enable it."
+                        .into())
+                };
+                if let Err(err) = result {
+                    caller.interface().send(SimulatorEvent::Warning(format!(
+                        "Couldn't write {len} bytes of memory at {location}: {err}"
+                    )));
+                }
+            }
+            SimulatorMessage::Watch {
+                location,
+                value_type,
+                rate,
+            } => watches.watch(location, value_type, rate),
+            SimulatorMessage::BreakOnCall { api, condition } => {
+                let module = caller.module();
+                if !module.imports().any(|import| import.name() == api) {
+                    caller.interface().send(SimulatorEvent::Warning(format!(
+                        "Asked to break on calls to `{api}`, but the robot code doesn't import it"
+                    )));
+                }
+                caller.breakpoints().add(api, condition);
+            }
+            SimulatorMessage::ClearBreakpoints => caller.breakpoints().clear(),
+            SimulatorMessage::Resume => caller.tasks_lock().await.resume_robot_code(),
+            SimulatorMessage::SetPose(pose) => {
+                *caller.pose_lock().await = Some(pose);
+                caller.interface().send(SimulatorEvent::PoseSet(pose));
+            }
+            SimulatorMessage::SetInputShaping {
+                controller,
+                shaping,
+            } => {
+                if shaping.deadzone > 126 || shaping.expo > 100 {
+                    caller.interface().send(SimulatorEvent::Warning(format!(
+                        "Input shaping for the {controller:?} controller is out of range, so it \
+                         was clamped: the deadzone can be at most 126 and expo at most 100, but \
+                         they were {} and {}",
+                        shaping.deadzone, shaping.expo
+                    )));
+                }
+                caller
+                    .controllers_lock()
+                    .await
+                    .set_shaping(controller, shaping);
+            }
+            SimulatorMessage::BrainButton(BrainButton::Stop) => {
+                caller
+                    .tasks_lock()
+                    .await
+                    .start_shutdown(StopReason::Cancelled);
+            }
+            SimulatorMessage::BrainButton(BrainButton::Run) => {
+                caller.interface().send(SimulatorEvent::Warning(
+                    "Can't run the program again, because the simulator stops once the program \
+                     does. `pros-simulator-server` restarts programs loaded with `--slot`."
+                        .into(),
+                ));
+            }
+            SimulatorMessage::Custom { tag, bytes } => {
+                caller.custom_messages_lock().await.push(tag, bytes);
+            }
+            SimulatorMessage::SelectSlot(slot) => {
+                caller.interface().send(SimulatorEvent::Warning(format!(
+                    "Can't switch to slot {slot}, because the simulator only has the one \
+                     program that's running. `pros-simulator-server` can load programs into \
+                     other slots with `--slot`."
+                )));
+            }
+            SimulatorMessage::AddScoringZone(zone) => {
+                if let Err(warning) = scoreboard.add(zone) {
+                    caller.interface().send(SimulatorEvent::Warning(warning));
+                }
+            }
+            SimulatorMessage::AddGameObject(object) => {
+                if let Err(warning) = field.add(object) {
+                    caller.interface().send(SimulatorEvent::Warning(warning));
+                }
+            }
+            SimulatorMessage::AddMechanism(mechanism) => {
+                if let Err(warning) = field.add_mechanism(mechanism) {
+                    caller.interface().send(SimulatorEvent::Warning(warning));
+                }
+            }
+            SimulatorMessage::AddMotorGroup(group) => {
+                let added = caller.smart_ports_lock().await.add_motor_group(group);
+                if let Err(warning) = added {
+                    caller.interface().send(SimulatorEvent::Warning(warning));
+                }
+            }
+            SimulatorMessage::ScreenTouch { x, y, pressed } => {
+                caller.screen_lock().await.touch(x, y, pressed);
+            }
+            SimulatorMessage::UploadProgram(chunk) => {
+                caller.interface().send(SimulatorEvent::Warning(format!(
+                    "Can't upload a program to slot {}, because the simulator only runs the \
+                     program it was started with. `pros-simulator-server` accepts uploads with \
+                     `--upload-dir`.",
+                    chunk.slot
+                )));
+            }
+            // added to a newer version of the interface crate
+            message => {
+                caller.interface().send(SimulatorEvent::Warning(format!(
+                    "The simulator doesn't support this message: {message:?}"
+                )));
+            }
         }
     }
 
+    let disconnected = caller.controllers_lock().await.apply_arrived();
+    for controller in disconnected {
+        caller
+            .interface()
+            .send(SimulatorEvent::ControllerDisconnected(controller));
+    }
+    let switched = caller.controllers_lock().await.take_switch_phase();
+    if let Some(phase) = switched {
+        *caller.competition_phase_lock().await = phase;
+    }
+
+    // send changes that were held back by the rate limit
+    let (millis, tick) = (caller.millis(), caller.ticks());
+    let motor_updates = caller
+        .smart_ports_lock()
+        .await
+        .take_motor_updates(millis, tick);
+    for event in motor_updates {
+        caller.interface().send(event);
+    }
+    let tick = caller.ticks();
+    caller.screen_lock().await.tick(tick);
+
+    if let Some(threshold) = caller.options().mutex_hold_threshold {
+        report_held_mutexes(caller, threshold).await;
+    }
+
+    automation.tick(caller).await;
+    telemetry.tick(caller).await;
+    watches.tick(caller).await;
+    field.tick(caller).await;
+    scoreboard.tick(caller).await;
+    #[cfg(feature = "render")]
+    render_frame(caller, field, scoreboard).await;
+
     Ok(())
 }
 
+/// Draws a frame of the field if one is due.
+#[cfg(feature = "render")]
+async fn render_frame(caller: &mut Caller<'_, Host>, field: &Field, scoreboard: &Scoreboard) {
+    let Some(renderer) = caller.renderer() else {
+        return;
+    };
+    let millis = caller.ticks() * TICK_PERIOD_MS;
+    if !renderer.frame_due(millis) {
+        return;
+    }
+    let scene = Scene {
+        pose: *caller.pose_lock().await,
+        robot_radius: field.robot_radius(),
+        objects: field.objects(),
+        zones: scoreboard.zones().collect(),
+    };
+    renderer.render(millis, &scene);
+}
+
+/// Sends a [`SimulatorEvent::MutexHeldTooLong`] for each mutex that's been held for longer than
+/// `threshold` since it was last reported.
+async fn report_held_mutexes(caller: &mut Caller<'_, Host>, threshold: Duration) {
+    let now = caller.ticks();
+    let deadline = now.saturating_sub(threshold.as_millis() as u64 / TICK_PERIOD_MS);
+    let overdue = caller.mutexes_lock().await.take_overdue(deadline);
+    for (mutex_id, holder) in overdue {
+        caller.interface().send(SimulatorEvent::MutexHeldTooLong {
+            mutex_id: mutex_id as u32,
+            task_id: holder.task_id,
+            task_name: holder.task_name,
+            held_millis: ((now - holder.since) * TICK_PERIOD_MS) as u32,
+        });
+    }
+}
+
 async fn system_daemon_task(
     mut caller: Caller<'_, Host>,
     mut messages: Receiver<SimulatorMessage>,
@@ -87,24 +361,63 @@ async fn system_daemon_task(
 
     let host = caller.data().clone();
 
-    let mut competition_task = {
-        let mut pool = caller.tasks_lock().await;
-        let init_options = TaskOptions::new_global(&mut pool, &host, "initialize")?
-            .name("User Initialization (PROS)");
-        pool.spawn(init_options, &host.module(), &host.interface())
-            .await?
-    };
-
-    let mut delay = interval(Duration::from_millis(2));
+    if host.options().test_pattern.is_some() {
+        return test_daemon_task(caller, messages).await;
+    }
+    if host.abi() == ProgramAbi::Vexide {
+        return vexide_daemon_task(caller, messages).await;
+    }
 
-    // wait for initialize to finish
-    while competition_task.lock().await.state() != TaskState::Finished {
-        do_background_operations(&mut caller, &mut messages).await?;
-        sleep(Duration::from_millis(2)).await;
+    let options = host.options();
+    let mut telemetry = TelemetryTimer::new(
+        options.telemetry_rate,
+        options.channel_stats_rate,
+        options.frame_rate,
+        options.task_stats,
+    );
+    let mut automation = MatchAutomation::new(options.match_timing);
+    let mut watches = Watches::default();
+    let mut scoreboard = Scoreboard::new(options.scoring_zones.clone());
+    let mut field = Field::new(
+        options.game_objects.clone(),
+        options.mechanisms.clone(),
+        options.robot_radius.unwrap_or(DEFAULT_ROBOT_RADIUS),
+    );
+
+    // run each initialization function to completion before starting the next
+    let mut competition_task = None;
+    for (entrypoint, name) in initialization_entrypoints(&host) {
+        let task = spawn_entrypoint(&mut caller, &host, entrypoint, name).await?;
+        while task.lock().await.state() != TaskState::Finished {
+            do_background_operations(
+                &mut caller,
+                &mut messages,
+                &mut telemetry,
+                &mut automation,
+                &mut watches,
+                &mut scoreboard,
+                &mut field,
+            )
+            .await?;
+            sleep(Duration::from_millis(2)).await;
+        }
+        competition_task = Some(task);
     }
+    let mut competition_task = competition_task.expect("initialize always runs");
+
+    automation.start();
 
     loop {
-        do_background_operations(&mut caller, &mut messages).await?;
+        do_background_operations(
+            &mut caller,
+            &mut messages,
+            &mut telemetry,
+            &mut automation,
+            &mut watches,
+            &mut scoreboard,
+            &mut field,
+        )
+        .await?;
 
         let new_status = *caller.competition_phase_lock().await;
 
@@ -130,19 +443,155 @@ async fn system_daemon_task(
                     UserTask::Opcontrol
                 };
 
-            let task = competition_task.lock().await;
-            if task.state() == TaskState::Ready {
-                let id = task.id();
+            // the previous phase's task is stopped even if it's waiting on something, and its
+            // lock has to be released first because deleting it locks it again
+            let (id, task_state) = {
+                let task = competition_task.lock().await;
+                (task.id(), task.state())
+            };
+            if matches!(task_state, TaskState::Ready | TaskState::Blocked) {
                 let mut tasks = caller.tasks_lock().await;
                 tasks.delete_task(id).await;
             }
-            drop(task);
 
             competition_task = spawn_user_code(&mut caller, &host, state).await?;
         }
 
-        delay.tick().await;
+        sleep(Duration::from_millis(2)).await;
+    }
+}
+
+/// Runs a vexide program, which handles competition phases itself, until it returns.
+async fn vexide_daemon_task(
+    mut caller: Caller<'_, Host>,
+    mut messages: Receiver<SimulatorMessage>,
+) -> anyhow::Result<()> {
+    let host = caller.data().clone();
+    let options = host.options();
+    let mut telemetry = TelemetryTimer::new(
+        options.telemetry_rate,
+        options.channel_stats_rate,
+        options.frame_rate,
+        options.task_stats,
+    );
+    let mut automation = MatchAutomation::new(options.match_timing);
+    let mut watches = Watches::default();
+    let mut scoreboard = Scoreboard::new(options.scoring_zones.clone());
+    let mut field = Field::new(
+        options.game_objects.clone(),
+        options.mechanisms.clone(),
+        options.robot_radius.unwrap_or(DEFAULT_ROBOT_RADIUS),
+    );
+
+    let main_task = {
+        let mut pool = caller.tasks_lock().await;
+        let main_options =
+            TaskOptions::new_global(&mut pool, &host, "_start")?.name("User Program (vexide)");
+        pool.spawn(main_options, &host.module(), &host.interface())
+            .await?
+    };
+    automation.start();
+
+    while main_task.lock().await.state() != TaskState::Finished {
+        do_background_operations(
+            &mut caller,
+            &mut messages,
+            &mut telemetry,
+            &mut automation,
+            &mut watches,
+            &mut scoreboard,
+            &mut field,
+        )
+        .await?;
+        sleep(Duration::from_millis(2)).await;
+    }
+
+    Ok(())
+}
+
+/// Runs the robot code's tests one at a time instead of its entrypoints, then stops the
+/// simulation.
+async fn test_daemon_task(
+    mut caller: Caller<'_, Host>,
+    mut messages: Receiver<SimulatorMessage>,
+) -> anyhow::Result<()> {
+    let host = caller.data().clone();
+    let options = host.options();
+    let mut telemetry = TelemetryTimer::new(
+        options.telemetry_rate,
+        options.channel_stats_rate,
+        options.frame_rate,
+        options.task_stats,
+    );
+    let mut automation = MatchAutomation::new(options.match_timing);
+    let mut watches = Watches::default();
+    let mut scoreboard = Scoreboard::new(options.scoring_zones.clone());
+    let mut field = Field::new(
+        options.game_objects.clone(),
+        options.mechanisms.clone(),
+        options.robot_radius.unwrap_or(DEFAULT_ROBOT_RADIUS),
+    );
+
+    let pattern = options.test_pattern.as_deref().unwrap_or_default();
+    let names = test_names(&host.module(), pattern);
+    if names.is_empty() {
+        caller.interface().send(SimulatorEvent::Warning(format!(
+            "Robot code doesn't export any tests matching `{pattern}`"
+        )));
+    }
+
+    let mut failed = 0;
+    for name in &names {
+        caller
+            .interface()
+            .send(SimulatorEvent::TestStarted { name: name.clone() });
+        let failed_assertions = caller.tasks_lock().await.failed_assertions();
+        let started = caller.millis();
+        let task = spawn_entrypoint(&mut caller, &host, name, name).await?;
+        while !matches!(
+            task.lock().await.state(),
+            TaskState::Finished | TaskState::Deleted
+        ) {
+            do_background_operations(
+                &mut caller,
+                &mut messages,
+                &mut telemetry,
+                &mut automation,
+                &mut watches,
+                &mut scoreboard,
+                &mut field,
+            )
+            .await?;
+            sleep(Duration::from_millis(2)).await;
+        }
+
+        let error = task.lock().await.error().map(String::from);
+        let failed_assertions = caller.tasks_lock().await.failed_assertions() - failed_assertions;
+        let passed = error.is_none() && failed_assertions == 0;
+        if !passed {
+            failed += 1;
+        }
+        caller
+            .interface()
+            .send(SimulatorEvent::TestFinished(TestResult {
+                name: name.clone(),
+                passed,
+                failed_assertions,
+                error,
+                millis: caller.millis().wrapping_sub(started),
+            }));
     }
+
+    // stops the tasks tests left running too
+    let reason = match failed {
+        0 => StopReason::Finished,
+        failed => StopReason::TestsFailed {
+            failed,
+            total: names.len() as u32,
+        },
+    };
+    caller.tasks_lock().await.start_shutdown(reason);
+    Ok(())
 }
 
 pub async fn system_daemon_initialize(
@@ -156,9 +605,11 @@ pub async fn system_daemon_initialize(
     })?
     .name("PROS System Daemon");
 
-    tasks
+    let daemon = tasks
         .spawn(daemon, &host.module(), &host.interface())
         .await?;
+    let daemon_id = daemon.lock().await.id();
+    tasks.set_daemon(daemon_id);
 
     Ok(())
 }
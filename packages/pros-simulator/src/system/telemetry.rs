@@ -0,0 +1,206 @@
+//! Periodic snapshots of the robot's state, the event channel and CPU usage, frame markers and
+//! the brain's screen header.
+//! See [`SimulatorOptions::telemetry`](crate::SimulatorOptions::telemetry),
+//! [`SimulatorOptions::channel_stats`](crate::SimulatorOptions::channel_stats),
+//! [`SimulatorOptions::task_stats`](crate::SimulatorOptions::task_stats) and
+//! [`SimulatorOptions::frame_markers`](crate::SimulatorOptions::frame_markers).
+
+use std::time::{Duration, Instant};
+
+use pros_simulator_interface::{
+    BrainHeader, ControllerId, EventRates, SimulatorEvent, TaskStats, Telemetry,
+};
+
+use crate::host::HostCtx;
+
+/// Decides when something that happens a number of times per second is next due.
+pub(crate) struct Periodic {
+    /// The time between occurrences, or `None` if disabled.
+    period: Option<Duration>,
+    next: Instant,
+}
+
+impl Periodic {
+    pub(crate) fn new(hz: Option<u32>) -> Self {
+        Self {
+            period: hz.map(|hz| Duration::from_secs(1) / hz.max(1)),
+            next: Instant::now(),
+        }
+    }
+
+    /// Whether it's due, in which case the next one is scheduled.
+    pub(crate) fn due(&mut self) -> bool {
+        let Some(period) = self.period else {
+            return false;
+        };
+        let now = Instant::now();
+        if now < self.next {
+            return false;
+        }
+        // skip occurrences that were missed instead of catching up on them all at once
+        while self.next <= now {
+            self.next += period;
+        }
+        true
+    }
+}
+
+/// Numbers frames by the simulation's clock rather than the wall clock, so that frame `n` is
+/// always the same time into the simulation.
+struct Frames {
+    /// The length of a frame, or `None` if disabled.
+    period: Option<Duration>,
+    /// The last frame a marker was sent for. This is kept when the rate changes, so numbers
+    /// never go back.
+    last: Option<u64>,
+}
+
+impl Frames {
+    /// The frame that's started since the last one, if any.
+    fn due(&mut self, host: &impl HostCtx) -> Option<u64> {
+        let period = self.period?;
+        let frame = (host.elapsed().as_nanos() / period.as_nanos()) as u64;
+        if self.last.is_some_and(|last| last >= frame) {
+            return None;
+        }
+        self.last = Some(frame);
+        Some(frame)
+    }
+}
+
+/// Measures how busy robot code kept the scheduler over each second of simulated time.
+#[derive(Default)]
+struct CpuUsage {
+    /// The simulation's clock when the current second's measurement started.
+    started: Duration,
+    /// The scheduler's busy time when the current second's measurement started.
+    busy: Duration,
+}
+
+impl CpuUsage {
+    /// The stats for the second that's just ended, if one has.
+    async fn due(&mut self, host: &(impl HostCtx + Sync)) -> Option<TaskStats> {
+        let elapsed = host.elapsed();
+        if elapsed.as_secs() <= self.started.as_secs() {
+            return None;
+        }
+        let tasks = host.tasks_lock().await;
+        let busy_time = tasks.busy_time();
+        let window = elapsed - self.started;
+        // tasks running in parallel in threaded mode can be busy for longer than the window
+        let busy = (busy_time - self.busy).min(window);
+        *self = Self {
+            started: elapsed,
+            busy: busy_time,
+        };
+        Some(TaskStats {
+            millis: host.millis(),
+            task_count: tasks.task_count() as u32,
+            busy_micros: busy.as_micros() as u64,
+            idle_micros: (window.as_micros() - busy.as_micros()) as u64,
+            cpu_usage: (busy.as_secs_f64() / window.as_secs_f64() * 100.0).round() as u8,
+        })
+    }
+}
+
+/// The brain's screen header as it's drawn while the program runs.
+pub(crate) fn brain_header(host: &impl HostCtx, running: bool) -> BrainHeader {
+    BrainHeader {
+        running,
+        seconds: host.elapsed().as_secs() as u32,
+        battery: 100,
+    }
+}
+
+/// Decides when the system daemon should send the next telemetry snapshot, channel stats, task
+/// stats, frame marker and brain header.
+pub struct TelemetryTimer {
+    telemetry: Periodic,
+    channel_stats: Periodic,
+    frames: Frames,
+    /// `None` if task stats are disabled.
+    cpu_usage: Option<CpuUsage>,
+    /// The second the brain header's timer showed when it was last sent.
+    header_seconds: Option<u32>,
+}
+
+impl TelemetryTimer {
+    pub fn new(
+        telemetry_hz: Option<u32>,
+        channel_stats_hz: Option<u32>,
+        frame_hz: Option<u32>,
+        task_stats: bool,
+    ) -> Self {
+        Self {
+            telemetry: Periodic::new(telemetry_hz),
+            channel_stats: Periodic::new(channel_stats_hz),
+            frames: Frames {
+                period: frame_hz.map(|hz| Duration::from_secs(1) / hz.max(1)),
+                last: None,
+            },
+            cpu_usage: task_stats.then(CpuUsage::default),
+            // the first header is sent when the program starts
+            header_seconds: Some(0),
+        }
+    }
+
+    /// Changes how many snapshots are sent per second, disabling the ones whose rate is `None`.
+    /// The next snapshots are sent right away. Task stats and the brain header are always sent
+    /// once a second, so they're left as they are.
+    pub fn set_rates(&mut self, rates: &EventRates) {
+        let last_frame = self.frames.last;
+        let cpu_usage = self.cpu_usage.take();
+        let header_seconds = self.header_seconds;
+        *self = Self::new(rates.telemetry, rates.channel_stats, rates.frames, false);
+        self.frames.last = last_frame;
+        self.cpu_usage = cpu_usage;
+        self.header_seconds = header_seconds;
+    }
+
+    /// Sends any snapshots that are due.
+    pub async fn tick(&mut self, host: &(impl HostCtx + Sync)) {
+        let header = brain_header(host, true);
+        if self.header_seconds != Some(header.seconds) {
+            self.header_seconds = Some(header.seconds);
+            host.interface().send(SimulatorEvent::BrainHeader(header));
+        }
+        if let Some(frame) = self.frames.due(host) {
+            let period = self.frames.period.unwrap();
+            let elapsed = (period * frame as u32).as_millis() as u32;
+            host.interface().send(SimulatorEvent::Frame {
+                frame,
+                millis: host.options().start_millis.wrapping_add(elapsed),
+            });
+        }
+        if let Some(cpu_usage) = &mut self.cpu_usage {
+            if let Some(stats) = cpu_usage.due(host).await {
+                host.interface().send(SimulatorEvent::TaskStats(stats));
+            }
+        }
+        if self.channel_stats.due() {
+            let stats = host.interface().stats();
+            host.interface().send(SimulatorEvent::ChannelStats(stats));
+        }
+        if !self.telemetry.due() {
+            return;
+        }
+
+        let (master, partner) = {
+            let controllers = host.controllers_lock().await;
+            (
+                controllers.state(ControllerId::Master),
+                controllers.state(ControllerId::Partner),
+            )
+        };
+        let telemetry = Telemetry {
+            millis: host.millis(),
+            competition_phase: *host.competition_phase_lock().await,
+            master,
+            partner,
+            task_count: host.tasks_lock().await.task_count() as u32,
+            pose: *host.pose_lock().await,
+            motors: host.smart_ports_lock().await.motor_states(host.ticks()),
+        };
+        host.interface().send(SimulatorEvent::Telemetry(telemetry));
+    }
+}
@@ -0,0 +1,42 @@
+//! Finding the tests robot code exports. See
+//! [`SimulatorOptions::run_tests`](crate::SimulatorOptions::run_tests).
+
+use wasmtime::{ExternType, Module};
+
+/// Whether `name` matches `pattern`, where `*` matches any number of characters and everything
+/// else matches itself.
+pub fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts = parts.collect::<Vec<_>>();
+    let Some(last) = parts.pop() else {
+        // no wildcards
+        return rest.is_empty();
+    };
+    for part in parts {
+        let Some(index) = rest.find(part) else {
+            return false;
+        };
+        rest = &rest[index + part.len()..];
+    }
+    rest.ends_with(last)
+}
+
+/// The functions exported by the robot code that match `pattern` and can be run as tests, which
+/// means they take no arguments and return nothing, in alphabetical order.
+pub fn test_names(module: &Module, pattern: &str) -> Vec<String> {
+    let mut names = module
+        .exports()
+        .filter(|export| match export.ty() {
+            ExternType::Func(ty) => ty.params().len() == 0 && ty.results().len() == 0,
+            _ => false,
+        })
+        .map(|export| export.name().to_string())
+        .filter(|name| matches_pattern(pattern, name))
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
@@ -0,0 +1,115 @@
+//! Reading and writing robot code variables for frontends. See
+//! [`SimulatorMessage::ReadMemory`](pros_simulator_interface::SimulatorMessage::ReadMemory),
+//! [`SimulatorMessage::WriteMemory`](pros_simulator_interface::SimulatorMessage::WriteMemory)
+//! and [`SimulatorMessage::Watch`](pros_simulator_interface::SimulatorMessage::Watch).
+
+use pros_simulator_interface::{MemoryLocation, SimulatorEvent, ValueType};
+use wasmtime::{Caller, Val};
+
+use super::telemetry::Periodic;
+use crate::host::{memory::SharedMemoryExt, Host, HostCtx};
+
+/// Resolves a location in robot code memory to an address, or a description of what went
+/// wrong.
+async fn resolve(caller: &mut Caller<'_, Host>, location: &MemoryLocation) -> Result<u32, String> {
+    match location {
+        MemoryLocation::Address(address) => Ok(*address),
+        MemoryLocation::Symbol(symbol) => {
+            let instance = caller.current_task().await.lock().await.instance;
+            let Some(global) = instance.get_global(&mut *caller, symbol) else {
+                return Err(format!(
+                    "the robot code doesn't export a global named `{symbol}`"
+                ));
+            };
+            match global.get(&mut *caller) {
+                Val::I32(address) => Ok(address as u32),
+                _ => Err(format!("`{symbol}` doesn't hold a 32-bit address")),
+            }
+        }
+    }
+}
+
+/// Resolves a location in robot code memory and reads `len` bytes from it, returning the
+/// address and the bytes or a description of what went wrong.
+pub async fn read_memory(
+    caller: &mut Caller<'_, Host>,
+    location: &MemoryLocation,
+    len: u32,
+) -> Result<(u32, Vec<u8>), String> {
+    let address = resolve(caller, location).await?;
+    let bytes = caller
+        .memory()
+        .read_relaxed(address as usize, len as usize)
+        .map_err(|err| err.to_string())?;
+    Ok((address, bytes))
+}
+
+/// Resolves a location in robot code memory and writes `bytes` to it, returning a description
+/// of what went wrong if it couldn't. Nothing is written unless all of `bytes` fit.
+pub async fn write_memory(
+    caller: &mut Caller<'_, Host>,
+    location: &MemoryLocation,
+    bytes: &[u8],
+) -> Result<(), String> {
+    let address = resolve(caller, location).await?;
+    caller
+        .memory()
+        .write_relaxed(address as usize, bytes)
+        .map_err(|err| err.to_string())
+}
+
+struct Watch {
+    location: MemoryLocation,
+    value_type: ValueType,
+    timer: Periodic,
+}
+
+/// The variables frontends have asked to watch.
+#[derive(Default)]
+pub struct Watches {
+    watches: Vec<Watch>,
+}
+
+impl Watches {
+    /// Starts watching a variable, replacing any watch on the same location. A rate of 0 stops
+    /// watching it.
+    pub fn watch(&mut self, location: MemoryLocation, value_type: ValueType, rate: u32) {
+        self.watches.retain(|watch| watch.location != location);
+        if rate > 0 {
+            self.watches.push(Watch {
+                location,
+                value_type,
+                timer: Periodic::new(Some(rate)),
+            });
+        }
+    }
+
+    /// Sends the values of the variables that are due. Variables that can't be read are warned
+    /// about once and no longer watched.
+    pub async fn tick(&mut self, caller: &mut Caller<'_, Host>) {
+        let mut unreadable = Vec::new();
+        for watch in &mut self.watches {
+            if !watch.timer.due() {
+                continue;
+            }
+            let len = watch.value_type.size() as u32;
+            let event = match read_memory(caller, &watch.location, len).await {
+                Ok((_, bytes)) => SimulatorEvent::WatchValue {
+                    location: watch.location.clone(),
+                    millis: caller.millis(),
+                    value: watch.value_type.decode(&bytes),
+                },
+                Err(err) => {
+                    unreadable.push(watch.location.clone());
+                    SimulatorEvent::Warning(format!(
+                        "Stopped watching {}, because it couldn't be read: {err}",
+                        watch.location
+                    ))
+                }
+            };
+            caller.interface().send(event);
+        }
+        self.watches
+            .retain(|watch| !unreadable.contains(&watch.location));
+    }
+}
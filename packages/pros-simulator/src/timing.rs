@@ -0,0 +1,199 @@
+//! Checking when things happen in a simulation, for tests of robot code that should fail when
+//! its timing regresses. See [`Timeline`].
+
+use std::{
+    collections::BTreeMap,
+    path::Path,
+    sync::{mpsc::Receiver, Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Result;
+use futures::executor::block_on;
+use pros_simulator_interface::{SimulatorEvent, SimulatorMessage};
+
+use crate::{
+    host::{Host, HostCtx, TICK_PERIOD_MS},
+    Simulation, SimulationOutcome, SimulatorOptions, StepResponse, StepResult,
+};
+
+/// A record of a simulation's events and of its tasks' loops, timed by the simulation's clock
+/// rather than the wall clock, with assertions about when things happened.
+///
+/// Times are in milliseconds since robot code started. A task's loop period is the time between
+/// the starts of its consecutive `delay`, `task_delay` or `task_delay_until` calls, so it
+/// includes the time spent working between them.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn run() -> anyhow::Result<()> {
+/// use std::{path::Path, sync::mpsc, time::Duration};
+///
+/// use pros_simulator::{SimulatorOptions, Timeline};
+/// use pros_simulator_interface::SimulatorEvent;
+///
+/// let (_messages, rx) = mpsc::channel();
+/// let timeline = Timeline::record(Path::new("robot.wasm"), SimulatorOptions::new(), rx)?;
+/// timeline.expect_event_within(500, |event| matches!(event, SimulatorEvent::LcdInitialized));
+/// timeline.assert_loop_period(
+///     "User Operator Control (PROS)",
+///     Duration::from_millis(10),
+///     Duration::from_millis(2),
+/// );
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Timeline {
+    events: Vec<TimedEvent>,
+    /// The name of each task that delayed and when each of its delays started, by task ID.
+    delays: BTreeMap<u32, (String, Vec<u32>)>,
+    outcome: SimulationOutcome,
+}
+
+/// An event recorded in a [`Timeline`].
+#[derive(Debug, Clone)]
+pub struct TimedEvent {
+    /// When the event was sent, in milliseconds since robot code started.
+    pub millis: u32,
+    pub event: SimulatorEvent,
+}
+
+impl Timeline {
+    /// Simulates the robot code at `robot_code` until it stops, recording its events and loops.
+    /// The simulation is [stepped](Simulation) rather than awaited, so this blocks and can't use
+    /// the [threaded](SimulatorOptions::threaded) scheduler. Returns an error if the robot code
+    /// couldn't be loaded.
+    pub fn record(
+        robot_code: &Path,
+        options: SimulatorOptions,
+        messages: Receiver<SimulatorMessage>,
+    ) -> Result<Self> {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let mut simulation = Simulation::new(
+            robot_code,
+            options,
+            {
+                let received = received.clone();
+                move |event| received.lock().unwrap().push(event)
+            },
+            messages,
+        )?;
+
+        let mut events = Vec::new();
+        let mut delays = BTreeMap::new();
+        let outcome = loop {
+            let result = simulation.poll();
+            let millis = elapsed_millis(simulation.host());
+            let received = received.lock().unwrap().drain(..).collect::<Vec<_>>();
+            events.extend(
+                received
+                    .into_iter()
+                    .map(|event| TimedEvent { millis, event }),
+            );
+            if let StepResult::Stopped(outcome) = result {
+                break outcome;
+            }
+            block_on(record_delays(simulation.host(), &mut delays));
+        };
+        Ok(Self {
+            events,
+            delays,
+            outcome,
+        })
+    }
+
+    /// Every event the simulation sent, in order.
+    pub fn events(&self) -> &[TimedEvent] {
+        &self.events
+    }
+
+    /// How the robot code stopped.
+    pub fn outcome(&self) -> &SimulationOutcome {
+        &self.outcome
+    }
+
+    /// The period of each iteration of the loops of the tasks named `task`, in milliseconds, in
+    /// the order they ran. Tasks that share a name have their periods listed one after the
+    /// other.
+    pub fn loop_periods(&self, task: &str) -> Vec<u32> {
+        self.delays
+            .values()
+            .filter(|(name, _)| name == task)
+            .flat_map(|(_, delays)| delays.windows(2).map(|pair| pair[1] - pair[0]))
+            .collect()
+    }
+
+    /// How the motor on `port` (1 to 21) responded to the first change in its target velocity.
+    /// See [`StepResponse::from_motor_updates`].
+    pub fn step_response(&self, port: u8) -> Option<StepResponse> {
+        StepResponse::from_motor_updates(&self.events, port)
+    }
+
+    /// Returns the first event that `predicate` matches, panicking if none was sent in the
+    /// first `millis` milliseconds.
+    #[track_caller]
+    pub fn expect_event_within(
+        &self,
+        millis: u32,
+        predicate: impl Fn(&SimulatorEvent) -> bool,
+    ) -> &TimedEvent {
+        match self.events.iter().find(|event| predicate(&event.event)) {
+            Some(event) if event.millis <= millis => event,
+            Some(event) => panic!(
+                "expected the event within {millis}ms, but it was sent at {}ms: {:?}",
+                event.millis, event.event
+            ),
+            None => panic!("expected the event within {millis}ms, but it was never sent"),
+        }
+    }
+
+    /// Panics unless every iteration of the loops of the tasks named `task` took `period`, give
+    /// or take `tolerance`. Loops have to run at least once, and times are rounded to whole
+    /// ticks.
+    #[track_caller]
+    pub fn assert_loop_period(&self, task: &str, period: Duration, tolerance: Duration) {
+        let periods = self.loop_periods(task);
+        assert!(
+            !periods.is_empty(),
+            "expected task `{task}` to loop every {period:?}, but it didn't loop"
+        );
+        let min = period.saturating_sub(tolerance);
+        let max = period + tolerance;
+        for (iteration, &millis) in periods.iter().enumerate() {
+            let actual = Duration::from_millis(millis.into());
+            assert!(
+                (min..=max).contains(&actual),
+                "expected task `{task}` to loop every {period:?} ± {tolerance:?}, but iteration \
+                 {iteration} took {actual:?}"
+            );
+        }
+    }
+}
+
+/// Notes the delays tasks have started since the last step in `delays`, which holds the name of
+/// each task that's delayed and when its delays started, by task ID.
+async fn record_delays(host: &Host, delays: &mut BTreeMap<u32, (String, Vec<u32>)>) {
+    let tasks = host.tasks_lock().await;
+    for info in tasks.task_list().await {
+        let Some(task) = tasks.by_id(info.id) else {
+            continue;
+        };
+        let Some(tick) = task.lock().await.delay_started() else {
+            continue;
+        };
+        let millis = (tick * TICK_PERIOD_MS) as u32;
+        let (_, starts) = delays
+            .entry(info.id)
+            .or_insert_with(|| (info.name, Vec::new()));
+        if starts.last() != Some(&millis) {
+            starts.push(millis);
+        }
+    }
+}
+
+/// Milliseconds since robot code started, by the simulation's clock.
+fn elapsed_millis(host: &Host) -> u32 {
+    (host.ticks() * TICK_PERIOD_MS) as u32
+}
@@ -0,0 +1,313 @@
+//! Mirrors every [`SimulatorEvent`] into a `tracing` event, for embedders that already collect
+//! tracing output (e.g. with OpenTelemetry) and want the simulator's events alongside it. See
+//! [`SimulatorOptions::trace_events`](crate::SimulatorOptions::trace_events).
+//!
+//! Events are recorded with the `pros_simulator::events` target and an `event` field holding the
+//! event's name, plus a field for each of its values. Faults are errors, warnings are warnings,
+//! and events a simulation sends many times a second, like motor updates and telemetry, are
+//! traces, so they can be filtered out by level. Robot code's own `sim_log` messages keep the
+//! level they were logged at.
+
+use pros_simulator_interface::{LogLevel, SimulatorEvent};
+
+macro_rules! emit {
+    ($level:ident, $name:literal $(, $($fields:tt)*)?) => {
+        tracing::event!(
+            target: "pros_simulator::events",
+            tracing::Level::$level,
+            event = $name
+            $(, $($fields)*)?
+        )
+    };
+}
+
+/// Records `event` as a `tracing` event.
+pub(crate) fn trace_event(event: &SimulatorEvent) {
+    match event {
+        SimulatorEvent::Warning(message) => emit!(WARN, "Warning", message = %message),
+        SimulatorEvent::ConsoleMessage(output) => {
+            emit!(INFO, "ConsoleMessage", message = %output.text.trim_end(), task_id = ?output.task_id)
+        }
+        SimulatorEvent::RobotCodeLoading => emit!(INFO, "RobotCodeLoading"),
+        SimulatorEvent::ProgramInfo(info) => emit!(INFO, "ProgramInfo", info = ?info),
+        SimulatorEvent::ApiCompatibility(compatibility) => {
+            emit!(INFO, "ApiCompatibility", missing = ?compatibility.missing)
+        }
+        SimulatorEvent::RobotCodeStarting => emit!(INFO, "RobotCodeStarting"),
+        SimulatorEvent::RobotCodeFinished => emit!(INFO, "RobotCodeFinished"),
+        SimulatorEvent::RobotCodeError {
+            message,
+            task_id,
+            task_name,
+            errno,
+            trap,
+            ..
+        } => emit!(
+            ERROR,
+            "RobotCodeError",
+            message = %message,
+            task_id,
+            task_name = %task_name,
+            errno,
+            trap = trap.as_deref(),
+        ),
+        SimulatorEvent::UnimplementedCall { name, .. } => {
+            emit!(WARN, "UnimplementedCall", name = %name)
+        }
+        SimulatorEvent::LcdInitialized => emit!(DEBUG, "LcdInitialized"),
+        SimulatorEvent::LcdUpdated(lines) => emit!(DEBUG, "LcdUpdated", lines = ?lines),
+        SimulatorEvent::LcdColorsUpdated {
+            foreground,
+            background,
+        } => emit!(DEBUG, "LcdColorsUpdated", foreground, background),
+        SimulatorEvent::LcdShutdown => emit!(DEBUG, "LcdShutdown"),
+        SimulatorEvent::ControllerDisconnected(id) => {
+            emit!(INFO, "ControllerDisconnected", controller = ?id)
+        }
+        SimulatorEvent::MotorUpdated {
+            port,
+            millivolts,
+            millis,
+            target_velocity,
+            actual_velocity,
+            position,
+            current,
+        } => emit!(
+            TRACE,
+            "MotorUpdated",
+            port,
+            millivolts,
+            millis,
+            target_velocity,
+            actual_velocity,
+            position,
+            current
+        ),
+        SimulatorEvent::CompetitionTimer {
+            phase,
+            remaining_ms,
+        } => emit!(DEBUG, "CompetitionTimer", phase = ?phase, remaining_ms),
+        SimulatorEvent::Telemetry(telemetry) => {
+            emit!(TRACE, "Telemetry", telemetry = ?telemetry)
+        }
+        SimulatorEvent::ChannelStats(stats) => emit!(
+            TRACE,
+            "ChannelStats",
+            sent = stats.sent,
+            queued = stats.queued,
+            dropped = stats.dropped,
+            coalesced = stats.coalesced,
+        ),
+        SimulatorEvent::ApiCoverage(coverage) => {
+            let used = coverage.calls.values().filter(|count| **count > 0).count();
+            emit!(INFO, "ApiCoverage", used, imported = coverage.calls.len())
+        }
+        SimulatorEvent::MemoryValue {
+            location,
+            address,
+            bytes,
+        } => emit!(DEBUG, "MemoryValue", location = ?location, address, bytes = ?bytes),
+        SimulatorEvent::WatchValue {
+            location,
+            millis,
+            value,
+        } => emit!(DEBUG, "WatchValue", location = ?location, millis, value = ?value),
+        SimulatorEvent::Breakpoint {
+            api,
+            args,
+            task_id,
+            task_name,
+            ..
+        } => emit!(
+            INFO,
+            "Breakpoint",
+            api = %api,
+            args = ?args,
+            task_id,
+            task_name = %task_name,
+        ),
+        SimulatorEvent::AssertionFailed {
+            message,
+            task_id,
+            task_name,
+            ..
+        } => emit!(
+            ERROR,
+            "AssertionFailed",
+            message = %message,
+            task_id,
+            task_name = %task_name,
+        ),
+        SimulatorEvent::TaskCreationFailed {
+            reason,
+            errno,
+            task_id,
+            task_name,
+        } => emit!(
+            WARN,
+            "TaskCreationFailed",
+            reason = %reason,
+            errno,
+            task_id,
+            task_name = %task_name,
+        ),
+        SimulatorEvent::TaskCrashed { task_id, task_name } => {
+            emit!(ERROR, "TaskCrashed", task_id, task_name = %task_name)
+        }
+        SimulatorEvent::DataAbort(screen) => emit!(ERROR, "DataAbort", screen = ?screen),
+        SimulatorEvent::InvalidArgument {
+            function,
+            argument,
+            value,
+            reason,
+            errno,
+            task_id,
+            task_name,
+        } => emit!(
+            WARN,
+            "InvalidArgument",
+            function = %function,
+            argument = %argument,
+            value,
+            reason = %reason,
+            errno,
+            task_id,
+            task_name = %task_name,
+        ),
+        SimulatorEvent::LcdSelectorUpdated(selector) => {
+            emit!(DEBUG, "LcdSelectorUpdated", selector = ?selector)
+        }
+        SimulatorEvent::ProgramSlots(slots) => emit!(INFO, "ProgramSlots", slots = ?slots),
+        SimulatorEvent::SlotSelected(slot) => emit!(INFO, "SlotSelected", slot),
+        SimulatorEvent::PoseSet(pose) => emit!(INFO, "PoseSet", pose = ?pose),
+        SimulatorEvent::Frame { frame, millis } => emit!(TRACE, "Frame", frame, millis),
+        SimulatorEvent::MutexCreated {
+            mutex_id,
+            task_id,
+            task_name,
+        } => emit!(
+            DEBUG,
+            "MutexCreated",
+            mutex_id,
+            task_id,
+            task_name = %task_name,
+        ),
+        SimulatorEvent::MutexContended {
+            mutex_id,
+            task_id,
+            task_name,
+            holder_id,
+            holder_name,
+        } => emit!(
+            DEBUG,
+            "MutexContended",
+            mutex_id,
+            task_id,
+            task_name = %task_name,
+            holder_id,
+            holder_name = %holder_name,
+        ),
+        SimulatorEvent::MutexHeldTooLong {
+            mutex_id,
+            task_id,
+            task_name,
+            held_millis,
+        } => emit!(
+            WARN,
+            "MutexHeldTooLong",
+            mutex_id,
+            task_id,
+            task_name = %task_name,
+            held_millis,
+        ),
+        SimulatorEvent::TaskStats(stats) => emit!(DEBUG, "TaskStats", stats = ?stats),
+        SimulatorEvent::TaskList(tasks) => emit!(DEBUG, "TaskList", tasks = ?tasks),
+        SimulatorEvent::ResourceLimitExceeded { limit, max } => {
+            emit!(ERROR, "ResourceLimitExceeded", limit = %limit, max)
+        }
+        SimulatorEvent::SessionStarted { session } => emit!(INFO, "SessionStarted", session),
+        SimulatorEvent::Log {
+            level,
+            message,
+            task_id,
+            task_name,
+        } => match level {
+            LogLevel::Error => {
+                emit!(ERROR, "Log", message = %message, task_id, task_name = %task_name)
+            }
+            LogLevel::Warn => {
+                emit!(WARN, "Log", message = %message, task_id, task_name = %task_name)
+            }
+            LogLevel::Info => {
+                emit!(INFO, "Log", message = %message, task_id, task_name = %task_name)
+            }
+            LogLevel::Debug => {
+                emit!(DEBUG, "Log", message = %message, task_id, task_name = %task_name)
+            }
+            LogLevel::Trace => {
+                emit!(TRACE, "Log", message = %message, task_id, task_name = %task_name)
+            }
+        },
+        SimulatorEvent::ScoreChanged {
+            zone,
+            points,
+            score,
+            millis,
+        } => emit!(INFO, "ScoreChanged", zone = %zone, points, score, millis),
+        SimulatorEvent::GameObjectUpdated(object) => emit!(
+            DEBUG,
+            "GameObjectUpdated",
+            name = %object.name,
+            kind = %object.kind,
+            x = object.x,
+            y = object.y,
+        ),
+        SimulatorEvent::MechanismUpdated(state) => emit!(
+            DEBUG,
+            "MechanismUpdated",
+            name = %state.name,
+            held = ?state.held,
+            height = state.height,
+        ),
+        SimulatorEvent::ScreenUpdated(region) => emit!(
+            TRACE,
+            "ScreenUpdated",
+            x = region.x,
+            y = region.y,
+            width = region.width,
+            height = region.height,
+        ),
+        SimulatorEvent::TestStarted { name } => emit!(INFO, "TestStarted", name = %name),
+        SimulatorEvent::TestFinished(result) if result.passed => {
+            emit!(INFO, "TestFinished", name = %result.name, millis = result.millis)
+        }
+        SimulatorEvent::TestFinished(result) => emit!(
+            ERROR,
+            "TestFinished",
+            name = %result.name,
+            failed_assertions = result.failed_assertions,
+            error = result.error.as_deref(),
+            millis = result.millis,
+        ),
+        SimulatorEvent::Custom { tag, bytes } => {
+            emit!(DEBUG, "Custom", tag = %tag, bytes = ?bytes)
+        }
+        SimulatorEvent::ControllerTextUpdated { controller, lines } => {
+            emit!(DEBUG, "ControllerTextUpdated", controller = ?controller, lines = ?lines)
+        }
+        SimulatorEvent::ControllerRumbled {
+            controller,
+            pattern,
+        } => emit!(DEBUG, "ControllerRumbled", controller = ?controller, pattern = %pattern),
+        SimulatorEvent::MotorGroupUpdated {
+            name,
+            millivolts,
+            motors,
+        } => emit!(TRACE, "MotorGroupUpdated", name = %name, millivolts, motors = ?motors),
+        SimulatorEvent::DrivetrainConfigured(drivetrain) => {
+            emit!(INFO, "DrivetrainConfigured", drivetrain = ?drivetrain)
+        }
+        // events added to the interface after this bridge
+        event => emit!(DEBUG, "Other", data = ?event),
+    }
+}
@@ -0,0 +1,4058 @@
+//! Runs a small robot program for each API area and checks the events it emits.
+
+mod common;
+
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use common::{
+    build_fixture, check_fixture, default_options, run_fixture, run_fixture_interactive,
+    run_fixture_interactive_with_options, run_fixture_with_options,
+};
+use futures::StreamExt;
+use indoc::indoc;
+use pros_simulator::{
+    interface::SimulatorInterface, stream::start_simulator, HostCtx, MatchTiming, OverflowPolicy,
+    Simulation, SimulatorConfig, SimulatorOptions, StartKind, StepResponse, StepResult, StopReason,
+    Sweep, TaskOptions, TaskPool, Timeline, Timeout, WarningKind,
+};
+use pros_simulator_interface::{
+    AnalogControllerState, BrainButton, BrainHeader, CallCondition, CompetitionPhase,
+    CompetitionSwitch, ConsoleOutput, ControllerId, ControllerState, DeviceType,
+    DigitalControllerState, DisplayGeometry, DriveType, DrivetrainConfig, EventRates, GameObject,
+    InputShaping, LcdSelectorRole, LogLevel, Mechanism, MechanismKind, MemoryLocation, MotorGroup,
+    Pose, ProgramAbi, ProgramInfo, ProsVersion, ResourceLimit, SchedulerInvariant, ScoringRule,
+    ScoringZone, SimulatorEvent, SimulatorMessage, TaskState, Telemetry, ValueType, WatchValue,
+    ZoneShape, SCREEN_HEIGHT, SCREEN_WIDTH,
+};
+
+fn opcontrol() -> SimulatorMessage {
+    SimulatorMessage::PhaseChange(CompetitionPhase {
+        autonomous: false,
+        enabled: true,
+        is_competition: false,
+    })
+}
+
+/// The A button pressed and the left joystick pushed up to 42.
+fn controller_state() -> ControllerState {
+    ControllerState {
+        digital: DigitalControllerState {
+            l1: false,
+            l2: false,
+            r1: false,
+            r2: false,
+            up: false,
+            down: false,
+            left: false,
+            right: false,
+            x: false,
+            b: false,
+            y: false,
+            a: true,
+        },
+        analog: AnalogControllerState {
+            left_x: 0,
+            left_y: 42,
+            right_x: 0,
+            right_y: 0,
+        },
+        competition_switch: None,
+    }
+}
+
+#[tokio::test]
+async fn lcd() {
+    let run = run_fixture("lcd", []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        run.outcome.reason
+    );
+    assert!(
+        matches!(
+            run.events[..],
+            [
+                SimulatorEvent::RobotCodeLoading,
+                SimulatorEvent::ProgramInfo(ProgramInfo {
+                    abi: ProgramAbi::ProsRs,
+                    ..
+                }),
+                SimulatorEvent::ApiCompatibility(_),
+                SimulatorEvent::RobotCodeStarting,
+                SimulatorEvent::BrainHeader(BrainHeader { running: true, .. }),
+                SimulatorEvent::LcdInitialized,
+                ..
+            ]
+        ),
+        "{:?}",
+        run.events
+    );
+
+    let updates = run
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::LcdUpdated(lines) => Some(lines),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(updates.len(), 5);
+    assert_eq!(updates[0][1].text, "Hello");
+    assert_eq!(updates[1][2].text, "World");
+    assert_eq!(updates[2][1].text, "");
+    assert_eq!(updates[2][2].text, "World");
+    assert_eq!(
+        updates[3][3].text,
+        "0123456789012345678901234567890123456789"
+    );
+    // wide characters take up two columns, so the one that would straddle the edge is dropped
+    assert_eq!(
+        updates[4][4].text,
+        "ロボット ステータス: 自律制御中。センサ"
+    );
+
+    // the LCD is drawn onto the screen too, with 24 pixel lines starting 8 pixels down
+    let mut screen = vec![[0u8; 3]; (SCREEN_WIDTH * SCREEN_HEIGHT) as usize];
+    for event in &run.events {
+        if let SimulatorEvent::ScreenUpdated(region) = event {
+            let rgb = region.rgb().unwrap();
+            for (index, pixel) in rgb.chunks(3).enumerate() {
+                let x = region.x + index as u32 % region.width;
+                let y = region.y + index as u32 / region.width;
+                screen[(y * SCREEN_WIDTH + x) as usize] = pixel.try_into().unwrap();
+            }
+        }
+    }
+    let line_pixels = |line: u32, color: [u8; 3]| {
+        let top = 8 + line * 24;
+        (top..top + 24)
+            .flat_map(|y| (0..SCREEN_WIDTH).map(move |x| (y * SCREEN_WIDTH + x) as usize))
+            .filter(|&index| screen[index] == color)
+            .count()
+    };
+    let (green, black) = ([0x5a, 0xbc, 0x03], [0, 0, 0]);
+    assert_eq!(line_pixels(0, green), 24 * SCREEN_WIDTH as usize);
+    // cleared
+    assert_eq!(line_pixels(1, black), 0);
+    assert!(line_pixels(2, black) > 0);
+    // every column is filled, so there's more text than in "World"
+    assert!(line_pixels(3, black) > line_pixels(2, black) * 4);
+    assert!(line_pixels(4, black) > 0);
+}
+
+#[tokio::test]
+async fn lcd_buttons() {
+    let run = run_fixture_interactive("lcd_buttons", vec![], |event| match event {
+        SimulatorEvent::ConsoleMessage(message) if message.text == "ready\n" => vec![
+            SimulatorMessage::LcdButtonsUpdate([true, false, false]),
+            SimulatorMessage::LcdButtonsUpdate([false, false, false]),
+        ],
+        _ => vec![],
+    })
+    .await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(1)),
+        "{:?}",
+        run.outcome.reason
+    );
+}
+
+#[tokio::test]
+async fn lcd_callbacks() {
+    let run = run_fixture_interactive("lcd_callbacks", vec![], |event| match event {
+        SimulatorEvent::ConsoleMessage(message) if message.text == "ready\n" => vec![
+            SimulatorMessage::LcdButtonsUpdate([true, false, false]),
+            SimulatorMessage::LcdButtonsUpdate([false, false, false]),
+        ],
+        _ => vec![],
+    })
+    .await;
+    // only the valid and NULL callbacks were registered, the others failed with EINVAL, and
+    // clearing the callback stopped it from being called
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(364)),
+        "{:?}",
+        run.outcome.reason
+    );
+}
+
+#[tokio::test]
+async fn tasks() {
+    let run = run_fixture("tasks", []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        run.outcome.reason
+    );
+    assert_eq!(run.console(), "from child\nfrom parent\n");
+
+    // each message is attributed to the task that printed it
+    let output = run
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::ConsoleMessage(output) => Some(format!(
+                "[{} {}]{}",
+                output.task_id.unwrap(),
+                output.task_name.as_deref().unwrap(),
+                output.text
+            )),
+            _ => None,
+        })
+        .collect::<String>();
+    assert_eq!(
+        output,
+        "[3 task 3]from child\n[2 User Initialization (PROS)]from parent\n"
+    );
+}
+
+#[tokio::test]
+async fn subscribers() {
+    let robot_code = build_fixture("tasks");
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let console = Arc::new(Mutex::new(Vec::new()));
+    let mirrored = Arc::new(Mutex::new(Vec::new()));
+    let interface = SimulatorInterface::from({
+        let events = events.clone();
+        move |event| events.lock().unwrap().push(event)
+    })
+    .with_subscriber(
+        |event| matches!(event, SimulatorEvent::ConsoleMessage(_)),
+        {
+            let console = console.clone();
+            move |event| console.lock().unwrap().push(event)
+        },
+    )
+    .with_subscriber(|_| true, {
+        let mirrored = mirrored.clone();
+        move |event| mirrored.lock().unwrap().push(event)
+    });
+    let (_tx, rx) = mpsc::channel();
+    let outcome = pros_simulator::simulate(&robot_code, default_options(), interface, rx)
+        .await
+        .unwrap();
+    _ = std::fs::remove_file(robot_code);
+    assert!(outcome.is_success(), "{:?}", outcome.reason);
+
+    let events = events.lock().unwrap();
+    assert_eq!(*mirrored.lock().unwrap(), *events);
+    assert_eq!(
+        *console.lock().unwrap(),
+        [
+            SimulatorEvent::ConsoleMessage(ConsoleOutput {
+                text: "from child\n".into(),
+                task_id: Some(3),
+                task_name: Some("task 3".into()),
+            }),
+            SimulatorEvent::ConsoleMessage(ConsoleOutput {
+                text: "from parent\n".into(),
+                task_id: Some(2),
+                task_name: Some("User Initialization (PROS)".into()),
+            }),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn mutexes() {
+    let run = run_fixture("mutexes", []).await;
+    // the child task can't take the mutex while it's held, but the parent can retake it after
+    // giving it back
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(1)),
+        "{:?}",
+        run.outcome.reason
+    );
+}
+
+#[tokio::test]
+async fn invalid_mutexes() {
+    let run = run_fixture("invalid_mutexes", []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0b1111)),
+        "{:?}",
+        run.outcome.reason
+    );
+    // giving back a mutex that isn't held fails without being reported as an invalid argument
+    let invalid = run
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::InvalidArgument {
+                function,
+                argument,
+                value,
+                errno,
+                ..
+            } => Some((function.as_str(), argument.as_str(), *value, *errno)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        invalid,
+        [
+            ("mutex_give", "mutex_id", 12345, pros_sys::EINVAL),
+            ("mutex_take", "mutex_id", 12345, pros_sys::EINVAL),
+            ("mutex_delete", "mutex_id", 12345, pros_sys::EINVAL),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn thread_local_storage() {
+    let run = run_fixture("thread_local_storage", []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0b111)),
+        "{:?}",
+        run.outcome.reason
+    );
+}
+
+#[tokio::test]
+async fn mutex_events() {
+    let run = run_fixture("mutexes", []).await;
+    assert!(!run.events.iter().any(|event| matches!(
+        event,
+        SimulatorEvent::MutexCreated { .. } | SimulatorEvent::MutexContended { .. }
+    )));
+
+    let options = default_options().mutex_events(Duration::from_millis(2));
+    let run = run_fixture_with_options("mutexes", options, []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(1)),
+        "{:?}",
+        run.outcome.reason
+    );
+    let created = run
+        .events
+        .iter()
+        .filter(|event| matches!(event, SimulatorEvent::MutexCreated { mutex_id: 0, .. }))
+        .count();
+    assert_eq!(created, 1);
+    // the child task waits for the mutex the parent is holding
+    let contended = run
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::MutexContended {
+                mutex_id: 0,
+                task_id,
+                holder_id,
+                holder_name,
+                ..
+            } => Some((task_id != holder_id, holder_name.as_str())),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(contended, [(true, "User Initialization (PROS)")]);
+    // the parent holds it for the child's 5 ms timeout, and is only reported once
+    let held = run
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::MutexHeldTooLong {
+                mutex_id: 0,
+                task_name,
+                held_millis,
+                ..
+            } => Some((task_name.as_str(), *held_millis)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert!(
+        matches!(held[..], [("User Initialization (PROS)", millis)] if millis > 2),
+        "{held:?}"
+    );
+}
+
+#[tokio::test]
+async fn task_list() {
+    let run = run_fixture("task_count", [SimulatorMessage::ListTasks]).await;
+    // the system daemon and `initialize`, then the child task too
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(23)),
+        "{:?}",
+        run.outcome.reason
+    );
+    let lists = run
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::TaskList(tasks) => Some(tasks),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let [tasks] = &lists[..] else {
+        panic!("{lists:?}");
+    };
+    let tasks = tasks
+        .iter()
+        .map(|task| (task.name.as_str(), task.state, task.system))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        tasks,
+        [
+            ("PROS System Daemon", TaskState::Running, true),
+            ("User Initialization (PROS)", TaskState::Ready, false),
+        ]
+    );
+}
+
+#[test]
+fn without_tokio() {
+    // mutex timeouts and the system daemon's delays don't depend on Tokio's timers
+    let run = futures::executor::block_on(run_fixture("mutexes", []));
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(1)),
+        "{:?}",
+        run.outcome.reason
+    );
+
+    let options = default_options().threaded(true);
+    let run = futures::executor::block_on(run_fixture_with_options("parallel", options, []));
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        run.outcome.reason
+    );
+}
+
+#[tokio::test]
+async fn delay_until() {
+    let run = run_fixture("delay_until", []).await;
+    // 1 if prev_time wasn't updated, 2 if the task didn't wait long enough
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        run.outcome.reason
+    );
+
+    // the same, but with `millis` wrapping around during the delays
+    let options = default_options().start_millis(u32::MAX - 15);
+    let run = run_fixture_with_options("delay_until", options, []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        run.outcome.reason
+    );
+}
+
+#[tokio::test]
+async fn task_names() {
+    let run = run_fixture("task_names", []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        run.outcome.reason
+    );
+    assert_eq!(
+        run.console(),
+        "User Initialization (PROS)\nUser Initialization (PROS)\n"
+    );
+}
+
+#[tokio::test]
+async fn no_allocator() {
+    let run = run_fixture("no_allocator", []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        run.outcome.reason
+    );
+    assert_eq!(run.console(), "User Initialization (PROS)\n");
+}
+
+#[tokio::test]
+async fn errno_out_of_memory() {
+    // robot code whose `errno` can't be allocated crashes instead of the simulator
+    let run = run_fixture("errno_out_of_memory", []).await;
+    assert!(
+        matches!(&run.outcome.reason, StopReason::Crashed(err) if format!("{err:#}").contains("Couldn't allocate errno")),
+        "{:?}",
+        run.outcome.reason
+    );
+}
+
+#[tokio::test]
+async fn invalid_allocation() {
+    // an allocator that returns a pointer outside of memory crashes robot code instead of the
+    // simulator
+    let run =
+        run_fixture_with_options("invalid_allocation", default_options().canaries(true), []).await;
+    assert!(
+        matches!(&run.outcome.reason, StopReason::Crashed(err) if format!("{err:#}").contains("invalid pointer")),
+        "{:?}",
+        run.outcome.reason
+    );
+}
+
+#[tokio::test]
+async fn allocation_failure() {
+    // robot code that can't allocate a task's name crashes instead of the simulator
+    let run = run_fixture("full_allocator", []).await;
+    assert!(
+        matches!(&run.outcome.reason, StopReason::Crashed(err) if format!("{err:#}").contains("out of memory")),
+        "{:?}",
+        run.outcome.reason
+    );
+}
+
+#[tokio::test]
+async fn newlib() {
+    let run = run_fixture("newlib", []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(7)),
+        "{:?}",
+        run.outcome.reason
+    );
+}
+
+#[tokio::test]
+async fn ticks() {
+    let run = run_fixture("ticks", []).await;
+    // 1 or 3 if a delay or timeout ended before enough ticks had passed
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        run.outcome.reason
+    );
+}
+
+#[tokio::test]
+async fn suspend_all() {
+    let run = run_fixture("suspend_all", []).await;
+    // exits early with 1-3 if the critical section wasn't atomic or the deferred yield was lost
+    assert!(
+        matches!(&run.outcome.reason, StopReason::Crashed(err) if err.root_cause().to_string().contains("suspended")),
+        "{:?}",
+        run.outcome.reason
+    );
+    assert_eq!(run.console(), "atomic\n");
+}
+
+#[tokio::test]
+async fn serial_throttling() {
+    let expected_console = format!("{}\n", "x".repeat(99)).repeat(30);
+
+    let run = run_fixture("serial", []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0..=100)),
+        "{:?}",
+        run.outcome.reason
+    );
+    assert!(run.console().starts_with(&expected_console));
+
+    // 2000 bytes per second, so the task blocks until the last 2048 of the 3000 bytes fit in the
+    // transmit buffer about 476 ms in
+    let options = default_options().throttle_serial(20_000);
+    let run = run_fixture_with_options("serial", options, []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(450..=1000)),
+        "{:?}",
+        run.outcome.reason
+    );
+    // the rest of the output is delivered when the robot code exits
+    assert!(run.console().starts_with(&expected_console));
+}
+
+#[tokio::test]
+async fn line_buffering() {
+    let messages = |run: &common::Run| {
+        run.events
+            .iter()
+            .filter_map(|event| match event {
+                SimulatorEvent::ConsoleMessage(message) => Some(message.text.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let run = run_fixture("line_buffering", []).await;
+    assert_eq!(messages(&run), ["a", "b", "c\n", "d"]);
+
+    // stderr isn't buffered, and the last line is flushed when the robot code exits
+    let options = default_options().line_buffering(true);
+    let run = run_fixture_with_options("line_buffering", options, []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        run.outcome.reason
+    );
+    assert_eq!(messages(&run), ["b", "ac\n", "d"]);
+}
+
+#[tokio::test]
+async fn telemetry() {
+    let run = run_fixture("ticks", []).await;
+    assert!(!run
+        .events
+        .iter()
+        .any(|event| matches!(event, SimulatorEvent::Telemetry(_))));
+
+    let options = default_options().telemetry(1000);
+    let run = run_fixture_with_options("ticks", options, []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        run.outcome.reason
+    );
+    let snapshots = run
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::Telemetry(telemetry) => Some(telemetry),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    // the fixture runs for at least 15 ms
+    assert!(snapshots.len() >= 2, "{snapshots:?}");
+    assert!(snapshots
+        .windows(2)
+        .all(|pair| pair[0].millis <= pair[1].millis));
+    // the system daemon and `initialize`
+    assert_eq!(snapshots[0].task_count, 2);
+    assert_eq!(snapshots[0].master, None);
+
+    // frontends can turn telemetry on while the simulation is running
+    let run = run_fixture_interactive(
+        "ticks",
+        vec![SimulatorMessage::SetRates(EventRates {
+            telemetry: Some(1000),
+            ..EventRates::default()
+        })],
+        |event| match event {
+            SimulatorEvent::Telemetry(_) => {
+                vec![SimulatorMessage::SetRates(EventRates::default())]
+            }
+            _ => vec![],
+        },
+    )
+    .await;
+    let snapshots = run
+        .events
+        .iter()
+        .filter(|event| matches!(event, SimulatorEvent::Telemetry(_)))
+        .count();
+    assert_eq!(snapshots, 1);
+}
+
+#[tokio::test]
+async fn motor_telemetry() {
+    let options = default_options()
+        .smart_port(1, DeviceType::Motor)
+        .smart_port(3, DeviceType::Imu)
+        .telemetry(1000);
+    let run = run_fixture_with_options("motor_model", options, []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        run.outcome.reason
+    );
+    let motors = run
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::Telemetry(telemetry) => Some(telemetry.motors.clone()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    // only the motor is included
+    assert!(motors
+        .iter()
+        .all(|motors| motors.len() == 1 && motors[0].port == 1));
+    let driven = motors
+        .iter()
+        .map(|motors| motors[0])
+        .filter(|motor| motor.millivolts == 12000)
+        .collect::<Vec<_>>();
+    assert!(driven.len() >= 2, "{driven:?}");
+    assert!(
+        driven
+            .windows(2)
+            .all(|pair| pair[0].position <= pair[1].position),
+        "{driven:?}"
+    );
+    // by the end of the half second it's at full speed, drawing almost no current
+    let last = driven.last().unwrap();
+    assert_eq!(last.velocity, 200, "{driven:?}");
+    assert!(last.position > 500, "{driven:?}");
+    assert!(last.current < 50, "{driven:?}");
+}
+
+#[tokio::test]
+async fn frame_markers() {
+    let options = default_options().frame_markers(500).start_millis(1000);
+    let run = run_fixture_with_options("ticks", options, []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        run.outcome.reason
+    );
+    let frames = run
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::Frame { frame, millis } => Some((*frame, *millis)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    // the fixture runs for at least 15 ms
+    assert!(frames.len() >= 2, "{frames:?}");
+    assert!(frames.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    // frames are 2 ms long
+    assert!(frames
+        .iter()
+        .all(|(frame, millis)| *millis == 1000 + *frame as u32 * 2));
+}
+
+#[tokio::test]
+async fn task_stats() {
+    let options = default_options().task_stats(true);
+    let run = run_fixture_with_options("cpu_usage", options, []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        run.outcome.reason
+    );
+    let stats = run
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::TaskStats(stats) => Some(*stats),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    // the robot code spins for the first second and waits through the second
+    assert_eq!(stats.len(), 2, "{stats:?}");
+    assert!(stats[0].cpu_usage > 50, "{stats:?}");
+    assert!(stats[1].cpu_usage < 50, "{stats:?}");
+    // each covers about a second, depending on when the system daemon got to run
+    assert!(stats
+        .iter()
+        .all(|stats| (900_000..1_100_000).contains(&(stats.busy_micros + stats.idle_micros))));
+}
+
+#[tokio::test]
+async fn set_pose() {
+    let pose = Pose {
+        x: -48.0,
+        y: 12.5,
+        heading: 180.0,
+    };
+    let options = default_options().telemetry(1000);
+    let run = run_fixture_with_options("ticks", options, [SimulatorMessage::SetPose(pose)]).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        run.outcome.reason
+    );
+    assert!(run.events.contains(&SimulatorEvent::PoseSet(pose)));
+    let snapshots = run
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::Telemetry(telemetry) => Some(telemetry),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert!(!snapshots.is_empty());
+    assert!(snapshots
+        .iter()
+        .all(|telemetry| telemetry.pose == Some(pose)));
+}
+
+#[tokio::test]
+async fn controller() {
+    let state = controller_state();
+    let run = run_fixture(
+        "controller",
+        [
+            SimulatorMessage::ControllerUpdate(Some(state), None),
+            opcontrol(),
+        ],
+    )
+    .await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(1042)),
+        "{:?}",
+        run.outcome.reason
+    );
+}
+
+#[tokio::test]
+async fn input_shaping() {
+    // left y is 42
+    for (deadzone, expo, exit_code) in [(10, 0, 1035), (0, 100, 1005), (50, 0, 1000)] {
+        let run = run_fixture(
+            "controller",
+            [
+                SimulatorMessage::SetInputShaping {
+                    controller: ControllerId::Master,
+                    shaping: InputShaping { deadzone, expo },
+                },
+                SimulatorMessage::ControllerUpdate(Some(controller_state()), None),
+                opcontrol(),
+            ],
+        )
+        .await;
+        assert!(
+            matches!(run.outcome.reason, StopReason::Exited(code) if code == exit_code),
+            "deadzone {deadzone}, expo {expo}: {:?}",
+            run.outcome.reason
+        );
+    }
+
+    let shaping = InputShaping {
+        deadzone: 20,
+        expo: 30,
+    };
+    assert_eq!(shaping.apply(127), 127);
+    assert_eq!(shaping.apply(-127), -127);
+    assert_eq!(shaping.apply(-20), 0);
+}
+
+#[tokio::test]
+async fn config_file() {
+    let write_config = |name: &str, source: &str| {
+        let path = std::env::temp_dir().join(format!(
+            "pros-simulator-test-{}-{name}.toml",
+            std::process::id()
+        ));
+        std::fs::write(&path, source).unwrap();
+        path
+    };
+    let path = write_config(
+        "config",
+        indoc! {r#"
+            timeout = 10
+            scenario = "auton.jsonl"
+
+            [ports]
+            5 = "imu"
+
+            [drivetrain]
+            drive_type = "Tank"
+            wheel_diameter = 3.25
+            track_width = 11.5
+            gear_ratio = 0.6
+            left_motors = [1]
+            right_motors = [-3]
+
+            [controllers.master]
+            deadzone = 10
+
+            [[field.objects]]
+            name = "ring"
+            kind = "ring"
+            x = 24.0
+            y = 0.0
+            radius = 3.5
+        "#},
+    );
+    let config = SimulatorConfig::load(&path).unwrap();
+    assert_eq!(
+        config.scenario(),
+        Some(&*path.with_file_name("auton.jsonl"))
+    );
+
+    let run = run_fixture_with_options("motor_voltage", config.options(), []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0b11_1111)),
+        "{:?}",
+        run.outcome.reason
+    );
+    let groups = |group: &str| {
+        run.events
+            .iter()
+            .filter(|event| {
+                matches!(event, SimulatorEvent::MotorGroupUpdated { name, .. } if name == group)
+            })
+            .count()
+    };
+    assert_eq!(groups("left drive"), 2);
+    assert_eq!(groups("right drive"), 0);
+    assert!(run.events.iter().any(|event| matches!(
+        event,
+        SimulatorEvent::GameObjectUpdated(object) if object.name == "ring"
+    )));
+    let drivetrain = run
+        .events
+        .iter()
+        .position(|event| {
+            *event
+                == SimulatorEvent::DrivetrainConfigured(DrivetrainConfig {
+                    drive_type: DriveType::Tank,
+                    wheel_diameter: 3.25,
+                    track_width: 11.5,
+                    gear_ratio: 0.6,
+                    left_motors: vec![1],
+                    right_motors: vec![-3],
+                    strafe_motors: Vec::new(),
+                })
+        })
+        .expect("the drivetrain is reported");
+    assert_eq!(
+        run.events[drivetrain + 1],
+        SimulatorEvent::RobotCodeStarting
+    );
+
+    // left y is 42, shaped by the deadzone
+    let run = run_fixture_with_options(
+        "controller",
+        SimulatorOptions::from_file(&path).unwrap(),
+        [
+            SimulatorMessage::ControllerUpdate(Some(controller_state()), None),
+            opcontrol(),
+        ],
+    )
+    .await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(1035)),
+        "{:?}",
+        run.outcome.reason
+    );
+
+    let path = write_config("simulated-timeout", "simulated_timeout = 0.2");
+    let run =
+        run_fixture_with_options("spin", SimulatorOptions::from_file(&path).unwrap(), []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::TimedOut),
+        "{:?}",
+        run.outcome.reason
+    );
+
+    for (source, error) in [
+        ("seed = 1\nspeed = 2", "unknown field `speed`"),
+        (
+            "timeout = 10\nsimulated_timeout = 10",
+            "only one of `timeout` and `simulated_timeout` can be set",
+        ),
+        ("simulated_timeout = -1", "the timeout can't be -1 seconds"),
+        ("[ports]\n22 = \"motor\"", "`22` isn't a smart port"),
+        ("[ports]\n1 = \"lidar\"", "unknown device type `lidar`"),
+        (
+            indoc! {r#"
+                [drivetrain]
+                drive_type = "Tank"
+                wheel_diameter = 4.0
+                track_width = 12.0
+                gear_ratio = 1.0
+                left_motors = [1]
+                right_motors = [-22]
+            "#},
+            "the drivetrain is invalid: one of its ports isn't a smart port",
+        ),
+        (
+            indoc! {r#"
+                [drivetrain]
+                drive_type = "Tank"
+                wheel_diameter = 0.0
+                track_width = 12.0
+                gear_ratio = 1.0
+                left_motors = [1]
+                right_motors = [2]
+            "#},
+            "its wheel diameter, track width and gear ratio must be positive",
+        ),
+        (
+            indoc! {r#"
+                [drivetrain]
+                drive_type = "Tank"
+                wheel_diameter = 4.0
+                track_width = 12.0
+                gear_ratio = 1.0
+                left_motors = [1, 2]
+                right_motors = [-2]
+            "#},
+            "it has more than one motor on the same port",
+        ),
+        (
+            indoc! {r#"
+                [ports]
+                1 = "imu"
+
+                [drivetrain]
+                drive_type = "Tank"
+                wheel_diameter = 4.0
+                track_width = 12.0
+                gear_ratio = 1.0
+                left_motors = [-1]
+                right_motors = [2]
+            "#},
+            "drive motor port 1 isn't a motor in `[ports]`",
+        ),
+        (
+            "[[motor_groups]]\nname = \"lift\"\nports = []",
+            "motor group `lift` is invalid",
+        ),
+    ] {
+        let path = write_config("invalid-config", source);
+        let err = SimulatorConfig::load(&path).unwrap_err();
+        assert!(format!("{err:#}").contains(error), "{err:#}");
+    }
+}
+
+#[tokio::test]
+async fn controller_latency() {
+    // opcontrol starts right away, but the controller update is still on its way
+    let options = default_options().controller_latency(Duration::from_millis(200));
+    let state = controller_state();
+    let run = run_fixture_with_options(
+        "controller",
+        options,
+        [
+            SimulatorMessage::ControllerUpdate(Some(state), None),
+            opcontrol(),
+        ],
+    )
+    .await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        run.outcome.reason
+    );
+}
+
+#[tokio::test]
+async fn controller_text() {
+    let run = run_fixture(
+        "controller_text",
+        [
+            SimulatorMessage::ControllerUpdate(Some(controller_state()), None),
+            opcontrol(),
+        ],
+    )
+    .await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(11111)),
+        "{:?}",
+        run.outcome.reason
+    );
+    let screens = run
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::ControllerTextUpdated { controller, lines } => {
+                Some((*controller, lines.clone()))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    // the write that was too soon after the first one never reached the controller
+    assert_eq!(
+        screens,
+        [
+            (
+                ControllerId::Master,
+                vec!["x=42 ok|-02.5".to_string(), String::new(), String::new()]
+            ),
+            (
+                ControllerId::Master,
+                vec![
+                    "x=42 ok|-02.5".to_string(),
+                    "  hi".to_string(),
+                    String::new()
+                ]
+            ),
+        ]
+    );
+    assert!(run.events.iter().any(|event| matches!(
+        event,
+        SimulatorEvent::ControllerRumbled { controller: ControllerId::Master, pattern }
+            if pattern == ".-"
+    )));
+}
+
+#[tokio::test]
+async fn controller_print_limits() {
+    let run = run_fixture(
+        "controller_print_limits",
+        [
+            SimulatorMessage::ControllerUpdate(Some(controller_state()), None),
+            opcontrol(),
+        ],
+    )
+    .await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(111111)),
+        "{:?}",
+        run.outcome.reason
+    );
+    // each character is 2 columns wide, so only 7 of them fit after the first column
+    assert!(run.events.iter().any(|event| matches!(
+        event,
+        SimulatorEvent::ControllerTextUpdated { controller: ControllerId::Master, lines }
+            if lines[0] == " 日本語日本語日"
+    )));
+}
+
+#[tokio::test]
+async fn controller_disconnect() {
+    let state = controller_state();
+    let run = run_fixture_interactive(
+        "controller_disconnect",
+        vec![
+            SimulatorMessage::ControllerUpdate(Some(state), None),
+            opcontrol(),
+        ],
+        |event| match event {
+            SimulatorEvent::ConsoleMessage(message) if message.text == "ready\n" => {
+                vec![SimulatorMessage::ControllerUpdate(None, None)]
+            }
+            _ => vec![],
+        },
+    )
+    .await;
+    // the stale button and joystick values would give 1042
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        run.outcome.reason
+    );
+    let disconnects = run
+        .events
+        .iter()
+        .filter(|event| matches!(event, SimulatorEvent::ControllerDisconnected(_)))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        disconnects,
+        [&SimulatorEvent::ControllerDisconnected(
+            ControllerId::Master
+        )]
+    );
+}
+
+#[tokio::test]
+async fn vexide() {
+    let state = controller_state();
+    let run = run_fixture(
+        "vexide",
+        [SimulatorMessage::ControllerUpdate(Some(state), None)],
+    )
+    .await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Finished),
+        "{:?}",
+        run.outcome.reason
+    );
+    assert_eq!(run.console(), "connected\nleft y is 42\n");
+}
+
+#[tokio::test]
+async fn abi_detection() {
+    let info = |abi| ProgramInfo {
+        display: Some(DisplayGeometry::default()),
+        ..ProgramInfo::new(abi)
+    };
+    for (fixture, abi) in [
+        ("tasks", ProgramAbi::ProsRs),
+        ("newlib", ProgramAbi::ProsC),
+        ("vexide", ProgramAbi::Vexide),
+    ] {
+        let (result, events) = check_fixture(fixture).await;
+        result.unwrap();
+        assert!(
+            events.contains(&SimulatorEvent::ProgramInfo(info(abi))),
+            "{fixture}: {events:?}"
+        );
+    }
+
+    let (result, events) = check_fixture("wasi").await;
+    let err = result.unwrap_err();
+    assert!(
+        err.to_string().contains("`wasi_snapshot_preview1`"),
+        "{err}"
+    );
+    assert!(events.contains(&SimulatorEvent::ProgramInfo(info(ProgramAbi::Unknown))));
+}
+
+#[tokio::test]
+async fn program_info() {
+    let (result, events) = check_fixture("program_info").await;
+    result.unwrap();
+    assert!(
+        events.contains(&SimulatorEvent::ProgramInfo(ProgramInfo {
+            abi: ProgramAbi::ProsRs,
+            name: Some("Competition Bot".into()),
+            version: Some("1.2.0".into()),
+            slot: Some(3),
+            icon: Some("USER902x.bmp".into()),
+            description: Some("Worlds code".into()),
+            display: Some(DisplayGeometry::default()),
+        })),
+        "{events:?}"
+    );
+    let warnings = events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::Warning(warning) => Some(warning.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(warnings.len(), 2, "{warnings:?}");
+    assert!(warnings[0].contains("slot `9`"), "{warnings:?}");
+    assert!(warnings[1].contains("`color`"), "{warnings:?}");
+}
+
+#[tokio::test]
+async fn api_compatibility() {
+    let (result, events) = check_fixture("compat").await;
+    result.unwrap();
+    let compatibility = events
+        .iter()
+        .find_map(|event| match event {
+            SimulatorEvent::ApiCompatibility(compatibility) => Some(compatibility),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(compatibility.target, ProsVersion::V3_8);
+    assert_eq!(compatibility.implemented, ["exit", "puts"]);
+    assert_eq!(compatibility.stubbed, ["controller_get_battery_level"]);
+    assert_eq!(compatibility.missing, ["motor_move_velocity"]);
+    assert_eq!(compatibility.not_in_target, ["sbrk", "sim_log_backtrace"]);
+
+    let (result, events) = check_fixture("vexide").await;
+    result.unwrap();
+    assert!(!events
+        .iter()
+        .any(|event| matches!(event, SimulatorEvent::ApiCompatibility(_))));
+}
+
+#[tokio::test]
+async fn unimplemented_call() {
+    for options in [default_options(), default_options().threaded(true)] {
+        let run = run_fixture_with_options("unimplemented_call", options, [opcontrol()]).await;
+        assert!(
+            matches!(run.outcome.reason, StopReason::Exited(0)),
+            "{:?}",
+            run.outcome.reason
+        );
+        let (name, backtrace) = run
+            .events
+            .iter()
+            .find_map(|event| match event {
+                SimulatorEvent::UnimplementedCall { name, backtrace } => Some((name, backtrace)),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(name, "motor_move_velocity");
+        assert_eq!(backtrace[0].func_name.as_deref(), Some("initialize"));
+        assert!(!run
+            .events
+            .iter()
+            .any(|event| matches!(event, SimulatorEvent::RobotCodeError { .. })));
+    }
+}
+
+#[tokio::test]
+async fn permissive() {
+    let options = default_options().permissive(true);
+    let run = run_fixture_with_options("permissive", options, []).await;
+    // PROS_ERR and PROS_ERR_F were returned, and errno is ENOSYS
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(188)),
+        "{:?}",
+        run.outcome.reason
+    );
+    let coverage = run
+        .events
+        .iter()
+        .find_map(|event| match event {
+            SimulatorEvent::ApiCoverage(coverage) => Some(coverage),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(coverage.calls["motor_move_velocity"], 1);
+    assert_eq!(coverage.calls["vision_print_signature"], 1);
+
+    let run = run_fixture("permissive", [opcontrol()]).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        run.outcome.reason
+    );
+    assert!(run
+        .events
+        .iter()
+        .any(|event| matches!(event, SimulatorEvent::UnimplementedCall { .. })));
+}
+
+#[tokio::test]
+async fn registry() {
+    let options = default_options().smart_port(1, DeviceType::Motor);
+    let run = run_fixture_with_options("registry", options, []).await;
+    // every check passed
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0b1_1111_1111)),
+        "{:?}",
+        run.outcome.reason
+    );
+}
+
+#[tokio::test]
+async fn motor_voltage() {
+    let options = default_options().smart_port(1, DeviceType::Motor);
+    let run = run_fixture_with_options("motor_voltage", options, []).await;
+    // every check passed
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0b11_1111)),
+        "{:?}",
+        run.outcome.reason
+    );
+    let mut updates = run
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::MotorUpdated {
+                port, millivolts, ..
+            } => Some((*port, *millivolts)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    // the motor can also be reported as it speeds up
+    updates.dedup();
+    assert_eq!(updates, [(1, 6000), (1, -12000)]);
+}
+
+/// `(millivolts, target_velocity, actual_velocity, position, current)` of each
+/// [`SimulatorEvent::MotorUpdated`] event.
+fn motor_states(events: &[SimulatorEvent]) -> Vec<(i32, i32, i32, i32, i32)> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::MotorUpdated {
+                millivolts,
+                target_velocity,
+                actual_velocity,
+                position,
+                current,
+                ..
+            } => Some((
+                *millivolts,
+                *target_velocity,
+                *actual_velocity,
+                *position,
+                *current,
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn motor_model() {
+    // the motor is modeled in ticks, so it keeps turning when `millis` wraps around
+    for start_millis in [0, u32::MAX - 295] {
+        let options = default_options()
+            .smart_port(1, DeviceType::Motor)
+            .start_millis(start_millis);
+        let run = run_fixture_with_options("motor_model", options, []).await;
+        assert!(
+            matches!(run.outcome.reason, StopReason::Exited(0)),
+            "{:?}",
+            run.outcome.reason
+        );
+        let updates = motor_states(&run.events);
+        // the motor starts at rest, drawing its stall current
+        assert_eq!(updates[0], (12000, 200, 0, 0, 2500));
+        // after half a second it's at full speed and has turned about one and a half times, and
+        // its back EMF is twice the new voltage
+        let slowed = *updates
+            .iter()
+            .find(|(millivolts, ..)| *millivolts == 6000)
+            .unwrap();
+        let (_, target_velocity, actual_velocity, position, current) = slowed;
+        assert_eq!((target_velocity, actual_velocity), (100, 200), "{slowed:?}");
+        assert!((530..=560).contains(&position), "{slowed:?}");
+        assert!((1240..=1260).contains(&current), "{slowed:?}");
+    }
+}
+
+#[tokio::test]
+async fn motor_ramp() {
+    let options = default_options().smart_port(1, DeviceType::Motor);
+    let run = run_fixture_with_options("motor_ramp", options.clone(), []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        run.outcome.reason
+    );
+    // the voltage never changes, but the motor is reported as it speeds up
+    let updates = motor_states(&run.events);
+    assert!(updates.len() >= 3, "{updates:?}");
+    assert!(updates.iter().all(|(millivolts, ..)| *millivolts == 12000));
+    let velocities = updates
+        .iter()
+        .map(|(_, _, actual_velocity, ..)| *actual_velocity)
+        .collect::<Vec<_>>();
+    assert!(
+        velocities.windows(2).all(|pair| pair[0] < pair[1]),
+        "{velocities:?}"
+    );
+    assert_eq!(velocities.first(), Some(&0));
+    // after 100 ms, two time constants, it's at about 86% of its free speed
+    assert!(velocities.last().unwrap() > &170, "{velocities:?}");
+
+    // still rate limited, apart from the final state
+    let run = run_fixture_with_options("motor_ramp", options.motor_update_rate(1), []).await;
+    let updates = motor_states(&run.events);
+    assert_eq!(updates.len(), 2, "{updates:?}");
+    assert!(updates[1].2 > 170, "{updates:?}");
+}
+
+#[tokio::test]
+async fn motor_update_rate() {
+    let motor_updates = |events: &[SimulatorEvent]| {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                SimulatorEvent::MotorUpdated {
+                    port, millivolts, ..
+                } => Some((*port, *millivolts)),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // repeated voltages aren't sent again, though the motor can be reported in between as it
+    // speeds up
+    let options = default_options().smart_port(1, DeviceType::Motor);
+    let run = run_fixture_with_options("motor_update_rate", options.clone(), []).await;
+    assert!(matches!(run.outcome.reason, StopReason::Exited(0)));
+    let mut voltages = motor_updates(&run.events);
+    assert_eq!(voltages[0], (1, 100));
+    voltages.dedup();
+    assert_eq!(voltages.len(), 50);
+    // stamped with the time robot code saw, with each voltage a millisecond apart
+    let mut millis = run
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::MotorUpdated {
+                millivolts, millis, ..
+            } => Some((*millivolts, *millis)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    millis.dedup_by_key(|(millivolts, _)| *millivolts);
+    assert!(
+        millis.windows(2).all(|pair| pair[0].1 < pair[1].1),
+        "{millis:?}"
+    );
+
+    // the first change is sent right away, and the last one when the simulation stops
+    let run = run_fixture_with_options("motor_update_rate", options.motor_update_rate(1), []).await;
+    assert_eq!(motor_updates(&run.events), [(1, 100), (1, 5000)]);
+}
+
+#[tokio::test]
+async fn motor_groups() {
+    let options = default_options()
+        .smart_port(1, DeviceType::Motor)
+        .smart_port(3, DeviceType::Motor)
+        .motor_group(MotorGroup {
+            name: "left drive".into(),
+            ports: vec![1, -3],
+        });
+    let run = run_fixture_with_options(
+        "motor_voltage",
+        options,
+        [
+            SimulatorMessage::AddMotorGroup(MotorGroup {
+                name: "right drive".into(),
+                ports: vec![-1],
+            }),
+            SimulatorMessage::AddMotorGroup(MotorGroup {
+                name: "lift".into(),
+                ports: vec![1, 22],
+            }),
+        ],
+    )
+    .await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0b11_1111)),
+        "{:?}",
+        run.outcome.reason
+    );
+    let groups = |group: &str| {
+        run.events
+            .iter()
+            .filter_map(|event| match event {
+                SimulatorEvent::MotorGroupUpdated {
+                    name,
+                    millivolts,
+                    motors,
+                } if name == group => Some((*millivolts, motors.clone())),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+    // port 3 is never driven
+    assert_eq!(
+        groups("left drive"),
+        [(3000, vec![6000, 0]), (-6000, vec![-12000, 0])]
+    );
+    // added by a message, which might arrive after the first voltage was set
+    assert_eq!(groups("right drive").last(), Some(&(12000, vec![12000])));
+    assert!(groups("lift").is_empty());
+    assert!(run.events.iter().any(|event| matches!(
+        event,
+        SimulatorEvent::Warning(message) if message.contains("Motor group `lift` was ignored")
+    )));
+}
+
+#[tokio::test]
+async fn motor_move() {
+    let options = default_options().smart_port(1, DeviceType::Motor);
+    let run = run_fixture_with_options("motor_move", options, []).await;
+    // every check passed
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0b11_1111)),
+        "{:?}",
+        run.outcome.reason
+    );
+    let mut updates = run
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::MotorUpdated {
+                port, millivolts, ..
+            } => Some((*port, *millivolts)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    // the motor can also be reported as it speeds up
+    updates.dedup();
+    // -64 / 127 of 12 V, rounded towards zero
+    assert_eq!(updates, [(1, 12000), (1, -6047)]);
+    let warnings = run
+        .events
+        .iter()
+        .filter(|event| matches!(event, SimulatorEvent::Warning(message) if message.contains("clamped")))
+        .count();
+    assert_eq!(warnings, 1);
+}
+
+#[tokio::test]
+async fn motor_move_voltage_clamped() {
+    let options = default_options().smart_port(1, DeviceType::Motor);
+    let run = run_fixture_with_options("motor_voltage", options.clone(), []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0b11_1111)),
+        "{:?}",
+        run.outcome.reason
+    );
+    // -20000 mV, like motor_move's out of range values
+    let warnings = run
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::Warning(message) if message.contains("clamped") => Some(message),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let [warning] = warnings[..] else {
+        panic!("{warnings:?}");
+    };
+    assert!(
+        warning.starts_with("motor_move_voltage was called with -20000 for port 1"),
+        "{warning}"
+    );
+
+    let options = options.strict(WarningKind::MotorVoltageClamped);
+    let run = run_fixture_with_options("motor_voltage", options, []).await;
+    assert!(
+        matches!(
+            run.outcome.reason,
+            StopReason::StrictWarning(WarningKind::MotorVoltageClamped)
+        ),
+        "{:?}",
+        run.outcome.reason
+    );
+}
+
+#[tokio::test]
+async fn strict_warnings() {
+    let motor = default_options().smart_port(1, DeviceType::Motor);
+    let run = run_fixture_with_options(
+        "motor_move",
+        motor.clone().strict(WarningKind::MotorVoltageClamped),
+        [],
+    )
+    .await;
+    assert!(
+        matches!(
+            run.outcome.reason,
+            StopReason::StrictWarning(WarningKind::MotorVoltageClamped)
+        ),
+        "{:?}",
+        run.outcome.reason
+    );
+
+    // other kinds of warning don't stop the simulation
+    let run = run_fixture_with_options(
+        "motor_move",
+        motor.strict(WarningKind::UnimplementedCall),
+        [],
+    )
+    .await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0b11_1111)),
+        "{:?}",
+        run.outcome.reason
+    );
+
+    for options in [default_options(), default_options().threaded(true)] {
+        let options = options.strict(WarningKind::UnimplementedCall);
+        let run = run_fixture_with_options("unimplemented_call", options, [opcontrol()]).await;
+        assert!(
+            matches!(
+                run.outcome.reason,
+                StopReason::StrictWarning(WarningKind::UnimplementedCall)
+            ),
+            "{:?}",
+            run.outcome.reason
+        );
+    }
+}
+
+#[tokio::test]
+async fn fail_next_call() {
+    let run = run_fixture(
+        "fail_next_call",
+        [
+            SimulatorMessage::FailNextCall {
+                api: "write".into(),
+                errno: pros_sys::EIO,
+            },
+            SimulatorMessage::FailNextCall {
+                api: "motor_move".into(),
+                errno: pros_sys::ENODEV,
+            },
+        ],
+    )
+    .await;
+    // only the first write fails
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(51)),
+        "{:?}",
+        run.outcome.reason
+    );
+    assert_eq!(run.console(), "xError 51\n");
+    assert!(run.events.iter().any(
+        |event| matches!(event, SimulatorEvent::Warning(message) if message.contains("motor_move"))
+    ));
+}
+
+#[tokio::test]
+async fn competition() {
+    let phase = CompetitionPhase {
+        autonomous: true,
+        enabled: true,
+        is_competition: true,
+    };
+    let run = run_fixture("competition", [SimulatorMessage::PhaseChange(phase)]).await;
+    // COMPETITION_AUTONOMOUS | COMPETITION_CONNECTED
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0b101)),
+        "{:?}",
+        run.outcome.reason
+    );
+}
+
+#[tokio::test]
+async fn competition_switch() {
+    let controller = ControllerState {
+        competition_switch: Some(CompetitionSwitch {
+            enabled: true,
+            autonomous: true,
+        }),
+        ..controller_state()
+    };
+    let run = run_fixture(
+        "competition",
+        [SimulatorMessage::ControllerUpdate(Some(controller), None)],
+    )
+    .await;
+    // COMPETITION_AUTONOMOUS | COMPETITION_CONNECTED
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0b101)),
+        "{:?}",
+        run.outcome.reason
+    );
+}
+
+#[tokio::test]
+async fn match_automation() {
+    let timing = MatchTiming {
+        autonomous: Duration::from_millis(300),
+        driver_control: Duration::from_millis(300),
+    };
+    let run = run_fixture_with_options("match", default_options().run_match(timing), []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Finished),
+        "{:?}",
+        run.outcome.reason
+    );
+
+    let timers = run
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::CompetitionTimer {
+                phase,
+                remaining_ms,
+            } => Some((*phase, *remaining_ms)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let mut phases = timers.iter().map(|(phase, _)| *phase).collect::<Vec<_>>();
+    phases.dedup();
+    assert_eq!(
+        phases,
+        [
+            CompetitionPhase {
+                autonomous: true,
+                enabled: true,
+                is_competition: true,
+            },
+            CompetitionPhase {
+                autonomous: false,
+                enabled: true,
+                is_competition: true,
+            },
+            CompetitionPhase {
+                autonomous: false,
+                enabled: false,
+                is_competition: true,
+            },
+        ]
+    );
+    // the clock counts down within each period and ends at zero
+    assert!(timers
+        .windows(2)
+        .all(|pair| pair[0].0 != pair[1].0 || pair[0].1 >= pair[1].1));
+    assert!(timers.iter().all(|(_, remaining_ms)| *remaining_ms <= 300));
+    assert_eq!(timers.last().unwrap().1, 0);
+}
+
+#[tokio::test]
+async fn manual_scheduling() {
+    let robot_code = build_fixture("ticks");
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let host = pros_simulator::load(&robot_code, default_options(), {
+        let events = events.clone();
+        move |event| events.lock().unwrap().push(event)
+    })
+    .unwrap();
+    _ = std::fs::remove_file(robot_code);
+
+    let (_messages, rx) = mpsc::channel();
+    pros_simulator::start(&host, rx).await.unwrap();
+    {
+        let mut tasks = host.tasks_lock().await;
+        let options = TaskOptions::new_closure(&mut tasks, &host, |caller| {
+            Box::new(async move {
+                caller
+                    .interface()
+                    .send(SimulatorEvent::ConsoleMessage("from the host\n".into()));
+                Ok(())
+            })
+        })
+        .unwrap()
+        .name("Host Task");
+        tasks
+            .spawn(options, &host.module(), &host.interface())
+            .await
+            .unwrap();
+    }
+
+    let reason = TaskPool::run_to_completion(&host).await;
+    let outcome = pros_simulator::finish(&host, reason);
+    assert!(
+        matches!(outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        outcome.reason
+    );
+    let events = events.lock().unwrap();
+    assert!(events
+        .iter()
+        .any(|event| matches!(event, SimulatorEvent::ConsoleMessage(message) if message.text == "from the host\n")));
+    assert!(matches!(
+        events.last(),
+        Some(SimulatorEvent::RobotCodeFinished)
+    ));
+}
+
+#[tokio::test]
+async fn scheduler_invariants() {
+    let robot_code = build_fixture("ticks");
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let host = pros_simulator::load(&robot_code, default_options(), {
+        let events = events.clone();
+        move |event| events.lock().unwrap().push(event)
+    })
+    .unwrap();
+    _ = std::fs::remove_file(robot_code);
+
+    let (_messages, rx) = mpsc::channel();
+    pros_simulator::start(&host, rx).await.unwrap();
+    {
+        let mut tasks = host.tasks_lock().await;
+        // a host task that suspends the scheduler on behalf of a task that doesn't exist
+        let options = TaskOptions::new_closure(&mut tasks, &host, |caller| {
+            Box::new(async move {
+                assert!(caller.tasks_lock().await.suspend_all(u32::MAX));
+                TaskPool::yield_now().await;
+                caller.tasks_lock().await.resume_all()?;
+                Ok(())
+            })
+        })
+        .unwrap()
+        .name("Host Task");
+        tasks
+            .spawn(options, &host.module(), &host.interface())
+            .await
+            .unwrap();
+    }
+
+    let reason = TaskPool::run_to_completion(&host).await;
+    let outcome = pros_simulator::finish(&host, reason);
+    assert!(
+        matches!(outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        outcome.reason
+    );
+    let violations = events
+        .lock()
+        .unwrap()
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::SchedulerInvariantViolated { invariant, .. } => Some(*invariant),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    // reported once, although it stayed broken for a few cycles
+    assert_eq!(violations, [SchedulerInvariant::SuspendBalance]);
+}
+
+#[test]
+fn poll_simulation() {
+    // no async runtime is needed to step a simulation
+    let robot_code = build_fixture("ticks");
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let (_messages, rx) = mpsc::channel();
+    let mut simulation = Simulation::new(
+        &robot_code,
+        default_options(),
+        {
+            let events = events.clone();
+            move |event| events.lock().unwrap().push(event)
+        },
+        rx,
+    )
+    .unwrap();
+    _ = std::fs::remove_file(robot_code);
+
+    let mut steps = 0;
+    let outcome = loop {
+        steps += 1;
+        if let StepResult::Stopped(outcome) = simulation.poll() {
+            break outcome;
+        }
+    };
+    assert!(
+        matches!(outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        outcome.reason
+    );
+    // the fixture yields every time it delays
+    assert!(steps > 10, "{steps}");
+    assert!(matches!(
+        events.lock().unwrap().last(),
+        Some(SimulatorEvent::RobotCodeFinished)
+    ));
+
+    // the threaded scheduler can't be stepped
+    let robot_code = build_fixture("ticks");
+    let (_messages, rx) = mpsc::channel();
+    let result = Simulation::new(&robot_code, default_options().threaded(true), |_| {}, rx);
+    _ = std::fs::remove_file(robot_code);
+    assert!(result.is_err());
+}
+
+#[test]
+fn timing_assertions() {
+    let robot_code = build_fixture("loop_period");
+    let (_messages, rx) = mpsc::channel();
+    let timeline = Timeline::record(&robot_code, default_options(), rx).unwrap();
+    _ = std::fs::remove_file(robot_code);
+    assert!(
+        matches!(timeline.outcome().reason, StopReason::Exited(0)),
+        "{:?}",
+        timeline.outcome().reason
+    );
+
+    let task = "User Initialization (PROS)";
+    assert_eq!(timeline.loop_periods(task).len(), 9);
+    timeline.assert_loop_period(task, Duration::from_millis(10), Duration::from_millis(5));
+    let done = |event: &SimulatorEvent| matches!(event, SimulatorEvent::ConsoleMessage(message) if message.text == "done\n");
+    let event = timeline.expect_event_within(150, done);
+    assert!((100..150).contains(&event.millis), "{}", event.millis);
+
+    // regressions fail the assertions
+    let slower = std::panic::catch_unwind(|| {
+        timeline.assert_loop_period(task, Duration::from_millis(2), Duration::from_millis(1))
+    });
+    assert!(slower.is_err());
+    let later = std::panic::catch_unwind(|| timeline.expect_event_within(50, done));
+    assert!(later.is_err());
+    let never = std::panic::catch_unwind(|| {
+        timeline.assert_loop_period("nope", Duration::ZERO, Duration::ZERO)
+    });
+    assert!(never.is_err());
+}
+
+#[test]
+fn step_response() {
+    let robot_code = build_fixture("motor_model");
+    let (_messages, rx) = mpsc::channel();
+    let options = default_options().smart_port(1, DeviceType::Motor);
+    let timeline = Timeline::record(&robot_code, options, rx).unwrap();
+    _ = std::fs::remove_file(robot_code);
+    assert!(
+        matches!(timeline.outcome().reason, StopReason::Exited(0)),
+        "{:?}",
+        timeline.outcome().reason
+    );
+
+    // the motor's speed rises exponentially with a time constant of 50 ms, taking 50 ms * ln 9
+    // to rise and 50 ms * ln 50 to settle, up until it's slowed down
+    let response = timeline.step_response(1).unwrap();
+    assert_eq!((response.initial, response.target), (0.0, 200.0));
+    let rise_time = response.rise_time.unwrap().as_millis();
+    assert!((100..=120).contains(&rise_time), "{response:?}");
+    assert_eq!(response.overshoot, 0.0);
+    let settling_time = response.settling_time.unwrap().as_millis();
+    assert!((185..=210).contains(&settling_time), "{response:?}");
+    assert_eq!(timeline.step_response(2), None);
+
+    // steps down are measured the same way, and overshoot is a fraction of the step's size
+    let samples = [
+        (0, 100.0),
+        (10, 80.0),
+        (20, 40.0),
+        (30, 10.0),
+        (40, 45.0),
+        (50, 50.5),
+    ];
+    let response = StepResponse::analyze(100.0, 50.0, &samples).unwrap();
+    assert_eq!(response.rise_time, Some(Duration::from_millis(10)));
+    assert!((response.overshoot - 0.8).abs() < 1e-9, "{response:?}");
+    assert_eq!(response.settling_time, Some(Duration::from_millis(50)));
+    // it never settled
+    let response = StepResponse::analyze(100.0, 50.0, &samples[..4]).unwrap();
+    assert_eq!(response.settling_time, None);
+    assert_eq!(StepResponse::analyze(1.0, 1.0, &samples), None);
+}
+
+#[tokio::test]
+async fn panic() {
+    let run = run_fixture("panic", []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Crashed(_)),
+        "{:?}",
+        run.outcome.reason
+    );
+    assert!(!run
+        .events
+        .iter()
+        .any(|event| matches!(event, SimulatorEvent::RobotCodeFinished)));
+
+    let panic = run
+        .events
+        .iter()
+        .find_map(|event| match event {
+            SimulatorEvent::RobotCodeError { panic, .. } => panic.clone(),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(panic.message, "boom");
+    assert_eq!(panic.file, "src/main.rs");
+    assert_eq!((panic.line, panic.column), (3, 5));
+}
+
+#[tokio::test]
+async fn unimplemented_motors() {
+    // motors aren't simulated yet, so using them should warn (once, not for every task) before
+    // the robot code starts
+    let run = run_fixture("motors", []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        run.outcome.reason
+    );
+    let warnings = run
+        .events
+        .iter()
+        .filter(
+            |event| matches!(event, SimulatorEvent::Warning(message) if message.contains("motor_move_velocity")),
+        )
+        .count();
+    assert_eq!(warnings, 1);
+}
+
+#[tokio::test]
+async fn plugins() {
+    let write_plugin = |name: &str, source: &str| {
+        let path = std::env::temp_dir().join(format!(
+            "pros-simulator-test-{}-{name}.wasm",
+            std::process::id()
+        ));
+        std::fs::write(&path, wat::parse_str(source).unwrap()).unwrap();
+        path
+    };
+    // each reading is further away, so the plugin has to keep its state between calls
+    let lidar = write_plugin(
+        "lidar",
+        r#"(module
+            (global $reads (mut i32) (i32.const 0))
+            (func (export "lidar_distance") (param $scale i32) (result i32)
+              (global.set $reads (i32.add (global.get $reads) (i32.const 1)))
+              (i32.mul (global.get $reads) (local.get $scale))))"#,
+    );
+    let run = run_fixture_with_options("plugin", default_options().plugin(&lidar), []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(30)),
+        "{:?}",
+        run.outcome.reason
+    );
+    assert!(!run
+        .events
+        .iter()
+        .any(|event| matches!(event, SimulatorEvent::Warning(_))));
+
+    let simulate_with = |plugin: &PathBuf| {
+        let options = default_options().plugin(&lidar).plugin(plugin);
+        async move {
+            let robot_code = build_fixture("plugin");
+            let (_tx, rx) = mpsc::channel();
+            let result = pros_simulator::simulate(&robot_code, options, |_| {}, rx).await;
+            _ = std::fs::remove_file(robot_code);
+            result.unwrap_err().to_string()
+        }
+    };
+    let delay = write_plugin("delay", r#"(module (func (export "delay") (param i32)))"#);
+    let err = simulate_with(&delay).await;
+    assert!(err.contains("exports `delay`"), "{err}");
+    let imports = write_plugin(
+        "imports",
+        r#"(module (import "env" "millis" (func (result i32))))"#,
+    );
+    let err = simulate_with(&imports).await;
+    assert!(err.contains("imports `env::millis`"), "{err}");
+
+    for plugin in [lidar, delay, imports] {
+        _ = std::fs::remove_file(plugin);
+    }
+}
+
+#[tokio::test]
+async fn plugin_limits() {
+    let write_plugin = |name: &str, source: &str| {
+        let path = std::env::temp_dir().join(format!(
+            "pros-simulator-test-{}-{name}.wasm",
+            std::process::id()
+        ));
+        std::fs::write(&path, wat::parse_str(source).unwrap()).unwrap();
+        path
+    };
+
+    // a plugin stuck in a loop is stopped by the timeout like robot code is
+    let looping = write_plugin(
+        "looping",
+        r#"(module
+            (func (export "lidar_distance") (param i32) (result i32)
+              (loop $forever (br $forever))
+              (unreachable)))"#,
+    );
+    let options = default_options()
+        .timeout(Timeout::RealTime(Duration::from_millis(200)))
+        .plugin(&looping);
+    let run = run_fixture_with_options("plugin", options, []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::TimedOut),
+        "{:?}",
+        run.outcome.reason
+    );
+
+    // and its memory counts against the memory limit
+    let growing = write_plugin(
+        "growing",
+        r#"(module
+            (memory 1)
+            (func (export "lidar_distance") (param i32) (result i32)
+              (memory.grow (i32.const 100))))"#,
+    );
+    let options = default_options().max_memory(1 << 20).plugin(&growing);
+    let run = run_fixture_with_options("plugin", options, []).await;
+    assert!(
+        matches!(
+            run.outcome.reason,
+            StopReason::LimitExceeded(ResourceLimit::Memory)
+        ),
+        "{:?}",
+        run.outcome.reason
+    );
+
+    for plugin in [looping, growing] {
+        _ = std::fs::remove_file(plugin);
+    }
+}
+
+#[tokio::test]
+async fn flash() {
+    // without a file, nothing is kept between runs
+    for _ in 0..2 {
+        let run = run_fixture("flash", []).await;
+        assert!(
+            matches!(run.outcome.reason, StopReason::Exited(1)),
+            "{:?}",
+            run.outcome.reason
+        );
+    }
+
+    let path = std::env::temp_dir().join(format!(
+        "pros-simulator-test-{}-flash.txt",
+        std::process::id()
+    ));
+    _ = std::fs::remove_file(&path);
+    for runs in 1..=2 {
+        let options = default_options().flash(&path);
+        let run = run_fixture_with_options("flash", options, []).await;
+        assert!(
+            matches!(run.outcome.reason, StopReason::Exited(code) if code == runs),
+            "{:?}",
+            run.outcome.reason
+        );
+    }
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "runs=02000000\n");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn resource_limits() {
+    // without limits, the fixture runs to the end
+    let run = run_fixture("resource_limits", []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        run.outcome.reason
+    );
+
+    let limit_exceeded = |events: &[SimulatorEvent]| {
+        events.iter().find_map(|event| match event {
+            SimulatorEvent::ResourceLimitExceeded { limit, max } => Some((*limit, *max)),
+            _ => None,
+        })
+    };
+
+    // the initialization task and 2 more
+    let options = default_options().max_tasks(3);
+    let run = run_fixture_with_options("resource_limits", options, []).await;
+    assert!(
+        matches!(
+            run.outcome.reason,
+            StopReason::LimitExceeded(ResourceLimit::Tasks)
+        ),
+        "{:?}",
+        run.outcome.reason
+    );
+    assert_eq!(limit_exceeded(&run.events), Some((ResourceLimit::Tasks, 3)));
+    let failures = run
+        .events
+        .iter()
+        .filter(|event| matches!(event, SimulatorEvent::TaskCreationFailed { .. }))
+        .count();
+    assert_eq!(failures, 3);
+
+    let options = default_options().max_memory(4 * 1024 * 1024);
+    let run = run_fixture_with_options("resource_limits", options, []).await;
+    assert!(
+        matches!(
+            run.outcome.reason,
+            StopReason::LimitExceeded(ResourceLimit::Memory)
+        ),
+        "{:?}",
+        run.outcome.reason
+    );
+    assert_eq!(
+        limit_exceeded(&run.events),
+        Some((ResourceLimit::Memory, 4 * 1024 * 1024))
+    );
+    assert!(!run
+        .events
+        .iter()
+        .any(|event| matches!(event, SimulatorEvent::RobotCodeError { .. })));
+
+    // writes 30 lines without yielding
+    let options = default_options().max_event_rate(20);
+    let run = run_fixture_with_options("serial", options, []).await;
+    assert!(
+        matches!(
+            run.outcome.reason,
+            StopReason::LimitExceeded(ResourceLimit::EventRate)
+        ),
+        "{:?}",
+        run.outcome.reason
+    );
+    assert_eq!(
+        limit_exceeded(&run.events),
+        Some((ResourceLimit::EventRate, 20))
+    );
+}
+
+#[tokio::test]
+async fn api_coverage() {
+    let output = std::env::temp_dir().join(format!(
+        "pros-simulator-test-{}-coverage.txt",
+        std::process::id()
+    ));
+    let options = default_options().coverage_report(&output);
+    let run = run_fixture_with_options("tasks", options, []).await;
+    let coverage = run
+        .events
+        .iter()
+        .find_map(|event| match event {
+            SimulatorEvent::ApiCoverage(coverage) => Some(coverage),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(coverage.calls["task_create"], 1);
+    assert_eq!(coverage.calls["puts"], 2);
+    assert!(coverage.calls["delay"] > 0);
+    assert!(coverage.unimplemented.is_empty());
+
+    let report = std::fs::read_to_string(&output).unwrap();
+    _ = std::fs::remove_file(&output);
+    assert!(
+        report.lines().any(|line| line.trim() == "2  puts"),
+        "{report}"
+    );
+
+    let run = run_fixture("motors", []).await;
+    let coverage = run
+        .events
+        .iter()
+        .find_map(|event| match event {
+            SimulatorEvent::ApiCoverage(coverage) => Some(coverage),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(coverage.unimplemented, ["motor_move_velocity"]);
+    assert_eq!(coverage.calls["motor_move_velocity"], 0);
+}
+
+#[tokio::test]
+async fn memory_faults() {
+    // every host function should fail with EFAULT when given a pointer outside of memory
+    let run = run_fixture("memory_faults", []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0b111)),
+        "{:?}",
+        run.outcome.reason
+    );
+}
+
+#[tokio::test]
+async fn strings() {
+    let run = run_fixture("strings", []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        run.outcome.reason
+    );
+
+    let console = run.console();
+    let lines = console.lines().collect::<Vec<_>>();
+    assert_eq!(lines[0], "bad \u{FFFD}\u{FFFD} bytes");
+    assert_eq!(lines[1].len(), pros_simulator::extension::MAX_C_STR_LEN);
+
+    let warnings = run
+        .events
+        .iter()
+        .filter(|event| matches!(event, SimulatorEvent::Warning(_)))
+        .count();
+    assert_eq!(warnings, 2);
+}
+
+#[tokio::test]
+async fn atomics() {
+    // a task blocked on memory.atomic.wait32 shouldn't stop other tasks from running
+    let run = run_fixture("atomics", []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(111)),
+        "{:?}",
+        run.outcome.reason
+    );
+}
+
+#[tokio::test]
+async fn atomic_wait_ticks() {
+    let options = default_options().test_build(true);
+    let run = run_fixture_with_options("atomic_wait_ticks", options, []).await;
+    // exits early with the child's wait result if it didn't time out with the clock
+    assert!(
+        matches!(&run.outcome.reason, StopReason::Crashed(err) if err.root_cause().to_string().contains("suspended")),
+        "{:?}",
+        run.outcome.reason
+    );
+    assert_eq!(run.console(), "advanced\n");
+}
+
+#[tokio::test]
+async fn sim_random() {
+    let exit_code = |run: common::Run| match run.outcome.reason {
+        StopReason::Exited(code) => code,
+        reason => panic!("{reason:?}"),
+    };
+    let random = |seed| async move {
+        let options = default_options().deterministic(seed);
+        exit_code(run_fixture_with_options("sim_random", options, []).await)
+    };
+
+    // the same seed gives the same numbers
+    let first = random(1).await;
+    assert_eq!(random(1).await, first);
+    // and a different seed different ones
+    assert_ne!(random(2).await, first);
+}
+
+#[tokio::test]
+async fn jitter() {
+    let run = run_fixture("jitter", []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        run.outcome.reason
+    );
+    let round_robin = run.console();
+    assert_eq!(round_robin, "a\nb\n".repeat(5));
+
+    let mut perturbed = false;
+    for seed in 0..10 {
+        let options = default_options()
+            .deterministic(seed)
+            .jitter(Duration::from_millis(2));
+        let first = run_fixture_with_options("jitter", options.clone(), []).await;
+        let second = run_fixture_with_options("jitter", options, []).await;
+        assert!(
+            matches!(first.outcome.reason, StopReason::Exited(0)),
+            "{:?}",
+            first.outcome.reason
+        );
+        // the same seed gives the same schedule
+        assert_eq!(first.console(), second.console());
+        perturbed |= first.console() != round_robin;
+    }
+    assert!(perturbed);
+}
+
+#[tokio::test]
+async fn profile() {
+    let output = std::env::temp_dir().join(format!(
+        "pros-simulator-test-{}-profile.folded",
+        std::process::id()
+    ));
+    let options = default_options().profile(&output);
+    let run = run_fixture_with_options("profile", options, []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        run.outcome.reason
+    );
+
+    let profile = std::fs::read_to_string(&output).unwrap();
+    _ = std::fs::remove_file(&output);
+    let hot_samples = profile
+        .lines()
+        .filter_map(|line| line.strip_prefix("initialize;hot "))
+        .map(|count| count.parse::<u64>().unwrap())
+        .sum::<u64>();
+    assert!(hot_samples > 10, "{profile}");
+}
+
+#[tokio::test]
+async fn timeout() {
+    // robot code that never yields is interrupted once it runs out of time
+    for (timeout, threaded) in [
+        (Timeout::RealTime(Duration::from_millis(200)), false),
+        (Timeout::Simulated(Duration::from_millis(200)), false),
+        (Timeout::RealTime(Duration::from_millis(200)), true),
+    ] {
+        let options = SimulatorOptions::new().timeout(timeout).threaded(threaded);
+        let start = Instant::now();
+        let run = run_fixture_with_options("spin", options, []).await;
+        assert!(
+            matches!(run.outcome.reason, StopReason::TimedOut),
+            "{timeout:?}, threaded: {threaded}: {:?}",
+            run.outcome.reason
+        );
+        assert!(start.elapsed() < Duration::from_secs(5), "{timeout:?}");
+    }
+}
+
+#[tokio::test]
+async fn stop_signal() {
+    // robot code that never yields is interrupted once it's asked to stop
+    for threaded in [false, true] {
+        let stop = Arc::new(AtomicBool::new(false));
+        std::thread::spawn({
+            let stop = stop.clone();
+            move || {
+                std::thread::sleep(Duration::from_millis(200));
+                stop.store(true, Ordering::Release);
+            }
+        });
+        let options = SimulatorOptions::new().stop_signal(stop).threaded(threaded);
+        let start = Instant::now();
+        let run = run_fixture_with_options("spin", options, []).await;
+        assert!(
+            matches!(run.outcome.reason, StopReason::Cancelled),
+            "threaded: {threaded}: {:?}",
+            run.outcome.reason
+        );
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
+
+#[tokio::test]
+async fn threaded() {
+    let options = default_options().threaded(true);
+
+    let run = run_fixture_with_options("parallel", options.clone(), []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        run.outcome.reason
+    );
+    assert_eq!(run.console(), "in parallel\n");
+
+    let run = run_fixture_with_options("tasks", options.clone(), []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        run.outcome.reason
+    );
+    assert_eq!(run.console(), "from child\nfrom parent\n");
+
+    let run = run_fixture_with_options("mutexes", options.clone(), []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(1)),
+        "{:?}",
+        run.outcome.reason
+    );
+
+    // a task that's waiting out another task's critical section is woken when it ends
+    let run = run_fixture_with_options("threaded_suspend_all", options, []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        run.outcome.reason
+    );
+}
+
+#[tokio::test]
+async fn instance_pool() {
+    // the system daemon, `initialize` and its child
+    let options = default_options().instance_pool(3);
+    let run = run_fixture_with_options("tasks", options, []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        run.outcome.reason
+    );
+    assert_eq!(run.console(), "from child\nfrom parent\n");
+
+    // creating a task fails once the pool is full, but the task that tried carries on
+    let options = default_options().instance_pool(2);
+    let run = run_fixture_with_options("task_create_failure", options, []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(2)),
+        "{:?}",
+        run.outcome.reason
+    );
+    assert!(
+        matches!(
+            run.events.iter().rev().find(|event| matches!(event, SimulatorEvent::TaskCreationFailed { .. })),
+            Some(SimulatorEvent::TaskCreationFailed { reason, errno: pros_sys::ENOMEM, .. })
+                if reason.contains("instance")
+        ),
+        "{:?}",
+        run.events
+    );
+}
+
+#[tokio::test]
+async fn no_function_table() {
+    let run = run_fixture("no_function_table", []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        run.outcome.reason
+    );
+    assert!(run.events.iter().any(|event| matches!(
+        event,
+        SimulatorEvent::TaskCreationFailed { reason, errno: pros_sys::EINVAL, .. }
+            if reason.contains("doesn't export its function table")
+    )));
+}
+
+#[tokio::test]
+async fn time_slicing() {
+    let run = run_fixture("time_slicing", []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        run.outcome.reason
+    );
+}
+
+#[tokio::test]
+async fn task_create_failure() {
+    let run = run_fixture("task_create_failure", []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        run.outcome.reason
+    );
+    let failures = run
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::TaskCreationFailed {
+                reason,
+                errno,
+                task_name,
+                ..
+            } => {
+                assert_eq!(task_name, "User Initialization (PROS)");
+                Some((reason.as_str(), *errno))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        failures,
+        [
+            ("priority 0 isn't between 1 and 16", pros_sys::EINVAL),
+            (
+                "entrypoint 100 is out of bounds of the function table",
+                pros_sys::EINVAL
+            ),
+            ("entrypoint 3 isn't a function", pros_sys::EINVAL),
+            (
+                "entrypoint 2 doesn't take a single pointer argument and return nothing",
+                pros_sys::EINVAL
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn event_queue() {
+    let robot_code = build_fixture("motor_update_rate");
+    let options = default_options()
+        .smart_port(1, DeviceType::Motor)
+        .channel_stats(1000);
+
+    // a frontend that doesn't read any events until the simulation has had time to finish
+    let read_late = |policy| {
+        let (_messages, rx) = mpsc::channel();
+        let options = options.clone().event_queue(1, policy);
+        let stream = start_simulator(robot_code.clone(), options, false, rx);
+        async move {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            stream
+                .map(|event| event.unwrap().inner)
+                .collect::<Vec<_>>()
+                .await
+        }
+    };
+    let motor_updates = |events: &[SimulatorEvent]| {
+        events
+            .iter()
+            .filter(|event| matches!(event, SimulatorEvent::MotorUpdated { .. }))
+            .count()
+    };
+    let last_stats = |events: &[SimulatorEvent]| {
+        events
+            .iter()
+            .rev()
+            .find_map(|event| match event {
+                SimulatorEvent::ChannelStats(stats) => Some(*stats),
+                _ => None,
+            })
+            .unwrap()
+    };
+
+    // nothing is lost, because the simulation waits for the frontend
+    let events = read_late(OverflowPolicy::Pause).await;
+    let mut voltages = events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::MotorUpdated { millivolts, .. } => Some(*millivolts),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    voltages.dedup();
+    assert_eq!(voltages, (1..=50).map(|i| i * 100).collect::<Vec<_>>());
+    assert!(events.contains(&SimulatorEvent::RobotCodeFinished));
+
+    // only the latest motor update is kept
+    let events = read_late(OverflowPolicy::Coalesce).await;
+    assert!(events.iter().any(|event| matches!(
+        event,
+        SimulatorEvent::MotorUpdated {
+            port: 1,
+            millivolts: 5000,
+            ..
+        }
+    )));
+    assert_eq!(motor_updates(&events), 1);
+    assert!(last_stats(&events).coalesced > 0);
+    assert!(events.contains(&SimulatorEvent::RobotCodeFinished));
+
+    let events = read_late(OverflowPolicy::Drop).await;
+    assert_eq!(motor_updates(&events), 0);
+    assert!(events.contains(&SimulatorEvent::RobotCodeFinished));
+
+    _ = std::fs::remove_file(robot_code);
+}
+
+#[tokio::test]
+async fn read_memory() {
+    let read = |location, len| SimulatorMessage::ReadMemory { location, len };
+    let run = run_fixture(
+        "read_memory",
+        [
+            read(MemoryLocation::Symbol("ODOMETRY_STATE".into()), 4),
+            read(MemoryLocation::Address(2049), 2),
+            read(MemoryLocation::Symbol("MISSING".into()), 4),
+            read(MemoryLocation::Address(u32::MAX), 4),
+        ],
+    )
+    .await;
+    assert!(run.outcome.is_success(), "{:?}", run.outcome.reason);
+    assert!(run.events.contains(&SimulatorEvent::MemoryValue {
+        location: MemoryLocation::Symbol("ODOMETRY_STATE".into()),
+        address: 2048,
+        bytes: vec![42, 0, 0, 0],
+    }));
+    assert!(run.events.contains(&SimulatorEvent::MemoryValue {
+        location: MemoryLocation::Address(2049),
+        address: 2049,
+        bytes: vec![0, 0],
+    }));
+    let warnings = run
+        .events
+        .iter()
+        .filter(|event| matches!(event, SimulatorEvent::Warning(message) if message.starts_with("Couldn't read")))
+        .count();
+    assert_eq!(warnings, 2);
+}
+
+#[tokio::test]
+async fn write_memory() {
+    let write = |location, bytes| SimulatorMessage::WriteMemory { location, bytes };
+    let messages = || {
+        [
+            write(MemoryLocation::Symbol("DRIVE_KP".into()), vec![7, 0, 0, 0]),
+            write(MemoryLocation::Symbol("MISSING".into()), vec![0]),
+            write(MemoryLocation::Address(u32::MAX), vec![0]),
+        ]
+    };
+    let warnings = |run: &common::Run| {
+        run.events
+            .iter()
+            .filter(|event| matches!(event, SimulatorEvent::Warning(message) if message.starts_with("Couldn't write")))
+            .count()
+    };
+
+    let options = default_options().test_build(true);
+    let run = run_fixture_with_options("write_memory", options, messages()).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(7)),
+        "{:?}",
+        run.outcome.reason
+    );
+    assert_eq!(warnings(&run), 2);
+
+    // only test builds can be written to
+    let run = run_fixture("write_memory", messages()).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(1)),
+        "{:?}",
+        run.outcome.reason
+    );
+    assert_eq!(warnings(&run), 3);
+}
+
+#[tokio::test]
+async fn sweep() {
+    let robot_code = build_fixture("sweep");
+    let final_x = |telemetry: &[Telemetry]| {
+        let pose = telemetry.last().and_then(|snapshot| snapshot.pose);
+        pose.map_or(f64::NAN, |pose| pose.x)
+    };
+    let sweep = Sweep::new(
+        &robot_code,
+        MemoryLocation::Symbol("DRIVE_KP".into()),
+        ValueType::F32,
+    )
+    .options(default_options());
+
+    let runs = sweep.clone().range(0.5, 2.0, 4).run(final_x).await.unwrap();
+    let values = runs.iter().map(|run| run.value).collect::<Vec<_>>();
+    assert_eq!(values, [0.5, 1.0, 1.5, 2.0]);
+    for run in &runs {
+        assert!(run.outcome.is_success(), "{:?}", run.outcome.reason);
+        assert!(run.warnings.is_empty(), "{:?}", run.warnings);
+        assert_eq!(run.metric, run.value);
+    }
+
+    let runs = Sweep::new(
+        &robot_code,
+        MemoryLocation::Symbol("MISSING".into()),
+        ValueType::F32,
+    )
+    .options(default_options())
+    .values([3.0])
+    .run(final_x)
+    .await
+    .unwrap();
+    // the constant keeps its original value
+    assert_eq!(runs[0].metric, 1.0);
+    assert!(runs[0].warnings[0].starts_with("Couldn't write"));
+
+    assert!(sweep
+        .range(0.0, 1.0, 0)
+        .run(final_x)
+        .await
+        .unwrap()
+        .is_empty());
+    _ = std::fs::remove_file(robot_code);
+}
+
+#[tokio::test]
+async fn watch() {
+    let watch = |location, rate| SimulatorMessage::Watch {
+        location,
+        value_type: ValueType::U32,
+        rate,
+    };
+    let run = run_fixture(
+        "watch",
+        [
+            watch(MemoryLocation::Symbol("COUNTER".into()), 200),
+            watch(MemoryLocation::Address(2048), 200),
+            // stops watching the address again
+            watch(MemoryLocation::Address(2048), 0),
+            watch(MemoryLocation::Symbol("MISSING".into()), 200),
+        ],
+    )
+    .await;
+    assert!(run.outcome.is_success(), "{:?}", run.outcome.reason);
+
+    let values = run
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::WatchValue {
+                location,
+                value: WatchValue::UInt(value),
+                ..
+            } => {
+                assert_eq!(*location, MemoryLocation::Symbol("COUNTER".into()));
+                Some(*value)
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert!(values.len() > 5, "{values:?}");
+    assert!(
+        values.windows(2).all(|pair| pair[0] <= pair[1]),
+        "{values:?}"
+    );
+    assert!(values.last().unwrap() > values.first().unwrap());
+
+    let warnings = run
+        .events
+        .iter()
+        .filter(|event| {
+            matches!(event, SimulatorEvent::Warning(message) if message.contains("MISSING"))
+        })
+        .count();
+    assert_eq!(warnings, 1);
+}
+
+#[tokio::test]
+async fn breakpoints() {
+    let mut watched_while_paused = None::<u32>;
+    let run = run_fixture_interactive(
+        "breakpoint",
+        vec![
+            SimulatorMessage::Watch {
+                location: MemoryLocation::Symbol("COUNTER".into()),
+                value_type: ValueType::U32,
+                rate: 500,
+            },
+            SimulatorMessage::BreakOnCall {
+                api: "motor_move".into(),
+                condition: Some(CallCondition { arg: 0, equals: 5 }),
+            },
+        ],
+        move |event| match event {
+            SimulatorEvent::Breakpoint { .. } => {
+                watched_while_paused = Some(0);
+                vec![]
+            }
+            // resume once the variable has been read a few times
+            SimulatorEvent::WatchValue { .. } => match &mut watched_while_paused {
+                Some(5) => {
+                    watched_while_paused = None;
+                    vec![SimulatorMessage::Resume]
+                }
+                Some(count) => {
+                    *count += 1;
+                    vec![]
+                }
+                None => vec![],
+            },
+            _ => vec![],
+        },
+    )
+    .await;
+    assert!(run.outcome.is_success(), "{:?}", run.outcome.reason);
+
+    let breakpoints = run
+        .events
+        .iter()
+        .enumerate()
+        .filter_map(|(index, event)| match event {
+            SimulatorEvent::Breakpoint {
+                api,
+                args,
+                task_name,
+                ..
+            } => Some((index, api, args, task_name)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(breakpoints.len(), 1);
+    let (index, api, args, task_name) = breakpoints[0];
+    assert_eq!(api, "motor_move");
+    assert_eq!(args, &[5, 100]);
+    assert_eq!(task_name, "User Initialization (PROS)");
+
+    // the robot code didn't run while it was paused
+    let paused_values = run.events[index..]
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::WatchValue { value, .. } => Some(*value),
+            _ => None,
+        })
+        .take(6)
+        .collect::<Vec<_>>();
+    assert_eq!(paused_values, vec![WatchValue::UInt(10); 6]);
+}
+
+#[tokio::test]
+async fn sim_assert() {
+    let run = run_fixture("sim_assert", []).await;
+    assert!(run.outcome.is_success(), "{:?}", run.outcome.reason);
+    let failures = run
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::AssertionFailed {
+                message, task_name, ..
+            } => Some((message.as_str(), task_name.as_str())),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        failures,
+        [("heading is within 1 degree", "User Initialization (PROS)")]
+    );
+
+    let options = default_options().strict(WarningKind::FailedAssertion);
+    let run = run_fixture_with_options("sim_assert", options, []).await;
+    assert!(
+        matches!(
+            run.outcome.reason,
+            StopReason::StrictWarning(WarningKind::FailedAssertion)
+        ),
+        "{:?}",
+        run.outcome.reason
+    );
+}
+
+#[tokio::test]
+async fn sim_log() {
+    let run = run_fixture("sim_log", []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(1)),
+        "{:?}",
+        run.outcome.reason
+    );
+    let logs = run
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::Log {
+                level,
+                message,
+                task_name,
+                ..
+            } => {
+                assert_eq!(task_name, "User Initialization (PROS)");
+                Some((*level, message.as_str()))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        logs,
+        [
+            (LogLevel::Warn, "battery is low"),
+            (LogLevel::Debug, "odometry reset"),
+        ]
+    );
+    assert!(run.events.iter().any(|event| matches!(
+        event,
+        SimulatorEvent::InvalidArgument { function, value: 9, errno, .. }
+            if function == "sim_log" && *errno == pros_sys::EINVAL
+    )));
+}
+
+#[tokio::test]
+async fn sim_emit_event() {
+    let run = run_fixture("sim_emit_event", []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(1)),
+        "{:?}",
+        run.outcome.reason
+    );
+    let events = run
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::Custom { tag, bytes } => Some((tag.as_str(), bytes.as_slice())),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(events, [("odom", &[1, 2, 3, 4][..]), ("state", &[][..])]);
+    assert!(run.events.iter().any(|event| matches!(
+        event,
+        SimulatorEvent::InvalidArgument { function, errno, .. }
+            if function == "sim_emit_event" && *errno == pros_sys::EFAULT
+    )));
+}
+
+#[tokio::test]
+async fn sim_poll_message() {
+    let custom = |tag: &str, bytes: &[u8]| SimulatorMessage::Custom {
+        tag: tag.into(),
+        bytes: bytes.to_vec(),
+    };
+    let run = run_fixture(
+        "sim_poll_message",
+        [
+            custom("auton", &[1, 2, 3, 4, 5]),
+            custom("tuning", &[7]),
+            custom("auton", &[9]),
+        ],
+    )
+    .await;
+    // 5 and 1 byte messages, then none left, as `tuning` messages are kept separately
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(905)),
+        "{:?}",
+        run.outcome.reason
+    );
+}
+
+#[tokio::test]
+async fn scoring_zones() {
+    let options = default_options()
+        .test_build(true)
+        .scoring_zone(ScoringZone {
+            name: "win point".into(),
+            shape: ZoneShape::Rectangle {
+                min_x: 20.0,
+                min_y: -5.0,
+                max_x: 30.0,
+                max_y: 5.0,
+            },
+            points: 10,
+            rule: ScoringRule::Enter,
+            autonomous_only: false,
+        })
+        .scoring_zone(ScoringZone {
+            name: "park".into(),
+            shape: ZoneShape::Circle {
+                x: 24.0,
+                y: 0.0,
+                radius: 6.0,
+            },
+            points: 5,
+            rule: ScoringRule::Inside,
+            autonomous_only: false,
+        });
+    let invalid = SimulatorMessage::AddScoringZone(ScoringZone {
+        name: "nowhere".into(),
+        shape: ZoneShape::Circle {
+            x: 0.0,
+            y: 0.0,
+            radius: -1.0,
+        },
+        points: 1,
+        rule: ScoringRule::Enter,
+        autonomous_only: false,
+    });
+    let run = run_fixture_with_options("scoring", options, [invalid]).await;
+    assert!(run.outcome.is_success(), "{:?}", run.outcome.reason);
+
+    let changes = run
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::ScoreChanged {
+                zone,
+                points,
+                score,
+                ..
+            } => Some((zone.as_str(), *points, *score)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        changes,
+        [
+            ("win point", 10, 10),
+            ("park", 5, 15),
+            ("park", -5, 10),
+            ("park", 5, 15),
+        ]
+    );
+    assert!(run.events.iter().any(|event| matches!(
+        event,
+        SimulatorEvent::Warning(message) if message.contains("its radius is negative")
+    )));
+}
+
+#[tokio::test]
+async fn game_objects() {
+    let object = |name: &str, x, y, radius, fixed| GameObject {
+        name: name.into(),
+        kind: "ring".into(),
+        x,
+        y,
+        radius,
+        fixed,
+        held_by: None,
+    };
+    let options = default_options()
+        .test_build(true)
+        .robot_radius(9.0)
+        .game_object(object("ring", 30.0, 0.0, 2.0, false))
+        .game_object(object("goal", 24.0, 0.0, 3.0, true));
+    let ball = SimulatorMessage::AddGameObject(object("ball", 72.0, 3.0, 2.0, false));
+    // reuses the poses of the scoring test: (24, 0), then (72, 0), then (24, 0) again
+    let run = run_fixture_with_options("scoring", options, [ball]).await;
+    assert!(run.outcome.is_success(), "{:?}", run.outcome.reason);
+
+    let updates = run
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::GameObjectUpdated(object) => {
+                Some((object.name.as_str(), object.x, object.y))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        updates,
+        [
+            ("ring", 30.0, 0.0),
+            ("goal", 24.0, 0.0),
+            ("ball", 72.0, 3.0),
+            // pushed until it touches the robot, while the fixed goal stays put
+            ("ring", 35.0, 0.0),
+            ("ball", 72.0, 11.0),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn mechanisms() {
+    let options = default_options()
+        .test_build(true)
+        .smart_port(1, DeviceType::Motor)
+        .smart_port(2, DeviceType::Motor)
+        .game_object(GameObject {
+            name: "ring".into(),
+            kind: "ring".into(),
+            x: 0.0,
+            y: 12.0,
+            radius: 2.0,
+            fixed: false,
+            held_by: None,
+        })
+        .mechanism(Mechanism {
+            name: "intake".into(),
+            port: 1,
+            kind: MechanismKind::Intake {
+                reach: 2.0,
+                capacity: 1,
+            },
+        })
+        .mechanism(Mechanism {
+            name: "lift".into(),
+            port: 2,
+            kind: MechanismKind::Lift {
+                max_height: 1.0,
+                speed: 20.0,
+            },
+        });
+    let run = run_fixture_with_options("mechanisms", options, []).await;
+    assert!(run.outcome.is_success(), "{:?}", run.outcome.reason);
+
+    let states = |name: &str| {
+        run.events
+            .iter()
+            .filter_map(|event| match event {
+                SimulatorEvent::MechanismUpdated(state) if state.name == name => Some(state),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+    let intake = states("intake")
+        .iter()
+        .map(|state| state.held.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(intake, [vec!["ring".to_string()], vec![]]);
+    let heights = states("lift")
+        .iter()
+        .map(|state| state.height)
+        .collect::<Vec<_>>();
+    assert!(
+        heights.windows(2).all(|pair| pair[0] < pair[1]),
+        "{heights:?}"
+    );
+    assert_eq!(heights.last(), Some(&1.0));
+
+    let ring = run
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::GameObjectUpdated(object) => {
+                Some((object.x, object.y, object.held_by.as_deref()))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    // let out touching the front of the robot
+    assert_eq!(
+        ring,
+        [
+            (0.0, 12.0, None),
+            (0.0, 0.0, Some("intake")),
+            (0.0, 11.0, None)
+        ]
+    );
+}
+
+#[tokio::test]
+async fn sensor_traces() {
+    let run =
+        run_fixture_with_options("sensor_traces", default_options().test_build(true), []).await;
+    assert!(run.outcome.is_success(), "{:?}", run.outcome.reason);
+    let failures = run
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::AssertionFailed { message, .. } => Some(message),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert!(failures.is_empty(), "{failures:?}");
+}
+
+#[tokio::test]
+async fn unit_tests() {
+    let options = default_options().run_tests("sim_test_*");
+    let run = run_fixture_with_options("unit_tests", options, []).await;
+    assert!(
+        matches!(
+            run.outcome.reason,
+            StopReason::TestsFailed {
+                failed: 2,
+                total: 4
+            }
+        ),
+        "{:?}",
+        run.outcome.reason
+    );
+
+    let started = run
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::TestStarted { name } => Some(name.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        started,
+        [
+            "sim_test_crash",
+            "sim_test_delay",
+            "sim_test_fail",
+            "sim_test_pass"
+        ]
+    );
+
+    let results = run
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::TestFinished(result) => Some((result.name.as_str(), result)),
+            _ => None,
+        })
+        .collect::<BTreeMap<_, _>>();
+    let crash = results["sim_test_crash"];
+    assert!(!crash.passed);
+    assert!(
+        crash.error.as_ref().unwrap().contains("unreachable"),
+        "{crash:?}"
+    );
+    let delay = results["sim_test_delay"];
+    assert!(delay.passed);
+    assert!(delay.millis >= 50, "{delay:?}");
+    let fail = results["sim_test_fail"];
+    assert!(!fail.passed);
+    assert_eq!((fail.failed_assertions, fail.error.as_deref()), (2, None));
+    let pass = results["sim_test_pass"];
+    assert!(pass.passed);
+    assert_eq!(pass.failed_assertions, 0);
+
+    let robot_code = build_fixture("unit_tests");
+    let tests = pros_simulator::list_tests(&robot_code, "sim_test_*").unwrap();
+    assert_eq!(tests, started);
+    // a pattern without wildcards runs just the test with that name
+    let tests = pros_simulator::list_tests(&robot_code, "sim_test_pass").unwrap();
+    assert_eq!(tests, ["sim_test_pass"]);
+}
+
+#[tokio::test]
+async fn copy_rect_bounds() {
+    let run = run_fixture("copy_rect_bounds", []).await;
+    // a rectangle that wide would overflow if its size were computed before clipping it, but
+    // its visible part starts past the end of memory
+    assert!(
+        matches!(&run.outcome.reason, StopReason::Crashed(err) if err.root_cause().to_string().contains("outside of robot code memory")),
+        "{:?}",
+        run.outcome.reason
+    );
+    let regions = run
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::ScreenUpdated(region) => Some(region),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let [copied] = regions[..] else {
+        panic!("{regions:?}");
+    };
+    assert_eq!(
+        (copied.x, copied.y, copied.width, copied.height),
+        (0, 0, 2, 1)
+    );
+    assert_eq!(copied.rgb().unwrap(), [0x33, 0x33, 0x33, 0x44, 0x44, 0x44]);
+}
+
+#[tokio::test]
+async fn screen() {
+    let run = run_fixture_with_options(
+        "screen",
+        default_options().test_build(true),
+        [SimulatorMessage::ScreenTouch {
+            x: 50,
+            y: 60,
+            pressed: true,
+        }],
+    )
+    .await;
+    assert!(run.outcome.is_success(), "{:?}", run.outcome.reason);
+    assert!(!run
+        .events
+        .iter()
+        .any(|event| matches!(event, SimulatorEvent::AssertionFailed { .. })));
+
+    let regions = run
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::ScreenUpdated(region) => Some(region),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(regions.len(), 2, "{regions:?}");
+    let filled = regions[0];
+    assert_eq!(
+        (filled.x, filled.y, filled.width, filled.height),
+        (0, 0, 10, 10)
+    );
+    assert_eq!(filled.rgb().unwrap(), [0xff, 0x00, 0x00].repeat(100));
+    let copied = regions[1];
+    assert_eq!(
+        (copied.x, copied.y, copied.width, copied.height),
+        (100, 100, 2, 1)
+    );
+    assert_eq!(copied.rgb().unwrap(), [0x11, 0x22, 0x33, 0x44, 0x55, 0x66]);
+}
+
+#[tokio::test]
+async fn sim_profile() {
+    let path = std::env::temp_dir().join(format!(
+        "pros-simulator-test-{}-trace.json",
+        std::process::id()
+    ));
+    let run =
+        run_fixture_with_options("sim_profile", default_options().chrome_trace(&path), []).await;
+    // ending `missing` failed
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(5)),
+        "{:?}",
+        run.outcome.reason
+    );
+    let trace: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let events = trace["traceEvents"].as_array().unwrap();
+    let span = |name: &str| {
+        let span = events
+            .iter()
+            .find(|event| event["ph"] == "X" && event["name"] == name)
+            .unwrap_or_else(|| panic!("no `{name}` span in {events:?}"));
+        (span["ts"].as_u64().unwrap(), span["dur"].as_u64().unwrap())
+    };
+    let (frame_start, frame_duration) = span("frame");
+    let (draw_start, draw_duration) = span("draw");
+    let (late_start, late_duration) = span("late");
+    assert!(frame_duration >= 5000, "{frame_duration}");
+    assert!(draw_start >= frame_start + 2000);
+    assert!(draw_duration >= 3000, "{draw_duration}");
+    assert_eq!(late_start, frame_start + frame_duration);
+    assert_eq!(late_duration, 0);
+    span("tail");
+    assert!(events
+        .iter()
+        .any(|event| event["ph"] == "M" && event["args"]["name"] == "User Initialization (PROS)"));
+
+    // without a trace to write, nothing is recorded, so ending `missing` succeeds
+    let run = run_fixture("sim_profile", []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(7)),
+        "{:?}",
+        run.outcome.reason
+    );
+}
+
+#[tokio::test]
+async fn capabilities() {
+    let run =
+        run_fixture_with_options("capabilities", default_options().deterministic(1), []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(3)),
+        "{:?}",
+        run.outcome.reason
+    );
+}
+
+#[tokio::test]
+async fn test_build() {
+    let options = default_options()
+        .test_build(true)
+        .test_config("test", "drive");
+    let run = run_fixture_with_options("test_build", options, []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0)),
+        "{:?}",
+        run.outcome.reason
+    );
+    assert_eq!(run.console(), "advanced\ndrive\ndr\nunset\n");
+    assert!(run.events.contains(&SimulatorEvent::PoseSet(Pose {
+        x: 24.0,
+        y: -36.0,
+        heading: 90.0,
+    })));
+
+    let run = run_fixture("test_build", []).await;
+    assert!(
+        matches!(&run.outcome.reason, StopReason::Crashed(err) if err.root_cause().to_string().contains("test build")),
+        "{:?}",
+        run.outcome.reason
+    );
+    assert!(!run
+        .events
+        .iter()
+        .any(|event| matches!(event, SimulatorEvent::PoseSet(_))));
+}
+
+#[tokio::test]
+async fn check_errno() {
+    let errno_warnings = |run: &common::Run| {
+        run.events
+            .iter()
+            .filter(|event| {
+                matches!(event, SimulatorEvent::Warning(warning) if warning.contains("errno"))
+            })
+            .count()
+    };
+
+    let run = run_fixture("errno_check", []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(pros_sys::ENXIO)),
+        "{:?}",
+        run.outcome.reason
+    );
+    assert_eq!(errno_warnings(&run), 0);
+
+    let options = default_options().check_errno(true);
+    let run = run_fixture_with_options("errno_check", options, []).await;
+    assert_eq!(errno_warnings(&run), 1, "{:?}", run.events);
+
+    let options = default_options()
+        .check_errno(true)
+        .strict(WarningKind::UnsetErrno);
+    let run = run_fixture_with_options("errno_check", options, []).await;
+    assert!(
+        matches!(
+            run.outcome.reason,
+            StopReason::StrictWarning(WarningKind::UnsetErrno)
+        ),
+        "{:?}",
+        run.outcome.reason
+    );
+}
+
+#[tokio::test]
+async fn start_kind() {
+    let run = run_fixture("start_kind", []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(3)),
+        "{:?}",
+        run.outcome.reason
+    );
+
+    let options = default_options().start_kind(StartKind::Hot);
+    let run = run_fixture_with_options("start_kind", options, []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(6)),
+        "{:?}",
+        run.outcome.reason
+    );
+}
+
+#[tokio::test]
+async fn canaries() {
+    let corruption_warnings = |run: &common::Run| {
+        run.events
+            .iter()
+            .filter_map(|event| match event {
+                SimulatorEvent::Warning(warning) if warning.contains("out of bounds") => {
+                    Some(warning.clone())
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let run = run_fixture("canaries", []).await;
+    assert!(run.outcome.is_success(), "{:?}", run.outcome.reason);
+    assert_eq!(corruption_warnings(&run), Vec::<String>::new());
+
+    let run = run_fixture_with_options("canaries", default_options().canaries(true), []).await;
+    assert!(run.outcome.is_success(), "{:?}", run.outcome.reason);
+    let warnings = corruption_warnings(&run);
+    assert_eq!(warnings.len(), 1, "{warnings:?}");
+    assert!(
+        warnings[0].starts_with(
+            "Task `User Initialization (PROS)` wrote out of bounds of the 4-byte buffer"
+        ),
+        "{warnings:?}"
+    );
+
+    let options = default_options()
+        .canaries(true)
+        .strict(WarningKind::HeapCorruption);
+    let run = run_fixture_with_options("canaries", options, []).await;
+    assert!(
+        matches!(
+            run.outcome.reason,
+            StopReason::StrictWarning(WarningKind::HeapCorruption)
+        ),
+        "{:?}",
+        run.outcome.reason
+    );
+}
+
+#[tokio::test]
+async fn crash_isolation() {
+    // without isolation, a crashing task stops the simulation
+    let run = run_fixture("crash_isolation", []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Crashed(_)),
+        "{:?}",
+        run.outcome.reason
+    );
+    assert_eq!(run.console(), "");
+
+    for options in [default_options(), default_options().threaded(true)] {
+        let run =
+            run_fixture_with_options("crash_isolation", options.isolate_crashes(true), []).await;
+        assert!(
+            matches!(run.outcome.reason, StopReason::Exited(0)),
+            "{:?}",
+            run.outcome.reason
+        );
+        assert_eq!(run.console(), "still running\n");
+        let position =
+            |predicate: fn(&SimulatorEvent) -> bool| run.events.iter().position(predicate).unwrap();
+        let error =
+            position(|event| matches!(event, SimulatorEvent::RobotCodeError { task_id: 3, .. }));
+        let crashed =
+            position(|event| matches!(event, SimulatorEvent::TaskCrashed { task_id: 3, .. }));
+        assert!(error < crashed);
+    }
+}
+
+#[tokio::test]
+async fn data_abort() {
+    let run = run_fixture("data_abort", []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Crashed(_)),
+        "{:?}",
+        run.outcome.reason
+    );
+    let backtrace = run
+        .events
+        .iter()
+        .find_map(|event| match event {
+            SimulatorEvent::RobotCodeError { backtrace, .. } => Some(backtrace),
+            _ => None,
+        })
+        .unwrap();
+    let screen = run
+        .events
+        .iter()
+        .find_map(|event| match event {
+            SimulatorEvent::DataAbort(screen) => Some(screen),
+            _ => None,
+        })
+        .unwrap();
+
+    // the faulting instruction stands in for the program counter
+    assert_eq!(screen.pc, backtrace[0].module_offset);
+    assert_eq!(screen.func_index, Some(backtrace[0].func_index));
+    assert_eq!(
+        screen.stack_trace,
+        backtrace
+            .iter()
+            .filter_map(|frame| frame.module_offset)
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(screen.stack_trace.len(), 2);
+    assert_eq!(screen.task_name, "User Initialization (PROS)");
+    assert!(screen.trap.contains("out of bounds"), "{}", screen.trap);
+
+    let text = screen.to_string();
+    let lines = text.lines().collect::<Vec<_>>();
+    assert_eq!(lines[0], "DATA ABORT EXCEPTION");
+    assert_eq!(lines[1], format!("PC: {:x}", screen.pc.unwrap()));
+    assert_eq!(lines[2], "CURRENT TASK: User Initialization (PROS)");
+}
+
+#[tokio::test]
+async fn invalid_arguments() {
+    let run = run_fixture("invalid_arguments", []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(0b111)),
+        "{:?}",
+        run.outcome.reason
+    );
+    let invalid = run
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::InvalidArgument {
+                function,
+                argument,
+                value,
+                errno,
+                task_name,
+                ..
+            } => {
+                assert_eq!(task_name, "User Initialization (PROS)");
+                Some((function.as_str(), argument.as_str(), *value, *errno))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        invalid,
+        [
+            ("registry_bind_port", "port", 30, pros_sys::ENXIO),
+            ("controller_get_analog", "id", 5, pros_sys::EINVAL),
+            ("lcd_clear_line", "line", 9, pros_sys::EINVAL),
+        ]
+    );
+
+    let options = default_options().strict(WarningKind::InvalidArgument);
+    let run = run_fixture_with_options("invalid_arguments", options, []).await;
+    assert!(
+        matches!(
+            run.outcome.reason,
+            StopReason::StrictWarning(WarningKind::InvalidArgument)
+        ),
+        "{:?}",
+        run.outcome.reason
+    );
+}
+
+#[tokio::test]
+async fn lcd_selector() {
+    let options = default_options().lcd_selector(true);
+    let run =
+        run_fixture_interactive_with_options(
+            "lcd_selector",
+            options,
+            vec![],
+            |event| match event {
+                SimulatorEvent::ConsoleMessage(message) if message.text == "ready\n" => vec![
+                    SimulatorMessage::LcdSelectorChoose("Skills".into()),
+                    SimulatorMessage::LcdButtonsUpdate([false, true, false]),
+                    SimulatorMessage::LcdButtonsUpdate([false, false, false]),
+                ],
+                _ => vec![],
+            },
+        )
+        .await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(2)),
+        "{:?}",
+        run.outcome.reason
+    );
+
+    let selectors = run
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::LcdSelectorUpdated(selector) => Some(selector),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    // the choice line isn't known until a button cycles the choices
+    assert_eq!(selectors[0].choice, None);
+    let selector = selectors.last().unwrap();
+    assert_eq!(selector.choice.as_deref(), Some("Skills"));
+    assert_eq!(selector.choices, ["Left", "Right", "Skills"]);
+    let roles = selector
+        .buttons
+        .iter()
+        .map(|button| button.as_ref().map(|button| button.role))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        roles,
+        [
+            Some(LcdSelectorRole::Previous),
+            Some(LcdSelectorRole::Select),
+            Some(LcdSelectorRole::Next)
+        ]
+    );
+    assert_eq!(
+        selector.buttons[0].as_ref().unwrap().task_name,
+        "User Initialization (PROS)"
+    );
+
+    // nothing is sent unless it's enabled
+    let run = run_fixture_interactive("lcd_selector", vec![], |event| match event {
+        SimulatorEvent::ConsoleMessage(message) if message.text == "ready\n" => vec![
+            SimulatorMessage::LcdButtonsUpdate([false, true, false]),
+            SimulatorMessage::LcdButtonsUpdate([false, false, false]),
+        ],
+        _ => vec![],
+    })
+    .await;
+    assert!(matches!(run.outcome.reason, StopReason::Exited(0)));
+    assert!(!run
+        .events
+        .iter()
+        .any(|event| matches!(event, SimulatorEvent::LcdSelectorUpdated(_))));
+}
+
+/// Collects what a `tracing` subscriber writes.
+#[derive(Clone, Default)]
+struct TraceOutput(Arc<Mutex<Vec<u8>>>);
+
+impl std::io::Write for TraceOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn trace_events() {
+    let traced = |options| async move {
+        let output = TraceOutput::default();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::TRACE)
+            .with_ansi(false)
+            .without_time()
+            .with_writer({
+                let output = output.clone();
+                move || output.clone()
+            })
+            .finish();
+        // the cooperative scheduler runs every task on this thread
+        let _guard = tracing::subscriber::set_default(subscriber);
+        let run = run_fixture_with_options("sim_assert", options, []).await;
+        let output = output.0.lock().unwrap();
+        (run, String::from_utf8_lossy(&output).into_owned())
+    };
+
+    let (run, output) = traced(default_options().trace_events(true)).await;
+    let events = output
+        .lines()
+        .filter(|line| line.contains("pros_simulator::events"))
+        .collect::<Vec<_>>();
+    assert_eq!(events.len(), run.events.len(), "{output}");
+    assert!(
+        events[0].contains(r#"event="RobotCodeLoading""#),
+        "{output}"
+    );
+    assert!(
+        events.iter().any(|line| line.starts_with("ERROR")
+            && line.contains(r#"event="AssertionFailed""#)
+            && line.contains("task_id=")),
+        "{output}"
+    );
+
+    // nothing is traced unless it's enabled
+    let (_, output) = traced(default_options()).await;
+    assert!(!output.contains("pros_simulator::events"), "{output}");
+}
+
+#[cfg(feature = "otlp")]
+#[tokio::test]
+async fn otlp_spans() {
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+    };
+
+    // a collector that accepts every request, keeping the bodies
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(String::new()));
+    std::thread::spawn({
+        let requests = requests.clone();
+        move || {
+            for stream in listener.incoming() {
+                let mut stream = BufReader::new(stream.unwrap());
+                let mut len = 0;
+                loop {
+                    let mut line = String::new();
+                    stream.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                        len = value.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; len];
+                stream.read_exact(&mut body).unwrap();
+                requests
+                    .lock()
+                    .unwrap()
+                    .push_str(&String::from_utf8(body).unwrap());
+                let response = "HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n";
+                stream.get_mut().write_all(response.as_bytes()).unwrap();
+            }
+        }
+    });
+
+    let options = default_options().otlp_endpoint(endpoint);
+    let run = run_fixture_with_options("serial", options, []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(_)),
+        "{:?}",
+        run.outcome.reason
+    );
+    assert!(
+        !run.events
+            .iter()
+            .any(|event| matches!(event, SimulatorEvent::Warning(_))),
+        "{:?}",
+        run.events
+    );
+
+    let requests = requests.lock().unwrap();
+    for name in [
+        "simulation",
+        "User Initialization (PROS)",
+        "write",
+        "millis",
+    ] {
+        assert!(
+            requests.contains(&format!(r#""name":"{name}""#)),
+            "no `{name}` span in {requests}"
+        );
+    }
+}
+
+#[cfg(feature = "render")]
+#[tokio::test]
+async fn render_frames() {
+    use pros_simulator::FrameOutput;
+
+    let dir =
+        std::env::temp_dir().join(format!("pros-simulator-test-{}-frames", std::process::id()));
+    let video = std::env::temp_dir().join(format!(
+        "pros-simulator-test-{}-frames.mjpeg",
+        std::process::id()
+    ));
+    let options = default_options()
+        .test_build(true)
+        .game_object(GameObject {
+            name: "goal".into(),
+            kind: "goal".into(),
+            x: 48.0,
+            y: 0.0,
+            radius: 5.0,
+            fixed: true,
+            held_by: None,
+        })
+        .scoring_zone(ScoringZone {
+            name: "park".into(),
+            shape: ZoneShape::Circle {
+                x: 24.0,
+                y: 0.0,
+                radius: 6.0,
+            },
+            points: 5,
+            rule: ScoringRule::Inside,
+            autonomous_only: false,
+        });
+
+    let run = run_fixture_with_options(
+        "scoring",
+        options
+            .clone()
+            .render_frames(FrameOutput::Png(dir.clone()), 100),
+        [],
+    )
+    .await;
+    assert!(run.outcome.is_success(), "{:?}", run.outcome.reason);
+    let mut frames = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect::<Vec<_>>();
+    frames.sort();
+    let frames = frames
+        .iter()
+        .map(|path| std::fs::read(path).unwrap())
+        .collect::<Vec<_>>();
+    _ = std::fs::remove_dir_all(&dir);
+    // the robot moves 3 times, 20ms apart
+    assert!(frames.len() >= 5, "{} frames", frames.len());
+    let frames = frames
+        .iter()
+        .map(|frame| {
+            let mut reader = png::Decoder::new(frame.as_slice()).read_info().unwrap();
+            let mut pixels = vec![0; reader.output_buffer_size()];
+            let info = reader.next_frame(&mut pixels).unwrap();
+            assert_eq!(
+                (info.color_type, info.bit_depth),
+                (png::ColorType::Rgb, png::BitDepth::Eight)
+            );
+            pixels.truncate(info.buffer_size());
+            (info.width, info.height, pixels)
+        })
+        .collect::<Vec<_>>();
+    assert_ne!(frames.first(), frames.last());
+
+    let run = run_fixture_with_options(
+        "scoring",
+        options.render_frames(FrameOutput::Mjpeg(video.clone()), 100),
+        [],
+    )
+    .await;
+    assert!(run.outcome.is_success(), "{:?}", run.outcome.reason);
+    let bytes = std::fs::read(&video).unwrap();
+    _ = std::fs::remove_file(&video);
+    assert!(bytes.starts_with(&[0xff, 0xd8]) && bytes.ends_with(&[0xff, 0xd9]));
+    let starts = bytes
+        .windows(4)
+        .enumerate()
+        .filter(|(_, bytes)| *bytes == [0xff, 0xd8, 0xff, 0xe0])
+        .map(|(start, _)| start)
+        .collect::<Vec<_>>();
+    assert!(starts.len() >= 5, "{} frames", starts.len());
+    let ends = starts.iter().skip(1).copied().chain([bytes.len()]);
+    for (start, end) in starts.iter().zip(ends) {
+        let mut decoder = jpeg_decoder::Decoder::new(&bytes[*start..end]);
+        let pixels = decoder.decode().unwrap();
+        let info = decoder.info().unwrap();
+        assert_eq!(info.pixel_format, jpeg_decoder::PixelFormat::RGB24);
+        // the two runs aren't frame-for-frame identical, but JPEG is lossy
+        // enough that the closest PNG frame should still be a close match
+        let error = frames
+            .iter()
+            .filter(|(width, height, _)| {
+                (*width, *height) == (info.width as u32, info.height as u32)
+            })
+            .map(|(_, _, expected)| {
+                pixels
+                    .iter()
+                    .zip(expected)
+                    .map(|(a, b)| a.abs_diff(*b) as u64)
+                    .sum::<u64>()
+                    / pixels.len() as u64
+            })
+            .min()
+            .expect("no PNG frame has the same size");
+        assert!(error < 8, "mean error {error}");
+    }
+}
+
+#[cfg(feature = "event-log")]
+#[tokio::test]
+async fn event_log() {
+    use miniz_oxide::{
+        inflate::stream::{inflate, InflateState},
+        DataFormat, MZFlush,
+    };
+
+    let path = std::env::temp_dir().join(format!(
+        "pros-simulator-test-{}-events.jsonl.gz",
+        std::process::id()
+    ));
+    let run = run_fixture_with_options("serial", default_options().event_log(&path), []).await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Exited(_)),
+        "{:?}",
+        run.outcome.reason
+    );
+    let bytes = std::fs::read(&path).unwrap();
+    _ = std::fs::remove_file(&path);
+
+    // the log is a series of complete gzip members
+    let mut members = 0;
+    let mut text = Vec::new();
+    let mut rest = &bytes[..];
+    while !rest.is_empty() {
+        assert_eq!(rest[..3], [0x1f, 0x8b, 8], "gzip header");
+        let mut state = InflateState::new_boxed(DataFormat::Raw);
+        let mut data = vec![0; 1 << 20];
+        let result = inflate(&mut state, &rest[10..], &mut data, MZFlush::Finish);
+        result.status.unwrap();
+        data.truncate(result.bytes_written);
+        rest = &rest[10 + result.bytes_consumed..];
+        let (crc, len) = (&rest[..4], &rest[4..8]);
+        assert_eq!(crc, crc32fast::hash(&data).to_le_bytes());
+        assert_eq!(len, (data.len() as u32).to_le_bytes());
+        rest = &rest[8..];
+        text.extend(data);
+        members += 1;
+    }
+    assert!(members >= 1);
+
+    let lines = String::from_utf8(text).unwrap();
+    let mut logged = Vec::new();
+    let mut last_millis = 0;
+    for line in lines.lines() {
+        let entry: serde_json::Value = serde_json::from_str(line).unwrap();
+        let millis = entry["millis"].as_u64().unwrap();
+        assert!(millis >= last_millis, "{line}");
+        last_millis = millis;
+        assert!(entry["time"].as_u64().unwrap() > 0, "{line}");
+        logged.push(serde_json::from_value::<SimulatorEvent>(entry["event"].clone()).unwrap());
+    }
+    // everything the frontend saw was logged, in the same order
+    assert_eq!(logged, run.events);
+}
+
+#[tokio::test]
+async fn brain_buttons() {
+    let run = run_fixture(
+        "brain_buttons",
+        [
+            SimulatorMessage::BrainButton(BrainButton::Run),
+            SimulatorMessage::BrainButton(BrainButton::Stop),
+        ],
+    )
+    .await;
+    assert!(
+        matches!(run.outcome.reason, StopReason::Cancelled),
+        "{:?}",
+        run.outcome.reason
+    );
+    assert!(run.events.iter().any(|event| matches!(
+        event,
+        SimulatorEvent::Warning(message) if message.starts_with("Can't run the program again")
+    )));
+
+    let headers = run
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            SimulatorEvent::BrainHeader(header) => Some(*header),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        headers,
+        [
+            BrainHeader {
+                running: true,
+                seconds: 0,
+                battery: 100,
+            },
+            BrainHeader {
+                running: false,
+                seconds: 0,
+                battery: 100,
+            },
+        ]
+    );
+}
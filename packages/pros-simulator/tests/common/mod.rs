@@ -0,0 +1,198 @@
+//! Helpers for running the WebAssembly text fixtures in `tests/fixtures`.
+
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use pros_simulator::{SimulationOutcome, SimulatorOptions, Timeout};
+use pros_simulator_interface::{SimulatorEvent, SimulatorMessage};
+
+/// The function table used by task and callback entrypoints, exported like wasm-ld does with
+/// `--export-table`. Appended to each fixture unless it has a [`NO_FUNCTION_TABLE`] line.
+const PRELUDE: &str = r#"
+(table (export "__indirect_function_table") 8 funcref)
+"#;
+
+/// A bump allocator for the simulator to allocate buffers with, appended to each fixture unless
+/// it has a [`NO_ALLOCATOR`] line.
+///
+/// Every task gets its own instance of the module, so state shared between tasks (like the
+/// allocator's next address, stored at address 512) has to live in memory rather than globals.
+const ALLOCATOR: &str = r#"
+(func (export "wasm_memalign") (param $align i32) (param $size i32) (result i32)
+  (local $ptr i32)
+  (local.set $ptr (i32.load (i32.const 512)))
+  (if (i32.eqz (local.get $ptr))
+    (then (local.set $ptr (i32.const 65536))))
+  (local.set $ptr
+    (i32.and
+      (i32.add (local.get $ptr) (i32.sub (local.get $align) (i32.const 1)))
+      (i32.sub (i32.const 0) (local.get $align))))
+  (i32.store (i32.const 512) (i32.add (local.get $ptr) (local.get $size)))
+  (local.get $ptr))
+(func (export "wasm_free") (param i32))
+"#;
+
+/// Marks fixtures that don't export an allocator, like C and C++ robot code. Like
+/// [`NO_FUNCTION_TABLE`], it goes in the comment at the top of the fixture.
+const NO_ALLOCATOR: &str = ";; no allocator";
+
+/// Marks fixtures that don't export their function table, like robot code linked without
+/// `--export-table`.
+const NO_FUNCTION_TABLE: &str = ";; no function table";
+
+/// Compiles `tests/fixtures/{name}.wat` to a temporary WASM file, which the caller should remove
+/// once it's done with it.
+pub fn build_fixture(name: &str) -> PathBuf {
+    static BUILDS: AtomicUsize = AtomicUsize::new(0);
+
+    let fixture = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(format!("{name}.wat"));
+    let body = std::fs::read_to_string(&fixture).unwrap();
+    let marked = |marker| {
+        body.lines()
+            .take_while(|line| line.starts_with(";;"))
+            .any(|line| line == marker)
+    };
+    let prelude = if marked(NO_FUNCTION_TABLE) {
+        ""
+    } else {
+        PRELUDE
+    };
+    let allocator = if marked(NO_ALLOCATOR) { "" } else { ALLOCATOR };
+    let source = format!(
+        "(module (import \"env\" \"memory\" (memory 18 16384 shared))\n{body}\n{prelude}{allocator})"
+    );
+    let wasm = wat::parse_str(source).unwrap_or_else(|err| panic!("{name}.wat: {err}"));
+
+    let build = BUILDS.fetch_add(1, Ordering::Relaxed);
+    let path = std::env::temp_dir().join(format!(
+        "pros-simulator-test-{}-{build}-{name}.wasm",
+        std::process::id()
+    ));
+    std::fs::write(&path, wasm).unwrap();
+    path
+}
+
+/// The result of simulating a fixture.
+pub struct Run {
+    pub events: Vec<SimulatorEvent>,
+    pub outcome: SimulationOutcome,
+}
+
+impl Run {
+    /// Everything the robot code printed to the console.
+    pub fn console(&self) -> String {
+        self.events
+            .iter()
+            .filter_map(|event| match event {
+                SimulatorEvent::ConsoleMessage(message) => Some(message.text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// The options fixtures are simulated with unless a test needs something else. The scheduler
+/// is checked, so every test also checks it doesn't break its invariants.
+pub fn default_options() -> SimulatorOptions {
+    SimulatorOptions::new()
+        .timeout(Timeout::RealTime(Duration::from_secs(10)))
+        .check_scheduler(true)
+}
+
+/// Simulates a fixture, sending it the given messages before it starts.
+pub async fn run_fixture(name: &str, messages: impl IntoIterator<Item = SimulatorMessage>) -> Run {
+    run_fixture_with_options(name, default_options(), messages).await
+}
+
+/// Simulates a fixture with custom options, sending it the given messages before it starts.
+pub async fn run_fixture_with_options(
+    name: &str,
+    options: SimulatorOptions,
+    messages: impl IntoIterator<Item = SimulatorMessage>,
+) -> Run {
+    let messages = messages.into_iter().collect();
+    simulate_fixture(name, options, messages, |_| vec![]).await
+}
+
+/// Simulates a fixture, sending it the given messages before it starts and the messages returned
+/// by `respond` after each event.
+pub async fn run_fixture_interactive(
+    name: &str,
+    messages: Vec<SimulatorMessage>,
+    respond: impl FnMut(&SimulatorEvent) -> Vec<SimulatorMessage> + Send + 'static,
+) -> Run {
+    run_fixture_interactive_with_options(name, default_options(), messages, respond).await
+}
+
+/// Simulates a fixture interactively like [`run_fixture_interactive`], with custom options.
+pub async fn run_fixture_interactive_with_options(
+    name: &str,
+    options: SimulatorOptions,
+    messages: Vec<SimulatorMessage>,
+    respond: impl FnMut(&SimulatorEvent) -> Vec<SimulatorMessage> + Send + 'static,
+) -> Run {
+    simulate_fixture(name, options, messages, respond).await
+}
+
+/// Loads a fixture without running it, returning the events sent while linking it.
+pub async fn check_fixture(name: &str) -> (anyhow::Result<()>, Vec<SimulatorEvent>) {
+    let robot_code = build_fixture(name);
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let result = pros_simulator::check(&robot_code, {
+        let events = events.clone();
+        move |event| events.lock().unwrap().push(event)
+    })
+    .await;
+    _ = std::fs::remove_file(robot_code);
+
+    let events = std::mem::take(&mut *events.lock().unwrap());
+    (result, events)
+}
+
+async fn simulate_fixture(
+    name: &str,
+    options: SimulatorOptions,
+    messages: Vec<SimulatorMessage>,
+    mut respond: impl FnMut(&SimulatorEvent) -> Vec<SimulatorMessage> + Send + 'static,
+) -> Run {
+    let robot_code = build_fixture(name);
+
+    let (tx, rx) = mpsc::channel();
+    for message in messages {
+        tx.send(message).unwrap();
+    }
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let outcome = pros_simulator::simulate(
+        &robot_code,
+        options,
+        {
+            let events = events.clone();
+            move |event| {
+                for message in respond(&event) {
+                    _ = tx.send(message);
+                }
+                events.lock().unwrap().push(event);
+            }
+        },
+        rx,
+    )
+    .await
+    .unwrap();
+    _ = std::fs::remove_file(robot_code);
+
+    let events = std::mem::take(&mut *events.lock().unwrap());
+    let violation = events
+        .iter()
+        .find(|event| matches!(event, SimulatorEvent::SchedulerInvariantViolated { .. }));
+    assert!(violation.is_none(), "{violation:?}");
+    Run { events, outcome }
+}
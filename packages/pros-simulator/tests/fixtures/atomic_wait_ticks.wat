@@ -0,0 +1,44 @@
+;; Checks that `memory.atomic.wait32` timeouts follow the simulation's clock: a task waiting for
+;; an hour times out as soon as the clock is advanced past it. Exits with the child's wait result
+;; plus 1, then waits inside an rtos_suspend_all critical section, which should crash.
+(import "env" "task_create" (func $task_create (param i32 i32 i32 i32 i32) (result i32)))
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "sim_advance_time" (func $sim_advance_time (param i32)))
+(import "env" "rtos_suspend_all" (func $rtos_suspend_all))
+(import "env" "puts" (func $puts (param i32) (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(elem (i32.const 1) $child)
+
+(data (i32.const 1024) "Child\00")
+(data (i32.const 1032) "advanced\00")
+
+;; 2048: the value the child waits on, which is never notified
+;; 2052: set by the child once its wait returns
+(func $child (param i32)
+  (i32.store (i32.const 2052)
+    (i32.add
+      (i32.const 1)
+      (memory.atomic.wait32 (i32.const 2048) (i32.const 0) (i64.const 3600000000000)))))
+
+(func (export "initialize")
+  (local $i i32)
+  (drop (call $task_create (i32.const 1) (i32.const 0) (i32.const 8) (i32.const 8192) (i32.const 1024)))
+  ;; give the child time to start waiting
+  (call $delay (i32.const 5))
+  (call $sim_advance_time (i32.const 3600000))
+  ;; the child should time out (2) within a few ticks rather than an hour
+  (loop $wait
+    (call $delay (i32.const 1))
+    (local.set $i (i32.add (local.get $i) (i32.const 1)))
+    (br_if $wait
+      (i32.and
+        (i32.eqz (i32.atomic.load (i32.const 2052)))
+        (i32.lt_u (local.get $i) (i32.const 100)))))
+  (if (i32.ne (i32.load (i32.const 2052)) (i32.const 3))
+    (then (call $exit (i32.load (i32.const 2052)))))
+  (drop (call $puts (i32.const 1032)))
+
+  (call $rtos_suspend_all)
+  (drop (memory.atomic.wait32 (i32.const 2048) (i32.const 0) (i64.const 1000000)))
+  (call $exit (i32.const 0)))
@@ -0,0 +1,41 @@
+;; Blocks a task with `memory.atomic.wait32` until another task notifies it, then exits with
+;; `100 * woken_by_notify + 10 * wait_timed_out + wait_value_mismatch`.
+(import "env" "task_create" (func $task_create (param i32 i32 i32 i32 i32) (result i32)))
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(elem (i32.const 1) $child)
+
+(data (i32.const 1024) "Child\00")
+
+;; 2048: the value the child waits on
+;; 2052: set by the child once it has been woken
+(func $child (param i32)
+  (i32.store (i32.const 2052)
+    (i32.add
+      (i32.const 1)
+      (memory.atomic.wait32 (i32.const 2048) (i32.const 0) (i64.const -1)))))
+
+(func (export "initialize")
+  (local $result i32)
+  (drop (call $task_create (i32.const 1) (i32.const 0) (i32.const 8) (i32.const 8192) (i32.const 1024)))
+  ;; give the child time to start waiting
+  (call $delay (i32.const 5))
+  (i32.atomic.store (i32.const 2048) (i32.const 1))
+  (local.set $result (i32.mul (memory.atomic.notify (i32.const 2048) (i32.const 1)) (i32.const 100)))
+  (loop $wait
+    (call $delay (i32.const 1))
+    (br_if $wait (i32.eqz (i32.atomic.load (i32.const 2052)))))
+  ;; the child should have been woken (0) rather than timing out
+  (if (i32.ne (i32.load (i32.const 2052)) (i32.const 1))
+    (then (call $exit (i32.const -1))))
+
+  ;; nobody will notify this, so it times out after 1ms
+  (local.set $result
+    (i32.add (local.get $result)
+      (i32.mul (memory.atomic.wait32 offset=8 (i32.const 2048) (i32.const 0) (i64.const 1000000)) (i32.const 5))))
+  ;; the value at 2048 is 1, not 0
+  (local.set $result
+    (i32.add (local.get $result)
+      (memory.atomic.wait32 (i32.const 2048) (i32.const 0) (i64.const -1))))
+  (call $exit (local.get $result)))
@@ -0,0 +1,13 @@
+;; Prints 10,000 console messages.
+(import "env" "puts" (func $puts (param i32) (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(data (i32.const 1024) "Hello from simulator!\00")
+
+(func (export "initialize")
+  (local $i i32)
+  (loop $print
+    (drop (call $puts (i32.const 1024)))
+    (local.set $i (i32.add (local.get $i) (i32.const 1)))
+    (br_if $print (i32.lt_u (local.get $i) (i32.const 10000))))
+  (call $exit (i32.const 0)))
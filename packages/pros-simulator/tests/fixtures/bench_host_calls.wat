@@ -0,0 +1,11 @@
+;; Calls a trivial host function 100,000 times.
+(import "env" "millis" (func $millis (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(func (export "initialize")
+  (local $i i32)
+  (loop $call
+    (drop (call $millis))
+    (local.set $i (i32.add (local.get $i) (i32.const 1)))
+    (br_if $call (i32.lt_u (local.get $i) (i32.const 100000))))
+  (call $exit (i32.const 0)))
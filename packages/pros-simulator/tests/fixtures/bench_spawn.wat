@@ -0,0 +1,22 @@
+;; Spawns 100 tasks that exit immediately, waiting for each to finish before spawning the next.
+(import "env" "task_create" (func $task_create (param i32 i32 i32 i32 i32) (result i32)))
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(elem (i32.const 1) $child)
+
+(data (i32.const 1024) "Child\00")
+
+(func $child (param i32)
+  (i32.store (i32.const 2048) (i32.add (i32.load (i32.const 2048)) (i32.const 1))))
+
+(func (export "initialize")
+  (local $spawned i32)
+  (loop $spawn
+    (drop (call $task_create (i32.const 1) (i32.const 0) (i32.const 8) (i32.const 8192) (i32.const 1024)))
+    (local.set $spawned (i32.add (local.get $spawned) (i32.const 1)))
+    (loop $wait
+      (call $delay (i32.const 0))
+      (br_if $wait (i32.ne (i32.load (i32.const 2048)) (local.get $spawned))))
+    (br_if $spawn (i32.lt_u (local.get $spawned) (i32.const 100))))
+  (call $exit (i32.const 0)))
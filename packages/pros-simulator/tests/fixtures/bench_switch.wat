@@ -0,0 +1,27 @@
+;; Runs 10 tasks that each yield to the scheduler 100 times.
+(import "env" "task_create" (func $task_create (param i32 i32 i32 i32 i32) (result i32)))
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(elem (i32.const 1) $child)
+
+(data (i32.const 1024) "Child\00")
+
+(func $child (param i32)
+  (local $i i32)
+  (loop $yield
+    (call $delay (i32.const 0))
+    (local.set $i (i32.add (local.get $i) (i32.const 1)))
+    (br_if $yield (i32.lt_u (local.get $i) (i32.const 100))))
+  (i32.store (i32.const 2048) (i32.add (i32.load (i32.const 2048)) (i32.const 1))))
+
+(func (export "initialize")
+  (local $spawned i32)
+  (loop $spawn
+    (drop (call $task_create (i32.const 1) (i32.const 0) (i32.const 8) (i32.const 8192) (i32.const 1024)))
+    (local.set $spawned (i32.add (local.get $spawned) (i32.const 1)))
+    (br_if $spawn (i32.lt_u (local.get $spawned) (i32.const 10))))
+  (loop $wait
+    (call $delay (i32.const 0))
+    (br_if $wait (i32.lt_u (i32.load (i32.const 2048)) (i32.const 10))))
+  (call $exit (i32.const 0)))
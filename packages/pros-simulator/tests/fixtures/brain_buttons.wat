@@ -0,0 +1,7 @@
+;; Runs until it's stopped.
+(import "env" "delay" (func $delay (param i32)))
+
+(func (export "initialize")
+  (loop $forever
+    (call $delay (i32.const 10))
+    (br $forever)))
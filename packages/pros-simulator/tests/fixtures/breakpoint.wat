@@ -0,0 +1,20 @@
+;; Counts to 20 in a variable at an exported address, driving the motor on port 5 at 10 and the
+;; motor on port 1 otherwise.
+(import "env" "motor_move" (func $motor_move (param i32 i32) (result i32)))
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(global (export "COUNTER") i32 (i32.const 2048))
+
+(func (export "initialize")
+  (local $count i32)
+  (loop $count_up
+    (local.set $count (i32.add (i32.load (i32.const 2048)) (i32.const 1)))
+    (i32.store (i32.const 2048) (local.get $count))
+    (drop
+      (if (result i32) (i32.eq (local.get $count) (i32.const 10))
+        (then (call $motor_move (i32.const 5) (i32.const 100)))
+        (else (call $motor_move (i32.const 1) (local.get $count)))))
+    (call $delay (i32.const 1))
+    (br_if $count_up (i32.lt_u (local.get $count) (i32.const 20))))
+  (call $exit (i32.const 0)))
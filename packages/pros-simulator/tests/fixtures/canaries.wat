@@ -0,0 +1,9 @@
+;; Writes just past the end of its errno, then yields and exits.
+(import "env" "__errno" (func $__errno (result i32)))
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(func (export "initialize")
+  (i32.store (i32.add (call $__errno) (i32.const 4)) (i32.const -1))
+  (call $delay (i32.const 1))
+  (call $exit (i32.const 0)))
@@ -0,0 +1,19 @@
+;; Exits with a bitmask of what it detected: 1 if it's simulated, 2 if the run is deterministic,
+;; 4 if tasks are threaded, and 8 if it has an IMU.
+(import "env" "sim_is_simulator" (func $sim_is_simulator (result i32)))
+(import "env" "sim_capability" (func $sim_capability (param i32) (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(data (i32.const 1024) "deterministic\00")
+(data (i32.const 1040) "threaded\00")
+(data (i32.const 1056) "imu\00")
+
+(func (export "initialize")
+  (call $exit
+    (i32.or
+      (i32.or
+        (call $sim_is_simulator)
+        (i32.shl (call $sim_capability (i32.const 1024)) (i32.const 1)))
+      (i32.or
+        (i32.shl (call $sim_capability (i32.const 1040)) (i32.const 2))
+        (i32.shl (call $sim_capability (i32.const 1056)) (i32.const 3))))))
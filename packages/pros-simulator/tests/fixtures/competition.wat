@@ -0,0 +1,11 @@
+;; Exits with the competition status from whichever phase entrypoint is run.
+(import "env" "competition_get_status" (func $competition_get_status (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(func (export "initialize"))
+(func (export "autonomous")
+  (call $exit (call $competition_get_status)))
+(func (export "opcontrol")
+  (call $exit (i32.const 100)))
+(func (export "disabled")
+  (call $exit (i32.const 200)))
@@ -0,0 +1,15 @@
+;; Exits with `1000 * A_pressed + left_y` from the master controller.
+(import "env" "controller_get_analog" (func $controller_get_analog (param i32 i32) (result i32)))
+(import "env" "controller_get_digital" (func $controller_get_digital (param i32 i32) (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(func (export "initialize"))
+(func (export "opcontrol")
+  (call $exit
+    (i32.add
+      (i32.mul
+        ;; E_CONTROLLER_MASTER, E_CONTROLLER_DIGITAL_A
+        (call $controller_get_digital (i32.const 0) (i32.const 17))
+        (i32.const 1000))
+      ;; E_CONTROLLER_MASTER, E_CONTROLLER_ANALOG_LEFT_Y
+      (call $controller_get_analog (i32.const 0) (i32.const 1)))))
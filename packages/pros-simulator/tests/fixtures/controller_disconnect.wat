@@ -0,0 +1,28 @@
+;; Prints "ready" once the master controller is connected, waits for it to disconnect, then exits
+;; with `1000 * A_pressed + left_y`, which should be 0 for a disconnected controller.
+(import "env" "controller_get_analog" (func $controller_get_analog (param i32 i32) (result i32)))
+(import "env" "controller_get_digital" (func $controller_get_digital (param i32 i32) (result i32)))
+(import "env" "controller_is_connected" (func $controller_is_connected (param i32) (result i32)))
+(import "env" "puts" (func $puts (param i32) (result i32)))
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(data (i32.const 1024) "ready\00")
+
+(func (export "initialize"))
+(func (export "opcontrol")
+  ;; E_CONTROLLER_MASTER
+  (if (i32.eqz (call $controller_is_connected (i32.const 0)))
+    (then (call $exit (i32.const -1))))
+  (drop (call $puts (i32.const 1024)))
+  (loop $wait
+    (call $delay (i32.const 1))
+    (br_if $wait (call $controller_is_connected (i32.const 0))))
+  (call $exit
+    (i32.add
+      (i32.mul
+        ;; E_CONTROLLER_DIGITAL_A
+        (call $controller_get_digital (i32.const 0) (i32.const 17))
+        (i32.const 1000))
+      ;; E_CONTROLLER_ANALOG_LEFT_Y
+      (call $controller_get_analog (i32.const 0) (i32.const 1)))))
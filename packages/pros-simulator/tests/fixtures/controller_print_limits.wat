@@ -0,0 +1,50 @@
+;; Prints to the master controller's screen with a width that doesn't fit in an `int`, a `*`
+;; width and a `*` precision that are far too large (which fail with `EINVAL`), and with
+;; arguments at the very end of the address space (which fail with `EFAULT`). Then prints wide
+;; characters that don't fit on the screen. Exits with `1 + 10 * huge_width + 100 * star_width
+;; + 1000 * star_precision + 10000 * end_of_memory + 100000 * wrote`.
+(import "env" "controller_print" (func $controller_print (param i32 i32 i32 i32 i32) (result i32)))
+(import "env" "__errno" (func $__errno (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(data (i32.const 1024) "%99999999999999999999d\00")
+(data (i32.const 1056) "%*d\00")
+(data (i32.const 1064) "%.*f\00")
+(data (i32.const 1072) "%lld\00")
+(data (i32.const 1080) "%s\00")
+;; a width of 2000000000, then 1
+(data (i32.const 1088) "\00\94\35\77\01\00\00\00")
+;; a precision of 1000000000, then 0.0 aligned to 8 bytes
+(data (i32.const 1096) "\00\ca\9a\3b\00\00\00\00\00\00\00\00\00\00\00\00")
+;; a pointer to the wide characters
+(data (i32.const 1112) "\78\04\00\00")
+(data (i32.const 1144) "日本語日本語日本語\00")
+
+;; Whether printing `format` with `args` fails with `errno`.
+(func $fails_with (param $format i32) (param $args i32) (param $errno i32) (result i32)
+  ;; E_CONTROLLER_MASTER, line 0, column 0
+  (i32.and
+    (i32.eq
+      (call $controller_print (i32.const 0) (i32.const 0) (i32.const 0) (local.get $format) (local.get $args))
+      (i32.const 2147483647))
+    (i32.eq (i32.load (call $__errno)) (local.get $errno))))
+
+(func (export "initialize"))
+(func (export "opcontrol")
+  (call $exit
+    (i32.add
+      (i32.add
+        (i32.add
+          (i32.const 1)
+          ;; EINVAL
+          (i32.mul (call $fails_with (i32.const 1024) (i32.const 1088) (i32.const 22)) (i32.const 10)))
+        (i32.add
+          (i32.mul (call $fails_with (i32.const 1056) (i32.const 1088) (i32.const 22)) (i32.const 100))
+          (i32.mul (call $fails_with (i32.const 1064) (i32.const 1096) (i32.const 22)) (i32.const 1000))))
+      (i32.add
+        ;; EFAULT
+        (i32.mul (call $fails_with (i32.const 1072) (i32.const -4) (i32.const 14)) (i32.const 10000))
+        ;; line 0, column 1
+        (i32.mul
+          (call $controller_print (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 1080) (i32.const 1112))
+          (i32.const 100000))))))
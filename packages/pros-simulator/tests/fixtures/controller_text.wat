@@ -0,0 +1,57 @@
+;; Prints to the master controller's screen, writes again straight away (which fails with
+;; `EAGAIN`), then writes, rumbles and clears the disconnected partner controller 50 ms apart.
+;; Exits with `1 + 10 * failed_with_eagain + 100 * wrote + 1000 * rumbled + 10000 *
+;; partner_failed`.
+(import "env" "controller_print" (func $controller_print (param i32 i32 i32 i32 i32) (result i32)))
+(import "env" "controller_set_text" (func $controller_set_text (param i32 i32 i32 i32) (result i32)))
+(import "env" "controller_rumble" (func $controller_rumble (param i32 i32) (result i32)))
+(import "env" "controller_clear" (func $controller_clear (param i32) (result i32)))
+(import "env" "__errno" (func $__errno (result i32)))
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(data (i32.const 1024) "x=%d %s|%05.1f\00")
+(data (i32.const 1056) "ok\00")
+(data (i32.const 1064) "hi\00")
+(data (i32.const 1072) "spam\00")
+(data (i32.const 1080) ".-\00")
+;; the variadic arguments: 42, "ok", then -2.5 aligned to 8 bytes
+(data (i32.const 1088) "\2a\00\00\00\20\04\00\00\00\00\00\00\00\00\04\c0")
+
+(func (export "initialize"))
+(func (export "opcontrol")
+  (local $result i32)
+  ;; E_CONTROLLER_MASTER, line 0, column 0
+  (local.set $result
+    (call $controller_print (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 1024) (i32.const 1088)))
+  (local.set $result
+    (i32.add
+      (local.get $result)
+      (i32.mul
+        (i32.and
+          (i32.eq
+            (call $controller_set_text (i32.const 0) (i32.const 1) (i32.const 2) (i32.const 1072))
+            (i32.const 2147483647))
+          ;; EAGAIN
+          (i32.eq (i32.load (call $__errno)) (i32.const 11)))
+        (i32.const 10))))
+  (call $delay (i32.const 50))
+  (local.set $result
+    (i32.add
+      (local.get $result)
+      (i32.mul
+        (call $controller_set_text (i32.const 0) (i32.const 1) (i32.const 2) (i32.const 1064))
+        (i32.const 100))))
+  (call $delay (i32.const 50))
+  (local.set $result
+    (i32.add
+      (local.get $result)
+      (i32.mul (call $controller_rumble (i32.const 0) (i32.const 1080)) (i32.const 1000))))
+  ;; E_CONTROLLER_PARTNER
+  (local.set $result
+    (i32.add
+      (local.get $result)
+      (i32.mul
+        (i32.eq (call $controller_clear (i32.const 1)) (i32.const 2147483647))
+        (i32.const 10000))))
+  (call $exit (local.get $result)))
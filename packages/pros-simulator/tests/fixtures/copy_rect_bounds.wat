@@ -0,0 +1,18 @@
+;; Copies a row of four pixels to x = -2 so only the last two are on the screen, renders, then
+;; copies a rectangle as wide as an i32 allows, which reads far past the end of memory.
+(import "env" "vexDisplayCopyRect" (func $copy_rect (param i32 i32 i32 i32 i32 i32)))
+(import "env" "vexDisplayRender" (func $render (param i32 i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(func (export "initialize")
+  (i32.store (i32.const 2048) (i32.const 0x111111))
+  (i32.store (i32.const 2052) (i32.const 0x222222))
+  (i32.store (i32.const 2056) (i32.const 0x333333))
+  (i32.store (i32.const 2060) (i32.const 0x444444))
+  (call $copy_rect (i32.const -2) (i32.const 0) (i32.const 1) (i32.const 0)
+    (i32.const 2048) (i32.const 4))
+  (call $render (i32.const 0) (i32.const 0))
+
+  (call $copy_rect (i32.const 0x80000000) (i32.const 0) (i32.const 0x7fffffff) (i32.const 0)
+    (i32.const 2048) (i32.const 0x7fffffff))
+  (call $exit (i32.const 0)))
@@ -0,0 +1,10 @@
+;; Spins for the first second, then waits for the next one, and exits.
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "millis" (func $millis (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(func (export "initialize")
+  (loop $spin
+    (br_if $spin (i32.lt_u (call $millis) (i32.const 1000))))
+  (call $delay (i32.const 1100))
+  (call $exit (i32.const 0)))
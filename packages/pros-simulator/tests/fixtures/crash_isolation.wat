@@ -0,0 +1,19 @@
+;; Spawns a task that traps, then keeps running and exits once the task has had time to crash.
+(import "env" "task_create" (func $task_create (param i32 i32 i32 i32 i32) (result i32)))
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "puts" (func $puts (param i32) (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(elem (i32.const 1) $child)
+
+(data (i32.const 1024) "still running\00")
+(data (i32.const 1056) "Child\00")
+
+(func $child (param i32)
+  unreachable)
+
+(func (export "initialize")
+  (drop (call $task_create (i32.const 1) (i32.const 0) (i32.const 8) (i32.const 8192) (i32.const 1056)))
+  (call $delay (i32.const 10))
+  (drop (call $puts (i32.const 1024)))
+  (call $exit (i32.const 0)))
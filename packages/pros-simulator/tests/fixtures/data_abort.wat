@@ -0,0 +1,6 @@
+;; Reads past the end of memory from a nested function call.
+(func $fault (result i32)
+  (i32.load (i32.const -4)))
+
+(func (export "initialize")
+  (drop (call $fault)))
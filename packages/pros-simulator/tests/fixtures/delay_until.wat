@@ -0,0 +1,18 @@
+;; Calls `task_delay_until` three times with the same `prev_time`, exiting with 0 if it was
+;; advanced by the delta each time and the delays added up.
+(import "env" "task_delay_until" (func $task_delay_until (param i32 i32)))
+(import "env" "millis" (func $millis (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(func (export "initialize")
+  (local $start i32)
+  (local.set $start (call $millis))
+  (i32.store (i32.const 2048) (local.get $start))
+  (call $task_delay_until (i32.const 2048) (i32.const 10))
+  (call $task_delay_until (i32.const 2048) (i32.const 10))
+  (call $task_delay_until (i32.const 2048) (i32.const 10))
+  (if (i32.ne (i32.load (i32.const 2048)) (i32.add (local.get $start) (i32.const 30)))
+    (then (call $exit (i32.const 1))))
+  (if (i32.lt_u (call $millis) (i32.add (local.get $start) (i32.const 30)))
+    (then (call $exit (i32.const 2))))
+  (call $exit (i32.const 0)))
@@ -0,0 +1,10 @@
+;; Reads errno twice before any call has failed, then again after a call fails, then exits.
+(import "env" "__errno" (func $__errno (result i32)))
+(import "env" "lcd_clear" (func $lcd_clear (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(func (export "initialize")
+  (drop (i32.load (call $__errno)))
+  (drop (i32.load (call $__errno)))
+  (drop (call $lcd_clear))
+  (call $exit (i32.load (call $__errno))))
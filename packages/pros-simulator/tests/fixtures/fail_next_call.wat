@@ -0,0 +1,17 @@
+;; Writes to stdout twice, exiting with `10 * errno + result` where `errno` is set by the first
+;; write and `result` is what the second one returned.
+(import "env" "write" (func $write (param i32 i32 i32) (result i32)))
+(import "env" "__errno" (func $__errno (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(data (i32.const 1024) "x")
+
+(func (export "initialize")
+  (local $errno i32)
+  (if (i32.ne (call $write (i32.const 1) (i32.const 1024) (i32.const 1)) (i32.const -1))
+    (then (call $exit (i32.const -1))))
+  (local.set $errno (i32.load (call $__errno)))
+  (call $exit
+    (i32.add
+      (i32.mul (local.get $errno) (i32.const 10))
+      (call $write (i32.const 1) (i32.const 1024) (i32.const 1)))))
@@ -0,0 +1,23 @@
+;; Counts how many times it has run in the `runs` key of the flash, exiting with the count. Exits
+;; with -1 if the flash doesn't behave.
+(import "env" "sim_flash_read" (func $sim_flash_read (param i32 i32 i32) (result i32)))
+(import "env" "sim_flash_write" (func $sim_flash_write (param i32 i32 i32) (result i32)))
+(import "env" "sim_flash_erase" (func $sim_flash_erase (param i32) (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(data (i32.const 1024) "runs\00")
+(data (i32.const 1040) "scratch\00")
+(data (i32.const 1056) "bad=key\00")
+
+(func (export "initialize")
+  (local $runs i32)
+  ;; a missing key reads as -1, and invalid keys can't be written
+  (if (i32.ne (call $sim_flash_erase (i32.const 1040)) (i32.const -1))
+    (then (call $exit (i32.const -1))))
+  (if (i32.ne (call $sim_flash_write (i32.const 1056) (i32.const 2048) (i32.const 4)) (i32.const -1))
+    (then (call $exit (i32.const -1))))
+  (if (i32.eq (call $sim_flash_read (i32.const 1024) (i32.const 2048) (i32.const 4)) (i32.const 4))
+    (then (local.set $runs (i32.load (i32.const 2048)))))
+  (i32.store (i32.const 2048) (i32.add (local.get $runs) (i32.const 1)))
+  (drop (call $sim_flash_write (i32.const 1024) (i32.const 2048) (i32.const 4)))
+  (call $exit (i32.load (i32.const 2048))))
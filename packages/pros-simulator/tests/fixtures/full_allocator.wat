@@ -0,0 +1,18 @@
+;; no allocator
+;; Exports an allocator that only has room for each task's `errno`, then asks for the task's
+;; name, which the simulator has to allocate.
+(import "env" "task_get_name" (func $task_get_name (param i32) (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(func (export "wasm_memalign") (param $align i32) (param $size i32) (result i32)
+  (local $ptr i32)
+  (if (i32.gt_u (local.get $size) (i32.const 4))
+    (then (return (i32.const 0))))
+  (local.set $ptr (i32.add (i32.load (i32.const 512)) (i32.const 65536)))
+  (i32.store (i32.const 512) (i32.add (i32.load (i32.const 512)) (i32.const 4)))
+  (local.get $ptr))
+(func (export "wasm_free") (param i32))
+
+(func (export "initialize")
+  (drop (call $task_get_name (i32.const 0)))
+  (call $exit (i32.const 0)))
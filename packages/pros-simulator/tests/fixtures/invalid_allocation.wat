@@ -0,0 +1,16 @@
+;; no allocator
+;; Exports an allocator that hands out a real buffer for the task's `errno`, then pointers past
+;; the end of memory. Asks for the task's name, which the simulator has to allocate.
+(import "env" "task_get_name" (func $task_get_name (param i32) (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(func (export "wasm_memalign") (param $align i32) (param $size i32) (result i32)
+  (if (i32.load (i32.const 512))
+    (then (return (i32.const -64))))
+  (i32.store (i32.const 512) (i32.const 1))
+  (i32.const 65536))
+(func (export "wasm_free") (param i32))
+
+(func (export "initialize")
+  (drop (call $task_get_name (i32.const 0)))
+  (call $exit (i32.const 0)))
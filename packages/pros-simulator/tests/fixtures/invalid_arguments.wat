@@ -0,0 +1,28 @@
+;; Passes invalid arguments to functions that validate them, then exits with a bit set for each
+;; one that failed with the expected errno.
+(import "env" "__errno" (func $errno (result i32)))
+(import "env" "registry_bind_port" (func $registry_bind_port (param i32 i32) (result i32)))
+(import "env" "controller_get_analog" (func $controller_get_analog (param i32 i32) (result i32)))
+(import "env" "lcd_initialize" (func $lcd_initialize (result i32)))
+(import "env" "lcd_clear_line" (func $lcd_clear_line (param i32) (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(func $errno_is (param $expected i32) (result i32)
+  (i32.eq (i32.load (call $errno)) (local.get $expected)))
+
+(func (export "initialize")
+  (local $result i32)
+  (drop (call $lcd_initialize))
+
+  ;; ENXIO, returning PROS_ERR
+  (if (i32.and (i32.eq (call $registry_bind_port (i32.const 30) (i32.const 2)) (i32.const 2147483647)) (call $errno_is (i32.const 6)))
+    (then (local.set $result (i32.or (local.get $result) (i32.const 1)))))
+
+  ;; EINVAL
+  (if (i32.and (i32.eqz (call $controller_get_analog (i32.const 5) (i32.const 0))) (call $errno_is (i32.const 22)))
+    (then (local.set $result (i32.or (local.get $result) (i32.const 2)))))
+
+  (if (i32.and (i32.eqz (call $lcd_clear_line (i32.const 9))) (call $errno_is (i32.const 22)))
+    (then (local.set $result (i32.or (local.get $result) (i32.const 4)))))
+
+  (call $exit (local.get $result)))
@@ -0,0 +1,38 @@
+;; Uses a mutex that doesn't exist and gives one back twice, then exits with a bit set for each
+;; call that failed with `EINVAL` instead of crashing the simulator.
+(import "env" "__errno" (func $errno (result i32)))
+(import "env" "mutex_create" (func $mutex_create (result i32)))
+(import "env" "mutex_take" (func $mutex_take (param i32 i32) (result i32)))
+(import "env" "mutex_give" (func $mutex_give (param i32) (result i32)))
+(import "env" "mutex_delete" (func $mutex_delete (param i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(func $failed (param $returned i32) (result i32)
+  (i32.and
+    (i32.eq (local.get $returned) (i32.const 2147483647))
+    (i32.eq (i32.load (call $errno)) (i32.const 22))))
+
+(func (export "initialize")
+  (local $result i32)
+  (local $mutex i32)
+
+  (if (call $failed (call $mutex_give (i32.const 12345)))
+    (then (local.set $result (i32.or (local.get $result) (i32.const 1)))))
+
+  (if (call $failed (call $mutex_take (i32.const 12345) (i32.const 0)))
+    (then (local.set $result (i32.or (local.get $result) (i32.const 2)))))
+
+  ;; only the first give succeeds
+  (local.set $mutex (call $mutex_create))
+  (drop (call $mutex_take (local.get $mutex) (i32.const 0)))
+  (if (i32.and
+        (i32.eq (call $mutex_give (local.get $mutex)) (i32.const 1))
+        (call $failed (call $mutex_give (local.get $mutex))))
+    (then (local.set $result (i32.or (local.get $result) (i32.const 4)))))
+
+  (i32.store (call $errno) (i32.const 0))
+  (call $mutex_delete (i32.const 12345))
+  (if (i32.eq (i32.load (call $errno)) (i32.const 22))
+    (then (local.set $result (i32.or (local.get $result) (i32.const 8)))))
+
+  (call $exit (local.get $result)))
@@ -0,0 +1,28 @@
+;; Spawns two tasks that take turns printing their names 5 times, and exits once both are done.
+;; Every task only yields, so the order they run in is decided entirely by the scheduler.
+(import "env" "task_create" (func $task_create (param i32 i32 i32 i32 i32) (result i32)))
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "puts" (func $puts (param i32) (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(elem (i32.const 1) $worker)
+
+(data (i32.const 1024) "a\00")
+(data (i32.const 1040) "b\00")
+
+(func $worker (param $name i32)
+  (local $i i32)
+  (loop $print
+    (drop (call $puts (local.get $name)))
+    (call $delay (i32.const 0))
+    (local.set $i (i32.add (local.get $i) (i32.const 1)))
+    (br_if $print (i32.lt_u (local.get $i) (i32.const 5))))
+  (i32.store (i32.const 2048) (i32.add (i32.load (i32.const 2048)) (i32.const 1))))
+
+(func (export "initialize")
+  (drop (call $task_create (i32.const 1) (i32.const 1024) (i32.const 8) (i32.const 8192) (i32.const 1024)))
+  (drop (call $task_create (i32.const 1) (i32.const 1040) (i32.const 8) (i32.const 8192) (i32.const 1040)))
+  (loop $wait
+    (call $delay (i32.const 0))
+    (br_if $wait (i32.lt_u (i32.load (i32.const 2048)) (i32.const 2))))
+  (call $exit (i32.const 0)))
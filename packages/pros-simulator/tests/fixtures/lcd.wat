@@ -0,0 +1,22 @@
+;; Writes to the LCD, including text too wide for it, waits for the screen to be sent, then exits
+;; with the result of writing to a line that doesn't exist.
+(import "env" "lcd_initialize" (func $lcd_initialize (result i32)))
+(import "env" "lcd_set_text" (func $lcd_set_text (param i32 i32) (result i32)))
+(import "env" "lcd_clear_line" (func $lcd_clear_line (param i32) (result i32)))
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(data (i32.const 1024) "Hello\00")
+(data (i32.const 1040) "World\00")
+(data (i32.const 1056) "0123456789012345678901234567890123456789overflow\00")
+(data (i32.const 1120) "\e3\83\ad\e3\83\9c\e3\83\83\e3\83\88 \e3\82\b9\e3\83\86\e3\83\bc\e3\82\bf\e3\82\b9: \e8\87\aa\e5\be\8b\e5\88\b6\e5\be\a1\e4\b8\ad\e3\80\82\e3\82\bb\e3\83\b3\e3\82\b5\e3\83\bc\00")
+
+(func (export "initialize")
+  (drop (call $lcd_initialize))
+  (drop (call $lcd_set_text (i32.const 1) (i32.const 1024)))
+  (drop (call $lcd_set_text (i32.const 2) (i32.const 1040)))
+  (drop (call $lcd_clear_line (i32.const 1)))
+  (drop (call $lcd_set_text (i32.const 3) (i32.const 1056)))
+  (drop (call $lcd_set_text (i32.const 4) (i32.const 1120)))
+  (call $delay (i32.const 20))
+  (call $exit (call $lcd_set_text (i32.const 8) (i32.const 1024))))
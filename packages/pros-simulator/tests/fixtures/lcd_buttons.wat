@@ -0,0 +1,24 @@
+;; Registers a callback for the left LCD button, prints "ready", and exits once the button has been
+;; pressed.
+(import "env" "lcd_initialize" (func $lcd_initialize (result i32)))
+(import "env" "lcd_register_btn0_cb" (func $lcd_register_btn0_cb (param i32) (result i32)))
+(import "env" "puts" (func $puts (param i32) (result i32)))
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(elem (i32.const 1) $on_press)
+
+(data (i32.const 1024) "ready\00")
+
+;; The callback runs in the system daemon's task, so the press count is stored in memory.
+(func $on_press
+  (i32.store (i32.const 2048) (i32.add (i32.load (i32.const 2048)) (i32.const 1))))
+
+(func (export "initialize")
+  (drop (call $lcd_initialize))
+  (drop (call $lcd_register_btn0_cb (i32.const 1)))
+  (drop (call $puts (i32.const 1024)))
+  (loop $wait
+    (call $delay (i32.const 1))
+    (br_if $wait (i32.eqz (i32.load (i32.const 2048)))))
+  (call $exit (i32.load (i32.const 2048))))
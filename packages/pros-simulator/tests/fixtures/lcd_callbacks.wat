@@ -0,0 +1,37 @@
+;; Registers callbacks for the left LCD button that are out of bounds, have the wrong type, are
+;; valid, and NULL, then prints "ready" and waits 20ms for the button to be pressed. Exits with
+;; the registration results as bits (lowest first), plus 16 times the `errno` of the failed
+;; registrations, plus 1000 times the number of presses the cleared callback saw.
+(import "env" "lcd_initialize" (func $lcd_initialize (result i32)))
+(import "env" "lcd_register_btn0_cb" (func $lcd_register_btn0_cb (param i32) (result i32)))
+(import "env" "__errno" (func $__errno (result i32)))
+(import "env" "puts" (func $puts (param i32) (result i32)))
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(elem (i32.const 1) $on_press $wrong_type)
+
+(data (i32.const 1024) "ready\00")
+
+(func $on_press
+  (i32.store (i32.const 2048) (i32.add (i32.load (i32.const 2048)) (i32.const 1))))
+
+(func $wrong_type (param i32))
+
+(func (export "initialize")
+  (local $results i32)
+  (drop (call $lcd_initialize))
+  (local.set $results
+    (i32.or
+      (i32.or
+        (call $lcd_register_btn0_cb (i32.const 99))
+        (i32.shl (call $lcd_register_btn0_cb (i32.const 2)) (i32.const 1)))
+      (i32.or
+        (i32.shl (call $lcd_register_btn0_cb (i32.const 1)) (i32.const 2))
+        (i32.shl (call $lcd_register_btn0_cb (i32.const 0)) (i32.const 3)))))
+  (drop (call $puts (i32.const 1024)))
+  (call $delay (i32.const 20))
+  (call $exit
+    (i32.add
+      (i32.add (local.get $results) (i32.mul (i32.load (call $__errno)) (i32.const 16)))
+      (i32.mul (i32.load (i32.const 2048)) (i32.const 1000)))))
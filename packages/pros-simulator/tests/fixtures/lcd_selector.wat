@@ -0,0 +1,50 @@
+;; An autonomous selector: the left and right LCD buttons cycle through three choices shown on
+;; line 1, and the center button confirms one. Prints "ready" once the callbacks are registered,
+;; then exits with the index of the confirmed choice.
+(import "env" "lcd_initialize" (func $lcd_initialize (result i32)))
+(import "env" "lcd_set_text" (func $lcd_set_text (param i32 i32) (result i32)))
+(import "env" "lcd_register_btn0_cb" (func $lcd_register_btn0_cb (param i32) (result i32)))
+(import "env" "lcd_register_btn1_cb" (func $lcd_register_btn1_cb (param i32) (result i32)))
+(import "env" "lcd_register_btn2_cb" (func $lcd_register_btn2_cb (param i32) (result i32)))
+(import "env" "puts" (func $puts (param i32) (result i32)))
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(elem (i32.const 1) $previous $select $next)
+
+(data (i32.const 1024) "Left\00")
+(data (i32.const 1040) "Right\00")
+(data (i32.const 1056) "Skills\00")
+(data (i32.const 1088) "Select auton:\00")
+(data (i32.const 1104) "ready\00")
+
+;; the index of the choice shown is at 2048, and 2052 is set once one is confirmed
+(func $show
+  (drop (call $lcd_set_text (i32.const 1)
+    (i32.add (i32.const 1024) (i32.mul (i32.load (i32.const 2048)) (i32.const 16))))))
+
+(func $previous
+  (i32.store (i32.const 2048)
+    (i32.rem_u (i32.add (i32.load (i32.const 2048)) (i32.const 2)) (i32.const 3)))
+  (call $show))
+
+(func $next
+  (i32.store (i32.const 2048)
+    (i32.rem_u (i32.add (i32.load (i32.const 2048)) (i32.const 1)) (i32.const 3)))
+  (call $show))
+
+(func $select
+  (i32.store (i32.const 2052) (i32.const 1)))
+
+(func (export "initialize")
+  (drop (call $lcd_initialize))
+  (drop (call $lcd_set_text (i32.const 0) (i32.const 1088)))
+  (call $show)
+  (drop (call $lcd_register_btn0_cb (i32.const 1)))
+  (drop (call $lcd_register_btn1_cb (i32.const 2)))
+  (drop (call $lcd_register_btn2_cb (i32.const 3)))
+  (drop (call $puts (i32.const 1104)))
+  (loop $wait
+    (call $delay (i32.const 1))
+    (br_if $wait (i32.eqz (i32.load (i32.const 2052)))))
+  (call $exit (i32.load (i32.const 2048))))
@@ -0,0 +1,12 @@
+;; Writes `a` to stdout, `b` to stderr, then `c\n` and `d` to stdout, and exits.
+(import "env" "write" (func $write (param i32 i32 i32) (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(data (i32.const 1024) "abc\nd")
+
+(func (export "initialize")
+  (drop (call $write (i32.const 1) (i32.const 1024) (i32.const 1)))
+  (drop (call $write (i32.const 2) (i32.const 1025) (i32.const 1)))
+  (drop (call $write (i32.const 1) (i32.const 1026) (i32.const 2)))
+  (drop (call $write (i32.const 1) (i32.const 1028) (i32.const 1)))
+  (call $exit (i32.const 0)))
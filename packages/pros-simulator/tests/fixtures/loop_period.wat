@@ -0,0 +1,17 @@
+;; Loops ten times every 10ms with `task_delay_until`, then prints "done" and exits.
+(import "env" "task_delay_until" (func $task_delay_until (param i32 i32)))
+(import "env" "millis" (func $millis (result i32)))
+(import "env" "puts" (func $puts (param i32) (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(data (i32.const 1024) "done\00")
+
+(func (export "initialize")
+  (local $i i32)
+  (i32.store (i32.const 2048) (call $millis))
+  (loop $iterations
+    (call $task_delay_until (i32.const 2048) (i32.const 10))
+    (local.set $i (i32.add (local.get $i) (i32.const 1)))
+    (br_if $iterations (i32.lt_u (local.get $i) (i32.const 10))))
+  (drop (call $puts (i32.const 1024)))
+  (call $exit (i32.const 0)))
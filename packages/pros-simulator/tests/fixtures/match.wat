@@ -0,0 +1,20 @@
+;; Waits in each phase entrypoint until the match moves on. `opcontrol` exits with 1 if
+;; `autonomous` didn't run first.
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(func $wait
+  (loop $forever
+    (call $delay (i32.const 5))
+    (br $forever)))
+
+(func (export "initialize"))
+(func (export "autonomous")
+  (i32.store (i32.const 2048) (i32.const 1))
+  (call $wait))
+(func (export "opcontrol")
+  (if (i32.eqz (i32.load (i32.const 2048)))
+    (then (call $exit (i32.const 1))))
+  (call $wait))
+(func (export "disabled")
+  (call $wait))
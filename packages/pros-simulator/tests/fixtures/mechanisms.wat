@@ -0,0 +1,16 @@
+;; A test build that places the robot at the origin facing a ring, runs the intake on port 1 and
+;; the lift on port 2 for 100ms, then reverses the intake to let the ring out and exits.
+(import "env" "sim_set_pose" (func $sim_set_pose (param i32)))
+(import "env" "motor_move_voltage" (func $motor_move_voltage (param i32 i32) (result i32)))
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(func (export "initialize")
+  ;; the pose starts out zeroed: (0, 0) facing 0°
+  (call $sim_set_pose (i32.const 1024))
+  (drop (call $motor_move_voltage (i32.const 1) (i32.const 12000)))
+  (drop (call $motor_move_voltage (i32.const 2) (i32.const 12000)))
+  (call $delay (i32.const 100))
+  (drop (call $motor_move_voltage (i32.const 1) (i32.const -12000)))
+  (call $delay (i32.const 20))
+  (call $exit (i32.const 0)))
@@ -0,0 +1,33 @@
+;; Passes pointers outside of memory to host functions, then exits with a bit set for each one
+;; that failed with `EFAULT` instead of crashing.
+(import "env" "__errno" (func $errno (result i32)))
+(import "env" "puts" (func $puts (param i32) (result i32)))
+(import "env" "write" (func $write (param i32 i32 i32) (result i32)))
+(import "env" "lcd_initialize" (func $lcd_initialize (result i32)))
+(import "env" "lcd_set_text" (func $lcd_set_text (param i32 i32) (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(func $efault (result i32)
+  ;; EFAULT
+  (i32.eq (i32.load (call $errno)) (i32.const 14)))
+
+(func $clear_errno
+  (i32.store (call $errno) (i32.const 0)))
+
+(func (export "initialize")
+  (local $result i32)
+  (drop (call $lcd_initialize))
+
+  (call $clear_errno)
+  (if (i32.and (i32.eq (call $puts (i32.const -16)) (i32.const -1)) (call $efault))
+    (then (local.set $result (i32.or (local.get $result) (i32.const 1)))))
+
+  (call $clear_errno)
+  (if (i32.and (i32.eq (call $write (i32.const 1) (i32.const -16) (i32.const 8)) (i32.const -1)) (call $efault))
+    (then (local.set $result (i32.or (local.get $result) (i32.const 2)))))
+
+  (call $clear_errno)
+  (if (i32.and (i32.eqz (call $lcd_set_text (i32.const 0) (i32.const -16))) (call $efault))
+    (then (local.set $result (i32.or (local.get $result) (i32.const 4)))))
+
+  (call $exit (local.get $result)))
@@ -0,0 +1,41 @@
+;; Drives a motor plugged into port 1 with `motor_move`. Exits with a bit set for each check that
+;; passed, lowest first.
+(import "env" "motor_move" (func $move (param i32 i32) (result i32)))
+(import "env" "__errno" (func $__errno (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(global $PROS_ERR i32 (i32.const 0x7fffffff))
+(global $ENXIO i32 (i32.const 6))
+(global $ENODEV i32 (i32.const 19))
+
+(global $passed (mut i32) (i32.const 0))
+(global $bit (mut i32) (i32.const 1))
+
+(func $check (param $ok i32)
+  (if (local.get $ok)
+    (then (global.set $passed (i32.or (global.get $passed) (global.get $bit)))))
+  (global.set $bit (i32.shl (global.get $bit) (i32.const 1))))
+
+(func $errno (result i32)
+  (i32.load (call $__errno)))
+
+(func (export "initialize")
+  (call $check (i32.eq (call $move (i32.const 1) (i32.const 127)) (i32.const 1)))
+  ;; clamped to 127 with a warning, which isn't an update
+  (call $check (i32.eq (call $move (i32.const 1) (i32.const 200)) (i32.const 1)))
+  (call $check (i32.eq (call $move (i32.const 1) (i32.const -64)) (i32.const 1)))
+  ;; nothing is plugged into port 2
+  (call $check
+    (i32.and
+      (i32.eq (call $move (i32.const 2) (i32.const 64)) (global.get $PROS_ERR))
+      (i32.eq (call $errno) (global.get $ENODEV))))
+  ;; there are only 21 ports, numbered from 1
+  (call $check
+    (i32.and
+      (i32.eq (call $move (i32.const 0) (i32.const 64)) (global.get $PROS_ERR))
+      (i32.eq (call $errno) (global.get $ENXIO))))
+  (call $check
+    (i32.and
+      (i32.eq (call $move (i32.const 22) (i32.const 64)) (global.get $PROS_ERR))
+      (i32.eq (call $errno) (global.get $ENXIO))))
+  (call $exit (global.get $passed)))
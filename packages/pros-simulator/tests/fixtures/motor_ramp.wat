@@ -0,0 +1,9 @@
+;; Drives a motor plugged into port 1 at 12 V for 100 ms, while it speeds up.
+(import "env" "motor_move_voltage" (func $move_voltage (param i32 i32) (result i32)))
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(func (export "initialize")
+  (drop (call $move_voltage (i32.const 1) (i32.const 12000)))
+  (call $delay (i32.const 100))
+  (call $exit (i32.const 0)))
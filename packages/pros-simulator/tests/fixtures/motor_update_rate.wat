@@ -0,0 +1,15 @@
+;; Drives a motor plugged into port 1 every millisecond for 50 ms, like an opcontrol loop, setting
+;; each voltage twice.
+(import "env" "motor_move_voltage" (func $move_voltage (param i32 i32) (result i32)))
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(func (export "initialize")
+  (local $i i32)
+  (loop $drive
+    (local.set $i (i32.add (local.get $i) (i32.const 1)))
+    (drop (call $move_voltage (i32.const 1) (i32.mul (local.get $i) (i32.const 100))))
+    (drop (call $move_voltage (i32.const 1) (i32.mul (local.get $i) (i32.const 100))))
+    (call $delay (i32.const 1))
+    (br_if $drive (i32.lt_u (local.get $i) (i32.const 50))))
+  (call $exit (i32.const 0)))
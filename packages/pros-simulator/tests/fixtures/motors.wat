@@ -0,0 +1,6 @@
+;; Imports a motor API, which the simulator doesn't implement yet.
+(import "env" "motor_move_velocity" (func $motor_move_velocity (param i32 i32) (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(func (export "initialize")
+  (call $exit (i32.const 0)))
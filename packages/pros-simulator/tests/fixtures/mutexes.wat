@@ -0,0 +1,35 @@
+;; Holds a mutex while another task tries to take it, then exits with
+;; `10 * child_took_mutex + parent_retook_mutex`.
+(import "env" "task_create" (func $task_create (param i32 i32 i32 i32 i32) (result i32)))
+(import "env" "mutex_create" (func $mutex_create (result i32)))
+(import "env" "mutex_take" (func $mutex_take (param i32 i32) (result i32)))
+(import "env" "mutex_give" (func $mutex_give (param i32) (result i32)))
+(import "env" "mutex_delete" (func $mutex_delete (param i32)))
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(elem (i32.const 1) $child)
+
+(data (i32.const 1024) "Child\00")
+
+(func $child (param $mutex i32)
+  (i32.store (i32.const 2048) (call $mutex_take (local.get $mutex) (i32.const 5))))
+
+(func (export "initialize")
+  (local $mutex i32)
+  (local $result i32)
+  (i32.store (i32.const 2048) (i32.const -1))
+  (local.set $mutex (call $mutex_create))
+  (drop (call $mutex_take (local.get $mutex) (i32.const -1)))
+  (drop (call $task_create (i32.const 1) (local.get $mutex) (i32.const 8) (i32.const 8192) (i32.const 1024)))
+  (loop $wait
+    (call $delay (i32.const 1))
+    (br_if $wait (i32.eq (i32.load (i32.const 2048)) (i32.const -1))))
+  (drop (call $mutex_give (local.get $mutex)))
+  (local.set $result
+    (i32.add
+      (i32.mul (i32.load (i32.const 2048)) (i32.const 10))
+      (call $mutex_take (local.get $mutex) (i32.const 0))))
+  (drop (call $mutex_give (local.get $mutex)))
+  (call $mutex_delete (local.get $mutex))
+  (call $exit (local.get $result)))
@@ -0,0 +1,37 @@
+;; no allocator
+;; no function table
+;; Uses newlib's system calls like C robot code does, exiting with 7 through `_exit` if they all
+;; behaved, or with the number of the first check that failed. Like C and C++ robot code linked
+;; without `--export-table`, it doesn't export its function table.
+(import "env" "sbrk" (func $sbrk (param i32) (result i32)))
+(import "env" "__errno" (func $errno (result i32)))
+(import "env" "gettimeofday" (func $gettimeofday (param i32 i32) (result i32)))
+(import "env" "isatty" (func $isatty (param i32) (result i32)))
+(import "env" "_exit" (func $_exit (param i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(global (export "__heap_base") i32 (i32.const 0x10000))
+
+(func (export "initialize")
+  ;; the break starts at __heap_base and moves forward
+  (if (i32.ne (call $sbrk (i32.const 100)) (i32.const 0x10000))
+    (then (call $exit (i32.const 1))))
+  (if (i32.ne (call $sbrk (i32.const 0)) (i32.const 0x10064))
+    (then (call $exit (i32.const 2))))
+  ;; growing past the end of memory
+  (if (i32.ne (call $sbrk (i32.const 0x200000)) (i32.const 0x10064))
+    (then (call $exit (i32.const 3))))
+  (if (i32.ne (call $sbrk (i32.const 0x7fffffff)) (i32.const -1))
+    (then (call $exit (i32.const 4))))
+  ;; ENOMEM
+  (if (i32.ne (i32.load (call $errno)) (i32.const 12))
+    (then (call $exit (i32.const 5))))
+
+  ;; some time after 2023
+  (if (i32.or
+        (call $gettimeofday (i32.const 2048) (i32.const 0))
+        (i64.lt_u (i64.load (i32.const 2048)) (i64.const 1700000000)))
+    (then (call $exit (i32.const 6))))
+  (if (i32.eqz (call $isatty (i32.const 1)))
+    (then (call $exit (i32.const 8))))
+  (call $_exit (i32.const 7)))
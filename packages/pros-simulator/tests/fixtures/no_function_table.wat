@@ -0,0 +1,19 @@
+;; no function table
+;; Robot code linked without `--export-table`, which is wasm-ld's default, so its function table
+;; is internal. Tries to create a task, which needs the table. Exits with 1 if the task was
+;; created.
+(import "env" "task_create" (func $task_create (param i32 i32 i32 i32 i32) (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(table 2 funcref)
+(elem (i32.const 1) $child)
+
+(data (i32.const 1024) "Child\00")
+
+(func $child (param i32))
+
+(func (export "initialize")
+  (call $exit
+    (i32.ne
+      (call $task_create (i32.const 1) (i32.const 0) (i32.const 8) (i32.const 8192) (i32.const 1024))
+      (i32.const 0))))
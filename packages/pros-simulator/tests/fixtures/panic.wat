@@ -0,0 +1,7 @@
+;; Aborts with a Rust panic message.
+(import "env" "sim_abort" (func $sim_abort (param i32)))
+
+(data (i32.const 1024) "panicked at src/main.rs:3:5:\0aboom\00")
+
+(func (export "initialize")
+  (call $sim_abort (i32.const 1024)))
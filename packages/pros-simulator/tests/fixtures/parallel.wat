@@ -0,0 +1,23 @@
+;; Two tasks that busy-wait on each other without ever calling into the simulator, which only
+;; finishes if they run in parallel.
+(import "env" "task_create" (func $task_create (param i32 i32 i32 i32 i32) (result i32)))
+(import "env" "puts" (func $puts (param i32) (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(elem (i32.const 1) $child)
+
+(data (i32.const 1024) "Child\00")
+(data (i32.const 1040) "in parallel\00")
+
+(func $child (param i32)
+  (i32.atomic.store (i32.const 2048) (i32.const 1))
+  (loop $wait
+    (br_if $wait (i32.eqz (i32.atomic.load (i32.const 2052))))))
+
+(func (export "initialize")
+  (drop (call $task_create (i32.const 1) (i32.const 0) (i32.const 8) (i32.const 8192) (i32.const 1024)))
+  (loop $wait
+    (br_if $wait (i32.eqz (i32.atomic.load (i32.const 2048)))))
+  (i32.atomic.store (i32.const 2052) (i32.const 1))
+  (drop (call $puts (i32.const 1040)))
+  (call $exit (i32.const 0)))
@@ -0,0 +1,23 @@
+;; Calls APIs the simulator doesn't implement that return an integer, a double and nothing, then
+;; exits with 1 if they returned `PROS_ERR` and `PROS_ERR_F` followed by the last `errno`. Without
+;; permissive mode, `initialize` is stopped and `opcontrol` exits with 0.
+(import "env" "motor_move_velocity" (func $motor_move_velocity (param i32 i32) (result i32)))
+(import "env" "motor_get_position" (func $motor_get_position (param i32) (result f64)))
+(import "env" "vision_print_signature" (func $vision_print_signature (param i32)))
+(import "env" "__errno" (func $__errno (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(func (export "initialize")
+  (local $ok i32)
+  (local.set $ok
+    (i32.and
+      (i32.eq (call $motor_move_velocity (i32.const 1) (i32.const 127)) (i32.const 0x7fffffff))
+      (f64.eq (call $motor_get_position (i32.const 1)) (f64.const inf))))
+  (call $vision_print_signature (i32.const 0))
+  (call $exit
+    (i32.add
+      (i32.mul (local.get $ok) (i32.const 100))
+      (i32.load (call $__errno)))))
+
+(func (export "opcontrol")
+  (call $exit (i32.const 0)))
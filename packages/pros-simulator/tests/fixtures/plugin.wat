@@ -0,0 +1,9 @@
+;; Reads a distance sensor simulated by a plugin twice, and exits with the sum of the readings.
+(import "env" "lidar_distance" (func $lidar_distance (param i32) (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(func (export "initialize")
+  (call $exit
+    (i32.add
+      (call $lidar_distance (i32.const 10))
+      (call $lidar_distance (i32.const 10)))))
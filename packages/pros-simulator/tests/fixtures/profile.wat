@@ -0,0 +1,17 @@
+;; Spins in `$hot` for 50 ms without yielding, then exits.
+(import "env" "millis" (func $millis (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(func $hot (param $until i32)
+  (local $i i32)
+  (loop $spin
+    ;; only check the clock every so often, so that most samples land in this function
+    (local.set $i (i32.const 0))
+    (loop $busy
+      (local.set $i (i32.add (local.get $i) (i32.const 1)))
+      (br_if $busy (i32.lt_u (local.get $i) (i32.const 100000))))
+    (br_if $spin (i32.lt_u (call $millis) (local.get $until)))))
+
+(func $initialize (export "initialize")
+  (call $hot (i32.add (call $millis) (i32.const 50)))
+  (call $exit (i32.const 0)))
@@ -0,0 +1,9 @@
+;; Describes itself in a `pros_program` section, padded with NULs like a fixed-size static. The
+;; second section has a slot that doesn't exist and a key the simulator doesn't know.
+(@custom "pros_program" "name=Competition Bot\nversion=1.2.0\nslot=3\nicon=USER902x.bmp\n\00\00\00")
+(@custom "pros_program" "description=Worlds code\nslot=9\ncolor=red\n")
+
+(import "env" "exit" (func $exit (param i32)))
+
+(func (export "initialize")
+  (call $exit (i32.const 0)))
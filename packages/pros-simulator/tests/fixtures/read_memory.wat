@@ -0,0 +1,11 @@
+;; Exports the address of a variable holding 42, like a linker-exported data symbol, and keeps
+;; running long enough for it to be read.
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(global (export "ODOMETRY_STATE") i32 (i32.const 2048))
+(data (i32.const 2048) "\2a\00\00\00")
+
+(func (export "initialize")
+  (call $delay (i32.const 50))
+  (call $exit (i32.const 0)))
@@ -0,0 +1,51 @@
+;; Exercises the device registry with a motor plugged into port 1 (zero-indexed port 0). Exits
+;; with a bit set for each check that passed, lowest first.
+(import "env" "registry_bind_port" (func $bind (param i32 i32) (result i32)))
+(import "env" "registry_unbind_port" (func $unbind (param i32) (result i32)))
+(import "env" "registry_get_bound_type" (func $bound (param i32) (result i32)))
+(import "env" "registry_get_plugged_type" (func $plugged (param i32) (result i32)))
+(import "env" "__errno" (func $__errno (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(global $E_DEVICE_MOTOR i32 (i32.const 2))
+(global $PROS_ERR i32 (i32.const 0x7fffffff))
+(global $ENXIO i32 (i32.const 6))
+(global $EADDRINUSE i32 (i32.const 112))
+
+(global $passed (mut i32) (i32.const 0))
+(global $bit (mut i32) (i32.const 1))
+
+(func $check (param $ok i32)
+  (if (local.get $ok)
+    (then (global.set $passed (i32.or (global.get $passed) (global.get $bit)))))
+  (global.set $bit (i32.shl (global.get $bit) (i32.const 1))))
+
+(func $errno (result i32)
+  (i32.load (call $__errno)))
+
+(func (export "initialize")
+  (call $check (i32.eq (call $plugged (i32.const 0)) (global.get $E_DEVICE_MOTOR)))
+  (call $check (i32.eqz (call $plugged (i32.const 1))))
+  (call $check (i32.eqz (call $bound (i32.const 0))))
+  (call $check (i32.eq (call $bind (i32.const 0) (global.get $E_DEVICE_MOTOR)) (i32.const 1)))
+  (call $check (i32.eq (call $bound (i32.const 0)) (global.get $E_DEVICE_MOTOR)))
+  ;; the port is already registered
+  (call $check
+    (i32.and
+      (i32.eq (call $bind (i32.const 0) (global.get $E_DEVICE_MOTOR)) (global.get $PROS_ERR))
+      (i32.eq (call $errno) (global.get $EADDRINUSE))))
+  ;; nothing is plugged into port 2
+  (call $check
+    (i32.and
+      (i32.eq (call $bind (i32.const 1) (global.get $E_DEVICE_MOTOR)) (global.get $PROS_ERR))
+      (i32.eq (call $errno) (global.get $ENXIO))))
+  (call $check
+    (i32.and
+      (i32.eq (call $unbind (i32.const 0)) (i32.const 1))
+      (i32.eqz (call $bound (i32.const 0)))))
+  ;; there are only 21 ports
+  (call $check
+    (i32.and
+      (i32.eq (call $plugged (i32.const 21)) (i32.const 255))
+      (i32.eq (call $errno) (global.get $ENXIO))))
+  (call $exit (global.get $passed)))
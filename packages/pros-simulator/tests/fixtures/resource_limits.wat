@@ -0,0 +1,22 @@
+;; Creates 5 tasks that wait forever, then grows memory by 100 pages (6.4 MB) and exits with 0.
+(import "env" "task_create" (func $task_create (param i32 i32 i32 i32 i32) (result i32)))
+(import "env" "task_delay" (func $task_delay (param i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(elem (i32.const 1) $child)
+
+(data (i32.const 1024) "Child\00")
+
+(func $child (param i32)
+  (loop $forever
+    (call $task_delay (i32.const 1000))
+    (br $forever)))
+
+(func (export "initialize")
+  (local $i i32)
+  (loop $tasks
+    (drop (call $task_create (i32.const 1) (i32.const 0) (i32.const 8) (i32.const 8192) (i32.const 1024)))
+    (local.set $i (i32.add (local.get $i) (i32.const 1)))
+    (br_if $tasks (i32.lt_u (local.get $i) (i32.const 5))))
+  (drop (memory.grow (i32.const 100)))
+  (call $exit (i32.const 0)))
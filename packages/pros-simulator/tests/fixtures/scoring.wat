@@ -0,0 +1,18 @@
+;; A test build that drives the robot into (24, 0), out to (72, 0) and back, waiting 20ms at
+;; each pose, then exits.
+(import "env" "sim_set_pose" (func $sim_set_pose (param i32)))
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(func $move_to (param $x f64)
+  (f64.store (i32.const 1024) (local.get $x))
+  (f64.store (i32.const 1032) (f64.const 0))
+  (f64.store (i32.const 1040) (f64.const 0))
+  (call $sim_set_pose (i32.const 1024))
+  (call $delay (i32.const 20)))
+
+(func (export "initialize")
+  (call $move_to (f64.const 24))
+  (call $move_to (f64.const 72))
+  (call $move_to (f64.const 24))
+  (call $exit (i32.const 0)))
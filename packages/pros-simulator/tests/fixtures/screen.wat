@@ -0,0 +1,37 @@
+;; A test build that fills the top-left 10x10 pixels of the screen with red, waits 20ms for it to
+;; be sent, then copies two pixels to (100, 100), renders, and checks that the screen is being
+;; touched at (50, 60) before exiting.
+(import "env" "vexDisplayForegroundColor" (func $foreground (param i32)))
+(import "env" "vexDisplayRectFill" (func $rect_fill (param i32 i32 i32 i32)))
+(import "env" "vexDisplayCopyRect" (func $copy_rect (param i32 i32 i32 i32 i32 i32)))
+(import "env" "vexDisplayRender" (func $render (param i32 i32)))
+(import "env" "vexTouchDataGet" (func $touch_data_get (param i32)))
+(import "env" "sim_assert" (func $sim_assert (param i32 i32)))
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(data (i32.const 1024) "pressed at (50, 60)\00")
+(data (i32.const 1056) "still pressed\00")
+
+(func (export "initialize")
+  (call $foreground (i32.const 0xff0000))
+  (call $rect_fill (i32.const 0) (i32.const 0) (i32.const 9) (i32.const 9))
+  (call $delay (i32.const 20))
+
+  (i32.store (i32.const 2048) (i32.const 0x112233))
+  (i32.store (i32.const 2052) (i32.const 0x445566))
+  (call $copy_rect (i32.const 100) (i32.const 100) (i32.const 101) (i32.const 100)
+    (i32.const 2048) (i32.const 2))
+  (call $render (i32.const 0) (i32.const 0))
+
+  (call $touch_data_get (i32.const 3072))
+  (call $sim_assert
+    (i32.and
+      (i32.and
+        (i32.eq (i32.load (i32.const 3072)) (i32.const 1))
+        (i32.eq (i32.load16_s (i32.const 3076)) (i32.const 50)))
+      (i32.eq (i32.load16_s (i32.const 3078)) (i32.const 60)))
+    (i32.const 1024))
+  (call $touch_data_get (i32.const 3072))
+  (call $sim_assert (i32.eq (i32.load (i32.const 3072)) (i32.const 2)) (i32.const 1056))
+  (call $exit (i32.const 0)))
@@ -0,0 +1,63 @@
+;; A test build that reads the last samples of the `square` and `arc` sensor traces and checks
+;; them against where the robot should end up, then exits.
+(import "env" "sim_sensor_trace" (func $sim_sensor_trace (param i32 i32 i32) (result i32)))
+(import "env" "sim_assert" (func $sim_assert (param i32 i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(data (i32.const 1024) "square\00")
+(data (i32.const 1032) "arc\00")
+(data (i32.const 1040) "figure_eight\00")
+(data (i32.const 1056) "unknown traces aren't found\00")
+(data (i32.const 1088) "square has 971 samples\00")
+(data (i32.const 1120) "square ends where it started\00")
+(data (i32.const 1152) "square's wheels differ by a full turn\00")
+(data (i32.const 1200) "arc ends at (24, 24) facing 90\00")
+(data (i32.const 1232) "imu heading is close\00")
+
+(func $near (param $a f64) (param $b f64) (param $tolerance f64) (result i32)
+  (f64.le (f64.abs (f64.sub (local.get $a) (local.get $b))) (local.get $tolerance)))
+
+;; Reads the last sample of a trace into 2048, returning how many samples it has.
+(func $last (param $name i32) (result i32)
+  (local $len i32)
+  (local.set $len (call $sim_sensor_trace (local.get $name) (i32.const 0) (i32.const 2048)))
+  (drop (call $sim_sensor_trace
+    (local.get $name) (i32.sub (local.get $len) (i32.const 1)) (i32.const 2048)))
+  (local.get $len))
+
+(func (export "initialize")
+  (call $sim_assert
+    (i32.eq (call $sim_sensor_trace (i32.const 1040) (i32.const 0) (i32.const 2048))
+      (i32.const -1))
+    (i32.const 1056))
+
+  (call $sim_assert (i32.eq (call $last (i32.const 1024)) (i32.const 971)) (i32.const 1088))
+  (call $sim_assert
+    (i32.and
+      (i32.and
+        (call $near (f64.load (i32.const 2048)) (f64.const 9700) (f64.const 0))
+        (call $near (f64.load (i32.const 2056)) (f64.const 0) (f64.const 1e-9)))
+      (i32.and
+        (call $near (f64.load (i32.const 2064)) (f64.const 0) (f64.const 1e-9))
+        (call $near (f64.load (i32.const 2072)) (f64.const 360) (f64.const 1e-9))))
+    (i32.const 1120))
+  ;; a full turn times the 10 inch track width, give or take a tick of each encoder
+  (call $sim_assert
+    (call $near
+      (f64.sub (f64.load (i32.const 2112)) (f64.load (i32.const 2120)))
+      (f64.const 62.8319) (f64.const 0.05))
+    (i32.const 1152))
+  ;; 9.7 seconds of drift at 0.02 degrees per second, plus noise
+  (call $sim_assert
+    (call $near (f64.load (i32.const 2080)) (f64.const 360.194) (f64.const 0.3))
+    (i32.const 1232))
+
+  (drop (call $last (i32.const 1032)))
+  (call $sim_assert
+    (i32.and
+      (i32.and
+        (call $near (f64.load (i32.const 2056)) (f64.const 24) (f64.const 1e-9))
+        (call $near (f64.load (i32.const 2064)) (f64.const 24) (f64.const 1e-9)))
+      (call $near (f64.load (i32.const 2072)) (f64.const 90) (f64.const 1e-9)))
+    (i32.const 1200))
+  (call $exit (i32.const 0)))
@@ -0,0 +1,16 @@
+;; Writes 30 lines of 100 bytes to the console, then exits with how many milliseconds that took.
+(import "env" "write" (func $write (param i32 i32 i32) (result i32)))
+(import "env" "millis" (func $millis (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(func (export "initialize")
+  (local $i i32)
+  (local $start i32)
+  (memory.fill (i32.const 4096) (i32.const 0x78) (i32.const 99))
+  (i32.store8 (i32.const 4195) (i32.const 0x0a))
+  (local.set $start (call $millis))
+  (loop $lines
+    (drop (call $write (i32.const 1) (i32.const 4096) (i32.const 100)))
+    (local.set $i (i32.add (local.get $i) (i32.const 1)))
+    (br_if $lines (i32.lt_u (local.get $i) (i32.const 30))))
+  (call $exit (i32.sub (call $millis) (local.get $start))))
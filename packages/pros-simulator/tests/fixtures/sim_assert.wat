@@ -0,0 +1,11 @@
+;; Makes one passing and one failing assertion, then exits.
+(import "env" "sim_assert" (func $sim_assert (param i32 i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(data (i32.const 1024) "odometry is calibrated\00")
+(data (i32.const 1056) "heading is within 1 degree\00")
+
+(func (export "initialize")
+  (call $sim_assert (i32.const 1) (i32.const 1024))
+  (call $sim_assert (i32.const 0) (i32.const 1056))
+  (call $exit (i32.const 0)))
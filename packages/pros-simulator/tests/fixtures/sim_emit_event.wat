@@ -0,0 +1,16 @@
+;; Sends an odometry event and an empty state event, then one whose payload is out of bounds,
+;; and exits with the sum of what the three calls returned.
+(import "env" "sim_emit_event" (func $sim_emit_event (param i32 i32 i32) (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(data (i32.const 1024) "odom\00")
+(data (i32.const 1040) "state\00")
+(data (i32.const 1056) "\01\02\03\04")
+
+(func (export "initialize")
+  (call $exit
+    (i32.add
+      (i32.add
+        (call $sim_emit_event (i32.const 1024) (i32.const 1056) (i32.const 4))
+        (call $sim_emit_event (i32.const 1040) (i32.const 1056) (i32.const 0)))
+      (call $sim_emit_event (i32.const 1024) (i32.const -16) (i32.const 32)))))
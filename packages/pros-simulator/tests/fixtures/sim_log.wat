@@ -0,0 +1,15 @@
+;; Logs a warning and a debug message, then tries an invalid level and exits with the sum of
+;; what the three calls returned.
+(import "env" "sim_log" (func $sim_log (param i32 i32) (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(data (i32.const 1024) "battery is low\00")
+(data (i32.const 1056) "odometry reset\00")
+
+(func (export "initialize")
+  (call $exit
+    (i32.add
+      (i32.add
+        (call $sim_log (i32.const 2) (i32.const 1024))
+        (call $sim_log (i32.const 4) (i32.const 1056)))
+      (call $sim_log (i32.const 9) (i32.const 1024)))))
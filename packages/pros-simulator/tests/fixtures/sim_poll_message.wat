@@ -0,0 +1,25 @@
+;; Polls for `auton` messages into 4 byte buffers until there are none left, then exits with the
+;; byte after the first buffer (which a truncated message mustn't overwrite) times 1000, the
+;; second buffer's first byte times 100, and the sum of what the three polls returned.
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "sim_poll_message" (func $sim_poll_message (param i32 i32 i32) (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(data (i32.const 1024) "auton\00")
+
+(func (export "initialize")
+  (local $sum i32)
+  ;; give the system daemon time to receive the messages
+  (call $delay (i32.const 20))
+  (local.set $sum
+    (i32.add
+      (i32.add
+        (call $sim_poll_message (i32.const 1024) (i32.const 2048) (i32.const 4))
+        (call $sim_poll_message (i32.const 1024) (i32.const 2056) (i32.const 4)))
+      (call $sim_poll_message (i32.const 1024) (i32.const 2064) (i32.const 4))))
+  (call $exit
+    (i32.add
+      (i32.add
+        (i32.mul (i32.load8_u (i32.const 2052)) (i32.const 1000))
+        (i32.mul (i32.load8_u (i32.const 2056)) (i32.const 100)))
+      (local.get $sum))))
@@ -0,0 +1,26 @@
+;; Times a 5 ms `frame` with a 3 ms `draw` inside it and a `late` span that ending `frame` also
+;; ends, fails to end a span that was never begun, then begins a `tail` span that the simulation
+;; ends. Exits with the sum of what the calls returned.
+(import "env" "sim_profile_begin" (func $begin (param i32) (result i32)))
+(import "env" "sim_profile_end" (func $end (param i32) (result i32)))
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(data (i32.const 1024) "frame\00")
+(data (i32.const 1040) "draw\00")
+(data (i32.const 1056) "late\00")
+(data (i32.const 1072) "missing\00")
+(data (i32.const 1088) "tail\00")
+
+(func (export "initialize")
+  (local $sum i32)
+  (local.set $sum (call $begin (i32.const 1024)))
+  (call $delay (i32.const 2))
+  (local.set $sum (i32.add (local.get $sum) (call $begin (i32.const 1040))))
+  (call $delay (i32.const 3))
+  (local.set $sum (i32.add (local.get $sum) (call $end (i32.const 1040))))
+  (local.set $sum (i32.add (local.get $sum) (call $begin (i32.const 1056))))
+  (local.set $sum (i32.add (local.get $sum) (call $end (i32.const 1024))))
+  (local.set $sum (i32.add (local.get $sum) (call $end (i32.const 1072))))
+  (local.set $sum (i32.add (local.get $sum) (call $begin (i32.const 1088))))
+  (call $exit (local.get $sum)))
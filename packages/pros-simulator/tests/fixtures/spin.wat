@@ -0,0 +1,4 @@
+;; Spins forever without ever calling into the simulator.
+(func $initialize (export "initialize")
+  (loop $spin
+    (br $spin)))
@@ -0,0 +1,18 @@
+;; Exits with a bitmask of what ran before initialize: 1 if cold_init ran and 2 if hot_init ran,
+;; plus 4 if it's a hot start according to sim_capability.
+(import "env" "sim_capability" (func $sim_capability (param i32) (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(data (i32.const 1024) "hot-start\00")
+
+(func (export "cold_init")
+  (i32.store (i32.const 2048) (i32.or (i32.load (i32.const 2048)) (i32.const 1))))
+
+(func (export "hot_init")
+  (i32.store (i32.const 2048) (i32.or (i32.load (i32.const 2048)) (i32.const 2))))
+
+(func (export "initialize")
+  (call $exit
+    (i32.or
+      (i32.load (i32.const 2048))
+      (i32.shl (call $sim_capability (i32.const 1024)) (i32.const 2)))))
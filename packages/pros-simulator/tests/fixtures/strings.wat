@@ -0,0 +1,11 @@
+;; Prints a string with invalid UTF-8, then a string longer than the simulator will read.
+(import "env" "puts" (func $puts (param i32) (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(data (i32.const 1024) "bad \ff\fe bytes\00")
+
+(func (export "initialize")
+  (drop (call $puts (i32.const 1024)))
+  (memory.fill (i32.const 200000) (i32.const 0x61) (i32.const 100000))
+  (drop (call $puts (i32.const 200000)))
+  (call $exit (i32.const 0)))
@@ -0,0 +1,37 @@
+;; Checks that other tasks can't run inside an rtos_suspend_all critical section, even if the
+;; current task yields, and that blocking inside one crashes like it would on FreeRTOS.
+(import "env" "task_create" (func $task_create (param i32 i32 i32 i32 i32) (result i32)))
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "rtos_suspend_all" (func $rtos_suspend_all))
+(import "env" "rtos_resume_all" (func $rtos_resume_all (result i32)))
+(import "env" "puts" (func $puts (param i32) (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(elem (i32.const 1) $child)
+
+(data (i32.const 1024) "atomic\00")
+(data (i32.const 1056) "Child\00")
+
+(func $child (param i32)
+  (i32.store (i32.const 2048) (i32.const 1)))
+
+(func (export "initialize")
+  (local $i i32)
+  (call $rtos_suspend_all)
+  (drop (call $task_create (i32.const 1) (i32.const 0) (i32.const 8) (i32.const 8192) (i32.const 1056)))
+  (loop $yield
+    (call $delay (i32.const 0))
+    (local.set $i (i32.add (local.get $i) (i32.const 1)))
+    (br_if $yield (i32.lt_u (local.get $i) (i32.const 10))))
+  (if (i32.load (i32.const 2048))
+    (then (call $exit (i32.const 1))))
+  ;; the yields above were deferred until now
+  (if (i32.eqz (call $rtos_resume_all))
+    (then (call $exit (i32.const 2))))
+  (if (i32.eqz (i32.load (i32.const 2048)))
+    (then (call $exit (i32.const 3))))
+  (drop (call $puts (i32.const 1024)))
+
+  (call $rtos_suspend_all)
+  (call $delay (i32.const 1))
+  (call $exit (i32.const 0)))
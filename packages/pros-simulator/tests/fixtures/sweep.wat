@@ -0,0 +1,15 @@
+;; Drives "forward" by its tuning constant: once it's had time to be changed, places the robot
+;; at x = DRIVE_KP and waits for a telemetry snapshot to see it there.
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "sim_set_pose" (func $sim_set_pose (param i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(global (export "DRIVE_KP") i32 (i32.const 2048))
+(data (i32.const 2048) "\00\00\80\3f")
+
+(func (export "initialize")
+  (call $delay (i32.const 20))
+  (f64.store (i32.const 1024) (f64.promote_f32 (f32.load (i32.const 2048))))
+  (call $sim_set_pose (i32.const 1024))
+  (call $delay (i32.const 50))
+  (call $exit (i32.const 0)))
@@ -0,0 +1,22 @@
+;; Counts the tasks before and after creating one, exiting with `10 * before + after`.
+(import "env" "task_create" (func $task_create (param i32 i32 i32 i32 i32) (result i32)))
+(import "env" "task_get_count" (func $task_get_count (result i32)))
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(elem (i32.const 1) $child)
+
+(data (i32.const 1024) "Child\00")
+
+(func $child (param i32)
+  (call $delay (i32.const 5)))
+
+(func (export "initialize")
+  (local $before i32)
+  (local.set $before (call $task_get_count))
+  (drop (call $task_create (i32.const 1) (i32.const 0) (i32.const 8) (i32.const 8192) (i32.const 1024)))
+  (call $delay (i32.const 1))
+  (call $exit
+    (i32.add
+      (i32.mul (local.get $before) (i32.const 10))
+      (call $task_get_count))))
@@ -0,0 +1,29 @@
+;; Tries to create tasks with a bad priority, an entrypoint that's out of bounds, one that isn't
+;; a function and one with the wrong signature, then a valid task. Exits with 1 if any of the
+;; invalid tasks were created, plus 2 if the valid task wasn't.
+(import "env" "task_create" (func $task_create (param i32 i32 i32 i32 i32) (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(elem (i32.const 1) $child $wrong_signature)
+
+(data (i32.const 1024) "Child\00")
+
+(func $child (param i32))
+(func $wrong_signature (param i32 i32))
+
+(func (export "initialize")
+  (local $invalid i32)
+  (local.set $invalid
+    (i32.or
+      (i32.or
+        (call $task_create (i32.const 1) (i32.const 0) (i32.const 0) (i32.const 8192) (i32.const 1024))
+        (call $task_create (i32.const 100) (i32.const 0) (i32.const 8) (i32.const 8192) (i32.const 1024)))
+      (i32.or
+        (call $task_create (i32.const 3) (i32.const 0) (i32.const 8) (i32.const 8192) (i32.const 1024))
+        (call $task_create (i32.const 2) (i32.const 0) (i32.const 8) (i32.const 8192) (i32.const 1024)))))
+  (call $exit
+    (i32.or
+      (i32.ne (local.get $invalid) (i32.const 0))
+      (i32.shl
+        (i32.eqz (call $task_create (i32.const 1) (i32.const 0) (i32.const 8) (i32.const 8192) (i32.const 1024)))
+        (i32.const 1)))))
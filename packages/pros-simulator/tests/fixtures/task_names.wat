@@ -0,0 +1,14 @@
+;; Prints the current task's name twice, exiting with 1 if `task_get_name` returned a new copy
+;; of the name the second time.
+(import "env" "task_get_name" (func $task_get_name (param i32) (result i32)))
+(import "env" "puts" (func $puts (param i32) (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(func (export "initialize")
+  (local $name i32)
+  (local.set $name (call $task_get_name (i32.const 0)))
+  (drop (call $puts (local.get $name)))
+  (if (i32.ne (call $task_get_name (i32.const 0)) (local.get $name))
+    (then (call $exit (i32.const 1))))
+  (drop (call $puts (local.get $name)))
+  (call $exit (i32.const 0)))
@@ -0,0 +1,23 @@
+;; Spawns a task and waits for it to finish before printing and exiting.
+(import "env" "task_create" (func $task_create (param i32 i32 i32 i32 i32) (result i32)))
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "puts" (func $puts (param i32) (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(elem (i32.const 1) $child)
+
+(data (i32.const 1024) "from child\00")
+(data (i32.const 1040) "from parent\00")
+(data (i32.const 1056) "Child\00")
+
+(func $child (param $message i32)
+  (drop (call $puts (local.get $message)))
+  (i32.store (i32.const 2048) (i32.const 1)))
+
+(func (export "initialize")
+  (drop (call $task_create (i32.const 1) (i32.const 1024) (i32.const 8) (i32.const 8192) (i32.const 1056)))
+  (loop $wait
+    (call $delay (i32.const 1))
+    (br_if $wait (i32.eqz (i32.load (i32.const 2048)))))
+  (drop (call $puts (i32.const 1040)))
+  (call $exit (i32.const 0)))
@@ -0,0 +1,36 @@
+;; A unit test compiled into the robot code. It places the robot at (24, -36) facing 90°, skips
+;; ahead 5 seconds, and prints the `test` config value, the value cut short to fit a 3-byte
+;; buffer, and whether `missing` is set.
+(import "env" "sim_set_pose" (func $sim_set_pose (param i32)))
+(import "env" "sim_advance_time" (func $sim_advance_time (param i32)))
+(import "env" "sim_config_get" (func $sim_config_get (param i32 i32 i32) (result i32)))
+(import "env" "millis" (func $millis (result i32)))
+(import "env" "puts" (func $puts (param i32) (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(data (i32.const 1024) "\00\00\00\00\00\00\38\40\00\00\00\00\00\00\42\c0\00\00\00\00\00\80\56\40")
+(data (i32.const 1056) "test\00")
+(data (i32.const 1064) "missing\00")
+(data (i32.const 1072) "advanced\00")
+(data (i32.const 1088) "unset\00")
+
+(func (export "initialize")
+  (local $start i32)
+  (call $sim_set_pose (i32.const 1024))
+
+  (local.set $start (call $millis))
+  (call $sim_advance_time (i32.const 5000))
+  (if (i32.ge_u (i32.sub (call $millis) (local.get $start)) (i32.const 5000))
+    (then (drop (call $puts (i32.const 1072)))))
+
+  ;; the whole value, then as much as fits in 3 bytes
+  (if (i32.ne (call $sim_config_get (i32.const 1056) (i32.const 2048) (i32.const 64)) (i32.const 5))
+    (then (call $exit (i32.const 1))))
+  (drop (call $puts (i32.const 2048)))
+  (if (i32.ne (call $sim_config_get (i32.const 1056) (i32.const 2048) (i32.const 3)) (i32.const 5))
+    (then (call $exit (i32.const 2))))
+  (drop (call $puts (i32.const 2048)))
+
+  (if (i32.eq (call $sim_config_get (i32.const 1064) (i32.const 2048) (i32.const 64)) (i32.const -1))
+    (then (drop (call $puts (i32.const 1088)))))
+  (call $exit (i32.const 0)))
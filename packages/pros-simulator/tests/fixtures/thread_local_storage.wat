@@ -0,0 +1,27 @@
+;; Uses thread local storage through the current task and a handle that doesn't belong to a task,
+;; then exits with a bit set for each check that passed.
+(import "env" "__errno" (func $errno (result i32)))
+(import "env" "pvTaskGetThreadLocalStoragePointer" (func $get (param i32 i32) (result i32)))
+(import "env" "vTaskSetThreadLocalStoragePointer" (func $set (param i32 i32 i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(func (export "initialize")
+  (local $result i32)
+
+  (call $set (i32.const 0) (i32.const 1) (i32.const 42))
+  (if (i32.eq (call $get (i32.const 0) (i32.const 1)) (i32.const 42))
+    (then (local.set $result (i32.or (local.get $result) (i32.const 1)))))
+
+  ;; NULL for an unknown task
+  (if (i32.eqz (call $get (i32.const 12345) (i32.const 1)))
+    (then (local.set $result (i32.or (local.get $result) (i32.const 2)))))
+
+  ;; EINVAL for an unknown task, without touching the current task's storage
+  (i32.store (call $errno) (i32.const 0))
+  (call $set (i32.const 12345) (i32.const 1) (i32.const 7))
+  (if (i32.and
+        (i32.eq (i32.load (call $errno)) (i32.const 22))
+        (i32.eq (call $get (i32.const 0) (i32.const 1)) (i32.const 42)))
+    (then (local.set $result (i32.or (local.get $result) (i32.const 4)))))
+
+  (call $exit (local.get $result)))
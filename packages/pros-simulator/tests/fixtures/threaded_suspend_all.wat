@@ -0,0 +1,38 @@
+;; Checks that a task held up by an rtos_suspend_all critical section runs once the scheduler is
+;; resumed. Exits with 1 if the task ran inside the critical section, or 2 if it never ran.
+(import "env" "task_create" (func $task_create (param i32 i32 i32 i32 i32) (result i32)))
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "millis" (func $millis (result i32)))
+(import "env" "rtos_suspend_all" (func $rtos_suspend_all))
+(import "env" "rtos_resume_all" (func $rtos_resume_all (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(elem (i32.const 1) $child)
+
+(data (i32.const 1056) "Child\00")
+
+(func $child (param i32)
+  (i32.store (i32.const 2048) (i32.const 1)))
+
+(func (export "initialize")
+  (local $i i32)
+  (local $start i32)
+  (call $rtos_suspend_all)
+  (drop (call $task_create (i32.const 1) (i32.const 0) (i32.const 8) (i32.const 8192) (i32.const 1056)))
+  ;; long enough for the task's thread to start and wait
+  (local.set $start (call $millis))
+  (loop $yield
+    (call $delay (i32.const 0))
+    (br_if $yield (i32.lt_u (i32.sub (call $millis) (local.get $start)) (i32.const 20))))
+  (if (i32.load (i32.const 2048))
+    (then (call $exit (i32.const 1))))
+  (drop (call $rtos_resume_all))
+
+  (local.set $i (i32.const 0))
+  (loop $wait
+    (if (i32.load (i32.const 2048))
+      (then (call $exit (i32.const 0))))
+    (call $delay (i32.const 1))
+    (local.set $i (i32.add (local.get $i) (i32.const 1)))
+    (br_if $wait (i32.lt_u (local.get $i) (i32.const 1000))))
+  (call $exit (i32.const 2)))
@@ -0,0 +1,29 @@
+;; Checks that delays and mutex timeouts last at least the given number of ticks as measured by
+;; `millis`, exiting with 0 if they did.
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "millis" (func $millis (result i32)))
+(import "env" "mutex_create" (func $mutex_create (result i32)))
+(import "env" "mutex_take" (func $mutex_take (param i32 i32) (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(func (export "initialize")
+  (local $i i32)
+  (local $start i32)
+  (local $mutex i32)
+  (loop $delays
+    (local.set $start (call $millis))
+    (call $delay (i32.const 1))
+    (if (i32.lt_u (i32.sub (call $millis) (local.get $start)) (i32.const 1))
+      (then (call $exit (i32.const 1))))
+    (local.set $i (i32.add (local.get $i) (i32.const 1)))
+    (br_if $delays (i32.lt_u (local.get $i) (i32.const 10))))
+
+  ;; the mutex is already held, so the second take times out
+  (local.set $mutex (call $mutex_create))
+  (drop (call $mutex_take (local.get $mutex) (i32.const 0)))
+  (local.set $start (call $millis))
+  (if (call $mutex_take (local.get $mutex) (i32.const 5))
+    (then (call $exit (i32.const 2))))
+  (if (i32.lt_u (i32.sub (call $millis) (local.get $start)) (i32.const 5))
+    (then (call $exit (i32.const 3))))
+  (call $exit (i32.const 0)))
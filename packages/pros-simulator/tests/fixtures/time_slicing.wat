@@ -0,0 +1,29 @@
+;; Two tasks of the same priority that busy-wait on each other without blocking or yielding, so
+;; they only make progress if they're switched between at tick boundaries. `initialize` creates
+;; the other task at its own priority, and they take turns setting flags at addresses 2048, 2052
+;; and 2056, each spinning until the other has set the flag before. Exits with 0 once the last
+;; flag is set.
+(import "env" "task_create" (func $task_create (param i32 i32 i32 i32 i32) (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(elem (i32.const 1) $child)
+
+(data (i32.const 1024) "Child\00")
+
+(func $wait_for (param $flag i32)
+  (loop $spin
+    (br_if $spin (i32.eqz (i32.atomic.load (local.get $flag))))))
+
+(func $child (param i32)
+  (i32.atomic.store (i32.const 2048) (i32.const 1))
+  (call $wait_for (i32.const 2052))
+  (i32.atomic.store (i32.const 2056) (i32.const 1)))
+
+(func (export "initialize")
+  (if (i32.eqz
+        (call $task_create (i32.const 1) (i32.const 0) (i32.const 8) (i32.const 8192) (i32.const 1024)))
+    (then (call $exit (i32.const 1))))
+  (call $wait_for (i32.const 2048))
+  (i32.atomic.store (i32.const 2052) (i32.const 1))
+  (call $wait_for (i32.const 2056))
+  (call $exit (i32.const 0)))
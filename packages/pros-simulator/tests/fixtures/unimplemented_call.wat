@@ -0,0 +1,11 @@
+;; Calls an API the simulator doesn't implement from `initialize`, then exits from `opcontrol`
+;; to show that the rest of the robot code kept running.
+(import "env" "motor_move_velocity" (func $motor_move_velocity (param i32 i32) (result i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(func $initialize (export "initialize")
+  (drop (call $motor_move_velocity (i32.const 1) (i32.const 127)))
+  (call $exit (i32.const 1)))
+
+(func (export "opcontrol")
+  (call $exit (i32.const 0)))
@@ -0,0 +1,30 @@
+;; Exports tests that pass, fail an assertion, crash and wait, along with functions that aren't
+;; tests: one that doesn't match the pattern and one that takes an argument. `initialize` exits
+;; with code 1, so it mustn't run.
+(import "env" "sim_assert" (func $sim_assert (param i32 i32)))
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(data (i32.const 1024) "one plus one is two\00")
+(data (i32.const 1056) "one plus one is three\00")
+
+(func (export "initialize")
+  (call $exit (i32.const 1)))
+
+(func (export "sim_test_pass")
+  (call $sim_assert (i32.eq (i32.add (i32.const 1) (i32.const 1)) (i32.const 2)) (i32.const 1024)))
+
+(func (export "sim_test_fail")
+  (call $sim_assert (i32.eq (i32.add (i32.const 1) (i32.const 1)) (i32.const 3)) (i32.const 1056))
+  (call $sim_assert (i32.const 0) (i32.const 1056)))
+
+(func (export "sim_test_crash")
+  unreachable)
+
+(func (export "sim_test_delay")
+  (call $delay (i32.const 50)))
+
+(func (export "sim_test_with_argument") (param i32))
+
+(func (export "helper")
+  (call $exit (i32.const 1)))
@@ -0,0 +1,19 @@
+;; no function table
+;; A vexide program: waits for the master controller to connect, then prints whether its left
+;; joystick's Y axis is at 42 and returns. Like real vexide programs, it's linked without
+;; exporting its function table.
+(import "vex" "vexSerialWriteBuffer" (func $vexSerialWriteBuffer (param i32 i32 i32) (result i32)))
+(import "vex" "vexControllerConnectionStatusGet" (func $vexControllerConnectionStatusGet (param i32) (result i32)))
+(import "vex" "vexControllerGet" (func $vexControllerGet (param i32 i32) (result i32)))
+(import "vex" "vexTasksRun" (func $vexTasksRun))
+
+(data (i32.const 1024) "connected\n")
+(data (i32.const 1040) "left y is 42\n")
+
+(func (export "_start")
+  (loop $wait
+    (call $vexTasksRun)
+    (br_if $wait (i32.eqz (call $vexControllerConnectionStatusGet (i32.const 0)))))
+  (drop (call $vexSerialWriteBuffer (i32.const 1) (i32.const 1024) (i32.const 10)))
+  (if (i32.eq (call $vexControllerGet (i32.const 0) (i32.const 1)) (i32.const 42))
+    (then (drop (call $vexSerialWriteBuffer (i32.const 1) (i32.const 1040) (i32.const 13))))))
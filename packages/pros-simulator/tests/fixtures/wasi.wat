@@ -0,0 +1,5 @@
+;; A WASI program, which the simulator can't run.
+(import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+(func (export "_start")
+  (call $proc_exit (i32.const 0)))
@@ -0,0 +1,12 @@
+;; Counts up in a variable at an exported address every 2 ms for 100 ms, then exits.
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(global (export "COUNTER") i32 (i32.const 2048))
+
+(func (export "initialize")
+  (loop $count
+    (i32.store (i32.const 2048) (i32.add (i32.load (i32.const 2048)) (i32.const 1)))
+    (call $delay (i32.const 2))
+    (br_if $count (i32.lt_u (i32.load (i32.const 2048)) (i32.const 50))))
+  (call $exit (i32.const 0)))
@@ -0,0 +1,11 @@
+;; Exports the address of a tuning constant, like a linker-exported data symbol, and exits with
+;; its value once it's had time to be changed.
+(import "env" "delay" (func $delay (param i32)))
+(import "env" "exit" (func $exit (param i32)))
+
+(global (export "DRIVE_KP") i32 (i32.const 2048))
+(data (i32.const 2048) "\01\00\00\00")
+
+(func (export "initialize")
+  (call $delay (i32.const 50))
+  (call $exit (i32.load (i32.const 2048))))
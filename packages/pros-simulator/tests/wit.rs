@@ -0,0 +1,175 @@
+//! Checks that `wit/pros.wit` matches the API the simulator implements, in both directions.
+
+use std::{
+    sync::{mpsc, Arc, Mutex},
+    time::Duration,
+};
+
+use pros_simulator::{HostCtx, SimulatorOptions, StopReason, Timeout};
+use pros_simulator_interface::{CompetitionPhase, SimulatorEvent, SimulatorMessage};
+use wit_parser::{Resolve, Type, TypeDefKind, WorldItem};
+
+/// Functions the simulator defines that aren't part of the API robot code is written against, so
+/// they aren't in `wit/pros.wit`, as `(module, name)` pairs.
+const UNDECLARED: &[(&str, &str)] = &[
+    // called by the instrumentation the simulator adds to robot code's atomic instructions
+    ("__pros_simulator_atomics", "memory_atomic_notify"),
+    ("__pros_simulator_atomics", "memory_atomic_wait32"),
+    ("__pros_simulator_atomics", "memory_atomic_wait64"),
+];
+
+/// The name robot code imports a WIT function as.
+fn core_name(name: &str) -> String {
+    match name {
+        "errno" => "__errno".into(),
+        "sys-exit" => "_exit".into(),
+        "pv-task-get-thread-local-storage-pointer" => "pvTaskGetThreadLocalStoragePointer".into(),
+        "v-task-set-thread-local-storage-pointer" => "vTaskSetThreadLocalStoragePointer".into(),
+        // VEX SDK functions are camel case
+        _ if name.starts_with("vex-") => name
+            .split('-')
+            .enumerate()
+            .map(|(i, word)| match i {
+                0 => word.to_string(),
+                _ => word[..1].to_uppercase() + &word[1..],
+            })
+            .collect(),
+        _ => name.replace('-', "_"),
+    }
+}
+
+/// The core WebAssembly type a WIT type is lowered to.
+fn core_type(resolve: &Resolve, ty: &Type) -> &'static str {
+    match ty {
+        Type::Bool | Type::U8 | Type::U16 | Type::U32 | Type::S8 | Type::S16 | Type::S32 => "i32",
+        Type::U64 | Type::S64 => "i64",
+        Type::Id(id) => match &resolve.types[*id].kind {
+            TypeDefKind::Type(ty) => core_type(resolve, ty),
+            kind => panic!("unsupported type {kind:?}"),
+        },
+        ty => panic!("unsupported type {ty:?}"),
+    }
+}
+
+#[tokio::test]
+async fn wit_matches_implementation() {
+    let mut resolve = Resolve::new();
+    let (package, _) = resolve
+        .push_dir(&std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("wit"))
+        .unwrap();
+    let world = resolve.select_world(package, Some("robot")).unwrap();
+
+    // Build a module that imports every function in the world and make sure it links.
+    let mut declared = Vec::new();
+    let mut imports = String::new();
+    for item in resolve.worlds[world].imports.values() {
+        let WorldItem::Interface(interface) = item else {
+            continue;
+        };
+        for function in resolve.interfaces[*interface].functions.values() {
+            let params = function
+                .params
+                .iter()
+                .map(|(_, ty)| format!(" {}", core_type(&resolve, ty)))
+                .collect::<String>();
+            let results = function
+                .results
+                .iter_types()
+                .map(|ty| format!(" {}", core_type(&resolve, ty)))
+                .collect::<String>();
+            let name = core_name(&function.name);
+            imports +=
+                &format!("(import \"env\" \"{name}\" (func (param{params}) (result{results})))\n");
+            declared.push(name);
+        }
+    }
+    let wasm = wat::parse_str(format!(
+        "(module (import \"env\" \"memory\" (memory 18 16384 shared))\n{imports})"
+    ))
+    .unwrap();
+    let robot_code = std::env::temp_dir().join(format!(
+        "pros-simulator-test-{}-wit.wasm",
+        std::process::id()
+    ));
+    std::fs::write(&robot_code, wasm).unwrap();
+
+    let warnings = Arc::new(Mutex::new(Vec::new()));
+    let result = pros_simulator::check(&robot_code, {
+        let warnings = warnings.clone();
+        move |event| {
+            if let SimulatorEvent::Warning(warning) = event {
+                warnings.lock().unwrap().push(warning);
+            }
+        }
+    })
+    .await;
+    result.unwrap();
+    assert_eq!(*warnings.lock().unwrap(), Vec::<String>::new());
+
+    // Then make sure every function the simulator implements for that module is declared.
+    let host = pros_simulator::load(&robot_code, SimulatorOptions::default(), |_| {}).unwrap();
+    _ = std::fs::remove_file(robot_code);
+    let mut tasks = host.tasks_lock().await;
+    let mut store = tasks.create_store(&host).unwrap();
+    let undeclared = tasks
+        .api_functions(&mut store)
+        .unwrap()
+        .into_iter()
+        .filter(|(module, name)| {
+            let declared = module == "env" && declared.contains(name);
+            let allowed = UNDECLARED.contains(&(module.as_str(), name.as_str()));
+            !declared && !allowed
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(undeclared, []);
+}
+
+/// A component whose `initialize` adds 41 to a static initialized to 1, and whose `opcontrol`
+/// exits with the static's value, which checks that tasks share memory and that statics are only
+/// initialized once.
+const COMPONENT: &str = r#"
+(component
+  (import "pros:simulator/newlib@0.5.0" (instance $newlib
+    (export "sys-exit" (func (param "code" s32)))))
+  (core module $robot
+    (import "pros:simulator/newlib@0.5.0" "sys-exit" (func $exit (param i32)))
+    (memory (export "memory") 1)
+    (data (i32.const 1024) "\01")
+    (func (export "initialize")
+      (i32.store8 (i32.const 1024) (i32.add (i32.load8_u (i32.const 1024)) (i32.const 41))))
+    (func (export "opcontrol")
+      (call $exit (i32.load8_u (i32.const 1024)))))
+  (core func $exit (canon lower (func $newlib "sys-exit")))
+  (core instance $newlib_exports (export "sys-exit" (func $exit)))
+  (core instance $instance (instantiate $robot
+    (with "pros:simulator/newlib@0.5.0" (instance $newlib_exports))))
+  (func (export "initialize") (canon lift (core func $instance "initialize")))
+  (func (export "opcontrol") (canon lift (core func $instance "opcontrol"))))
+"#;
+
+#[tokio::test]
+async fn runs_components() {
+    let robot_code = std::env::temp_dir().join(format!(
+        "pros-simulator-test-{}-component.wasm",
+        std::process::id()
+    ));
+    std::fs::write(&robot_code, wat::parse_str(COMPONENT).unwrap()).unwrap();
+
+    let (messages, rx) = mpsc::channel();
+    messages
+        .send(SimulatorMessage::PhaseChange(CompetitionPhase {
+            autonomous: false,
+            enabled: true,
+            is_competition: false,
+        }))
+        .unwrap();
+    let options = SimulatorOptions::new().timeout(Timeout::RealTime(Duration::from_secs(10)));
+    let outcome = pros_simulator::simulate(&robot_code, options, |_| {}, rx).await;
+    _ = std::fs::remove_file(robot_code);
+    let outcome = outcome.unwrap();
+    assert!(
+        matches!(outcome.reason, StopReason::Exited(42)),
+        "{:?}",
+        outcome.reason
+    );
+}